@@ -56,7 +56,7 @@ fn type_mapping(kind: &str) -> String {
         "int8" => String::from("i8"),
         "string" => String::from("String"),
         "uint16" => String::from("u16"),
-        "uuid" => String::from("[u8; 16]"),
+        "uuid" => String::from("crate::primitive::uuid::Uuid"),
         "records" => String::from("crate::RecordBatch"),
 
         sequence if sequence.starts_with("[]") => type_mapping(&sequence[2..]),
@@ -160,6 +160,13 @@ pub enum Listener {
     Controller,
 }
 
+impl ToTokens for Listener {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let expr = with_crate!("Listener", self);
+        syn::parse_str::<Expr>(&expr).unwrap().to_tokens(tokens);
+    }
+}
+
 impl TryFrom<&Value> for Listener {
     type Error = Error;
 
@@ -275,7 +282,8 @@ impl FromStr for VersionRange {
         }
 
         if s == "none" {
-            Ok(VersionRange { start: 0, end: 0 })
+            // an empty range, so that no version is within it
+            Ok(VersionRange { start: 0, end: -1 })
         } else {
             RX.captures(s)
                 .ok_or(Error::Message(format!("invalid version range format: {s}")))
@@ -773,6 +781,7 @@ pub struct MessageMeta {
     pub api_key: i16,
     pub version: Version,
     pub message_kind: MessageKind,
+    pub listeners: &'static [Listener],
     pub fields: &'static [(&'static str, &'static FieldMeta)],
 }
 
@@ -782,20 +791,60 @@ impl MessageMeta {
         self.version.flexible.within(version)
     }
 
+    /// The version of the request or response header used with this message
+    /// at `api_version`.
+    #[must_use]
+    pub fn header_version(&self, api_version: i16) -> i16 {
+        match self.message_kind {
+            // ControlledShutdown v0 predates the client id in the header
+            MessageKind::Request if self.api_key == 7 && api_version == 0 => 0,
+            MessageKind::Request if self.is_flexible(api_version) => 2,
+            MessageKind::Request => 1,
+
+            // a client parses an ApiVersions response before knowing which
+            // versions the broker supports, so its header is never flexible
+            MessageKind::Response if self.api_key == 18 => 0,
+            MessageKind::Response if self.is_flexible(api_version) => 1,
+            MessageKind::Response => 0,
+        }
+    }
+
+    /// Whether the header at `api_version` ends with tagged fields.
+    #[must_use]
+    pub fn is_header_flexible(&self, api_version: i16) -> bool {
+        let header_version = self.header_version(api_version);
+
+        match self.message_kind {
+            MessageKind::Request => header_version >= 2,
+            MessageKind::Response => header_version >= 1,
+        }
+    }
+
+    /// The listeners accepting this message, which is empty for a response.
+    #[must_use]
+    pub fn listeners(&self) -> &'static [Listener] {
+        self.listeners
+    }
+
+    #[must_use]
+    pub fn is_listening(&self, listener: Listener) -> bool {
+        self.listeners.contains(&listener)
+    }
+
     #[must_use]
     pub fn structures(&self) -> BTreeMap<&str, &FieldMeta> {
-        self.fields.iter().filter(|(_, fm)| fm.is_structure()).fold(
-            BTreeMap::new(),
-            |mut acc, (_, fm)| {
-                if let Some(kind) = fm.kind.kind_of_sequence() {
-                    _ = acc.insert(kind.name(), fm);
-                }
+        self.fields
+            .iter()
+            .filter(|(_, fm)| !fm.fields.is_empty())
+            .fold(BTreeMap::new(), |mut acc, (_, fm)| {
+                // a structure is either within a sequence or a (nullable) field itself
+                let kind = fm.kind.kind_of_sequence().unwrap_or(fm.kind);
+                _ = acc.insert(kind.name(), fm);
 
                 let mut children = fm.structures();
                 acc.append(&mut children);
                 acc
-            },
-        )
+            })
     }
 
     #[must_use]
@@ -837,18 +886,18 @@ impl FieldMeta {
 
     #[must_use]
     pub fn structures(&self) -> BTreeMap<&str, &FieldMeta> {
-        self.fields.iter().filter(|(_, fm)| fm.is_structure()).fold(
-            BTreeMap::new(),
-            |mut acc, (_, fm)| {
-                if let Some(kind) = fm.kind.kind_of_sequence() {
-                    _ = acc.insert(kind.name(), fm);
-                }
+        self.fields
+            .iter()
+            .filter(|(_, fm)| !fm.fields.is_empty())
+            .fold(BTreeMap::new(), |mut acc, (_, fm)| {
+                // a structure is either within a sequence or a (nullable) field itself
+                let kind = fm.kind.kind_of_sequence().unwrap_or(fm.kind);
+                _ = acc.insert(kind.name(), fm);
 
                 let mut children = fm.structures();
                 acc.append(&mut children);
                 acc
-            },
-        )
+            })
     }
 }
 
@@ -881,6 +930,11 @@ impl KindMeta {
         self.0 == "records"
     }
 
+    #[must_use]
+    pub fn is_uuid(&self) -> bool {
+        self.0 == "uuid"
+    }
+
     #[must_use]
     pub fn kind_of_sequence(&self) -> Option<Self> {
         if self.is_sequence() {
@@ -917,7 +971,7 @@ mod tests {
         assert_eq!("i8", type_mapping("int8"));
         assert_eq!("String", type_mapping("string"));
         assert_eq!("u16", type_mapping("uint16"));
-        assert_eq!("[u8; 16]", type_mapping("uuid"));
+        assert_eq!("crate::primitive::uuid::Uuid", type_mapping("uuid"));
 
         assert_eq!("i16", type_mapping("[]int16"));
         assert_eq!("SomeType", type_mapping("[]SomeType"));
@@ -1055,10 +1109,12 @@ mod tests {
     #[test]
     fn version_range_from_str() -> Result<()> {
         assert_eq!(
-            VersionRange { start: 0, end: 0 },
+            VersionRange { start: 0, end: -1 },
             VersionRange::from_str("none")?
         );
 
+        assert!(!VersionRange::from_str("none")?.within(0));
+
         assert_eq!(
             VersionRange {
                 start: 3,
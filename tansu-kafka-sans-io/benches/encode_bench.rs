@@ -0,0 +1,89 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use bytes::BytesMut;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tansu_kafka_sans_io::{
+    record::{deflated, inflated, Record, Records},
+    response::FetchResponse,
+    ApiKey, ErrorCode, Frame, Header,
+};
+
+const API_VERSION: i16 = 12;
+
+fn fetch_response_1mb() -> Frame {
+    let records = inflated::Batch::builder()
+        .record(Record::builder().value(vec![7; 1_024 * 1_024].into()))
+        .build()
+        .and_then(deflated::Batch::try_from)
+        .and_then(Records::try_from)
+        .expect("records");
+
+    let body = FetchResponse::builder()
+        .response("test", |t| {
+            t.partition(0, ErrorCode::None, 1, Some(records))
+        })
+        .build()
+        .expect("fetch response");
+
+    Frame {
+        size: 0,
+        header: Header::Response { correlation_id: 6 },
+        body,
+    }
+}
+
+fn encode_response_1mb(c: &mut Criterion) {
+    let frame = fetch_response_1mb();
+
+    _ = c.bench_function("encode_response_1mb", |b| {
+        b.iter(|| {
+            Frame::encode_response(
+                frame.header.clone(),
+                frame.body.clone(),
+                ApiKey::Fetch,
+                API_VERSION,
+            )
+        })
+    });
+}
+
+fn write_response_into_1mb(c: &mut Criterion) {
+    let frame = fetch_response_1mb();
+    let mut buf = BytesMut::new();
+
+    _ = c.bench_function("write_response_into_1mb", |b| {
+        b.iter(|| {
+            buf.clear();
+            frame.write_response_into(black_box(&mut buf), ApiKey::Fetch, API_VERSION)
+        })
+    });
+}
+
+fn response_segments_1mb(c: &mut Criterion) {
+    let frame = fetch_response_1mb();
+
+    _ = c.bench_function("response_segments_1mb", |b| {
+        b.iter(|| frame.response_segments(ApiKey::Fetch, API_VERSION))
+    });
+}
+
+criterion_group!(
+    benches,
+    encode_response_1mb,
+    write_response_into_1mb,
+    response_segments_1mb
+);
+criterion_main!(benches);
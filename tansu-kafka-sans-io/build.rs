@@ -15,7 +15,7 @@
 
 use convert_case::{Case, Casing};
 use proc_macro2::TokenStream;
-use quote::{quote, ToTokens};
+use quote::{format_ident, quote, ToTokens};
 use serde_json::Value;
 use std::{
     collections::HashMap,
@@ -26,7 +26,7 @@ use std::{
     path::Path,
 };
 use syn::{Expr, Type};
-use tansu_kafka_model::{wv::Wv, CommonStruct, Field, Message};
+use tansu_kafka_model::{wv::Wv, CommonStruct, Field, Message, MessageKind};
 
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -237,44 +237,54 @@ fn tag_kind(
     }
 }
 
+// the fields of the Body variant for a message
+fn body_fields(message: &Message, include_tag: bool) -> Vec<TokenStream> {
+    let module = syn::parse_str::<syn::Path>(
+        &message
+            .type_name()
+            .to_token_stream()
+            .to_string()
+            .to_case(Case::Snake),
+    )
+    .unwrap();
+
+    let dependencies: Vec<Type> = message
+        .fields()
+        .iter()
+        .filter(|f| f.fields().is_some())
+        .map(|f| f.kind().type_name())
+        .chain(
+            message
+                .common_structs()
+                .unwrap_or(&[][..])
+                .iter()
+                .map(CommonStruct::type_name),
+        )
+        .collect();
+
+    pfk(
+        None,
+        None,
+        message.fields(),
+        &module,
+        &dependencies,
+        include_tag,
+    )
+}
+
 #[allow(clippy::too_many_lines)]
 fn body_enum(messages: &[Message], include_tag: bool) -> TokenStream {
     let variants: Vec<TokenStream> = messages
         .iter()
         .map(|message| {
             let name = message.type_name();
-            let module = syn::parse_str::<syn::Path>(
-                &name.to_token_stream().to_string().to_case(Case::Snake),
-            )
-            .unwrap();
-
-            let dependencies: Vec<Type> = message
-                .fields()
-                .iter()
-                .filter(|f| f.fields().is_some())
-                .map(|f| f.kind().type_name())
-                .chain(
-                    message
-                        .common_structs()
-                        .unwrap_or(&[][..])
-                        .iter()
-                        .map(CommonStruct::type_name),
-                )
-                .collect();
-
-            let pfk = pfk(
-                None,
-                None,
-                message.fields(),
-                &module,
-                &dependencies,
-                include_tag,
-            );
+            let pfk = body_fields(message, include_tag);
 
             if include_tag {
                 quote! {
                     #name {
                         #(#pfk,)*
+                        unknown_tagged_fields: Vec<(u32, bytes::Bytes)>,
                     }
                 }
             } else {
@@ -386,41 +396,39 @@ fn body_enum(messages: &[Message], include_tag: bool) -> TokenStream {
 
                 let to_idents = message.fields().iter().map(Field::ident);
 
-                if message.has_tags() {
-                    quote! {
-                        #from {
-                            #(#from_idents,)*
-                            tag_buffer,
-                        } => {
-                            #(#conversions;)*
-
-                            #to {
-                                #(#to_idents,)*
-                            }
+                let tags = message.fields().iter().filter_map(Field::tag);
 
+                quote! {
+                    #from {
+                        #(#from_idents,)*
+                        tag_buffer,
+                    } => {
+                        #(#conversions;)*
+
+                        let unknown_tagged_fields = tag_buffer.as_ref().map_or_else(Vec::new, |tag_buffer| {
+                            tag_buffer
+                                .iter()
+                                .filter(|tag_field| ![#(#tags),*].contains(&tag_field.tag()))
+                                .map(|tag_field| (tag_field.tag(), bytes::Bytes::copy_from_slice(tag_field.data())))
+                                .collect()
+                        });
+
+                        #to {
+                            #(#to_idents,)*
+                            unknown_tagged_fields,
                         }
-                    }
-                } else {
-                    quote! {
-                        #from {
-                            #(#from_idents,)*
-                            tag_buffer,
-                        } => {
-                            #(#conversions;)*
 
-                            let _ = tag_buffer;
-
-                            #to {
-                                #(#to_idents,)*
-                            }
-
-                        }
                     }
-
                 }
             })
             .collect();
 
+        let idents = messages.iter().map(Message::type_name).collect::<Vec<_>>();
+
+        let names = messages.iter().map(Message::name);
+
+        let json = json_body_enum(messages);
+
         quote! {
             #[derive(Clone, Debug, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize)]
             #[serde(from = "mezzanine::Body")]
@@ -429,6 +437,17 @@ fn body_enum(messages: &[Message], include_tag: bool) -> TokenStream {
                 #(#variants),*
             }
 
+            impl Body {
+                #[must_use]
+                pub fn name(&self) -> &'static str {
+                    match self {
+                        #(Self::#idents { .. } => #names,)*
+                    }
+                }
+            }
+
+            #json
+
             impl From<mezzanine::Body> for Body {
                 fn from(value: mezzanine::Body) -> Self {
                     match value {
@@ -534,11 +553,16 @@ fn body_enum(messages: &[Message], include_tag: bool) -> TokenStream {
                     quote! {
                         #from {
                             #(#from_idents,)*
+                            unknown_tagged_fields,
                         } => {
-                            #[allow(unused_mut)]
                             let mut tag_buffer = Vec::new();
                             #(#conversions;)*
 
+                            tag_buffer.extend(unknown_tagged_fields.into_iter().map(|(tag, data)| {
+                                crate::primitive::tagged::TagField(tag, data.to_vec())
+                            }));
+                            tag_buffer.sort_by_key(crate::primitive::tagged::TagField::tag);
+
                             #to {
                                 #(#to_idents,)*
                                 tag_buffer: Some(tag_buffer.into()),
@@ -565,6 +589,79 @@ fn body_enum(messages: &[Message], include_tag: bool) -> TokenStream {
     }
 }
 
+// A plain data copy of Body for formats other than the wire protocol, which
+// is only used through crate::json
+fn json_body_enum(messages: &[Message]) -> TokenStream {
+    let variants = messages.iter().map(|message| {
+        let name = message.type_name();
+        let pfk = body_fields(message, true);
+
+        quote! {
+            #name {
+                #(#pfk,)*
+                #[serde(with = "crate::json::unknown_tagged_fields")]
+                unknown_tagged_fields: Vec<(u32, bytes::Bytes)>,
+            }
+        }
+    });
+
+    let conversions = messages
+        .iter()
+        .map(|message| {
+            let name = message.type_name();
+            let idents = message
+                .fields()
+                .iter()
+                .map(Field::ident)
+                .collect::<Vec<_>>();
+
+            (
+                quote! {
+                    crate::Body::#name { #(#idents,)* unknown_tagged_fields } => {
+                        Self::#name { #(#idents,)* unknown_tagged_fields }
+                    }
+                },
+                quote! {
+                    Body::#name { #(#idents,)* unknown_tagged_fields } => {
+                        Self::#name { #(#idents,)* unknown_tagged_fields }
+                    }
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let into_json = conversions.iter().map(|(into_json, _)| into_json);
+    let from_json = conversions.iter().map(|(_, from_json)| from_json);
+
+    quote! {
+        #[cfg(feature = "json")]
+        pub(crate) mod json_body {
+            use super::*;
+
+            #[derive(Clone, Debug, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize)]
+            pub enum Body {
+                #(#variants),*
+            }
+
+            impl From<crate::Body> for Body {
+                fn from(value: crate::Body) -> Self {
+                    match value {
+                        #(#into_json,)*
+                    }
+                }
+            }
+
+            impl From<Body> for crate::Body {
+                fn from(value: Body) -> Self {
+                    match value {
+                        #(#from_json,)*
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn pfk(
     parent: Option<&Field>,
     visibility: Option<&TokenStream>,
@@ -579,7 +676,22 @@ fn pfk(
         .map(|field| {
             let f = field.ident();
             let k = kind(parent, module, field, dependencies);
+
+            // bytes are base64 in json, leaving the wire encoding alone
+            let with = (include_tag && field.kind().name() == "bytes").then(|| {
+                let with = if k.to_string().starts_with("Option") {
+                    "crate::json::option_bytes"
+                } else {
+                    "crate::json::bytes"
+                };
+
+                quote! {
+                    #[cfg_attr(feature = "json", serde(with = #with))]
+                }
+            });
+
             quote! {
+                #with
                 #visibility #f: #k
             }
         })
@@ -885,6 +997,7 @@ fn message_struct(
                     let mut tag_buffer = Vec::new();
 
                     #(#tags)*
+                    tag_buffer.sort_by_key(crate::primitive::tagged::TagField::tag);
 
                     Self {
                         #(#assignments,)*
@@ -1074,6 +1187,7 @@ fn common_struct(
                     let mut tag_buffer = Vec::new();
 
                     #(#tags)*
+                    tag_buffer.sort_by_key(crate::primitive::tagged::TagField::tag);
 
                     Self {
                         #(#assignments,)*
@@ -1186,6 +1300,7 @@ fn each_message_meta(message: &Message) -> TokenStream {
     let api_key = message.api_key();
     let version = message.version();
     let message_kind = message.kind();
+    let listeners = message.listeners().unwrap_or_default();
 
     let common_structs = message
         .common_structs()
@@ -1205,6 +1320,7 @@ fn each_message_meta(message: &Message) -> TokenStream {
             api_key: #api_key,
             version: #version,
             message_kind: #message_kind,
+            listeners: &[#(#listeners),*],
             fields: &[#(#children),*],
         })
     }
@@ -1220,6 +1336,104 @@ fn message_meta(messages: &[Message]) -> TokenStream {
     }
 }
 
+fn api_key_enum(messages: &[Message]) -> TokenStream {
+    let mut requests = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.kind() == MessageKind::Request)
+        .map(|(index, message)| {
+            let response = messages
+                .iter()
+                .position(|candidate| {
+                    candidate.kind() == MessageKind::Response
+                        && candidate.api_key() == message.api_key()
+                })
+                .unwrap_or_else(|| panic!("no response for: {}", message.name()));
+
+            (message.api_key(), message.name(), index, response)
+        })
+        .collect::<Vec<_>>();
+
+    requests.sort_unstable_by_key(|(api_key, ..)| *api_key);
+
+    let api_keys = requests
+        .iter()
+        .map(|(api_key, ..)| api_key)
+        .collect::<Vec<_>>();
+
+    let names = requests
+        .iter()
+        .map(|(_, name, ..)| name.trim_end_matches("Request"))
+        .collect::<Vec<_>>();
+
+    let variants = names
+        .iter()
+        .map(|name| format_ident!("{name}"))
+        .collect::<Vec<_>>();
+
+    let request_meta = requests.iter().map(|(_, _, index, _)| index);
+    let response_meta = requests.iter().map(|(.., response)| response);
+
+    quote! {
+        #[non_exhaustive]
+        #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        #[repr(i16)]
+        pub enum ApiKey {
+            #(#variants = #api_keys,)*
+        }
+
+        impl ApiKey {
+            #[must_use]
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(Self::#variants => #names,)*
+                }
+            }
+
+            #[must_use]
+            pub fn request_meta(&self) -> &'static tansu_kafka_model::MessageMeta {
+                match self {
+                    #(Self::#variants => MESSAGE_META[#request_meta].1,)*
+                }
+            }
+
+            #[must_use]
+            pub fn response_meta(&self) -> &'static tansu_kafka_model::MessageMeta {
+                match self {
+                    #(Self::#variants => MESSAGE_META[#response_meta].1,)*
+                }
+            }
+
+            #[must_use]
+            pub fn request_header_version(&self, api_version: i16) -> i16 {
+                self.request_meta().header_version(api_version)
+            }
+
+            #[must_use]
+            pub fn response_header_version(&self, api_version: i16) -> i16 {
+                self.response_meta().header_version(api_version)
+            }
+        }
+
+        impl From<ApiKey> for i16 {
+            fn from(value: ApiKey) -> Self {
+                value as i16
+            }
+        }
+
+        impl TryFrom<i16> for ApiKey {
+            type Error = Error;
+
+            fn try_from(value: i16) -> Result<Self, Self::Error> {
+                match value {
+                    #(#api_keys => Ok(Self::#variants),)*
+                    otherwise => Err(Error::NoSuchRequest(otherwise)),
+                }
+            }
+        }
+    }
+}
+
 pub fn main() {
     let files = "message/[A-Z]*Re[qs]*.json";
 
@@ -1229,6 +1443,7 @@ pub fn main() {
     let untagged = process(&messages, false);
 
     let message_meta = message_meta(&messages);
+    let api_key = api_key_enum(&messages);
 
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("generate.rs");
@@ -1237,6 +1452,7 @@ pub fn main() {
         #tagged
         #untagged
         #message_meta
+        #api_key
     };
 
     let r = syn::parse_file(&q.to_string()).unwrap();
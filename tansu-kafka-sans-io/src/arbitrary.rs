@@ -0,0 +1,287 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Strategies that generate encoded frames for any message at a chosen
+// version, driven by the generated message meta. A field that isn't valid
+// at the version is left out of the encoding, decoding as None, while
+// tagged fields are always absent so that the tag buffer is empty.
+
+use proptest::{collection, num, option, prelude::*};
+use tansu_kafka_model::{FieldMeta, MessageMeta};
+
+use crate::RootMessageMeta;
+
+// the number of elements in a generated sequence
+const SEQUENCE: std::ops::RangeInclusive<usize> = 0..=2;
+
+/// An encoded request frame, including its size prefix.
+///
+/// Returns [`None`] when there is no request with this api key or the
+/// version is not valid.
+pub fn request(api_key: i16, api_version: i16) -> Option<BoxedStrategy<Vec<u8>>> {
+    let meta = RootMessageMeta::messages()
+        .requests()
+        .get(&api_key)
+        .copied()
+        .filter(|meta| meta.version.valid.within(api_version))?;
+
+    let flexible = meta.is_flexible(api_version);
+
+    Some(
+        (any::<i32>(), nullable_string(true), body(meta, api_version))
+            .prop_map(move |(correlation_id, client_id, body)| {
+                let mut header = Vec::new();
+                header.extend(api_key.to_be_bytes());
+                header.extend(api_version.to_be_bytes());
+                header.extend(correlation_id.to_be_bytes());
+                header.extend(client_id);
+
+                if flexible {
+                    header.push(0);
+                }
+
+                frame(header, body)
+            })
+            .boxed(),
+    )
+}
+
+/// An encoded response frame, including its size prefix.
+///
+/// Returns [`None`] when there is no response with this api key or the
+/// version is not valid.
+pub fn response(api_key: i16, api_version: i16) -> Option<BoxedStrategy<Vec<u8>>> {
+    let meta = RootMessageMeta::messages()
+        .responses()
+        .get(&api_key)
+        .copied()
+        .filter(|meta| meta.version.valid.within(api_version))?;
+
+    // the api versions response header is never flexible
+    let flexible = meta.is_flexible(api_version) && api_key != 18;
+
+    Some(
+        (any::<i32>(), body(meta, api_version))
+            .prop_map(move |(correlation_id, body)| {
+                let mut header = Vec::new();
+                header.extend(correlation_id.to_be_bytes());
+
+                if flexible {
+                    header.push(0);
+                }
+
+                frame(header, body)
+            })
+            .boxed(),
+    )
+}
+
+fn frame(header: Vec<u8>, body: Vec<u8>) -> Vec<u8> {
+    let size = header.len() + body.len();
+
+    let mut frame = Vec::with_capacity(size + 4);
+    frame.extend(i32::try_from(size).expect("frame size").to_be_bytes());
+    frame.extend(header);
+    frame.extend(body);
+    frame
+}
+
+fn body(meta: &'static MessageMeta, api_version: i16) -> BoxedStrategy<Vec<u8>> {
+    structure(meta.fields, api_version, meta.is_flexible(api_version))
+}
+
+fn structure(
+    fields: &'static [(&'static str, &'static FieldMeta)],
+    api_version: i16,
+    flexible: bool,
+) -> BoxedStrategy<Vec<u8>> {
+    fields
+        .iter()
+        .filter(|(_, field)| field.version.within(api_version))
+        .filter(|(_, field)| {
+            !field
+                .tagged
+                .is_some_and(|tagged| tagged.within(api_version))
+        })
+        .map(|(_, field)| self::field(field, api_version, flexible))
+        .collect::<Vec<_>>()
+        .prop_map(move |encoded| {
+            let mut structure = encoded.concat();
+
+            if flexible {
+                // an empty tag buffer
+                structure.push(0);
+            }
+
+            structure
+        })
+        .boxed()
+}
+
+fn field(field: &'static FieldMeta, api_version: i16, flexible: bool) -> BoxedStrategy<Vec<u8>> {
+    let nullable = field.is_nullable(api_version);
+
+    if let Some(kind) = field.kind.kind_of_sequence() {
+        let element = if kind.is_primitive() {
+            primitive(kind.name(), false, flexible)
+        } else {
+            structure(field.fields, api_version, flexible)
+        };
+
+        maybe(
+            nullable,
+            collection::vec(element, SEQUENCE).prop_map(move |elements| {
+                let mut encoded = length(elements.len(), flexible);
+                encoded.extend(elements.concat());
+                encoded
+            }),
+            null_length(flexible, i32::to_be_bytes(-1).to_vec()),
+        )
+    } else if field.kind.is_primitive() {
+        primitive(field.kind.name(), nullable, flexible)
+    } else if nullable {
+        // a nullable structure is prefixed with its presence
+        option::of(structure(field.fields, api_version, flexible))
+            .prop_map(|structure| {
+                structure.map_or(vec![0xff], |structure| {
+                    let mut encoded = vec![1];
+                    encoded.extend(structure);
+                    encoded
+                })
+            })
+            .boxed()
+    } else {
+        structure(field.fields, api_version, flexible)
+    }
+}
+
+fn primitive(kind: &'static str, nullable: bool, flexible: bool) -> BoxedStrategy<Vec<u8>> {
+    match kind {
+        "bool" => any::<bool>().prop_map(|b| vec![u8::from(b)]).boxed(),
+        "int8" => any::<i8>().prop_map(|i| i.to_be_bytes().to_vec()).boxed(),
+        "int16" => any::<i16>().prop_map(|i| i.to_be_bytes().to_vec()).boxed(),
+        "int32" => any::<i32>().prop_map(|i| i.to_be_bytes().to_vec()).boxed(),
+        "int64" => any::<i64>().prop_map(|i| i.to_be_bytes().to_vec()).boxed(),
+        "uint16" => any::<u16>().prop_map(|i| i.to_be_bytes().to_vec()).boxed(),
+
+        // NaN is not equal to itself
+        "float64" => (num::f64::NORMAL | num::f64::ZERO)
+            .prop_map(|f| f.to_be_bytes().to_vec())
+            .boxed(),
+
+        "uuid" => any::<[u8; 16]>().prop_map(|uuid| uuid.to_vec()).boxed(),
+
+        "string" if flexible => maybe(
+            nullable,
+            "[a-z0-9._-]{0,8}".prop_map(|s| compact(s.as_bytes())),
+            vec![0],
+        ),
+
+        "string" => nullable_string(nullable),
+
+        "bytes" => maybe(
+            nullable,
+            collection::vec(any::<u8>(), 0..8).prop_map(move |bytes| {
+                let mut encoded = length(bytes.len(), flexible);
+                encoded.extend(bytes);
+                encoded
+            }),
+            null_length(flexible, i32::to_be_bytes(-1).to_vec()),
+        ),
+
+        // records are kept encoded, with an empty batch encoded as null
+        "records" => option::of(collection::vec(any::<u8>(), 1..16))
+            .prop_map(move |records| {
+                records.map_or_else(
+                    || length(0, flexible),
+                    |records| {
+                        let mut encoded = length(records.len(), flexible);
+                        encoded.extend(records);
+                        encoded
+                    },
+                )
+            })
+            .boxed(),
+
+        otherwise => unimplemented!("primitive: {otherwise}"),
+    }
+}
+
+// a non-compact string, as used by the client id of a request header
+fn nullable_string(nullable: bool) -> BoxedStrategy<Vec<u8>> {
+    maybe(
+        nullable,
+        "[a-z0-9._-]{0,8}".prop_map(|s| {
+            let mut encoded = i16::try_from(s.len())
+                .expect("string length")
+                .to_be_bytes()
+                .to_vec();
+            encoded.extend(s.as_bytes());
+            encoded
+        }),
+        i16::to_be_bytes(-1).to_vec(),
+    )
+}
+
+fn maybe(
+    nullable: bool,
+    present: impl Strategy<Value = Vec<u8>> + 'static,
+    null: Vec<u8>,
+) -> BoxedStrategy<Vec<u8>> {
+    if nullable {
+        option::of(present)
+            .prop_map(move |encoded| encoded.unwrap_or_else(|| null.clone()))
+            .boxed()
+    } else {
+        present.boxed()
+    }
+}
+
+fn null_length(flexible: bool, otherwise: Vec<u8>) -> Vec<u8> {
+    if flexible {
+        vec![0]
+    } else {
+        otherwise
+    }
+}
+
+fn length(length: usize, flexible: bool) -> Vec<u8> {
+    if flexible {
+        unsigned_varint(u32::try_from(length + 1).expect("compact length"))
+    } else {
+        i32::try_from(length)
+            .expect("length")
+            .to_be_bytes()
+            .to_vec()
+    }
+}
+
+fn compact(bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = length(bytes.len(), true);
+    encoded.extend(bytes);
+    encoded
+}
+
+fn unsigned_varint(mut value: u32) -> Vec<u8> {
+    let mut encoded = Vec::new();
+
+    while value >= 0x80 {
+        encoded.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+
+    encoded.push(value as u8);
+    encoded
+}
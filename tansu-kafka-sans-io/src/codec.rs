@@ -0,0 +1,240 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// A tokio_util codec for Kafka frames, for use with Framed.
+
+use crate::{Error, Frame, Header, Result, DEFAULT_MAX_FRAME_BYTES};
+use bytes::{Buf, Bytes, BytesMut};
+use std::{collections::HashMap, io};
+use tokio_util::codec::{Decoder, Encoder};
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Kind {
+    Request,
+    Response,
+}
+
+/// A decoded frame: the parsed header together with the undecoded frame.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct RawFrame {
+    pub header: Header,
+
+    /// The api key and version of this frame, for a response only known when
+    /// the request was encoded by the same codec.
+    pub api: Option<(i16, i16)>,
+
+    /// The whole frame, including its size prefix.
+    pub frame: Bytes,
+}
+
+impl RawFrame {
+    /// Fully decode this frame.
+    pub fn decode(&self) -> Result<Frame> {
+        match (&self.header, self.api) {
+            (Header::Request { .. }, _) => Frame::request_from_bytes(&self.frame[..]),
+
+            (Header::Response { .. }, Some((api_key, api_version))) => {
+                Frame::decode(&self.frame[..], api_key, api_version)
+            }
+
+            (Header::Response { correlation_id }, None) => {
+                Err(Error::UnknownCorrelationId(*correlation_id))
+            }
+        }
+    }
+}
+
+fn truncated() -> Error {
+    Error::Io(io::Error::from(io::ErrorKind::UnexpectedEof))
+}
+
+// the request header client id is a nullable (non-compact) string in every version
+fn request_header(frame: &Bytes) -> Result<Header> {
+    let mut buf = frame.slice(size_of::<i32>()..);
+
+    if buf.remaining() < size_of::<i16>() + size_of::<i16>() + size_of::<i32>() + size_of::<i16>() {
+        return Err(truncated());
+    }
+
+    let api_key = buf.get_i16();
+    let api_version = buf.get_i16();
+    let correlation_id = buf.get_i32();
+
+    let client_id = match buf.get_i16() {
+        -1 => None,
+
+        length => {
+            let length = usize::try_from(length)?;
+
+            if buf.remaining() < length {
+                return Err(truncated());
+            }
+
+            Some(String::from_utf8(buf.split_to(length).to_vec())?)
+        }
+    };
+
+    Ok(Header::Request {
+        api_key,
+        api_version,
+        correlation_id,
+        client_id,
+    })
+}
+
+fn response_header(frame: &Bytes) -> Result<Header> {
+    let mut buf = frame.slice(size_of::<i32>()..);
+
+    if buf.remaining() < size_of::<i32>() {
+        return Err(truncated());
+    }
+
+    Ok(Header::Response {
+        correlation_id: buf.get_i32(),
+    })
+}
+
+/// Decodes Kafka frames into a [`RawFrame`], with only the header parsed, and
+/// encodes either a pre-encoded frame or a typed [`Frame`].
+///
+/// The api key and version of each request passing through the codec is
+/// retained until its response, so that a response can be decoded or encoded
+/// from just its correlation id.
+#[derive(Clone, Debug)]
+pub struct KafkaFrameCodec {
+    kind: Kind,
+    max_frame_length: usize,
+    in_flight: HashMap<i32, (i16, i16)>,
+}
+
+impl KafkaFrameCodec {
+    /// A codec decoding requests and encoding responses, as used by a broker.
+    #[must_use]
+    pub fn request() -> Self {
+        Self::new(Kind::Request)
+    }
+
+    /// A codec decoding responses and encoding requests, as used by a client.
+    #[must_use]
+    pub fn response() -> Self {
+        Self::new(Kind::Response)
+    }
+
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            max_frame_length: DEFAULT_MAX_FRAME_BYTES,
+            in_flight: HashMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn max_frame_length(self, max_frame_length: usize) -> Self {
+        Self {
+            max_frame_length,
+            ..self
+        }
+    }
+
+    fn track(&mut self, header: &Header) -> Option<(i16, i16)> {
+        match header {
+            Header::Request {
+                api_key,
+                api_version,
+                correlation_id,
+                ..
+            } => {
+                _ = self
+                    .in_flight
+                    .insert(*correlation_id, (*api_key, *api_version));
+                Some((*api_key, *api_version))
+            }
+
+            Header::Response { correlation_id } => self.in_flight.remove(correlation_id),
+        }
+    }
+}
+
+impl Decoder for KafkaFrameCodec {
+    type Item = RawFrame;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let length = match Frame::check_within(&src[..], self.max_frame_length) {
+            Ok(length) => length,
+
+            Err(Error::Incomplete { needed }) => {
+                src.reserve(needed.unwrap_or_default());
+                return Ok(None);
+            }
+
+            Err(otherwise) => return Err(otherwise),
+        };
+
+        let frame = src.split_to(length).freeze();
+
+        let header = match self.kind {
+            Kind::Request => request_header(&frame),
+            Kind::Response => response_header(&frame),
+        }?;
+
+        debug!(?header, length);
+
+        let api = self.track(&header);
+        Ok(Some(RawFrame { header, api, frame }))
+    }
+}
+
+impl Encoder<Bytes> for KafkaFrameCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        Frame::check(&item[..])
+            .and_then(|_| match self.kind {
+                Kind::Request => response_header(&item),
+                Kind::Response => request_header(&item),
+            })
+            .map(|header| {
+                _ = self.track(&header);
+            })?;
+
+        dst.extend_from_slice(&item[..]);
+        Ok(())
+    }
+}
+
+impl Encoder<Frame> for KafkaFrameCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: Frame, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let encoded = match item.header {
+            Header::Request { .. } => {
+                _ = self.track(&item.header);
+                Frame::request(item.header, item.body)
+            }
+
+            Header::Response { correlation_id } => self
+                .track(&item.header)
+                .ok_or(Error::UnknownCorrelationId(correlation_id))
+                .and_then(|(api_key, api_version)| {
+                    Frame::encode(item.header, item.body, api_key, api_version)
+                }),
+        }?;
+
+        dst.extend_from_slice(&encoded[..]);
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// The embedded consumer protocol, carried as opaque bytes in the metadata of
+// a join group protocol and the assignment of a sync group. Each is prefixed
+// by an i16 version and uses the non-flexible primitive encoding. A newer
+// version is parsed using the current format, ignoring any trailing fields.
+
+use crate::{Error, Result};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::io;
+
+/// The protocol type of a join group request using the consumer protocol.
+pub const PROTOCOL_TYPE: &str = "consumer";
+
+const CURRENT_VERSION: i16 = 3;
+
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TopicPartition {
+    pub topic: String,
+    pub partitions: Vec<i32>,
+}
+
+impl TopicPartition {
+    #[must_use]
+    pub fn new(topic: &str, partitions: &[i32]) -> Self {
+        Self {
+            topic: topic.to_owned(),
+            partitions: partitions.to_vec(),
+        }
+    }
+
+    fn decode(buf: &mut Bytes) -> Result<Self> {
+        Ok(Self {
+            topic: string(buf)?,
+            partitions: array(buf, get_i32)?,
+        })
+    }
+
+    fn encode(&self, buf: &mut BytesMut) -> Result<()> {
+        put_string(buf, &self.topic)?;
+        put_array(buf, &self.partitions, |buf, partition| {
+            buf.put_i32(*partition);
+            Ok(())
+        })
+    }
+}
+
+/// The subscription of a consumer group member, carried in the metadata of
+/// each of its join group protocols.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ConsumerProtocolSubscription {
+    pub version: i16,
+    pub topics: Vec<String>,
+    pub user_data: Option<Bytes>,
+    pub owned_partitions: Vec<TopicPartition>,
+    pub generation_id: i32,
+    pub rack_id: Option<String>,
+}
+
+impl Default for ConsumerProtocolSubscription {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            topics: Vec::new(),
+            user_data: None,
+            owned_partitions: Vec::new(),
+            generation_id: -1,
+            rack_id: None,
+        }
+    }
+}
+
+impl ConsumerProtocolSubscription {
+    pub fn encode(&self) -> Result<Bytes> {
+        let version = valid_version(self.version)?;
+
+        let mut buf = BytesMut::new();
+        buf.put_i16(self.version);
+
+        put_array(&mut buf, &self.topics, |buf, topic| put_string(buf, topic))?;
+        put_nullable_bytes(&mut buf, self.user_data.as_ref())?;
+
+        if version >= 1 {
+            put_array(&mut buf, &self.owned_partitions, |buf, owned| {
+                owned.encode(buf)
+            })?;
+        }
+
+        if version >= 2 {
+            buf.put_i32(self.generation_id);
+        }
+
+        if version >= 3 {
+            put_nullable_string(&mut buf, self.rack_id.as_deref())?;
+        }
+
+        Ok(buf.freeze())
+    }
+}
+
+impl TryFrom<Bytes> for ConsumerProtocolSubscription {
+    type Error = Error;
+
+    fn try_from(mut buf: Bytes) -> Result<Self, Self::Error> {
+        let version = get_i16(&mut buf).and_then(valid_version)?;
+
+        let mut subscription = Self {
+            version,
+            topics: array(&mut buf, string)?,
+            user_data: nullable_bytes(&mut buf)?,
+            ..Default::default()
+        };
+
+        if version >= 1 {
+            subscription.owned_partitions = array(&mut buf, TopicPartition::decode)?;
+        }
+
+        if version >= 2 {
+            subscription.generation_id = get_i32(&mut buf)?;
+        }
+
+        if version >= 3 {
+            subscription.rack_id = nullable_string(&mut buf)?;
+        }
+
+        Ok(subscription)
+    }
+}
+
+/// The partitions assigned to a consumer group member, carried in the
+/// assignment of a sync group.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ConsumerProtocolAssignment {
+    pub version: i16,
+    pub assigned_partitions: Vec<TopicPartition>,
+    pub user_data: Option<Bytes>,
+}
+
+impl Default for ConsumerProtocolAssignment {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            assigned_partitions: Vec::new(),
+            user_data: None,
+        }
+    }
+}
+
+impl ConsumerProtocolAssignment {
+    pub fn encode(&self) -> Result<Bytes> {
+        _ = valid_version(self.version)?;
+
+        let mut buf = BytesMut::new();
+        buf.put_i16(self.version);
+
+        put_array(&mut buf, &self.assigned_partitions, |buf, assigned| {
+            assigned.encode(buf)
+        })?;
+        put_nullable_bytes(&mut buf, self.user_data.as_ref())?;
+
+        Ok(buf.freeze())
+    }
+}
+
+impl TryFrom<Bytes> for ConsumerProtocolAssignment {
+    type Error = Error;
+
+    fn try_from(mut buf: Bytes) -> Result<Self, Self::Error> {
+        Ok(Self {
+            version: get_i16(&mut buf).and_then(valid_version)?,
+            assigned_partitions: array(&mut buf, TopicPartition::decode)?,
+            user_data: nullable_bytes(&mut buf)?,
+        })
+    }
+}
+
+fn valid_version(version: i16) -> Result<i16> {
+    if version < 0 {
+        Err(Error::InvalidConsumerProtocolVersion(version))
+    } else {
+        Ok(version)
+    }
+}
+
+fn truncated() -> Error {
+    Error::Io(io::Error::from(io::ErrorKind::UnexpectedEof))
+}
+
+fn get_i16(buf: &mut Bytes) -> Result<i16> {
+    if buf.remaining() >= size_of::<i16>() {
+        Ok(buf.get_i16())
+    } else {
+        Err(truncated())
+    }
+}
+
+fn get_i32(buf: &mut Bytes) -> Result<i32> {
+    if buf.remaining() >= size_of::<i32>() {
+        Ok(buf.get_i32())
+    } else {
+        Err(truncated())
+    }
+}
+
+fn slice(buf: &mut Bytes, length: usize) -> Result<Bytes> {
+    if buf.remaining() >= length {
+        Ok(buf.split_to(length))
+    } else {
+        Err(truncated())
+    }
+}
+
+fn nullable_string(buf: &mut Bytes) -> Result<Option<String>> {
+    match get_i16(buf)? {
+        -1 => Ok(None),
+        length => usize::try_from(length)
+            .map_err(Into::into)
+            .and_then(|length| slice(buf, length))
+            .and_then(|encoded| String::from_utf8(encoded.to_vec()).map_err(Into::into))
+            .map(Some),
+    }
+}
+
+fn string(buf: &mut Bytes) -> Result<String> {
+    nullable_string(buf)?.ok_or(Error::StringWithoutLength)
+}
+
+fn nullable_bytes(buf: &mut Bytes) -> Result<Option<Bytes>> {
+    match get_i32(buf)? {
+        -1 => Ok(None),
+        length => usize::try_from(length)
+            .map_err(Into::into)
+            .and_then(|length| slice(buf, length))
+            .map(Some),
+    }
+}
+
+fn array<T>(buf: &mut Bytes, element: impl Fn(&mut Bytes) -> Result<T>) -> Result<Vec<T>> {
+    match get_i32(buf)? {
+        -1 => Ok(Vec::new()),
+        length => {
+            // each element occupies at least one byte
+            let length = usize::try_from(length)?;
+            let mut elements = Vec::with_capacity(length.min(buf.remaining()));
+
+            for _ in 0..length {
+                elements.push(element(buf)?);
+            }
+
+            Ok(elements)
+        }
+    }
+}
+
+fn put_nullable_string(buf: &mut BytesMut, s: Option<&str>) -> Result<()> {
+    if let Some(s) = s {
+        put_string(buf, s)
+    } else {
+        buf.put_i16(-1);
+        Ok(())
+    }
+}
+
+fn put_string(buf: &mut BytesMut, s: &str) -> Result<()> {
+    buf.put_i16(i16::try_from(s.len())?);
+    buf.put_slice(s.as_bytes());
+    Ok(())
+}
+
+fn put_nullable_bytes(buf: &mut BytesMut, bytes: Option<&Bytes>) -> Result<()> {
+    if let Some(bytes) = bytes {
+        buf.put_i32(i32::try_from(bytes.len())?);
+        buf.put_slice(bytes);
+    } else {
+        buf.put_i32(-1);
+    }
+
+    Ok(())
+}
+
+fn put_array<T>(
+    buf: &mut BytesMut,
+    elements: &[T],
+    element: impl Fn(&mut BytesMut, &T) -> Result<()>,
+) -> Result<()> {
+    buf.put_i32(i32::try_from(elements.len())?);
+    elements.iter().try_for_each(|e| element(buf, e))
+}
@@ -13,21 +13,25 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{Error, Result, RootMessageMeta};
+use crate::{primitive::varint, Error, Result, RootMessageMeta, DEFAULT_MAX_FRAME_BYTES};
 use serde::{
     de::{DeserializeSeed, EnumAccess, SeqAccess, VariantAccess, Visitor},
     Deserializer,
 };
 use std::{
     any::{type_name, type_name_of_val},
+    borrow::Cow,
     collections::VecDeque,
     fmt,
     io::{self, Read},
     str::from_utf8,
 };
-use tansu_kafka_model::{FieldMeta, MessageMeta};
+use tansu_kafka_model::{FieldMeta, KindMeta, MessageMeta};
 use tracing::{debug, warn};
 
+// strings up to this length are borrowed from the stack by deserialize_str
+const SHORT_STR: usize = 128;
+
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 enum Kind {
     Request,
@@ -97,6 +101,8 @@ pub struct Decoder<'de> {
     in_seq_of_primitive: bool,
     path: VecDeque<&'static str>,
     in_records: bool,
+    max_frame_bytes: usize,
+    frame_length: Option<usize>,
 }
 
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -139,6 +145,8 @@ impl<'de> Decoder<'de> {
             in_seq_of_primitive: false,
             path: VecDeque::new(),
             in_records: false,
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+            frame_length: None,
         }
     }
 
@@ -155,37 +163,122 @@ impl<'de> Decoder<'de> {
             in_seq_of_primitive: false,
             path: VecDeque::new(),
             in_records: false,
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+            frame_length: None,
         }
     }
 
-    pub fn response(reader: &'de mut dyn Read, api_key: i16, api_version: i16) -> Self {
-        Self {
+    /// A decoder for the response to `api_key` at `api_version`, failing when
+    /// the api key is unknown or the version is outside those it supports.
+    pub fn response(reader: &'de mut dyn Read, api_key: i16, api_version: i16) -> Result<Self> {
+        let meta = RootMessageMeta::messages()
+            .responses()
+            .get(&api_key)
+            .ok_or(Error::UnknownApiKey(api_key))?;
+
+        if !meta.version.valid.within(api_version) {
+            return Err(Error::UnsupportedApiVersion {
+                api_key,
+                api_version,
+            });
+        }
+
+        let mut parse = VecDeque::new();
+        parse.push_front(meta.fields.into());
+
+        Ok(Self {
             reader: ReadPosition::new(reader),
             containers: VecDeque::new(),
             field: None,
             kind: Some(Kind::Response),
             api_key: Some(api_key),
             api_version: Some(api_version),
-            meta: RootMessageMeta::messages()
-                .responses()
-                .get(&api_key)
-                .map_or_else(Meta::default, |meta| {
-                    let mut parse = VecDeque::new();
-                    parse.push_front(meta.fields.into());
-
-                    Meta {
-                        message: Some(*meta),
-                        parse,
-                        ..Default::default()
-                    }
-                }),
+            meta: Meta {
+                message: Some(*meta),
+                parse,
+                ..Default::default()
+            },
             length: None,
             in_seq_of_primitive: false,
             path: VecDeque::new(),
             in_records: false,
+            max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+            frame_length: None,
+        })
+    }
+
+    /// The maximum length of the frame, and of any single string, bytes or
+    /// records allocation made while decoding it.
+    #[must_use]
+    pub fn max_frame_bytes(self, max_frame_bytes: usize) -> Self {
+        Self {
+            max_frame_bytes,
+            ..self
+        }
+    }
+
+    /// The length of the frame being decoded, so that a string, bytes or
+    /// records length running past its end is rejected before allocating.
+    #[must_use]
+    pub fn frame_length(self, frame_length: usize) -> Self {
+        Self {
+            frame_length: Some(frame_length),
+            ..self
+        }
+    }
+
+    fn within_limit(&self, length: usize) -> Result<usize> {
+        if length > self.max_frame_bytes {
+            Err(Error::FrameTooLarge {
+                length,
+                maximum: self.max_frame_bytes,
+            })
+        } else {
+            Ok(length)
+        }
+    }
+
+    // a length read from the frame, checked against the bytes that
+    // remain in it before anything is allocated to hold them
+    fn within_remaining(&self, length: usize) -> Result<usize> {
+        let Some(frame_length) = self.frame_length else {
+            return self.within_limit(length);
+        };
+
+        let remaining = usize::try_from(self.reader.position)
+            .map_or(0, |position| frame_length.saturating_sub(position));
+
+        if length > remaining {
+            Err(Error::FrameTooLarge {
+                length,
+                maximum: remaining,
+            })
+        } else {
+            Ok(length)
         }
     }
 
+    // read exactly length bytes without zero filling a buffer first, the
+    // result becoming a String or Bytes without a further copy
+    fn read_vec(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(self.within_remaining(length)?);
+
+        let read = (&mut self.reader)
+            .take(u64::try_from(length)?)
+            .read_to_end(&mut buf)?;
+
+        if read == length {
+            Ok(buf)
+        } else {
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+        }
+    }
+
+    fn is_frame_size(&self) -> bool {
+        self.containers.front().is_some_and(|c| c.name() == "Frame")
+            && self.field.is_some_and(|field| field == "size")
+    }
+
     #[must_use]
     pub fn field_name(&self) -> String {
         self.path.iter().fold(String::new(), |acc, step| {
@@ -203,25 +296,35 @@ impl<'de> Decoder<'de> {
             .is_some_and(|c| c.name() == "HeaderMezzanine")
     }
 
+    fn is_client_id(&self) -> bool {
+        self.in_header() && self.field.is_some_and(|field| field == "client_id")
+    }
+
     #[must_use]
     fn is_flexible(&self) -> bool {
-        if self.in_header()
-            && ((self.kind.is_some_and(|kind| kind == Kind::Request)
-                && self.field.is_some_and(|field| field == "client_id"))
-                || (self.kind.is_some_and(|kind| kind == Kind::Response)
-                    && self.api_key.is_some_and(|api_key| api_key == 18)))
-        {
-            false
-        } else {
-            self.meta.message.is_some_and(|meta| {
-                self.api_version
-                    .map_or(false, |api_version| meta.is_flexible(api_version))
+        self.meta.message.is_some_and(|meta| {
+            self.api_version.is_some_and(|api_version| {
+                if self.in_header() {
+                    // only the tagged fields of a header, never the client id
+                    self.field.is_some_and(|field| field == "tag_buffer")
+                        && meta.is_header_flexible(api_version)
+                } else {
+                    meta.is_flexible(api_version)
+                }
             })
-        }
+        })
     }
 
     #[must_use]
     fn is_valid(&self) -> bool {
+        if self.is_client_id() {
+            return self
+                .meta
+                .message
+                .zip(self.api_version)
+                .is_none_or(|(meta, api_version)| meta.header_version(api_version) > 0);
+        }
+
         self.api_version.map_or(true, |api_version| {
             self.meta
                 .field
@@ -248,10 +351,16 @@ impl<'de> Decoder<'de> {
         self.meta.field.is_some_and(|field| field.kind.is_records())
     }
 
+    #[must_use]
+    fn is_structure(&self) -> bool {
+        self.meta
+            .field
+            .is_some_and(|field| !field.kind.is_sequence() && !field.kind.is_primitive())
+    }
+
     #[must_use]
     fn is_string(&self) -> bool {
-        self.in_header() && self.field.is_some_and(|field| field == "client_id")
-            || self.meta.field.is_some_and(|field| field.kind.is_string())
+        self.is_client_id() || self.meta.field.is_some_and(|field| field.kind.is_string())
     }
 
     fn read_mandatory_non_nullable_length(&mut self) -> Result<()> {
@@ -262,7 +371,9 @@ impl<'de> Decoder<'de> {
             self.is_valid(),
         );
 
-        if self.in_header() || self.is_nullable() || !self.is_valid() {
+        // the elements of a nullable sequence of primitives are never null
+        if self.in_header() || (self.is_nullable() && !self.in_seq_of_primitive) || !self.is_valid()
+        {
             debug!(
                 "field: {} is not a mandatory non nullable length",
                 self.field_name()
@@ -278,9 +389,9 @@ impl<'de> Decoder<'de> {
         );
 
         if self.is_flexible() {
-            let length = self.unsigned_varint()?;
+            let length = self.compact_length()?;
             debug!("length: {length}");
-            self.length = Some((length - 1).try_into()?);
+            self.length = Some(length.try_into()?);
         } else if self.is_string()
             || (self.in_seq_of_primitive
                 && self.meta.field.is_some_and(|field| {
@@ -309,33 +420,142 @@ impl<'de> Decoder<'de> {
     }
 
     fn unsigned_varint(&mut self) -> Result<u32> {
-        const CONTINUATION: u8 = 0b1000_0000;
-        const MASK: u8 = 0b0111_1111;
-        let mut shift = 0u8;
-        let mut accumulator = 0u32;
-        let mut done = false;
+        varint::read_unsigned_varint(&mut self.reader)
+    }
 
-        let mut buf = [0u8; 1];
+    // compact length of a non-nullable field, where zero would encode null
+    fn compact_length(&mut self) -> Result<u32> {
+        self.unsigned_varint().and_then(|length| {
+            length.checked_sub(1).ok_or_else(|| Error::InvalidLength {
+                field: self.field_name(),
+                length,
+            })
+        })
+    }
+
+    fn skip_bytes(&mut self, length: usize) -> Result<()> {
+        let expected = u64::try_from(length)?;
+
+        if io::copy(&mut (&mut self.reader).take(expected), &mut io::sink())? == expected {
+            Ok(())
+        } else {
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+        }
+    }
 
-        while !done {
+    // the length of a string, bytes, records or sequence, none when null
+    fn skip_length(&mut self, non_flexible: usize) -> Result<Option<usize>> {
+        if self.is_flexible() {
+            match self.unsigned_varint()? {
+                0 => Ok(None),
+                length => usize::try_from(length - 1).map(Some).map_err(Into::into),
+            }
+        } else if non_flexible == size_of::<i16>() {
+            let mut buf = [0u8; 2];
             self.reader.read_exact(&mut buf)?;
 
-            if buf[0] & CONTINUATION == CONTINUATION {
-                let intermediate = u32::from(buf[0] & MASK);
-                accumulator += intermediate << shift;
-                shift += 7;
-            } else {
-                accumulator += u32::from(buf[0]) << shift;
-                done = true;
+            match i16::from_be_bytes(buf) {
+                -1 => Ok(None),
+                length => usize::try_from(length).map(Some).map_err(Into::into),
+            }
+        } else {
+            let mut buf = [0u8; 4];
+            self.reader.read_exact(&mut buf)?;
+
+            match i32::from_be_bytes(buf) {
+                -1 => Ok(None),
+                length => usize::try_from(length).map(Some).map_err(Into::into),
+            }
+        }
+    }
+
+    fn skip_tag_buffer(&mut self) -> Result<()> {
+        for _ in 0..self.unsigned_varint()? {
+            _ = self.unsigned_varint()?;
+
+            let length = self.unsigned_varint().map(usize::try_from)??;
+            self.skip_bytes(length)?;
+        }
+
+        Ok(())
+    }
+
+    fn skip_kind(&mut self, kind: KindMeta, fields: &[(&str, &FieldMeta)]) -> Result<()> {
+        debug!(kind = kind.name(), field = %self.field_name());
+
+        match kind.name() {
+            "bool" | "int8" => self.skip_bytes(1),
+            "int16" | "uint16" => self.skip_bytes(2),
+            "int32" => self.skip_bytes(4),
+            "int64" | "float64" => self.skip_bytes(8),
+            "uuid" => self.skip_bytes(16),
+
+            "string" => self
+                .skip_length(size_of::<i16>())?
+                .map_or(Ok(()), |length| self.skip_bytes(length)),
+
+            "bytes" | "records" => self
+                .skip_length(size_of::<i32>())?
+                .map_or(Ok(()), |length| self.skip_bytes(length)),
+
+            _ => {
+                if let Some(element) = kind.kind_of_sequence() {
+                    for _ in 0..self.skip_length(size_of::<i32>())?.unwrap_or_default() {
+                        self.skip_kind(element, fields)?;
+                    }
+
+                    Ok(())
+                } else if fields.is_empty() {
+                    Err(Error::UnsupportedKind {
+                        kind: kind.name().into(),
+                        field: self.field_name(),
+                    })
+                } else {
+                    for (_, field) in fields.iter().filter(|(_, field)| field.tag.is_none()) {
+                        self.skip_field(field)?;
+                    }
+
+                    if self.is_flexible() {
+                        self.skip_tag_buffer()
+                    } else {
+                        Ok(())
+                    }
+                }
             }
         }
+    }
+
+    // consume the wire representation of a field, using its metadata
+    fn skip_field(&mut self, field: &FieldMeta) -> Result<()> {
+        if self
+            .api_version
+            .is_some_and(|api_version| !field.version.within(api_version))
+        {
+            return Ok(());
+        }
 
-        Ok(accumulator)
+        self.skip_kind(field.kind, field.fields)
     }
 
     pub fn position(&self) -> u64 {
         self.reader.position
     }
+
+    // attach the position, message and field path to an error, leaving an
+    // error that has already been located by a nested field untouched
+    fn located(&self, error: Error) -> Error {
+        if matches!(error, Error::At { .. }) {
+            error
+        } else {
+            Error::At {
+                position: self.position(),
+                message: self.meta.message.map(|message| message.name),
+                api_version: self.api_version,
+                path: self.field_name(),
+                source: Box::new(error),
+            }
+        }
+    }
 }
 
 impl<'de> fmt::Debug for Decoder<'de> {
@@ -347,12 +567,19 @@ impl<'de> fmt::Debug for Decoder<'de> {
 impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
     type Error = Error;
 
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
         let _ = visitor;
-        todo!()
+        Err(Error::UnsupportedDeserialization {
+            what: "deserialize_any",
+            field: self.field_name(),
+        })
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -445,6 +672,13 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
             self.field_name(),
             type_name::<V::Value>(),
         );
+
+        if self.is_frame_size() {
+            _ = usize::try_from(v)
+                .map_err(Into::into)
+                .and_then(|size| self.within_limit(size + size_of::<i32>()))?;
+        }
+
         visitor.visit_i32(v)
     }
 
@@ -568,7 +802,10 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
             debug!("struct: {:?}, field: {}", self.containers.front(), field);
         }
         let _ = visitor;
-        unimplemented!()
+        Err(Error::UnsupportedDeserialization {
+            what: "deserialize_char",
+            field: self.field_name(),
+        })
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -577,16 +814,22 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
     {
         self.read_mandatory_non_nullable_length()?;
 
-        self.length
-            .ok_or(Error::StringWithoutLength)
-            .and_then(|length| {
-                let mut buf = vec![0u8; length];
-                self.reader.read_exact(&mut buf)?;
-                from_utf8(buf.as_slice())
-                    .map_err(Into::into)
-                    .inspect(|v| debug!("visitor: {}, v: {v}", type_name_of_val(&visitor)))
-                    .and_then(|s| visitor.visit_str(s))
-            })
+        let length = self.length.ok_or(Error::StringWithoutLength)?;
+
+        // the visitor only borrows the string, so a short one stays on the stack
+        let mut short = [0u8; SHORT_STR];
+
+        let buf = if length <= SHORT_STR {
+            self.reader.read_exact(&mut short[..length])?;
+            Cow::Borrowed(&short[..length])
+        } else {
+            self.read_vec(length).map(Cow::Owned)?
+        };
+
+        from_utf8(&buf)
+            .map_err(Into::into)
+            .inspect(|v| debug!("visitor: {}, v: {v}", type_name_of_val(&visitor)))
+            .and_then(|s| visitor.visit_str(s))
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -606,10 +849,7 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
         }
 
         if let Some(length) = self.length.take() {
-            let mut buf = vec![0u8; length];
-            self.reader.read_exact(&mut buf)?;
-
-            String::from_utf8(buf)
+            String::from_utf8(self.read_vec(length)?)
                 .map_err(Into::into)
                 .inspect(|v| {
                     debug!(r#"field: {}, value: "{v}""#, self.field_name(),);
@@ -629,8 +869,8 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
         }
 
         let length = if self.is_flexible() {
-            self.unsigned_varint()
-                .and_then(|length| usize::try_from(length - 1).map_err(Into::into))?
+            self.compact_length()
+                .and_then(|length| usize::try_from(length).map_err(Into::into))?
         } else {
             let mut buf = [0u8; 4];
 
@@ -638,7 +878,7 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
             usize::try_from(u32::from_be_bytes(buf))?
         };
 
-        let mut buf = vec![0u8; length];
+        let mut buf = vec![0u8; self.within_remaining(length)?];
         self.reader.read_exact(&mut buf)?;
         visitor.visit_bytes(&buf[..])
     }
@@ -651,9 +891,18 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
             debug!("struct: {:?}, field: {}", self.containers.front(), field);
         }
 
+        // the length of records has already been read (and limited) by deserialize_option
+        if self.in_records {
+            self.in_records = false;
+
+            let mut buf = vec![0u8; self.length.take().unwrap_or_default()];
+            self.reader.read_exact(&mut buf)?;
+            return visitor.visit_byte_buf(buf);
+        }
+
         let length = if self.is_flexible() {
-            self.unsigned_varint()
-                .and_then(|length| usize::try_from(length - 1).map_err(Into::into))?
+            self.compact_length()
+                .and_then(|length| usize::try_from(length).map_err(Into::into))?
         } else {
             let mut buf = [0u8; 4];
 
@@ -661,9 +910,9 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
             usize::try_from(u32::from_be_bytes(buf))?
         };
 
-        let mut buf = vec![0u8; length];
+        let mut buf = vec![0u8; self.within_remaining(length)?];
         self.reader.read_exact(&mut buf)?;
-        visitor.visit_bytes(&buf[..])
+        visitor.visit_byte_buf(buf)
     }
 
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -689,7 +938,8 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
                 }
             } else if self.is_records() {
                 let length = if self.is_flexible() {
-                    self.unsigned_varint().map(|length| length - 1)?
+                    self.unsigned_varint()
+                        .map(|length| length.saturating_sub(1))?
                 } else {
                     let mut buf = [0u8; 4];
                     self.reader.read_exact(&mut buf)?;
@@ -702,7 +952,7 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
                 if length == 0 {
                     visitor.visit_none()
                 } else {
-                    self.length = Some(length.try_into()?);
+                    self.length = Some(self.within_remaining(length.try_into()?)?);
                     self.in_records = true;
                     visitor.visit_some(self)
                 }
@@ -735,7 +985,13 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
             } else if self.is_string() {
                 if self.is_flexible() {
                     self.unsigned_varint().and_then(|length| {
-                        if length == 0 {
+                        if length == 0 && !self.is_nullable() {
+                            // zero would encode null, which this string cannot be
+                            Err(Error::InvalidLength {
+                                field: self.field_name(),
+                                length,
+                            })
+                        } else if length == 0 {
                             self.length = None;
                             visitor.visit_none()
                         } else {
@@ -758,6 +1014,18 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
                         visitor.visit_some(self)
                     }
                 }
+            } else if self.is_nullable() && self.is_structure() {
+                // a nullable structure is prefixed with -1 when absent
+                let mut buf = [0u8; 1];
+                self.reader.read_exact(&mut buf)?;
+
+                self.length = None;
+
+                if i8::from_be_bytes(buf) == -1 {
+                    visitor.visit_none()
+                } else {
+                    visitor.visit_some(self)
+                }
             } else {
                 self.length = None;
                 visitor.visit_some(self)
@@ -777,7 +1045,10 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
             type_name_of_val(&visitor),
             type_name::<V::Value>(),
         );
-        todo!()
+        Err(Error::UnsupportedDeserialization {
+            what: "deserialize_unit",
+            field: self.field_name(),
+        })
     }
 
     fn deserialize_unit_struct<V>(
@@ -793,7 +1064,10 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
         }
 
         debug!("name: {name}, visitor: {}", type_name_of_val(&visitor));
-        todo!()
+        Err(Error::UnsupportedDeserialization {
+            what: "deserialize_unit_struct",
+            field: self.field_name(),
+        })
     }
 
     fn deserialize_newtype_struct<V>(
@@ -880,7 +1154,10 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
             "name: {name}, len: {len}, visitor: {}",
             type_name_of_val(&visitor)
         );
-        todo!()
+        Err(Error::UnsupportedDeserialization {
+            what: "deserialize_tuple_struct",
+            field: self.field_name(),
+        })
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -888,7 +1165,10 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
         V: Visitor<'de>,
     {
         debug!("visitor: {}", type_name_of_val(&visitor));
-        todo!()
+        Err(Error::UnsupportedDeserialization {
+            what: "deserialize_map",
+            field: self.field_name(),
+        })
     }
 
     fn deserialize_struct<V>(
@@ -992,7 +1272,12 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
 
             (Some(Container::Enum { name: "Body", .. }), Some(meta)) => meta.name,
 
-            container => todo!("container: {:?}", container),
+            container => {
+                return Err(Error::UnsupportedDeserialization {
+                    what: "identifier",
+                    field: format!("{container:?}"),
+                })
+            }
         })
     }
 
@@ -1000,8 +1285,17 @@ impl<'de, 'a> Deserializer<'de> for &'a mut Decoder<'de> {
     where
         V: Visitor<'de>,
     {
-        debug!("visitor: {}", type_name_of_val(&visitor));
-        todo!()
+        debug!(
+            "deserialize_ignored_any, field: {}, visitor: {}",
+            self.field_name(),
+            type_name_of_val(&visitor)
+        );
+
+        self.meta
+            .field
+            .ok_or(Error::NoSuchField(self.field.unwrap_or_default()))
+            .and_then(|field| self.skip_field(field))
+            .and_then(|()| visitor.visit_unit())
     }
 }
 
@@ -1035,6 +1329,10 @@ impl<'de, 'a> SeqAccess<'de> for Batch<'de, 'a> {
             Ok(None)
         }
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        usize::try_from(self.remaining).ok()
+    }
 }
 
 #[derive(Debug)]
@@ -1121,7 +1419,10 @@ impl<'de, 'a> SeqAccess<'de> for Struct<'de, 'a> {
         );
 
         self.de.path.push_front(field);
-        let outcome = seed.deserialize(&mut *self.de).map(Some);
+        let outcome = seed
+            .deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|error| self.de.located(error));
         _ = self.de.path.pop_front();
         outcome
     }
@@ -1156,7 +1457,10 @@ impl<'de, 'a> VariantAccess<'de> for Enum<'de, 'a> {
     type Error = Error;
 
     fn unit_variant(self) -> Result<(), Self::Error> {
-        Err(Error::Message(String::from("expecting string")))
+        Err(Error::UnsupportedVariant {
+            container: self.name,
+            got: "unit",
+        })
     }
 
     fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
@@ -1164,7 +1468,10 @@ impl<'de, 'a> VariantAccess<'de> for Enum<'de, 'a> {
         T: DeserializeSeed<'de>,
     {
         debug!("seed: {}", type_name_of_val(&seed));
-        todo!()
+        Err(Error::UnsupportedDeserialization {
+            what: "newtype_variant",
+            field: self.de.field_name(),
+        })
     }
 
     fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
@@ -1172,7 +1479,10 @@ impl<'de, 'a> VariantAccess<'de> for Enum<'de, 'a> {
         V: Visitor<'de>,
     {
         debug!("len: {len}, visitor: {}", type_name_of_val(&visitor));
-        todo!()
+        Err(Error::UnsupportedDeserialization {
+            what: "tuple_variant",
+            field: self.de.field_name(),
+        })
     }
 
     fn struct_variant<V>(
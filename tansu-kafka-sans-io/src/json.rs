@@ -0,0 +1,206 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Frames as plain data for JSON, or any other self describing format.
+//
+// The Serialize and Deserialize of crate::Frame, crate::Header and
+// crate::Body are for the Kafka wire protocol. The types here have the same
+// shape, convert to and from their wire counterparts, and serialize as
+// ordinary structures with any bytes (including records) as base64:
+//
+// let json = serde_json::to_string(&json::Frame::from(frame))?;
+// let frame = Frame::from(serde_json::from_str::<json::Frame>(&json)?);
+
+use serde::{Deserialize, Serialize};
+
+pub use crate::json_body::Body;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
+pub struct Frame {
+    pub size: i32,
+    pub header: Header,
+    pub body: Body,
+}
+
+impl From<crate::Frame> for Frame {
+    fn from(value: crate::Frame) -> Self {
+        Self {
+            size: value.size,
+            header: value.header.into(),
+            body: value.body.into(),
+        }
+    }
+}
+
+impl From<Frame> for crate::Frame {
+    fn from(value: Frame) -> Self {
+        Self {
+            size: value.size,
+            header: value.header.into(),
+            body: value.body.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
+pub enum Header {
+    Request {
+        api_key: i16,
+        api_version: i16,
+        correlation_id: i32,
+        client_id: Option<String>,
+    },
+    Response {
+        correlation_id: i32,
+    },
+}
+
+impl From<crate::Header> for Header {
+    fn from(value: crate::Header) -> Self {
+        match value {
+            crate::Header::Request {
+                api_key,
+                api_version,
+                correlation_id,
+                client_id,
+            } => Self::Request {
+                api_key,
+                api_version,
+                correlation_id,
+                client_id,
+            },
+
+            crate::Header::Response { correlation_id } => Self::Response { correlation_id },
+        }
+    }
+}
+
+impl From<Header> for crate::Header {
+    fn from(value: Header) -> Self {
+        match value {
+            Header::Request {
+                api_key,
+                api_version,
+                correlation_id,
+                client_id,
+            } => Self::Request {
+                api_key,
+                api_version,
+                correlation_id,
+                client_id,
+            },
+
+            Header::Response { correlation_id } => Self::Response { correlation_id },
+        }
+    }
+}
+
+// The wire codec is not human readable, so these fall back to the
+// serialization of Bytes leaving the protocol encoding unchanged.
+
+pub(crate) mod bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use bytes::Bytes;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S>(value: &Bytes, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&STANDARD.encode(value))
+        } else {
+            value.serialize(serializer)
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Bytes, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            String::deserialize(deserializer).and_then(|encoded| {
+                STANDARD
+                    .decode(encoded)
+                    .map(Bytes::from)
+                    .map_err(de::Error::custom)
+            })
+        } else {
+            Bytes::deserialize(deserializer)
+        }
+    }
+}
+
+pub(crate) mod option_bytes {
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize, Serialize)]
+    struct Base64(#[serde(with = "super::bytes")] Bytes);
+
+    pub(crate) fn serialize<S>(value: &Option<Bytes>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            value.clone().map(Base64).serialize(serializer)
+        } else {
+            value.serialize(serializer)
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Option<Bytes>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            Option::<Base64>::deserialize(deserializer)
+                .map(|value| value.map(|Base64(value)| value))
+        } else {
+            Option::<Bytes>::deserialize(deserializer)
+        }
+    }
+}
+
+pub(crate) mod unknown_tagged_fields {
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize, Serialize)]
+    struct Base64(u32, #[serde(with = "super::bytes")] Bytes);
+
+    pub(crate) fn serialize<S>(value: &[(u32, Bytes)], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        value
+            .iter()
+            .map(|(tag, data)| Base64(*tag, data.clone()))
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Vec<(u32, Bytes)>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<Base64>::deserialize(deserializer).map(|fields| {
+            fields
+                .into_iter()
+                .map(|Base64(tag, data)| (tag, data))
+                .collect()
+        })
+    }
+}
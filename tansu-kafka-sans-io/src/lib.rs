@@ -14,17 +14,30 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 #![cfg_attr(feature = "nightly-features", feature(error_generic_member_access))]
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+#[cfg(feature = "tokio-util")]
+pub mod codec;
+pub mod consumer;
 pub mod de;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod primitive;
 pub mod record;
+pub mod response;
 pub mod ser;
+#[cfg(feature = "tokio")]
+pub mod stream;
+mod summary;
 
-use bytes::{Buf, Bytes};
+#[cfg(feature = "snappy")]
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 pub use de::Decoder;
 use flate2::read::GzDecoder;
 use primitive::tagged::TagBuffer;
-use record::deflated::Frame as RecordBatch;
-pub use ser::Encoder;
+use record::Records as RecordBatch;
+use ser::Output;
+pub use ser::{Encoder, Segments};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "nightly-features")]
 use std::backtrace::Backtrace;
@@ -37,9 +50,13 @@ use std::{
     sync::OnceLock,
     time::{Duration, SystemTime, SystemTimeError},
 };
-use tansu_kafka_model::{MessageKind, MessageMeta};
+use tansu_kafka_model::{Listener, MessageKind, MessageMeta};
 use tracing::{debug, error, warn};
 
+/// The default maximum length of a frame, including its size prefix, and of
+/// any single string, bytes or records allocation made while decoding it.
+pub const DEFAULT_MAX_FRAME_BYTES: usize = 100 * 1024 * 1024;
+
 #[derive(Debug)]
 pub struct RootMessageMeta {
     pub(crate) requests: HashMap<i16, &'static MessageMeta>,
@@ -85,26 +102,128 @@ impl RootMessageMeta {
     pub fn responses(&self) -> &HashMap<i16, &'static MessageMeta> {
         &self.responses
     }
+
+    /// Requests that are accepted by a broker listener.
+    #[must_use]
+    pub fn broker_requests(&self) -> HashMap<i16, &'static MessageMeta> {
+        self.requests_for(Listener::Broker)
+    }
+
+    /// Requests that are accepted by a controller listener.
+    #[must_use]
+    pub fn controller_requests(&self) -> HashMap<i16, &'static MessageMeta> {
+        self.requests_for(Listener::Controller)
+    }
+
+    fn requests_for(&self, listener: Listener) -> HashMap<i16, &'static MessageMeta> {
+        self.requests
+            .iter()
+            .filter(|(_, meta)| meta.is_listening(listener))
+            .map(|(api_key, meta)| (*api_key, *meta))
+            .collect()
+    }
+
+    /// The api key, minimum and maximum valid version of every request,
+    /// ordered by api key.
+    #[must_use]
+    pub fn api_versions(&self) -> Vec<(i16, i16, i16)> {
+        Self::versions_of(self.requests.values())
+    }
+
+    fn versions_of<'a>(
+        requests: impl Iterator<Item = &'a &'static MessageMeta>,
+    ) -> Vec<(i16, i16, i16)> {
+        let mut api_versions = requests
+            .map(|meta| {
+                (
+                    meta.api_key,
+                    meta.version.valid.start,
+                    meta.version.valid.end,
+                )
+            })
+            .filter(|(_, min, max)| min <= max)
+            .collect::<Vec<_>>();
+
+        api_versions.sort_unstable();
+        api_versions
+    }
+
+    /// The api versions of only those broker requests with an api key
+    /// that is in `handled`.
+    #[must_use]
+    pub fn api_versions_within(&self, handled: &[i16]) -> Vec<(i16, i16, i16)> {
+        Self::versions_of(self.broker_requests().values())
+            .into_iter()
+            .filter(|(api_key, _, _)| handled.contains(api_key))
+            .collect()
+    }
 }
 
+impl Display for ApiKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[non_exhaustive]
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     ApiError(ErrorCode),
-    EnvVar(VarError),
-    FromUtf8(string::FromUtf8Error),
+    At {
+        position: u64,
+        message: Option<&'static str>,
+        api_version: Option<i16>,
+        path: String,
+        source: Box<Error>,
+    },
+    CrcMismatch {
+        expected: u32,
+        computed: u32,
+    },
+    EnvVar(#[source] VarError),
+    FrameTooLarge {
+        length: usize,
+        maximum: usize,
+    },
+    FromUtf8(#[source] string::FromUtf8Error),
+    Incomplete {
+        needed: Option<usize>,
+    },
+    InflatedTooLarge {
+        maximum: usize,
+    },
     InvalidAckValue(i16),
+    InvalidAclOperation(i8),
+    InvalidAclPermissionType(i8),
+    InvalidBatchLength(i32),
+    InvalidConsumerProtocolVersion(i16),
     InvalidCoordinatorType(i8),
+    InvalidFrameLength(i32),
     InvalidIsolationLevel(i8),
-    Io(io::Error),
+    InvalidLength {
+        field: String,
+        length: u32,
+    },
+    InvalidMessageSize(i32),
+    InvalidPatternType(i8),
+    InvalidResourceType(i8),
+    InvalidVarint,
+    Io(#[source] io::Error),
+    MalformedControlRecord,
+    MalformedMessageSet,
     Message(String),
+    MissingField {
+        name: &'static str,
+        api_version: i16,
+    },
     NoSuchField(&'static str),
     NoSuchMessage(&'static str),
     NoSuchRequest(i16),
-    Snap(#[from] snap::Error),
     StringWithoutApiVersion,
     StringWithoutLength,
-    SystemTime(SystemTimeError),
-    TansuKafkaModel(tansu_kafka_model::Error),
+    SystemTime(#[source] SystemTimeError),
+    TansuKafkaModel(#[source] tansu_kafka_model::Error),
+    TruncatedSnappyBlock,
     TryFromInt {
         #[from]
         source: num::TryFromIntError,
@@ -113,8 +232,29 @@ pub enum Error {
     },
     UnexpectedTaggedHeader(HeaderMezzanine),
     UnknownApiErrorCode(i16),
+    UnknownApiKey(i16),
     UnknownCompressionType(i16),
-    Utf8(str::Utf8Error),
+    UnknownControlType(i16),
+    UnknownCorrelationId(i32),
+    UnsupportedApiVersion {
+        api_key: i16,
+        api_version: i16,
+    },
+    UnsupportedCompression(Compression),
+    UnsupportedDeserialization {
+        what: &'static str,
+        field: String,
+    },
+    UnsupportedKind {
+        kind: String,
+        field: String,
+    },
+    UnsupportedMagic(i8),
+    UnsupportedVariant {
+        container: &'static str,
+        got: &'static str,
+    },
+    Utf8(#[source] str::Utf8Error),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -122,12 +262,143 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Error::Message(e) => f.write_str(e),
-            e => write!(f, "{e:?}"),
+            Error::At {
+                position,
+                message,
+                api_version,
+                path,
+                source,
+            } => {
+                write!(f, "at byte {position}")?;
+
+                match (message, api_version) {
+                    (Some(message), Some(api_version)) => write!(f, ", {message} v{api_version}")?,
+                    (Some(message), None) => write!(f, ", {message}")?,
+                    (None, _) => (),
+                }
+
+                if !path.is_empty() {
+                    write!(f, ", {path}")?;
+                }
+
+                write!(f, ": {source}")
+            }
+
+            Error::ApiError(error_code) => write!(f, "api error: {error_code}"),
+            Error::CrcMismatch { expected, computed } => {
+                write!(
+                    f,
+                    "crc mismatch, expected: {expected}, computed: {computed}"
+                )
+            }
+            Error::EnvVar(_) => f.write_str("environment variable"),
+            Error::FrameTooLarge { length, maximum } => {
+                write!(
+                    f,
+                    "frame of {length} bytes exceeds maximum of {maximum} bytes"
+                )
+            }
+            Error::FromUtf8(_) | Error::Utf8(_) => f.write_str("invalid utf-8"),
+            Error::Incomplete {
+                needed: Some(needed),
+            } => {
+                write!(f, "incomplete, needed: {needed} more bytes")
+            }
+            Error::Incomplete { needed: None } => f.write_str("incomplete"),
+            Error::InflatedTooLarge { maximum } => {
+                write!(f, "inflated records exceed maximum of {maximum} bytes")
+            }
+            Error::InvalidAckValue(value) => write!(f, "invalid ack value: {value}"),
+            Error::InvalidAclOperation(value) => write!(f, "invalid acl operation: {value}"),
+            Error::InvalidAclPermissionType(value) => {
+                write!(f, "invalid acl permission type: {value}")
+            }
+            Error::InvalidBatchLength(length) => write!(f, "invalid batch length: {length}"),
+            Error::InvalidConsumerProtocolVersion(version) => {
+                write!(f, "invalid consumer protocol version: {version}")
+            }
+            Error::InvalidCoordinatorType(value) => {
+                write!(f, "invalid coordinator type: {value}")
+            }
+            Error::InvalidFrameLength(length) => write!(f, "invalid frame length: {length}"),
+            Error::InvalidIsolationLevel(value) => write!(f, "invalid isolation level: {value}"),
+            Error::InvalidLength { field, length } => {
+                write!(f, "invalid length: {length}, for field: {field}")
+            }
+            Error::InvalidMessageSize(size) => write!(f, "invalid message size: {size}"),
+            Error::InvalidPatternType(value) => write!(f, "invalid pattern type: {value}"),
+            Error::InvalidResourceType(value) => write!(f, "invalid resource type: {value}"),
+            Error::InvalidVarint => f.write_str("invalid varint"),
+            Error::Io(_) => f.write_str("io"),
+            Error::MalformedControlRecord => f.write_str("malformed control record"),
+            Error::MalformedMessageSet => f.write_str("malformed message set"),
+            Error::Message(message) => f.write_str(message),
+            Error::MissingField { name, api_version } => {
+                write!(f, "missing field: {name}, in v{api_version}")
+            }
+            Error::NoSuchField(name) => write!(f, "no such field: {name}"),
+            Error::NoSuchMessage(name) => write!(f, "no such message: {name}"),
+            Error::NoSuchRequest(api_key) => write!(f, "no such request: {api_key}"),
+            Error::StringWithoutApiVersion => f.write_str("string without api version"),
+            Error::StringWithoutLength => f.write_str("string without length"),
+            Error::SystemTime(_) => f.write_str("system time"),
+            Error::TansuKafkaModel(_) => f.write_str("kafka model"),
+            Error::TruncatedSnappyBlock => f.write_str("truncated snappy block"),
+            Error::TryFromInt { .. } => f.write_str("integer conversion"),
+            Error::UnexpectedTaggedHeader(header) => {
+                write!(f, "unexpected tagged header: {header:?}")
+            }
+            Error::UnknownApiErrorCode(code) => write!(f, "unknown api error code: {code}"),
+            Error::UnknownApiKey(api_key) => write!(f, "unknown api key: {api_key}"),
+            Error::UnknownCompressionType(value) => {
+                write!(f, "unknown compression type: {value}")
+            }
+            Error::UnknownControlType(value) => write!(f, "unknown control type: {value}"),
+            Error::UnknownCorrelationId(correlation_id) => {
+                write!(f, "unknown correlation id: {correlation_id}")
+            }
+            Error::UnsupportedApiVersion {
+                api_key,
+                api_version,
+            } => write!(f, "unsupported api key: {api_key}, version: {api_version}"),
+            Error::UnsupportedCompression(compression) => {
+                write!(f, "unsupported compression: {compression:?}")
+            }
+            Error::UnsupportedDeserialization { what, field } => {
+                write!(
+                    f,
+                    "unsupported deserialization of {what}, for field: {field}"
+                )
+            }
+            Error::UnsupportedKind { kind, field } => {
+                write!(f, "unsupported kind: {kind}, for field: {field}")
+            }
+            Error::UnsupportedMagic(magic) => write!(f, "unsupported magic: {magic}"),
+            Error::UnsupportedVariant { container, got } => {
+                write!(f, "unsupported {got} variant of {container}")
+            }
         }
     }
 }
 
+impl Error {
+    /// The underlying error, without the position and field path that the
+    /// [`Decoder`] attaches as [`Error::At`].
+    #[must_use]
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Error::At { source, .. } => source.root_cause(),
+            otherwise => otherwise,
+        }
+    }
+
+    /// Whether more bytes are needed to complete a frame, batch or field.
+    #[must_use]
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self.root_cause(), Error::Incomplete { .. })
+    }
+}
+
 impl serde::ser::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
         Error::Message(msg.to_string())
@@ -184,6 +455,49 @@ pub struct Frame {
 }
 
 impl Frame {
+    const SIZE_PREFIX: usize = size_of::<i32>();
+
+    /// The length of the frame at the start of `bytes`, including its size prefix.
+    ///
+    /// Returns [`Error::Incomplete`] with the number of further bytes that are
+    /// needed when `bytes` holds only part of a frame, so that a caller can buffer
+    /// more before trying again. A negative size prefix is an
+    /// [`Error::InvalidFrameLength`].
+    pub fn check(bytes: &[u8]) -> Result<usize> {
+        Self::check_within(bytes, usize::MAX)
+    }
+
+    /// As [`Frame::check`], returning [`Error::FrameTooLarge`] as soon as the
+    /// size prefix declares a frame longer than `max_frame_bytes`.
+    pub fn check_within(bytes: &[u8], max_frame_bytes: usize) -> Result<usize> {
+        if bytes.len() < Self::SIZE_PREFIX {
+            return Err(Error::Incomplete {
+                needed: Some(Self::SIZE_PREFIX - bytes.len()),
+            });
+        }
+
+        let mut prefix = [0u8; Self::SIZE_PREFIX];
+        prefix.copy_from_slice(&bytes[..Self::SIZE_PREFIX]);
+        let size = i32::from_be_bytes(prefix);
+
+        let length = usize::try_from(size)
+            .map(|size| size + Self::SIZE_PREFIX)
+            .map_err(|_| Error::InvalidFrameLength(size))?;
+
+        if length > max_frame_bytes {
+            Err(Error::FrameTooLarge {
+                length,
+                maximum: max_frame_bytes,
+            })
+        } else if bytes.len() < length {
+            Err(Error::Incomplete {
+                needed: Some(length - bytes.len()),
+            })
+        } else {
+            Ok(length)
+        }
+    }
+
     pub fn request(header: Header, body: Body) -> Result<Vec<u8>> {
         let mut c = Cursor::new(vec![]);
 
@@ -206,12 +520,32 @@ impl Frame {
     }
 
     pub fn request_from_bytes(bytes: &[u8]) -> Result<Frame> {
-        let mut c = Cursor::new(bytes);
-        let mut deserializer = Decoder::request(&mut c);
+        let length = Self::check_within(bytes, DEFAULT_MAX_FRAME_BYTES)?;
+        let mut c = Cursor::new(&bytes[..length]);
+        let mut deserializer = Decoder::request(&mut c).frame_length(length);
         Frame::deserialize(&mut deserializer)
     }
 
+    #[deprecated(note = "use Frame::encode_response with an ApiKey")]
     pub fn response(header: Header, body: Body, api_key: i16, api_version: i16) -> Result<Vec<u8>> {
+        Self::encode(header, body, api_key, api_version)
+    }
+
+    pub fn encode_response(
+        header: Header,
+        body: Body,
+        api_key: ApiKey,
+        api_version: i16,
+    ) -> Result<Vec<u8>> {
+        Self::encode(header, body, api_key.into(), api_version)
+    }
+
+    pub(crate) fn encode(
+        header: Header,
+        body: Body,
+        api_key: i16,
+        api_version: i16,
+    ) -> Result<Vec<u8>> {
         let mut c = Cursor::new(vec![]);
         let mut serializer = Encoder::response(&mut c, api_key, api_version);
 
@@ -236,11 +570,111 @@ impl Frame {
         Ok(c.into_inner())
     }
 
+    #[deprecated(note = "use Frame::decode_response with an ApiKey")]
     pub fn response_from_bytes(bytes: &[u8], api_key: i16, api_version: i16) -> Result<Frame> {
-        let mut c = Cursor::new(bytes);
-        let mut deserializer = Decoder::response(&mut c, api_key, api_version);
+        Self::decode(bytes, api_key, api_version)
+    }
+
+    pub fn decode_response(bytes: &[u8], api_key: ApiKey, api_version: i16) -> Result<Frame> {
+        Self::decode(bytes, api_key.into(), api_version)
+    }
+
+    pub(crate) fn decode(bytes: &[u8], api_key: i16, api_version: i16) -> Result<Frame> {
+        let length = Self::check_within(bytes, DEFAULT_MAX_FRAME_BYTES)?;
+        let mut c = Cursor::new(&bytes[..length]);
+        let mut deserializer =
+            Decoder::response(&mut c, api_key, api_version)?.frame_length(length);
         Frame::deserialize(&mut deserializer)
     }
+
+    /// Append this request to `buf`, returning the length of the frame
+    /// including its size prefix.
+    pub fn write_request_into(&self, buf: &mut BytesMut) -> Result<usize> {
+        let start = buf.len();
+        let mut writer = (&mut *buf).writer();
+        self.serialize(&mut Encoder::request(&mut writer))?;
+        Self::patch_size(&mut buf[start..])
+    }
+
+    /// Append this response to `buf`, returning the length of the frame
+    /// including its size prefix.
+    pub fn write_response_into(
+        &self,
+        buf: &mut BytesMut,
+        api_key: ApiKey,
+        api_version: i16,
+    ) -> Result<usize> {
+        let start = buf.len();
+        let mut writer = (&mut *buf).writer();
+        self.serialize(&mut Encoder::response(
+            &mut writer,
+            api_key.into(),
+            api_version,
+        ))?;
+        Self::patch_size(&mut buf[start..])
+    }
+
+    /// This request as [`Segments`] for a vectored write, referencing rather
+    /// than copying any record data.
+    pub fn request_segments(&self) -> Result<Segments> {
+        let mut segments = Segments::sharing(self.body.record_data());
+        self.serialize(&mut Encoder::request_to(Output::Segments(&mut segments)))?;
+        Self::patch_segments(segments)
+    }
+
+    /// This response as [`Segments`] for a vectored write, referencing rather
+    /// than copying any record data.
+    pub fn response_segments(&self, api_key: ApiKey, api_version: i16) -> Result<Segments> {
+        let mut segments = Segments::sharing(self.body.record_data());
+        self.serialize(&mut Encoder::response_to(
+            Output::Segments(&mut segments),
+            api_key.into(),
+            api_version,
+        ))?;
+        Self::patch_segments(segments)
+    }
+
+    fn patch_size(frame: &mut [u8]) -> Result<usize> {
+        let size = i32::try_from(frame.len() - Self::SIZE_PREFIX)?;
+        frame[..Self::SIZE_PREFIX].copy_from_slice(&size.to_be_bytes());
+        Ok(frame.len())
+    }
+
+    fn patch_segments(mut segments: Segments) -> Result<Segments> {
+        let size = i32::try_from(segments.len() - Self::SIZE_PREFIX)?;
+        segments.patch(0, &size.to_be_bytes());
+        Ok(segments)
+    }
+}
+
+impl Body {
+    // the records carried by this body, which a vectored encoding
+    // references rather than copies
+    fn record_data(&self) -> Vec<Bytes> {
+        match self {
+            Self::FetchResponse {
+                responses: Some(responses),
+                ..
+            } => responses
+                .iter()
+                .flat_map(|topic| topic.partitions.iter().flatten())
+                .flat_map(|partition| partition.records.iter())
+                .map(|records| records.as_bytes().clone())
+                .collect(),
+
+            Self::ProduceRequest {
+                topic_data: Some(topic_data),
+                ..
+            } => topic_data
+                .iter()
+                .flat_map(|topic| topic.partition_data.iter().flatten())
+                .flat_map(|partition| partition.records.iter())
+                .map(|records| records.as_bytes().clone())
+                .collect(),
+
+            _ => vec![],
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, PartialOrd, Serialize)]
@@ -364,749 +798,199 @@ impl From<Header> for HeaderMezzanine {
     }
 }
 
-impl TryFrom<i16> for ErrorCode {
-    type Error = Error;
+// Each Kafka error with its code, whether it is retriable and its message,
+// as defined by org.apache.kafka.common.protocol.Errors.
+macro_rules! error_codes {
+    ($(($variant:ident, $code:literal, $retriable:literal, $message:literal),)+) => {
+        #[non_exhaustive]
+        #[derive(Clone, Copy, Deserialize, Eq, Hash, Debug, Ord, PartialEq, PartialOrd, Serialize)]
+        pub enum ErrorCode {
+            $($variant,)+
+        }
 
-    #[allow(clippy::too_many_lines)]
-    fn try_from(value: i16) -> Result<Self, Self::Error> {
-        match value {
-            -1 => Ok(Self::UnknownServerError),
-            0 => Ok(Self::None),
-            1 => Ok(Self::OffsetOutOfRange),
-            2 => Ok(Self::CorruptMessage),
-            3 => Ok(Self::UnknownTopicOrPartition),
-            4 => Ok(Self::InvalidFetchSize),
-            5 => Ok(Self::LeaderNotAvailable),
-            6 => Ok(Self::NotLeaderOrFollower),
-            7 => Ok(Self::RequestTimedOut),
-            8 => Ok(Self::BrokerNotAvailable),
-            9 => Ok(Self::ReplicaNotAvailable),
-            10 => Ok(Self::MessageTooLarge),
-            11 => Ok(Self::StaleControllerEpoch),
-            12 => Ok(Self::OffsetMetadataTooLarge),
-            13 => Ok(Self::NetworkException),
-            14 => Ok(Self::CoordinatorLoadInProgress),
-            15 => Ok(Self::CoordinatorNotAvailable),
-            16 => Ok(Self::NotCoordinator),
-            17 => Ok(Self::InvalidTopicException),
-            18 => Ok(Self::RecordListTooLarge),
-            19 => Ok(Self::NotEnoughReplicas),
-            20 => Ok(Self::NotEnoughReplicasAfterAppend),
-            21 => Ok(Self::InvalidRequiredAcks),
-            22 => Ok(Self::IllegalGeneration),
-            23 => Ok(Self::InconsistentGroupProtocol),
-            24 => Ok(Self::InvalidGroupId),
-            25 => Ok(Self::UnknownMemberId),
-            26 => Ok(Self::InvalidSessionTimeout),
-            27 => Ok(Self::RebalanceInProgress),
-            28 => Ok(Self::InvalidCommitOffsetSize),
-            29 => Ok(Self::TopicAuthorizationFailed),
-            30 => Ok(Self::GroupAuthorizationFailed),
-            31 => Ok(Self::ClusterAuthorizationFailed),
-            32 => Ok(Self::InvalidTimestamp),
-            33 => Ok(Self::UnsupportedSaslMechanism),
-            34 => Ok(Self::IllegalSaslState),
-            35 => Ok(Self::UnsupportedVersion),
-            36 => Ok(Self::TopicAlreadyExists),
-            37 => Ok(Self::InvalidPartitions),
-            38 => Ok(Self::InvalidReplicationFactor),
-            39 => Ok(Self::InvalidReplicaAssignment),
-            40 => Ok(Self::InvalidConfig),
-            41 => Ok(Self::NotController),
-            42 => Ok(Self::InvalidRequest),
-            43 => Ok(Self::UnsupportedForMessageFormat),
-            44 => Ok(Self::PolicyViolation),
-            45 => Ok(Self::OutOfOrderSequenceNumber),
-            46 => Ok(Self::DuplicateSequenceNumber),
-            47 => Ok(Self::InvalidProducerEpoch),
-            48 => Ok(Self::InvalidTxnState),
-            49 => Ok(Self::InvalidProducerIdMapping),
-            50 => Ok(Self::InvalidTransactionTimeout),
-            51 => Ok(Self::ConcurrentTransactions),
-            52 => Ok(Self::TransactionCoordinatorFenced),
-            53 => Ok(Self::TransactionalIdAuthorizationFailed),
-            54 => Ok(Self::SecurityDisabled),
-            55 => Ok(Self::OperationNotAttempted),
-            56 => Ok(Self::KafkaStorageError),
-            57 => Ok(Self::LogDirNotFound),
-            58 => Ok(Self::SaslAuthenticationFailed),
-            59 => Ok(Self::UnknownProducerId),
-            60 => Ok(Self::ReassignmentInProgress),
-            61 => Ok(Self::DelegationTokenAuthDisabled),
-            62 => Ok(Self::DelegationTokenNotFound),
-            63 => Ok(Self::DelegationTokenOwnerMismatch),
-            64 => Ok(Self::DelegationTokenRequestNotAllowed),
-            65 => Ok(Self::DelegationTokenAuthorizationFailed),
-            66 => Ok(Self::DelegationTokenExpired),
-            67 => Ok(Self::InvalidPrincipalType),
-            68 => Ok(Self::NonEmptyGroup),
-            69 => Ok(Self::GroupIdNotFound),
-            70 => Ok(Self::FetchSessionIdNotFound),
-            71 => Ok(Self::InvalidFetchSessionEpoch),
-            72 => Ok(Self::ListenerNotFound),
-            73 => Ok(Self::TopicDeletionDisabled),
-            74 => Ok(Self::FencedLeaderEpoch),
-            75 => Ok(Self::UnknownLeaderEpoch),
-            76 => Ok(Self::UnsupportedCompressionType),
-            77 => Ok(Self::StaleBrokerEpoch),
-            78 => Ok(Self::OffsetNotAvailable),
-            79 => Ok(Self::MemberIdRequired),
-            80 => Ok(Self::PreferredLeaderNotAvailable),
-            81 => Ok(Self::GroupMaxSizeReached),
-            82 => Ok(Self::FencedInstanceId),
-            83 => Ok(Self::EligibleLeadersNotAvailable),
-            84 => Ok(Self::ElectionNotNeeded),
-            85 => Ok(Self::NoReassignmentInProgress),
-            86 => Ok(Self::GroupSubscribedToTopic),
-            87 => Ok(Self::InvalidRecord),
-            88 => Ok(Self::UnstableOffsetCommit),
-            89 => Ok(Self::ThrottlingQuotaExceeded),
-            90 => Ok(Self::ProducerFenced),
-            91 => Ok(Self::ResourceNotFound),
-            92 => Ok(Self::DuplicateResource),
-            93 => Ok(Self::UnacceptableCredential),
-            94 => Ok(Self::InconsistentVoterSet),
-            95 => Ok(Self::InvalidUpdateVersion),
-            96 => Ok(Self::FeatureUpdateFailed),
-            97 => Ok(Self::PrincipalDeserializationFailure),
-            98 => Ok(Self::SnapshotNotFound),
-            99 => Ok(Self::PositionOutOfRange),
-            100 => Ok(Self::UnknownTopicId),
-            101 => Ok(Self::DuplicateBrokerRegistration),
-            102 => Ok(Self::BrokerIdNotRegistered),
-            103 => Ok(Self::InconsistentTopicId),
-            104 => Ok(Self::InconsistentClusterId),
-            105 => Ok(Self::TransactionalIdNotFound),
-            106 => Ok(Self::FetchSessionTopicIdError),
-            107 => Ok(Self::IneligibleReplica),
-            108 => Ok(Self::NewLeaderElected),
-            109 => Ok(Self::OffsetMovedToTieredStorage),
-            110 => Ok(Self::FencedMemberEpoch),
-            111 => Ok(Self::UnreleasedInstanceId),
-            112 => Ok(Self::UnsupportedAssignor),
-            113 => Ok(Self::StaleMemberEpoch),
-            114 => Ok(Self::MismatchedEndpointType),
-            115 => Ok(Self::UnsupportedEndpointType),
-            116 => Ok(Self::UnknownControllerId),
-            117 => Ok(Self::UnknownSubscriptionId),
-            118 => Ok(Self::TelemetryTooLarge),
-            119 => Ok(Self::InvalidRegistration),
-            otherwise => Err(Error::UnknownApiErrorCode(otherwise)),
+        impl ErrorCode {
+            #[must_use]
+            pub fn code(&self) -> i16 {
+                match self {
+                    $(Self::$variant => $code,)+
+                }
+            }
+
+            /// Whether a request failing with this error may succeed if retried.
+            #[must_use]
+            pub fn is_retriable(&self) -> bool {
+                match self {
+                    $(Self::$variant => $retriable,)+
+                }
+            }
+
+            #[must_use]
+            pub fn message(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $message,)+
+                }
+            }
         }
-    }
+
+        impl TryFrom<i16> for ErrorCode {
+            type Error = Error;
+
+            fn try_from(value: i16) -> Result<Self, Self::Error> {
+                match value {
+                    $($code => Ok(Self::$variant),)+
+                    otherwise => Err(Error::UnknownApiErrorCode(otherwise)),
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    (UnknownServerError, -1, false, "The server experienced an unexpected error when processing the request."),
+    (None, 0, false, "No error."),
+    (OffsetOutOfRange, 1, false, "The requested offset is not within the range of offsets maintained by the server."),
+    (CorruptMessage, 2, true, "This message has failed its CRC checksum, exceeds the valid size, has a null key for a compacted topic, or is otherwise corrupt."),
+    (UnknownTopicOrPartition, 3, true, "This server does not host this topic-partition."),
+    (InvalidFetchSize, 4, false, "The requested fetch size is invalid."),
+    (LeaderNotAvailable, 5, true, "There is no leader for this topic-partition as we are in the middle of a leadership election."),
+    (NotLeaderOrFollower, 6, true, "For requests intended only for the leader, this error indicates that the broker is not the current leader. For requests intended for any replica, this error indicates that the broker is not a replica of the topic partition."),
+    (RequestTimedOut, 7, true, "The request timed out."),
+    (BrokerNotAvailable, 8, false, "The broker is not available."),
+    (ReplicaNotAvailable, 9, true, "The replica is not available for the requested topic-partition. Produce/Fetch requests and other requests intended only for the leader or follower return NOT_LEADER_OR_FOLLOWER if the broker is not a replica of the topic-partition."),
+    (MessageTooLarge, 10, false, "The request included a message larger than the max message size the server will accept."),
+    (StaleControllerEpoch, 11, false, "The controller moved to another broker."),
+    (OffsetMetadataTooLarge, 12, false, "The metadata field of the offset request was too large."),
+    (NetworkException, 13, true, "The server disconnected before a response was received."),
+    (CoordinatorLoadInProgress, 14, true, "The coordinator is loading and hence can't process requests."),
+    (CoordinatorNotAvailable, 15, true, "The coordinator is not available."),
+    (NotCoordinator, 16, true, "This is not the correct coordinator."),
+    (InvalidTopicException, 17, false, "The request attempted to perform an operation on an invalid topic."),
+    (RecordListTooLarge, 18, false, "The request included message batch larger than the configured segment size on the server."),
+    (NotEnoughReplicas, 19, true, "Messages are rejected since there are fewer in-sync replicas than required."),
+    (NotEnoughReplicasAfterAppend, 20, true, "Messages are written to the log, but to fewer in-sync replicas than required."),
+    (InvalidRequiredAcks, 21, false, "Produce request specified an invalid value for required acks."),
+    (IllegalGeneration, 22, false, "Specified group generation id is not valid."),
+    (InconsistentGroupProtocol, 23, false, "The group member's supported protocols are incompatible with those of existing members or first group member tried to join with empty protocol type or empty protocol list."),
+    (InvalidGroupId, 24, false, "The configured groupId is invalid."),
+    (UnknownMemberId, 25, false, "The coordinator is not aware of this member."),
+    (InvalidSessionTimeout, 26, false, "The session timeout is not within the range allowed by the broker (as configured by group.min.session.timeout.ms and group.max.session.timeout.ms)."),
+    (RebalanceInProgress, 27, false, "The group is rebalancing, so a rejoin is needed."),
+    (InvalidCommitOffsetSize, 28, false, "The committing offset data size is not valid."),
+    (TopicAuthorizationFailed, 29, false, "Topic authorization failed."),
+    (GroupAuthorizationFailed, 30, false, "Group authorization failed."),
+    (ClusterAuthorizationFailed, 31, false, "Cluster authorization failed."),
+    (InvalidTimestamp, 32, false, "The timestamp of the message is out of acceptable range."),
+    (UnsupportedSaslMechanism, 33, false, "The broker does not support the requested SASL mechanism."),
+    (IllegalSaslState, 34, false, "Request is not valid given the current SASL state."),
+    (UnsupportedVersion, 35, false, "The version of API is not supported."),
+    (TopicAlreadyExists, 36, false, "Topic with this name already exists."),
+    (InvalidPartitions, 37, false, "Number of partitions is below 1."),
+    (InvalidReplicationFactor, 38, false, "Replication factor is below 1 or larger than the number of available brokers."),
+    (InvalidReplicaAssignment, 39, false, "Replica assignment is invalid."),
+    (InvalidConfig, 40, false, "Configuration is invalid."),
+    (NotController, 41, true, "This is not the correct controller for this cluster."),
+    (InvalidRequest, 42, false, "This most likely occurs because of a request being malformed by the client library or the message was sent to an incompatible broker. See the broker logs for more details."),
+    (UnsupportedForMessageFormat, 43, false, "The message format version on the broker does not support the request."),
+    (PolicyViolation, 44, false, "Request parameters do not satisfy the configured policy."),
+    (OutOfOrderSequenceNumber, 45, false, "The broker received an out of order sequence number."),
+    (DuplicateSequenceNumber, 46, false, "The broker received a duplicate sequence number."),
+    (InvalidProducerEpoch, 47, false, "Producer attempted to produce with an old epoch."),
+    (InvalidTxnState, 48, false, "The producer attempted a transactional operation in an invalid state."),
+    (InvalidProducerIdMapping, 49, false, "The producer attempted to use a producer id which is not currently assigned to its transactional id."),
+    (InvalidTransactionTimeout, 50, false, "The transaction timeout is larger than the maximum value allowed by the broker (as configured by transaction.max.timeout.ms)."),
+    (ConcurrentTransactions, 51, false, "The producer attempted to update a transaction while another concurrent operation on the same transaction was ongoing."),
+    (TransactionCoordinatorFenced, 52, false, "Indicates that the transaction coordinator sending a WriteTxnMarker is no longer the current coordinator for a given producer."),
+    (TransactionalIdAuthorizationFailed, 53, false, "Transactional Id authorization failed."),
+    (SecurityDisabled, 54, false, "Security features are disabled."),
+    (OperationNotAttempted, 55, false, "The broker did not attempt to execute this operation. This may happen for batched RPCs where some operations in the batch failed, causing the broker to respond without trying the rest."),
+    (KafkaStorageError, 56, true, "Disk error when trying to access log file on the disk."),
+    (LogDirNotFound, 57, false, "The user-specified log directory is not found in the broker config."),
+    (SaslAuthenticationFailed, 58, false, "SASL Authentication failed."),
+    (UnknownProducerId, 59, false, "This exception is raised by the broker if it could not locate the producer metadata associated with the producerId in question. This could happen if, for instance, the producer's records were deleted because their retention time had elapsed. Once the last records of the producerId are removed, the producer's metadata is removed from the broker, and future appends by the producer will return this exception."),
+    (ReassignmentInProgress, 60, false, "A partition reassignment is in progress."),
+    (DelegationTokenAuthDisabled, 61, false, "Delegation Token feature is not enabled."),
+    (DelegationTokenNotFound, 62, false, "Delegation Token is not found on server."),
+    (DelegationTokenOwnerMismatch, 63, false, "Specified Principal is not valid Owner/Renewer."),
+    (DelegationTokenRequestNotAllowed, 64, false, "Delegation Token requests are not allowed on PLAINTEXT/1-way SSL channels and on delegation token authenticated channels."),
+    (DelegationTokenAuthorizationFailed, 65, false, "Delegation Token authorization failed."),
+    (DelegationTokenExpired, 66, false, "Delegation Token is expired."),
+    (InvalidPrincipalType, 67, false, "Supplied principalType is not supported."),
+    (NonEmptyGroup, 68, false, "The group is not empty."),
+    (GroupIdNotFound, 69, false, "The group id does not exist."),
+    (FetchSessionIdNotFound, 70, true, "The fetch session ID was not found."),
+    (InvalidFetchSessionEpoch, 71, true, "The fetch session epoch is invalid."),
+    (ListenerNotFound, 72, true, "There is no listener on the leader broker that matches the listener on which metadata request was processed."),
+    (TopicDeletionDisabled, 73, false, "Topic deletion is disabled."),
+    (FencedLeaderEpoch, 74, true, "The leader epoch in the request is older than the epoch on the broker."),
+    (UnknownLeaderEpoch, 75, true, "The leader epoch in the request is newer than the epoch on the broker."),
+    (UnsupportedCompressionType, 76, false, "The requesting client does not support the compression type of given partition."),
+    (StaleBrokerEpoch, 77, false, "Broker epoch has changed."),
+    (OffsetNotAvailable, 78, true, "The leader high watermark has not caught up from a recent leader election so the offsets cannot be guaranteed to be monotonically increasing."),
+    (MemberIdRequired, 79, false, "The group member needs to have a valid member id before actually entering a consumer group."),
+    (PreferredLeaderNotAvailable, 80, true, "The preferred leader was not available."),
+    (GroupMaxSizeReached, 81, false, "The consumer group has reached its max size."),
+    (FencedInstanceId, 82, false, "The broker rejected this static consumer since another consumer with the same group.instance.id has registered with a different member.id."),
+    (EligibleLeadersNotAvailable, 83, true, "Eligible topic partition leaders are not available."),
+    (ElectionNotNeeded, 84, false, "Leader election not needed for topic partition."),
+    (NoReassignmentInProgress, 85, false, "No partition reassignment is in progress."),
+    (GroupSubscribedToTopic, 86, false, "Deleting offsets of a topic is forbidden while the consumer group is actively subscribed to it."),
+    (InvalidRecord, 87, false, "This record has failed the validation on broker and hence will be rejected."),
+    (UnstableOffsetCommit, 88, true, "There are unstable offsets that need to be cleared."),
+    (ThrottlingQuotaExceeded, 89, true, "The throttling quota has been exceeded."),
+    (ProducerFenced, 90, false, "There is a newer producer with the same transactionalId which fences the current one."),
+    (ResourceNotFound, 91, false, "A request illegally referred to a resource that does not exist."),
+    (DuplicateResource, 92, false, "A request illegally referred to the same resource twice."),
+    (UnacceptableCredential, 93, false, "Requested credential would not meet criteria for acceptability."),
+    (InconsistentVoterSet, 94, false, "Indicates that the either the sender or recipient of a voter-only request is not one of the expected voters"),
+    (InvalidUpdateVersion, 95, false, "The given update version was invalid."),
+    (FeatureUpdateFailed, 96, false, "Unable to update finalized features due to an unexpected server error."),
+    (PrincipalDeserializationFailure, 97, false, "Request principal deserialization failed during forwarding. This indicates an internal error on the broker cluster security setup."),
+    (SnapshotNotFound, 98, false, "Requested snapshot was not found"),
+    (PositionOutOfRange, 99, false, "Requested position is not greater than or equal to zero, and less than the size of the snapshot."),
+    (UnknownTopicId, 100, true, "This server does not host this topic ID."),
+    (DuplicateBrokerRegistration, 101, false, "This broker ID is already in use."),
+    (BrokerIdNotRegistered, 102, false, "The given broker ID was not registered."),
+    (InconsistentTopicId, 103, true, "The log's topic ID did not match the topic ID in the request"),
+    (InconsistentClusterId, 104, false, "The clusterId in the request does not match that found on the server"),
+    (TransactionalIdNotFound, 105, false, "The transactionalId could not be found"),
+    (FetchSessionTopicIdError, 106, true, "The fetch session encountered inconsistent topic ID usage"),
+    (IneligibleReplica, 107, false, "The new ISR contains at least one ineligible replica."),
+    (NewLeaderElected, 108, false, "The AlterPartition request successfully updated the partition state but the leader has changed."),
+    (OffsetMovedToTieredStorage, 109, false, "The requested offset is moved to tiered storage."),
+    (FencedMemberEpoch, 110, false, "The member epoch is fenced by the group coordinator. The member must abandon all its partitions and rejoin."),
+    (UnreleasedInstanceId, 111, false, "The instance ID is still used by another member in the consumer group. That member must leave first."),
+    (UnsupportedAssignor, 112, false, "The assignor or its version range is not supported by the consumer group."),
+    (StaleMemberEpoch, 113, false, "The member epoch is stale. The member must retry after receiving its updated member epoch via the ConsumerGroupHeartbeat API."),
+    (MismatchedEndpointType, 114, false, "The request was sent to an endpoint of the wrong type."),
+    (UnsupportedEndpointType, 115, false, "This endpoint type is not supported yet."),
+    (UnknownControllerId, 116, false, "This controller ID is not known."),
+    (UnknownSubscriptionId, 117, false, "Client sent a push telemetry request with an invalid or outdated subscription ID."),
+    (TelemetryTooLarge, 118, false, "Client sent a push telemetry request larger than the maximum size the broker will accept."),
+    (InvalidRegistration, 119, false, "The controller has considered the broker registration to be invalid."),
+    (TransactionAbortable, 120, false, "The server encountered an error with the transaction. The client can abort the transaction to continue using this transactional ID."),
+    (InvalidRecordState, 121, false, "The record state is invalid. The acknowledgement of delivery could not be completed."),
+    (ShareSessionNotFound, 122, true, "The share session was not found."),
+    (InvalidShareSessionEpoch, 123, true, "The share session epoch is invalid."),
+    (FencedStateEpoch, 124, false, "The share coordinator rejected the request because the share-group state epoch did not match."),
+    (InvalidVoterKey, 125, false, "The voter key doesn't match the receiving replica's key."),
+    (DuplicateVoter, 126, false, "The voter is already part of the set of voters."),
+    (VoterNotFound, 127, false, "The voter is not part of the set of voters."),
+    (InvalidRegularExpression, 128, false, "The regular expression is not valid."),
+    (RebootstrapRequired, 129, false, "Client metadata is stale. The client should rebootstrap to obtain new metadata."),
 }
 
 impl From<ErrorCode> for i16 {
-    #[allow(clippy::too_many_lines)]
     fn from(value: ErrorCode) -> Self {
-        match value {
-            ErrorCode::UnknownServerError => -1,
-            ErrorCode::None => 0,
-            ErrorCode::OffsetOutOfRange => 1,
-            ErrorCode::CorruptMessage => 2,
-            ErrorCode::UnknownTopicOrPartition => 3,
-            ErrorCode::InvalidFetchSize => 4,
-            ErrorCode::LeaderNotAvailable => 5,
-            ErrorCode::NotLeaderOrFollower => 6,
-            ErrorCode::RequestTimedOut => 7,
-            ErrorCode::BrokerNotAvailable => 8,
-            ErrorCode::ReplicaNotAvailable => 9,
-            ErrorCode::MessageTooLarge => 10,
-            ErrorCode::StaleControllerEpoch => 11,
-            ErrorCode::OffsetMetadataTooLarge => 12,
-            ErrorCode::NetworkException => 13,
-            ErrorCode::CoordinatorLoadInProgress => 14,
-            ErrorCode::CoordinatorNotAvailable => 15,
-            ErrorCode::NotCoordinator => 16,
-            ErrorCode::InvalidTopicException => 17,
-            ErrorCode::RecordListTooLarge => 18,
-            ErrorCode::NotEnoughReplicas => 19,
-            ErrorCode::NotEnoughReplicasAfterAppend => 20,
-            ErrorCode::InvalidRequiredAcks => 21,
-            ErrorCode::IllegalGeneration => 22,
-            ErrorCode::InconsistentGroupProtocol => 23,
-            ErrorCode::InvalidGroupId => 24,
-            ErrorCode::UnknownMemberId => 25,
-            ErrorCode::InvalidSessionTimeout => 26,
-            ErrorCode::RebalanceInProgress => 27,
-            ErrorCode::InvalidCommitOffsetSize => 28,
-            ErrorCode::TopicAuthorizationFailed => 29,
-            ErrorCode::GroupAuthorizationFailed => 30,
-            ErrorCode::ClusterAuthorizationFailed => 31,
-            ErrorCode::InvalidTimestamp => 32,
-            ErrorCode::UnsupportedSaslMechanism => 33,
-            ErrorCode::IllegalSaslState => 34,
-            ErrorCode::UnsupportedVersion => 35,
-            ErrorCode::TopicAlreadyExists => 36,
-            ErrorCode::InvalidPartitions => 37,
-            ErrorCode::InvalidReplicationFactor => 38,
-            ErrorCode::InvalidReplicaAssignment => 39,
-            ErrorCode::InvalidConfig => 40,
-            ErrorCode::NotController => 41,
-            ErrorCode::InvalidRequest => 42,
-            ErrorCode::UnsupportedForMessageFormat => 43,
-            ErrorCode::PolicyViolation => 44,
-            ErrorCode::OutOfOrderSequenceNumber => 45,
-            ErrorCode::DuplicateSequenceNumber => 46,
-            ErrorCode::InvalidProducerEpoch => 47,
-            ErrorCode::InvalidTxnState => 48,
-            ErrorCode::InvalidProducerIdMapping => 49,
-            ErrorCode::InvalidTransactionTimeout => 50,
-            ErrorCode::ConcurrentTransactions => 51,
-            ErrorCode::TransactionCoordinatorFenced => 52,
-            ErrorCode::TransactionalIdAuthorizationFailed => 53,
-            ErrorCode::SecurityDisabled => 54,
-            ErrorCode::OperationNotAttempted => 55,
-            ErrorCode::KafkaStorageError => 56,
-            ErrorCode::LogDirNotFound => 57,
-            ErrorCode::SaslAuthenticationFailed => 58,
-            ErrorCode::UnknownProducerId => 59,
-            ErrorCode::ReassignmentInProgress => 60,
-            ErrorCode::DelegationTokenAuthDisabled => 61,
-            ErrorCode::DelegationTokenNotFound => 62,
-            ErrorCode::DelegationTokenOwnerMismatch => 63,
-            ErrorCode::DelegationTokenRequestNotAllowed => 64,
-            ErrorCode::DelegationTokenAuthorizationFailed => 65,
-            ErrorCode::DelegationTokenExpired => 66,
-            ErrorCode::InvalidPrincipalType => 67,
-            ErrorCode::NonEmptyGroup => 68,
-            ErrorCode::GroupIdNotFound => 69,
-            ErrorCode::FetchSessionIdNotFound => 70,
-            ErrorCode::InvalidFetchSessionEpoch => 71,
-            ErrorCode::ListenerNotFound => 72,
-            ErrorCode::TopicDeletionDisabled => 73,
-            ErrorCode::FencedLeaderEpoch => 74,
-            ErrorCode::UnknownLeaderEpoch => 75,
-            ErrorCode::UnsupportedCompressionType => 76,
-            ErrorCode::StaleBrokerEpoch => 77,
-            ErrorCode::OffsetNotAvailable => 78,
-            ErrorCode::MemberIdRequired => 79,
-            ErrorCode::PreferredLeaderNotAvailable => 80,
-            ErrorCode::GroupMaxSizeReached => 81,
-            ErrorCode::FencedInstanceId => 82,
-            ErrorCode::EligibleLeadersNotAvailable => 83,
-            ErrorCode::ElectionNotNeeded => 84,
-            ErrorCode::NoReassignmentInProgress => 85,
-            ErrorCode::GroupSubscribedToTopic => 86,
-            ErrorCode::InvalidRecord => 87,
-            ErrorCode::UnstableOffsetCommit => 88,
-            ErrorCode::ThrottlingQuotaExceeded => 89,
-            ErrorCode::ProducerFenced => 90,
-            ErrorCode::ResourceNotFound => 91,
-            ErrorCode::DuplicateResource => 92,
-            ErrorCode::UnacceptableCredential => 93,
-            ErrorCode::InconsistentVoterSet => 94,
-            ErrorCode::InvalidUpdateVersion => 95,
-            ErrorCode::FeatureUpdateFailed => 96,
-            ErrorCode::PrincipalDeserializationFailure => 97,
-            ErrorCode::SnapshotNotFound => 98,
-            ErrorCode::PositionOutOfRange => 99,
-            ErrorCode::UnknownTopicId => 100,
-            ErrorCode::DuplicateBrokerRegistration => 101,
-            ErrorCode::BrokerIdNotRegistered => 102,
-            ErrorCode::InconsistentTopicId => 103,
-            ErrorCode::InconsistentClusterId => 104,
-            ErrorCode::TransactionalIdNotFound => 105,
-            ErrorCode::FetchSessionTopicIdError => 106,
-            ErrorCode::IneligibleReplica => 107,
-            ErrorCode::NewLeaderElected => 108,
-            ErrorCode::OffsetMovedToTieredStorage => 109,
-            ErrorCode::FencedMemberEpoch => 110,
-            ErrorCode::UnreleasedInstanceId => 111,
-            ErrorCode::UnsupportedAssignor => 112,
-            ErrorCode::StaleMemberEpoch => 113,
-            ErrorCode::MismatchedEndpointType => 114,
-            ErrorCode::UnsupportedEndpointType => 115,
-            ErrorCode::UnknownControllerId => 116,
-            ErrorCode::UnknownSubscriptionId => 117,
-            ErrorCode::TelemetryTooLarge => 118,
-            ErrorCode::InvalidRegistration => 119,
-        }
+        value.code()
     }
 }
 
 impl Display for ErrorCode {
-    #[allow(clippy::too_many_lines)]
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            ErrorCode::UnknownServerError => f.write_str(
-                "The server experienced an unexpected error when processing the request.",
-            ),
-            ErrorCode::None => f.write_str("No error."),
-            ErrorCode::OffsetOutOfRange => f.write_str(
-                "The requested offset is not within the range of offsets maintained by the server.",
-            ),
-            ErrorCode::CorruptMessage => f.write_str(
-                "This message has failed its CRC checksum, exceeds the valid size, has a null key \
-                 for a compacted topic, or is otherwise corrupt.",
-            ),
-            ErrorCode::UnknownTopicOrPartition => {
-                f.write_str("This server does not host this topic-partition.")
-            }
-            ErrorCode::InvalidFetchSize => f.write_str("The requested fetch size is invalid."),
-            ErrorCode::LeaderNotAvailable => f.write_str(
-                "There is no leader for this topic-partition as we are in the middle of a \
-                 leadership election.",
-            ),
-            ErrorCode::NotLeaderOrFollower => f.write_str(
-                "For requests intended only for the leader, this error indicates that the broker \
-                 is not the current leader. For requests intended for any replica, this error \
-                 indicates that the broker is not a replica of the topic partition.",
-            ),
-            ErrorCode::RequestTimedOut => f.write_str("The request timed out."),
-            ErrorCode::BrokerNotAvailable => f.write_str("The broker is not available."),
-            ErrorCode::ReplicaNotAvailable => f.write_str(
-                "The replica is not available for the requested topic-partition. Produce/Fetch \
-                 requests and other requests intended only for the leader or follower return \
-                 NOT_LEADER_OR_FOLLOWER if the broker is not a replica of the topic-partition.",
-            ),
-            ErrorCode::MessageTooLarge => f.write_str(
-                "The request included a message larger than the max message size the server will \
-                 accept.",
-            ),
-            ErrorCode::StaleControllerEpoch => {
-                f.write_str("The controller moved to another broker.")
-            }
-            ErrorCode::OffsetMetadataTooLarge => {
-                f.write_str("The metadata field of the offset request was too large.")
-            }
-            ErrorCode::NetworkException => {
-                f.write_str("The server disconnected before a response was received.")
-            }
-            ErrorCode::CoordinatorLoadInProgress => {
-                f.write_str("The coordinator is loading and hence can't process requests.")
-            }
-            ErrorCode::CoordinatorNotAvailable => f.write_str("The coordinator is not available."),
-            ErrorCode::NotCoordinator => f.write_str("This is not the correct coordinator."),
-            ErrorCode::InvalidTopicException => {
-                f.write_str("The request attempted to perform an operation on an invalid topic.")
-            }
-            ErrorCode::RecordListTooLarge => f.write_str(
-                "The request included message batch larger than the configured segment size on \
-                 the server.",
-            ),
-            ErrorCode::NotEnoughReplicas => f.write_str(
-                "Messages are rejected since there are fewer in-sync replicas than required.",
-            ),
-            ErrorCode::NotEnoughReplicasAfterAppend => f.write_str(
-                "Messages are written to the log, but to fewer in-sync replicas than required.",
-            ),
-            ErrorCode::InvalidRequiredAcks => {
-                f.write_str("Produce request specified an invalid value for required acks.")
-            }
-            ErrorCode::IllegalGeneration => {
-                f.write_str("Specified group generation id is not valid.")
-            }
-            ErrorCode::InconsistentGroupProtocol => f.write_str(
-                "The group member's supported protocols are incompatible with those of existing \
-                 members or first group member tried to join with empty protocol type or empty \
-                 protocol list.",
-            ),
-            ErrorCode::InvalidGroupId => f.write_str("The configured groupId is invalid."),
-            ErrorCode::UnknownMemberId => {
-                f.write_str("The coordinator is not aware of this member.")
-            }
-            ErrorCode::InvalidSessionTimeout => f.write_str(
-                "The session timeout is not within the range allowed by the broker (as configured \
-                 by group.min.session.timeout.ms and group.max.session.timeout.ms).",
-            ),
-            ErrorCode::RebalanceInProgress => {
-                f.write_str("The group is rebalancing, so a rejoin is needed.")
-            }
-            ErrorCode::InvalidCommitOffsetSize => {
-                f.write_str("The committing offset data size is not valid.")
-            }
-            ErrorCode::TopicAuthorizationFailed => f.write_str("Topic authorization failed."),
-            ErrorCode::GroupAuthorizationFailed => f.write_str("Group authorization failed."),
-            ErrorCode::ClusterAuthorizationFailed => f.write_str("Cluster authorization failed."),
-            ErrorCode::InvalidTimestamp => {
-                f.write_str("The timestamp of the message is out of acceptable range.")
-            }
-            ErrorCode::UnsupportedSaslMechanism => {
-                f.write_str("The broker does not support the requested SASL mechanism.")
-            }
-            ErrorCode::IllegalSaslState => {
-                f.write_str("Request is not valid given the current SASL state.")
-            }
-            ErrorCode::UnsupportedVersion => f.write_str("The version of API is not supported."),
-            ErrorCode::TopicAlreadyExists => f.write_str("Topic with this name already exists."),
-            ErrorCode::InvalidPartitions => f.write_str("Number of partitions is below 1."),
-            ErrorCode::InvalidReplicationFactor => f.write_str(
-                "Replication factor is below 1 or larger than the number of available brokers.",
-            ),
-            ErrorCode::InvalidReplicaAssignment => f.write_str("Replica assignment is invalid."),
-            ErrorCode::InvalidConfig => f.write_str("Configuration is invalid."),
-            ErrorCode::NotController => {
-                f.write_str("This is not the correct controller for this cluster.")
-            }
-            ErrorCode::InvalidRequest => f.write_str(
-                "This most likely occurs because of a request being malformed by the client \
-                 library or the message was sent to an incompatible broker. See the broker logs \
-                 for more details.",
-            ),
-            ErrorCode::UnsupportedForMessageFormat => f.write_str(
-                "The message format version on the broker does not support the request.",
-            ),
-            ErrorCode::PolicyViolation => {
-                f.write_str("Request parameters do not satisfy the configured policy.")
-            }
-            ErrorCode::OutOfOrderSequenceNumber => {
-                f.write_str("The broker received an out of order sequence number.")
-            }
-            ErrorCode::DuplicateSequenceNumber => {
-                f.write_str("The broker received a duplicate sequence number.")
-            }
-            ErrorCode::InvalidProducerEpoch => {
-                f.write_str("Producer attempted to produce with an old epoch.")
-            }
-            ErrorCode::InvalidTxnState => {
-                f.write_str("The producer attempted a transactional operation in an invalid state.")
-            }
-            ErrorCode::InvalidProducerIdMapping => f.write_str(
-                "The producer attempted to use a producer id which is not currently assigned to \
-                 its transactional id.",
-            ),
-            ErrorCode::InvalidTransactionTimeout => f.write_str(
-                "The transaction timeout is larger than the maximum value allowed by the broker \
-                 (as configured by transaction.max.timeout.ms).",
-            ),
-            ErrorCode::ConcurrentTransactions => f.write_str(
-                "The producer attempted to update a transaction while another concurrent \
-                 operation on the same transaction was ongoing.",
-            ),
-            ErrorCode::TransactionCoordinatorFenced => f.write_str(
-                "Indicates that the transaction coordinator sending a WriteTxnMarker is no longer \
-                 the current coordinator for a given producer.",
-            ),
-            ErrorCode::TransactionalIdAuthorizationFailed => {
-                f.write_str("Transactional Id authorization failed.")
-            }
-            ErrorCode::SecurityDisabled => f.write_str("Security features are disabled."),
-            ErrorCode::OperationNotAttempted => f.write_str(
-                "The broker did not attempt to execute this operation. This may happen for \
-                 batched RPCs where some operations in the batch failed, causing the broker to \
-                 respond without trying the rest.",
-            ),
-            ErrorCode::KafkaStorageError => {
-                f.write_str("Disk error when trying to access log file on the disk.")
-            }
-            ErrorCode::LogDirNotFound => {
-                f.write_str("The user-specified log directory is not found in the broker config.")
-            }
-            ErrorCode::SaslAuthenticationFailed => f.write_str("SASL Authentication failed."),
-            ErrorCode::UnknownProducerId => f.write_str(
-                "This exception is raised by the broker if it could not locate the producer \
-                 metadata associated with the producerId in question. This could happen if, for \
-                 instance, the producer's records were deleted because their retention time had \
-                 elapsed. Once the last records of the producerId are removed, the producer's \
-                 metadata is removed from the broker, and future appends by the producer will \
-                 return this exception.",
-            ),
-            ErrorCode::ReassignmentInProgress => {
-                f.write_str("A partition reassignment is in progress.")
-            }
-            ErrorCode::DelegationTokenAuthDisabled => {
-                f.write_str("Delegation Token feature is not enabled.")
-            }
-            ErrorCode::DelegationTokenNotFound => {
-                f.write_str("Delegation Token is not found on server.")
-            }
-            ErrorCode::DelegationTokenOwnerMismatch => {
-                f.write_str("Specified Principal is not valid Owner/Renewer.")
-            }
-            ErrorCode::DelegationTokenRequestNotAllowed => f.write_str(
-                "Delegation Token requests are not allowed on PLAINTEXT/1-way SSL channels and on \
-                 delegation token authenticated channels.",
-            ),
-            ErrorCode::DelegationTokenAuthorizationFailed => {
-                f.write_str("Delegation Token authorization failed.")
-            }
-            ErrorCode::DelegationTokenExpired => f.write_str("Delegation Token is expired."),
-            ErrorCode::InvalidPrincipalType => {
-                f.write_str("Supplied principalType is not supported.")
-            }
-            ErrorCode::NonEmptyGroup => f.write_str("The group is not empty."),
-            ErrorCode::GroupIdNotFound => f.write_str("The group id does not exist."),
-            ErrorCode::FetchSessionIdNotFound => f.write_str("The fetch session ID was not found."),
-            ErrorCode::InvalidFetchSessionEpoch => {
-                f.write_str("The fetch session epoch is invalid.")
-            }
-            ErrorCode::ListenerNotFound => f.write_str(
-                "There is no listener on the leader broker that matches the listener on which \
-                 metadata request was processed.",
-            ),
-            ErrorCode::TopicDeletionDisabled => f.write_str("Topic deletion is disabled."),
-            ErrorCode::FencedLeaderEpoch => f.write_str(
-                "The leader epoch in the request is older than the epoch on the broker.",
-            ),
-            ErrorCode::UnknownLeaderEpoch => f.write_str(
-                "The leader epoch in the request is newer than the epoch on the broker.",
-            ),
-            ErrorCode::UnsupportedCompressionType => f.write_str(
-                "The requesting client does not support the compression type of given partition.",
-            ),
-            ErrorCode::StaleBrokerEpoch => f.write_str("Broker epoch has changed."),
-            ErrorCode::OffsetNotAvailable => f.write_str(
-                "The leader high watermark has not caught up from a recent leader election so the \
-                 offsets cannot be guaranteed to be monotonically increasing.",
-            ),
-            ErrorCode::MemberIdRequired => f.write_str(
-                "The group member needs to have a valid member id before actually entering a \
-                 consumer group.",
-            ),
-            ErrorCode::PreferredLeaderNotAvailable => {
-                f.write_str("The preferred leader was not available.")
-            }
-            ErrorCode::GroupMaxSizeReached => {
-                f.write_str("The consumer group has reached its max size.")
-            }
-            ErrorCode::FencedInstanceId => f.write_str(
-                "The broker rejected this static consumer since another consumer with the same \
-                 group.instance.id has registered with a different member.id.",
-            ),
-            ErrorCode::EligibleLeadersNotAvailable => {
-                f.write_str("Eligible topic partition leaders are not available.")
-            }
-            ErrorCode::ElectionNotNeeded => {
-                f.write_str("Leader election not needed for topic partition.")
-            }
-            ErrorCode::NoReassignmentInProgress => {
-                f.write_str("No partition reassignment is in progress.")
-            }
-            ErrorCode::GroupSubscribedToTopic => f.write_str(
-                "Deleting offsets of a topic is forbidden while the consumer group is actively \
-                 subscribed to it.",
-            ),
-            ErrorCode::InvalidRecord => f.write_str(
-                "This record has failed the validation on broker and hence will be rejected.",
-            ),
-            ErrorCode::UnstableOffsetCommit => {
-                f.write_str("There are unstable offsets that need to be cleared.")
-            }
-            ErrorCode::ThrottlingQuotaExceeded => {
-                f.write_str("The throttling quota has been exceeded.")
-            }
-            ErrorCode::ProducerFenced => f.write_str(
-                "There is a newer producer with the same transactionalId which fences the current \
-                 one.",
-            ),
-            ErrorCode::ResourceNotFound => {
-                f.write_str("A request illegally referred to a resource that does not exist.")
-            }
-            ErrorCode::DuplicateResource => {
-                f.write_str("A request illegally referred to the same resource twice.")
-            }
-            ErrorCode::UnacceptableCredential => {
-                f.write_str("Requested credential would not meet criteria for acceptability.")
-            }
-            ErrorCode::InconsistentVoterSet => f.write_str(
-                "Indicates that the either the sender or recipient of a voter-only request is not \
-                 one of the expected voters",
-            ),
-            ErrorCode::InvalidUpdateVersion => f.write_str("The given update version was invalid."),
-            ErrorCode::FeatureUpdateFailed => f.write_str(
-                "Unable to update finalized features due to an unexpected server error.",
-            ),
-            ErrorCode::PrincipalDeserializationFailure => f.write_str(
-                "Request principal deserialization failed during forwarding. This indicates an \
-                 internal error on the broker cluster security setup.",
-            ),
-            ErrorCode::SnapshotNotFound => f.write_str("Requested snapshot was not found"),
-            ErrorCode::PositionOutOfRange => f.write_str(
-                "Requested position is not greater than or equal to zero, and less than the size \
-                 of the snapshot.",
-            ),
-            ErrorCode::UnknownTopicId => f.write_str("This server does not host this topic ID."),
-            ErrorCode::DuplicateBrokerRegistration => {
-                f.write_str("This broker ID is already in use.")
-            }
-            ErrorCode::BrokerIdNotRegistered => {
-                f.write_str("The given broker ID was not registered.")
-            }
-            ErrorCode::InconsistentTopicId => {
-                f.write_str("The log's topic ID did not match the topic ID in the request")
-            }
-            ErrorCode::InconsistentClusterId => {
-                f.write_str("The clusterId in the request does not match that found on the server")
-            }
-            ErrorCode::TransactionalIdNotFound => {
-                f.write_str("The transactionalId could not be found")
-            }
-            ErrorCode::FetchSessionTopicIdError => {
-                f.write_str("The fetch session encountered inconsistent topic ID usage")
-            }
-            ErrorCode::IneligibleReplica => {
-                f.write_str("The new ISR contains at least one ineligible replica.")
-            }
-            ErrorCode::NewLeaderElected => f.write_str(
-                "The AlterPartition request successfully updated the partition state but the \
-                 leader has changed.",
-            ),
-            ErrorCode::OffsetMovedToTieredStorage => {
-                f.write_str("The requested offset is moved to tiered storage.")
-            }
-            ErrorCode::FencedMemberEpoch => f.write_str(
-                "The member epoch is fenced by the group coordinator. The member must abandon all \
-                 its partitions and rejoin.",
-            ),
-            ErrorCode::UnreleasedInstanceId => f.write_str(
-                "The instance ID is still used by another member in the consumer group. That \
-                 member must leave first.",
-            ),
-            ErrorCode::UnsupportedAssignor => f.write_str(
-                "The assignor or its version range is not supported by the consumer group.",
-            ),
-            ErrorCode::StaleMemberEpoch => f.write_str(
-                "The member epoch is stale. The member must retry after receiving its updated \
-                 member epoch via the ConsumerGroupHeartbeat API.",
-            ),
-            ErrorCode::MismatchedEndpointType => {
-                f.write_str("The request was sent to an endpoint of the wrong type.")
-            }
-            ErrorCode::UnsupportedEndpointType => {
-                f.write_str("This endpoint type is not supported yet.")
-            }
-            ErrorCode::UnknownControllerId => f.write_str("This controller ID is not known."),
-            ErrorCode::UnknownSubscriptionId => f.write_str(
-                "Client sent a push telemetry request with an invalid or outdated subscription ID.",
-            ),
-            ErrorCode::TelemetryTooLarge => f.write_str(
-                "Client sent a push telemetry request larger than the maximum size the broker \
-                 will accept.",
-            ),
-            ErrorCode::InvalidRegistration => {
-                f.write_str("The controller has considered the broker registration to be invalid.")
-            }
-        }
+        f.write_str(self.message())
     }
 }
 
-#[non_exhaustive]
-#[derive(Clone, Copy, Deserialize, Eq, Hash, Debug, Ord, PartialEq, PartialOrd, Serialize)]
-pub enum ErrorCode {
-    UnknownServerError,
-    None,
-    OffsetOutOfRange,
-    CorruptMessage,
-    UnknownTopicOrPartition,
-    InvalidFetchSize,
-    LeaderNotAvailable,
-    NotLeaderOrFollower,
-    RequestTimedOut,
-    BrokerNotAvailable,
-    ReplicaNotAvailable,
-    MessageTooLarge,
-    StaleControllerEpoch,
-    OffsetMetadataTooLarge,
-    NetworkException,
-    CoordinatorLoadInProgress,
-    CoordinatorNotAvailable,
-    NotCoordinator,
-    InvalidTopicException,
-    RecordListTooLarge,
-    NotEnoughReplicas,
-    NotEnoughReplicasAfterAppend,
-    InvalidRequiredAcks,
-    IllegalGeneration,
-    InconsistentGroupProtocol,
-    InvalidGroupId,
-    UnknownMemberId,
-    InvalidSessionTimeout,
-    RebalanceInProgress,
-    InvalidCommitOffsetSize,
-    TopicAuthorizationFailed,
-    GroupAuthorizationFailed,
-    ClusterAuthorizationFailed,
-    InvalidTimestamp,
-    UnsupportedSaslMechanism,
-    IllegalSaslState,
-    UnsupportedVersion,
-    TopicAlreadyExists,
-    InvalidPartitions,
-    InvalidReplicationFactor,
-    InvalidReplicaAssignment,
-    InvalidConfig,
-    NotController,
-    InvalidRequest,
-    UnsupportedForMessageFormat,
-    PolicyViolation,
-    OutOfOrderSequenceNumber,
-    DuplicateSequenceNumber,
-    InvalidProducerEpoch,
-    InvalidTxnState,
-    InvalidProducerIdMapping,
-    InvalidTransactionTimeout,
-    ConcurrentTransactions,
-    TransactionCoordinatorFenced,
-    TransactionalIdAuthorizationFailed,
-    SecurityDisabled,
-    OperationNotAttempted,
-    KafkaStorageError,
-    LogDirNotFound,
-    SaslAuthenticationFailed,
-    UnknownProducerId,
-    ReassignmentInProgress,
-    DelegationTokenAuthDisabled,
-    DelegationTokenNotFound,
-    DelegationTokenOwnerMismatch,
-    DelegationTokenRequestNotAllowed,
-    DelegationTokenAuthorizationFailed,
-    DelegationTokenExpired,
-    InvalidPrincipalType,
-    NonEmptyGroup,
-    GroupIdNotFound,
-    FetchSessionIdNotFound,
-    InvalidFetchSessionEpoch,
-    ListenerNotFound,
-    TopicDeletionDisabled,
-    FencedLeaderEpoch,
-    UnknownLeaderEpoch,
-    UnsupportedCompressionType,
-    StaleBrokerEpoch,
-    OffsetNotAvailable,
-    MemberIdRequired,
-    PreferredLeaderNotAvailable,
-    GroupMaxSizeReached,
-    FencedInstanceId,
-    EligibleLeadersNotAvailable,
-    ElectionNotNeeded,
-    NoReassignmentInProgress,
-    GroupSubscribedToTopic,
-    InvalidRecord,
-    UnstableOffsetCommit,
-    ThrottlingQuotaExceeded,
-    ProducerFenced,
-    ResourceNotFound,
-    DuplicateResource,
-    UnacceptableCredential,
-    InconsistentVoterSet,
-    InvalidUpdateVersion,
-    FeatureUpdateFailed,
-    PrincipalDeserializationFailure,
-    SnapshotNotFound,
-    PositionOutOfRange,
-    UnknownTopicId,
-    DuplicateBrokerRegistration,
-    BrokerIdNotRegistered,
-    InconsistentTopicId,
-    InconsistentClusterId,
-    TransactionalIdNotFound,
-    FetchSessionTopicIdError,
-    IneligibleReplica,
-    NewLeaderElected,
-    OffsetMovedToTieredStorage,
-    FencedMemberEpoch,
-    UnreleasedInstanceId,
-    UnsupportedAssignor,
-    StaleMemberEpoch,
-    MismatchedEndpointType,
-    UnsupportedEndpointType,
-    UnknownControllerId,
-    UnknownSubscriptionId,
-    TelemetryTooLarge,
-    InvalidRegistration,
-}
-
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum IsolationLevel {
     ReadUncommitted,
@@ -1125,13 +1009,25 @@ impl TryFrom<i8> for IsolationLevel {
     }
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
-enum Ack {
+/// The acknowledgement required by a produce request: none, for which no
+/// response is sent; the leader alone; or the full in-sync replica set.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Ack {
     None,
     Leader,
     FullIsr,
 }
 
+impl From<Ack> for i16 {
+    fn from(value: Ack) -> Self {
+        match value {
+            Ack::FullIsr => -1,
+            Ack::None => 0,
+            Ack::Leader => 1,
+        }
+    }
+}
+
 impl TryFrom<i16> for Ack {
     type Error = Error;
 
@@ -1183,43 +1079,46 @@ impl From<Compression> for i16 {
 }
 
 impl Compression {
-    fn inflator(&self, mut deflated: impl BufRead + 'static) -> Result<Box<dyn Read>> {
+    // a reader of the inflated data that stops one byte past the maximum,
+    // so that a caller can tell when the data would inflate beyond it
+    fn inflator(
+        &self,
+        mut deflated: impl BufRead + 'static,
+        maximum: usize,
+    ) -> Result<Box<dyn Read>> {
+        let limit = u64::try_from(maximum).map_or(u64::MAX, |maximum| maximum.saturating_add(1));
+
         match self {
-            Compression::None => Ok(Box::new(deflated)),
-            Compression::Gzip => Ok(Box::new(GzDecoder::new(deflated))),
+            Compression::None => Ok(Box::new(deflated.take(limit))),
+            Compression::Gzip => Ok(Box::new(GzDecoder::new(deflated).take(limit))),
+            #[cfg(feature = "snappy")]
             Compression::Snappy => {
                 let mut input = vec![];
                 _ = deflated.read_to_end(&mut input)?;
                 debug!(?input);
 
-                let mut decoder = snap::raw::Decoder::new();
-
-                decoder
-                    .decompress_vec(
-                        // https://github.com/xerial/snappy-java/tree/master?tab=readme-ov-file#compatibility-notes
-                        if input.starts_with(b"\x82SNAPPY\0") {
-                            let skip_header = &input[20..];
-                            debug!(?skip_header);
-                            skip_header
-                        } else {
-                            &input[..]
-                        },
-                    )
-                    .map_err(Into::into)
-                    .map(Bytes::from)
+                record::snappy::decompress(&input[..], maximum)
                     .map(|bytes| bytes.reader())
                     .map(Box::new)
                     .map(|boxed| boxed as Box<dyn Read>)
                     .inspect_err(|err| error!(?err))
             }
+            #[cfg(not(feature = "snappy"))]
+            Compression::Snappy => Err(Error::UnsupportedCompression(self.clone())),
+            #[cfg(feature = "lz4")]
             Compression::Lz4 => lz4::Decoder::new(deflated)
-                .map(Box::new)
+                .map(|decoder| Box::new(decoder.take(limit)))
                 .map(|boxed| boxed as Box<dyn Read>)
                 .map_err(Into::into),
+            #[cfg(not(feature = "lz4"))]
+            Compression::Lz4 => Err(Error::UnsupportedCompression(self.clone())),
+            #[cfg(feature = "zstd")]
             Compression::Zstd => zstd::stream::read::Decoder::with_buffer(deflated)
-                .map(Box::new)
+                .map(|decoder| Box::new(decoder.take(limit)))
                 .map(|boxed| boxed as Box<dyn Read>)
                 .map_err(Into::into),
+            #[cfg(not(feature = "zstd"))]
+            Compression::Zstd => Err(Error::UnsupportedCompression(self.clone())),
         }
     }
 }
@@ -1250,7 +1149,10 @@ impl From<EndpointType> for i8 {
     }
 }
 
+/// The type of key used to find a coordinator.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum CoordinatorType {
+    #[default]
     Group,
     Transaction,
     Share,
@@ -1421,6 +1323,199 @@ impl From<ConfigSource> for i8 {
     }
 }
 
+/// The type of resource an ACL binding applies to.
+///
+/// [`ResourceType::Any`] only matches in a filter, as used by describe and delete.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ResourceType {
+    Unknown,
+    Any,
+    Topic,
+    Group,
+    Cluster,
+    TransactionalId,
+    DelegationToken,
+    User,
+}
+
+impl TryFrom<i8> for ResourceType {
+    type Error = Error;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Unknown),
+            1 => Ok(Self::Any),
+            2 => Ok(Self::Topic),
+            3 => Ok(Self::Group),
+            4 => Ok(Self::Cluster),
+            5 => Ok(Self::TransactionalId),
+            6 => Ok(Self::DelegationToken),
+            7 => Ok(Self::User),
+            otherwise => Err(Error::InvalidResourceType(otherwise)),
+        }
+    }
+}
+
+impl From<ResourceType> for i8 {
+    fn from(value: ResourceType) -> Self {
+        match value {
+            ResourceType::Unknown => 0,
+            ResourceType::Any => 1,
+            ResourceType::Topic => 2,
+            ResourceType::Group => 3,
+            ResourceType::Cluster => 4,
+            ResourceType::TransactionalId => 5,
+            ResourceType::DelegationToken => 6,
+            ResourceType::User => 7,
+        }
+    }
+}
+
+/// How the resource name of an ACL binding is matched.
+///
+/// [`PatternType::Any`] and [`PatternType::Match`] are only valid in a filter.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PatternType {
+    Unknown,
+    Any,
+    Match,
+    #[default]
+    Literal,
+    Prefixed,
+}
+
+impl TryFrom<i8> for PatternType {
+    type Error = Error;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Unknown),
+            1 => Ok(Self::Any),
+            2 => Ok(Self::Match),
+            3 => Ok(Self::Literal),
+            4 => Ok(Self::Prefixed),
+            otherwise => Err(Error::InvalidPatternType(otherwise)),
+        }
+    }
+}
+
+impl From<PatternType> for i8 {
+    fn from(value: PatternType) -> Self {
+        match value {
+            PatternType::Unknown => 0,
+            PatternType::Any => 1,
+            PatternType::Match => 2,
+            PatternType::Literal => 3,
+            PatternType::Prefixed => 4,
+        }
+    }
+}
+
+/// The operation permitted or denied by an ACL binding.
+///
+/// [`AclOperation::Any`] only matches in a filter.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum AclOperation {
+    Unknown,
+    Any,
+    All,
+    Read,
+    Write,
+    Create,
+    Delete,
+    Alter,
+    Describe,
+    ClusterAction,
+    DescribeConfigs,
+    AlterConfigs,
+    IdempotentWrite,
+    CreateTokens,
+    DescribeTokens,
+}
+
+impl TryFrom<i8> for AclOperation {
+    type Error = Error;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Unknown),
+            1 => Ok(Self::Any),
+            2 => Ok(Self::All),
+            3 => Ok(Self::Read),
+            4 => Ok(Self::Write),
+            5 => Ok(Self::Create),
+            6 => Ok(Self::Delete),
+            7 => Ok(Self::Alter),
+            8 => Ok(Self::Describe),
+            9 => Ok(Self::ClusterAction),
+            10 => Ok(Self::DescribeConfigs),
+            11 => Ok(Self::AlterConfigs),
+            12 => Ok(Self::IdempotentWrite),
+            13 => Ok(Self::CreateTokens),
+            14 => Ok(Self::DescribeTokens),
+            otherwise => Err(Error::InvalidAclOperation(otherwise)),
+        }
+    }
+}
+
+impl From<AclOperation> for i8 {
+    fn from(value: AclOperation) -> Self {
+        match value {
+            AclOperation::Unknown => 0,
+            AclOperation::Any => 1,
+            AclOperation::All => 2,
+            AclOperation::Read => 3,
+            AclOperation::Write => 4,
+            AclOperation::Create => 5,
+            AclOperation::Delete => 6,
+            AclOperation::Alter => 7,
+            AclOperation::Describe => 8,
+            AclOperation::ClusterAction => 9,
+            AclOperation::DescribeConfigs => 10,
+            AclOperation::AlterConfigs => 11,
+            AclOperation::IdempotentWrite => 12,
+            AclOperation::CreateTokens => 13,
+            AclOperation::DescribeTokens => 14,
+        }
+    }
+}
+
+/// Whether an ACL binding allows or denies its operation.
+///
+/// [`AclPermissionType::Any`] only matches in a filter.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum AclPermissionType {
+    Unknown,
+    Any,
+    Deny,
+    Allow,
+}
+
+impl TryFrom<i8> for AclPermissionType {
+    type Error = Error;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Unknown),
+            1 => Ok(Self::Any),
+            2 => Ok(Self::Deny),
+            3 => Ok(Self::Allow),
+            otherwise => Err(Error::InvalidAclPermissionType(otherwise)),
+        }
+    }
+}
+
+impl From<AclPermissionType> for i8 {
+    fn from(value: AclPermissionType) -> Self {
+        match value {
+            AclPermissionType::Unknown => 0,
+            AclPermissionType::Any => 1,
+            AclPermissionType::Deny => 2,
+            AclPermissionType::Allow => 3,
+        }
+    }
+}
+
 pub fn to_system_time(timestamp: i64) -> Result<SystemTime> {
     u64::try_from(timestamp)
         .map(|timestamp| SystemTime::UNIX_EPOCH + Duration::from_millis(timestamp))
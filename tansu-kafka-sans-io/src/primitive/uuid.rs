@@ -13,7 +13,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+// A uuid is always 16 raw bytes on the wire, in both flexible and
+// non-flexible versions, with the nil uuid as the default.
+
 use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
 
 use super::ByteSize;
 use crate::Result;
@@ -23,12 +27,53 @@ use crate::Result;
 )]
 pub struct Uuid(pub [u8; 16]);
 
+impl Uuid {
+    #[must_use]
+    pub const fn nil() -> Self {
+        Self([0; 16])
+    }
+
+    #[must_use]
+    pub fn is_nil(&self) -> bool {
+        self.0 == [0; 16]
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
 impl From<[u8; 16]> for Uuid {
     fn from(value: [u8; 16]) -> Self {
         Self(value)
     }
 }
 
+impl From<Uuid> for [u8; 16] {
+    fn from(value: Uuid) -> Self {
+        value.0
+    }
+}
+
+impl From<::uuid::Uuid> for Uuid {
+    fn from(value: ::uuid::Uuid) -> Self {
+        Self(value.into_bytes())
+    }
+}
+
+impl From<Uuid> for ::uuid::Uuid {
+    fn from(value: Uuid) -> Self {
+        Self::from_bytes(value.0)
+    }
+}
+
+impl Display for Uuid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&::uuid::Uuid::from_bytes(self.0), f)
+    }
+}
+
 impl ByteSize for Uuid {
     fn size_in_bytes(&self) -> Result<usize> {
         Ok(size_of_val(&self.0))
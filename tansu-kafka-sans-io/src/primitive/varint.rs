@@ -15,17 +15,180 @@
 
 use super::ByteSize;
 use crate::{Error, Result};
+use bytes::{Buf, BufMut};
 use serde::{
     de::{self, SeqAccess, Visitor},
     ser::SerializeSeq,
     Deserialize, Deserializer, Serialize, Serializer,
 };
-use std::{any::type_name_of_val, fmt::Formatter, ops::Deref};
+use std::{any::type_name_of_val, fmt::Formatter, io, iter, ops::Deref};
 use tracing::debug;
 
 const CONTINUATION: u8 = 0b1000_0000;
 const MASK: u8 = 0b0111_1111;
 
+/// The maximum encoded length of a 32 bit varint.
+pub const MAX_VARINT_BYTES: usize = 5;
+
+/// The maximum encoded length of a 64 bit varint.
+pub const MAX_VARLONG_BYTES: usize = 10;
+
+/// Incrementally decodes an unsigned varint that must fit within `bits`,
+/// rejecting over long encodings rather than looping or wrapping.
+#[derive(Clone, Copy, Debug)]
+struct Accumulator {
+    bits: u32,
+    shift: u32,
+    value: u64,
+}
+
+impl Accumulator {
+    fn new(bits: u32) -> Self {
+        Self {
+            bits,
+            shift: 0,
+            value: 0,
+        }
+    }
+
+    /// The decoded value once the final byte has been pushed.
+    fn push(&mut self, byte: u8) -> Result<Option<u64>> {
+        let intermediate = u64::from(byte & MASK);
+
+        if self.shift >= self.bits
+            || (self.bits - self.shift < 7 && intermediate >> (self.bits - self.shift) != 0)
+        {
+            return Err(Error::InvalidVarint);
+        }
+
+        self.value |= intermediate << self.shift;
+
+        if byte & CONTINUATION == CONTINUATION {
+            self.shift += 7;
+            Ok(None)
+        } else {
+            Ok(Some(self.value))
+        }
+    }
+
+    fn decode(mut self, mut next: impl FnMut() -> Result<u8>) -> Result<u64> {
+        loop {
+            if let Some(value) = next().and_then(|byte| self.push(byte))? {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn visit_seq<'de, A>(mut self, seq: &mut A) -> Result<u64, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        loop {
+            let byte = seq
+                .next_element::<u8>()?
+                .ok_or_else(|| de::Error::custom("u8"))?;
+
+            if let Some(value) = self.push(byte).map_err(<A::Error as de::Error>::custom)? {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+fn encode(mut v: u64) -> impl Iterator<Item = u8> {
+    let mut done = false;
+
+    iter::from_fn(move || {
+        if done {
+            None
+        } else if v >= u64::from(CONTINUATION) {
+            #[allow(clippy::cast_possible_truncation)]
+            let byte = v as u8 | CONTINUATION;
+            v >>= 7;
+            Some(byte)
+        } else {
+            done = true;
+
+            #[allow(clippy::cast_possible_truncation)]
+            Some(v as u8)
+        }
+    })
+}
+
+fn next_u8(buf: &mut impl Buf) -> impl FnMut() -> Result<u8> + '_ {
+    move || {
+        if buf.has_remaining() {
+            Ok(buf.get_u8())
+        } else {
+            Err(Error::Io(io::Error::from(io::ErrorKind::UnexpectedEof)))
+        }
+    }
+}
+
+pub fn get_unsigned_varint(buf: &mut impl Buf) -> Result<u32> {
+    Accumulator::new(u32::BITS)
+        .decode(next_u8(buf))
+        .and_then(|value| u32::try_from(value).map_err(Into::into))
+}
+
+pub fn get_unsigned_varlong(buf: &mut impl Buf) -> Result<u64> {
+    Accumulator::new(u64::BITS).decode(next_u8(buf))
+}
+
+pub fn get_varint(buf: &mut impl Buf) -> Result<i32> {
+    get_unsigned_varint(buf).map(VarInt::de_zigzag)
+}
+
+pub fn get_varlong(buf: &mut impl Buf) -> Result<i64> {
+    get_unsigned_varlong(buf).map(LongVarInt::de_zigzag)
+}
+
+pub fn read_unsigned_varint(reader: &mut impl io::Read) -> Result<u32> {
+    Accumulator::new(u32::BITS)
+        .decode(|| {
+            let mut buf = [0u8; 1];
+            reader.read_exact(&mut buf)?;
+            Ok(buf[0])
+        })
+        .and_then(|value| u32::try_from(value).map_err(Into::into))
+}
+
+pub fn read_unsigned_varlong(reader: &mut impl io::Read) -> Result<u64> {
+    Accumulator::new(u64::BITS).decode(|| {
+        let mut buf = [0u8; 1];
+        reader.read_exact(&mut buf)?;
+        Ok(buf[0])
+    })
+}
+
+pub fn put_unsigned_varint(buf: &mut impl BufMut, v: u32) {
+    put_unsigned_varlong(buf, u64::from(v));
+}
+
+pub fn put_unsigned_varlong(buf: &mut impl BufMut, v: u64) {
+    encode(v).for_each(|byte| buf.put_u8(byte));
+}
+
+pub fn put_varint(buf: &mut impl BufMut, v: i32) {
+    put_unsigned_varint(buf, VarInt::en_zigzag(v));
+}
+
+pub fn put_varlong(buf: &mut impl BufMut, v: i64) {
+    put_unsigned_varlong(buf, LongVarInt::en_zigzag(v));
+}
+
+pub fn write_unsigned_varint(writer: &mut impl io::Write, v: u32) -> Result<()> {
+    encode(u64::from(v))
+        .try_for_each(|byte| writer.write_all(&[byte]))
+        .map_err(Into::into)
+}
+
+pub fn write_unsigned_varlong(writer: &mut impl io::Write, v: u64) -> Result<()> {
+    encode(v)
+        .try_for_each(|byte| writer.write_all(&[byte]))
+        .map_err(Into::into)
+}
+
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct VarInt(pub i32);
 
@@ -39,12 +202,14 @@ impl Deref for VarInt {
 
 impl VarInt {
     #[allow(clippy::cast_sign_loss)]
-    fn en_zigzag(decoded: i32) -> u32 {
+    #[must_use]
+    pub fn en_zigzag(decoded: i32) -> u32 {
         ((decoded << 1) ^ (decoded >> 31)) as u32
     }
 
     #[allow(clippy::cast_possible_wrap)]
-    fn de_zigzag(encoded: u32) -> i32 {
+    #[must_use]
+    pub fn de_zigzag(encoded: u32) -> i32 {
         ((encoded >> 1) as i32) ^ -((encoded & 1) as i32)
     }
 
@@ -54,17 +219,8 @@ impl VarInt {
     {
         debug!(?i);
 
-        let mut v = Self::en_zigzag(*i);
         let mut s = serializer.serialize_seq(None)?;
-
-        while v >= u32::from(CONTINUATION) {
-            #[allow(clippy::cast_possible_truncation)]
-            s.serialize_element(&(v as u8 | CONTINUATION))?;
-            v >>= 7;
-        }
-
-        #[allow(clippy::cast_possible_truncation)]
-        s.serialize_element(&(v as u8))?;
+        encode(u64::from(Self::en_zigzag(*i))).try_for_each(|byte| s.serialize_element(&byte))?;
         s.end()
     }
 
@@ -85,24 +241,9 @@ impl VarInt {
             where
                 A: SeqAccess<'de>,
             {
-                let mut shift = 0u8;
-                let mut accumulator = 0u32;
-                let mut done = false;
-
-                while !done {
-                    let byte = seq
-                        .next_element::<u8>()?
-                        .ok_or_else(|| de::Error::custom("u8"))?;
-
-                    if byte & CONTINUATION == CONTINUATION {
-                        let intermediate = u32::from(byte & MASK);
-                        accumulator += intermediate << shift;
-                        shift += 7;
-                    } else {
-                        accumulator += u32::from(byte) << shift;
-                        done = true;
-                    }
-                }
+                let accumulator = Accumulator::new(u32::BITS)
+                    .visit_seq(&mut seq)
+                    .and_then(|value| u32::try_from(value).map_err(de::Error::custom))?;
 
                 let i = VarInt::de_zigzag(accumulator);
                 debug!("i: {i}");
@@ -181,12 +322,14 @@ impl Deref for LongVarInt {
 
 impl LongVarInt {
     #[allow(clippy::cast_sign_loss)]
-    fn en_zigzag(decoded: i64) -> u64 {
+    #[must_use]
+    pub fn en_zigzag(decoded: i64) -> u64 {
         ((decoded << 1) ^ (decoded >> 63)) as u64
     }
 
     #[allow(clippy::cast_possible_wrap)]
-    fn de_zigzag(encoded: u64) -> i64 {
+    #[must_use]
+    pub fn de_zigzag(encoded: u64) -> i64 {
         ((encoded >> 1) as i64) ^ -((encoded & 1) as i64)
     }
 
@@ -194,17 +337,8 @@ impl LongVarInt {
     where
         S: Serializer,
     {
-        let mut v = Self::en_zigzag(*i);
         let mut s = serializer.serialize_seq(None)?;
-
-        while v >= u64::from(CONTINUATION) {
-            #[allow(clippy::cast_possible_truncation)]
-            s.serialize_element(&(v as u8 | CONTINUATION))?;
-            v >>= 7;
-        }
-
-        #[allow(clippy::cast_possible_truncation)]
-        s.serialize_element(&(v as u8))?;
+        encode(Self::en_zigzag(*i)).try_for_each(|byte| s.serialize_element(&byte))?;
         s.end()
     }
 
@@ -225,26 +359,9 @@ impl LongVarInt {
             where
                 A: SeqAccess<'de>,
             {
-                let mut shift = 0u8;
-                let mut accumulator = 0u64;
-                let mut done = false;
-
-                while !done {
-                    let byte = seq
-                        .next_element::<u8>()?
-                        .ok_or_else(|| de::Error::custom("u8"))?;
-
-                    if byte & CONTINUATION == CONTINUATION {
-                        let intermediate = u64::from(byte & MASK);
-                        accumulator += intermediate << shift;
-                        shift += 7;
-                    } else {
-                        accumulator += u64::from(byte) << shift;
-                        done = true;
-                    }
-                }
-
-                Ok(LongVarInt::de_zigzag(accumulator))
+                Accumulator::new(u64::BITS)
+                    .visit_seq(&mut seq)
+                    .map(LongVarInt::de_zigzag)
             }
         }
 
@@ -322,17 +439,8 @@ impl UnsignedVarInt {
     where
         S: Serializer,
     {
-        let mut v = *i;
         let mut s = serializer.serialize_seq(None)?;
-
-        while v >= u32::from(CONTINUATION) {
-            #[allow(clippy::cast_possible_truncation)]
-            s.serialize_element(&(v as u8 | CONTINUATION))?;
-            v >>= 7;
-        }
-
-        #[allow(clippy::cast_possible_truncation)]
-        s.serialize_element(&(v as u8))?;
+        encode(u64::from(*i)).try_for_each(|byte| s.serialize_element(&byte))?;
         s.end()
     }
 
@@ -355,26 +463,9 @@ impl UnsignedVarInt {
             {
                 debug!("seq: {}", type_name_of_val(&seq));
 
-                let mut shift = 0u8;
-                let mut accumulator = 0u32;
-                let mut done = false;
-
-                while !done {
-                    let byte = seq
-                        .next_element::<u8>()?
-                        .ok_or_else(|| de::Error::custom("byte"))?;
-
-                    debug!("byte: {byte}");
-
-                    if byte & CONTINUATION == CONTINUATION {
-                        let intermediate = u32::from(byte & MASK);
-                        accumulator += intermediate << shift;
-                        shift += 7;
-                    } else {
-                        accumulator += u32::from(byte) << shift;
-                        done = true;
-                    }
-                }
+                let accumulator = Accumulator::new(u32::BITS)
+                    .visit_seq(&mut seq)
+                    .and_then(|value| u32::try_from(value).map_err(de::Error::custom))?;
 
                 debug!("accumulator: {accumulator}");
 
@@ -458,6 +549,7 @@ impl<'de> Deserialize<'de> for UnsignedVarInt {
 mod tests {
     use super::*;
     use crate::ser::Encoder;
+    use std::io::Read;
 
     // #[test]
     // fn serde_varint() -> Result<()> {
@@ -522,6 +614,207 @@ mod tests {
         Ok(())
     }
 
+    // every power of two, either side of it, and a coarse stride across the range
+    fn sweep_i32() -> impl Iterator<Item = i32> {
+        (0..i32::BITS)
+            .flat_map(|bit| {
+                let power = 1i32.wrapping_shl(bit);
+                [
+                    power.wrapping_sub(1),
+                    power,
+                    power.wrapping_add(1),
+                    power.wrapping_neg(),
+                ]
+            })
+            .chain([i32::MIN, i32::MAX])
+            .chain((i32::MIN..=i32::MAX).step_by(65_521))
+    }
+
+    fn sweep_i64() -> impl Iterator<Item = i64> {
+        (0..i64::BITS)
+            .flat_map(|bit| {
+                let power = 1i64.wrapping_shl(bit);
+                [
+                    power.wrapping_sub(1),
+                    power,
+                    power.wrapping_add(1),
+                    power.wrapping_neg(),
+                ]
+            })
+            .chain([i64::MIN, i64::MAX])
+            .chain((i64::MIN..=i64::MAX).step_by(0x0000_7fff_ffff_fff1))
+    }
+
+    #[test]
+    fn varint_round_trip() -> Result<()> {
+        for decoded in sweep_i32() {
+            let mut encoded = Vec::new();
+            put_varint(&mut encoded, decoded);
+            assert!(encoded.len() <= MAX_VARINT_BYTES);
+            assert_eq!(VarInt(decoded).size_in_bytes()?, encoded.len());
+
+            let mut serialized = Vec::new();
+            VarInt(decoded).serialize(&mut Encoder::new(&mut serialized))?;
+            assert_eq!(encoded, serialized);
+
+            let mut buf = &encoded[..];
+            assert_eq!(decoded, get_varint(&mut buf)?);
+            assert!(buf.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn varlong_round_trip() -> Result<()> {
+        for decoded in sweep_i64() {
+            let mut encoded = Vec::new();
+            put_varlong(&mut encoded, decoded);
+            assert!(encoded.len() <= MAX_VARLONG_BYTES);
+            assert_eq!(LongVarInt(decoded).size_in_bytes()?, encoded.len());
+
+            let mut serialized = Vec::new();
+            LongVarInt(decoded).serialize(&mut Encoder::new(&mut serialized))?;
+            assert_eq!(encoded, serialized);
+
+            let mut buf = &encoded[..];
+            assert_eq!(decoded, get_varlong(&mut buf)?);
+            assert!(buf.is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unsigned_varint_round_trip() -> Result<()> {
+        for decoded in sweep_i32().map(VarInt::en_zigzag) {
+            let mut encoded = Vec::new();
+            put_unsigned_varint(&mut encoded, decoded);
+            assert_eq!(UnsignedVarInt(decoded).size_in_bytes()?, encoded.len());
+
+            let mut written = Vec::new();
+            write_unsigned_varint(&mut written, decoded)?;
+            assert_eq!(encoded, written);
+
+            assert_eq!(decoded, get_unsigned_varint(&mut &encoded[..])?);
+            assert_eq!(decoded, read_unsigned_varint(&mut &encoded[..])?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unsigned_varlong_round_trip() -> Result<()> {
+        for decoded in sweep_i64().map(LongVarInt::en_zigzag) {
+            let mut encoded = Vec::new();
+            put_unsigned_varlong(&mut encoded, decoded);
+
+            let mut written = Vec::new();
+            write_unsigned_varlong(&mut written, decoded)?;
+            assert_eq!(encoded, written);
+
+            assert_eq!(decoded, get_unsigned_varlong(&mut &encoded[..])?);
+            assert_eq!(decoded, read_unsigned_varlong(&mut &encoded[..])?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn zigzag() {
+        assert_eq!(0, VarInt::en_zigzag(0));
+        assert_eq!(1, VarInt::en_zigzag(-1));
+        assert_eq!(2, VarInt::en_zigzag(1));
+        assert_eq!(u32::MAX, VarInt::en_zigzag(i32::MIN));
+        assert_eq!(u32::MAX - 1, VarInt::en_zigzag(i32::MAX));
+        assert_eq!(u64::MAX, LongVarInt::en_zigzag(i64::MIN));
+        assert_eq!(i64::MAX, LongVarInt::de_zigzag(u64::MAX - 1));
+    }
+
+    #[test]
+    fn unterminated_continuation() {
+        let encoded = [0xff; 64];
+
+        assert!(matches!(
+            get_unsigned_varint(&mut &encoded[..]),
+            Err(Error::InvalidVarint)
+        ));
+
+        assert!(matches!(
+            read_unsigned_varint(&mut &encoded[..]),
+            Err(Error::InvalidVarint)
+        ));
+
+        assert!(matches!(
+            get_varlong(&mut &encoded[..]),
+            Err(Error::InvalidVarint)
+        ));
+    }
+
+    #[test]
+    fn endless_continuation() {
+        // a reader that never ends is abandoned after the maximum length
+        let mut reader = io::repeat(0x80).take(1_024);
+
+        assert!(matches!(
+            read_unsigned_varint(&mut reader),
+            Err(Error::InvalidVarint)
+        ));
+        assert_eq!(1_024 - MAX_VARINT_BYTES as u64 - 1, reader.limit());
+
+        let mut reader = io::repeat(0x80).take(1_024);
+
+        assert!(matches!(
+            read_unsigned_varlong(&mut reader),
+            Err(Error::InvalidVarint)
+        ));
+        assert_eq!(1_024 - MAX_VARLONG_BYTES as u64 - 1, reader.limit());
+    }
+
+    #[test]
+    fn overflow() {
+        // u32::MAX + 1
+        assert!(matches!(
+            get_unsigned_varint(&mut &[0x80, 0x80, 0x80, 0x80, 0x10][..]),
+            Err(Error::InvalidVarint)
+        ));
+
+        assert_eq!(
+            u32::MAX,
+            get_unsigned_varint(&mut &[0xff, 0xff, 0xff, 0xff, 0x0f][..]).unwrap_or_default()
+        );
+
+        // u64::MAX + 1
+        assert!(matches!(
+            get_unsigned_varlong(
+                &mut &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x02][..]
+            ),
+            Err(Error::InvalidVarint)
+        ));
+
+        assert_eq!(
+            u64::MAX,
+            read_unsigned_varlong(
+                &mut &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01][..]
+            )
+            .unwrap_or_default()
+        );
+
+        // u32::MAX as 5 bytes, with the final byte setting bits beyond 32
+        assert!(matches!(
+            read_unsigned_varint(&mut &[0xff, 0xff, 0xff, 0xff, 0x1f][..]),
+            Err(Error::InvalidVarint)
+        ));
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(matches!(
+            get_varint(&mut &[0x80, 0x80][..]),
+            Err(Error::Io(_))
+        ));
+    }
+
     // #[test]
     // fn decode_varint_minus_one() -> Result<()> {
     //     assert_eq!(-1, Decoder::decode::<VarInt>(&[1u8]).map(|v| v.0)?);
@@ -13,10 +13,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+pub mod attributes;
 pub(crate) mod codec;
+pub mod control;
 pub mod deflated;
 pub mod header;
 pub mod inflated;
+pub mod legacy;
+pub mod records;
+#[cfg(feature = "snappy")]
+pub(crate) mod snappy;
 
 use crate::{
     primitive::{
@@ -25,13 +31,17 @@ use crate::{
     },
     Result,
 };
+pub use attributes::{BatchAttributes, TimestampType};
 use bytes::Bytes;
 use codec::{Octets, VarIntSequence};
+pub use control::ControlRecord;
 pub use header::Header;
+pub use records::Records;
 use serde::{
     ser::{self, SerializeSeq},
     Deserialize, Serialize, Serializer,
 };
+use std::ops::Deref;
 
 #[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Record {
@@ -68,17 +78,62 @@ impl Record {
         Builder::default()
     }
 
-    pub fn key(&self) -> Option<Bytes> {
-        self.key.clone()
+    #[must_use]
+    pub fn key(&self) -> Option<&[u8]> {
+        self.key.as_deref()
     }
 
-    pub fn value(&self) -> Option<Bytes> {
-        self.value.clone()
+    #[must_use]
+    pub fn value(&self) -> Option<&[u8]> {
+        self.value.as_deref()
+    }
+
+    #[must_use]
+    pub fn headers(&self) -> &[Header] {
+        &self.headers[..]
     }
 
     pub fn is_tombstone(&self) -> bool {
         self.key.is_some() && self.value.is_none()
     }
+
+    /// This record with its offset and timestamp resolved against those of its batch.
+    #[must_use]
+    pub fn resolve(&self, base_offset: i64, base_timestamp: i64) -> Resolved<'_> {
+        Resolved {
+            base_offset,
+            base_timestamp,
+            record: self,
+        }
+    }
+}
+
+/// A record together with the base offset and timestamp of its batch.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Resolved<'a> {
+    base_offset: i64,
+    base_timestamp: i64,
+    record: &'a Record,
+}
+
+impl Deref for Resolved<'_> {
+    type Target = Record;
+
+    fn deref(&self) -> &Self::Target {
+        self.record
+    }
+}
+
+impl Resolved<'_> {
+    #[must_use]
+    pub fn offset(&self) -> i64 {
+        self.base_offset + i64::from(self.record.offset_delta)
+    }
+
+    #[must_use]
+    pub fn timestamp(&self) -> i64 {
+        self.base_timestamp + self.record.timestamp_delta
+    }
 }
 
 impl TryFrom<Builder> for Record {
@@ -260,6 +315,62 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn null_and_empty_round_trip() -> Result<()> {
+        let batch = inflated::Batch::builder()
+            .base_offset(32_123)
+            .base_timestamp(1_707_058_170_000)
+            .record(Record::builder().value(Bytes::from_static(b"pqr").into()))
+            .record(
+                Record::builder()
+                    .offset_delta(1)
+                    .timestamp_delta(165)
+                    .key(Bytes::from_static(b"abc").into())
+                    .value(Bytes::new().into()),
+            )
+            .record(
+                Record::builder()
+                    .offset_delta(2)
+                    .key(Bytes::new().into())
+                    .header(Header::builder().key(b"absent".into()))
+                    .header(Header::builder().key(b"empty".into()).value(vec![]))
+                    .header(Header::builder().value(b"xyz".into())),
+            )
+            .last_offset_delta(2)
+            .build()
+            .and_then(deflated::Batch::try_from)
+            .and_then(inflated::Batch::try_from)?;
+
+        let records = batch.resolved().collect::<Vec<_>>();
+        assert_eq!(3, records.len());
+
+        assert_eq!(32_123, records[0].offset());
+        assert_eq!(1_707_058_170_000, records[0].timestamp());
+        assert_eq!(None, records[0].key());
+        assert_eq!(Some(&b"pqr"[..]), records[0].value());
+        assert!(records[0].headers().is_empty());
+
+        assert_eq!(32_124, records[1].offset());
+        assert_eq!(1_707_058_170_165, records[1].timestamp());
+        assert_eq!(Some(&b"abc"[..]), records[1].key());
+        assert_eq!(Some(&b""[..]), records[1].value());
+
+        assert_eq!(32_125, records[2].offset());
+        assert_eq!(Some(&b""[..]), records[2].key());
+        assert_eq!(None, records[2].value());
+
+        let headers = records[2].headers();
+        assert_eq!(3, headers.len());
+        assert_eq!(Some(&b"absent"[..]), headers[0].key());
+        assert_eq!(None, headers[0].value());
+        assert_eq!(Some(&b"empty"[..]), headers[1].key());
+        assert_eq!(Some(&b""[..]), headers[1].value());
+        assert_eq!(None, headers[2].key());
+        assert_eq!(Some(&b"xyz"[..]), headers[2].value());
+
+        Ok(())
+    }
+
     #[test]
     fn crc_check() {
         use crc::Crc;
@@ -0,0 +1,159 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// The attributes of a record batch:
+// https://kafka.apache.org/documentation/#recordbatch
+//
+// bit 0~2: compression codec
+// bit 3: timestamp type
+// bit 4: is transactional
+// bit 5: is control batch
+// bit 6: has delete horizon ms
+// bit 7~15: unused
+
+use crate::{
+    record::control::{CONTROL, TRANSACTIONAL},
+    Compression, Result,
+};
+
+const COMPRESSION: i16 = 0b111;
+const TIMESTAMP_TYPE: i16 = 0b1000;
+
+/// Whether the timestamps of a batch were set by the producer or the broker.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TimestampType {
+    #[default]
+    CreateTime,
+    LogAppendTime,
+}
+
+/// The attributes of a record batch, as sent on the wire.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BatchAttributes(i16);
+
+impl BatchAttributes {
+    pub fn compression(&self) -> Result<Compression> {
+        Compression::try_from(self.0)
+    }
+
+    #[must_use]
+    pub fn timestamp_type(&self) -> TimestampType {
+        if self.0 & TIMESTAMP_TYPE == TIMESTAMP_TYPE {
+            TimestampType::LogAppendTime
+        } else {
+            TimestampType::CreateTime
+        }
+    }
+
+    #[must_use]
+    pub fn is_transactional(&self) -> bool {
+        self.0 & TRANSACTIONAL == TRANSACTIONAL
+    }
+
+    #[must_use]
+    pub fn is_control(&self) -> bool {
+        self.0 & CONTROL == CONTROL
+    }
+
+    #[must_use]
+    pub fn with_compression(self, compression: Compression) -> Self {
+        Self((self.0 & !COMPRESSION) | i16::from(compression))
+    }
+
+    #[must_use]
+    pub fn with_timestamp_type(self, timestamp_type: TimestampType) -> Self {
+        self.with(
+            TIMESTAMP_TYPE,
+            timestamp_type == TimestampType::LogAppendTime,
+        )
+    }
+
+    #[must_use]
+    pub fn with_transactional(self, transactional: bool) -> Self {
+        self.with(TRANSACTIONAL, transactional)
+    }
+
+    #[must_use]
+    pub fn with_control(self, control: bool) -> Self {
+        self.with(CONTROL, control)
+    }
+
+    fn with(self, bit: i16, set: bool) -> Self {
+        if set {
+            Self(self.0 | bit)
+        } else {
+            Self(self.0 & !bit)
+        }
+    }
+}
+
+impl From<i16> for BatchAttributes {
+    fn from(value: i16) -> Self {
+        Self(value)
+    }
+}
+
+impl From<BatchAttributes> for i16 {
+    fn from(value: BatchAttributes) -> Self {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits() -> Result<()> {
+        let attributes = BatchAttributes::from(0b11_1011);
+
+        assert_eq!(Compression::Lz4, attributes.compression()?);
+        assert_eq!(TimestampType::LogAppendTime, attributes.timestamp_type());
+        assert!(attributes.is_transactional());
+        assert!(attributes.is_control());
+
+        let attributes = BatchAttributes::default();
+
+        assert_eq!(Compression::None, attributes.compression()?);
+        assert_eq!(TimestampType::CreateTime, attributes.timestamp_type());
+        assert!(!attributes.is_transactional());
+        assert!(!attributes.is_control());
+
+        Ok(())
+    }
+
+    #[test]
+    fn with() -> Result<()> {
+        let attributes = BatchAttributes::default()
+            .with_compression(Compression::Zstd)
+            .with_timestamp_type(TimestampType::LogAppendTime)
+            .with_transactional(true)
+            .with_control(true);
+
+        assert_eq!(0b11_1100, i16::from(attributes));
+
+        let attributes = attributes
+            .with_compression(Compression::Gzip)
+            .with_timestamp_type(TimestampType::CreateTime)
+            .with_control(false);
+
+        assert_eq!(0b1_0001, i16::from(attributes));
+        assert_eq!(Compression::Gzip, attributes.compression()?);
+        assert!(attributes.is_transactional());
+        assert!(!attributes.is_control());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Control records are written by the transaction coordinator as the only
+// record of a control batch:
+// https://kafka.apache.org/documentation/#controlbatch
+
+use crate::{
+    record::{deflated, inflated, Record},
+    Error, Result,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// Batch attribute bit marking the batch as transactional.
+pub const TRANSACTIONAL: i16 = 0b1_0000;
+
+/// Batch attribute bit marking the batch as containing a control record.
+pub const CONTROL: i16 = 0b10_0000;
+
+const KEY_VERSION: i16 = 0;
+const ABORT: i16 = 0;
+const COMMIT: i16 = 1;
+
+/// The value of an abort or commit control record.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct EndTransactionMarker {
+    pub version: i16,
+    pub coordinator_epoch: i32,
+}
+
+impl EndTransactionMarker {
+    const LENGTH: usize = size_of::<i16>() + size_of::<i32>();
+
+    #[must_use]
+    pub fn new(coordinator_epoch: i32) -> Self {
+        Self {
+            version: 0,
+            coordinator_epoch,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ControlRecord {
+    Abort(EndTransactionMarker),
+    Commit(EndTransactionMarker),
+}
+
+impl ControlRecord {
+    fn control_type(&self) -> i16 {
+        match self {
+            Self::Abort(_) => ABORT,
+            Self::Commit(_) => COMMIT,
+        }
+    }
+
+    #[must_use]
+    pub fn marker(&self) -> EndTransactionMarker {
+        match self {
+            Self::Abort(marker) | Self::Commit(marker) => *marker,
+        }
+    }
+
+    #[must_use]
+    pub fn key(&self) -> Bytes {
+        let mut key = BytesMut::with_capacity(size_of::<i16>() + size_of::<i16>());
+        key.put_i16(KEY_VERSION);
+        key.put_i16(self.control_type());
+        key.freeze()
+    }
+
+    #[must_use]
+    pub fn value(&self) -> Bytes {
+        let marker = self.marker();
+
+        let mut value = BytesMut::with_capacity(EndTransactionMarker::LENGTH);
+        value.put_i16(marker.version);
+        value.put_i32(marker.coordinator_epoch);
+        value.freeze()
+    }
+
+    /// A transactional control batch containing this record as its only record.
+    pub fn batch(
+        &self,
+        producer_id: i64,
+        producer_epoch: i16,
+        timestamp: i64,
+    ) -> Result<deflated::Batch> {
+        inflated::Batch::builder()
+            .transactional(true)
+            .control(true)
+            .base_timestamp(timestamp)
+            .max_timestamp(timestamp)
+            .producer_id(producer_id)
+            .producer_epoch(producer_epoch)
+            .base_sequence(-1)
+            .record(
+                Record::builder()
+                    .key(self.key().into())
+                    .value(self.value().into()),
+            )
+            .build()
+            .and_then(deflated::Batch::try_from)
+    }
+}
+
+impl TryFrom<&Record> for ControlRecord {
+    type Error = Error;
+
+    fn try_from(record: &Record) -> Result<Self, Self::Error> {
+        let mut key = record
+            .key
+            .clone()
+            .filter(|key| key.len() == size_of::<i16>() + size_of::<i16>())
+            .ok_or(Error::MalformedControlRecord)?;
+
+        let mut value = record
+            .value
+            .clone()
+            .filter(|value| value.len() >= EndTransactionMarker::LENGTH)
+            .ok_or(Error::MalformedControlRecord)?;
+
+        if key.get_i16() != KEY_VERSION {
+            return Err(Error::MalformedControlRecord);
+        }
+
+        let control_type = key.get_i16();
+
+        let marker = EndTransactionMarker {
+            version: value.get_i16(),
+            coordinator_epoch: value.get_i32(),
+        };
+
+        match control_type {
+            ABORT => Ok(Self::Abort(marker)),
+            COMMIT => Ok(Self::Commit(marker)),
+            otherwise => Err(Error::UnknownControlType(otherwise)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{de::Decoder, ser::Encoder};
+    use serde::{Deserialize, Serialize};
+    use std::io::Cursor;
+
+    #[test]
+    fn commit() -> Result<()> {
+        let commit = ControlRecord::Commit(EndTransactionMarker::new(5));
+
+        assert_eq!(Bytes::from_static(&[0, 0, 0, 1]), commit.key());
+        assert_eq!(Bytes::from_static(&[0, 0, 0, 0, 0, 5]), commit.value());
+
+        let record = Record::builder()
+            .key(Bytes::from_static(&[0, 0, 0, 1]).into())
+            .value(Bytes::from_static(&[0, 0, 0, 0, 0, 5]).into())
+            .build()?;
+
+        assert_eq!(commit, ControlRecord::try_from(&record)?);
+        Ok(())
+    }
+
+    #[test]
+    fn abort() -> Result<()> {
+        let record = Record::builder()
+            .key(Bytes::from_static(&[0, 0, 0, 0]).into())
+            .value(Bytes::from_static(&[0, 0, 0, 0, 0, 0]).into())
+            .build()?;
+
+        assert_eq!(
+            ControlRecord::Abort(EndTransactionMarker::new(0)),
+            ControlRecord::try_from(&record)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_control_type() -> Result<()> {
+        let record = Record::builder()
+            .key(Bytes::from_static(&[0, 0, 0, 3]).into())
+            .value(Bytes::from_static(&[0, 0, 0, 0, 0, 0]).into())
+            .build()?;
+
+        assert!(matches!(
+            ControlRecord::try_from(&record),
+            Err(Error::UnknownControlType(3))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn malformed() -> Result<()> {
+        let record = Record::builder()
+            .value(Bytes::from_static(&[0, 0, 0, 0, 0, 0]).into())
+            .build()?;
+
+        assert!(matches!(
+            ControlRecord::try_from(&record),
+            Err(Error::MalformedControlRecord)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn control_batch() -> Result<()> {
+        let abort = ControlRecord::Abort(EndTransactionMarker::new(3));
+        let batch = abort.batch(4_321, 6, 1_707_058_170_165)?;
+
+        assert!(batch.is_control());
+        assert!(batch.is_transactional());
+        assert_eq!(1, batch.record_count);
+
+        let mut c = Cursor::new(vec![]);
+        batch.serialize(&mut Encoder::new(&mut c))?;
+
+        let decoded =
+            deflated::Batch::deserialize(&mut Decoder::new(&mut Cursor::new(c.into_inner())))?;
+        decoded.verify()?;
+        assert!(decoded.is_control());
+        assert_eq!(4_321, decoded.producer_id);
+        assert_eq!(6, decoded.producer_epoch);
+
+        let records = decoded.records().collect::<Result<Vec<_>>>()?;
+        assert_eq!(1, records.len());
+        assert_eq!(abort, ControlRecord::try_from(&records[0])?);
+
+        Ok(())
+    }
+}
@@ -13,24 +13,39 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::fmt::Formatter;
+use std::{
+    fmt::Formatter,
+    io::{self, Read},
+};
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use crc::{Crc, Digest, CRC_32_ISCSI};
 use flate2::write::GzEncoder;
 use serde::{
     de::{self, SeqAccess, Visitor},
-    Deserialize, Deserializer, Serialize,
+    ser::{self, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
 };
 use tracing::debug;
 
-use crate::{record::Record, Compression, Decoder, Encoder, Error, Result};
+use crate::{
+    primitive::varint,
+    record::{BatchAttributes, Header, Record, TimestampType},
+    Compression, Decoder, Encoder, Error, Result,
+};
 
 #[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Frame {
     pub batches: Vec<Batch>,
 }
 
+impl Frame {
+    /// Verify the CRC of every batch in this frame.
+    pub fn verify(&self) -> Result<()> {
+        self.batches.iter().try_for_each(Batch::verify)
+    }
+}
+
 impl TryFrom<crate::record::inflated::Frame> for Frame {
     type Error = Error;
 
@@ -48,7 +63,7 @@ impl TryFrom<crate::record::inflated::Frame> for Frame {
     }
 }
 
-#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Batch {
     pub base_offset: i64,
     pub batch_length: i32,
@@ -129,7 +144,25 @@ impl CrcData {
     }
 }
 
-fn into_record_data(records: &[Record], compression: Compression) -> Result<Bytes> {
+/// Options used when deflating the records of an inflated batch.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Options {
+    /// The codec specific compression level, using the codec default when absent.
+    pub level: Option<i32>,
+}
+
+impl Options {
+    #[must_use]
+    pub fn level(self, level: Option<i32>) -> Self {
+        Self { level }
+    }
+}
+
+fn into_record_data(
+    records: &[Record],
+    compression: Compression,
+    options: Options,
+) -> Result<Bytes> {
     match compression {
         Compression::None => {
             let mut record_data = BytesMut::new().writer();
@@ -143,7 +176,13 @@ fn into_record_data(records: &[Record], compression: Compression) -> Result<Byte
         }
 
         Compression::Gzip => {
-            let mut gz = GzEncoder::new(BytesMut::new().writer(), flate2::Compression::default());
+            let level = options
+                .level
+                .map_or(Ok(flate2::Compression::default()), |level| {
+                    u32::try_from(level).map(flate2::Compression::new)
+                })?;
+
+            let mut gz = GzEncoder::new(BytesMut::new().writer(), level);
             let mut encoder = Encoder::new(&mut gz);
 
             for record in records {
@@ -156,8 +195,15 @@ fn into_record_data(records: &[Record], compression: Compression) -> Result<Byte
                 .map_err(Into::into)
         }
 
+        #[cfg(feature = "lz4")]
         Compression::Lz4 => {
-            let mut lz4 = lz4::EncoderBuilder::new().build(BytesMut::new().writer())?;
+            let level = options.level.map_or(Ok(0), u32::try_from)?;
+
+            // independent blocks, as the Java client does not support linked blocks
+            let mut lz4 = lz4::EncoderBuilder::new()
+                .block_mode(lz4::BlockMode::Independent)
+                .level(level)
+                .build(BytesMut::new().writer())?;
             let mut encoder = Encoder::new(&mut lz4);
 
             for record in records {
@@ -168,8 +214,15 @@ fn into_record_data(records: &[Record], compression: Compression) -> Result<Byte
             Ok(Bytes::from(w.into_inner()))
         }
 
+        #[cfg(not(feature = "lz4"))]
+        Compression::Lz4 => Err(Error::UnsupportedCompression(compression)),
+
+        #[cfg(feature = "zstd")]
         Compression::Zstd => {
-            let mut zstd = zstd::stream::write::Encoder::new(BytesMut::new().writer(), 0)?;
+            let mut zstd = zstd::stream::write::Encoder::new(
+                BytesMut::new().writer(),
+                options.level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL),
+            )?;
             let mut encoder = Encoder::new(&mut zstd);
 
             for record in records {
@@ -182,7 +235,23 @@ fn into_record_data(records: &[Record], compression: Compression) -> Result<Byte
                 .map_err(Into::into)
         }
 
-        _ => todo!(),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => Err(Error::UnsupportedCompression(compression)),
+
+        #[cfg(feature = "snappy")]
+        Compression::Snappy => {
+            let mut record_data = BytesMut::new().writer();
+            let mut encoder = Encoder::new(&mut record_data);
+
+            for record in records {
+                record.serialize(&mut encoder)?;
+            }
+
+            crate::record::snappy::compress(&record_data.into_inner()[..])
+        }
+
+        #[cfg(not(feature = "snappy"))]
+        Compression::Snappy => Err(Error::UnsupportedCompression(compression)),
     }
 }
 
@@ -190,6 +259,12 @@ impl TryFrom<crate::record::inflated::Batch> for Batch {
     type Error = Error;
 
     fn try_from(batch: crate::record::inflated::Batch) -> std::result::Result<Self, Self::Error> {
+        Self::deflate(batch, Options::default())
+    }
+}
+
+impl Batch {
+    pub fn deflate(batch: crate::record::inflated::Batch, options: Options) -> Result<Self> {
         CrcData {
             attributes: batch.attributes,
             last_offset_delta: batch.last_offset_delta,
@@ -199,19 +274,263 @@ impl TryFrom<crate::record::inflated::Batch> for Batch {
             producer_epoch: batch.producer_epoch,
             base_sequence: batch.base_sequence,
             record_count: u32::try_from(batch.records.len())?,
-            record_data: into_record_data(&batch.records[..], batch.compression()?)?,
+            record_data: into_record_data(&batch.records[..], batch.compression()?, options)?,
         }
         .into_batch(batch.base_offset, batch.partition_leader_epoch, batch.magic)
     }
-}
 
-impl Batch {
     pub fn max_offset(&self) -> i64 {
         self.base_offset + i64::from(self.last_offset_delta)
     }
 
+    /// The length of this batch when encoded, from the base offset through
+    /// the last of its records.
+    #[must_use]
+    pub fn encoded_len(&self) -> usize {
+        // base offset and batch length
+        size_of::<i64>() + size_of::<i32>() + FIXED_BATCH_LENGTH + self.record_data.len()
+    }
+
+    fn crc_data(&self) -> CrcData {
+        CrcData {
+            attributes: self.attributes,
+            last_offset_delta: self.last_offset_delta,
+            base_timestamp: self.base_timestamp,
+            max_timestamp: self.max_timestamp,
+            producer_id: self.producer_id,
+            producer_epoch: self.producer_epoch,
+            base_sequence: self.base_sequence,
+            record_count: self.record_count,
+            record_data: self.record_data.clone(),
+        }
+    }
+
+    /// The CRC-32C of this batch, computed over everything following the crc field.
+    pub fn computed_crc(&self) -> Result<u32> {
+        self.crc_data().crc()
+    }
+
+    #[must_use]
+    pub fn crc_valid(&self) -> bool {
+        self.computed_crc()
+            .is_ok_and(|computed| computed == self.crc)
+    }
+
+    pub fn verify(&self) -> Result<()> {
+        self.computed_crc().and_then(|computed| {
+            if computed == self.crc {
+                Ok(())
+            } else {
+                Err(Error::CrcMismatch {
+                    expected: self.crc,
+                    computed,
+                })
+            }
+        })
+    }
+
+    #[must_use]
+    pub fn batch_attributes(&self) -> BatchAttributes {
+        BatchAttributes::from(self.attributes)
+    }
+
     fn compression(&self) -> Result<Compression> {
-        Compression::try_from(self.attributes)
+        self.batch_attributes().compression()
+    }
+
+    #[must_use]
+    pub fn timestamp_type(&self) -> TimestampType {
+        self.batch_attributes().timestamp_type()
+    }
+
+    #[must_use]
+    pub fn is_transactional(&self) -> bool {
+        self.batch_attributes().is_transactional()
+    }
+
+    /// A control batch contains a single [`ControlRecord`](crate::record::ControlRecord),
+    /// rather than user records.
+    #[must_use]
+    pub fn is_control(&self) -> bool {
+        self.batch_attributes().is_control()
+    }
+
+    fn record_compression(&self) -> Result<Compression> {
+        self.compression().and_then(|compression| {
+            // lz4 in message format v0/v1 used an incorrect frame header checksum
+            // (KAFKA-3160), only the corrected v2 framing is supported
+            if self.magic < 2 && compression == Compression::Lz4 {
+                Err(Error::UnsupportedCompression(compression))
+            } else {
+                Ok(compression)
+            }
+        })
+    }
+
+    // a small batch of highly compressed data can't expand without bound
+    fn inflated_record_data(&self, maximum: usize) -> Result<Bytes> {
+        match self.record_compression()? {
+            Compression::None => Ok(self.record_data.clone()),
+
+            compression => {
+                let mut inflated = Vec::new();
+
+                _ = compression
+                    .inflator(self.record_data.clone().reader(), maximum)?
+                    .read_to_end(&mut inflated)?;
+
+                if inflated.len() > maximum {
+                    Err(Error::InflatedTooLarge { maximum })
+                } else {
+                    Ok(Bytes::from(inflated))
+                }
+            }
+        }
+    }
+
+    /// Lazily decode the records of this batch.
+    ///
+    /// Compressed record data is inflated once into a single buffer, with each
+    /// record only decoded as the iterator advances. The key, value and headers
+    /// of each record are slices of that buffer (or of the uncompressed record
+    /// data) rather than copies.
+    pub fn records(&self) -> Records {
+        self.records_within(usize::MAX)
+    }
+
+    /// As [`Batch::records`], with the iterator returning
+    /// [`Error::InflatedTooLarge`] when compressed record data inflates to
+    /// more than `maximum` bytes.
+    pub fn records_within(&self, maximum: usize) -> Records {
+        match self.inflated_record_data(maximum) {
+            Ok(inflated) => Records {
+                remaining: self.record_count,
+                inflated,
+                error: None,
+            },
+
+            Err(error) => Records {
+                remaining: 0,
+                inflated: Bytes::new(),
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// An iterator over the records of a [`Batch`], see [`Batch::records`].
+#[derive(Debug)]
+pub struct Records {
+    remaining: u32,
+    inflated: Bytes,
+    error: Option<Error>,
+}
+
+impl Records {
+    /// The length of the record data that has not been decoded, which is
+    /// zero once every record of a well formed batch has been read.
+    #[must_use]
+    pub fn remaining_bytes(&self) -> usize {
+        self.inflated.len()
+    }
+
+    fn truncated() -> Error {
+        Error::Io(io::Error::from(io::ErrorKind::UnexpectedEof))
+    }
+
+    fn u8(buf: &mut Bytes) -> Result<u8> {
+        if buf.has_remaining() {
+            Ok(buf.get_u8())
+        } else {
+            Err(Self::truncated())
+        }
+    }
+
+    fn slice(buf: &mut Bytes, length: usize) -> Result<Bytes> {
+        if buf.remaining() >= length {
+            Ok(buf.split_to(length))
+        } else {
+            Err(Self::truncated())
+        }
+    }
+
+    fn octets(buf: &mut Bytes) -> Result<Option<Bytes>> {
+        match varint::get_varint(buf)? {
+            -1 => Ok(None),
+            length => usize::try_from(length)
+                .map_err(Into::into)
+                .and_then(|length| Self::slice(buf, length))
+                .map(Some),
+        }
+    }
+
+    fn record(buf: &mut Bytes) -> Result<Record> {
+        let length = varint::get_varint(buf)?;
+        let mut body = usize::try_from(length)
+            .map_err(Into::into)
+            .and_then(|size| Self::slice(buf, size))?;
+
+        let attributes = Self::u8(&mut body)?;
+        let timestamp_delta = varint::get_varlong(&mut body)?;
+        let offset_delta = varint::get_varint(&mut body)?;
+        let key = Self::octets(&mut body)?;
+        let value = Self::octets(&mut body)?;
+
+        let header_count = varint::get_varint(&mut body).and_then(|count| {
+            usize::try_from(count)
+                .map_err(Into::into)
+                .map(|count| count.min(body.remaining()))
+        })?;
+
+        let mut headers = Vec::with_capacity(header_count);
+
+        for _ in 0..header_count {
+            headers.push(Header {
+                key: Self::octets(&mut body)?,
+                value: Self::octets(&mut body)?,
+            });
+        }
+
+        Ok(Record {
+            length,
+            attributes,
+            timestamp_delta,
+            offset_delta,
+            key,
+            value,
+            headers,
+        })
+    }
+}
+
+impl Iterator for Records {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+
+        Some(Self::record(&mut self.inflated).map_err(|error| {
+            debug!(?error, remaining = self.remaining);
+            self.remaining = 0;
+            error
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (
+            0,
+            usize::try_from(self.remaining)
+                .ok()
+                .map(|remaining| remaining + usize::from(self.error.is_some())),
+        )
     }
 }
 
@@ -225,8 +544,8 @@ impl TryFrom<Batch> for Vec<Record> {
         debug!(?batch.record_data);
 
         let mut reader = batch
-            .compression()
-            .and_then(|compression| compression.inflator(batch.record_data.reader()))?;
+            .record_compression()?
+            .inflator(batch.record_data.reader(), usize::MAX)?;
 
         let mut decoder = Decoder::new(&mut reader);
         let mut records = Vec::with_capacity(record_count);
@@ -240,7 +559,7 @@ impl TryFrom<Batch> for Vec<Record> {
     }
 }
 
-const FIXED_BATCH_LENGTH: usize =
+pub(crate) const FIXED_BATCH_LENGTH: usize =
     // partition leader epoch
     size_of::<i32>()
     // magic
@@ -264,6 +583,35 @@ const FIXED_BATCH_LENGTH: usize =
     // record count
     + size_of::<u32>();
 
+// the crc is always recomputed, so that a mutated batch is never sent with a stale crc
+impl Serialize for Batch {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let crc = self
+            .computed_crc()
+            .map_err(|e| ser::Error::custom(e.to_string()))?;
+
+        let mut s = serializer.serialize_struct(stringify!(Batch), 14)?;
+        s.serialize_field("base_offset", &self.base_offset)?;
+        s.serialize_field("batch_length", &self.batch_length)?;
+        s.serialize_field("partition_leader_epoch", &self.partition_leader_epoch)?;
+        s.serialize_field("magic", &self.magic)?;
+        s.serialize_field("crc", &crc)?;
+        s.serialize_field("attributes", &self.attributes)?;
+        s.serialize_field("last_offset_delta", &self.last_offset_delta)?;
+        s.serialize_field("base_timestamp", &self.base_timestamp)?;
+        s.serialize_field("max_timestamp", &self.max_timestamp)?;
+        s.serialize_field("producer_id", &self.producer_id)?;
+        s.serialize_field("producer_epoch", &self.producer_epoch)?;
+        s.serialize_field("base_sequence", &self.base_sequence)?;
+        s.serialize_field("record_count", &self.record_count)?;
+        s.serialize_field("record_data", &self.record_data)?;
+        s.end()
+    }
+}
+
 impl<'de> Deserialize<'de> for Batch {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -332,7 +680,12 @@ impl<'de> Deserialize<'de> for Batch {
 
                 debug!(?record_data_size);
 
-                let mut record_data = BytesMut::with_capacity(record_data_size);
+                // a hostile batch length is bounded by the remaining records
+                let mut record_data = BytesMut::with_capacity(
+                    seq.size_hint().map_or(record_data_size, |remaining| {
+                        remaining.min(record_data_size)
+                    }),
+                );
 
                 for n in 0..record_data_size {
                     let byte = seq
@@ -498,6 +851,181 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn decode_java_gzip() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        // idempotent batch of three records compressed with java.util.zip.GZIPOutputStream,
+        // as written by the Java producer
+        let encoded = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 175, 255, 255, 255, 255, 2, 61, 39, 83, 78, 0, 1, 0,
+            0, 0, 2, 0, 0, 1, 146, 25, 56, 182, 0, 0, 0, 1, 146, 25, 56, 182, 7, 0, 0, 0, 0, 0, 0,
+            0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 31, 139, 8, 0, 0, 0, 0, 0, 0, 255, 13, 204, 81, 10,
+            194, 48, 12, 0, 208, 56, 100, 200, 62, 60, 67, 14, 33, 126, 251, 45, 59, 131, 144, 37,
+            65, 3, 77, 91, 154, 244, 254, 238, 29, 224, 189, 1, 96, 163, 210, 127, 244, 216, 219,
+            80, 71, 235, 49, 29, 165, 149, 54, 48, 44, 145, 92, 19, 62, 176, 46, 183, 67, 147, 158,
+            220, 106, 40, 167, 230, 28, 72, 98, 221, 130, 173, 126, 81, 139, 229, 178, 229, 32,
+            214, 149, 14, 222, 225, 126, 189, 188, 66, 229, 140, 80, 109, 134, 55, 193, 84, 239,
+            103, 106, 149, 77, 76, 102, 77, 248, 3, 89, 136, 51, 160, 125, 0, 0, 0,
+        ];
+
+        let decoded = Batch::deserialize(&mut Decoder::new(&mut Cursor::new(encoded)))?;
+        decoded.verify()?;
+
+        assert_eq!(encoded.len(), decoded.encoded_len());
+        assert_eq!(Compression::Gzip, decoded.compression()?);
+        assert_eq!(2, decoded.last_offset_delta);
+        assert_eq!(1, decoded.producer_id);
+        assert_eq!(0, decoded.base_sequence);
+        assert_eq!(3, decoded.record_count);
+
+        let inflated = crate::record::inflated::Batch::try_from(decoded.clone())?;
+
+        assert_eq!(
+            vec![
+                (
+                    0,
+                    0,
+                    Some(Bytes::from_static(b"alpha")),
+                    Some(Bytes::from_static(b"Lorem ipsum dolor sit amet")),
+                    vec![],
+                ),
+                (
+                    1,
+                    3,
+                    Some(Bytes::from_static(b"beta")),
+                    Some(Bytes::from_static(b"consectetur adipiscing elit")),
+                    vec![Header {
+                        key: Some(Bytes::from_static(b"trace")),
+                        value: Some(Bytes::from_static(b"abc")),
+                    }],
+                ),
+                (
+                    2,
+                    7,
+                    None,
+                    Some(Bytes::from_static(b"sed do eiusmod tempor incididunt")),
+                    vec![],
+                ),
+            ],
+            inflated
+                .records
+                .iter()
+                .map(|record| (
+                    record.offset_delta,
+                    record.timestamp_delta,
+                    record.key.clone(),
+                    record.value.clone(),
+                    record.headers.clone(),
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        let deflated = Batch::try_from(inflated.clone())?;
+        assert_eq!(decoded.attributes, deflated.attributes);
+        assert_eq!(decoded.producer_id, deflated.producer_id);
+        assert_eq!(decoded.base_timestamp, deflated.base_timestamp);
+        assert_eq!(decoded.max_timestamp, deflated.max_timestamp);
+
+        let records: Vec<Record> = deflated.try_into()?;
+        assert_eq!(inflated.records, records);
+
+        Ok(())
+    }
+
+    #[test]
+    fn crc_flipped_byte() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let encoded = [
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 59, 255, 255, 255, 255, 2, 67, 41, 231, 61, 0, 0, 0,
+            0, 0, 0, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 0, 0,
+            0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 18, 0, 0, 0, 1, 6, 100, 101, 102, 0,
+        ];
+
+        let batch = Batch::deserialize(&mut Decoder::new(&mut Cursor::new(encoded)))?;
+        assert!(batch.crc_valid());
+        batch.verify()?;
+
+        let mut flipped = encoded;
+        flipped[encoded.len() - 2] ^= 0xff;
+
+        let corrupt = Batch::deserialize(&mut Decoder::new(&mut Cursor::new(flipped)))?;
+        assert!(!corrupt.crc_valid());
+        assert!(matches!(
+            corrupt.verify(),
+            Err(Error::CrcMismatch {
+                expected: 1_126_819_645,
+                ..
+            })
+        ));
+
+        // encoding always recomputes the crc
+        let mut c = Cursor::new(vec![]);
+        corrupt.serialize(&mut Encoder::new(&mut c))?;
+
+        let reencoded = Batch::deserialize(&mut Decoder::new(&mut Cursor::new(c.into_inner())))?;
+        assert!(reencoded.crc_valid());
+        assert_eq!(corrupt.computed_crc()?, reencoded.crc);
+
+        Ok(())
+    }
+
+    #[test]
+    fn gzip_round_trip() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let inflated = crate::record::inflated::Batch::builder()
+            .attributes(Compression::Gzip.into())
+            .record(
+                Record::builder()
+                    .key(b"lorem".as_slice().into())
+                    .value(Bytes::from_static(LOREM).into()),
+            )
+            .build()?;
+
+        let deflated = Batch::try_from(inflated.clone())?;
+        assert_eq!(Compression::Gzip, deflated.compression()?);
+        assert!(deflated.record_data.len() < LOREM.len());
+
+        let records: Vec<Record> = deflated.try_into()?;
+        assert_eq!(inflated.records, records);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_compression() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let batch = Batch {
+            attributes: 5,
+            record_count: 1,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            Vec::<Record>::try_from(batch),
+            Err(Error::UnknownCompressionType(5))
+        ));
+
+        // a known codec that cannot be used with this batch is unsupported rather than unknown
+        let batch = Batch {
+            magic: 1,
+            attributes: Compression::Lz4.into(),
+            record_count: 1,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            Vec::<Record>::try_from(batch),
+            Err(Error::UnsupportedCompression(Compression::Lz4))
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "zstd")]
     #[test]
     fn decode_zstd() -> Result<()> {
         let _guard = init_tracing()?;
@@ -578,6 +1106,7 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(feature = "lz4")]
     #[test]
     fn decode_lz4() -> Result<()> {
         let _guard = init_tracing()?;
@@ -663,6 +1192,165 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn codec_round_trip() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let codecs = [
+            Compression::None,
+            Compression::Gzip,
+            #[cfg(feature = "snappy")]
+            Compression::Snappy,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd,
+        ];
+
+        for compression in codecs {
+            let inflated = (0..10)
+                .fold(
+                    crate::record::inflated::Batch::builder()
+                        .attributes(compression.clone().into())
+                        .last_offset_delta(9),
+                    |builder, offset_delta| {
+                        builder.record(
+                            Record::builder()
+                                .offset_delta(offset_delta)
+                                .key(format!("k{offset_delta}").as_bytes().into())
+                                .value(Bytes::from_static(LOREM).into()),
+                        )
+                    },
+                )
+                .build()?;
+
+            let deflated = Batch::try_from(inflated.clone())?;
+            assert_eq!(compression, deflated.compression()?);
+
+            let mut records = deflated.records();
+            let lazy = records.by_ref().collect::<Result<Vec<_>>>()?;
+            assert_eq!(inflated.records, lazy, "{compression:?}");
+            assert_eq!(0, records.remaining_bytes(), "{compression:?}");
+
+            let records: Vec<Record> = deflated.try_into()?;
+            assert_eq!(inflated.records, records, "{compression:?}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn records_truncated() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let deflated = Batch::try_from(
+            crate::record::inflated::Batch::builder()
+                .record(Record::builder().value(Bytes::from_static(LOREM).into()))
+                .record(
+                    Record::builder()
+                        .offset_delta(1)
+                        .value(Bytes::from_static(LOREM).into()),
+                )
+                .build()?,
+        )?;
+
+        let truncated = Batch {
+            record_data: deflated.record_data.slice(..deflated.record_data.len() - 1),
+            ..deflated
+        };
+
+        let mut records = truncated.records();
+        assert!(matches!(
+            records.next(),
+            Some(Ok(Record {
+                offset_delta: 0,
+                ..
+            }))
+        ));
+        assert!(matches!(records.next(), Some(Err(Error::Io(_)))));
+        assert!(records.next().is_none());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_level() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let inflated = crate::record::inflated::Batch::builder()
+            .attributes(Compression::Zstd.into())
+            .record(Record::builder().value(Bytes::from_static(LOREM).into()))
+            .build()?;
+
+        for level in [1, 19] {
+            let deflated = Batch::deflate(inflated.clone(), Options::default().level(Some(level)))?;
+
+            // the record data is a plain zstd frame
+            assert!(deflated.record_data.starts_with(&[40, 181, 47, 253]));
+
+            let records: Vec<Record> = deflated.try_into()?;
+            assert_eq!(inflated.records, records);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn lz4_round_trip() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let inflated = crate::record::inflated::Batch::builder()
+            .attributes(Compression::Lz4.into())
+            .record(
+                Record::builder()
+                    .key(b"lorem".as_slice().into())
+                    .value(Bytes::from_static(LOREM).into()),
+            )
+            .build()?;
+
+        let deflated = Batch::try_from(inflated.clone())?;
+        assert_eq!(Compression::Lz4, deflated.compression()?);
+        assert_eq!(inflated.attributes, deflated.attributes);
+        assert_eq!(
+            deflated.crc,
+            crate::record::inflated::Batch::try_from(deflated.clone())
+                .and_then(Batch::try_from)?
+                .crc
+        );
+
+        let records: Vec<Record> = deflated.try_into()?;
+        assert_eq!(inflated.records, records);
+
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_lz4() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let batch = Batch {
+            magic: 1,
+            attributes: Compression::Lz4.into(),
+            record_count: 1,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            batch.records().next(),
+            Some(Err(Error::UnsupportedCompression(Compression::Lz4)))
+        ));
+
+        assert!(matches!(
+            Vec::<Record>::try_from(batch),
+            Err(Error::UnsupportedCompression(Compression::Lz4))
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "snappy")]
     #[test]
     fn decode_snappy() -> Result<()> {
         let _guard = init_tracing()?;
@@ -706,7 +1394,7 @@ mod tests {
             Compression::try_from(batch.attributes)?
         );
 
-        let records: Vec<Record> = batch.try_into()?;
+        let records: Vec<Record> = batch.clone().try_into()?;
 
         assert_eq!(
             vec![Record {
@@ -721,6 +1409,45 @@ mod tests {
             records
         );
 
+        // the same raw snappy block wrapped in xerial framing
+        let mut xerial = BytesMut::new();
+        xerial.put_slice(b"\x82SNAPPY\0");
+        xerial.put_i32(1);
+        xerial.put_i32(1);
+        xerial.put_i32(i32::try_from(batch.record_data.len())?);
+        xerial.put_slice(&batch.record_data[..]);
+
+        let framed = Batch {
+            record_data: xerial.freeze(),
+            ..batch
+        };
+
+        assert_eq!(records, Vec::<Record>::try_from(framed)?);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "snappy")]
+    #[test]
+    fn snappy_round_trip() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let inflated = crate::record::inflated::Batch::builder()
+            .attributes(Compression::Snappy.into())
+            .record(
+                Record::builder()
+                    .key(b"lorem".as_slice().into())
+                    .value(Bytes::from_static(LOREM).into()),
+            )
+            .build()?;
+
+        let deflated = Batch::try_from(inflated.clone())?;
+        assert_eq!(Compression::Snappy, deflated.compression()?);
+        assert!(deflated.record_data.starts_with(b"\x82SNAPPY\0"));
+
+        let records: Vec<Record> = deflated.try_into()?;
+        assert_eq!(inflated.records, records);
+
         Ok(())
     }
 }
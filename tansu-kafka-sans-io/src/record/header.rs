@@ -32,6 +32,16 @@ impl Header {
     pub fn builder() -> Builder {
         Builder::default()
     }
+
+    #[must_use]
+    pub fn key(&self) -> Option<&[u8]> {
+        self.key.as_deref()
+    }
+
+    #[must_use]
+    pub fn value(&self) -> Option<&[u8]> {
+        self.value.as_deref()
+    }
 }
 
 impl From<Builder> for Header {
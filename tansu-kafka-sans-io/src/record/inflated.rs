@@ -15,7 +15,7 @@
 
 use crate::{
     primitive::ByteSize,
-    record::{codec::Sequence, deflated, Record},
+    record::{codec::Sequence, deflated, BatchAttributes, Record, Resolved, TimestampType},
     Compression, Encoder, Error, Result,
 };
 use bytes::Bytes;
@@ -113,8 +113,28 @@ pub struct Compaction {
 }
 
 impl Batch {
+    #[must_use]
+    pub fn batch_attributes(&self) -> BatchAttributes {
+        BatchAttributes::from(self.attributes)
+    }
+
     pub fn compression(&self) -> Result<Compression> {
-        Compression::try_from(self.attributes)
+        self.batch_attributes().compression()
+    }
+
+    #[must_use]
+    pub fn timestamp_type(&self) -> TimestampType {
+        self.batch_attributes().timestamp_type()
+    }
+
+    #[must_use]
+    pub fn is_transactional(&self) -> bool {
+        self.batch_attributes().is_transactional()
+    }
+
+    #[must_use]
+    pub fn is_control(&self) -> bool {
+        self.batch_attributes().is_control()
     }
 
     #[must_use]
@@ -130,11 +150,18 @@ impl Batch {
         self.base_offset + i64::from(self.last_offset_delta)
     }
 
+    /// The records of this batch, with their offsets and timestamps resolved.
+    pub fn resolved(&self) -> impl Iterator<Item = Resolved<'_>> {
+        self.records
+            .iter()
+            .map(|record| record.resolve(self.base_offset, self.base_timestamp))
+    }
+
     pub fn keys(&self) -> BTreeSet<Bytes> {
         self.records
             .iter()
             .fold(BTreeSet::new(), |mut acc, record| {
-                if let Some(key) = record.key() {
+                if let Some(key) = record.key.clone() {
                     _ = acc.insert(key);
                 }
 
@@ -147,7 +174,7 @@ impl Batch {
         let mut records = 0;
 
         for record in self.records.iter() {
-            if let Some(key) = record.key() {
+            if let Some(key) = record.key.clone() {
                 if head.contains(&key) {
                     records += 1;
                     continue;
@@ -277,6 +304,38 @@ impl Builder {
         self
     }
 
+    #[must_use]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.attributes = BatchAttributes::from(self.attributes)
+            .with_compression(compression)
+            .into();
+        self
+    }
+
+    #[must_use]
+    pub fn timestamp_type(mut self, timestamp_type: TimestampType) -> Self {
+        self.attributes = BatchAttributes::from(self.attributes)
+            .with_timestamp_type(timestamp_type)
+            .into();
+        self
+    }
+
+    #[must_use]
+    pub fn transactional(mut self, transactional: bool) -> Self {
+        self.attributes = BatchAttributes::from(self.attributes)
+            .with_transactional(transactional)
+            .into();
+        self
+    }
+
+    #[must_use]
+    pub fn control(mut self, control: bool) -> Self {
+        self.attributes = BatchAttributes::from(self.attributes)
+            .with_control(control)
+            .into();
+        self
+    }
+
     #[must_use]
     pub fn last_offset_delta(mut self, last_offset_delta: i32) -> Self {
         self.last_offset_delta = last_offset_delta;
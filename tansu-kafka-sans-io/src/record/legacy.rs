@@ -0,0 +1,306 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Message sets in the legacy (magic 0 and 1) format still sent by old
+// producers: https://kafka.apache.org/documentation/#messageset
+//
+// A message set is only ever decoded, and is upgraded into a single magic 2
+// batch so that storage only sees record batches.
+
+use std::io::{self, Read};
+
+use bytes::{Buf, Bytes};
+use crc::{Crc, CRC_32_ISO_HDLC};
+use tracing::debug;
+
+use crate::{
+    record::{inflated, BatchAttributes, Record, TimestampType},
+    Compression, Error, Result,
+};
+
+// offset and message size
+const LOG_OVERHEAD: usize = size_of::<i64>() + size_of::<i32>();
+
+// crc, magic, attributes, and the lengths of a null key and value
+const MINIMUM_MESSAGE_SIZE: usize =
+    size_of::<u32>() + size_of::<i8>() + size_of::<i8>() + size_of::<i32>() + size_of::<i32>();
+
+// a message without a timestamp, as in magic 0
+const NO_TIMESTAMP: i64 = -1;
+
+/// A message in the legacy format.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Message {
+    pub offset: i64,
+    pub magic: i8,
+    pub attributes: i8,
+    pub timestamp: Option<i64>,
+    pub key: Option<Bytes>,
+    pub value: Option<Bytes>,
+}
+
+impl Message {
+    #[must_use]
+    pub fn batch_attributes(&self) -> BatchAttributes {
+        BatchAttributes::from(i16::from(self.attributes))
+    }
+}
+
+/// The messages of a legacy message set, with any compressed wrapper
+/// messages replaced by the messages they contain.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MessageSet {
+    pub messages: Vec<Message>,
+}
+
+impl MessageSet {
+    /// Whether the encoded bytes are a legacy message set rather than record
+    /// batches, using the magic that both formats have at the same position.
+    #[must_use]
+    pub fn is_legacy(encoded: &[u8]) -> bool {
+        encoded
+            .get(LOG_OVERHEAD + size_of::<u32>())
+            .is_some_and(|magic| (*magic as i8) < 2)
+    }
+
+    /// Decode a legacy message set, as [`MessageSet::try_from`], failing with
+    /// [`Error::InflatedTooLarge`] when its compressed wrapper messages
+    /// inflate to more than `maximum` bytes in total.
+    pub fn decode_within(encoded: Bytes, maximum: usize) -> Result<Self> {
+        let mut remaining = maximum;
+
+        Self::decode(encoded, false, maximum, &mut remaining).map(|messages| Self { messages })
+    }
+
+    fn decode(
+        mut encoded: Bytes,
+        wrapped: bool,
+        maximum: usize,
+        remaining: &mut usize,
+    ) -> Result<Vec<Message>> {
+        let mut messages = Vec::new();
+
+        while encoded.has_remaining() {
+            let offset = get_i64(&mut encoded)?;
+
+            let size = get_i32(&mut encoded)?;
+            let mut message = usize::try_from(size)
+                .ok()
+                .filter(|size| *size >= MINIMUM_MESSAGE_SIZE)
+                .ok_or(Error::InvalidMessageSize(size))
+                .and_then(|size| slice(&mut encoded, size))?;
+
+            let expected = message.get_u32();
+            let computed = Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&message[..]);
+
+            if expected != computed {
+                return Err(Error::CrcMismatch { expected, computed });
+            }
+
+            let magic = message.get_i8();
+            let attributes = message.get_i8();
+
+            let timestamp = match magic {
+                0 => None,
+                1 => Some(get_i64(&mut message)?),
+                otherwise => return Err(Error::UnsupportedMagic(otherwise)),
+            };
+
+            let key = bytes(&mut message)?;
+            let value = bytes(&mut message)?;
+
+            let message = Message {
+                offset,
+                magic,
+                attributes,
+                timestamp,
+                key,
+                value,
+            };
+
+            match message.batch_attributes().compression()? {
+                Compression::None => messages.push(message),
+
+                // a compressed message is never wrapped more than once
+                _ if wrapped => return Err(Error::MalformedMessageSet),
+
+                compression => {
+                    messages.append(&mut Self::unwrap(message, compression, maximum, remaining)?)
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+
+    // every wrapper in a message set inflates from the same remaining maximum
+    fn unwrap(
+        wrapper: Message,
+        compression: Compression,
+        maximum: usize,
+        remaining: &mut usize,
+    ) -> Result<Vec<Message>> {
+        debug!(?wrapper, ?compression);
+
+        // lz4 in magic 0 and 1 used an incorrect frame header checksum
+        // (KAFKA-3160), only the corrected v2 framing is supported
+        if compression == Compression::Lz4 {
+            return Err(Error::UnsupportedCompression(compression));
+        }
+
+        let mut inflated = Vec::new();
+        _ = compression
+            .inflator(
+                wrapper.value.clone().unwrap_or_default().reader(),
+                *remaining,
+            )?
+            .read_to_end(&mut inflated)?;
+
+        *remaining = remaining
+            .checked_sub(inflated.len())
+            .ok_or(Error::InflatedTooLarge { maximum })?;
+
+        let mut messages = Self::decode(Bytes::from(inflated), true, maximum, remaining)?;
+
+        if wrapper.magic == 1 {
+            // the inner offsets of magic 1 are relative, with the wrapper
+            // having the absolute offset of the last inner message
+            let last = messages.last().map_or(0, |message| message.offset);
+
+            let log_append_time =
+                wrapper.batch_attributes().timestamp_type() == TimestampType::LogAppendTime;
+
+            for message in &mut messages {
+                message.offset += wrapper.offset - last;
+
+                if log_append_time {
+                    message.timestamp = wrapper.timestamp;
+                }
+            }
+        }
+
+        Ok(messages)
+    }
+}
+
+impl TryFrom<Bytes> for MessageSet {
+    type Error = Error;
+
+    fn try_from(encoded: Bytes) -> Result<Self, Self::Error> {
+        Self::decode_within(encoded, usize::MAX)
+    }
+}
+
+impl TryFrom<MessageSet> for inflated::Batch {
+    type Error = Error;
+
+    fn try_from(message_set: MessageSet) -> Result<Self, Self::Error> {
+        let Some(first) = message_set.messages.first() else {
+            return inflated::Batch::builder().build();
+        };
+
+        let base_offset = first.offset;
+
+        let timestamps = message_set
+            .messages
+            .iter()
+            .map(|message| message.timestamp.unwrap_or(NO_TIMESTAMP));
+
+        let base_timestamp = timestamps.clone().min().unwrap_or(NO_TIMESTAMP);
+        let max_timestamp = timestamps.max().unwrap_or(NO_TIMESTAMP);
+
+        let last_offset_delta = message_set
+            .messages
+            .last()
+            .map_or(Ok(0), |last| i32::try_from(last.offset - base_offset))?;
+
+        let timestamp_type = first.batch_attributes().timestamp_type();
+
+        message_set
+            .messages
+            .into_iter()
+            .try_fold(
+                inflated::Batch::builder()
+                    .base_offset(base_offset)
+                    .last_offset_delta(last_offset_delta)
+                    .base_timestamp(base_timestamp)
+                    .max_timestamp(max_timestamp)
+                    .timestamp_type(timestamp_type)
+                    .producer_epoch(-1)
+                    .base_sequence(-1),
+                |builder, message| {
+                    i32::try_from(message.offset - base_offset).map(|offset_delta| {
+                        builder.record(
+                            Record::builder()
+                                .offset_delta(offset_delta)
+                                .timestamp_delta(
+                                    message.timestamp.unwrap_or(NO_TIMESTAMP) - base_timestamp,
+                                )
+                                .key(message.key.into())
+                                .value(message.value.into()),
+                        )
+                    })
+                },
+            )
+            .map_err(Into::into)
+            .and_then(inflated::Builder::build)
+    }
+}
+
+fn truncated() -> Error {
+    Error::Io(io::Error::from(io::ErrorKind::UnexpectedEof))
+}
+
+fn slice(buf: &mut Bytes, length: usize) -> Result<Bytes> {
+    if buf.remaining() >= length {
+        Ok(buf.split_to(length))
+    } else {
+        Err(Error::Incomplete {
+            needed: Some(length - buf.remaining()),
+        })
+    }
+}
+
+fn get_i32(buf: &mut Bytes) -> Result<i32> {
+    if buf.remaining() >= size_of::<i32>() {
+        Ok(buf.get_i32())
+    } else {
+        Err(truncated())
+    }
+}
+
+fn get_i64(buf: &mut Bytes) -> Result<i64> {
+    if buf.remaining() >= size_of::<i64>() {
+        Ok(buf.get_i64())
+    } else {
+        Err(truncated())
+    }
+}
+
+fn bytes(buf: &mut Bytes) -> Result<Option<Bytes>> {
+    match get_i32(buf)? {
+        -1 => Ok(None),
+        length => usize::try_from(length)
+            .map_err(Into::into)
+            .and_then(|length| {
+                if buf.remaining() >= length {
+                    Ok(buf.split_to(length))
+                } else {
+                    Err(truncated())
+                }
+            })
+            .map(Some),
+    }
+}
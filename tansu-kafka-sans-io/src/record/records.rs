@@ -0,0 +1,430 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// The records field of a fetch response or produce request, kept as the
+// bytes that were received. Batch headers are only parsed when asked for,
+// and the record data of a batch is a slice of the original bytes.
+
+use std::{
+    fmt::Formatter,
+    io::{self, Read},
+};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use crc::{Crc, CRC_32_ISCSI};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    record::{
+        deflated::{self, Batch, FIXED_BATCH_LENGTH},
+        inflated, BatchAttributes,
+    },
+    Encoder, Error, Result,
+};
+
+// base offset and batch length, which are not included in the batch length
+const LOG_OVERHEAD: usize = size_of::<i64>() + size_of::<i32>();
+
+// the CRC covers the attributes through to the end of the batch
+const CRC_OFFSET: usize = LOG_OVERHEAD + size_of::<i32>() + size_of::<i8>() + size_of::<u32>();
+
+const HEADER_LENGTH: usize = LOG_OVERHEAD + FIXED_BATCH_LENGTH;
+
+/// The fixed length header of a batch, without its record data.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BatchHeader {
+    pub base_offset: i64,
+    pub batch_length: i32,
+    pub partition_leader_epoch: i32,
+    pub magic: i8,
+    pub crc: u32,
+    pub attributes: i16,
+    pub last_offset_delta: i32,
+    pub base_timestamp: i64,
+    pub max_timestamp: i64,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub base_sequence: i32,
+    pub record_count: u32,
+}
+
+impl BatchHeader {
+    #[must_use]
+    pub fn batch_attributes(&self) -> BatchAttributes {
+        BatchAttributes::from(self.attributes)
+    }
+
+    // the length of the encoded batch, including its header
+    fn size(&self) -> Result<usize> {
+        usize::try_from(self.batch_length)
+            .ok()
+            .filter(|batch_length| *batch_length >= FIXED_BATCH_LENGTH)
+            .map(|batch_length| batch_length + LOG_OVERHEAD)
+            .ok_or(Error::InvalidBatchLength(self.batch_length))
+    }
+
+    fn parse(mut buf: &[u8]) -> Self {
+        Self {
+            base_offset: buf.get_i64(),
+            batch_length: buf.get_i32(),
+            partition_leader_epoch: buf.get_i32(),
+            magic: buf.get_i8(),
+            crc: buf.get_u32(),
+            attributes: buf.get_i16(),
+            last_offset_delta: buf.get_i32(),
+            base_timestamp: buf.get_i64(),
+            max_timestamp: buf.get_i64(),
+            producer_id: buf.get_i64(),
+            producer_epoch: buf.get_i16(),
+            base_sequence: buf.get_i32(),
+            record_count: buf.get_u32(),
+        }
+    }
+
+    fn into_batch(self, record_data: Bytes) -> Batch {
+        Batch {
+            base_offset: self.base_offset,
+            batch_length: self.batch_length,
+            partition_leader_epoch: self.partition_leader_epoch,
+            magic: self.magic,
+            crc: self.crc,
+            attributes: self.attributes,
+            last_offset_delta: self.last_offset_delta,
+            base_timestamp: self.base_timestamp,
+            max_timestamp: self.max_timestamp,
+            producer_id: self.producer_id,
+            producer_epoch: self.producer_epoch,
+            base_sequence: self.base_sequence,
+            record_count: self.record_count,
+            record_data,
+        }
+    }
+}
+
+/// Record batches retained as encoded bytes.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Records(Bytes);
+
+impl Records {
+    #[must_use]
+    pub fn new(encoded: Bytes) -> Self {
+        Self(encoded)
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The header of each batch, without decoding any record data.
+    pub fn headers(&self) -> Result<Vec<BatchHeader>> {
+        self.split()
+            .map(|split| split.map(|(header, _)| header))
+            .collect()
+    }
+
+    /// Decode each batch, the record data of which shares these bytes.
+    pub fn batches(&self) -> Result<Vec<Batch>> {
+        self.split()
+            .map(|split| {
+                split.map(|(header, encoded)| header.into_batch(encoded.slice(HEADER_LENGTH..)))
+            })
+            .collect()
+    }
+
+    /// Verify the length and CRC of every batch.
+    pub fn verify(&self) -> Result<()> {
+        let crc = Crc::<u32>::new(&CRC_32_ISCSI);
+
+        self.split().try_for_each(|split| {
+            split.and_then(|(header, encoded)| {
+                let computed = crc.checksum(&encoded[CRC_OFFSET..]);
+
+                if computed == header.crc {
+                    Ok(())
+                } else {
+                    Err(Error::CrcMismatch {
+                        expected: header.crc,
+                        computed,
+                    })
+                }
+            })
+        })
+    }
+
+    fn split(&self) -> Split {
+        Split {
+            remaining: self.0.clone(),
+        }
+    }
+}
+
+// splits the encoded bytes into the header and bytes of each batch
+struct Split {
+    remaining: Bytes,
+}
+
+impl Split {
+    fn next_batch(&mut self) -> Result<(BatchHeader, Bytes)> {
+        if self.remaining.len() < HEADER_LENGTH {
+            return Err(Error::Incomplete {
+                needed: Some(HEADER_LENGTH - self.remaining.len()),
+            });
+        }
+
+        let header = BatchHeader::parse(&self.remaining[..HEADER_LENGTH]);
+        let size = header.size()?;
+
+        if size > self.remaining.len() {
+            Err(Error::Incomplete {
+                needed: Some(size - self.remaining.len()),
+            })
+        } else {
+            Ok((header, self.remaining.split_to(size)))
+        }
+    }
+}
+
+impl Iterator for Split {
+    type Item = Result<(BatchHeader, Bytes)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            None
+        } else {
+            Some(self.next_batch().inspect_err(|_| self.remaining.clear()))
+        }
+    }
+}
+
+/// Batches read one at a time from `size_in_bytes` of records, so that
+/// only a single batch is held in memory rather than the whole field.
+///
+/// A trailing partial batch is an [`Error::Incomplete`], after which the
+/// rest of the records are skipped leaving the reader at their end.
+#[derive(Debug)]
+pub struct Reader<R> {
+    reader: R,
+    remaining: usize,
+}
+
+impl<R: Read> Reader<R> {
+    #[must_use]
+    pub fn new(reader: R, size_in_bytes: usize) -> Self {
+        Self {
+            reader,
+            remaining: size_in_bytes,
+        }
+    }
+
+    /// The number of bytes of records not yet read.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut buf = Vec::with_capacity(length);
+
+        let read = (&mut self.reader)
+            .take(u64::try_from(length)?)
+            .read_to_end(&mut buf)?;
+
+        self.remaining -= read;
+
+        if read == length {
+            Ok(buf)
+        } else {
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+        }
+    }
+
+    fn skip(&mut self) -> Result<()> {
+        let length = u64::try_from(self.remaining)?;
+        self.remaining = 0;
+
+        let skipped = io::copy(&mut (&mut self.reader).take(length), &mut io::sink())?;
+
+        if skipped == length {
+            Ok(())
+        } else {
+            Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+        }
+    }
+
+    fn next_batch(&mut self) -> Result<Batch> {
+        if self.remaining < HEADER_LENGTH {
+            let needed = HEADER_LENGTH - self.remaining;
+            return self.skip().and(Err(Error::Incomplete {
+                needed: Some(needed),
+            }));
+        }
+
+        let header = self
+            .read(HEADER_LENGTH)
+            .map(|buf| BatchHeader::parse(&buf))?;
+        let size = header.size()?;
+
+        if size - HEADER_LENGTH > self.remaining {
+            let needed = size - HEADER_LENGTH - self.remaining;
+            return self.skip().and(Err(Error::Incomplete {
+                needed: Some(needed),
+            }));
+        }
+
+        self.read(size - HEADER_LENGTH)
+            .map(|record_data| header.into_batch(Bytes::from(record_data)))
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Batch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            None
+        } else {
+            Some(self.next_batch().inspect_err(|_| self.remaining = 0))
+        }
+    }
+}
+
+impl From<Bytes> for Records {
+    fn from(encoded: Bytes) -> Self {
+        Self(encoded)
+    }
+}
+
+impl From<Records> for Bytes {
+    fn from(records: Records) -> Self {
+        records.0
+    }
+}
+
+impl TryFrom<Batch> for Records {
+    type Error = Error;
+
+    fn try_from(batch: Batch) -> Result<Self, Self::Error> {
+        Self::try_from(deflated::Frame {
+            batches: vec![batch],
+        })
+    }
+}
+
+impl TryFrom<deflated::Frame> for Records {
+    type Error = Error;
+
+    fn try_from(frame: deflated::Frame) -> Result<Self, Self::Error> {
+        let mut writer = BytesMut::new().writer();
+
+        for batch in frame.batches {
+            let mut encoder = Encoder::new(&mut writer);
+            batch.serialize(&mut encoder)?;
+        }
+
+        Ok(Self(writer.into_inner().freeze()))
+    }
+}
+
+impl TryFrom<inflated::Frame> for Records {
+    type Error = Error;
+
+    fn try_from(inflated: inflated::Frame) -> Result<Self, Self::Error> {
+        deflated::Frame::try_from(inflated).and_then(Self::try_from)
+    }
+}
+
+impl TryFrom<Records> for deflated::Frame {
+    type Error = Error;
+
+    fn try_from(records: Records) -> Result<Self, Self::Error> {
+        records.batches().map(|batches| Self { batches })
+    }
+}
+
+impl Serialize for Records {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[cfg(feature = "json")]
+        if serializer.is_human_readable() {
+            return crate::json::bytes::serialize(&self.0, serializer);
+        }
+
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Records {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[cfg(feature = "json")]
+        if deserializer.is_human_readable() {
+            return crate::json::bytes::deserialize(deserializer).map(Records);
+        }
+
+        struct V;
+
+        impl<'de> Visitor<'de> for V {
+            type Value = Records;
+
+            fn expecting(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+                formatter.write_str(stringify!(Records))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Records(Bytes::copy_from_slice(v)))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Records(Bytes::from(v)))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(V)
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Snappy record data is either raw snappy, or uses the xerial block framing:
+// https://github.com/xerial/snappy-java/tree/master?tab=readme-ov-file#compatibility-notes
+
+use crate::{Error, Result};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::io;
+use tracing::debug;
+
+const XERIAL_MAGIC: &[u8] = b"\x82SNAPPY\0";
+const XERIAL_VERSION: i32 = 1;
+const XERIAL_COMPATIBLE_VERSION: i32 = 1;
+const XERIAL_HEADER_LENGTH: usize = XERIAL_MAGIC.len() + size_of::<i32>() + size_of::<i32>();
+const XERIAL_BLOCK_SIZE: usize = 32 * 1024;
+
+pub(crate) fn compress(inflated: &[u8]) -> Result<Bytes> {
+    let mut encoder = snap::raw::Encoder::new();
+
+    let mut deflated =
+        BytesMut::with_capacity(XERIAL_HEADER_LENGTH + snap::raw::max_compress_len(inflated.len()));
+    deflated.put_slice(XERIAL_MAGIC);
+    deflated.put_i32(XERIAL_VERSION);
+    deflated.put_i32(XERIAL_COMPATIBLE_VERSION);
+
+    for block in inflated.chunks(XERIAL_BLOCK_SIZE) {
+        let compressed = encoder.compress_vec(block).map_err(io::Error::from)?;
+        deflated.put_i32(i32::try_from(compressed.len())?);
+        deflated.put_slice(&compressed[..]);
+    }
+
+    Ok(deflated.freeze())
+}
+
+// the inflated length of a raw block, which the decoder allocates before
+// decompressing, is checked against what remains of the maximum
+fn inflated_len(block: &[u8], remaining: usize, maximum: usize) -> Result<usize> {
+    snap::raw::decompress_len(block)
+        .map_err(io::Error::from)
+        .map_err(Into::into)
+        .and_then(|length| {
+            if length > remaining {
+                Err(Error::InflatedTooLarge { maximum })
+            } else {
+                Ok(length)
+            }
+        })
+}
+
+pub(crate) fn decompress(deflated: &[u8], maximum: usize) -> Result<Bytes> {
+    let mut decoder = snap::raw::Decoder::new();
+
+    if deflated.len() >= XERIAL_HEADER_LENGTH && deflated.starts_with(XERIAL_MAGIC) {
+        let mut blocks = &deflated[XERIAL_HEADER_LENGTH..];
+        let mut inflated = BytesMut::new();
+
+        while blocks.has_remaining() {
+            if blocks.remaining() < size_of::<i32>() {
+                return Err(Error::TruncatedSnappyBlock);
+            }
+
+            let length = usize::try_from(blocks.get_i32())?;
+            debug!(length, remaining = blocks.remaining());
+
+            if blocks.remaining() < length {
+                return Err(Error::TruncatedSnappyBlock);
+            }
+
+            _ = inflated_len(&blocks[..length], maximum - inflated.len(), maximum)?;
+
+            decoder
+                .decompress_vec(&blocks[..length])
+                .map(|block| inflated.put_slice(&block[..]))
+                .map_err(io::Error::from)?;
+
+            blocks.advance(length);
+        }
+
+        Ok(inflated.freeze())
+    } else {
+        _ = inflated_len(deflated, maximum, maximum)?;
+
+        decoder
+            .decompress_vec(deflated)
+            .map(Bytes::from)
+            .map_err(io::Error::from)
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOREM: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do \
+    eiusmod tempor incididunt ut labore et dolore magna aliqua.";
+
+    #[test]
+    fn xerial_round_trip() -> Result<()> {
+        let deflated = compress(LOREM)?;
+        assert!(deflated.starts_with(XERIAL_MAGIC));
+        assert_eq!(LOREM, &decompress(&deflated[..], usize::MAX)?[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn xerial_multiple_blocks() -> Result<()> {
+        let inflated = LOREM.repeat((2 * XERIAL_BLOCK_SIZE / LOREM.len()) + 1);
+
+        let deflated = compress(&inflated[..])?;
+        assert_eq!(inflated, &decompress(&deflated[..], usize::MAX)?[..]);
+        Ok(())
+    }
+
+    // Neither librdkafka nor a Java snappy library is available to capture a
+    // stream from, so this one is assembled by hand from the framing of
+    // snappy-java's SnappyOutputStream and the raw snappy format, rather
+    // than with compress. The second block has a copy as well as a literal.
+    #[test]
+    fn xerial_fixture() -> Result<()> {
+        let deflated = [
+            // magic, version and compatible version
+            0x82, b'S', b'N', b'A', b'P', b'P', b'Y', 0, 0, 0, 0, 1, 0, 0, 0, 1,
+            // 9 bytes: an inflated length of 7, with a literal of 7
+            0, 0, 0, 9, 7, 0x18, b'h', b'e', b'l', b'l', b'o', b',', b' ',
+            // 7 bytes: an inflated length of 12, with a literal of 3 and a
+            // copy of 9 from an offset of 3
+            0, 0, 0, 7, 12, 0x08, b'a', b'b', b'c', 0x15, 3,
+        ];
+
+        assert_eq!(
+            b"hello, abcabcabcabc",
+            &decompress(&deflated[..], usize::MAX)?[..]
+        );
+
+        assert!(matches!(
+            decompress(&deflated[..], 18),
+            Err(Error::InflatedTooLarge { maximum: 18 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn raw() -> Result<()> {
+        let deflated = snap::raw::Encoder::new()
+            .compress_vec(LOREM)
+            .map_err(io::Error::from)?;
+
+        assert_eq!(LOREM, &decompress(&deflated[..], usize::MAX)?[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn beyond_maximum() -> Result<()> {
+        let inflated = LOREM.repeat((2 * XERIAL_BLOCK_SIZE / LOREM.len()) + 1);
+
+        let xerial = compress(&inflated[..])?;
+
+        assert!(matches!(
+            decompress(&xerial[..], XERIAL_BLOCK_SIZE),
+            Err(Error::InflatedTooLarge { maximum }) if maximum == XERIAL_BLOCK_SIZE
+        ));
+
+        let raw = snap::raw::Encoder::new()
+            .compress_vec(&inflated[..])
+            .map_err(io::Error::from)?;
+
+        assert!(matches!(
+            decompress(&raw[..], inflated.len() - 1),
+            Err(Error::InflatedTooLarge { .. })
+        ));
+
+        assert_eq!(inflated, &decompress(&raw[..], inflated.len())?[..]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn xerial_truncated() -> Result<()> {
+        let deflated = compress(LOREM)?;
+
+        assert!(matches!(
+            decompress(&deflated[..deflated.len() - 1], usize::MAX),
+            Err(Error::TruncatedSnappyBlock)
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,807 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Builders for the response bodies most often constructed by a broker.
+//
+// Version gated fields are given their schema defaults, so that a built
+// body encodes at any version of its API. When the target version is
+// known, it may be given to the builder with `api_version`, and `build`
+// then checks that every field mandatory at that version is present.
+
+use crate::{
+    fetch_response::{self, FetchableTopicResponse, PartitionData},
+    find_coordinator_response::Coordinator,
+    metadata_response::{MetadataResponseBroker, MetadataResponsePartition, MetadataResponseTopic},
+    offset_fetch_response::{
+        OffsetFetchResponseGroup, OffsetFetchResponsePartition, OffsetFetchResponsePartitions,
+        OffsetFetchResponseTopic, OffsetFetchResponseTopics,
+    },
+    primitive::uuid::Uuid,
+    produce_response::{self, PartitionProduceResponse, TopicProduceResponse},
+    record::Records,
+    Body, Error, ErrorCode, Result,
+};
+
+fn missing(name: &'static str, api_version: i16) -> Error {
+    Error::MissingField { name, api_version }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ProduceResponse {
+    throttle_time_ms: i32,
+    responses: Vec<TopicProduceResponse>,
+    node_endpoints: Vec<produce_response::NodeEndpoint>,
+}
+
+impl ProduceResponse {
+    pub fn builder() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn throttle_time_ms(self, throttle_time_ms: i32) -> Self {
+        Self {
+            throttle_time_ms,
+            ..self
+        }
+    }
+
+    /// Add the partition responses for `name`, built by `f`.
+    #[must_use]
+    pub fn response<F>(self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(TopicProduce) -> TopicProduce,
+    {
+        let topic = f(TopicProduce {
+            name: name.into(),
+            partitions: vec![],
+        });
+
+        self.responses([TopicProduceResponse {
+            name: topic.name,
+            partition_responses: Some(topic.partitions),
+        }])
+    }
+
+    #[must_use]
+    pub fn responses<I>(mut self, responses: I) -> Self
+    where
+        I: IntoIterator<Item = TopicProduceResponse>,
+    {
+        self.responses.extend(responses);
+        self
+    }
+
+    #[must_use]
+    pub fn node_endpoint(self, node_id: i32, host: &str, port: i32) -> Self {
+        self.node_endpoints([produce_response::NodeEndpoint {
+            node_id,
+            host: host.into(),
+            port,
+            rack: None,
+        }])
+    }
+
+    #[must_use]
+    pub fn node_endpoints<I>(mut self, node_endpoints: I) -> Self
+    where
+        I: IntoIterator<Item = produce_response::NodeEndpoint>,
+    {
+        self.node_endpoints.extend(node_endpoints);
+        self
+    }
+
+    /// Every field of a produce response has a default, so the body is
+    /// valid at all versions.
+    pub fn build(self) -> Result<Body> {
+        Ok(Body::ProduceResponse {
+            responses: Some(self.responses),
+            throttle_time_ms: Some(self.throttle_time_ms),
+            node_endpoints: Some(self.node_endpoints),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TopicProduce {
+    name: String,
+    partitions: Vec<PartitionProduceResponse>,
+}
+
+impl TopicProduce {
+    #[must_use]
+    pub fn partition(self, index: i32, error_code: ErrorCode, base_offset: i64) -> Self {
+        self.partition_response(PartitionProduceResponse {
+            index,
+            error_code: error_code.into(),
+            base_offset,
+            log_append_time_ms: Some(-1),
+            log_start_offset: Some(-1),
+            record_errors: Some(vec![]),
+            error_message: None,
+            current_leader: None,
+        })
+    }
+
+    #[must_use]
+    pub fn partition_response(mut self, partition: PartitionProduceResponse) -> Self {
+        self.partitions.push(partition);
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FetchResponse {
+    api_version: Option<i16>,
+    throttle_time_ms: i32,
+    error_code: ErrorCode,
+    session_id: i32,
+    responses: Vec<FetchableTopicResponse>,
+    node_endpoints: Vec<fetch_response::NodeEndpoint>,
+}
+
+impl Default for FetchResponse {
+    fn default() -> Self {
+        Self {
+            api_version: None,
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None,
+            session_id: 0,
+            responses: vec![],
+            node_endpoints: vec![],
+        }
+    }
+}
+
+impl FetchResponse {
+    pub fn builder() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn api_version(self, api_version: i16) -> Self {
+        Self {
+            api_version: Some(api_version),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn throttle_time_ms(self, throttle_time_ms: i32) -> Self {
+        Self {
+            throttle_time_ms,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn error_code(self, error_code: ErrorCode) -> Self {
+        Self { error_code, ..self }
+    }
+
+    #[must_use]
+    pub fn session_id(self, session_id: i32) -> Self {
+        Self { session_id, ..self }
+    }
+
+    /// Add the partition data for the topic `name`, built by `f`.
+    #[must_use]
+    pub fn response<F>(self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(FetchTopic) -> FetchTopic,
+    {
+        let topic = f(FetchTopic {
+            name: name.into(),
+            topic_id: None,
+            partitions: vec![],
+        });
+
+        self.responses([FetchableTopicResponse {
+            topic: Some(topic.name),
+            topic_id: topic.topic_id,
+            partitions: Some(topic.partitions),
+        }])
+    }
+
+    #[must_use]
+    pub fn responses<I>(mut self, responses: I) -> Self
+    where
+        I: IntoIterator<Item = FetchableTopicResponse>,
+    {
+        self.responses.extend(responses);
+        self
+    }
+
+    #[must_use]
+    pub fn node_endpoint(self, node_id: i32, host: &str, port: i32) -> Self {
+        self.node_endpoints([fetch_response::NodeEndpoint {
+            node_id,
+            host: host.into(),
+            port,
+            rack: None,
+        }])
+    }
+
+    #[must_use]
+    pub fn node_endpoints<I>(mut self, node_endpoints: I) -> Self
+    where
+        I: IntoIterator<Item = fetch_response::NodeEndpoint>,
+    {
+        self.node_endpoints.extend(node_endpoints);
+        self
+    }
+
+    /// Topics are identified by name up to version 12, and by id from
+    /// version 13.
+    pub fn build(self) -> Result<Body> {
+        if let Some(api_version) = self.api_version {
+            for response in &self.responses {
+                if api_version <= 12 && response.topic.is_none() {
+                    return Err(missing("topic", api_version));
+                }
+
+                if api_version >= 13 && response.topic_id.is_none() {
+                    return Err(missing("topic_id", api_version));
+                }
+            }
+        }
+
+        Ok(Body::FetchResponse {
+            throttle_time_ms: Some(self.throttle_time_ms),
+            error_code: Some(self.error_code.into()),
+            session_id: Some(self.session_id),
+            responses: Some(self.responses),
+            node_endpoints: Some(self.node_endpoints),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FetchTopic {
+    name: String,
+    topic_id: Option<Uuid>,
+    partitions: Vec<PartitionData>,
+}
+
+impl FetchTopic {
+    #[must_use]
+    pub fn topic_id(self, topic_id: impl Into<Uuid>) -> Self {
+        Self {
+            topic_id: Some(topic_id.into()),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn partition(
+        self,
+        partition_index: i32,
+        error_code: ErrorCode,
+        high_watermark: i64,
+        records: Option<Records>,
+    ) -> Self {
+        self.partition_data(PartitionData {
+            partition_index,
+            error_code: error_code.into(),
+            high_watermark,
+            last_stable_offset: Some(-1),
+            log_start_offset: Some(-1),
+            diverging_epoch: None,
+            current_leader: None,
+            snapshot_id: None,
+            aborted_transactions: Some(vec![]),
+            preferred_read_replica: Some(-1),
+            records,
+        })
+    }
+
+    #[must_use]
+    pub fn partition_data(mut self, partition: PartitionData) -> Self {
+        self.partitions.push(partition);
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MetadataResponse {
+    api_version: Option<i16>,
+    throttle_time_ms: i32,
+    brokers: Vec<MetadataResponseBroker>,
+    cluster_id: Option<String>,
+    controller_id: i32,
+    topics: Vec<MetadataResponseTopic>,
+    cluster_authorized_operations: i32,
+}
+
+impl Default for MetadataResponse {
+    fn default() -> Self {
+        Self {
+            api_version: None,
+            throttle_time_ms: 0,
+            brokers: vec![],
+            cluster_id: None,
+            controller_id: -1,
+            topics: vec![],
+            cluster_authorized_operations: i32::MIN,
+        }
+    }
+}
+
+impl MetadataResponse {
+    pub fn builder() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn api_version(self, api_version: i16) -> Self {
+        Self {
+            api_version: Some(api_version),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn throttle_time_ms(self, throttle_time_ms: i32) -> Self {
+        Self {
+            throttle_time_ms,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn broker(self, node_id: i32, host: &str, port: i32) -> Self {
+        self.brokers([MetadataResponseBroker {
+            node_id,
+            host: host.into(),
+            port,
+            rack: None,
+        }])
+    }
+
+    #[must_use]
+    pub fn brokers<I>(mut self, brokers: I) -> Self
+    where
+        I: IntoIterator<Item = MetadataResponseBroker>,
+    {
+        self.brokers.extend(brokers);
+        self
+    }
+
+    #[must_use]
+    pub fn cluster_id(self, cluster_id: Option<&str>) -> Self {
+        Self {
+            cluster_id: cluster_id.map(Into::into),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn controller_id(self, controller_id: i32) -> Self {
+        Self {
+            controller_id,
+            ..self
+        }
+    }
+
+    /// Add the metadata for the topic `name`, built by `f`.
+    #[must_use]
+    pub fn topic<F>(self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(MetadataTopic) -> MetadataTopic,
+    {
+        let topic = f(MetadataTopic(MetadataResponseTopic {
+            error_code: ErrorCode::None.into(),
+            name: Some(name.into()),
+            topic_id: Some(Uuid::nil()),
+            is_internal: Some(false),
+            partitions: Some(vec![]),
+            topic_authorized_operations: Some(i32::MIN),
+        }));
+
+        self.topics([topic.0])
+    }
+
+    #[must_use]
+    pub fn topics<I>(mut self, topics: I) -> Self
+    where
+        I: IntoIterator<Item = MetadataResponseTopic>,
+    {
+        self.topics.extend(topics);
+        self
+    }
+
+    /// Topic names are nullable only from version 12.
+    pub fn build(self) -> Result<Body> {
+        if let Some(api_version) = self.api_version {
+            if api_version < 12 && self.topics.iter().any(|topic| topic.name.is_none()) {
+                return Err(missing("name", api_version));
+            }
+        }
+
+        Ok(Body::MetadataResponse {
+            throttle_time_ms: Some(self.throttle_time_ms),
+            brokers: Some(self.brokers),
+            cluster_id: self.cluster_id,
+            controller_id: Some(self.controller_id),
+            topics: Some(self.topics),
+            cluster_authorized_operations: Some(self.cluster_authorized_operations),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MetadataTopic(MetadataResponseTopic);
+
+impl MetadataTopic {
+    #[must_use]
+    pub fn error_code(self, error_code: ErrorCode) -> Self {
+        Self(MetadataResponseTopic {
+            error_code: error_code.into(),
+            ..self.0
+        })
+    }
+
+    #[must_use]
+    pub fn topic_id(self, topic_id: impl Into<Uuid>) -> Self {
+        Self(MetadataResponseTopic {
+            topic_id: Some(topic_id.into()),
+            ..self.0
+        })
+    }
+
+    #[must_use]
+    pub fn is_internal(self, is_internal: bool) -> Self {
+        Self(MetadataResponseTopic {
+            is_internal: Some(is_internal),
+            ..self.0
+        })
+    }
+
+    #[must_use]
+    pub fn partition(
+        mut self,
+        partition_index: i32,
+        leader_id: i32,
+        replica_nodes: &[i32],
+        isr_nodes: &[i32],
+    ) -> Self {
+        self.0
+            .partitions
+            .get_or_insert_with(Vec::new)
+            .push(MetadataResponsePartition {
+                error_code: ErrorCode::None.into(),
+                partition_index,
+                leader_id,
+                leader_epoch: Some(-1),
+                replica_nodes: Some(replica_nodes.to_vec()),
+                isr_nodes: Some(isr_nodes.to_vec()),
+                offline_replicas: Some(vec![]),
+            });
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct OffsetFetchResponse {
+    api_version: Option<i16>,
+    throttle_time_ms: i32,
+    topics: Option<Vec<OffsetFetchResponseTopic>>,
+    error_code: Option<ErrorCode>,
+    groups: Option<Vec<OffsetFetchResponseGroup>>,
+}
+
+impl OffsetFetchResponse {
+    pub fn builder() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn api_version(self, api_version: i16) -> Self {
+        Self {
+            api_version: Some(api_version),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn throttle_time_ms(self, throttle_time_ms: i32) -> Self {
+        Self {
+            throttle_time_ms,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn error_code(self, error_code: ErrorCode) -> Self {
+        Self {
+            error_code: Some(error_code),
+            ..self
+        }
+    }
+
+    /// Add the committed offsets of the topic `name`, built by `f`, as
+    /// answered by versions 0 through 7.
+    #[must_use]
+    pub fn topic<F>(self, name: &str, f: F) -> Self
+    where
+        F: FnOnce(OffsetFetchTopic) -> OffsetFetchTopic,
+    {
+        let topic = f(OffsetFetchTopic {
+            name: name.into(),
+            partitions: vec![],
+        });
+
+        self.topics([OffsetFetchResponseTopic {
+            name: topic.name,
+            partitions: Some(
+                topic
+                    .partitions
+                    .into_iter()
+                    .map(|partition| OffsetFetchResponsePartition {
+                        partition_index: partition.partition_index,
+                        committed_offset: partition.committed_offset,
+                        committed_leader_epoch: Some(partition.committed_leader_epoch),
+                        metadata: partition.metadata,
+                        error_code: partition.error_code,
+                    })
+                    .collect(),
+            ),
+        }])
+    }
+
+    #[must_use]
+    pub fn topics<I>(mut self, topics: I) -> Self
+    where
+        I: IntoIterator<Item = OffsetFetchResponseTopic>,
+    {
+        self.topics.get_or_insert_with(Vec::new).extend(topics);
+        self
+    }
+
+    /// Add the committed offsets of the topic `name` for `group_id`, built
+    /// by `f`, as answered from version 8.
+    #[must_use]
+    pub fn group<F>(self, group_id: &str, name: &str, f: F) -> Self
+    where
+        F: FnOnce(OffsetFetchTopic) -> OffsetFetchTopic,
+    {
+        let topic = f(OffsetFetchTopic {
+            name: name.into(),
+            partitions: vec![],
+        });
+
+        let topics = OffsetFetchResponseTopics {
+            name: topic.name,
+            partitions: Some(topic.partitions),
+        };
+
+        let mut groups = self.groups.unwrap_or_default();
+
+        if let Some(group) = groups.iter_mut().find(|group| group.group_id == group_id) {
+            group.topics.get_or_insert_with(Vec::new).push(topics);
+        } else {
+            groups.push(OffsetFetchResponseGroup {
+                group_id: group_id.into(),
+                topics: Some(vec![topics]),
+                error_code: ErrorCode::None.into(),
+            });
+        }
+
+        Self {
+            groups: Some(groups),
+            ..self
+        }
+    }
+
+    /// The error of the whole group `group_id`, as answered from version 8.
+    #[must_use]
+    pub fn group_error_code(self, group_id: &str, error_code: ErrorCode) -> Self {
+        let mut groups = self.groups.unwrap_or_default();
+
+        if let Some(group) = groups.iter_mut().find(|group| group.group_id == group_id) {
+            group.error_code = error_code.into();
+        } else {
+            groups.push(OffsetFetchResponseGroup {
+                group_id: group_id.into(),
+                topics: Some(vec![]),
+                error_code: error_code.into(),
+            });
+        }
+
+        Self {
+            groups: Some(groups),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn groups<I>(mut self, groups: I) -> Self
+    where
+        I: IntoIterator<Item = OffsetFetchResponseGroup>,
+    {
+        self.groups.get_or_insert_with(Vec::new).extend(groups);
+        self
+    }
+
+    /// Offsets are answered by topic up to version 7, and by group from
+    /// version 8.
+    pub fn build(self) -> Result<Body> {
+        if let Some(api_version) = self.api_version {
+            if api_version < 8 && self.topics.is_none() {
+                return Err(missing("topics", api_version));
+            }
+
+            if api_version >= 8 && self.groups.is_none() {
+                return Err(missing("groups", api_version));
+            }
+        }
+
+        Ok(Body::OffsetFetchResponse {
+            throttle_time_ms: Some(self.throttle_time_ms),
+            topics: self.topics,
+            error_code: Some(self.error_code.unwrap_or(ErrorCode::None).into()),
+            groups: self.groups,
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OffsetFetchTopic {
+    name: String,
+    partitions: Vec<OffsetFetchResponsePartitions>,
+}
+
+impl OffsetFetchTopic {
+    #[must_use]
+    pub fn partition(
+        mut self,
+        partition_index: i32,
+        committed_offset: i64,
+        error_code: ErrorCode,
+    ) -> Self {
+        self.partitions.push(OffsetFetchResponsePartitions {
+            partition_index,
+            committed_offset,
+            committed_leader_epoch: -1,
+            metadata: None,
+            error_code: error_code.into(),
+        });
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FindCoordinatorResponse {
+    api_version: Option<i16>,
+    throttle_time_ms: i32,
+    error_code: ErrorCode,
+    error_message: Option<String>,
+    coordinator: Option<(i32, String, i32)>,
+    keys: Vec<String>,
+}
+
+impl Default for FindCoordinatorResponse {
+    fn default() -> Self {
+        Self {
+            api_version: None,
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None,
+            error_message: None,
+            coordinator: None,
+            keys: vec![],
+        }
+    }
+}
+
+impl FindCoordinatorResponse {
+    pub fn builder() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn api_version(self, api_version: i16) -> Self {
+        Self {
+            api_version: Some(api_version),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn throttle_time_ms(self, throttle_time_ms: i32) -> Self {
+        Self {
+            throttle_time_ms,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn error(self, error_code: ErrorCode, error_message: Option<&str>) -> Self {
+        Self {
+            error_code,
+            error_message: error_message.map(Into::into),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn coordinator(self, node_id: i32, host: &str, port: i32) -> Self {
+        Self {
+            coordinator: Some((node_id, host.into(), port)),
+            ..self
+        }
+    }
+
+    /// The keys answered by the coordinator from version 4.
+    #[must_use]
+    pub fn keys<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.keys.extend(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Up to version 3 the coordinator is always required, from version 4
+    /// only when there are keys to answer.
+    pub fn build(self) -> Result<Body> {
+        let api_version = self.api_version.unwrap_or_default();
+
+        let Some((node_id, host, port)) = self.coordinator else {
+            return if api_version >= 4 && self.keys.is_empty() {
+                Ok(Body::FindCoordinatorResponse {
+                    throttle_time_ms: Some(self.throttle_time_ms),
+                    error_code: Some(self.error_code.into()),
+                    error_message: self.error_message,
+                    node_id: None,
+                    host: None,
+                    port: None,
+                    coordinators: Some(vec![]),
+                    unknown_tagged_fields: vec![],
+                })
+            } else {
+                Err(missing("node_id", api_version))
+            };
+        };
+
+        let coordinators = self
+            .keys
+            .into_iter()
+            .map(|key| Coordinator {
+                key,
+                node_id,
+                host: host.clone(),
+                port,
+                error_code: self.error_code.into(),
+                error_message: self.error_message.clone(),
+            })
+            .collect();
+
+        Ok(Body::FindCoordinatorResponse {
+            throttle_time_ms: Some(self.throttle_time_ms),
+            error_code: Some(self.error_code.into()),
+            error_message: self.error_message,
+            node_id: Some(node_id),
+            host: Some(host),
+            port: Some(port),
+            coordinators: Some(coordinators),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
@@ -17,9 +17,11 @@ use std::{
     any::type_name_of_val,
     collections::VecDeque,
     fmt,
-    io::{Cursor, Write},
+    io::{self, Cursor, IoSlice, Write},
 };
 
+use bytes::{Bytes, BytesMut};
+
 use serde::{
     ser::{
         SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
@@ -30,7 +32,10 @@ use serde::{
 use tansu_kafka_model::{FieldMeta, MessageMeta};
 use tracing::debug;
 
-use crate::{Error, Result, RootMessageMeta};
+use crate::{
+    primitive::{uuid::Uuid, varint},
+    Error, Result, RootMessageMeta,
+};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 enum Kind {
@@ -88,8 +93,140 @@ struct Meta {
     parse: VecDeque<FieldLookup>,
 }
 
+/// Encoded output held as a sequence of [`Bytes`].
+///
+/// Record data that is already held as [`Bytes`] is referenced by a segment
+/// of its own rather than being copied, so that a large fetch response can be
+/// written with [`Write::write_vectored`] straight from the batches.
+#[derive(Clone, Debug, Default)]
+pub struct Segments {
+    current: BytesMut,
+    segments: Vec<Bytes>,
+    shared: Vec<Bytes>,
+}
+
+impl Segments {
+    pub(crate) fn sharing(shared: Vec<Bytes>) -> Self {
+        Self {
+            shared,
+            ..Default::default()
+        }
+    }
+
+    /// The total length of every segment.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(Bytes::len).sum::<usize>() + self.current.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[must_use]
+    pub fn into_inner(mut self) -> Vec<Bytes> {
+        self.seal();
+        self.segments
+    }
+
+    pub fn io_slices(&mut self) -> Vec<IoSlice<'_>> {
+        self.seal();
+        self.segments
+            .iter()
+            .map(|segment| IoSlice::new(segment))
+            .collect()
+    }
+
+    pub(crate) fn patch(&mut self, offset: usize, patch: &[u8]) {
+        let mut start = 0;
+
+        for segment in &mut self.segments {
+            if offset < start + segment.len() {
+                let mut patched = BytesMut::from(&segment[..]);
+                patched[offset - start..offset - start + patch.len()].copy_from_slice(patch);
+                *segment = patched.freeze();
+                return;
+            }
+
+            start += segment.len();
+        }
+
+        self.current[offset - start..offset - start + patch.len()].copy_from_slice(patch);
+    }
+
+    fn seal(&mut self) {
+        if !self.current.is_empty() {
+            self.segments.push(self.current.split().freeze());
+        }
+    }
+
+    fn splice(&mut self, segment: Bytes) {
+        self.seal();
+        self.segments.push(segment);
+    }
+
+    fn nested(&self) -> Self {
+        Self::sharing(self.shared.clone())
+    }
+
+    fn append(&mut self, other: Self) {
+        for segment in other.into_inner() {
+            if self.shared.iter().any(|shared| same(shared, &segment)) {
+                self.splice(segment);
+            } else {
+                self.current.extend_from_slice(&segment);
+            }
+        }
+    }
+
+    fn write_shared(&mut self, v: &[u8]) {
+        if let Some(shared) = self.shared.iter().find(|shared| same(shared, v)).cloned() {
+            self.splice(shared);
+        } else {
+            self.current.extend_from_slice(v);
+        }
+    }
+}
+
+fn same(shared: &[u8], v: &[u8]) -> bool {
+    !v.is_empty() && shared.as_ptr() == v.as_ptr() && shared.len() == v.len()
+}
+
+impl Write for Segments {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.current.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub(crate) enum Output<'a> {
+    Writer(&'a mut dyn Write),
+    Segments(&'a mut Segments),
+}
+
+impl Write for Output<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Writer(writer) => writer.write(buf),
+            Self::Segments(segments) => segments.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Writer(writer) => writer.flush(),
+            Self::Segments(segments) => segments.flush(),
+        }
+    }
+}
+
 pub struct Encoder<'a> {
-    writer: &'a mut dyn Write,
+    writer: Output<'a>,
     containers: VecDeque<Container>,
     field: Option<&'static str>,
     kind: Option<Kind>,
@@ -106,6 +243,10 @@ impl<'a> fmt::Debug for Encoder<'a> {
 
 impl<'a> Encoder<'a> {
     pub fn request(writer: &'a mut dyn Write) -> Self {
+        Self::request_to(Output::Writer(writer))
+    }
+
+    pub(crate) fn request_to(writer: Output<'a>) -> Self {
         Self {
             writer,
             containers: VecDeque::new(),
@@ -118,6 +259,10 @@ impl<'a> Encoder<'a> {
     }
 
     pub fn response(writer: &'a mut dyn Write, api_key: i16, api_version: i16) -> Self {
+        Self::response_to(Output::Writer(writer), api_key, api_version)
+    }
+
+    pub(crate) fn response_to(writer: Output<'a>, api_key: i16, api_version: i16) -> Self {
         Self {
             writer,
             containers: VecDeque::new(),
@@ -142,6 +287,10 @@ impl<'a> Encoder<'a> {
     }
 
     pub fn new(writer: &'a mut dyn Write) -> Self {
+        Self::new_to(Output::Writer(writer))
+    }
+
+    fn new_to(writer: Output<'a>) -> Self {
         Self {
             writer,
             containers: VecDeque::new(),
@@ -180,18 +329,19 @@ impl<'a> Encoder<'a> {
         )
     }
 
-    fn unsigned_varint(&mut self, mut v: u32) -> Result<()> {
-        const CONTINUATION: u8 = 0b1000_0000;
+    fn unsigned_varint(&mut self, v: u32) -> Result<()> {
+        varint::write_unsigned_varint(&mut self.writer, v)
+    }
 
-        while v >= u32::from(CONTINUATION) {
-            #[allow(clippy::cast_possible_truncation)]
-            self.writer.write_all(&[(v as u8 | CONTINUATION)])?;
-            v >>= 7;
-        }
+    fn records_length(&mut self, length: u32) -> Result<()> {
+        debug!(?length);
 
-        #[allow(clippy::cast_possible_truncation)]
-        self.writer.write_all(&[(v as u8)])?;
-        Ok(())
+        if self.is_flexible() {
+            self.unsigned_varint(length + 1)
+        } else {
+            let buf = length.to_be_bytes();
+            self.writer.write_all(&buf).map_err(Into::into)
+        }
     }
 
     fn in_header(&self) -> bool {
@@ -200,6 +350,20 @@ impl<'a> Encoder<'a> {
             .is_some_and(|c| c.name().starts_with("HeaderMezzanine::"))
     }
 
+    fn is_client_id(&self) -> bool {
+        self.in_header()
+            && self.kind.is_some_and(|kind| kind == Kind::Request)
+            && self.field.is_some_and(|field| field == "client_id")
+    }
+
+    // a version 0 request header has no client id
+    fn has_client_id(&self) -> bool {
+        self.meta
+            .message
+            .zip(self.api_version)
+            .is_none_or(|(meta, api_version)| meta.header_version(api_version) > 0)
+    }
+
     #[must_use]
     fn is_flexible(&self) -> bool {
         debug!(
@@ -207,22 +371,20 @@ impl<'a> Encoder<'a> {
             self.api_key,
             self.api_version,
             self.in_header(),
-            self.field.is_some_and(|field| field == "client_id")
+            self.is_client_id()
         );
 
-        if self.in_header()
-            && ((self.kind.is_some_and(|kind| kind == Kind::Request)
-                && self.field.is_some_and(|field| field == "client_id"))
-                || (self.kind.is_some_and(|kind| kind == Kind::Response)
-                    && self.api_key.is_some_and(|api_key| api_key == 18)))
-        {
-            false
-        } else {
-            self.meta.message.is_some_and(|meta| {
-                self.api_version
-                    .map_or(false, |api_version| meta.is_flexible(api_version))
+        self.meta.message.is_some_and(|meta| {
+            self.api_version.is_some_and(|api_version| {
+                if self.in_header() {
+                    // only the tagged fields of a header, never the client id
+                    self.field.is_some_and(|field| field == "tag_buffer")
+                        && meta.is_header_flexible(api_version)
+                } else {
+                    meta.is_flexible(api_version)
+                }
             })
-        }
+        })
     }
 
     fn is_nullable(&self) -> bool {
@@ -251,14 +413,25 @@ impl<'a> Encoder<'a> {
 
     #[must_use]
     fn is_string(&self) -> bool {
-        self.in_header() && self.field.is_some_and(|field| field == "client_id")
-            || self.meta.field.is_some_and(|field| field.kind.is_string())
+        self.is_client_id() || self.meta.field.is_some_and(|field| field.kind.is_string())
     }
 
     #[must_use]
     fn is_records(&self) -> bool {
         self.meta.field.is_some_and(|field| field.kind.is_records())
     }
+
+    #[must_use]
+    fn is_uuid(&self) -> bool {
+        self.meta.field.is_some_and(|field| field.kind.is_uuid())
+    }
+
+    #[must_use]
+    fn is_structure(&self) -> bool {
+        self.meta
+            .field
+            .is_some_and(|field| !field.kind.is_sequence() && !field.kind.is_primitive())
+    }
 }
 
 impl<'a> Serializer for &'a mut Encoder<'_> {
@@ -273,6 +446,10 @@ impl<'a> Serializer for &'a mut Encoder<'_> {
     type SerializeStruct = Self;
     type SerializeStructVariant = Self;
 
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
         debug!(
             "name: {}, v: {v:?}:{}",
@@ -442,10 +619,7 @@ impl<'a> Serializer for &'a mut Encoder<'_> {
             self.is_valid()
         );
 
-        if self.in_header()
-            && self.kind.is_some_and(|kind| kind == Kind::Request)
-            && self.field.is_some_and(|field| field == "client_id")
-        {
+        if self.is_client_id() {
             v.len()
                 .try_into()
                 .map_err(Into::into)
@@ -488,7 +662,12 @@ impl<'a> Serializer for &'a mut Encoder<'_> {
             }
         }
 
-        self.writer.write_all(v).map_err(Into::into)
+        if let Output::Segments(segments) = &mut self.writer {
+            segments.write_shared(v);
+            Ok(())
+        } else {
+            self.writer.write_all(v).map_err(Into::into)
+        }
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
@@ -499,17 +678,23 @@ impl<'a> Serializer for &'a mut Encoder<'_> {
             self.is_nullable()
         );
 
-        if self.in_header()
-            && self.kind.is_some_and(|kind| kind == Kind::Request)
-            && self.field.is_some_and(|field| field == "client_id")
-        {
-            self.serialize_i16(-1)
+        if self.is_client_id() {
+            if self.has_client_id() {
+                self.serialize_i16(-1)
+            } else {
+                Ok(())
+            }
         } else if self.is_valid() && self.is_records() {
             if self.is_flexible() {
                 self.unsigned_varint(1)
             } else {
                 self.serialize_i32(0)
             }
+        } else if self.is_valid() && self.is_uuid() && !self.is_nullable() {
+            // a uuid that is absent but required by this version is the nil uuid
+            Uuid::nil().serialize(self)
+        } else if self.is_valid() && self.is_nullable() && self.is_structure() {
+            self.serialize_i8(-1)
         } else if self.is_valid() && self.is_nullable() {
             if self.is_flexible() {
                 self.unsigned_varint(0)
@@ -530,26 +715,41 @@ impl<'a> Serializer for &'a mut Encoder<'_> {
         T: Serialize,
         T: ?Sized,
     {
-        if self.field.is_some_and(|field| field == "tag_buffer") && !self.is_flexible() {
+        if (self.field.is_some_and(|field| field == "tag_buffer") && !self.is_flexible())
+            || (self.is_client_id() && !self.has_client_id())
+        {
+            Ok(())
+        } else if self.meta.field.is_some() && self.api_version.is_some() && !self.is_valid() {
+            // a field that is not part of this version is never written
             Ok(())
         } else if self.is_records() {
+            if let Output::Segments(segments) = &self.writer {
+                let mut nested = segments.nested();
+                value.serialize(&mut Encoder::new_to(Output::Segments(&mut nested)))?;
+
+                u32::try_from(nested.len())
+                    .map_err(Into::into)
+                    .and_then(|length| self.records_length(length))?;
+
+                if let Output::Segments(segments) = &mut self.writer {
+                    segments.append(nested);
+                }
+
+                return Ok(());
+            }
+
             let mut c = Cursor::new(vec![]);
             let mut e = Encoder::new(&mut c);
             value.serialize(&mut e)?;
 
             u32::try_from(c.position())
                 .map_err(Into::into)
-                .inspect(|length| debug!(?length))
-                .and_then(|length| {
-                    if self.is_flexible() {
-                        self.unsigned_varint(length + 1)
-                    } else {
-                        let buf = length.to_be_bytes();
-                        self.writer.write_all(&buf).map_err(Into::into)
-                    }
-                })?;
+                .and_then(|length| self.records_length(length))?;
 
             self.writer.write_all(&c.into_inner()).map_err(Into::into)
+        } else if self.is_valid() && self.is_nullable() && self.is_structure() {
+            self.serialize_i8(1)?;
+            value.serialize(self)
         } else {
             value.serialize(self)
         }
@@ -0,0 +1,136 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// Reading and writing length prefixed frames over tokio streams.
+
+use crate::{Body, Error, Frame, Header, Result, DEFAULT_MAX_FRAME_BYTES};
+use bytes::{Bytes, BytesMut};
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::debug;
+
+/// Reads frames from an [`AsyncRead`].
+///
+/// Partially read frames are held in an internal buffer, so each of the
+/// read methods are cancel safe: a read abandoned in a `tokio::select!`
+/// loses no data, and the next read continues with the same frame.
+#[derive(Debug)]
+pub struct FrameReader<R> {
+    reader: R,
+    buffer: BytesMut,
+    max_frame_length: usize,
+}
+
+impl<R> FrameReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: BytesMut::new(),
+            max_frame_length: DEFAULT_MAX_FRAME_BYTES,
+        }
+    }
+
+    #[must_use]
+    pub fn max_frame_length(self, max_frame_length: usize) -> Self {
+        Self {
+            max_frame_length,
+            ..self
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// The next complete frame, including its size prefix.
+    ///
+    /// Returns `Ok(None)` when the stream ends cleanly between frames, with
+    /// an [`io::ErrorKind::UnexpectedEof`] error if the stream ends part way
+    /// through a frame.
+    pub async fn read_frame(&mut self) -> Result<Option<Bytes>> {
+        loop {
+            match Frame::check_within(&self.buffer[..], self.max_frame_length) {
+                Ok(length) => return Ok(Some(self.buffer.split_to(length).freeze())),
+
+                Err(Error::Incomplete { needed }) => {
+                    self.buffer.reserve(needed.unwrap_or_default())
+                }
+
+                Err(otherwise) => return Err(otherwise),
+            }
+
+            if self.reader.read_buf(&mut self.buffer).await? == 0 {
+                debug!(buffered = self.buffer.len());
+
+                return if self.buffer.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+                };
+            }
+        }
+    }
+
+    pub async fn read_request(&mut self) -> Result<Option<Frame>> {
+        match self.read_frame().await? {
+            Some(frame) => Frame::request_from_bytes(&frame[..]).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn read_response(&mut self, api_key: i16, api_version: i16) -> Result<Option<Frame>> {
+        match self.read_frame().await? {
+            Some(frame) => Frame::decode(&frame[..], api_key, api_version).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Write an encoded frame, including its size prefix.
+///
+/// This is not cancel safe, the frame may be partially written if the
+/// returned future is dropped before completion.
+pub async fn write_frame<W>(writer: &mut W, frame: &[u8]) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer.write_all(frame).await?;
+    writer.flush().await.map_err(Into::into)
+}
+
+pub async fn write_request<W>(writer: &mut W, header: Header, body: Body) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let frame = Frame::request(header, body)?;
+    write_frame(writer, &frame[..]).await
+}
+
+pub async fn write_response<W>(
+    writer: &mut W,
+    header: Header,
+    body: Body,
+    api_key: i16,
+    api_version: i16,
+) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let frame = Frame::encode(header, body, api_key, api_version)?;
+    write_frame(writer, &frame[..]).await
+}
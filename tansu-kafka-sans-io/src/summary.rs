@@ -0,0 +1,421 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// A compact, single line Display of a frame for logging, naming the API
+// with its version and correlation id followed by the fields that identify
+// what a request or response is about (groups, topics, partitions and
+// offsets).
+//
+// Record data is shown by its size, and long lists are cut short with a
+// count of what was left out. A body without a summary shows just its name.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::{primitive::uuid::Uuid, ApiKey, Body, ErrorCode, Frame, Header, RecordBatch};
+
+// the number of items shown in a list before the remainder is counted
+const LIST_LIMIT: usize = 8;
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.body.name())?;
+
+        match &self.header {
+            Header::Request {
+                api_version,
+                correlation_id,
+                client_id,
+                ..
+            } => {
+                write!(f, " v{api_version} correlation={correlation_id}")?;
+
+                if let Some(client_id) = client_id {
+                    write!(f, " client={client_id}")?;
+                }
+            }
+
+            Header::Response { correlation_id } => write!(f, " correlation={correlation_id}")?,
+        }
+
+        self.body.summary(f)
+    }
+}
+
+impl Display for Header {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request {
+                api_key,
+                api_version,
+                correlation_id,
+                client_id,
+            } => {
+                match ApiKey::try_from(*api_key) {
+                    Ok(api_key) => write!(f, "{api_key}")?,
+                    Err(_) => write!(f, "api_key={api_key}")?,
+                }
+
+                write!(f, " v{api_version} correlation={correlation_id}")?;
+
+                if let Some(client_id) = client_id {
+                    write!(f, " client={client_id}")?;
+                }
+
+                Ok(())
+            }
+
+            Self::Response { correlation_id } => write!(f, "correlation={correlation_id}"),
+        }
+    }
+}
+
+impl Display for Body {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())?;
+        self.summary(f)
+    }
+}
+
+impl Body {
+    fn summary(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreateTopicsRequest {
+                topics,
+                validate_only,
+                ..
+            } => {
+                field(
+                    f,
+                    "validate_only",
+                    validate_only.filter(|validate| *validate),
+                )?;
+                list(f, "topics", topics.as_deref(), |f, topic| {
+                    write!(
+                        f,
+                        "{}(partitions={}, replication={})",
+                        topic.name, topic.num_partitions, topic.replication_factor
+                    )
+                })
+            }
+
+            Self::DeleteTopicsRequest {
+                topics,
+                topic_names,
+                ..
+            } => {
+                list(f, "topics", topics.as_deref(), |f, topic| {
+                    name_or_id(f, topic.name.as_deref(), Some(&topic.topic_id))
+                })?;
+                list(f, "topics", topic_names.as_deref(), |f, name| {
+                    f.write_str(name)
+                })
+            }
+
+            Self::FetchRequest {
+                session_id,
+                session_epoch,
+                topics,
+                ..
+            } => {
+                field(f, "session", *session_id)?;
+                field(f, "epoch", *session_epoch)?;
+                list(f, "topics", topics.as_deref(), |f, topic| {
+                    name_or_id(f, topic.topic.as_deref(), topic.topic_id.as_ref())?;
+                    nested(f, topic.partitions.as_deref(), |f, partition| {
+                        write!(f, "{}@{}", partition.partition, partition.fetch_offset)
+                    })
+                })
+            }
+
+            Self::FetchResponse {
+                error_code,
+                session_id,
+                responses,
+                ..
+            } => {
+                error(f, error_code.unwrap_or_default())?;
+                field(f, "session", *session_id)?;
+                list(f, "topics", responses.as_deref(), |f, topic| {
+                    name_or_id(f, topic.topic.as_deref(), topic.topic_id.as_ref())?;
+                    nested(f, topic.partitions.as_deref(), |f, partition| {
+                        write!(
+                            f,
+                            "{}@{}",
+                            partition.partition_index, partition.high_watermark
+                        )?;
+                        records(f, partition.records.as_ref())?;
+                        error(f, partition.error_code)
+                    })
+                })
+            }
+
+            Self::FindCoordinatorRequest {
+                key,
+                key_type,
+                coordinator_keys,
+                ..
+            } => {
+                field(f, "key", key.as_deref())?;
+                field(f, "type", *key_type)?;
+                list(f, "keys", coordinator_keys.as_deref(), |f, key| {
+                    f.write_str(key)
+                })
+            }
+
+            Self::HeartbeatRequest {
+                group_id,
+                generation_id,
+                member_id,
+                ..
+            } => {
+                field(f, "group", Some(group_id))?;
+                field(f, "generation", Some(generation_id))?;
+                field(f, "member", non_empty(member_id))
+            }
+
+            Self::InitProducerIdRequest {
+                transactional_id,
+                producer_id,
+                producer_epoch,
+                ..
+            } => {
+                field(f, "transaction", transactional_id.as_deref())?;
+                field(f, "producer", *producer_id)?;
+                field(f, "epoch", *producer_epoch)
+            }
+
+            Self::JoinGroupRequest {
+                group_id,
+                member_id,
+                protocol_type,
+                protocols,
+                ..
+            } => {
+                field(f, "group", Some(group_id))?;
+                field(f, "member", non_empty(member_id))?;
+                field(f, "protocol_type", Some(protocol_type))?;
+                list(f, "protocols", protocols.as_deref(), |f, protocol| {
+                    f.write_str(&protocol.name)
+                })
+            }
+
+            Self::JoinGroupResponse {
+                error_code,
+                generation_id,
+                leader,
+                member_id,
+                members,
+                ..
+            } => {
+                error(f, *error_code)?;
+                field(f, "generation", Some(generation_id))?;
+                field(f, "leader", non_empty(leader))?;
+                field(f, "member", non_empty(member_id))?;
+                count(f, "members", members.as_deref())
+            }
+
+            Self::LeaveGroupRequest {
+                group_id,
+                member_id,
+                members,
+                ..
+            } => {
+                field(f, "group", Some(group_id))?;
+                field(f, "member", member_id.as_deref())?;
+                list(f, "members", members.as_deref(), |f, member| {
+                    f.write_str(&member.member_id)
+                })
+            }
+
+            Self::ListOffsetsRequest { topics, .. } => {
+                list(f, "topics", topics.as_deref(), |f, topic| {
+                    f.write_str(&topic.name)?;
+                    nested(f, topic.partitions.as_deref(), |f, partition| {
+                        write!(f, "{}@{}", partition.partition_index, partition.timestamp)
+                    })
+                })
+            }
+
+            Self::MetadataRequest { topics, .. } => {
+                if topics.is_none() {
+                    f.write_str(" topics=*")
+                } else {
+                    list(f, "topics", topics.as_deref(), |f, topic| {
+                        name_or_id(f, topic.name.as_deref(), topic.topic_id.as_ref())
+                    })
+                }
+            }
+
+            Self::OffsetCommitRequest {
+                group_id,
+                generation_id_or_member_epoch,
+                member_id,
+                topics,
+                ..
+            } => {
+                field(f, "group", Some(group_id))?;
+                field(f, "generation", *generation_id_or_member_epoch)?;
+                field(f, "member", member_id.as_deref().and_then(non_empty))?;
+                list(f, "topics", topics.as_deref(), |f, topic| {
+                    f.write_str(&topic.name)?;
+                    nested(f, topic.partitions.as_deref(), |f, partition| {
+                        write!(
+                            f,
+                            "{}@{}",
+                            partition.partition_index, partition.committed_offset
+                        )
+                    })
+                })
+            }
+
+            Self::OffsetFetchRequest {
+                group_id,
+                topics,
+                groups,
+                ..
+            } => {
+                field(f, "group", group_id.as_deref())?;
+                list(f, "topics", topics.as_deref(), |f, topic| {
+                    f.write_str(&topic.name)?;
+                    nested(f, topic.partition_indexes.as_deref(), |f, partition| {
+                        write!(f, "{partition}")
+                    })
+                })?;
+                list(f, "groups", groups.as_deref(), |f, group| {
+                    f.write_str(&group.group_id)?;
+                    nested(f, group.topics.as_deref(), |f, topic| {
+                        f.write_str(&topic.name)?;
+                        nested(f, topic.partition_indexes.as_deref(), |f, partition| {
+                            write!(f, "{partition}")
+                        })
+                    })
+                })
+            }
+
+            Self::ProduceRequest {
+                transactional_id,
+                acks,
+                topic_data,
+                ..
+            } => {
+                field(f, "transaction", transactional_id.as_deref())?;
+                field(f, "acks", Some(acks))?;
+                list(f, "topics", topic_data.as_deref(), |f, topic| {
+                    f.write_str(&topic.name)?;
+                    nested(f, topic.partition_data.as_deref(), |f, partition| {
+                        write!(f, "{}", partition.index)?;
+                        records(f, partition.records.as_ref())
+                    })
+                })
+            }
+
+            Self::ProduceResponse { responses, .. } => {
+                list(f, "topics", responses.as_deref(), |f, topic| {
+                    f.write_str(&topic.name)?;
+                    nested(f, topic.partition_responses.as_deref(), |f, partition| {
+                        write!(f, "{}@{}", partition.index, partition.base_offset)?;
+                        error(f, partition.error_code)
+                    })
+                })
+            }
+
+            Self::SyncGroupRequest {
+                group_id,
+                generation_id,
+                member_id,
+                assignments,
+                ..
+            } => {
+                field(f, "group", Some(group_id))?;
+                field(f, "generation", Some(generation_id))?;
+                field(f, "member", non_empty(member_id))?;
+                count(f, "assignments", assignments.as_deref())
+            }
+
+            _ => Ok(()),
+        }
+    }
+}
+
+fn non_empty(s: &str) -> Option<&str> {
+    Some(s).filter(|s| !s.is_empty())
+}
+
+fn field<T: Display>(f: &mut Formatter<'_>, name: &str, value: Option<T>) -> fmt::Result {
+    value.map_or(Ok(()), |value| write!(f, " {name}={value}"))
+}
+
+fn error(f: &mut Formatter<'_>, error_code: i16) -> fmt::Result {
+    match ErrorCode::try_from(error_code) {
+        Ok(ErrorCode::None) => Ok(()),
+        Ok(error_code) => write!(f, " {error_code:?}"),
+        Err(_) => write!(f, " error={error_code}"),
+    }
+}
+
+fn name_or_id(f: &mut Formatter<'_>, name: Option<&str>, id: Option<&Uuid>) -> fmt::Result {
+    match (name, id) {
+        (Some(name), _) if !name.is_empty() => f.write_str(name),
+        (_, Some(id)) => write!(f, "{id}"),
+        _ => Ok(()),
+    }
+}
+
+fn records(f: &mut Formatter<'_>, records: Option<&RecordBatch>) -> fmt::Result {
+    match records.map(|records| records.as_bytes().len()) {
+        Some(length) if length > 0 => write!(f, " {length}B"),
+        _ => Ok(()),
+    }
+}
+
+fn count<T>(f: &mut Formatter<'_>, name: &str, items: Option<&[T]>) -> fmt::Result {
+    field(f, name, items.map(<[T]>::len))
+}
+
+fn list<T>(
+    f: &mut Formatter<'_>,
+    name: &str,
+    items: Option<&[T]>,
+    each: impl Fn(&mut Formatter<'_>, &T) -> fmt::Result,
+) -> fmt::Result {
+    items.map_or(Ok(()), |items| {
+        write!(f, " {name}=")?;
+        nested(f, Some(items), each)
+    })
+}
+
+fn nested<T>(
+    f: &mut Formatter<'_>,
+    items: Option<&[T]>,
+    each: impl Fn(&mut Formatter<'_>, &T) -> fmt::Result,
+) -> fmt::Result {
+    let items = items.unwrap_or_default();
+
+    f.write_str("[")?;
+
+    for (index, item) in items.iter().take(LIST_LIMIT).enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+
+        each(f, item)?;
+    }
+
+    if items.len() > LIST_LIMIT {
+        write!(f, ", ... +{}", items.len() - LIST_LIMIT)?;
+    }
+
+    f.write_str("]")
+}
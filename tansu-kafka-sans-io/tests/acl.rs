@@ -0,0 +1,85 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use tansu_kafka_sans_io::{
+    AclOperation, AclPermissionType, Error, PatternType, ResourceType, Result,
+};
+
+#[test]
+fn resource_type() -> Result<()> {
+    for value in 0..=7 {
+        assert_eq!(value, i8::from(ResourceType::try_from(value)?));
+    }
+
+    assert_eq!(ResourceType::Topic, ResourceType::try_from(2)?);
+    assert_eq!(ResourceType::TransactionalId, ResourceType::try_from(5)?);
+
+    assert!(matches!(
+        ResourceType::try_from(8),
+        Err(Error::InvalidResourceType(8))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn pattern_type() -> Result<()> {
+    for value in 0..=4 {
+        assert_eq!(value, i8::from(PatternType::try_from(value)?));
+    }
+
+    assert_eq!(PatternType::Literal, PatternType::default());
+    assert_eq!(PatternType::Prefixed, PatternType::try_from(4)?);
+
+    assert!(matches!(
+        PatternType::try_from(-1),
+        Err(Error::InvalidPatternType(-1))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn acl_operation() -> Result<()> {
+    for value in 0..=14 {
+        assert_eq!(value, i8::from(AclOperation::try_from(value)?));
+    }
+
+    assert_eq!(AclOperation::Read, AclOperation::try_from(3)?);
+    assert_eq!(AclOperation::IdempotentWrite, AclOperation::try_from(12)?);
+
+    assert!(matches!(
+        AclOperation::try_from(15),
+        Err(Error::InvalidAclOperation(15))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn acl_permission_type() -> Result<()> {
+    for value in 0..=3 {
+        assert_eq!(value, i8::from(AclPermissionType::try_from(value)?));
+    }
+
+    assert_eq!(AclPermissionType::Allow, AclPermissionType::try_from(3)?);
+
+    assert!(matches!(
+        AclPermissionType::try_from(4),
+        Err(Error::InvalidAclPermissionType(4))
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use tansu_kafka_model::{Listener, MessageKind};
+use tansu_kafka_sans_io::{ApiKey, Error, Result, RootMessageMeta};
+
+#[test]
+fn every_request_has_an_api_key() -> Result<()> {
+    for (api_key, meta) in RootMessageMeta::messages().requests() {
+        let typed = ApiKey::try_from(*api_key)?;
+        assert_eq!(*api_key, i16::from(typed));
+        assert_eq!(meta.name, typed.request_meta().name);
+        assert_eq!(meta.name.trim_end_matches("Request"), typed.to_string());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn request_and_response_meta() {
+    let api_key = ApiKey::Fetch;
+    assert_eq!(1, i16::from(api_key));
+    assert_eq!("Fetch", api_key.to_string());
+
+    assert_eq!("FetchRequest", api_key.request_meta().name);
+    assert_eq!(MessageKind::Request, api_key.request_meta().message_kind);
+
+    assert_eq!("FetchResponse", api_key.response_meta().name);
+    assert_eq!(MessageKind::Response, api_key.response_meta().message_kind);
+}
+
+#[test]
+fn unknown_api_key() {
+    assert!(matches!(
+        ApiKey::try_from(-1),
+        Err(Error::NoSuchRequest(-1))
+    ));
+    assert!(matches!(
+        ApiKey::try_from(i16::MAX),
+        Err(Error::NoSuchRequest(i16::MAX))
+    ));
+}
+
+#[test]
+fn broker_and_controller_requests() {
+    let messages = RootMessageMeta::messages();
+
+    let broker = messages.broker_requests();
+    let controller = messages.controller_requests();
+
+    for api_key in [ApiKey::ApiVersions, ApiKey::CreateTopics, ApiKey::Fetch] {
+        assert!(broker.contains_key(&i16::from(api_key)), "{api_key}");
+        assert!(controller.contains_key(&i16::from(api_key)), "{api_key}");
+    }
+
+    for api_key in [ApiKey::Produce, ApiKey::Heartbeat, ApiKey::JoinGroup] {
+        assert!(broker.contains_key(&i16::from(api_key)), "{api_key}");
+        assert!(!controller.contains_key(&i16::from(api_key)), "{api_key}");
+    }
+
+    for api_key in [ApiKey::BrokerRegistration, ApiKey::Vote] {
+        assert!(!broker.contains_key(&i16::from(api_key)), "{api_key}");
+        assert!(controller.contains_key(&i16::from(api_key)), "{api_key}");
+    }
+
+    assert_eq!(
+        &[Listener::ZkBroker, Listener::Broker][..],
+        ApiKey::Produce.request_meta().listeners()
+    );
+    assert!(ApiKey::Produce.response_meta().listeners().is_empty());
+}
+
+#[test]
+fn header_versions() {
+    assert_eq!(0, ApiKey::ControlledShutdown.request_header_version(0));
+    assert_eq!(1, ApiKey::ControlledShutdown.request_header_version(1));
+    assert_eq!(2, ApiKey::ControlledShutdown.request_header_version(3));
+    assert_eq!(1, ApiKey::ControlledShutdown.response_header_version(3));
+
+    assert_eq!(1, ApiKey::ApiVersions.request_header_version(2));
+    assert_eq!(2, ApiKey::ApiVersions.request_header_version(3));
+    assert_eq!(0, ApiKey::ApiVersions.response_header_version(3));
+
+    assert_eq!(1, ApiKey::Fetch.request_header_version(11));
+    assert_eq!(2, ApiKey::Fetch.request_header_version(12));
+    assert_eq!(0, ApiKey::Fetch.response_header_version(11));
+    assert_eq!(1, ApiKey::Fetch.response_header_version(12));
+}
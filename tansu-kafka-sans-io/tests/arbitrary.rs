@@ -0,0 +1,113 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![cfg(feature = "proptest")]
+
+use proptest::{
+    prop_assert_eq,
+    test_runner::{Config, TestCaseError, TestRunner},
+};
+use tansu_kafka_sans_io::{arbitrary, ApiKey, Error, Frame, RootMessageMeta};
+
+// the number of cases for each api key and version
+const CASES: u32 = 16;
+
+fn runner() -> TestRunner {
+    TestRunner::new(Config {
+        cases: CASES,
+        failure_persistence: None,
+        ..Config::default()
+    })
+}
+
+fn error(api_key: i16, api_version: i16) -> impl Fn(Error) -> TestCaseError {
+    move |error| TestCaseError::fail(format!("{api_key} v{api_version}: {error:?}"))
+}
+
+#[test]
+fn request_round_trip() {
+    let mut runner = runner();
+
+    for (api_key, min_version, max_version) in RootMessageMeta::messages().api_versions() {
+        for api_version in min_version..=max_version {
+            let strategy = arbitrary::request(api_key, api_version).expect("request");
+
+            runner
+                .run(&strategy, |encoded| {
+                    let frame =
+                        Frame::request_from_bytes(&encoded).map_err(error(api_key, api_version))?;
+
+                    let reencoded = Frame::request(frame.header.clone(), frame.body.clone())
+                        .map_err(error(api_key, api_version))?;
+                    prop_assert_eq!(&encoded, &reencoded, "{} v{}", api_key, api_version);
+
+                    let decoded = Frame::request_from_bytes(&reencoded)
+                        .map_err(error(api_key, api_version))?;
+                    prop_assert_eq!(frame.body, decoded.body, "{} v{}", api_key, api_version);
+
+                    Ok(())
+                })
+                .unwrap_or_else(|failure| panic!("{failure}"));
+        }
+    }
+}
+
+#[test]
+fn response_round_trip() {
+    let mut runner = runner();
+
+    let mut api_keys = RootMessageMeta::messages()
+        .responses()
+        .values()
+        .map(|meta| (meta.api_key, meta.version.valid))
+        .collect::<Vec<_>>();
+    api_keys.sort_unstable();
+
+    for (api_key, valid) in api_keys {
+        let key = ApiKey::try_from(api_key).expect("api key");
+
+        for api_version in valid.start..=valid.end {
+            let strategy = arbitrary::response(api_key, api_version).expect("response");
+
+            runner
+                .run(&strategy, |encoded| {
+                    let frame = Frame::decode_response(&encoded, key, api_version)
+                        .map_err(error(api_key, api_version))?;
+
+                    let reencoded = Frame::encode_response(
+                        frame.header.clone(),
+                        frame.body.clone(),
+                        key,
+                        api_version,
+                    )
+                    .map_err(error(api_key, api_version))?;
+                    prop_assert_eq!(&encoded, &reencoded, "{} v{}", api_key, api_version);
+
+                    let decoded = Frame::decode_response(&reencoded, key, api_version)
+                        .map_err(error(api_key, api_version))?;
+                    prop_assert_eq!(frame.body, decoded.body, "{} v{}", api_key, api_version);
+
+                    Ok(())
+                })
+                .unwrap_or_else(|failure| panic!("{failure}"));
+        }
+    }
+}
+
+#[test]
+fn invalid_version() {
+    assert!(arbitrary::request(3, i16::MAX).is_none());
+    assert!(arbitrary::response(3, -1).is_none());
+}
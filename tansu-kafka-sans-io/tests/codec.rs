@@ -13,8 +13,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use bytes::Bytes;
 use std::{fs::File, sync::Arc, thread};
-use tansu_kafka_sans_io::{Error, Frame, Result};
+use tansu_kafka_sans_io::{primitive::uuid::Uuid, ApiKey, Body, Error, Frame, Result};
 use tracing::subscriber::DefaultGuard;
 use tracing_subscriber::fmt::format::FmtSpan;
 
@@ -49,6 +50,139 @@ fn init_tracing() -> Result<DefaultGuard> {
     ))
 }
 
+#[test]
+fn add_offsets_to_txn_request_v4_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 62, 0, 25, 0, 4, 0, 0, 0, 7, 0, 14, 112, 114, 111, 100, 117, 99, 101, 114, 45,
+        116, 120, 110, 45, 49, 0, 6, 116, 120, 110, 45, 49, 0, 0, 0, 0, 0, 0, 15, 160, 0, 0, 20,
+        116, 101, 115, 116, 45, 99, 111, 110, 115, 117, 109, 101, 114, 45, 103, 114, 111, 117, 112,
+        0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn add_offsets_to_txn_response_v4_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![0, 0, 0, 12, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    let api_key = ApiKey::AddOffsetsToTxn;
+    let api_version = 4;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn add_partitions_to_txn_request_v3_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 62, 0, 24, 0, 3, 0, 0, 0, 4, 0, 14, 112, 114, 111, 100, 117, 99, 101, 114, 45,
+        116, 120, 110, 45, 49, 0, 6, 116, 120, 110, 45, 49, 0, 0, 0, 0, 0, 0, 15, 160, 0, 0, 2, 5,
+        116, 101, 115, 116, 4, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn add_partitions_to_txn_request_v5_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 55, 0, 24, 0, 5, 0, 0, 0, 4, 0, 8, 98, 114, 111, 107, 101, 114, 45, 49, 0, 2, 6,
+        116, 120, 110, 45, 49, 0, 0, 0, 0, 0, 0, 15, 160, 0, 0, 0, 2, 5, 116, 101, 115, 116, 3, 0,
+        0, 0, 0, 0, 0, 0, 1, 0, 0, 0,
+    ];
+
+    let frame = Frame::request_from_bytes(&expected)?;
+
+    // from v4 the partitions are batched by transaction
+    assert!(matches!(
+        frame.body,
+        Body::AddPartitionsToTxnRequest {
+            transactions: Some(ref transactions),
+            v_3_and_below_transactional_id: None,
+            v_3_and_below_topics: None,
+            ..
+        } if transactions.len() == 1
+            && transactions[0].transactional_id == "txn-1"
+            && transactions[0].producer_id == 4_000
+            && !transactions[0].verify_only
+    ));
+
+    assert_eq!(expected, Frame::request(frame.header, frame.body)?);
+
+    Ok(())
+}
+
+#[test]
+fn add_partitions_to_txn_response_v3_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 39, 0, 0, 0, 4, 0, 0, 0, 0, 0, 2, 5, 116, 101, 115, 116, 4, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::AddPartitionsToTxn;
+    let api_version = 3;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn add_partitions_to_txn_response_v5_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 42, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 2, 6, 116, 120, 110, 45, 49, 2, 5, 116, 101,
+        115, 116, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::AddPartitionsToTxn;
+    let api_version = 5;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
 #[test]
 fn api_versions_request_v3_000() -> Result<()> {
     let _guard = init_tracing()?;
@@ -84,13 +218,14 @@ fn api_versions_response_v1_000() -> Result<()> {
         0, 0, 0, 0, 37, 0, 0, 0, 0, 0, 0, 0, 0,
     ];
 
-    let api_key = 18;
+    let api_key = ApiKey::ApiVersions;
     let api_version = 1;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -120,13 +255,345 @@ fn api_versions_response_v3_000() -> Result<()> {
         101, 114, 115, 105, 111, 110, 0, 14, 0, 14, 0,
     ];
 
-    let api_key = 18;
+    let api_key = ApiKey::ApiVersions;
+    let api_version = 3;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn api_versions_request_v3_unknown_tag() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    // api_versions_request_v3_000 with an unknown tag 7 in the body tag buffer
+    let expected = vec![
+        0, 0, 0, 56, 0, 18, 0, 3, 0, 0, 0, 3, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112,
+        114, 111, 100, 117, 99, 101, 114, 0, 18, 97, 112, 97, 99, 104, 101, 45, 107, 97, 102, 107,
+        97, 45, 106, 97, 118, 97, 6, 51, 46, 54, 46, 49, 1, 7, 2, 1, 2,
+    ];
+
+    let frame = Frame::request_from_bytes(&expected)?;
+
+    assert!(matches!(
+        frame.body,
+        Body::ApiVersionsRequest {
+            ref unknown_tagged_fields,
+            ..
+        } if unknown_tagged_fields[..] == [(7, Bytes::from_static(&[1, 2]))]
+    ));
+
+    assert_eq!(expected, Frame::request(frame.header, frame.body)?);
+
+    Ok(())
+}
+
+#[test]
+fn api_versions_response_v3_unknown_tag() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    // api_versions_response_v3_000 with an unknown tag 7 following the known tags 0, 1 and 2
+    let expected = vec![
+        0, 0, 1, 205, 0, 0, 0, 0, 0, 0, 56, 0, 0, 0, 0, 0, 9, 0, 0, 1, 0, 0, 0, 15, 0, 0, 2, 0, 0,
+        0, 8, 0, 0, 3, 0, 0, 0, 12, 0, 0, 8, 0, 0, 0, 8, 0, 0, 9, 0, 0, 0, 8, 0, 0, 10, 0, 0, 0, 4,
+        0, 0, 11, 0, 0, 0, 9, 0, 0, 12, 0, 0, 0, 4, 0, 0, 13, 0, 0, 0, 5, 0, 0, 14, 0, 0, 0, 5, 0,
+        0, 15, 0, 0, 0, 5, 0, 0, 16, 0, 0, 0, 4, 0, 0, 17, 0, 0, 0, 1, 0, 0, 18, 0, 0, 0, 3, 0, 0,
+        19, 0, 0, 0, 7, 0, 0, 20, 0, 0, 0, 6, 0, 0, 21, 0, 0, 0, 2, 0, 0, 22, 0, 0, 0, 4, 0, 0, 23,
+        0, 0, 0, 4, 0, 0, 24, 0, 0, 0, 4, 0, 0, 25, 0, 0, 0, 3, 0, 0, 26, 0, 0, 0, 3, 0, 0, 27, 0,
+        0, 0, 1, 0, 0, 28, 0, 0, 0, 3, 0, 0, 29, 0, 0, 0, 3, 0, 0, 30, 0, 0, 0, 3, 0, 0, 31, 0, 0,
+        0, 3, 0, 0, 32, 0, 0, 0, 4, 0, 0, 33, 0, 0, 0, 2, 0, 0, 34, 0, 0, 0, 2, 0, 0, 35, 0, 0, 0,
+        4, 0, 0, 36, 0, 0, 0, 2, 0, 0, 37, 0, 0, 0, 3, 0, 0, 38, 0, 0, 0, 3, 0, 0, 39, 0, 0, 0, 2,
+        0, 0, 40, 0, 0, 0, 2, 0, 0, 41, 0, 0, 0, 3, 0, 0, 42, 0, 0, 0, 2, 0, 0, 43, 0, 0, 0, 2, 0,
+        0, 44, 0, 0, 0, 1, 0, 0, 45, 0, 0, 0, 0, 0, 0, 46, 0, 0, 0, 0, 0, 0, 47, 0, 0, 0, 0, 0, 0,
+        48, 0, 0, 0, 1, 0, 0, 49, 0, 0, 0, 1, 0, 0, 50, 0, 0, 0, 0, 0, 0, 51, 0, 0, 0, 0, 0, 0, 55,
+        0, 0, 0, 1, 0, 0, 57, 0, 0, 0, 1, 0, 0, 60, 0, 0, 0, 0, 0, 0, 61, 0, 0, 0, 0, 0, 0, 64, 0,
+        0, 0, 0, 0, 0, 65, 0, 0, 0, 0, 0, 0, 66, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 23, 2, 17, 109,
+        101, 116, 97, 100, 97, 116, 97, 46, 118, 101, 114, 115, 105, 111, 110, 0, 1, 0, 14, 0, 1,
+        8, 0, 0, 0, 0, 0, 0, 0, 76, 2, 23, 2, 17, 109, 101, 116, 97, 100, 97, 116, 97, 46, 118,
+        101, 114, 115, 105, 111, 110, 0, 14, 0, 14, 0, 7, 2, 1, 2,
+    ];
+
+    let api_key = ApiKey::ApiVersions;
     let api_version = 3;
 
+    let frame = Frame::decode_response(&expected, api_key, api_version)?;
+
+    assert!(matches!(
+        frame.body,
+        Body::ApiVersionsResponse {
+            supported_features: Some(_),
+            finalized_features_epoch: Some(76),
+            finalized_features: Some(_),
+            ref unknown_tagged_fields,
+            ..
+        } if unknown_tagged_fields[..] == [(7, Bytes::from_static(&[1, 2]))]
+    ));
+
+    assert_eq!(
+        expected,
+        Frame::encode_response(frame.header, frame.body, api_key, api_version)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn begin_quorum_epoch_request_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 87, 0, 53, 0, 0, 0, 0, 0, 2, 0, 13, 114, 97, 102, 116, 45, 99, 108, 105, 101, 110,
+        116, 45, 49, 0, 22, 82, 118, 81, 119, 114, 89, 101, 103, 83, 85, 67, 107, 73, 80, 107, 97,
+        105, 65, 90, 81, 108, 81, 0, 0, 0, 1, 0, 18, 95, 95, 99, 108, 117, 115, 116, 101, 114, 95,
+        109, 101, 116, 97, 100, 97, 116, 97, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn begin_quorum_epoch_response_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 48, 0, 0, 0, 2, 0, 0, 0, 0, 0, 1, 0, 18, 95, 95, 99, 108, 117, 115, 116, 101, 114,
+        95, 109, 101, 116, 97, 100, 97, 116, 97, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0,
+        2,
+    ];
+
+    let api_key = ApiKey::BeginQuorumEpoch;
+    let api_version = 0;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn consumer_group_describe_request_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 47, 0, 69, 0, 0, 0, 0, 0, 6, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+        110, 116, 45, 49, 0, 2, 20, 116, 101, 115, 116, 45, 99, 111, 110, 115, 117, 109, 101, 114,
+        45, 103, 114, 111, 117, 112, 0, 0,
+    ];
+
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn consumer_group_describe_response_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 201, 0, 0, 0, 6, 0, 0, 0, 0, 0, 2, 0, 0, 0, 20, 116, 101, 115, 116, 45, 99, 111,
+        110, 115, 117, 109, 101, 114, 45, 103, 114, 111, 117, 112, 7, 83, 116, 97, 98, 108, 101, 0,
+        0, 0, 1, 0, 0, 0, 1, 8, 117, 110, 105, 102, 111, 114, 109, 2, 23, 120, 82, 81, 48, 101, 78,
+        78, 55, 83, 115, 105, 86, 81, 45, 100, 77, 88, 119, 112, 77, 52, 65, 0, 0, 0, 0, 0, 1, 16,
+        99, 111, 110, 115, 117, 109, 101, 114, 45, 116, 101, 115, 116, 45, 49, 11, 47, 49, 50, 55,
+        46, 48, 46, 48, 46, 49, 2, 5, 116, 101, 115, 116, 0, 2, 107, 30, 47, 92, 145, 58, 77, 126,
+        143, 16, 33, 50, 67, 84, 101, 118, 5, 116, 101, 115, 116, 4, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0,
+        0, 2, 0, 0, 2, 107, 30, 47, 92, 145, 58, 77, 126, 143, 16, 33, 50, 67, 84, 101, 118, 5,
+        116, 101, 115, 116, 4, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 128, 0, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::ConsumerGroupDescribe;
+    let api_version = 0;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn consumer_group_heartbeat_request_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 73, 0, 68, 0, 0, 0, 0, 0, 4, 0, 15, 99, 111, 110, 115, 117, 109, 101, 114, 45,
+        116, 101, 115, 116, 45, 49, 0, 20, 116, 101, 115, 116, 45, 99, 111, 110, 115, 117, 109,
+        101, 114, 45, 103, 114, 111, 117, 112, 1, 0, 0, 0, 0, 0, 0, 0, 4, 147, 224, 2, 5, 116, 101,
+        115, 116, 8, 117, 110, 105, 102, 111, 114, 109, 1, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn consumer_group_heartbeat_response_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 77, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 23, 120, 82, 81, 48, 101, 78, 78, 55, 83,
+        115, 105, 86, 81, 45, 100, 77, 88, 119, 112, 77, 52, 65, 0, 0, 0, 1, 0, 0, 19, 136, 1, 2,
+        107, 30, 47, 92, 145, 58, 77, 126, 143, 16, 33, 50, 67, 84, 101, 118, 4, 0, 0, 0, 0, 0, 0,
+        0, 1, 0, 0, 0, 2, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::ConsumerGroupHeartbeat;
+    let api_version = 0;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn consumer_group_heartbeat_response_v0_001() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 23, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 19, 136, 255, 0,
+    ];
+
+    let api_key = ApiKey::ConsumerGroupHeartbeat;
+    let api_version = 0;
+
+    let frame = Frame::decode_response(&expected, api_key, api_version)?;
+
+    assert!(matches!(
+        frame.body,
+        Body::ConsumerGroupHeartbeatResponse {
+            assignment: None,
+            ..
+        }
+    ));
+
+    assert_eq!(
+        expected,
+        Frame::encode_response(frame.header, frame.body, api_key, api_version)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn create_acls_request_v3_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 87, 0, 30, 0, 3, 0, 0, 0, 6, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+        110, 116, 45, 49, 0, 3, 2, 5, 116, 101, 115, 116, 3, 11, 85, 115, 101, 114, 58, 97, 108,
+        105, 99, 101, 2, 42, 3, 3, 0, 3, 20, 116, 101, 115, 116, 45, 99, 111, 110, 115, 117, 109,
+        101, 114, 45, 103, 114, 111, 117, 112, 4, 11, 85, 115, 101, 114, 58, 97, 108, 105, 99, 101,
+        2, 42, 3, 3, 0, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn create_acls_response_v3_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 19, 0, 0, 0, 6, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::CreateAcls;
+    let api_version = 3;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn create_topics_response_v7_topic_config_error_code() -> Result<()> {
+    use tansu_kafka_sans_io::{
+        create_topics_response::CreatableTopicResult, primitive::uuid::Uuid, Header,
+    };
+
+    let _guard = init_tracing()?;
+
+    let api_key = ApiKey::CreateTopics;
+    let api_version = 7;
+
+    let header = Header::Response { correlation_id: 5 };
+
+    let body = Body::CreateTopicsResponse {
+        throttle_time_ms: Some(0),
+        topics: Some(vec![CreatableTopicResult {
+            name: "abc".into(),
+            topic_id: Some(Uuid([1; 16])),
+            error_code: 0,
+            error_message: None,
+            topic_config_error_code: Some(42),
+            num_partitions: Some(3),
+            replication_factor: Some(1),
+            configs: None,
+        }]),
+        unknown_tagged_fields: vec![],
+    };
+
+    // the tagged topic config error code follows the replication factor and
+    // nullable configs, as tag 0 with a size of 2
+    let expected = vec![
+        0, 0, 0, 46, 0, 0, 0, 5, 0, 0, 0, 0, 0, 2, 4, 97, 98, 99, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+        1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 3, 0, 1, 0, 1, 0, 2, 0, 42, 0,
+    ];
+
+    let encoded = Frame::encode_response(header.clone(), body.clone(), api_key, api_version)?;
+    assert_eq!(expected, encoded);
+
+    assert_eq!(
+        Frame {
+            size: 46,
+            header,
+            body
+        },
+        Frame::decode_response(&encoded, api_key, api_version)?
     );
 
     Ok(())
@@ -213,13 +680,74 @@ fn create_topics_response_v7_000() -> Result<()> {
         101, 110, 97, 98, 108, 101, 6, 102, 97, 108, 115, 101, 0, 5, 0, 0, 0, 0,
     ];
 
-    let api_key = 19;
+    let api_key = ApiKey::CreateTopics;
     let api_version = 7;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn delete_acls_request_v3_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 48, 0, 31, 0, 3, 0, 0, 0, 7, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+        110, 116, 45, 49, 0, 2, 2, 5, 116, 101, 115, 116, 3, 11, 85, 115, 101, 114, 58, 97, 108,
+        105, 99, 101, 0, 1, 1, 0, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn delete_acls_response_v3_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 42, 0, 0, 0, 7, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 2, 5, 116, 101, 115, 116,
+        3, 11, 85, 115, 101, 114, 58, 97, 108, 105, 99, 101, 2, 42, 3, 3, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::DeleteAcls;
+    let api_version = 3;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn delete_records_request_v2_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 49, 0, 21, 0, 2, 0, 0, 0, 5, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+        110, 116, 45, 49, 0, 2, 4, 112, 113, 114, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0,
+        0, 117, 48, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
     );
 
     Ok(())
@@ -244,6 +772,47 @@ fn delete_topics_request_v6_000() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn describe_acls_request_v3_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 36, 0, 29, 0, 3, 0, 0, 0, 5, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+        110, 116, 45, 49, 0, 2, 5, 116, 101, 115, 116, 3, 0, 0, 1, 1, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_acls_response_v3_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 53, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 5, 116, 101, 115, 116, 3, 3, 11, 85,
+        115, 101, 114, 58, 97, 108, 105, 99, 101, 2, 42, 3, 3, 0, 9, 85, 115, 101, 114, 58, 98,
+        111, 98, 2, 42, 4, 2, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::DescribeAcls;
+    let api_version = 3;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
 #[test]
 fn describe_cluster_request_v1_000() -> Result<()> {
     let _guard = init_tracing()?;
@@ -400,24 +969,113 @@ fn describe_configs_response_v4_001() -> Result<()> {
         9, 49, 48, 52, 56, 53, 55, 54, 48, 5, 0, 3, 0, 0, 0, 0,
     ];
 
-    let api_key = 32;
-    let api_version = 4;
+    let api_key = ApiKey::DescribeConfigs;
+    let api_version = 4;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_groups_request_v1_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 22, 0, 15, 0, 1, 0, 0, 0, 0, 255, 255, 0, 0, 0, 1, 0, 6, 97, 98, 99, 97, 98, 99,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_groups_response_v1_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 16, 0, 6, 97, 98, 99, 97, 98, 99, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::DescribeGroups;
+    let api_version = 1;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_quorum_request_v1_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 52, 0, 55, 0, 1, 0, 0, 0, 4, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+        110, 116, 45, 49, 0, 2, 19, 95, 95, 99, 108, 117, 115, 116, 101, 114, 95, 109, 101, 116,
+        97, 100, 97, 116, 97, 2, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_quorum_response_v1_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 142, 0, 0, 0, 4, 0, 0, 0, 2, 19, 95, 95, 99, 108, 117, 115, 116, 101, 114, 95,
+        109, 101, 116, 97, 100, 97, 116, 97, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0,
+        0, 0, 0, 0, 42, 3, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 42, 0, 0, 1, 141, 116, 152, 137, 53, 0,
+        0, 1, 141, 116, 152, 137, 53, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 40, 0, 0, 1, 141, 116,
+        152, 136, 244, 0, 0, 1, 141, 116, 152, 136, 144, 0, 2, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 42,
+        0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::DescribeQuorum;
+    let api_version = 1;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
 }
 
 #[test]
-fn describe_groups_request_v1_000() -> Result<()> {
+fn end_quorum_epoch_request_v0_000() -> Result<()> {
     let _guard = init_tracing()?;
 
     let expected = vec![
-        0, 0, 0, 22, 0, 15, 0, 1, 0, 0, 0, 0, 255, 255, 0, 0, 0, 1, 0, 6, 97, 98, 99, 97, 98, 99,
+        0, 0, 0, 99, 0, 54, 0, 0, 0, 0, 0, 3, 0, 13, 114, 97, 102, 116, 45, 99, 108, 105, 101, 110,
+        116, 45, 49, 0, 22, 82, 118, 81, 119, 114, 89, 101, 103, 83, 85, 67, 107, 73, 80, 107, 97,
+        105, 65, 90, 81, 108, 81, 0, 0, 0, 1, 0, 18, 95, 95, 99, 108, 117, 115, 116, 101, 114, 95,
+        109, 101, 116, 97, 100, 97, 116, 97, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0,
+        0, 2, 0, 0, 0, 2, 0, 0, 0, 3,
     ];
 
     assert_eq!(
@@ -430,21 +1088,60 @@ fn describe_groups_request_v1_000() -> Result<()> {
 }
 
 #[test]
-fn describe_groups_response_v1_000() -> Result<()> {
+fn end_quorum_epoch_response_v0_000() -> Result<()> {
     let _guard = init_tracing()?;
 
     let expected = vec![
-        0, 0, 0, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 16, 0, 6, 97, 98, 99, 97, 98, 99, 0, 0,
-        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 48, 0, 0, 0, 3, 0, 0, 0, 0, 0, 1, 0, 18, 95, 95, 99, 108, 117, 115, 116, 101, 114,
+        95, 109, 101, 116, 97, 100, 97, 116, 97, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255,
+        0, 0, 0, 2,
     ];
 
-    let api_key = 15;
-    let api_version = 1;
+    let api_key = ApiKey::EndQuorumEpoch;
+    let api_version = 0;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn end_txn_request_v4_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 43, 0, 26, 0, 4, 0, 0, 0, 9, 0, 14, 112, 114, 111, 100, 117, 99, 101, 114, 45,
+        116, 120, 110, 45, 49, 0, 6, 116, 120, 110, 45, 49, 0, 0, 0, 0, 0, 0, 15, 160, 0, 0, 1, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn end_txn_response_v4_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![0, 0, 0, 12, 0, 0, 0, 9, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    let api_key = ApiKey::EndTxn;
+    let api_version = 4;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -516,7 +1213,7 @@ fn fetch_request_v16_000() -> Result<()> {
 fn fetch_response_v12_000() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 12;
 
     let expected = vec![
@@ -531,8 +1228,9 @@ fn fetch_response_v12_000() -> Result<()> {
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -542,7 +1240,7 @@ fn fetch_response_v12_000() -> Result<()> {
 fn fetch_response_v12_001() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 12;
 
     let expected = vec![
@@ -561,8 +1259,9 @@ fn fetch_response_v12_001() -> Result<()> {
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -572,7 +1271,7 @@ fn fetch_response_v12_001() -> Result<()> {
 fn fetch_response_v12_002() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 12;
 
     let expected = vec![
@@ -594,8 +1293,61 @@ fn fetch_response_v12_002() -> Result<()> {
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn fetch_snapshot_request_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 110, 0, 59, 0, 0, 0, 0, 0, 5, 0, 13, 114, 97, 102, 116, 45, 99, 108, 105, 101,
+        110, 116, 45, 50, 0, 0, 0, 0, 2, 0, 16, 0, 0, 2, 19, 95, 95, 99, 108, 117, 115, 116, 101,
+        114, 95, 109, 101, 116, 97, 100, 97, 116, 97, 2, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0,
+        0, 42, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 23, 23, 82, 118, 81, 119, 114,
+        89, 101, 103, 83, 85, 67, 107, 73, 80, 107, 97, 105, 65, 90, 81, 108, 81,
+    ];
+
+    let frame = Frame::request_from_bytes(&expected)?;
+
+    // the cluster id is a tagged field
+    assert!(matches!(
+        &frame.body,
+        Body::FetchSnapshotRequest {
+            cluster_id: Some(cluster_id),
+            ..
+        } if cluster_id == "RvQwrYegSUCkIPkaiAZQlQ"
+    ));
+
+    assert_eq!(expected, Frame::request(frame.header, frame.body)?);
+
+    Ok(())
+}
+
+#[test]
+fn fetch_snapshot_response_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 83, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 2, 19, 95, 95, 99, 108, 117, 115, 116, 101,
+        114, 95, 109, 101, 116, 97, 100, 97, 116, 97, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 42,
+        0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 13, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 59, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::FetchSnapshot;
+    let api_version = 0;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -666,13 +1418,14 @@ fn find_coordinator_response_v1_000() -> Result<()> {
         111, 109, 112, 117, 116, 101, 46, 105, 110, 116, 101, 114, 110, 97, 108, 0, 0, 35, 132,
     ];
 
-    let api_key = 10;
+    let api_key = ApiKey::FindCoordinator;
     let api_version = 1;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -687,13 +1440,14 @@ fn find_coordinator_response_v2_000() -> Result<()> {
         48, 46, 48, 46, 49, 0, 0, 35, 132,
     ];
 
-    let api_key = 10;
+    let api_key = ApiKey::FindCoordinator;
     let api_version = 2;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -778,13 +1532,14 @@ fn join_group_response_v5_000() -> Result<()> {
         0,
     ];
 
-    let api_key = 11;
+    let api_key = ApiKey::JoinGroup;
     let api_version = 5;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -863,13 +1618,14 @@ fn list_offsets_response_v0_000() -> Result<()> {
         252, 0, 0, 0, 0, 0, 17, 198, 100, 0, 0, 0, 0, 0, 0, 0, 0,
     ];
 
-    let api_key = 2;
+    let api_key = ApiKey::ListOffsets;
     let api_version = 0;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -928,13 +1684,14 @@ fn metadata_response_v1_0000() -> Result<()> {
         0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1,
     ];
 
-    let api_key = 3;
+    let api_key = ApiKey::Metadata;
     let api_version = 1;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -988,13 +1745,61 @@ fn metadata_response_v12_0000() -> Result<()> {
         115, 116, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 128, 0, 0, 0, 0, 0,
     ];
 
-    let api_key = 3;
+    let api_key = ApiKey::Metadata;
     let api_version = 12;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn metadata_response_v12_0001() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    // the benchmark topic has a non nil topic id
+    let expected = vec![
+        0, 0, 1, 20, 0, 0, 0, 2, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 10, 108, 111, 99, 97, 108, 104, 111,
+        115, 116, 0, 0, 35, 132, 0, 0, 23, 53, 76, 54, 103, 51, 110, 83, 104, 84, 45, 101, 77, 67,
+        116, 75, 45, 45, 88, 56, 54, 115, 119, 0, 0, 0, 1, 2, 0, 0, 10, 98, 101, 110, 99, 104, 109,
+        97, 114, 107, 177, 248, 14, 236, 65, 78, 72, 57, 179, 196, 215, 75, 145, 238, 120, 241, 0,
+        8, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 2, 0, 0, 0, 1, 2, 0, 0, 0, 1, 1, 0, 0, 0, 0,
+        0, 0, 3, 0, 0, 0, 1, 0, 0, 0, 0, 2, 0, 0, 0, 1, 2, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 6, 0,
+        0, 0, 1, 0, 0, 0, 0, 2, 0, 0, 0, 1, 2, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 0,
+        0, 0, 0, 2, 0, 0, 0, 1, 2, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 1, 0, 0, 0, 0, 2,
+        0, 0, 0, 1, 2, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 2, 0, 0, 0, 1,
+        2, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 0, 2, 0, 0, 0, 1, 2, 0, 0, 0,
+        1, 1, 0, 128, 0, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::Metadata;
+    let api_version = 12;
+
+    let frame = Frame::decode_response(&expected, api_key, api_version)?;
+
+    let Body::MetadataResponse {
+        topics: Some(ref topics),
+        ..
+    } = frame.body
+    else {
+        panic!("expected a metadata response with topics")
+    };
+
+    assert_eq!(
+        Some(Uuid([
+            177, 248, 14, 236, 65, 78, 72, 57, 179, 196, 215, 75, 145, 238, 120, 241
+        ])),
+        topics[0].topic_id
+    );
+
+    assert_eq!(
+        expected,
+        Frame::encode_response(frame.header, frame.body, api_key, api_version)?
     );
 
     Ok(())
@@ -1061,6 +1866,27 @@ fn offset_fetch_request_v9_000() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn offset_fetch_request_v9_001() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 105, 0, 9, 0, 9, 0, 0, 0, 7, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 99, 111,
+        110, 115, 117, 109, 101, 114, 0, 3, 20, 116, 101, 115, 116, 45, 99, 111, 110, 115, 117,
+        109, 101, 114, 45, 103, 114, 111, 117, 112, 0, 255, 255, 255, 255, 2, 5, 116, 101, 115,
+        116, 4, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 12, 111, 116, 104, 101, 114, 45, 103,
+        114, 111, 117, 112, 0, 255, 255, 255, 255, 2, 4, 97, 98, 99, 2, 0, 0, 0, 0, 0, 0, 1, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
 #[test]
 fn offset_fetch_response_v7_000() -> Result<()> {
     let _guard = init_tracing()?;
@@ -1076,13 +1902,38 @@ fn offset_fetch_response_v7_000() -> Result<()> {
         255, 255, 255, 255, 255, 255, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0,
     ];
 
-    let api_key = 9;
+    let api_key = ApiKey::OffsetFetch;
     let api_version = 7;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn offset_fetch_response_v9_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 78, 0, 0, 0, 7, 0, 0, 0, 0, 0, 3, 20, 116, 101, 115, 116, 45, 99, 111, 110, 115,
+        117, 109, 101, 114, 45, 103, 114, 111, 117, 112, 2, 5, 116, 101, 115, 116, 2, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 5, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 12, 111, 116, 104, 101,
+        114, 45, 103, 114, 111, 117, 112, 1, 0, 69, 0, 0,
+    ];
+
+    let api_key = ApiKey::OffsetFetch;
+    let api_version = 9;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -1163,13 +2014,14 @@ fn produce_response_v9_000() -> Result<()> {
         0, 0, 0,
     ];
 
-    let api_key = 0;
+    let api_key = ApiKey::Produce;
     let api_version = 9;
 
     assert_eq!(
         expected,
-        Frame::response_from_bytes(&expected, api_key, api_version)
-            .and_then(|frame| Frame::response(frame.header, frame.body, api_key, api_version))?
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
     );
 
     Ok(())
@@ -1196,3 +2048,199 @@ pub fn sync_group_request_v5_000() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn txn_offset_commit_request_v4_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 112, 0, 28, 0, 4, 0, 0, 0, 8, 0, 14, 112, 114, 111, 100, 117, 99, 101, 114, 45,
+        116, 120, 110, 45, 49, 0, 6, 116, 120, 110, 45, 49, 20, 116, 101, 115, 116, 45, 99, 111,
+        110, 115, 117, 109, 101, 114, 45, 103, 114, 111, 117, 112, 0, 0, 0, 0, 0, 0, 15, 160, 0, 0,
+        255, 255, 255, 255, 1, 0, 2, 5, 116, 101, 115, 116, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 42,
+        255, 255, 255, 255, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 17, 255, 255, 255, 255, 0, 0, 0,
+        0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn txn_offset_commit_response_v4_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 32, 0, 0, 0, 8, 0, 0, 0, 0, 0, 2, 5, 116, 101, 115, 116, 3, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 1, 0, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::TxnOffsetCommit;
+    let api_version = 4;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn vote_request_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 95, 0, 52, 0, 0, 0, 0, 0, 1, 0, 13, 114, 97, 102, 116, 45, 99, 108, 105, 101, 110,
+        116, 45, 50, 0, 23, 82, 118, 81, 119, 114, 89, 101, 103, 83, 85, 67, 107, 73, 80, 107, 97,
+        105, 65, 90, 81, 108, 81, 2, 19, 95, 95, 99, 108, 117, 115, 116, 101, 114, 95, 109, 101,
+        116, 97, 100, 97, 116, 97, 2, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0,
+        0, 0, 0, 9, 0, 0, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn vote_response_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 46, 0, 0, 0, 1, 0, 0, 0, 2, 19, 95, 95, 99, 108, 117, 115, 116, 101, 114, 95, 109,
+        101, 116, 97, 100, 97, 116, 97, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 1, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::Vote;
+    let api_version = 0;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_txn_markers_request_v1_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 53, 0, 27, 0, 1, 0, 0, 0, 11, 0, 8, 98, 114, 111, 107, 101, 114, 45, 49, 0, 2, 0,
+        0, 0, 0, 0, 0, 15, 160, 0, 0, 1, 2, 5, 116, 101, 115, 116, 3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0,
+        0, 0, 0, 0, 0,
+    ];
+
+    assert_eq!(
+        expected,
+        Frame::request_from_bytes(&expected)
+            .and_then(|frame| Frame::request(frame.header, frame.body))?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_txn_markers_response_v1_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let expected = vec![
+        0, 0, 0, 38, 0, 0, 0, 11, 0, 2, 0, 0, 0, 0, 0, 0, 15, 160, 2, 5, 116, 101, 115, 116, 3, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::WriteTxnMarkers;
+    let api_version = 1;
+
+    assert_eq!(
+        expected,
+        Frame::decode_response(&expected, api_key, api_version).and_then(|frame| {
+            Frame::encode_response(frame.header, frame.body, api_key, api_version)
+        })?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn check_incomplete_size_prefix() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    assert!(matches!(
+        Frame::check(&[]),
+        Err(Error::Incomplete { needed: Some(4) })
+    ));
+
+    assert!(matches!(
+        Frame::check(&[0, 0, 0]),
+        Err(Error::Incomplete { needed: Some(1) })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn check_incomplete_body() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let frame = vec![
+        0, 0, 0, 52, 0, 18, 0, 3, 0, 0, 0, 3, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112,
+        114, 111, 100, 117, 99, 101, 114, 0, 18, 97, 112, 97, 99, 104, 101, 45, 107, 97, 102, 107,
+        97, 45, 106, 97, 118, 97, 6, 51, 46, 54, 46, 49, 0,
+    ];
+
+    for split in 4..frame.len() {
+        let needed = frame.len() - split;
+
+        assert!(matches!(
+            Frame::check(&frame[..split]),
+            Err(Error::Incomplete { needed: Some(n) }) if n == needed
+        ));
+
+        assert!(matches!(
+            Frame::request_from_bytes(&frame[..split]),
+            Err(Error::Incomplete { needed: Some(n) }) if n == needed
+        ));
+    }
+
+    assert_eq!(frame.len(), Frame::check(&frame)?);
+
+    // a complete frame followed by the start of the next
+    let mut pipelined = frame.clone();
+    pipelined.extend_from_slice(&frame[..7]);
+    assert_eq!(frame.len(), Frame::check(&pipelined)?);
+
+    assert_eq!(
+        Frame::request_from_bytes(&frame)?,
+        Frame::request_from_bytes(&pipelined)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn check_negative_length() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    assert!(matches!(
+        Frame::check(&[255, 255, 255, 254, 0, 18]),
+        Err(Error::InvalidFrameLength(-2))
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,176 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use bytes::Bytes;
+use tansu_kafka_sans_io::{
+    consumer::{ConsumerProtocolAssignment, ConsumerProtocolSubscription, TopicPartition},
+    Error, Result,
+};
+
+// the range protocol metadata of join_group_request_v9_000 from the java consumer
+const RANGE_SUBSCRIPTION_V3: &[u8] =
+    b"\0\x03\0\0\0\x01\0\x04test\xff\xff\xff\xff\0\0\0\0\xff\xff\xff\xff\xff\xff";
+
+// the cooperative-sticky protocol metadata of join_group_request_v9_000
+const STICKY_SUBSCRIPTION_V3: &[u8] =
+    b"\0\x03\0\0\0\x01\0\x04test\0\0\0\x04\xff\xff\xff\xff\0\0\0\0\xff\xff\xff\xff\xff\xff";
+
+#[test]
+fn range_subscription_v3() -> Result<()> {
+    let encoded = Bytes::from_static(RANGE_SUBSCRIPTION_V3);
+
+    let subscription = ConsumerProtocolSubscription::try_from(encoded.clone())?;
+
+    assert_eq!(
+        ConsumerProtocolSubscription {
+            version: 3,
+            topics: vec!["test".into()],
+            user_data: None,
+            owned_partitions: vec![],
+            generation_id: -1,
+            rack_id: None,
+        },
+        subscription
+    );
+
+    assert_eq!(encoded, subscription.encode()?);
+
+    Ok(())
+}
+
+#[test]
+fn sticky_subscription_v3() -> Result<()> {
+    let encoded = Bytes::from_static(STICKY_SUBSCRIPTION_V3);
+
+    let subscription = ConsumerProtocolSubscription::try_from(encoded.clone())?;
+
+    assert_eq!(
+        ConsumerProtocolSubscription {
+            version: 3,
+            topics: vec!["test".into()],
+            user_data: Some(Bytes::from_static(b"\xff\xff\xff\xff")),
+            owned_partitions: vec![],
+            generation_id: -1,
+            rack_id: None,
+        },
+        subscription
+    );
+
+    assert_eq!(encoded, subscription.encode()?);
+
+    Ok(())
+}
+
+#[test]
+fn subscription_versions() -> Result<()> {
+    let subscription = ConsumerProtocolSubscription {
+        topics: vec!["abc".into(), "pqr".into()],
+        user_data: Some(Bytes::from_static(b"user")),
+        owned_partitions: vec![TopicPartition::new("abc", &[0, 2])],
+        generation_id: 6,
+        rack_id: Some("rack-1".into()),
+        ..Default::default()
+    };
+
+    for version in 0..=3 {
+        let versioned = ConsumerProtocolSubscription {
+            version,
+            ..subscription.clone()
+        };
+
+        let decoded = ConsumerProtocolSubscription::try_from(versioned.encode()?)?;
+
+        assert_eq!(version, decoded.version);
+        assert_eq!(subscription.topics, decoded.topics);
+        assert_eq!(subscription.user_data, decoded.user_data);
+
+        if version >= 1 {
+            assert_eq!(subscription.owned_partitions, decoded.owned_partitions);
+        } else {
+            assert!(decoded.owned_partitions.is_empty());
+        }
+
+        assert_eq!(if version >= 2 { 6 } else { -1 }, decoded.generation_id);
+
+        assert_eq!(
+            if version >= 3 { Some("rack-1") } else { None },
+            decoded.rack_id.as_deref()
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn newer_subscription_version() -> Result<()> {
+    // a future version is parsed with the current format, ignoring trailing fields
+    let mut encoded = RANGE_SUBSCRIPTION_V3.to_vec();
+    encoded[1] = 4;
+    encoded.extend_from_slice(b"trailing");
+
+    let subscription = ConsumerProtocolSubscription::try_from(Bytes::from(encoded))?;
+    assert_eq!(4, subscription.version);
+    assert_eq!(vec![String::from("test")], subscription.topics);
+
+    Ok(())
+}
+
+#[test]
+fn truncated_subscription() {
+    let encoded = Bytes::from_static(&RANGE_SUBSCRIPTION_V3[..10]);
+
+    assert!(matches!(
+        ConsumerProtocolSubscription::try_from(encoded),
+        Err(Error::Io(_))
+    ));
+}
+
+#[test]
+fn negative_version() {
+    assert!(matches!(
+        ConsumerProtocolAssignment::try_from(Bytes::from_static(
+            b"\xff\xfe\0\0\0\0\xff\xff\xff\xff"
+        )),
+        Err(Error::InvalidConsumerProtocolVersion(-2))
+    ));
+}
+
+#[test]
+fn assignment() -> Result<()> {
+    let assignment = ConsumerProtocolAssignment {
+        version: 0,
+        assigned_partitions: vec![
+            TopicPartition::new("test", &[0, 1, 2]),
+            TopicPartition::new("other", &[3]),
+        ],
+        user_data: None,
+    };
+
+    let encoded = assignment.encode()?;
+
+    assert_eq!(
+        Bytes::from_static(
+            b"\0\0\0\0\0\x02\
+              \0\x04test\0\0\0\x03\0\0\0\0\0\0\0\x01\0\0\0\x02\
+              \0\x05other\0\0\0\x01\0\0\0\x03\
+              \xff\xff\xff\xff"
+        ),
+        encoded
+    );
+
+    assert_eq!(assignment, ConsumerProtocolAssignment::try_from(encoded)?);
+
+    Ok(())
+}
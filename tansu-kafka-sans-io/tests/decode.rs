@@ -14,9 +14,10 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use bytes::Bytes;
-use serde::Deserialize;
+use serde::{de::IgnoredAny, Deserialize};
 use std::{fs::File, io::Cursor, sync::Arc, thread};
 use tansu_kafka_sans_io::{
+    controlled_shutdown_response::RemainingPartition,
     de::Decoder,
     describe_configs_response::{
         DescribeConfigsResourceResult, DescribeConfigsResult, DescribeConfigsSynonym,
@@ -30,8 +31,9 @@ use tansu_kafka_sans_io::{
     metadata_request::MetadataRequestTopic,
     metadata_response::{MetadataResponseBroker, MetadataResponsePartition, MetadataResponseTopic},
     offset_fetch_response::{OffsetFetchResponsePartition, OffsetFetchResponseTopic},
+    primitive::uuid::Uuid,
     record::{self, deflated, inflated, Record},
-    Body, Error, ErrorCode, Frame, Header, Result,
+    ApiKey, Body, Error, ErrorCode, Frame, Header, Result, DEFAULT_MAX_FRAME_BYTES,
 };
 use tracing::{debug, subscriber::DefaultGuard};
 use tracing_subscriber::fmt::format::FmtSpan;
@@ -92,6 +94,7 @@ fn api_versions_request_v3_000() -> Result<()> {
             body: Body::ApiVersionsRequest {
                 client_software_name: Some("apache-kafka-java".into()),
                 client_software_version: Some("3.6.1".into()),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -119,7 +122,7 @@ fn api_versions_response_v1_000() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, 18, 1);
+    let mut deserializer = Decoder::response(&mut c, 18, 1)?;
 
     assert_eq!(
         Frame {
@@ -327,6 +330,7 @@ fn api_versions_response_v1_000() -> Result<()> {
                     .into()
                 ),
                 throttle_time_ms: Some(0),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -364,7 +368,7 @@ fn api_versions_response_v3_000() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, 18, 3);
+    let mut deserializer = Decoder::response(&mut c, 18, 3)?;
 
     assert_eq!(
         Frame {
@@ -665,6 +669,71 @@ fn api_versions_response_v3_000() -> Result<()> {
                     .into()
                 ),
                 throttle_time_ms: Some(0),
+                unknown_tagged_fields: vec![],
+            }
+        },
+        Frame::deserialize(&mut deserializer)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn controlled_shutdown_request_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    // a version 0 request header has no client id
+    let v = vec![0, 0, 0, 12, 0, 7, 0, 0, 0, 0, 0, 1, 0, 0, 0, 5];
+
+    let mut c = Cursor::new(v);
+    let mut deserializer = Decoder::request(&mut c);
+
+    assert_eq!(
+        Frame {
+            size: 12,
+            header: Header::Request {
+                api_key: 7,
+                api_version: 0,
+                correlation_id: 1,
+                client_id: None,
+            },
+            body: Body::ControlledShutdownRequest {
+                broker_id: 5,
+                broker_epoch: None,
+                unknown_tagged_fields: vec![],
+            }
+        },
+        Frame::deserialize(&mut deserializer)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn controlled_shutdown_response_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let v = vec![
+        0, 0, 0, 17, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 1, 116, 0, 0, 0, 3,
+    ];
+
+    let mut c = Cursor::new(v);
+    let mut deserializer = Decoder::response(&mut c, 7, 0)?;
+
+    assert_eq!(
+        Frame {
+            size: 17,
+            header: Header::Response { correlation_id: 1 },
+            body: Body::ControlledShutdownResponse {
+                error_code: 0,
+                remaining_partitions: Some(
+                    [RemainingPartition {
+                        topic_name: "t".into(),
+                        partition_index: 3,
+                    }]
+                    .into()
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -720,6 +789,7 @@ fn create_topics_request_v7_000() -> Result<()> {
                 ),
                 timeout_ms,
                 validate_only,
+                unknown_tagged_fields: vec![],
             },
         },
         Frame::deserialize(&mut deserializer)?
@@ -792,7 +862,7 @@ fn create_topics_response_v7_000() -> Result<()> {
         101, 110, 97, 98, 108, 101, 6, 102, 97, 108, 115, 101, 0, 5, 0, 0, 0, 0,
     ];
 
-    let api_key = 19;
+    let api_key = ApiKey::CreateTopics;
     let api_version = 7;
 
     let frame = Frame {
@@ -805,9 +875,9 @@ fn create_topics_response_v7_000() -> Result<()> {
             topics: Some(
                 [CreatableTopicResult {
                     name: "balances".into(),
-                    topic_id: Some([
+                    topic_id: Some(Uuid([
                         222, 159, 182, 217, 102, 152, 68, 189, 174, 152, 214, 59, 29, 216, 240, 198,
-                    ]),
+                    ])),
                     error_code: 0,
                     error_message: None,
                     topic_config_error_code: None,
@@ -1038,13 +1108,13 @@ fn create_topics_response_v7_000() -> Result<()> {
                 }]
                 .into(),
             ),
+            unknown_tagged_fields: vec![],
         },
     };
 
     assert_eq!(
         frame,
-        Frame::response_from_bytes(&encoded, api_key, api_version)
-            .inspect(|frame| debug!(?frame))?
+        Frame::decode_response(&encoded, api_key, api_version).inspect(|frame| debug!(?frame))?
     );
 
     Ok(())
@@ -1075,12 +1145,13 @@ fn delete_topics_request_v6_000() -> Result<()> {
                 topics: Some(
                     [DeleteTopicState {
                         name: Some("test".into()),
-                        topic_id: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+                        topic_id: Uuid([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
                     }]
                     .into()
                 ),
                 topic_names: None,
-                timeout_ms: 30000
+                timeout_ms: 30000,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -1109,7 +1180,8 @@ fn describe_cluster_request_v1_000() -> Result<()> {
             },
             body: Body::DescribeClusterRequest {
                 include_cluster_authorized_operations: false,
-                endpoint_type: Some(1)
+                endpoint_type: Some(1),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -1148,7 +1220,8 @@ fn describe_configs_request_v4_000() -> Result<()> {
                     .into()
                 ),
                 include_synonyms: Some(false),
-                include_documentation: Some(false)
+                include_documentation: Some(false),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -1187,7 +1260,8 @@ fn describe_configs_request_v4_001() -> Result<()> {
                     .into()
                 ),
                 include_synonyms: Some(true),
-                include_documentation: Some(false)
+                include_documentation: Some(false),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -1316,7 +1390,7 @@ fn describe_configs_response_v4_001() -> Result<()> {
         9, 49, 48, 52, 56, 53, 55, 54, 48, 5, 0, 3, 0, 0, 0, 0,
     ];
 
-    let api_key = 32;
+    let api_key = ApiKey::DescribeConfigs;
     let api_version = 4;
 
     assert_eq!(
@@ -1922,10 +1996,11 @@ fn describe_configs_response_v4_001() -> Result<()> {
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
-        Frame::response_from_bytes(&v, api_key, api_version)?
+        Frame::decode_response(&v, api_key, api_version)?
     );
 
     Ok(())
@@ -1954,6 +2029,7 @@ fn describe_groups_request_v1_000() -> Result<()> {
             body: Body::DescribeGroupsRequest {
                 groups: Some(["abcabc".into()].into()),
                 include_authorized_operations: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -1972,7 +2048,7 @@ fn describe_groups_response_v1_000() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, 15, 1);
+    let mut deserializer = Decoder::response(&mut c, 15, 1)?;
 
     assert_eq!(
         Frame {
@@ -1992,6 +2068,7 @@ fn describe_groups_response_v1_000() -> Result<()> {
                     }]
                     .into()
                 ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -2000,6 +2077,212 @@ fn describe_groups_response_v1_000() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn describe_producers_request_v0_000() -> Result<()> {
+    use tansu_kafka_sans_io::describe_producers_request::TopicRequest;
+
+    let _guard = init_tracing()?;
+
+    let v = vec![
+        0, 0, 0, 41, 0, 61, 0, 0, 0, 0, 0, 5, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+        110, 116, 45, 49, 0, 2, 5, 116, 101, 115, 116, 3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0,
+    ];
+
+    assert_eq!(
+        Frame {
+            size: 41,
+            header: Header::Request {
+                api_key: 61,
+                api_version: 0,
+                correlation_id: 5,
+                client_id: Some("adminclient-1".into())
+            },
+            body: Body::DescribeProducersRequest {
+                topics: Some(
+                    [TopicRequest {
+                        name: "test".into(),
+                        partition_indexes: Some([0, 1].into()),
+                    }]
+                    .into()
+                ),
+                unknown_tagged_fields: vec![],
+            }
+        },
+        Frame::request_from_bytes(&v)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_producers_response_v0_000() -> Result<()> {
+    use tansu_kafka_sans_io::describe_producers_response::{
+        PartitionResponse, ProducerState, TopicResponse,
+    };
+
+    let _guard = init_tracing()?;
+
+    let api_key = ApiKey::DescribeProducers;
+    let api_version = 0;
+
+    let v = vec![
+        0, 0, 0, 110, 0, 0, 0, 5, 0, 0, 0, 0, 0, 2, 5, 116, 101, 115, 116, 3, 0, 0, 0, 0, 0, 0, 0,
+        3, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 1, 146, 204, 9, 20, 0, 255, 255,
+        255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 1, 0,
+        0, 0, 4, 0, 0, 1, 146, 204, 9, 23, 232, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0,
+        0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0,
+    ];
+
+    assert_eq!(
+        Frame {
+            size: 110,
+            header: Header::Response { correlation_id: 5 },
+            body: Body::DescribeProducersResponse {
+                throttle_time_ms: 0,
+                topics: Some(
+                    [TopicResponse {
+                        name: "test".into(),
+                        partitions: Some(
+                            [
+                                PartitionResponse {
+                                    partition_index: 0,
+                                    error_code: 0,
+                                    error_message: None,
+                                    active_producers: Some(
+                                        [
+                                            ProducerState {
+                                                producer_id: 6,
+                                                producer_epoch: 0,
+                                                last_sequence: 2,
+                                                last_timestamp: 1_730_000_000_000,
+                                                coordinator_epoch: -1,
+                                                current_txn_start_offset: -1,
+                                            },
+                                            ProducerState {
+                                                producer_id: 7,
+                                                producer_epoch: 1,
+                                                last_sequence: 4,
+                                                last_timestamp: 1_730_000_001_000,
+                                                coordinator_epoch: -1,
+                                                current_txn_start_offset: 3,
+                                            }
+                                        ]
+                                        .into()
+                                    ),
+                                },
+                                PartitionResponse {
+                                    partition_index: 1,
+                                    error_code: 0,
+                                    error_message: None,
+                                    active_producers: Some([].into()),
+                                }
+                            ]
+                            .into()
+                        ),
+                    }]
+                    .into()
+                ),
+                unknown_tagged_fields: vec![],
+            }
+        },
+        Frame::decode_response(&v, api_key, api_version)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_transactions_request_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let v = vec![
+        0, 0, 0, 34, 0, 65, 0, 0, 0, 0, 0, 6, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+        110, 116, 45, 49, 0, 3, 4, 97, 98, 99, 4, 112, 113, 114, 0,
+    ];
+
+    assert_eq!(
+        Frame {
+            size: 34,
+            header: Header::Request {
+                api_key: 65,
+                api_version: 0,
+                correlation_id: 6,
+                client_id: Some("adminclient-1".into())
+            },
+            body: Body::DescribeTransactionsRequest {
+                transactional_ids: Some(["abc".into(), "pqr".into()].into()),
+                unknown_tagged_fields: vec![],
+            }
+        },
+        Frame::request_from_bytes(&v)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_transactions_response_v0_000() -> Result<()> {
+    use tansu_kafka_sans_io::describe_transactions_response::{TopicData, TransactionState};
+
+    let _guard = init_tracing()?;
+
+    let api_key = ApiKey::DescribeTransactions;
+    let api_version = 0;
+
+    let v = vec![
+        0, 0, 0, 95, 0, 0, 0, 6, 0, 0, 0, 0, 0, 3, 0, 0, 4, 97, 98, 99, 8, 79, 110, 103, 111, 105,
+        110, 103, 0, 0, 234, 96, 0, 0, 1, 146, 204, 9, 20, 0, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 2, 5,
+        116, 101, 115, 116, 3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 105, 4, 112, 113, 114, 1, 0, 0, 0,
+        0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+        255, 1, 0, 0,
+    ];
+
+    assert_eq!(
+        Frame {
+            size: 95,
+            header: Header::Response { correlation_id: 6 },
+            body: Body::DescribeTransactionsResponse {
+                throttle_time_ms: 0,
+                transaction_states: Some(
+                    [
+                        TransactionState {
+                            error_code: ErrorCode::None.into(),
+                            transactional_id: "abc".into(),
+                            transaction_state: "Ongoing".into(),
+                            transaction_timeout_ms: 60_000,
+                            transaction_start_time_ms: 1_730_000_000_000,
+                            producer_id: 6,
+                            producer_epoch: 0,
+                            topics: Some(
+                                [TopicData {
+                                    topic: "test".into(),
+                                    partitions: Some([0, 1].into()),
+                                }]
+                                .into()
+                            ),
+                        },
+                        TransactionState {
+                            error_code: ErrorCode::TransactionalIdNotFound.into(),
+                            transactional_id: "pqr".into(),
+                            transaction_state: "".into(),
+                            transaction_timeout_ms: 0,
+                            transaction_start_time_ms: -1,
+                            producer_id: -1,
+                            producer_epoch: -1,
+                            topics: Some([].into()),
+                        }
+                    ]
+                    .into()
+                ),
+                unknown_tagged_fields: vec![],
+            }
+        },
+        Frame::decode_response(&v, api_key, api_version)?
+    );
+
+    Ok(())
+}
+
 #[test]
 fn fetch_request_v6_000() -> Result<()> {
     use tansu_kafka_sans_io::fetch_request::{FetchPartition, FetchTopic};
@@ -2054,6 +2337,7 @@ fn fetch_request_v6_000() -> Result<()> {
                 ),
                 forgotten_topics_data: None,
                 rack_id: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -2138,7 +2422,8 @@ fn fetch_request_v12_000() -> Result<()> {
                     .into()
                 ),
                 forgotten_topics_data: Some([].into()),
-                rack_id: Some("".into())
+                rack_id: Some("".into()),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -2209,10 +2494,10 @@ fn fetch_request_v15_000() -> Result<()> {
                 topics: Some(
                     [FetchTopic {
                         topic: None,
-                        topic_id: Some([
+                        topic_id: Some(Uuid([
                             139, 193, 249, 209, 188, 231, 73, 214, 186, 217, 20, 95, 74, 239, 160,
                             61
-                        ]),
+                        ])),
                         partitions: Some(
                             [
                                 FetchPartition {
@@ -2350,7 +2635,8 @@ fn fetch_request_v15_000() -> Result<()> {
                     .into()
                 ),
                 forgotten_topics_data: Some([].into()),
-                rack_id: Some("".into())
+                rack_id: Some("".into()),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -2390,7 +2676,8 @@ fn fetch_request_v16_000() -> Result<()> {
                 session_epoch: Some(-1),
                 topics: Some([].into()),
                 forgotten_topics_data: Some([].into()),
-                rack_id: Some("".into())
+                rack_id: Some("".into()),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&encoded)?
@@ -2434,9 +2721,9 @@ fn fetch_request_v16_001() -> Result<()> {
                 topics: Some(
                     [FetchTopic {
                         topic: None,
-                        topic_id: Some([
+                        topic_id: Some(Uuid([
                             246, 177, 3, 16, 190, 12, 74, 195, 190, 197, 130, 25, 106, 235, 221, 30
-                        ]),
+                        ])),
                         partitions: Some(
                             [FetchPartition {
                                 partition: 0,
@@ -2452,7 +2739,8 @@ fn fetch_request_v16_001() -> Result<()> {
                     .into()
                 ),
                 forgotten_topics_data: Some([].into()),
-                rack_id: Some("".into())
+                rack_id: Some("".into()),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&encoded)?
@@ -2467,7 +2755,7 @@ fn fetch_response_v12_000() -> Result<()> {
 
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 12;
 
     let v = vec![
@@ -2481,7 +2769,7 @@ fn fetch_response_v12_000() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, api_key, api_version);
+    let mut deserializer = Decoder::response(&mut c, api_key.into(), api_version)?;
 
     assert_eq!(
         Frame {
@@ -2542,7 +2830,8 @@ fn fetch_response_v12_000() -> Result<()> {
                     }]
                     .into()
                 ),
-                node_endpoints: None
+                node_endpoints: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -2555,7 +2844,7 @@ fn fetch_response_v12_000() -> Result<()> {
 fn fetch_response_v12_001() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 12;
 
     let v = vec![
@@ -2573,7 +2862,7 @@ fn fetch_response_v12_001() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, api_key, api_version);
+    let mut deserializer = Decoder::response(&mut c, api_key.into(), api_version)?;
 
     assert_eq!(
         Frame {
@@ -2697,7 +2986,8 @@ fn fetch_response_v12_001() -> Result<()> {
                     }]
                     .into()
                 ),
-                node_endpoints: None
+                node_endpoints: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -2710,7 +3000,7 @@ fn fetch_response_v12_001() -> Result<()> {
 fn fetch_response_v12_002() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 12;
 
     let v = vec![
@@ -2731,7 +3021,7 @@ fn fetch_response_v12_002() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, api_key, api_version);
+    let mut deserializer = Decoder::response(&mut c, api_key.into(), api_version)?;
 
     assert_eq!(
         Frame {
@@ -2917,7 +3207,8 @@ fn fetch_response_v12_002() -> Result<()> {
                     }]
                     .into()
                 ),
-                node_endpoints: None
+                node_endpoints: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -2926,11 +3217,71 @@ fn fetch_response_v12_002() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn fetch_response_v12_002_records() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let api_key = ApiKey::Fetch;
+    let api_version = 12;
+
+    let v = vec![
+        0, 0, 1, 64, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 58, 96, 28, 234, 2, 5, 116, 101, 115, 116, 4,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 22, 0, 0, 0, 0, 0, 0, 0, 22, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 255, 255, 255, 255, 185, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 172, 0, 0, 0, 0, 2, 143,
+        254, 2, 228, 0, 0, 0, 0, 0, 10, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 1, 141, 116, 152,
+        137, 53, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 11, 20, 0, 0, 0, 4, 107, 49, 4,
+        118, 49, 0, 20, 0, 0, 2, 4, 107, 50, 4, 118, 50, 0, 20, 0, 0, 4, 4, 107, 49, 4, 118, 51, 0,
+        20, 0, 0, 6, 4, 107, 49, 4, 118, 52, 0, 20, 0, 0, 8, 4, 107, 51, 4, 118, 53, 0, 20, 0, 0,
+        10, 4, 107, 50, 4, 118, 54, 0, 20, 0, 0, 12, 4, 107, 52, 4, 118, 55, 0, 20, 0, 0, 14, 4,
+        107, 53, 4, 118, 56, 0, 20, 0, 0, 16, 4, 107, 53, 4, 118, 57, 0, 22, 0, 0, 18, 4, 107, 50,
+        6, 118, 49, 48, 0, 22, 0, 0, 20, 4, 107, 54, 6, 118, 49, 49, 0, 0, 0, 0, 0, 1, 0, 3, 255,
+        255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+        255, 255, 255, 255, 255, 1, 255, 255, 255, 255, 1, 0, 0, 0, 0, 2, 0, 3, 255, 255, 255, 255,
+        255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+        255, 255, 1, 255, 255, 255, 255, 1, 0, 0, 0,
+    ];
+
+    let Body::FetchResponse {
+        responses: Some(responses),
+        ..
+    } = Frame::decode_response(&v, api_key, api_version)?.body
+    else {
+        panic!("expected a fetch response with responses")
+    };
+
+    let batches = responses
+        .iter()
+        .flat_map(|topic| topic.partitions.iter().flatten())
+        .flat_map(|partition| partition.records.iter())
+        .map(record::Records::batches)
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    assert_eq!(1, batches.len());
+
+    for batch in &batches {
+        let lazy = batch.records().collect::<Result<Vec<_>>>()?;
+        assert_eq!(Vec::<Record>::try_from(batch.clone())?, lazy);
+
+        assert_eq!(
+            (0..=10).collect::<Vec<i64>>(),
+            lazy.iter()
+                .map(|record| batch.base_offset + i64::from(record.offset_delta))
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(Some(&b"v11"[..]), lazy.last().and_then(Record::value));
+    }
+
+    Ok(())
+}
+
 #[test]
 fn fetch_response_v16_001() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 16;
 
     let v = vec![
@@ -2945,7 +3296,7 @@ fn fetch_response_v16_001() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, api_key, api_version);
+    let mut deserializer = Decoder::response(&mut c, api_key.into(), api_version)?;
 
     assert_eq!(
         Frame {
@@ -2958,9 +3309,9 @@ fn fetch_response_v16_001() -> Result<()> {
                 responses: Some(
                     [FetchableTopicResponse {
                         topic: None,
-                        topic_id: Some([
+                        topic_id: Some(Uuid([
                             28, 205, 172, 195, 142, 19, 71, 71, 182, 128, 13, 18, 65, 142, 210, 222
-                        ]),
+                        ])),
                         partitions: Some(
                             [PartitionData {
                                 partition_index: 0,
@@ -3020,7 +3371,8 @@ fn fetch_response_v16_001() -> Result<()> {
                     }]
                     .into()
                 ),
-                node_endpoints: Some([].into())
+                node_endpoints: Some([].into()),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3033,7 +3385,7 @@ fn fetch_response_v16_001() -> Result<()> {
 fn fetch_response_v16_002() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 16;
 
     let v = vec![
@@ -3048,7 +3400,7 @@ fn fetch_response_v16_002() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, api_key, api_version);
+    let mut deserializer = Decoder::response(&mut c, api_key.into(), api_version)?;
 
     assert_eq!(
         Frame {
@@ -3061,9 +3413,9 @@ fn fetch_response_v16_002() -> Result<()> {
                 responses: Some(
                     [FetchableTopicResponse {
                         topic: None,
-                        topic_id: Some([
+                        topic_id: Some(Uuid([
                             28, 205, 172, 195, 142, 19, 71, 71, 182, 128, 13, 18, 65, 142, 210, 222
-                        ]),
+                        ])),
                         partitions: Some(
                             [PartitionData {
                                 partition_index: 0,
@@ -3123,7 +3475,8 @@ fn fetch_response_v16_002() -> Result<()> {
                     }]
                     .into()
                 ),
-                node_endpoints: None
+                node_endpoints: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3156,6 +3509,7 @@ fn find_coordinator_request_v1_000() -> Result<()> {
                 key: Some("abcdef".into()),
                 key_type: Some(0),
                 coordinator_keys: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3189,7 +3543,8 @@ fn find_coordinator_request_v2_000() -> Result<()> {
             body: Body::FindCoordinatorRequest {
                 key: Some("example_consumer_group_id".into()),
                 key_type: Some(0),
-                coordinator_keys: None
+                coordinator_keys: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3209,7 +3564,7 @@ fn find_coordinator_response_v1_000() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, 10, 1);
+    let mut deserializer = Decoder::response(&mut c, 10, 1)?;
 
     assert_eq!(
         Frame {
@@ -3223,6 +3578,7 @@ fn find_coordinator_response_v1_000() -> Result<()> {
                 host: Some("ip-10-2-91-66.eu-west-1.compute.internal".into()),
                 port: Some(9092),
                 coordinators: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3256,7 +3612,8 @@ fn find_coordinator_request_v4_000() -> Result<()> {
             body: Body::FindCoordinatorRequest {
                 key: None,
                 key_type: Some(0),
-                coordinator_keys: Some(["test-consumer-group".into()].into())
+                coordinator_keys: Some(["test-consumer-group".into()].into()),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3291,7 +3648,8 @@ fn heartbeat_request_v4_000() -> Result<()> {
                 group_id: "test-consumer-group".into(),
                 generation_id: 0,
                 member_id: "1000".into(),
-                group_instance_id: None
+                group_instance_id: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3327,6 +3685,7 @@ fn init_producer_id_request_v4_000() -> Result<()> {
                 transaction_timeout_ms: 2147483647,
                 producer_id: Some(-1),
                 producer_epoch: Some(-1),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3386,7 +3745,8 @@ fn join_group_request_v5_000() -> Result<()> {
                     ]
                     .into()
                 ),
-                reason: None
+                reason: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -3412,7 +3772,7 @@ fn join_group_response_v5_000() -> Result<()> {
         0,
     ];
 
-    let api_key = 11;
+    let api_key = ApiKey::JoinGroup;
     let api_version = 5;
 
     let metadata =
@@ -3438,10 +3798,11 @@ fn join_group_response_v5_000() -> Result<()> {
                         metadata
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
-        Frame::response_from_bytes(&v, api_key, api_version)?
+        Frame::decode_response(&v, api_key, api_version)?
     );
 
     Ok(())
@@ -3490,7 +3851,8 @@ fn join_group_request_v9_000() -> Result<()> {
                         metadata: Bytes::from_static(b"\0\x03\0\0\0\x01\0\x04test\0\0\0\x04\xff\xff\xff\xff\0\0\0\0\xff\xff\xff\xff\xff\xff")
                     }
                     ].into()),
-                reason: Some("".into())
+                reason: Some("".into()),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -3535,7 +3897,8 @@ fn leave_group_request_v5_000() -> Result<()> {
                         reason: Some("the consumer is being closed".into())
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3566,7 +3929,8 @@ fn list_groups_request_v4_000() -> Result<()> {
                 client_id: Some("adminclient-1".into()),
             },
             body: Body::ListGroupsRequest {
-                states_filter: Some([].into())
+                states_filter: Some([].into()),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3590,7 +3954,7 @@ fn list_offsets_response_v0_000() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, 2, 0);
+    let mut deserializer = Decoder::response(&mut c, 2, 0)?;
 
     assert_eq!(
         Frame {
@@ -3615,6 +3979,7 @@ fn list_offsets_response_v0_000() -> Result<()> {
                     }]
                     .into()
                 ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3655,7 +4020,8 @@ fn list_partition_reassignments_request_v0_000() -> Result<()> {
                         partition_indexes: Some([1, 0, 2].into())
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3664,6 +4030,75 @@ fn list_partition_reassignments_request_v0_000() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn list_transactions_request_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let v = vec![
+        0, 0, 0, 48, 0, 66, 0, 0, 0, 0, 0, 7, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+        110, 116, 45, 49, 0, 3, 8, 79, 110, 103, 111, 105, 110, 103, 5, 76, 111, 115, 116, 2, 0, 0,
+        0, 0, 0, 0, 0, 6, 0,
+    ];
+
+    assert_eq!(
+        Frame {
+            size: 48,
+            header: Header::Request {
+                api_key: 66,
+                api_version: 0,
+                correlation_id: 7,
+                client_id: Some("adminclient-1".into())
+            },
+            body: Body::ListTransactionsRequest {
+                state_filters: Some(["Ongoing".into(), "Lost".into()].into()),
+                producer_id_filters: Some([6].into()),
+                duration_filter: None,
+                unknown_tagged_fields: vec![],
+            }
+        },
+        Frame::request_from_bytes(&v)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn list_transactions_response_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let api_key = ApiKey::ListTransactions;
+    let api_version = 0;
+
+    let v = vec![
+        0, 0, 0, 40, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 2, 5, 76, 111, 115, 116, 2, 4, 97, 98, 99, 0,
+        0, 0, 0, 0, 0, 0, 6, 8, 79, 110, 103, 111, 105, 110, 103, 0, 0,
+    ];
+
+    assert_eq!(
+        Frame {
+            size: 40,
+            header: Header::Response { correlation_id: 7 },
+            body: Body::ListTransactionsResponse {
+                throttle_time_ms: 0,
+                error_code: 0,
+                unknown_state_filters: Some(["Lost".into()].into()),
+                transaction_states: Some(
+                    [TransactionState {
+                        transactional_id: "abc".into(),
+                        producer_id: 6,
+                        transaction_state: "Ongoing".into()
+                    }]
+                    .into()
+                ),
+                unknown_tagged_fields: vec![],
+            }
+        },
+        Frame::decode_response(&v, api_key, api_version)?
+    );
+
+    Ok(())
+}
+
 #[ignore]
 #[test]
 fn list_transactions_request_v1_000() -> Result<()> {
@@ -3690,6 +4125,7 @@ fn list_transactions_request_v1_000() -> Result<()> {
                 state_filters: Some([].into()),
                 producer_id_filters: Some([].into()),
                 duration_filter: Some(-1),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -3702,7 +4138,7 @@ fn list_transactions_request_v1_000() -> Result<()> {
 fn list_transactions_response_v1_000() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 66;
+    let api_key = ApiKey::ListTransactions;
     let api_version = 1;
 
     let v = vec![
@@ -3727,10 +4163,11 @@ fn list_transactions_response_v1_000() -> Result<()> {
                         transaction_state: "CompleteCommit".into()
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
-        Frame::response_from_bytes(&v, api_key, api_version)?
+        Frame::decode_response(&v, api_key, api_version)?
     );
 
     Ok(())
@@ -3766,7 +4203,8 @@ fn metadata_request_v1_000() -> Result<()> {
                 ),
                 allow_auto_topic_creation: None,
                 include_cluster_authorized_operations: None,
-                include_topic_authorized_operations: None
+                include_topic_authorized_operations: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -3779,7 +4217,7 @@ fn metadata_request_v1_000() -> Result<()> {
 fn metadata_response_v1_000() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 3;
+    let api_key = ApiKey::Metadata;
     let api_version = 1;
 
     let v = vec![
@@ -3889,10 +4327,11 @@ fn metadata_response_v1_000() -> Result<()> {
                     }]
                     .into()
                 ),
-                cluster_authorized_operations: None
+                cluster_authorized_operations: None,
+                unknown_tagged_fields: vec![],
             }
         },
-        Frame::response_from_bytes(&v, api_key, api_version)?
+        Frame::decode_response(&v, api_key, api_version)?
     );
 
     Ok(())
@@ -3922,7 +4361,7 @@ fn metadata_request_v12_000() -> Result<()> {
             body: Body::MetadataRequest {
                 topics: Some(
                     [MetadataRequestTopic {
-                        topic_id: Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                        topic_id: Some(Uuid([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])),
                         name: Some("test".into()),
                     }]
                     .into()
@@ -3930,6 +4369,7 @@ fn metadata_request_v12_000() -> Result<()> {
                 allow_auto_topic_creation: Some(true),
                 include_cluster_authorized_operations: None,
                 include_topic_authorized_operations: Some(false),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -3951,11 +4391,11 @@ fn metadata_response_v12_000() -> Result<()> {
         115, 116, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 128, 0, 0, 0, 0, 0,
     ];
 
-    let api_key = 3;
+    let api_key = ApiKey::Metadata;
     let api_version = 12;
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, api_key, api_version);
+    let mut deserializer = Decoder::response(&mut c, api_key.into(), api_version)?;
 
     assert_eq!(
         Frame {
@@ -3974,12 +4414,13 @@ fn metadata_response_v12_000() -> Result<()> {
                 topics: Some(vec![MetadataResponseTopic {
                     error_code: 3,
                     name: Some("test".into()),
-                    topic_id: Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                    topic_id: Some(Uuid([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])),
                     is_internal: Some(false),
                     partitions: Some(vec![]),
                     topic_authorized_operations: Some(-2147483648),
                 }]),
                 cluster_authorized_operations: None,
+                unknown_tagged_fields: vec![],
             },
         },
         Frame::deserialize(&mut deserializer)?
@@ -4011,6 +4452,7 @@ fn metadata_request_v12_001() -> Result<()> {
                 allow_auto_topic_creation: Some(true),
                 include_cluster_authorized_operations: None,
                 include_topic_authorized_operations: Some(false),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -4041,14 +4483,15 @@ fn metadata_request_v12_002() -> Result<()> {
             body: Body::MetadataRequest {
                 topics: Some(
                     [MetadataRequestTopic {
-                        topic_id: Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                        topic_id: Some(Uuid([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])),
                         name: Some("benchmark".into())
                     }]
                     .into()
                 ),
                 allow_auto_topic_creation: Some(true),
                 include_cluster_authorized_operations: None,
-                include_topic_authorized_operations: Some(false)
+                include_topic_authorized_operations: Some(false),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -4075,7 +4518,7 @@ fn metadata_response_v12_002() -> Result<()> {
         1, 1, 0, 128, 0, 0, 0, 0, 0,
     ];
 
-    let api_key = 3;
+    let api_key = ApiKey::Metadata;
     let api_version = 12;
 
     assert_eq!(
@@ -4099,10 +4542,10 @@ fn metadata_response_v12_002() -> Result<()> {
                     [MetadataResponseTopic {
                         error_code: 0,
                         name: Some("benchmark".into()),
-                        topic_id: Some([
+                        topic_id: Some(Uuid([
                             177, 248, 14, 236, 65, 78, 72, 57, 179, 196, 215, 75, 145, 238, 120,
                             241
-                        ]),
+                        ])),
                         is_internal: Some(false),
                         partitions: Some(
                             [
@@ -4176,19 +4619,145 @@ fn metadata_response_v12_002() -> Result<()> {
                     }]
                     .into()
                 ),
-                cluster_authorized_operations: None
+                cluster_authorized_operations: None,
+                unknown_tagged_fields: vec![],
             }
         },
-        Frame::response_from_bytes(&v, api_key, api_version)?
+        Frame::decode_response(&v, api_key, api_version)?
     );
 
     Ok(())
 }
 
 #[test]
-fn offset_fetch_request_v3_000() -> Result<()> {
-    use tansu_kafka_sans_io::offset_fetch_request::OffsetFetchRequestTopic;
-
+fn metadata_response_v10_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let v = vec![
+        0, 0, 0, 100, 0, 0, 0, 7, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 10, 108, 111, 99, 97, 108, 104,
+        111, 115, 116, 0, 0, 35, 132, 0, 0, 4, 97, 98, 99, 0, 0, 0, 1, 2, 0, 0, 5, 116, 101, 115,
+        116, 0, 17, 34, 51, 68, 85, 102, 119, 136, 153, 170, 187, 204, 221, 238, 255, 0, 2, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 2, 0, 0, 0, 1, 2, 0, 0, 0, 1, 1, 0, 128, 0, 0, 0, 0,
+        128, 0, 0, 0, 0,
+    ];
+
+    let api_key = ApiKey::Metadata;
+    let api_version = 10;
+
+    let topic_id = Uuid([
+        0, 17, 34, 51, 68, 85, 102, 119, 136, 153, 170, 187, 204, 221, 238, 255,
+    ]);
+    assert_eq!("00112233-4455-6677-8899-aabbccddeeff", topic_id.to_string());
+
+    let frame = Frame {
+        size: 100,
+        header: Header::Response { correlation_id: 7 },
+        body: Body::MetadataResponse {
+            throttle_time_ms: Some(0),
+            brokers: Some(
+                [MetadataResponseBroker {
+                    node_id: 1,
+                    host: "localhost".into(),
+                    port: 9092,
+                    rack: None,
+                }]
+                .into(),
+            ),
+            cluster_id: Some("abc".into()),
+            controller_id: Some(1),
+            topics: Some(
+                [MetadataResponseTopic {
+                    error_code: 0,
+                    name: Some("test".into()),
+                    topic_id: Some(topic_id),
+                    is_internal: Some(false),
+                    partitions: Some(
+                        [MetadataResponsePartition {
+                            error_code: 0,
+                            partition_index: 0,
+                            leader_id: 1,
+                            leader_epoch: Some(0),
+                            replica_nodes: Some([1].into()),
+                            isr_nodes: Some([1].into()),
+                            offline_replicas: Some([].into()),
+                        }]
+                        .into(),
+                    ),
+                    topic_authorized_operations: Some(i32::MIN),
+                }]
+                .into(),
+            ),
+            cluster_authorized_operations: Some(i32::MIN),
+            unknown_tagged_fields: vec![],
+        },
+    };
+
+    assert_eq!(frame, Frame::decode_response(&v, api_key, api_version)?);
+
+    // the topic id is 16 raw bytes, without a length
+    assert_eq!(
+        v,
+        Frame::encode_response(frame.header, frame.body, api_key, api_version)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn metadata_response_v10_absent_topic_id() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let api_key = ApiKey::Metadata;
+    let api_version = 10;
+
+    let body = Body::MetadataResponse {
+        throttle_time_ms: Some(0),
+        brokers: Some([].into()),
+        cluster_id: None,
+        controller_id: Some(1),
+        topics: Some(
+            [MetadataResponseTopic {
+                error_code: 0,
+                name: Some("test".into()),
+                topic_id: None,
+                is_internal: Some(false),
+                partitions: Some([].into()),
+                topic_authorized_operations: Some(i32::MIN),
+            }]
+            .into(),
+        ),
+        cluster_authorized_operations: Some(i32::MIN),
+        unknown_tagged_fields: vec![],
+    };
+
+    let encoded = Frame::encode_response(
+        Header::Response { correlation_id: 8 },
+        body,
+        api_key,
+        api_version,
+    )?;
+
+    // a topic id that is required by the version, but absent, is the nil uuid
+    match Frame::decode_response(&encoded, api_key, api_version)? {
+        Frame {
+            body:
+                Body::MetadataResponse {
+                    topics: Some(topics),
+                    ..
+                },
+            ..
+        } => assert_eq!(Some(Uuid::nil()), topics[0].topic_id),
+
+        otherwise => panic!("{otherwise:?}"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn offset_fetch_request_v3_000() -> Result<()> {
+    use tansu_kafka_sans_io::offset_fetch_request::OffsetFetchRequestTopic;
+
     let _guard = init_tracing()?;
 
     let v = vec![
@@ -4226,6 +4795,7 @@ fn offset_fetch_request_v3_000() -> Result<()> {
                 ),
                 groups: None,
                 require_stable: None,
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -4267,6 +4837,7 @@ fn offset_fetch_request_v7_000() -> Result<()> {
                 ),
                 groups: None,
                 require_stable: Some(true),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -4290,7 +4861,7 @@ fn offset_fetch_response_v7_000() -> Result<()> {
         255, 255, 255, 255, 255, 255, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0,
     ];
 
-    let api_key = 9;
+    let api_key = ApiKey::OffsetFetch;
     let api_version = 7;
 
     assert_eq!(
@@ -4320,10 +4891,11 @@ fn offset_fetch_response_v7_000() -> Result<()> {
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
-        Frame::response_from_bytes(&v, api_key, api_version)?
+        Frame::decode_response(&v, api_key, api_version)?
     );
 
     Ok(())
@@ -4374,7 +4946,78 @@ fn offset_fetch_request_v9_000() -> Result<()> {
                     }]
                     .into()
                 ),
-                require_stable: Some(true)
+                require_stable: Some(true),
+                unknown_tagged_fields: vec![],
+            }
+        },
+        Frame::deserialize(&mut deserializer)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn offset_fetch_request_v9_001() -> Result<()> {
+    use tansu_kafka_sans_io::offset_fetch_request::{
+        OffsetFetchRequestGroup, OffsetFetchRequestTopics,
+    };
+
+    let _guard = init_tracing()?;
+
+    let v = vec![
+        0, 0, 0, 105, 0, 9, 0, 9, 0, 0, 0, 7, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 99, 111,
+        110, 115, 117, 109, 101, 114, 0, 3, 20, 116, 101, 115, 116, 45, 99, 111, 110, 115, 117,
+        109, 101, 114, 45, 103, 114, 111, 117, 112, 0, 255, 255, 255, 255, 2, 5, 116, 101, 115,
+        116, 4, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 12, 111, 116, 104, 101, 114, 45, 103,
+        114, 111, 117, 112, 0, 255, 255, 255, 255, 2, 4, 97, 98, 99, 2, 0, 0, 0, 0, 0, 0, 1, 0,
+    ];
+
+    let mut c = Cursor::new(v);
+    let mut deserializer = Decoder::request(&mut c);
+
+    assert_eq!(
+        Frame {
+            size: 105,
+            header: Header::Request {
+                api_key: 9,
+                api_version: 9,
+                correlation_id: 7,
+                client_id: Some("console-consumer".into())
+            },
+            body: Body::OffsetFetchRequest {
+                group_id: None,
+                topics: None,
+                groups: Some(
+                    [
+                        OffsetFetchRequestGroup {
+                            group_id: "test-consumer-group".into(),
+                            member_id: None,
+                            member_epoch: Some(-1),
+                            topics: Some(
+                                [OffsetFetchRequestTopics {
+                                    name: "test".into(),
+                                    partition_indexes: Some([1, 0, 2].into())
+                                }]
+                                .into()
+                            )
+                        },
+                        OffsetFetchRequestGroup {
+                            group_id: "other-group".into(),
+                            member_id: None,
+                            member_epoch: Some(-1),
+                            topics: Some(
+                                [OffsetFetchRequestTopics {
+                                    name: "abc".into(),
+                                    partition_indexes: Some([0].into())
+                                }]
+                                .into()
+                            )
+                        }
+                    ]
+                    .into()
+                ),
+                require_stable: Some(true),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -4449,7 +5092,8 @@ fn offset_commit_request_v9_000() -> Result<()> {
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -4490,6 +5134,7 @@ fn offset_for_leader_request_v0_000() -> Result<()> {
                     }]
                     .into()
                 ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -4608,7 +5253,8 @@ fn produce_request_v3_000() -> Result<()> {
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -4694,7 +5340,8 @@ fn produce_request_v7_000() -> Result<()> {
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::request_from_bytes(&v)?
@@ -4776,6 +5423,7 @@ fn produce_request_v9_000() -> Result<()> {
                     }]
                     .into()
                 ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -4952,7 +5600,8 @@ fn produce_request_v9_001() -> Result<()> {
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -5058,7 +5707,8 @@ fn produce_request_v10_000() -> Result<()> {
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -5341,7 +5991,8 @@ fn produce_request_v10_001() -> Result<()> {
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -5443,15 +6094,14 @@ sunt\x01uXculpa qui officia deser\x01\x1e\x1cmollit a!!<id est laborum.\0");
                         partition_data: Some(
                             [PartitionProduceData {
                                 index: 2,
-                                records: Some(deflated::Frame {
-                                    batches: [deflated_batch.clone()].into()
-                                })
+                                records: Some(record::Records::try_from(deflated_batch.clone())?)
                             }]
                             .into()
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -5575,15 +6225,14 @@ sun\x05uXculpa qui officia deser\x01\x1e\x0cmoll!\x93!!<id est laborum.\0",
                         partition_data: Some(
                             [PartitionProduceData {
                                 index: 0,
-                                records: Some(deflated::Frame {
-                                    batches: [deflated_batch.clone()].into()
-                                })
+                                records: Some(record::Records::try_from(deflated_batch.clone())?)
                             }]
                             .into()
                         )
                     }]
                     .into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -5633,7 +6282,7 @@ fn produce_response_v9_000() -> Result<()> {
     ];
 
     let mut c = Cursor::new(v);
-    let mut deserializer = Decoder::response(&mut c, 0, 9);
+    let mut deserializer = Decoder::response(&mut c, 0, 9)?;
 
     assert_eq!(
         Frame {
@@ -5661,6 +6310,7 @@ fn produce_response_v9_000() -> Result<()> {
                     .into()
                 ),
                 throttle_time_ms: Some(0),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -5709,7 +6359,8 @@ pub fn sync_group_request_v5_000() -> Result<()> {
                         assignment: Bytes::from_static(b"\0\x03\0\0\0\x01\0\x04test\0\0\0\x03\0\0\0\0\0\0\0\x01\0\0\0\x02\xff\xff\xff\xff"),
                     }
                     ].into()
-                )
+                ),
+                unknown_tagged_fields: vec![],
             }
         },
         Frame::deserialize(&mut deserializer)?
@@ -5717,3 +6368,358 @@ pub fn sync_group_request_v5_000() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn frame_too_large() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    assert!(matches!(
+        Frame::request_from_bytes(&[127, 255, 255, 255, 0, 18, 0, 3]),
+        Err(Error::FrameTooLarge {
+            length: 2_147_483_651,
+            maximum: DEFAULT_MAX_FRAME_BYTES,
+        })
+    ));
+
+    let v = vec![
+        0, 0, 0, 52, 0, 18, 0, 3, 0, 0, 0, 3, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112,
+        114, 111, 100, 117, 99, 101, 114, 0, 18, 97, 112, 97, 99, 104, 101, 45, 107, 97, 102, 107,
+        97, 45, 106, 97, 118, 97, 6, 51, 46, 54, 46, 49, 0,
+    ];
+
+    let mut c = Cursor::new(v);
+    let mut deserializer = Decoder::request(&mut c).max_frame_bytes(32);
+
+    assert!(matches!(
+        Frame::deserialize(&mut deserializer)
+            .as_ref()
+            .map_err(Error::root_cause),
+        Err(Error::FrameTooLarge {
+            length: 56,
+            maximum: 32,
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn string_too_large() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    // api versions v3, with a compact client software name of u32::MAX - 1 bytes
+    let v = vec![
+        0, 0, 0, 32, 0, 18, 0, 3, 0, 0, 0, 3, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112,
+        114, 111, 100, 117, 99, 101, 114, 0, 255, 255, 255, 255, 15,
+    ];
+
+    let error = Frame::request_from_bytes(&v).unwrap_err();
+
+    assert!(matches!(
+        error,
+        Error::At {
+            position: 36,
+            message: Some("ApiVersionsRequest"),
+            api_version: Some(3),
+            ref path,
+            ref source,
+        } if path == "body.client_software_name"
+            && matches!(
+                **source,
+                Error::FrameTooLarge {
+                    length: 4_294_967_294,
+                    maximum: 0,
+                }
+            )
+    ));
+
+    assert!(error
+        .to_string()
+        .starts_with("at byte 36, ApiVersionsRequest v3, body.client_software_name: "));
+
+    Ok(())
+}
+
+#[test]
+fn null_compact_length() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    // api versions v3, with a null compact client software name
+    let v = vec![
+        0, 0, 0, 28, 0, 18, 0, 3, 0, 0, 0, 3, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112,
+        114, 111, 100, 117, 99, 101, 114, 0, 0,
+    ];
+
+    let error = Frame::request_from_bytes(&v).unwrap_err();
+
+    assert!(matches!(
+        error.root_cause(),
+        Error::InvalidLength { field, length: 0 } if field == "body.client_software_name"
+    ));
+
+    assert_eq!(
+        "at byte 32, ApiVersionsRequest v3, body.client_software_name: \
+            invalid length: 0, for field: body.client_software_name",
+        error.to_string()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn error_display_and_source() -> Result<()> {
+    use std::error::Error as _;
+
+    let _guard = init_tracing()?;
+
+    assert_eq!(
+        "incomplete, needed: 3 more bytes",
+        Error::Incomplete { needed: Some(3) }.to_string()
+    );
+
+    assert_eq!(
+        "unknown api key: 32123",
+        Error::UnknownApiKey(32123).to_string()
+    );
+
+    assert_eq!(
+        "unsupported api key: 18, version: 12",
+        Error::UnsupportedApiVersion {
+            api_key: 18,
+            api_version: 12
+        }
+        .to_string()
+    );
+
+    let io = Error::from(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+    assert_eq!("io", io.to_string());
+    assert!(io.source().is_some());
+
+    let utf8 = Error::from(String::from_utf8(vec![0xff]).unwrap_err());
+    assert_eq!("invalid utf-8", utf8.to_string());
+    assert!(utf8.source().is_some());
+
+    let at = Error::At {
+        position: 12,
+        message: None,
+        api_version: None,
+        path: String::new(),
+        source: Box::new(Error::Incomplete { needed: None }),
+    };
+    assert_eq!("at byte 12: incomplete", at.to_string());
+    assert!(at.is_incomplete());
+    assert_eq!(
+        Some("incomplete".into()),
+        at.source().map(ToString::to_string)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn records_too_large() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    // produce v7, with a records length of i32::MAX
+    let v = vec![
+        0, 0, 0, 52, 0, 0, 0, 7, 0, 0, 0, 3, 0, 7, 114, 100, 107, 97, 102, 107, 97, 255, 255, 255,
+        255, 0, 0, 117, 48, 0, 0, 0, 1, 0, 9, 98, 101, 110, 99, 104, 109, 97, 114, 107, 0, 0, 0, 1,
+        0, 0, 0, 0, 127, 255, 255, 255,
+    ];
+
+    assert!(matches!(
+        Frame::request_from_bytes(&v)
+            .as_ref()
+            .map_err(Error::root_cause),
+        Err(Error::FrameTooLarge {
+            length: 2_147_483_647,
+            maximum: 0,
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn client_id_longer_than_frame() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    // api versions v0, with a client id of 32767 bytes in a 15 byte frame
+    let v = vec![
+        0, 0, 0, 15, 0, 18, 0, 0, 0, 0, 0, 3, 127, 255, 99, 111, 110, 115, 111,
+    ];
+
+    assert!(matches!(
+        Frame::request_from_bytes(&v)
+            .as_ref()
+            .map_err(Error::root_cause),
+        Err(Error::FrameTooLarge {
+            length: 32_767,
+            maximum: 5,
+        })
+    ));
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename = "Body")]
+struct ApiVersionsResponseSkippingApiKeys {
+    error_code: i16,
+    #[allow(dead_code)]
+    api_keys: IgnoredAny,
+    throttle_time_ms: i32,
+}
+
+#[test]
+fn ignored_any_api_versions_response_v1() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let v = vec![
+        0, 0, 0, 242, 0, 0, 0, 0, 0, 0, 0, 0, 0, 38, 0, 0, 0, 0, 0, 5, 0, 1, 0, 0, 0, 6, 0, 2, 0,
+        0, 0, 2, 0, 3, 0, 0, 0, 5, 0, 4, 0, 0, 0, 1, 0, 5, 0, 0, 0, 0, 0, 6, 0, 0, 0, 4, 0, 7, 0,
+        0, 0, 1, 0, 8, 0, 0, 0, 3, 0, 9, 0, 0, 0, 3, 0, 10, 0, 0, 0, 1, 0, 11, 0, 0, 0, 2, 0, 12,
+        0, 0, 0, 1, 0, 13, 0, 0, 0, 1, 0, 14, 0, 0, 0, 1, 0, 15, 0, 0, 0, 1, 0, 16, 0, 0, 0, 1, 0,
+        17, 0, 0, 0, 1, 0, 18, 0, 0, 0, 1, 0, 19, 0, 0, 0, 2, 0, 20, 0, 0, 0, 1, 0, 21, 0, 0, 0, 0,
+        0, 22, 0, 0, 0, 0, 0, 23, 0, 0, 0, 0, 0, 24, 0, 0, 0, 0, 0, 25, 0, 0, 0, 0, 0, 26, 0, 0, 0,
+        0, 0, 27, 0, 0, 0, 0, 0, 28, 0, 0, 0, 0, 0, 29, 0, 0, 0, 0, 0, 30, 0, 0, 0, 0, 0, 31, 0, 0,
+        0, 0, 0, 32, 0, 0, 0, 0, 0, 33, 0, 0, 0, 0, 0, 34, 0, 0, 0, 0, 0, 35, 0, 0, 0, 0, 0, 36, 0,
+        0, 0, 0, 0, 37, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    // skip the size and response header
+    let mut c = Cursor::new(&v[8..]);
+    let mut deserializer = Decoder::response(&mut c, 18, 1)?;
+
+    let body = ApiVersionsResponseSkippingApiKeys::deserialize(&mut deserializer)?;
+    assert_eq!(0, body.error_code);
+    assert_eq!(0, body.throttle_time_ms);
+    assert_eq!(v.len() - 8, usize::try_from(deserializer.position())?);
+
+    Ok(())
+}
+
+#[test]
+fn ignored_any_api_versions_response_v3() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let v = vec![
+        0, 0, 1, 201, 0, 0, 0, 0, 0, 0, 56, 0, 0, 0, 0, 0, 9, 0, 0, 1, 0, 0, 0, 15, 0, 0, 2, 0, 0,
+        0, 8, 0, 0, 3, 0, 0, 0, 12, 0, 0, 8, 0, 0, 0, 8, 0, 0, 9, 0, 0, 0, 8, 0, 0, 10, 0, 0, 0, 4,
+        0, 0, 11, 0, 0, 0, 9, 0, 0, 12, 0, 0, 0, 4, 0, 0, 13, 0, 0, 0, 5, 0, 0, 14, 0, 0, 0, 5, 0,
+        0, 15, 0, 0, 0, 5, 0, 0, 16, 0, 0, 0, 4, 0, 0, 17, 0, 0, 0, 1, 0, 0, 18, 0, 0, 0, 3, 0, 0,
+        19, 0, 0, 0, 7, 0, 0, 20, 0, 0, 0, 6, 0, 0, 21, 0, 0, 0, 2, 0, 0, 22, 0, 0, 0, 4, 0, 0, 23,
+        0, 0, 0, 4, 0, 0, 24, 0, 0, 0, 4, 0, 0, 25, 0, 0, 0, 3, 0, 0, 26, 0, 0, 0, 3, 0, 0, 27, 0,
+        0, 0, 1, 0, 0, 28, 0, 0, 0, 3, 0, 0, 29, 0, 0, 0, 3, 0, 0, 30, 0, 0, 0, 3, 0, 0, 31, 0, 0,
+        0, 3, 0, 0, 32, 0, 0, 0, 4, 0, 0, 33, 0, 0, 0, 2, 0, 0, 34, 0, 0, 0, 2, 0, 0, 35, 0, 0, 0,
+        4, 0, 0, 36, 0, 0, 0, 2, 0, 0, 37, 0, 0, 0, 3, 0, 0, 38, 0, 0, 0, 3, 0, 0, 39, 0, 0, 0, 2,
+        0, 0, 40, 0, 0, 0, 2, 0, 0, 41, 0, 0, 0, 3, 0, 0, 42, 0, 0, 0, 2, 0, 0, 43, 0, 0, 0, 2, 0,
+        0, 44, 0, 0, 0, 1, 0, 0, 45, 0, 0, 0, 0, 0, 0, 46, 0, 0, 0, 0, 0, 0, 47, 0, 0, 0, 0, 0, 0,
+        48, 0, 0, 0, 1, 0, 0, 49, 0, 0, 0, 1, 0, 0, 50, 0, 0, 0, 0, 0, 0, 51, 0, 0, 0, 0, 0, 0, 55,
+        0, 0, 0, 1, 0, 0, 57, 0, 0, 0, 1, 0, 0, 60, 0, 0, 0, 0, 0, 0, 61, 0, 0, 0, 0, 0, 0, 64, 0,
+        0, 0, 0, 0, 0, 65, 0, 0, 0, 0, 0, 0, 66, 0, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 23, 2, 17, 109,
+        101, 116, 97, 100, 97, 116, 97, 46, 118, 101, 114, 115, 105, 111, 110, 0, 1, 0, 14, 0, 1,
+        8, 0, 0, 0, 0, 0, 0, 0, 76, 2, 23, 2, 17, 109, 101, 116, 97, 100, 97, 116, 97, 46, 118,
+        101, 114, 115, 105, 111, 110, 0, 14, 0, 14, 0,
+    ];
+
+    // the api keys are a compact array of structures each with a tag buffer,
+    // the trailing body tag buffer is missing from the struct and is not read
+    let mut c = Cursor::new(&v[8..]);
+    let mut deserializer = Decoder::response(&mut c, 18, 3)?;
+
+    let body = ApiVersionsResponseSkippingApiKeys::deserialize(&mut deserializer)?;
+    assert_eq!(0, body.error_code);
+    assert_eq!(0, body.throttle_time_ms);
+
+    Ok(())
+}
+
+#[test]
+fn ignored_any_without_field_meta() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename = "Body")]
+    struct Unknown {
+        #[allow(dead_code)]
+        not_a_field: IgnoredAny,
+    }
+
+    let mut c = Cursor::new(vec![0, 0, 0, 0]);
+    let mut deserializer = Decoder::response(&mut c, 18, 1)?;
+
+    assert!(matches!(
+        Unknown::deserialize(&mut deserializer),
+        Err(Error::At { ref path, ref source, .. })
+            if path == "not_a_field" && matches!(**source, Error::NoSuchField("not_a_field"))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn random_request_bytes_do_not_panic() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    // xorshift, so that any failure is reproducible from the seed
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for _ in 0..10_000 {
+        let length = usize::try_from(next() % 256)?;
+
+        let mut body = vec![0u8; length];
+        body.iter_mut().for_each(|b| *b = next().to_be_bytes()[0]);
+
+        // a plausible api key and version, so that decoding reaches the body
+        let api_key = i16::try_from(next() % 76)?;
+        let api_version = i16::try_from(next() % 16)?;
+
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&i32::try_from(length + 4)?.to_be_bytes());
+        frame.extend_from_slice(&api_key.to_be_bytes());
+        frame.extend_from_slice(&api_version.to_be_bytes());
+        frame.extend_from_slice(&body);
+
+        _ = Frame::request_from_bytes(&frame).inspect_err(|err| debug!(?err));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn response_with_unknown_api_key() {
+    let mut c = Cursor::new(vec![0, 0, 0, 4, 0, 0, 0, 1]);
+
+    assert!(matches!(
+        Decoder::response(&mut c, -1, 0),
+        Err(Error::UnknownApiKey(-1))
+    ));
+}
+
+#[test]
+fn response_with_unsupported_api_version() {
+    let mut c = Cursor::new(vec![0, 0, 0, 4, 0, 0, 0, 1]);
+
+    assert!(matches!(
+        Decoder::response(&mut c, 18, 99),
+        Err(Error::UnsupportedApiVersion {
+            api_key: 18,
+            api_version: 99
+        })
+    ));
+
+    assert!(matches!(
+        Frame::decode_response(&[0, 0, 0, 4, 0, 0, 0, 1], ApiKey::ApiVersions, -1),
+        Err(Error::UnsupportedApiVersion {
+            api_key: 18,
+            api_version: -1
+        })
+    ));
+}
@@ -20,12 +20,13 @@ use std::{fs::File, io::Cursor, sync::Arc, thread};
 use tansu_kafka_sans_io::{
     join_group_request::JoinGroupRequestProtocol,
     join_group_response::JoinGroupResponseMember,
+    primitive::uuid::Uuid,
     record::{
         inflated::{self, Batch},
         Record,
     },
     ser::Encoder,
-    Body, Error, Frame, Header, Result,
+    ApiKey, Body, Error, Frame, Header, Result,
 };
 use tracing::subscriber::DefaultGuard;
 use tracing_subscriber::fmt::format::FmtSpan;
@@ -76,6 +77,7 @@ fn api_versions_request_v3_000() -> Result<()> {
         body: Body::ApiVersionsRequest {
             client_software_name: Some("apache-kafka-java".into()),
             client_software_version: Some("3.6.1".into()),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -306,6 +308,7 @@ fn api_versions_response_v1_000() -> Result<()> {
                 .into(),
             ),
             throttle_time_ms: Some(0),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -638,6 +641,7 @@ fn api_versions_response_v3_000() -> Result<()> {
                 .into(),
             ),
             throttle_time_ms: Some(0),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -672,6 +676,46 @@ fn api_versions_response_v3_000() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn controlled_shutdown_request_v0_000() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let body = Body::ControlledShutdownRequest {
+        broker_id: 5,
+        broker_epoch: None,
+        unknown_tagged_fields: vec![],
+    };
+
+    // the client id is only present from version 1 of the request header
+    assert_eq!(
+        vec![0, 0, 0, 12, 0, 7, 0, 0, 0, 0, 0, 1, 0, 0, 0, 5],
+        Frame::request(
+            Header::Request {
+                api_key: 7,
+                api_version: 0,
+                correlation_id: 1,
+                client_id: Some("ignored".into()),
+            },
+            body.clone(),
+        )?
+    );
+
+    assert_eq!(
+        vec![0, 0, 0, 14, 0, 7, 0, 1, 0, 0, 0, 1, 255, 255, 0, 0, 0, 5],
+        Frame::request(
+            Header::Request {
+                api_key: 7,
+                api_version: 1,
+                correlation_id: 1,
+                client_id: None,
+            },
+            body,
+        )?
+    );
+
+    Ok(())
+}
+
 #[test]
 fn create_topics_request_v7_000() -> Result<()> {
     use tansu_kafka_sans_io::create_topics_request::{CreatableTopic, CreateableTopicConfig};
@@ -707,6 +751,7 @@ fn create_topics_request_v7_000() -> Result<()> {
         ),
         timeout_ms,
         validate_only,
+        unknown_tagged_fields: vec![],
     };
 
     assert_eq!(
@@ -730,11 +775,11 @@ fn create_topics_response_v7_000() -> Result<()> {
 
     let _guard = init_tracing()?;
 
-    let api_key = 19;
+    let api_key = ApiKey::CreateTopics;
     let api_version = 7;
 
     let mut c = Cursor::new(vec![]);
-    let mut serializer = Encoder::response(&mut c, api_key, api_version);
+    let mut serializer = Encoder::response(&mut c, api_key.into(), api_version);
 
     let decoded = Frame {
         size: 1116,
@@ -746,9 +791,9 @@ fn create_topics_response_v7_000() -> Result<()> {
             topics: Some(
                 [CreatableTopicResult {
                     name: "balances".into(),
-                    topic_id: Some([
+                    topic_id: Some(Uuid([
                         222, 159, 182, 217, 102, 152, 68, 189, 174, 152, 214, 59, 29, 216, 240, 198,
-                    ]),
+                    ])),
                     error_code: 0,
                     error_message: None,
                     topic_config_error_code: None,
@@ -979,6 +1024,7 @@ fn create_topics_response_v7_000() -> Result<()> {
                 }]
                 .into(),
             ),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1067,12 +1113,13 @@ fn delete_topics_request_v6_000() -> Result<()> {
             topics: Some(
                 [DeleteTopicState {
                     name: Some("test".into()),
-                    topic_id: [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                    topic_id: Uuid([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
                 }]
                 .into(),
             ),
             topic_names: None,
             timeout_ms: 30000,
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1107,6 +1154,7 @@ fn describe_cluster_request_v1_000() -> Result<()> {
         body: Body::DescribeClusterRequest {
             include_cluster_authorized_operations: false,
             endpoint_type: Some(1),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1150,6 +1198,7 @@ fn describe_configs_request_v4_000() -> Result<()> {
             ),
             include_synonyms: Some(false),
             include_documentation: Some(false),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1180,6 +1229,7 @@ fn describe_groups_request_v1_000() -> Result<()> {
         body: Body::DescribeGroupsRequest {
             groups: Some(["abcabc".into()].into()),
             include_authorized_operations: None,
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1220,6 +1270,7 @@ fn describe_groups_response_v1_000() -> Result<()> {
                 }]
                 .into(),
             ),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1237,6 +1288,134 @@ fn describe_groups_response_v1_000() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn describe_producers_response_v0_000() -> Result<()> {
+    use tansu_kafka_sans_io::describe_producers_response::{
+        PartitionResponse, ProducerState, TopicResponse,
+    };
+
+    let _guard = init_tracing()?;
+
+    let header = Header::Response { correlation_id: 5 };
+    let body = Body::DescribeProducersResponse {
+        throttle_time_ms: 0,
+        topics: Some(
+            [TopicResponse {
+                name: "test".into(),
+                partitions: Some(
+                    [
+                        PartitionResponse {
+                            partition_index: 0,
+                            error_code: 0,
+                            error_message: None,
+                            active_producers: Some(
+                                [
+                                    ProducerState {
+                                        producer_id: 6,
+                                        producer_epoch: 0,
+                                        last_sequence: 2,
+                                        last_timestamp: 1_730_000_000_000,
+                                        coordinator_epoch: -1,
+                                        current_txn_start_offset: -1,
+                                    },
+                                    ProducerState {
+                                        producer_id: 7,
+                                        producer_epoch: 1,
+                                        last_sequence: 4,
+                                        last_timestamp: 1_730_000_001_000,
+                                        coordinator_epoch: -1,
+                                        current_txn_start_offset: 3,
+                                    },
+                                ]
+                                .into(),
+                            ),
+                        },
+                        PartitionResponse {
+                            partition_index: 1,
+                            error_code: 0,
+                            error_message: None,
+                            active_producers: Some([].into()),
+                        },
+                    ]
+                    .into(),
+                ),
+            }]
+            .into(),
+        ),
+        unknown_tagged_fields: vec![],
+    };
+
+    assert_eq!(
+        vec![
+            0, 0, 0, 110, 0, 0, 0, 5, 0, 0, 0, 0, 0, 2, 5, 116, 101, 115, 116, 3, 0, 0, 0, 0, 0, 0,
+            0, 3, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 1, 146, 204, 9, 20, 0, 255,
+            255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0,
+            0, 1, 0, 0, 0, 4, 0, 0, 1, 146, 204, 9, 23, 232, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0,
+            0, 3, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0,
+        ],
+        Frame::encode_response(header, body, ApiKey::DescribeProducers, 0)?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn describe_transactions_response_v0_000() -> Result<()> {
+    use tansu_kafka_sans_io::describe_transactions_response::{TopicData, TransactionState};
+
+    let _guard = init_tracing()?;
+
+    let header = Header::Response { correlation_id: 6 };
+    let body = Body::DescribeTransactionsResponse {
+        throttle_time_ms: 0,
+        transaction_states: Some(
+            [
+                TransactionState {
+                    error_code: 0,
+                    transactional_id: "abc".into(),
+                    transaction_state: "Ongoing".into(),
+                    transaction_timeout_ms: 60_000,
+                    transaction_start_time_ms: 1_730_000_000_000,
+                    producer_id: 6,
+                    producer_epoch: 0,
+                    topics: Some(
+                        [TopicData {
+                            topic: "test".into(),
+                            partitions: Some([0, 1].into()),
+                        }]
+                        .into(),
+                    ),
+                },
+                TransactionState {
+                    error_code: 105,
+                    transactional_id: "pqr".into(),
+                    transaction_state: "".into(),
+                    transaction_timeout_ms: 0,
+                    transaction_start_time_ms: -1,
+                    producer_id: -1,
+                    producer_epoch: -1,
+                    topics: Some([].into()),
+                },
+            ]
+            .into(),
+        ),
+        unknown_tagged_fields: vec![],
+    };
+
+    assert_eq!(
+        vec![
+            0, 0, 0, 95, 0, 0, 0, 6, 0, 0, 0, 0, 0, 3, 0, 0, 4, 97, 98, 99, 8, 79, 110, 103, 111,
+            105, 110, 103, 0, 0, 234, 96, 0, 0, 1, 146, 204, 9, 20, 0, 0, 0, 0, 0, 0, 0, 0, 6, 0,
+            0, 2, 5, 116, 101, 115, 116, 3, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 105, 4, 112, 113, 114,
+            1, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+            255, 255, 255, 255, 1, 0, 0,
+        ],
+        Frame::encode_response(header, body, ApiKey::DescribeTransactions, 0)?
+    );
+
+    Ok(())
+}
+
 #[test]
 fn fetch_request_v6_000() -> Result<()> {
     use tansu_kafka_sans_io::fetch_request::{FetchPartition, FetchTopic};
@@ -1280,6 +1459,7 @@ fn fetch_request_v6_000() -> Result<()> {
         ),
         forgotten_topics_data: None,
         rack_id: None,
+        unknown_tagged_fields: vec![],
     };
 
     assert_eq!(
@@ -1318,6 +1498,7 @@ fn fetch_request_v16_000() -> Result<()> {
         topics: Some([].into()),
         forgotten_topics_data: Some([].into()),
         rack_id: Some("".into()),
+        unknown_tagged_fields: vec![],
     };
 
     assert_eq!(
@@ -1338,7 +1519,7 @@ fn fetch_response_v12_000() -> Result<()> {
 
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 12;
 
     let header = Header::Response { correlation_id: 8 };
@@ -1398,6 +1579,7 @@ fn fetch_response_v12_000() -> Result<()> {
             .into(),
         ),
         node_endpoints: None,
+        unknown_tagged_fields: vec![],
     };
 
     let expected = vec![
@@ -1412,7 +1594,7 @@ fn fetch_response_v12_000() -> Result<()> {
 
     assert_eq!(
         expected,
-        Frame::response(header, body, api_key, api_version)?
+        Frame::encode_response(header, body, api_key, api_version)?
     );
     Ok(())
 }
@@ -1423,7 +1605,7 @@ fn fetch_response_v12_001() -> Result<()> {
 
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 12;
 
     let header = Header::Response { correlation_id: 8 };
@@ -1538,6 +1720,7 @@ fn fetch_response_v12_001() -> Result<()> {
             .into(),
         ),
         node_endpoints: None,
+        unknown_tagged_fields: vec![],
     };
 
     let expected = vec![
@@ -1556,7 +1739,7 @@ fn fetch_response_v12_001() -> Result<()> {
 
     assert_eq!(
         expected,
-        Frame::response(header, body, api_key, api_version)?
+        Frame::encode_response(header, body, api_key, api_version)?
     );
     Ok(())
 }
@@ -1569,7 +1752,7 @@ fn fetch_response_v16_001() -> Result<()> {
 
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 16;
 
     let header = Header::Response { correlation_id: 8 };
@@ -1580,9 +1763,9 @@ fn fetch_response_v16_001() -> Result<()> {
         responses: Some(
             [FetchableTopicResponse {
                 topic: None,
-                topic_id: Some([
+                topic_id: Some(Uuid([
                     28, 205, 172, 195, 142, 19, 71, 71, 182, 128, 13, 18, 65, 142, 210, 222,
-                ]),
+                ])),
                 partitions: Some(
                     [PartitionData {
                         partition_index: 0,
@@ -1641,6 +1824,7 @@ fn fetch_response_v16_001() -> Result<()> {
             .into(),
         ),
         node_endpoints: Some([].into()),
+        unknown_tagged_fields: vec![],
     };
 
     let expected = vec![
@@ -1656,7 +1840,7 @@ fn fetch_response_v16_001() -> Result<()> {
 
     assert_eq!(
         expected,
-        Frame::response(header, body, api_key, api_version)?
+        Frame::encode_response(header, body, api_key, api_version)?
     );
     Ok(())
 }
@@ -1669,7 +1853,7 @@ fn fetch_response_v16_002() -> Result<()> {
 
     let _guard = init_tracing()?;
 
-    let api_key = 1;
+    let api_key = ApiKey::Fetch;
     let api_version = 16;
 
     let header = Header::Response { correlation_id: 8 };
@@ -1680,9 +1864,9 @@ fn fetch_response_v16_002() -> Result<()> {
         responses: Some(
             [FetchableTopicResponse {
                 topic: None,
-                topic_id: Some([
+                topic_id: Some(Uuid([
                     28, 205, 172, 195, 142, 19, 71, 71, 182, 128, 13, 18, 65, 142, 210, 222,
-                ]),
+                ])),
                 partitions: Some(
                     [PartitionData {
                         partition_index: 0,
@@ -1741,6 +1925,7 @@ fn fetch_response_v16_002() -> Result<()> {
             .into(),
         ),
         node_endpoints: None,
+        unknown_tagged_fields: vec![],
     };
 
     let expected = vec![
@@ -1756,7 +1941,7 @@ fn fetch_response_v16_002() -> Result<()> {
 
     assert_eq!(
         expected,
-        Frame::response(header, body, api_key, api_version)?
+        Frame::encode_response(header, body, api_key, api_version)?
     );
     Ok(())
 }
@@ -1777,6 +1962,7 @@ fn find_coordinator_request_v1_000() -> Result<()> {
             key: Some("abcdef".into()),
             key_type: Some(0),
             coordinator_keys: None,
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1807,6 +1993,7 @@ fn find_coordinator_response_v1_000() -> Result<()> {
             host: Some("ip-10-2-91-66.eu-west-1.compute.internal".into()),
             port: Some(9092),
             coordinators: None,
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1847,6 +2034,7 @@ fn heartbeat_request_v4_000() -> Result<()> {
             generation_id: 0,
             member_id: "1000".into(),
             group_instance_id: None,
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1881,6 +2069,7 @@ fn init_producer_id_request_v4_000() -> Result<()> {
             transaction_timeout_ms: 2147483647,
             producer_id: Some(-1),
             producer_epoch: Some(-1),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1938,6 +2127,7 @@ fn join_group_request_v5_000() -> Result<()> {
                 .into(),
             ),
             reason: None,
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -1966,7 +2156,7 @@ fn join_group_request_v5_000() -> Result<()> {
 fn join_group_response_v5_000() -> Result<()> {
     let _guard = init_tracing()?;
 
-    let api_key = 11;
+    let api_key = ApiKey::JoinGroup;
     let api_version = 5;
 
     let metadata =
@@ -1992,11 +2182,12 @@ fn join_group_response_v5_000() -> Result<()> {
                 }]
                 .into(),
             ),
+            unknown_tagged_fields: vec![],
         },
     };
 
     let mut c = Cursor::new(vec![]);
-    let mut serializer = Encoder::response(&mut c, api_key, api_version);
+    let mut serializer = Encoder::response(&mut c, api_key.into(), api_version);
     frame.serialize(&mut serializer)?;
 
     assert_eq!(
@@ -2032,6 +2223,7 @@ fn list_groups_request_v4_000() -> Result<()> {
         },
         body: Body::ListGroupsRequest {
             states_filter: Some([].into()),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -2080,6 +2272,7 @@ fn list_offsets_response_v0_000() -> Result<()> {
                 }]
                 .into(),
             ),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -2125,6 +2318,7 @@ fn list_partition_reassignments_request_v0_000() -> Result<()> {
                 }]
                 .into(),
             ),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -2141,6 +2335,39 @@ fn list_partition_reassignments_request_v0_000() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn list_transactions_response_v0_000() -> Result<()> {
+    use tansu_kafka_sans_io::list_transactions_response::TransactionState;
+
+    let _guard = init_tracing()?;
+
+    let header = Header::Response { correlation_id: 7 };
+    let body = Body::ListTransactionsResponse {
+        throttle_time_ms: 0,
+        error_code: 0,
+        unknown_state_filters: Some(["Lost".into()].into()),
+        transaction_states: Some(
+            [TransactionState {
+                transactional_id: "abc".into(),
+                producer_id: 6,
+                transaction_state: "Ongoing".into(),
+            }]
+            .into(),
+        ),
+        unknown_tagged_fields: vec![],
+    };
+
+    assert_eq!(
+        vec![
+            0, 0, 0, 40, 0, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0, 2, 5, 76, 111, 115, 116, 2, 4, 97, 98,
+            99, 0, 0, 0, 0, 0, 0, 0, 6, 8, 79, 110, 103, 111, 105, 110, 103, 0, 0,
+        ],
+        Frame::encode_response(header, body, ApiKey::ListTransactions, 0)?
+    );
+
+    Ok(())
+}
+
 #[test]
 fn metadata_request_v12_000() -> Result<()> {
     use tansu_kafka_sans_io::metadata_request::MetadataRequestTopic;
@@ -2161,7 +2388,7 @@ fn metadata_request_v12_000() -> Result<()> {
         body: Body::MetadataRequest {
             topics: Some(
                 [MetadataRequestTopic {
-                    topic_id: Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                    topic_id: Some(Uuid([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])),
                     name: Some("test".into()),
                 }]
                 .into(),
@@ -2169,6 +2396,7 @@ fn metadata_request_v12_000() -> Result<()> {
             allow_auto_topic_creation: Some(true),
             include_cluster_authorized_operations: None,
             include_topic_authorized_operations: Some(false),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -2205,6 +2433,7 @@ fn metadata_request_v12_001() -> Result<()> {
             allow_auto_topic_creation: Some(true),
             include_cluster_authorized_operations: None,
             include_topic_authorized_operations: Some(false),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -2227,11 +2456,11 @@ fn metadata_response_v12_0000() -> Result<()> {
 
     let _guard = init_tracing()?;
 
-    let api_key = 3;
+    let api_key = ApiKey::Metadata;
     let api_version = 12;
 
     let mut c = Cursor::new(vec![]);
-    let mut serializer = Encoder::response(&mut c, api_key, api_version);
+    let mut serializer = Encoder::response(&mut c, api_key.into(), api_version);
 
     let frame = Frame {
         size: 92,
@@ -2249,12 +2478,13 @@ fn metadata_response_v12_0000() -> Result<()> {
             topics: Some(vec![MetadataResponseTopic {
                 error_code: 3,
                 name: Some("test".into()),
-                topic_id: Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                topic_id: Some(Uuid([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])),
                 is_internal: Some(false),
                 partitions: Some(vec![]),
                 topic_authorized_operations: Some(-2147483648),
             }]),
             cluster_authorized_operations: None,
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -2304,6 +2534,7 @@ fn offset_fetch_request_v3_000() -> Result<()> {
             ),
             groups: None,
             require_stable: None,
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -2346,6 +2577,7 @@ fn offset_for_leader_request_v0_000() -> Result<()> {
                 }]
                 .into(),
             ),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -2424,6 +2656,7 @@ fn produce_request_v9_000() -> Result<()> {
                 }]
                 .into(),
             ),
+            unknown_tagged_fields: vec![],
         },
     };
 
@@ -2475,6 +2708,7 @@ fn produce_response_v9_000() -> Result<()> {
         ),
         node_endpoints: None,
         throttle_time_ms: Some(0),
+        unknown_tagged_fields: vec![],
     };
 
     assert_eq!(
@@ -2483,7 +2717,7 @@ fn produce_response_v9_000() -> Result<()> {
             0, 0, 0, 2, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0,
             0, 0, 0, 0, 0,
         ],
-        Frame::response(header, body, 0, 9)?
+        Frame::encode_response(header, body, ApiKey::Produce, 9)?
     );
 
     Ok(())
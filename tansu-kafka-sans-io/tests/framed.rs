@@ -0,0 +1,236 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![cfg(feature = "tokio-util")]
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use std::{fs::File, sync::Arc, thread};
+use tansu_kafka_sans_io::{
+    codec::{KafkaFrameCodec, RawFrame},
+    ApiKey, Error, Frame, Header, Result,
+};
+use tokio::io::{duplex, AsyncWriteExt};
+use tokio_util::codec::{Framed, FramedRead};
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[cfg(miri)]
+fn init_tracing() -> Result<()> {
+    Ok(())
+}
+
+#[cfg(not(miri))]
+fn init_tracing() -> Result<DefaultGuard> {
+    Ok(tracing::subscriber::set_default(
+        tracing_subscriber::fmt()
+            .with_level(true)
+            .with_line_number(true)
+            .with_thread_names(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::ACTIVE)
+            .with_writer(
+                thread::current()
+                    .name()
+                    .ok_or(Error::Message(String::from("unnamed thread")))
+                    .and_then(|name| {
+                        File::create(format!(
+                            "../logs/{}/framed-{name}.log",
+                            env!("CARGO_PKG_NAME")
+                        ))
+                        .map_err(Into::into)
+                    })
+                    .map(Arc::new)?,
+            )
+            .finish(),
+    ))
+}
+
+const API_VERSIONS_REQUEST_V3: &[u8] = &[
+    0, 0, 0, 52, 0, 18, 0, 3, 0, 0, 0, 3, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112, 114,
+    111, 100, 117, 99, 101, 114, 0, 18, 97, 112, 97, 99, 104, 101, 45, 107, 97, 102, 107, 97, 45,
+    106, 97, 118, 97, 6, 51, 46, 54, 46, 49, 0,
+];
+
+const API_VERSIONS_RESPONSE_V1: &[u8] = &[
+    0, 0, 0, 242, 0, 0, 0, 0, 0, 0, 0, 0, 0, 38, 0, 0, 0, 0, 0, 5, 0, 1, 0, 0, 0, 6, 0, 2, 0, 0, 0,
+    2, 0, 3, 0, 0, 0, 5, 0, 4, 0, 0, 0, 1, 0, 5, 0, 0, 0, 0, 0, 6, 0, 0, 0, 4, 0, 7, 0, 0, 0, 1, 0,
+    8, 0, 0, 0, 3, 0, 9, 0, 0, 0, 3, 0, 10, 0, 0, 0, 1, 0, 11, 0, 0, 0, 2, 0, 12, 0, 0, 0, 1, 0,
+    13, 0, 0, 0, 1, 0, 14, 0, 0, 0, 1, 0, 15, 0, 0, 0, 1, 0, 16, 0, 0, 0, 1, 0, 17, 0, 0, 0, 1, 0,
+    18, 0, 0, 0, 1, 0, 19, 0, 0, 0, 2, 0, 20, 0, 0, 0, 1, 0, 21, 0, 0, 0, 0, 0, 22, 0, 0, 0, 0, 0,
+    23, 0, 0, 0, 0, 0, 24, 0, 0, 0, 0, 0, 25, 0, 0, 0, 0, 0, 26, 0, 0, 0, 0, 0, 27, 0, 0, 0, 0, 0,
+    28, 0, 0, 0, 0, 0, 29, 0, 0, 0, 0, 0, 30, 0, 0, 0, 0, 0, 31, 0, 0, 0, 0, 0, 32, 0, 0, 0, 0, 0,
+    33, 0, 0, 0, 0, 0, 34, 0, 0, 0, 0, 0, 35, 0, 0, 0, 0, 0, 36, 0, 0, 0, 0, 0, 37, 0, 0, 0, 0, 0,
+    0, 0, 0,
+];
+
+#[tokio::test]
+async fn pipelined_requests_in_one_segment() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let (mut client, server) = duplex(1_024);
+
+    let mut segment = Vec::new();
+    for _ in 0..3 {
+        segment.extend_from_slice(API_VERSIONS_REQUEST_V3);
+    }
+    client.write_all(&segment).await?;
+    drop(client);
+
+    let mut framed = FramedRead::new(server, KafkaFrameCodec::request());
+
+    for _ in 0..3 {
+        let RawFrame { header, api, frame } = framed
+            .next()
+            .await
+            .transpose()?
+            .ok_or(Error::Message(String::from("expected a frame")))?;
+
+        assert_eq!(
+            Header::Request {
+                api_key: 18,
+                api_version: 3,
+                correlation_id: 3,
+                client_id: Some(String::from("console-producer")),
+            },
+            header
+        );
+        assert_eq!(Some((18, 3)), api);
+        assert_eq!(API_VERSIONS_REQUEST_V3, &frame[..]);
+    }
+
+    assert!(framed.next().await.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_split_across_segments() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let (mut client, server) = duplex(1_024);
+    let mut framed = FramedRead::new(server, KafkaFrameCodec::request());
+
+    let (head, tail) = API_VERSIONS_REQUEST_V3.split_at(7);
+
+    client.write_all(head).await?;
+    client.flush().await?;
+
+    let next = tokio::spawn(async move { framed.next().await.transpose() });
+    tokio::task::yield_now().await;
+
+    client.write_all(tail).await?;
+    drop(client);
+
+    let raw = next
+        .await
+        .map_err(|join| Error::Message(join.to_string()))??
+        .ok_or(Error::Message(String::from("expected a frame")))?;
+
+    assert_eq!(
+        Frame::request_from_bytes(API_VERSIONS_REQUEST_V3)?,
+        raw.decode()?
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn broker_and_client() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let api_key = ApiKey::ApiVersions;
+    let api_version = 3;
+
+    let (client, broker) = duplex(4_096);
+    let mut client = Framed::new(client, KafkaFrameCodec::response());
+    let mut broker = Framed::new(broker, KafkaFrameCodec::request());
+
+    let request = Frame::request_from_bytes(API_VERSIONS_REQUEST_V3)?;
+    client.send(request.clone()).await?;
+
+    let received = broker
+        .next()
+        .await
+        .transpose()?
+        .ok_or(Error::Message(String::from("expected a request")))?;
+    assert_eq!(request, received.decode()?);
+
+    let response =
+        Frame::decode_response(API_VERSIONS_RESPONSE_V1, api_key, 1).map(|frame| Frame {
+            header: Header::Response { correlation_id: 3 },
+            ..frame
+        })?;
+
+    // typed, using the api key and version of the received request
+    broker.send(response.clone()).await?;
+
+    let received = client
+        .next()
+        .await
+        .transpose()?
+        .ok_or(Error::Message(String::from("expected a response")))?;
+    assert_eq!(Some((i16::from(api_key), api_version)), received.api);
+    assert_eq!(
+        Frame::encode_response(response.header, response.body, api_key, api_version)?,
+        received.frame.to_vec()
+    );
+
+    // the response has been sent, and is no longer in flight
+    assert!(matches!(
+        broker
+            .send(Frame::decode_response(
+                API_VERSIONS_RESPONSE_V1,
+                api_key,
+                1
+            )?)
+            .await,
+        Err(Error::UnknownCorrelationId(0))
+    ));
+
+    // pre-encoded frames pass through unchanged
+    client
+        .send(Bytes::from_static(API_VERSIONS_REQUEST_V3))
+        .await?;
+
+    let received = broker
+        .next()
+        .await
+        .transpose()?
+        .ok_or(Error::Message(String::from("expected a request")))?;
+    assert_eq!(API_VERSIONS_REQUEST_V3, &received.frame[..]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn frame_too_large() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let (mut client, server) = duplex(1_024);
+    client.write_all(&[0, 1, 0, 0]).await?;
+
+    let mut framed = FramedRead::new(server, KafkaFrameCodec::request().max_frame_length(1_024));
+
+    assert!(matches!(
+        framed.next().await,
+        Some(Err(Error::FrameTooLarge {
+            length: 65_540,
+            maximum: 1_024
+        }))
+    ));
+
+    Ok(())
+}
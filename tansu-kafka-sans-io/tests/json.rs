@@ -0,0 +1,90 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![cfg(feature = "json")]
+
+use serde_json::{json, Value};
+use tansu_kafka_sans_io::{json, Frame};
+
+type Result<T, E = Box<dyn std::error::Error>> = std::result::Result<T, E>;
+
+#[test]
+fn fetch_request_v12_000() -> Result<()> {
+    let encoded = vec![
+        0, 0, 0, 162, 0, 1, 0, 12, 0, 0, 0, 8, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 99,
+        111, 110, 115, 117, 109, 101, 114, 0, 255, 255, 255, 255, 0, 0, 1, 244, 0, 0, 0, 1, 3, 32,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 5, 116, 101, 115, 116, 4, 0, 0, 0, 1, 255, 255, 255,
+        255, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0,
+        16, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255,
+        255, 255, 255, 255, 255, 255, 255, 255, 0, 16, 0, 0, 0, 0, 0, 0, 2, 255, 255, 255, 255, 0,
+        0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 16, 0,
+        0, 0, 0, 1, 1, 0,
+    ];
+
+    let frame = Frame::request_from_bytes(&encoded)?;
+
+    let encoded_json = serde_json::to_string(&json::Frame::from(frame.clone()))?;
+    let value = serde_json::from_str::<Value>(&encoded_json)?;
+
+    assert_eq!(
+        json!({
+            "Request": {
+                "api_key": 1,
+                "api_version": 12,
+                "correlation_id": 8,
+                "client_id": "console-consumer"
+            }
+        }),
+        value["header"]
+    );
+
+    let body = &value["body"]["FetchRequest"];
+    assert_eq!(json!(500), body["max_wait_ms"]);
+    assert_eq!(json!("test"), body["topics"][0]["topic"]);
+    assert_eq!(json!(1), body["topics"][0]["partitions"][0]["partition"]);
+
+    let decoded = Frame::from(serde_json::from_str::<json::Frame>(&encoded_json)?);
+    assert_eq!(frame, decoded);
+    assert_eq!(encoded, Frame::request(decoded.header, decoded.body)?);
+
+    Ok(())
+}
+
+#[test]
+fn produce_request_v9_000() -> Result<()> {
+    let encoded = vec![
+        0, 0, 0, 120, 0, 0, 0, 9, 0, 0, 0, 6, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112,
+        114, 111, 100, 117, 99, 101, 114, 0, 0, 255, 255, 0, 0, 5, 220, 2, 5, 116, 101, 115, 116,
+        2, 0, 0, 0, 0, 72, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 59, 255, 255, 255, 255, 2, 67, 41, 231,
+        61, 0, 0, 0, 0, 0, 0, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 1, 141, 116, 152, 137, 53, 0,
+        0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 18, 0, 0, 0, 1, 6, 100, 101, 102, 0, 0,
+        0, 0,
+    ];
+
+    let frame = Frame::request_from_bytes(&encoded)?;
+
+    let value = serde_json::to_value(json::Frame::from(frame.clone()))?;
+
+    assert!(
+        value["body"]["ProduceRequest"]["topic_data"][0]["partition_data"][0]["records"]
+            .is_string()
+    );
+
+    let decoded = Frame::from(serde_json::from_value::<json::Frame>(value)?);
+    assert_eq!(frame, decoded);
+    assert_eq!(encoded, Frame::request(decoded.header, decoded.body)?);
+
+    Ok(())
+}
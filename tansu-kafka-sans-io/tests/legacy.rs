@@ -0,0 +1,223 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use bytes::{Bytes, BytesMut};
+use tansu_kafka_sans_io::{
+    record::{
+        inflated,
+        legacy::{Message, MessageSet},
+        TimestampType,
+    },
+    Compression, Error, Result,
+};
+
+// magic 0 messages: a null key with "abc", then "k" with "def"
+const V0: &[u8] = &[
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 17, 67, 220, 63, 175, 0, 0, 255, 255, 255, 255, 0, 0, 0, 3,
+    97, 98, 99, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 18, 170, 113, 70, 244, 0, 0, 0, 0, 0, 1, 107, 0,
+    0, 0, 3, 100, 101, 102,
+];
+
+// the same messages as magic 1, with create time timestamps
+const V1: &[u8] = &[
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 25, 177, 170, 71, 74, 1, 0, 0, 0, 1, 141, 116, 152, 137, 53,
+    255, 255, 255, 255, 0, 0, 0, 3, 97, 98, 99, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 26, 199, 41, 74,
+    181, 1, 0, 0, 0, 1, 141, 116, 152, 137, 54, 0, 0, 0, 1, 107, 0, 0, 0, 3, 100, 101, 102,
+];
+
+// the magic 1 messages with relative offsets, in a gzip wrapper message
+const V1_GZIP: &[u8] = &[
+    0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 88, 42, 56, 123, 11, 1, 1, 0, 0, 1, 141, 116, 152, 137, 54,
+    255, 255, 255, 255, 0, 0, 0, 66, 31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 99, 96, 128, 3, 201, 141,
+    171, 220, 189, 24, 129, 12, 198, 222, 146, 25, 157, 166, 255, 129, 0, 200, 97, 78, 76, 74, 134,
+    42, 0, 201, 73, 29, 215, 244, 218, 10, 87, 100, 6, 98, 100, 131, 84, 165, 164, 166, 1, 0, 2,
+    116, 204, 19, 75, 0, 0, 0,
+];
+
+// a magic 2 record batch
+const BATCH: &[u8] = &[
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 59, 255, 255, 255, 255, 2, 67, 41, 231, 61, 0, 0, 0, 0, 0, 0,
+    0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0,
+    0, 0, 0, 1, 0, 0, 0, 1, 18, 0, 0, 0, 1, 6, 100, 101, 102, 0,
+];
+
+fn key_values(batch: &inflated::Batch) -> Vec<(i32, i64, Option<Bytes>, Option<Bytes>)> {
+    batch
+        .records
+        .iter()
+        .map(|record| {
+            (
+                record.offset_delta,
+                record.timestamp_delta,
+                record.key.clone(),
+                record.value.clone(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn is_legacy() {
+    assert!(MessageSet::is_legacy(V0));
+    assert!(MessageSet::is_legacy(V1));
+    assert!(MessageSet::is_legacy(V1_GZIP));
+    assert!(!MessageSet::is_legacy(BATCH));
+    assert!(!MessageSet::is_legacy(&[]));
+}
+
+#[test]
+fn magic_0() -> Result<()> {
+    let message_set = MessageSet::try_from(Bytes::from_static(V0))?;
+
+    assert_eq!(
+        vec![
+            Message {
+                offset: 0,
+                magic: 0,
+                attributes: 0,
+                timestamp: None,
+                key: None,
+                value: Some(Bytes::from_static(b"abc")),
+            },
+            Message {
+                offset: 1,
+                magic: 0,
+                attributes: 0,
+                timestamp: None,
+                key: Some(Bytes::from_static(b"k")),
+                value: Some(Bytes::from_static(b"def")),
+            },
+        ],
+        message_set.messages
+    );
+
+    let batch = inflated::Batch::try_from(message_set)?;
+
+    assert_eq!(2, batch.magic);
+    assert_eq!(0, batch.base_offset);
+    assert_eq!(1, batch.last_offset_delta);
+    assert_eq!(-1, batch.base_timestamp);
+    assert_eq!(-1, batch.producer_id);
+    assert_eq!(Compression::None, batch.compression()?);
+
+    assert_eq!(
+        vec![
+            (0, 0, None, Some(Bytes::from_static(b"abc"))),
+            (
+                1,
+                0,
+                Some(Bytes::from_static(b"k")),
+                Some(Bytes::from_static(b"def"))
+            ),
+        ],
+        key_values(&batch)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn magic_1() -> Result<()> {
+    let batch = MessageSet::try_from(Bytes::from_static(V1)).and_then(inflated::Batch::try_from)?;
+
+    assert_eq!(1_707_058_170_165, batch.base_timestamp);
+    assert_eq!(1_707_058_170_166, batch.max_timestamp);
+    assert_eq!(TimestampType::CreateTime, batch.timestamp_type());
+
+    assert_eq!(
+        vec![
+            (0, 0, None, Some(Bytes::from_static(b"abc"))),
+            (
+                1,
+                1,
+                Some(Bytes::from_static(b"k")),
+                Some(Bytes::from_static(b"def"))
+            ),
+        ],
+        key_values(&batch)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn magic_1_gzip() -> Result<()> {
+    let message_set = MessageSet::try_from(Bytes::from_static(V1_GZIP))?;
+
+    assert_eq!(
+        vec![0, 1],
+        message_set
+            .messages
+            .iter()
+            .map(|message| message.offset)
+            .collect::<Vec<_>>()
+    );
+
+    let uncompressed =
+        MessageSet::try_from(Bytes::from_static(V1)).and_then(inflated::Batch::try_from)?;
+
+    assert_eq!(uncompressed, inflated::Batch::try_from(message_set)?);
+
+    Ok(())
+}
+
+#[test]
+fn magic_1_gzip_beyond_maximum() -> Result<()> {
+    assert!(matches!(
+        MessageSet::decode_within(Bytes::from_static(V1_GZIP), 16),
+        Err(Error::InflatedTooLarge { maximum: 16 })
+    ));
+
+    assert_eq!(
+        MessageSet::try_from(Bytes::from_static(V1_GZIP))?,
+        MessageSet::decode_within(Bytes::from_static(V1_GZIP), V1.len())?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn crc_mismatch() {
+    let mut encoded = BytesMut::from(V0);
+    let last = encoded.len() - 1;
+    encoded[last] ^= 0xff;
+
+    assert!(matches!(
+        MessageSet::try_from(encoded.freeze()),
+        Err(Error::CrcMismatch {
+            expected: 2_859_550_452,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn truncated() {
+    assert!(matches!(
+        MessageSet::try_from(Bytes::from_static(&V0[..V0.len() - 1])),
+        Err(Error::Incomplete { needed: Some(1) })
+    ));
+}
+
+#[test]
+fn invalid_message_size() {
+    let mut encoded = BytesMut::from(V0);
+    encoded[8..12].copy_from_slice(&3i32.to_be_bytes());
+
+    assert!(matches!(
+        MessageSet::try_from(encoded.freeze()),
+        Err(Error::InvalidMessageSize(3))
+    ));
+}
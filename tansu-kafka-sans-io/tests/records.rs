@@ -0,0 +1,243 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use bytes::{Bytes, BytesMut};
+use std::io::Cursor;
+use tansu_kafka_sans_io::{
+    record::{
+        deflated, inflated,
+        records::{BatchHeader, Reader},
+        Record, Records,
+    },
+    Body, Error, Frame, Result,
+};
+
+// the records of produce_request_v9_000 from the console producer
+const BATCH: &[u8] = &[
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 59, 255, 255, 255, 255, 2, 67, 41, 231, 61, 0, 0, 0, 0, 0, 0,
+    0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0,
+    0, 0, 0, 1, 0, 0, 0, 1, 18, 0, 0, 0, 1, 6, 100, 101, 102, 0,
+];
+
+const PRODUCE_REQUEST_V9_000: &[u8] = &[
+    0, 0, 0, 120, 0, 0, 0, 9, 0, 0, 0, 6, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112, 114,
+    111, 100, 117, 99, 101, 114, 0, 0, 255, 255, 0, 0, 5, 220, 2, 5, 116, 101, 115, 116, 2, 0, 0,
+    0, 0, 72, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 59, 255, 255, 255, 255, 2, 67, 41, 231, 61, 0, 0, 0,
+    0, 0, 0, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 0, 0, 0, 0, 0,
+    1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 18, 0, 0, 0, 1, 6, 100, 101, 102, 0, 0, 0, 0,
+];
+
+#[test]
+fn headers() -> Result<()> {
+    let records = Records::new(Bytes::from_static(BATCH));
+
+    assert_eq!(
+        vec![BatchHeader {
+            base_offset: 0,
+            batch_length: 59,
+            partition_leader_epoch: -1,
+            magic: 2,
+            crc: 1126819645,
+            attributes: 0,
+            last_offset_delta: 0,
+            base_timestamp: 1707058170165,
+            max_timestamp: 1707058170165,
+            producer_id: 1,
+            producer_epoch: 0,
+            base_sequence: 1,
+            record_count: 1,
+        }],
+        records.headers()?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn batches_share_record_data() -> Result<()> {
+    let encoded = Bytes::from_static(BATCH);
+    let records = Records::new(encoded.clone());
+
+    let batches = records.batches()?;
+    assert_eq!(1, batches.len());
+
+    // the record data follows the 61 byte batch header
+    assert_eq!(
+        encoded.as_ptr().wrapping_add(61),
+        batches[0].record_data.as_ptr()
+    );
+    assert_eq!(&BATCH[61..], &batches[0].record_data[..]);
+
+    assert_eq!(
+        vec![Record {
+            length: 9,
+            attributes: 0,
+            timestamp_delta: 0,
+            offset_delta: 0,
+            key: None,
+            value: Some(Bytes::from_static(b"def")),
+            headers: [].into(),
+        }],
+        inflated::Batch::try_from(batches[0].clone())?.records
+    );
+
+    Ok(())
+}
+
+#[test]
+fn verify() -> Result<()> {
+    Records::new(Bytes::from_static(BATCH)).verify()?;
+
+    let mut corrupted = BytesMut::from(BATCH);
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0xff;
+
+    assert!(matches!(
+        Records::new(corrupted.freeze()).verify(),
+        Err(Error::CrcMismatch {
+            expected: 1126819645,
+            ..
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn truncated() -> Result<()> {
+    let records = Records::new(Bytes::from_static(&BATCH[..BATCH.len() - 1]));
+
+    assert!(matches!(
+        records.batches(),
+        Err(Error::Incomplete { needed: Some(1) })
+    ));
+
+    let records = Records::new(Bytes::from_static(&BATCH[..32]));
+
+    assert!(matches!(
+        records.headers(),
+        Err(Error::Incomplete { needed: Some(29) })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn invalid_batch_length() -> Result<()> {
+    let mut encoded = BytesMut::from(BATCH);
+    encoded[8..12].copy_from_slice(&(-1i32).to_be_bytes());
+
+    assert!(matches!(
+        Records::new(encoded.freeze()).headers(),
+        Err(Error::InvalidBatchLength(-1))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn round_trip() -> Result<()> {
+    let records = inflated::Batch::builder()
+        .record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+        .record(Record::builder().value(Bytes::from_static(b"ipsum").into()))
+        .build()
+        .and_then(deflated::Batch::try_from)
+        .and_then(Records::try_from)?;
+
+    records.verify()?;
+    assert_eq!(2, records.headers()?[0].record_count);
+
+    let frame = deflated::Frame::try_from(records.clone())?;
+    assert_eq!(records, Records::try_from(frame)?);
+
+    Ok(())
+}
+
+#[test]
+fn produce_request_keeps_encoded_records() -> Result<()> {
+    let frame = Frame::request_from_bytes(PRODUCE_REQUEST_V9_000)?;
+
+    let Body::ProduceRequest {
+        topic_data: Some(topic_data),
+        ..
+    } = &frame.body
+    else {
+        panic!("expected a produce request with topic data")
+    };
+
+    let records = topic_data
+        .iter()
+        .flat_map(|topic| topic.partition_data.iter().flatten())
+        .flat_map(|partition| partition.records.iter())
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec![&Records::new(Bytes::from_static(BATCH))], records);
+
+    // spliced back into the frame as received
+    assert_eq!(
+        PRODUCE_REQUEST_V9_000,
+        &Frame::request(frame.header.clone(), frame.body.clone())?[..]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reader() -> Result<()> {
+    let mut encoded = [BATCH, BATCH].concat();
+    let following = [0xca, 0xfe];
+    encoded.extend_from_slice(&following);
+
+    let mut c = Cursor::new(encoded);
+    let mut reader = Reader::new(&mut c, BATCH.len() * 2);
+
+    let expected = Records::new(Bytes::from_static(BATCH)).batches()?;
+
+    assert_eq!(expected.first(), reader.next().transpose()?.as_ref());
+    assert_eq!(BATCH.len(), reader.remaining());
+
+    assert_eq!(expected.first(), reader.next().transpose()?.as_ref());
+    assert_eq!(0, reader.remaining());
+
+    assert!(reader.next().is_none());
+
+    // the bytes after the records are left unread
+    assert_eq!(BATCH.len() as u64 * 2, c.position());
+
+    Ok(())
+}
+
+#[test]
+fn reader_with_partial_batch() -> Result<()> {
+    let encoded = [BATCH, &BATCH[..BATCH.len() - 1]].concat();
+    let length = encoded.len();
+
+    let mut c = Cursor::new(encoded);
+    let mut reader = Reader::new(&mut c, length);
+
+    assert!(reader.next().transpose()?.is_some());
+
+    assert!(matches!(
+        reader.next(),
+        Some(Err(Error::Incomplete { needed: Some(1) }))
+    ));
+
+    assert!(reader.next().is_none());
+
+    // the partial batch is skipped
+    assert_eq!(length as u64, c.position());
+
+    Ok(())
+}
@@ -0,0 +1,352 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs::File, sync::Arc, thread};
+use tansu_kafka_sans_io::{
+    response::{
+        FetchResponse, FindCoordinatorResponse, MetadataResponse, OffsetFetchResponse,
+        ProduceResponse,
+    },
+    ApiKey, Body, Error, ErrorCode, Frame, Header, Result,
+};
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[cfg(miri)]
+fn init_tracing() -> Result<()> {
+    Ok(())
+}
+
+#[cfg(not(miri))]
+fn init_tracing() -> Result<DefaultGuard> {
+    Ok(tracing::subscriber::set_default(
+        tracing_subscriber::fmt()
+            .with_level(true)
+            .with_line_number(true)
+            .with_thread_names(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::ACTIVE)
+            .with_writer(
+                thread::current()
+                    .name()
+                    .ok_or(Error::Message(String::from("unnamed thread")))
+                    .and_then(|name| {
+                        File::create(format!(
+                            "../logs/{}/response-{name}.log",
+                            env!("CARGO_PKG_NAME")
+                        ))
+                        .map_err(Into::into)
+                    })
+                    .map(Arc::new)?,
+            )
+            .finish(),
+    ))
+}
+
+fn round_trip(body: Body, api_key: ApiKey, api_version: i16) -> Result<Body> {
+    let correlation_id = 12321;
+
+    let encoded = Frame::encode_response(
+        Header::Response { correlation_id },
+        body,
+        api_key,
+        api_version,
+    )?;
+
+    let frame = Frame::decode_response(&encoded, api_key, api_version)?;
+    assert_eq!(Header::Response { correlation_id }, frame.header);
+    Ok(frame.body)
+}
+
+#[test]
+fn produce() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    for api_version in [0, 3, 8, 9, 11] {
+        let body = ProduceResponse::builder()
+            .response("test", |t| {
+                t.partition(0, ErrorCode::None, 32123).partition(
+                    1,
+                    ErrorCode::NotLeaderOrFollower,
+                    -1,
+                )
+            })
+            .build()?;
+
+        let Body::ProduceResponse { responses, .. } =
+            round_trip(body, ApiKey::Produce, api_version)?
+        else {
+            panic!("expected produce response at v{api_version}")
+        };
+
+        let responses = responses.unwrap_or_default();
+        assert_eq!(1, responses.len());
+        assert_eq!("test", responses[0].name);
+
+        let partitions = responses[0]
+            .partition_responses
+            .as_deref()
+            .unwrap_or_default();
+        assert_eq!(2, partitions.len());
+        assert_eq!(32123, partitions[0].base_offset);
+        assert_eq!(
+            i16::from(ErrorCode::NotLeaderOrFollower),
+            partitions[1].error_code
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn fetch() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    for api_version in [0, 4, 7, 12, 13, 16] {
+        let body = FetchResponse::builder()
+            .api_version(api_version)
+            .response("test", |t| {
+                t.topic_id([1; 16]).partition(0, ErrorCode::None, 6, None)
+            })
+            .build()?;
+
+        let Body::FetchResponse { responses, .. } = round_trip(body, ApiKey::Fetch, api_version)?
+        else {
+            panic!("expected fetch response at v{api_version}")
+        };
+
+        let responses = responses.unwrap_or_default();
+        assert_eq!(1, responses.len());
+
+        let partitions = responses[0].partitions.as_deref().unwrap_or_default();
+        assert_eq!(1, partitions.len());
+        assert_eq!(6, partitions[0].high_watermark);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn fetch_without_topic_id() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    assert!(FetchResponse::builder()
+        .api_version(12)
+        .response("test", |t| t.partition(0, ErrorCode::None, 6, None))
+        .build()
+        .is_ok());
+
+    assert!(matches!(
+        FetchResponse::builder()
+            .api_version(13)
+            .response("test", |t| t.partition(0, ErrorCode::None, 6, None))
+            .build(),
+        Err(Error::MissingField {
+            name: "topic_id",
+            api_version: 13
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn metadata() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    for api_version in [0, 1, 5, 9, 12] {
+        let body = MetadataResponse::builder()
+            .api_version(api_version)
+            .broker(111, "localhost", 9092)
+            .cluster_id(Some("abc"))
+            .controller_id(111)
+            .topic("test", |t| t.partition(0, 111, &[111], &[111]))
+            .build()?;
+
+        let Body::MetadataResponse {
+            brokers, topics, ..
+        } = round_trip(body, ApiKey::Metadata, api_version)?
+        else {
+            panic!("expected metadata response at v{api_version}")
+        };
+
+        let brokers = brokers.unwrap_or_default();
+        assert_eq!(1, brokers.len());
+        assert_eq!(111, brokers[0].node_id);
+
+        let topics = topics.unwrap_or_default();
+        assert_eq!(1, topics.len());
+        assert_eq!(Some("test"), topics[0].name.as_deref());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn offset_fetch() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    for api_version in [0, 5, 7] {
+        let body = OffsetFetchResponse::builder()
+            .api_version(api_version)
+            .topic("test", |t| t.partition(0, 5, ErrorCode::None))
+            .build()?;
+
+        let Body::OffsetFetchResponse { topics, .. } =
+            round_trip(body, ApiKey::OffsetFetch, api_version)?
+        else {
+            panic!("expected offset fetch response at v{api_version}")
+        };
+
+        let topics = topics.unwrap_or_default();
+        assert_eq!(1, topics.len());
+        assert_eq!(
+            Some(5),
+            topics[0]
+                .partitions
+                .as_deref()
+                .and_then(|partitions| partitions.first())
+                .map(|partition| partition.committed_offset)
+        );
+    }
+
+    for api_version in [8, 9] {
+        let body = OffsetFetchResponse::builder()
+            .api_version(api_version)
+            .group("abc", "test", |t| t.partition(0, 5, ErrorCode::None))
+            .group("abc", "other", |t| t.partition(1, 7, ErrorCode::None))
+            .build()?;
+
+        let Body::OffsetFetchResponse { groups, .. } =
+            round_trip(body, ApiKey::OffsetFetch, api_version)?
+        else {
+            panic!("expected offset fetch response at v{api_version}")
+        };
+
+        let groups = groups.unwrap_or_default();
+        assert_eq!(1, groups.len());
+        assert_eq!("abc", groups[0].group_id);
+        assert_eq!(2, groups[0].topics.as_ref().map_or(0, Vec::len));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn offset_fetch_group_error_code() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let body = OffsetFetchResponse::builder()
+        .api_version(8)
+        .group("abc", "test", |t| t.partition(0, 5, ErrorCode::None))
+        .group_error_code("abc", ErrorCode::CoordinatorNotAvailable)
+        .group_error_code("pqr", ErrorCode::GroupAuthorizationFailed)
+        .build()?;
+
+    let Body::OffsetFetchResponse { groups, .. } = round_trip(body, ApiKey::OffsetFetch, 8)? else {
+        panic!("expected offset fetch response")
+    };
+
+    assert_eq!(
+        vec![
+            ("abc", i16::from(ErrorCode::CoordinatorNotAvailable), 1),
+            ("pqr", i16::from(ErrorCode::GroupAuthorizationFailed), 0),
+        ],
+        groups
+            .iter()
+            .flatten()
+            .map(|group| (
+                group.group_id.as_str(),
+                group.error_code,
+                group.topics.as_ref().map_or(0, Vec::len)
+            ))
+            .collect::<Vec<_>>()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn offset_fetch_missing_groups() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    assert!(matches!(
+        OffsetFetchResponse::builder()
+            .api_version(8)
+            .topic("test", |t| t.partition(0, 5, ErrorCode::None))
+            .build(),
+        Err(Error::MissingField {
+            name: "groups",
+            api_version: 8
+        })
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn find_coordinator() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    for api_version in [0, 1, 3, 4, 5] {
+        let body = FindCoordinatorResponse::builder()
+            .coordinator(111, "localhost", 9092)
+            .keys(["abc"])
+            .build()?;
+
+        match round_trip(body, ApiKey::FindCoordinator, api_version)? {
+            Body::FindCoordinatorResponse {
+                node_id,
+                coordinators,
+                ..
+            } if api_version < 4 => {
+                assert_eq!(Some(111), node_id);
+                assert!(coordinators.is_none());
+            }
+
+            Body::FindCoordinatorResponse { coordinators, .. } => {
+                let coordinators = coordinators.unwrap_or_default();
+                assert_eq!(1, coordinators.len());
+                assert_eq!("abc", coordinators[0].key);
+                assert_eq!(111, coordinators[0].node_id);
+            }
+
+            otherwise => panic!("{otherwise:?}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn find_coordinator_without_coordinator() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    assert!(matches!(
+        FindCoordinatorResponse::builder().api_version(3).build(),
+        Err(Error::MissingField {
+            name: "node_id",
+            api_version: 3
+        })
+    ));
+
+    assert!(FindCoordinatorResponse::builder()
+        .api_version(4)
+        .error(ErrorCode::CoordinatorNotAvailable, None)
+        .build()
+        .is_ok());
+
+    Ok(())
+}
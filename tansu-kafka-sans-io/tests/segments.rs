@@ -0,0 +1,190 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use bytes::{Bytes, BytesMut};
+use std::{fs::File, io::Write, sync::Arc, thread};
+use tansu_kafka_sans_io::{
+    produce_request::{PartitionProduceData, TopicProduceData},
+    record::{deflated, inflated, Record, Records},
+    response::FetchResponse,
+    ApiKey, Body, Error, ErrorCode, Frame, Header, Result,
+};
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[cfg(miri)]
+fn init_tracing() -> Result<()> {
+    Ok(())
+}
+
+#[cfg(not(miri))]
+fn init_tracing() -> Result<DefaultGuard> {
+    Ok(tracing::subscriber::set_default(
+        tracing_subscriber::fmt()
+            .with_level(true)
+            .with_line_number(true)
+            .with_thread_names(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::ACTIVE)
+            .with_writer(
+                thread::current()
+                    .name()
+                    .ok_or(Error::Message(String::from("unnamed thread")))
+                    .and_then(|name| {
+                        File::create(format!(
+                            "../logs/{}/segments-{name}.log",
+                            env!("CARGO_PKG_NAME")
+                        ))
+                        .map_err(Into::into)
+                    })
+                    .map(Arc::new)?,
+            )
+            .finish(),
+    ))
+}
+
+fn records(length: usize) -> Result<Records> {
+    inflated::Batch::builder()
+        .record(Record::builder().value(vec![7; length].into()))
+        .build()
+        .and_then(deflated::Batch::try_from)
+        .and_then(Records::try_from)
+}
+
+fn record_data(records: &Records) -> Bytes {
+    records.as_bytes().clone()
+}
+
+fn fetch_response(records: Records) -> Result<Frame> {
+    FetchResponse::builder()
+        .response("test", |t| {
+            t.topic_id([1; 16])
+                .partition(0, ErrorCode::None, 1, Some(records))
+        })
+        .build()
+        .map(|body| Frame {
+            size: 0,
+            header: Header::Response { correlation_id: 6 },
+            body,
+        })
+}
+
+#[test]
+fn write_response_into() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let frame = fetch_response(records(1_024)?)?;
+
+    for api_version in [4, 12, 16] {
+        let expected = Frame::encode_response(
+            frame.header.clone(),
+            frame.body.clone(),
+            ApiKey::Fetch,
+            api_version,
+        )?;
+
+        let mut buf = BytesMut::from(&b"pqr"[..]);
+        let length = frame.write_response_into(&mut buf, ApiKey::Fetch, api_version)?;
+
+        assert_eq!(expected.len(), length);
+        assert_eq!(&b"pqr"[..], &buf[..3]);
+        assert_eq!(&expected[..], &buf[3..]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn response_segments_reference_record_data() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let records = records(1_024 * 1_024)?;
+    let record_data = record_data(&records);
+    let frame = fetch_response(records)?;
+
+    for api_version in [4, 12, 16] {
+        let expected = Frame::encode_response(
+            frame.header.clone(),
+            frame.body.clone(),
+            ApiKey::Fetch,
+            api_version,
+        )?;
+
+        let mut segments = frame.response_segments(ApiKey::Fetch, api_version)?;
+        assert_eq!(expected.len(), segments.len());
+
+        let mut written = vec![];
+        let length = written.write_vectored(&segments.io_slices())?;
+        assert_eq!(expected.len(), length);
+        assert_eq!(expected, written);
+
+        assert!(segments
+            .into_inner()
+            .iter()
+            .any(|segment| segment.as_ptr() == record_data.as_ptr()
+                && segment.len() == record_data.len()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn request_segments_reference_record_data() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let records = records(64 * 1_024)?;
+    let record_data = record_data(&records);
+
+    let header = Header::Request {
+        api_key: ApiKey::Produce.into(),
+        api_version: 9,
+        correlation_id: 7,
+        client_id: Some("console-producer".into()),
+    };
+
+    let body = Body::ProduceRequest {
+        transactional_id: None,
+        acks: -1,
+        timeout_ms: 1_500,
+        topic_data: Some(vec![TopicProduceData {
+            name: "test".into(),
+            partition_data: Some(vec![PartitionProduceData {
+                index: 0,
+                records: Some(records),
+            }]),
+        }]),
+        unknown_tagged_fields: vec![],
+    };
+
+    let expected = Frame::request(header.clone(), body.clone())?;
+
+    let frame = Frame {
+        size: 0,
+        header,
+        body,
+    };
+
+    let mut buf = BytesMut::new();
+    assert_eq!(expected.len(), frame.write_request_into(&mut buf)?);
+    assert_eq!(&expected[..], &buf[..]);
+
+    let segments = frame.request_segments()?.into_inner();
+    assert_eq!(expected, segments.concat());
+    assert!(segments
+        .iter()
+        .any(|segment| segment.as_ptr() == record_data.as_ptr()));
+
+    Ok(())
+}
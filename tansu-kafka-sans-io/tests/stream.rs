@@ -0,0 +1,201 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![cfg(feature = "tokio")]
+
+use std::{fs::File, io, sync::Arc, thread};
+use tansu_kafka_sans_io::{
+    stream::{write_frame, write_request, write_response, FrameReader},
+    ApiKey, Error, Frame, Result,
+};
+use tokio::io::{duplex, AsyncWriteExt};
+use tracing::subscriber::DefaultGuard;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[cfg(miri)]
+fn init_tracing() -> Result<()> {
+    Ok(())
+}
+
+#[cfg(not(miri))]
+fn init_tracing() -> Result<DefaultGuard> {
+    Ok(tracing::subscriber::set_default(
+        tracing_subscriber::fmt()
+            .with_level(true)
+            .with_line_number(true)
+            .with_thread_names(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::ACTIVE)
+            .with_writer(
+                thread::current()
+                    .name()
+                    .ok_or(Error::Message(String::from("unnamed thread")))
+                    .and_then(|name| {
+                        File::create(format!(
+                            "../logs/{}/stream-{name}.log",
+                            env!("CARGO_PKG_NAME")
+                        ))
+                        .map_err(Into::into)
+                    })
+                    .map(Arc::new)?,
+            )
+            .finish(),
+    ))
+}
+
+const API_VERSIONS_REQUEST_V3: &[u8] = &[
+    0, 0, 0, 52, 0, 18, 0, 3, 0, 0, 0, 3, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112, 114,
+    111, 100, 117, 99, 101, 114, 0, 18, 97, 112, 97, 99, 104, 101, 45, 107, 97, 102, 107, 97, 45,
+    106, 97, 118, 97, 6, 51, 46, 54, 46, 49, 0,
+];
+
+const API_VERSIONS_RESPONSE_V1: &[u8] = &[
+    0, 0, 0, 242, 0, 0, 0, 0, 0, 0, 0, 0, 0, 38, 0, 0, 0, 0, 0, 5, 0, 1, 0, 0, 0, 6, 0, 2, 0, 0, 0,
+    2, 0, 3, 0, 0, 0, 5, 0, 4, 0, 0, 0, 1, 0, 5, 0, 0, 0, 0, 0, 6, 0, 0, 0, 4, 0, 7, 0, 0, 0, 1, 0,
+    8, 0, 0, 0, 3, 0, 9, 0, 0, 0, 3, 0, 10, 0, 0, 0, 1, 0, 11, 0, 0, 0, 2, 0, 12, 0, 0, 0, 1, 0,
+    13, 0, 0, 0, 1, 0, 14, 0, 0, 0, 1, 0, 15, 0, 0, 0, 1, 0, 16, 0, 0, 0, 1, 0, 17, 0, 0, 0, 1, 0,
+    18, 0, 0, 0, 1, 0, 19, 0, 0, 0, 2, 0, 20, 0, 0, 0, 1, 0, 21, 0, 0, 0, 0, 0, 22, 0, 0, 0, 0, 0,
+    23, 0, 0, 0, 0, 0, 24, 0, 0, 0, 0, 0, 25, 0, 0, 0, 0, 0, 26, 0, 0, 0, 0, 0, 27, 0, 0, 0, 0, 0,
+    28, 0, 0, 0, 0, 0, 29, 0, 0, 0, 0, 0, 30, 0, 0, 0, 0, 0, 31, 0, 0, 0, 0, 0, 32, 0, 0, 0, 0, 0,
+    33, 0, 0, 0, 0, 0, 34, 0, 0, 0, 0, 0, 35, 0, 0, 0, 0, 0, 36, 0, 0, 0, 0, 0, 37, 0, 0, 0, 0, 0,
+    0, 0, 0,
+];
+
+#[tokio::test]
+async fn pipelined_requests() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let (mut client, server) = duplex(1_024);
+
+    let mut pipelined = Vec::new();
+    for _ in 0..3 {
+        pipelined.extend_from_slice(API_VERSIONS_REQUEST_V3);
+    }
+    client.write_all(&pipelined).await?;
+    drop(client);
+
+    let expected = Frame::request_from_bytes(API_VERSIONS_REQUEST_V3)?;
+
+    let mut reader = FrameReader::new(server);
+    for _ in 0..3 {
+        assert_eq!(Some(expected.clone()), reader.read_request().await?);
+    }
+
+    assert_eq!(None, reader.read_request().await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn split_request() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let (mut client, server) = duplex(1_024);
+    let mut reader = FrameReader::new(server);
+
+    let writer = tokio::spawn(async move {
+        for chunk in API_VERSIONS_REQUEST_V3.chunks(3) {
+            client.write_all(chunk).await?;
+            client.flush().await?;
+            tokio::task::yield_now().await;
+        }
+
+        Ok::<(), io::Error>(())
+    });
+
+    assert_eq!(
+        Some(Frame::request_from_bytes(API_VERSIONS_REQUEST_V3)?),
+        reader.read_request().await?
+    );
+
+    writer
+        .await
+        .map_err(|join| Error::Message(join.to_string()))??;
+
+    assert_eq!(None, reader.read_request().await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn request_response_round_trip() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let api_key = ApiKey::ApiVersions;
+    let api_version = 1;
+
+    let (mut client, server) = duplex(1_024);
+
+    let request = Frame::request_from_bytes(API_VERSIONS_REQUEST_V3)?;
+    write_request(&mut client, request.header.clone(), request.body.clone()).await?;
+
+    let response = Frame::decode_response(API_VERSIONS_RESPONSE_V1, api_key, api_version)?;
+    write_response(
+        &mut client,
+        response.header.clone(),
+        response.body.clone(),
+        api_key.into(),
+        api_version,
+    )
+    .await?;
+    drop(client);
+
+    let mut reader = FrameReader::new(server);
+    assert_eq!(Some(request), reader.read_request().await?);
+    assert_eq!(
+        Some(response),
+        reader.read_response(api_key.into(), api_version).await?
+    );
+    assert_eq!(None, reader.read_frame().await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn eof_within_frame() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let (mut client, server) = duplex(1_024);
+    write_frame(&mut client, &API_VERSIONS_REQUEST_V3[..20]).await?;
+    drop(client);
+
+    assert!(matches!(
+        FrameReader::new(server).read_frame().await,
+        Err(Error::Io(ref error)) if error.kind() == io::ErrorKind::UnexpectedEof
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn frame_too_large() -> Result<()> {
+    let _guard = init_tracing()?;
+
+    let (mut client, server) = duplex(1_024);
+    client.write_all(&[127, 255, 255, 255]).await?;
+
+    assert!(matches!(
+        FrameReader::new(server)
+            .max_frame_length(1_024)
+            .read_frame()
+            .await,
+        Err(Error::FrameTooLarge {
+            length: 2_147_483_651,
+            maximum: 1_024
+        })
+    ));
+
+    Ok(())
+}
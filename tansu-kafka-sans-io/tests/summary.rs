@@ -0,0 +1,165 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use tansu_kafka_sans_io::{
+    metadata_request::MetadataRequestTopic, ApiKey, Body, Frame, Header, Result,
+};
+
+#[test]
+fn fetch_request_v12_000() -> Result<()> {
+    let encoded = vec![
+        0, 0, 0, 162, 0, 1, 0, 12, 0, 0, 0, 8, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 99,
+        111, 110, 115, 117, 109, 101, 114, 0, 255, 255, 255, 255, 0, 0, 1, 244, 0, 0, 0, 1, 3, 32,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 5, 116, 101, 115, 116, 4, 0, 0, 0, 1, 255, 255, 255,
+        255, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0,
+        16, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255,
+        255, 255, 255, 255, 255, 255, 255, 255, 0, 16, 0, 0, 0, 0, 0, 0, 2, 255, 255, 255, 255, 0,
+        0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 0, 16, 0,
+        0, 0, 0, 1, 1, 0,
+    ];
+
+    let frame = Frame::request_from_bytes(&encoded)?;
+
+    assert_eq!(
+        "FetchRequest v12 correlation=8 client=console-consumer session=0 epoch=0 \
+         topics=[test[1@0, 0@0, 2@0]]",
+        frame.to_string()
+    );
+
+    assert_eq!(
+        "Fetch v12 correlation=8 client=console-consumer",
+        frame.header.to_string()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn join_group_request_v5_000() -> Result<()> {
+    let encoded = vec![
+        0, 0, 0, 159, 0, 11, 0, 5, 0, 0, 0, 3, 0, 7, 114, 100, 107, 97, 102, 107, 97, 0, 25, 101,
+        120, 97, 109, 112, 108, 101, 95, 99, 111, 110, 115, 117, 109, 101, 114, 95, 103, 114, 111,
+        117, 112, 95, 105, 100, 0, 0, 23, 112, 0, 4, 147, 224, 0, 0, 255, 255, 0, 8, 99, 111, 110,
+        115, 117, 109, 101, 114, 0, 0, 0, 2, 0, 5, 114, 97, 110, 103, 101, 0, 0, 0, 31, 0, 3, 0, 0,
+        0, 1, 0, 9, 98, 101, 110, 99, 104, 109, 97, 114, 107, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255,
+        255, 255, 0, 0, 0, 10, 114, 111, 117, 110, 100, 114, 111, 98, 105, 110, 0, 0, 0, 31, 0, 3,
+        0, 0, 0, 1, 0, 9, 98, 101, 110, 99, 104, 109, 97, 114, 107, 0, 0, 0, 0, 0, 0, 0, 0, 255,
+        255, 255, 255, 0, 0,
+    ];
+
+    assert_eq!(
+        "JoinGroupRequest v5 correlation=3 client=rdkafka group=example_consumer_group_id \
+         protocol_type=consumer protocols=[range, roundrobin]",
+        Frame::request_from_bytes(&encoded)?.to_string()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn produce_request_v9_000() -> Result<()> {
+    let encoded = vec![
+        0, 0, 0, 120, 0, 0, 0, 9, 0, 0, 0, 6, 0, 16, 99, 111, 110, 115, 111, 108, 101, 45, 112,
+        114, 111, 100, 117, 99, 101, 114, 0, 0, 255, 255, 0, 0, 5, 220, 2, 5, 116, 101, 115, 116,
+        2, 0, 0, 0, 0, 72, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 59, 255, 255, 255, 255, 2, 67, 41, 231,
+        61, 0, 0, 0, 0, 0, 0, 0, 0, 1, 141, 116, 152, 137, 53, 0, 0, 1, 141, 116, 152, 137, 53, 0,
+        0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 18, 0, 0, 0, 1, 6, 100, 101, 102, 0, 0,
+        0, 0,
+    ];
+
+    assert_eq!(
+        "ProduceRequest v9 correlation=6 client=console-producer acks=-1 topics=[test[0 71B]]",
+        Frame::request_from_bytes(&encoded)?.to_string()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn produce_response_v9_000() -> Result<()> {
+    let encoded = vec![
+        0, 0, 0, 51, 0, 0, 0, 6, 0, 2, 5, 116, 101, 115, 116, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 2, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0,
+        0, 0, 0,
+    ];
+
+    let frame = Frame::decode_response(&encoded, ApiKey::Produce, 9)?;
+
+    assert_eq!(
+        "ProduceResponse correlation=6 topics=[test[0@2]]",
+        frame.to_string()
+    );
+
+    assert_eq!("correlation=6", frame.header.to_string());
+
+    Ok(())
+}
+
+#[test]
+fn long_lists_are_counted() {
+    let body = Body::MetadataRequest {
+        topics: Some(
+            (0..10)
+                .map(|i| MetadataRequestTopic {
+                    topic_id: None,
+                    name: Some(format!("t{i}")),
+                })
+                .collect(),
+        ),
+        allow_auto_topic_creation: Some(false),
+        include_cluster_authorized_operations: None,
+        include_topic_authorized_operations: Some(false),
+        unknown_tagged_fields: vec![],
+    };
+
+    assert_eq!(
+        "MetadataRequest topics=[t0, t1, t2, t3, t4, t5, t6, t7, ... +2]",
+        body.to_string()
+    );
+}
+
+#[test]
+fn all_topics() {
+    let body = Body::MetadataRequest {
+        topics: None,
+        allow_auto_topic_creation: Some(false),
+        include_cluster_authorized_operations: None,
+        include_topic_authorized_operations: Some(false),
+        unknown_tagged_fields: vec![],
+    };
+
+    assert_eq!("MetadataRequest topics=*", body.to_string());
+}
+
+#[test]
+fn name_only() {
+    let frame = Frame {
+        size: 0,
+        header: Header::Request {
+            api_key: 18,
+            api_version: 3,
+            correlation_id: 0,
+            client_id: None,
+        },
+        body: Body::ApiVersionsRequest {
+            client_software_name: Some("tansu".into()),
+            client_software_version: Some("0.0.1".into()),
+            unknown_tagged_fields: vec![],
+        },
+    };
+
+    assert_eq!("ApiVersionsRequest v3 correlation=0", frame.to_string());
+    assert_eq!("ApiVersions v3 correlation=0", frame.header.to_string());
+}
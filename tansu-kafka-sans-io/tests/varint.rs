@@ -0,0 +1,102 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+#![cfg(feature = "proptest")]
+
+use proptest::{collection::vec, prelude::*};
+use serde::Serialize;
+use std::io::Cursor;
+use tansu_kafka_sans_io::{
+    primitive::varint::{
+        get_unsigned_varint, get_unsigned_varlong, get_varint, get_varlong, put_unsigned_varint,
+        put_unsigned_varlong, read_unsigned_varint, read_unsigned_varlong, write_unsigned_varint,
+        write_unsigned_varlong, LongVarInt, VarInt, MAX_VARINT_BYTES, MAX_VARLONG_BYTES,
+    },
+    ser::Encoder,
+    Error,
+};
+
+fn serialize(value: &impl Serialize) -> Vec<u8> {
+    let mut c = Cursor::new(vec![]);
+    let mut e = Encoder::new(&mut c);
+    value.serialize(&mut e).expect("serialize");
+    c.into_inner()
+}
+
+proptest! {
+    #[test]
+    fn unsigned_varint(decoded: u32) {
+        let mut encoded = Vec::new();
+        put_unsigned_varint(&mut encoded, decoded);
+        prop_assert!(encoded.len() <= MAX_VARINT_BYTES);
+
+        let mut written = Vec::new();
+        write_unsigned_varint(&mut written, decoded).expect("write");
+        prop_assert_eq!(&encoded, &written);
+
+        prop_assert_eq!(decoded, get_unsigned_varint(&mut &encoded[..]).expect("get"));
+        prop_assert_eq!(decoded, read_unsigned_varint(&mut &encoded[..]).expect("read"));
+    }
+
+    #[test]
+    fn unsigned_varlong(decoded: u64) {
+        let mut encoded = Vec::new();
+        put_unsigned_varlong(&mut encoded, decoded);
+        prop_assert!(encoded.len() <= MAX_VARLONG_BYTES);
+
+        let mut written = Vec::new();
+        write_unsigned_varlong(&mut written, decoded).expect("write");
+        prop_assert_eq!(&encoded, &written);
+
+        prop_assert_eq!(decoded, get_unsigned_varlong(&mut &encoded[..]).expect("get"));
+        prop_assert_eq!(decoded, read_unsigned_varlong(&mut &encoded[..]).expect("read"));
+    }
+
+    #[test]
+    fn varint(decoded: i32) {
+        let encoded = serialize(&VarInt::from(decoded));
+        prop_assert_eq!(decoded, get_varint(&mut &encoded[..]).expect("get"));
+    }
+
+    #[test]
+    fn varlong(decoded: i64) {
+        let encoded = serialize(&LongVarInt::from(decoded));
+        prop_assert_eq!(decoded, get_varlong(&mut &encoded[..]).expect("get"));
+    }
+
+    #[test]
+    fn any_bytes(encoded in vec(any::<u8>(), 0..16)) {
+        // arbitrary input is either decoded within the maximum length or rejected
+        let mut buf = &encoded[..];
+
+        match get_unsigned_varint(&mut buf) {
+            Ok(_) => prop_assert!(encoded.len() - buf.len() <= MAX_VARINT_BYTES),
+            Err(error) => prop_assert!(
+                matches!(error, Error::InvalidVarint) || encoded.len() <= MAX_VARINT_BYTES,
+                "{error:?}"
+            ),
+        }
+
+        let mut buf = &encoded[..];
+
+        match get_unsigned_varlong(&mut buf) {
+            Ok(_) => prop_assert!(encoded.len() - buf.len() <= MAX_VARLONG_BYTES),
+            Err(error) => prop_assert!(
+                matches!(error, Error::InvalidVarint) || encoded.len() <= MAX_VARLONG_BYTES,
+                "{error:?}"
+            ),
+        }
+    }
+}
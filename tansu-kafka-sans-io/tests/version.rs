@@ -22,7 +22,7 @@ use tansu_kafka_sans_io::{
         OffsetFetchResponseGroup, OffsetFetchResponsePartition, OffsetFetchResponsePartitions,
         OffsetFetchResponseTopic, OffsetFetchResponseTopics,
     },
-    Body, Error, ErrorCode, Frame, Header, Result,
+    ApiKey, Body, Error, ErrorCode, Frame, Header, Result,
 };
 use tracing::{debug, subscriber::DefaultGuard};
 use tracing_subscriber::fmt::format::FmtSpan;
@@ -95,16 +95,17 @@ fn join_group_response() -> Result<()> {
         skip_assignment,
         member_id: member_id.clone(),
         members: members.clone(),
+        unknown_tagged_fields: vec![],
     };
 
-    let api_key = 11;
+    let api_key = ApiKey::JoinGroup;
     let api_version = 5;
 
-    let encoded = Frame::response(header, body, api_key, api_version)?;
+    let encoded = Frame::encode_response(header, body, api_key, api_version)?;
 
     debug!(?encoded);
 
-    match Frame::response_from_bytes(&encoded, api_key, api_version) {
+    match Frame::decode_response(&encoded, api_key, api_version) {
         Ok(Frame {
             header: Header::Response { correlation_id },
             body:
@@ -118,6 +119,7 @@ fn join_group_response() -> Result<()> {
                     skip_assignment: encoded_skip_assignment,
                     member_id: encoded_member_id,
                     members: encoded_members,
+                    ..
                 },
             ..
         }) => {
@@ -193,14 +195,15 @@ fn offset_fetch_response() -> Result<()> {
         error_code: encoded_error_code,
         groups: encoded_groups.clone(),
         topics: encoded_topics.clone(),
+        unknown_tagged_fields: vec![],
     };
 
-    let api_key = 9;
+    let api_key = ApiKey::OffsetFetch;
     let api_version = 7;
 
-    let encoded = Frame::response(header, body, api_key, api_version)?;
+    let encoded = Frame::encode_response(header, body, api_key, api_version)?;
 
-    match Frame::response_from_bytes(&encoded, api_key, api_version) {
+    match Frame::decode_response(&encoded, api_key, api_version) {
         Ok(Frame {
             header: Header::Response { correlation_id },
             body:
@@ -209,6 +212,7 @@ fn offset_fetch_response() -> Result<()> {
                     topics,
                     error_code,
                     groups,
+                    ..
                 },
             ..
         }) => {
@@ -20,7 +20,7 @@ use std::{
     result,
     sync::Arc,
 };
-use tansu_kafka_sans_io::{Frame, Header};
+use tansu_kafka_sans_io::{ApiKey, Frame, Header};
 use thiserror::Error;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
@@ -159,9 +159,10 @@ impl Connection {
                 },
             ) = Frame::request_from_bytes(&request_buffer)
             {
-                debug!(?self.addr, ?request);
+                let api_key = ApiKey::try_from(api_key)?;
+                debug!(?self.addr, api = %api_key, v = api_version, ?request);
 
-                let response = Frame::response_from_bytes(&response_buffer, api_key, api_version)?;
+                let response = Frame::decode_response(&response_buffer, api_key, api_version)?;
                 debug!(?self.addr, ?response);
             }
         }
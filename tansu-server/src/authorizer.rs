@@ -0,0 +1,497 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Authorization of a principal performing an operation on a resource.
+
+use async_trait::async_trait;
+use std::{collections::BTreeSet, fmt::Debug};
+use tansu_kafka_sans_io::{AclOperation, AclPermissionType, PatternType, ResourceType};
+use tansu_storage::{AclBinding, Storage};
+use tracing::{debug, error};
+
+pub const ANONYMOUS: &str = "User:ANONYMOUS";
+pub const WILDCARD: &str = "*";
+pub const WILDCARD_PRINCIPAL: &str = "User:*";
+pub const CLUSTER: &str = "kafka-cluster";
+
+/// The authorized operations of a describe response that didn't ask for them.
+pub const AUTHORIZED_OPERATIONS_OMITTED: i32 = i32::MIN;
+
+/// The principal making a request, with the host it is connecting from.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Principal {
+    name: String,
+    host: String,
+}
+
+impl Principal {
+    /// An authenticated user, or anonymous when the connection has not authenticated.
+    pub fn new(user: Option<&str>, host: Option<&str>) -> Self {
+        Self {
+            name: user.map_or(ANONYMOUS.into(), |user| format!("User:{user}")),
+            host: host.unwrap_or_default().into(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub fn host(&self) -> &str {
+        self.host.as_str()
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Resource {
+    resource_type: ResourceType,
+    name: String,
+}
+
+impl Resource {
+    pub fn new(resource_type: ResourceType, name: &str) -> Self {
+        Self {
+            resource_type,
+            name: name.into(),
+        }
+    }
+
+    pub fn topic(name: &str) -> Self {
+        Self::new(ResourceType::Topic, name)
+    }
+
+    pub fn group(name: &str) -> Self {
+        Self::new(ResourceType::Group, name)
+    }
+
+    pub fn transactional_id(name: &str) -> Self {
+        Self::new(ResourceType::TransactionalId, name)
+    }
+
+    pub fn cluster() -> Self {
+        Self::new(ResourceType::Cluster, CLUSTER)
+    }
+
+    pub fn resource_type(&self) -> ResourceType {
+        self.resource_type
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+#[async_trait]
+pub trait Authorizer: Debug + Send + Sync {
+    async fn authorize(
+        &self,
+        principal: &Principal,
+        operation: AclOperation,
+        resource: &Resource,
+    ) -> bool;
+}
+
+/// Permits every operation, the behaviour without an authorizer.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AllowAll;
+
+#[async_trait]
+impl Authorizer for AllowAll {
+    async fn authorize(
+        &self,
+        _principal: &Principal,
+        _operation: AclOperation,
+        _resource: &Resource,
+    ) -> bool {
+        true
+    }
+}
+
+/// Authorizes using the ACL bindings held in storage, maintained by
+/// CreateAcls and DeleteAcls. Without a matching allow binding an
+/// operation is denied, unless the principal is a super user.
+#[derive(Clone, Debug)]
+pub struct AclAuthorizer<S> {
+    storage: S,
+    super_users: BTreeSet<String>,
+}
+
+impl<S> AclAuthorizer<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self {
+            storage,
+            super_users: BTreeSet::new(),
+        }
+    }
+
+    /// Principals, such as "User:admin", that are permitted every operation.
+    pub fn with_super_users<I, T>(self, super_users: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        Self {
+            super_users: super_users.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+}
+
+#[async_trait]
+impl<S> Authorizer for AclAuthorizer<S>
+where
+    S: Storage,
+{
+    async fn authorize(
+        &self,
+        principal: &Principal,
+        operation: AclOperation,
+        resource: &Resource,
+    ) -> bool {
+        if self.super_users.contains(principal.name()) {
+            return true;
+        }
+
+        match self.storage.clone().acls().await {
+            Ok(acls) => {
+                let permitted = is_permitted(&acls, principal, operation, resource);
+                debug!(?principal, ?operation, ?resource, permitted);
+                permitted
+            }
+
+            Err(error) => {
+                error!(?principal, ?operation, ?resource, ?error);
+                false
+            }
+        }
+    }
+}
+
+fn applies(binding: &AclBinding, principal: &Principal, resource: &Resource) -> bool {
+    ResourceType::try_from(binding.resource_type)
+        .is_ok_and(|resource_type| resource_type == resource.resource_type)
+        && match PatternType::try_from(binding.pattern_type) {
+            Ok(PatternType::Literal) => {
+                binding.resource_name == WILDCARD || binding.resource_name == resource.name
+            }
+            Ok(PatternType::Prefixed) => resource.name.starts_with(&binding.resource_name),
+            _ => false,
+        }
+        && (binding.principal == WILDCARD_PRINCIPAL || binding.principal == principal.name)
+        && (binding.host == WILDCARD || binding.host == principal.host)
+}
+
+// an allow of any of read, write, delete or alter implies describe, and
+// alter configs implies describe configs
+fn implies(granted: AclOperation, operation: AclOperation) -> bool {
+    granted == operation
+        || granted == AclOperation::All
+        || (operation == AclOperation::Describe
+            && matches!(
+                granted,
+                AclOperation::Read
+                    | AclOperation::Write
+                    | AclOperation::Delete
+                    | AclOperation::Alter
+            ))
+        || (operation == AclOperation::DescribeConfigs && granted == AclOperation::AlterConfigs)
+}
+
+/// Whether the bindings permit the principal to perform the operation on the
+/// resource: a matching deny takes precedence over any allow.
+pub fn is_permitted(
+    acls: &[AclBinding],
+    principal: &Principal,
+    operation: AclOperation,
+    resource: &Resource,
+) -> bool {
+    let applicable = || {
+        acls.iter()
+            .filter(|binding| applies(binding, principal, resource))
+    };
+
+    let has = |permission_type: AclPermissionType,
+               implied: fn(AclOperation, AclOperation) -> bool| {
+        applicable().any(|binding| {
+            AclPermissionType::try_from(binding.permission_type)
+                .is_ok_and(|permission| permission == permission_type)
+                && AclOperation::try_from(binding.operation)
+                    .is_ok_and(|granted| implied(granted, operation))
+        })
+    };
+
+    !has(AclPermissionType::Deny, |denied, requested| {
+        denied == requested || denied == AclOperation::All
+    }) && has(AclPermissionType::Allow, implies)
+}
+
+/// The operations on a resource that the principal is authorized to
+/// perform, as the bit field of a describe response, with each operation
+/// being the bit of its ACL operation code.
+pub async fn authorized_operations(
+    authorizer: &dyn Authorizer,
+    principal: &Principal,
+    resource: &Resource,
+    operations: &[AclOperation],
+) -> i32 {
+    let mut authorized = 0;
+
+    for operation in operations {
+        if authorizer.authorize(principal, *operation, resource).await {
+            authorized |= 1 << i8::from(*operation);
+        }
+    }
+
+    authorized
+}
+
+/// A filter over ACL bindings, as used by DescribeAcls and DeleteAcls.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AclFilter {
+    pub resource_type: i8,
+    pub resource_name: Option<String>,
+    pub pattern_type: i8,
+    pub principal: Option<String>,
+    pub host: Option<String>,
+    pub operation: i8,
+    pub permission_type: i8,
+}
+
+impl AclFilter {
+    pub fn matches(&self, binding: &AclBinding) -> bool {
+        (self.resource_type == i8::from(ResourceType::Any)
+            || self.resource_type == binding.resource_type)
+            && self.matches_pattern(binding)
+            && self
+                .principal
+                .as_ref()
+                .is_none_or(|principal| principal == &binding.principal)
+            && self.host.as_ref().is_none_or(|host| host == &binding.host)
+            && (self.operation == i8::from(AclOperation::Any)
+                || self.operation == binding.operation)
+            && (self.permission_type == i8::from(AclPermissionType::Any)
+                || self.permission_type == binding.permission_type)
+    }
+
+    fn matches_pattern(&self, binding: &AclBinding) -> bool {
+        match PatternType::try_from(self.pattern_type) {
+            Ok(PatternType::Any) => self
+                .resource_name
+                .as_ref()
+                .is_none_or(|name| name == &binding.resource_name),
+
+            // the bindings that would apply to a resource with this name
+            Ok(PatternType::Match) => self.resource_name.as_ref().is_none_or(|name| {
+                match PatternType::try_from(binding.pattern_type) {
+                    Ok(PatternType::Literal) => {
+                        binding.resource_name == *name || binding.resource_name == WILDCARD
+                    }
+                    Ok(PatternType::Prefixed) => name.starts_with(&binding.resource_name),
+                    _ => false,
+                }
+            }),
+
+            Ok(pattern_type @ (PatternType::Literal | PatternType::Prefixed)) => {
+                i8::from(pattern_type) == binding.pattern_type
+                    && self
+                        .resource_name
+                        .as_ref()
+                        .is_none_or(|name| name == &binding.resource_name)
+            }
+
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+    use tansu_storage::dynostore::DynoStore;
+
+    fn binding(
+        resource: &Resource,
+        pattern_type: PatternType,
+        principal: &str,
+        operation: AclOperation,
+        permission_type: AclPermissionType,
+    ) -> AclBinding {
+        AclBinding {
+            resource_type: resource.resource_type().into(),
+            resource_name: resource.name().into(),
+            pattern_type: pattern_type.into(),
+            principal: principal.into(),
+            host: WILDCARD.into(),
+            operation: operation.into(),
+            permission_type: permission_type.into(),
+        }
+    }
+
+    #[test]
+    fn implied_describe() {
+        let alice = Principal::new(Some("alice"), Some("127.0.0.1"));
+        let topic = Resource::topic("orders");
+
+        let acls = [binding(
+            &topic,
+            PatternType::Literal,
+            "User:alice",
+            AclOperation::Read,
+            AclPermissionType::Allow,
+        )];
+
+        assert!(is_permitted(&acls, &alice, AclOperation::Read, &topic));
+        assert!(is_permitted(&acls, &alice, AclOperation::Describe, &topic));
+        assert!(!is_permitted(&acls, &alice, AclOperation::Write, &topic));
+        assert!(!is_permitted(
+            &acls,
+            &alice,
+            AclOperation::Read,
+            &Resource::topic("payments")
+        ));
+
+        let bob = Principal::new(Some("bob"), Some("127.0.0.1"));
+        assert!(!is_permitted(&acls, &bob, AclOperation::Read, &topic));
+    }
+
+    #[test]
+    fn deny_takes_precedence() {
+        let alice = Principal::new(Some("alice"), None);
+
+        let acls = [
+            binding(
+                &Resource::topic("order"),
+                PatternType::Prefixed,
+                WILDCARD_PRINCIPAL,
+                AclOperation::All,
+                AclPermissionType::Allow,
+            ),
+            binding(
+                &Resource::topic("orders-audit"),
+                PatternType::Literal,
+                "User:alice",
+                AclOperation::Write,
+                AclPermissionType::Deny,
+            ),
+        ];
+
+        assert!(is_permitted(
+            &acls,
+            &alice,
+            AclOperation::Write,
+            &Resource::topic("orders")
+        ));
+
+        assert!(!is_permitted(
+            &acls,
+            &alice,
+            AclOperation::Write,
+            &Resource::topic("orders-audit")
+        ));
+
+        assert!(is_permitted(
+            &acls,
+            &alice,
+            AclOperation::Read,
+            &Resource::topic("orders-audit")
+        ));
+    }
+
+    #[test]
+    fn filter() {
+        let acl = binding(
+            &Resource::topic("order"),
+            PatternType::Prefixed,
+            "User:alice",
+            AclOperation::Read,
+            AclPermissionType::Allow,
+        );
+
+        let any = AclFilter {
+            resource_type: ResourceType::Any.into(),
+            pattern_type: PatternType::Any.into(),
+            operation: AclOperation::Any.into(),
+            permission_type: AclPermissionType::Any.into(),
+            ..Default::default()
+        };
+        assert!(any.matches(&acl));
+
+        assert!(AclFilter {
+            resource_name: Some("orders".into()),
+            pattern_type: PatternType::Match.into(),
+            ..any.clone()
+        }
+        .matches(&acl));
+
+        assert!(!AclFilter {
+            resource_name: Some("orders".into()),
+            pattern_type: PatternType::Literal.into(),
+            ..any.clone()
+        }
+        .matches(&acl));
+
+        assert!(!AclFilter {
+            principal: Some("User:bob".into()),
+            ..any
+        }
+        .matches(&acl));
+    }
+
+    #[tokio::test]
+    async fn acl_authorizer() -> tansu_storage::Result<()> {
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        let authorizer =
+            AclAuthorizer::with_storage(storage.clone()).with_super_users(["User:admin"]);
+
+        let alice = Principal::new(Some("alice"), None);
+        let group = Resource::group("abc");
+
+        assert!(
+            !authorizer
+                .authorize(&alice, AclOperation::Read, &group)
+                .await
+        );
+
+        storage
+            .create_acls(&[binding(
+                &group,
+                PatternType::Literal,
+                "User:alice",
+                AclOperation::Read,
+                AclPermissionType::Allow,
+            )])
+            .await?;
+
+        assert!(
+            authorizer
+                .authorize(&alice, AclOperation::Read, &group)
+                .await
+        );
+
+        let admin = Principal::new(Some("admin"), None);
+        assert!(
+            authorizer
+                .authorize(&admin, AclOperation::Alter, &Resource::cluster())
+                .await
+        );
+
+        Ok(())
+    }
+}
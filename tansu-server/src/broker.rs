@@ -13,48 +13,88 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+pub mod alter_client_quotas;
+pub mod alter_partition_reassignments;
+pub mod alter_user_scram_credentials;
 pub mod api_versions;
+pub mod authorize;
+pub mod broker_heartbeat;
+pub mod create_acls;
+pub mod create_partitions;
 pub mod create_topic;
+pub mod delete_acls;
 pub mod delete_records;
 pub mod delete_topics;
+pub mod describe_acls;
+pub mod describe_client_quotas;
 pub mod describe_cluster;
 pub mod describe_configs;
+pub mod describe_producers;
+pub mod describe_transactions;
+pub mod describe_user_scram_credentials;
+pub mod elect_leaders;
+pub mod error_response;
 pub mod fetch;
 pub mod find_coordinator;
 pub mod group;
+pub mod handler;
 pub mod init_producer_id;
 pub mod list_offsets;
 pub mod list_partition_reassignments;
+pub mod list_transactions;
+pub mod listener;
 pub mod metadata;
+pub mod notify;
+pub mod offset_for_leader_epoch;
+pub mod pipeline;
 pub mod produce;
+pub mod quota;
+pub mod registry;
+pub mod sasl;
 pub mod telemetry;
 pub mod txn;
-
-use crate::{coordinator::group::Coordinator, Error, Result};
+pub mod unregister_broker;
+
+use crate::{
+    authorizer::{AllowAll, Authorizer, Principal},
+    coordinator::{
+        group::Coordinator,
+        liveness::{Liveness, BROKER_HEARTBEAT_INTERVAL},
+    },
+    metrics, Error, Result,
+};
 use api_versions::ApiVersionsRequest;
-use create_topic::CreateTopic;
-use delete_records::DeleteRecordsRequest;
-use delete_topics::DeleteTopicsRequest;
-use describe_cluster::DescribeClusterRequest;
-use describe_configs::DescribeConfigsRequest;
-use fetch::FetchRequest;
-use find_coordinator::FindCoordinatorRequest;
-use init_producer_id::InitProducerIdRequest;
-use list_offsets::ListOffsetsRequest;
-use list_partition_reassignments::ListPartitionReassignmentsRequest;
-use metadata::MetadataRequest;
-use produce::ProduceRequest;
-use std::io::ErrorKind;
-use tansu_kafka_sans_io::{broker_registration_request::Listener, Body, Frame, Header};
+use error_response::{error_response, ErrorResponse};
+use fetch::{
+    replica::{LeaderSelector, ReplicaSelector},
+    session::Sessions,
+    DEFAULT_FETCH_MAX_BYTES,
+};
+use handler::ConnectionContext;
+use listener::ListenerConfig;
+use notify::Notifications;
+use pipeline::{is_read_only, Pipeline, DEFAULT_MAX_IN_FLIGHT};
+use quota::Quotas;
+use registry::Registry;
+use sasl::{Authentication, Credentials};
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tansu_kafka_sans_io::{ApiKey, Body, ErrorCode, Frame, Header, DEFAULT_MAX_FRAME_BYTES};
 use tansu_storage::{BrokerRegistationRequest, Storage};
-use telemetry::GetTelemetrySubscriptionsRequest;
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::{TcpListener, TcpStream},
+    io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc,
+    task::JoinSet,
+    time::sleep,
 };
-use tracing::{debug, error, info};
-use txn::{add_offsets::AddOffsets, add_partitions::AddPartitions};
-use url::Url;
+use tokio_rustls::{rustls::ServerConfig, TlsAcceptor};
+use tracing::{debug, debug_span, error, field, info, warn, Instrument, Span};
 use uuid::Uuid;
 
 #[derive(Clone, Debug)]
@@ -62,12 +102,26 @@ pub struct Broker<G, S> {
     node_id: i32,
     cluster_id: String,
     incarnation_id: Uuid,
-    listener: Url,
-    advertised_listener: Url,
-    #[allow(dead_code)]
+    listeners: Vec<ListenerConfig>,
+    listener: Option<ListenerConfig>,
+    tls: Option<Arc<ServerConfig>>,
     rack: Option<String>,
     storage: S,
     groups: G,
+    notifications: Notifications,
+    fetch_sessions: Arc<Mutex<Sessions>>,
+    max_in_flight: usize,
+    client_host: Option<String>,
+    peer: Option<SocketAddr>,
+    authentication: Authentication,
+    authorizer: Arc<dyn Authorizer>,
+    replica_selector: Arc<dyn ReplicaSelector>,
+    quotas: Quotas,
+    registry: Arc<Registry<G, S>>,
+    topic_defaults: BTreeMap<String, String>,
+    strict_correlation: bool,
+    recompression: bool,
+    fetch_max_bytes: u32,
 }
 
 impl<G, S> Broker<G, S>
@@ -79,8 +133,7 @@ where
     pub fn new(
         node_id: i32,
         cluster_id: &str,
-        listener: Url,
-        advertised_listener: Url,
+        listeners: Vec<ListenerConfig>,
         rack: Option<String>,
         storage: S,
         groups: G,
@@ -91,61 +144,236 @@ where
             node_id,
             cluster_id: cluster_id.to_owned(),
             incarnation_id,
-            listener,
-            advertised_listener,
+            listeners,
+            listener: None,
+            tls: None,
             rack,
             storage,
             groups,
+            notifications: Notifications::new(),
+            fetch_sessions: Arc::new(Mutex::new(Sessions::default())),
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            client_host: None,
+            peer: None,
+            authentication: Authentication::default(),
+            authorizer: Arc::new(AllowAll),
+            replica_selector: Arc::new(LeaderSelector),
+            quotas: Quotas::default(),
+            registry: Arc::new(Registry::default()),
+            topic_defaults: BTreeMap::new(),
+            strict_correlation: false,
+            recompression: true,
+            fetch_max_bytes: DEFAULT_FETCH_MAX_BYTES,
+        }
+    }
+
+    /// Require connections to authenticate with SASL/PLAIN using these credentials.
+    pub fn with_credentials(self, credentials: Credentials) -> Self {
+        Self {
+            authentication: self.authentication.with_credentials(credentials),
+            ..self
+        }
+    }
+
+    /// Require connections to authenticate with SASL/SCRAM, using the
+    /// credentials maintained by AlterUserScramCredentials.
+    pub fn with_scram(self) -> Self {
+        Self {
+            authentication: self.authentication.with_scram(),
+            ..self
+        }
+    }
+
+    /// Encrypt connections to SSL and SASL_SSL listeners with this configuration.
+    pub fn with_tls(self, tls: Arc<ServerConfig>) -> Self {
+        Self {
+            tls: Some(tls),
+            ..self
         }
     }
 
+    /// Authorize every request with this authorizer, rather than allowing all.
+    pub fn with_authorizer(self, authorizer: impl Authorizer + 'static) -> Self {
+        Self {
+            authorizer: Arc::new(authorizer),
+            ..self
+        }
+    }
+
+    /// Steer consumers that give their rack to the replica chosen by this
+    /// selector, rather than always fetching from this broker.
+    pub fn with_replica_selector(self, replica_selector: impl ReplicaSelector + 'static) -> Self {
+        Self {
+            replica_selector: Arc::new(replica_selector),
+            ..self
+        }
+    }
+
+    /// Throttle clients that exceed these quotas, which are shared by every
+    /// connection and may be changed with AlterClientQuotas.
+    pub fn with_quotas(self, quotas: Quotas) -> Self {
+        Self { quotas, ..self }
+    }
+
+    /// Configure topics created by clients with these defaults, unless the
+    /// client gives its own.
+    pub fn with_topic_defaults(self, topic_defaults: BTreeMap<String, String>) -> Self {
+        Self {
+            topic_defaults,
+            ..self
+        }
+    }
+
+    /// Answer a request that reuses the correlation id of an earlier request
+    /// on its connection with INVALID_REQUEST, rather than only logging it.
+    pub fn with_strict_correlation(self) -> Self {
+        Self {
+            strict_correlation: true,
+            ..self
+        }
+    }
+
+    /// Store produced batches with the codec chosen by the producer, rather
+    /// than recompressing them with the compression.type of their topic.
+    pub fn without_recompression(self) -> Self {
+        Self {
+            recompression: false,
+            ..self
+        }
+    }
+
+    /// Limit the response to a fetch to this many bytes, whatever the max
+    /// bytes of the request.
+    pub fn with_fetch_max_bytes(self, fetch_max_bytes: u32) -> Self {
+        Self {
+            fetch_max_bytes,
+            ..self
+        }
+    }
+
+    /// Answer requests with the handlers of this registry, which may
+    /// replace or add to those of the broker.
+    pub fn with_registry(self, registry: Registry<G, S>) -> Self {
+        Self {
+            registry: Arc::new(registry),
+            ..self
+        }
+    }
+
+    /// Handle up to this many requests pipelined on a connection concurrently,
+    /// with responses written in request order.
+    pub fn with_max_in_flight(self, max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight: max_in_flight.max(1),
+            ..self
+        }
+    }
+
+    /// The authenticated principal of this connection, if any.
+    pub fn principal(&self) -> Option<&str> {
+        self.authentication.principal()
+    }
+
     pub async fn serve(&mut self) -> Result<()> {
-        self.register().await?;
-        self.listen().await
+        let broker_epoch = self.register().await?;
+
+        let liveness = Liveness::with_storage(self.storage.clone());
+
+        // stop serving once this registration has been replaced
+        tokio::select! {
+            heartbeat = liveness.heartbeat(self.node_id, broker_epoch, BROKER_HEARTBEAT_INTERVAL) => heartbeat,
+            listen = self.listen() => listen,
+        }
     }
 
-    pub async fn register(&mut self) -> Result<()> {
+    /// Register this broker, returning its epoch.
+    pub async fn register(&mut self) -> Result<i64> {
         self.storage
             .register_broker(BrokerRegistationRequest {
                 broker_id: self.node_id,
                 cluster_id: self.cluster_id.clone(),
                 incarnation_id: self.incarnation_id,
-                listeners: [Listener {
-                    name: "broker".into(),
-                    host: self
-                        .advertised_listener
-                        .host_str()
-                        .unwrap_or("localhost")
-                        .to_owned(),
-                    port: self.advertised_listener.port().unwrap_or(9092),
-                    security_protocol: 0,
-                }]
-                .into(),
+                listeners: self
+                    .listeners
+                    .iter()
+                    .map(|listener| listener.advertised.clone())
+                    .collect(),
                 features: [].into(),
-                rack: None,
+                rack: self.rack.clone(),
             })
             .await
             .map_err(Into::into)
     }
 
     pub async fn listen(&self) -> Result<()> {
-        debug!("listener: {}", self.listener.as_str());
+        let mut set = JoinSet::new();
 
-        let listener = TcpListener::bind(format!(
-            "{}:{}",
-            self.listener.host_str().unwrap_or("0.0.0.0"),
-            self.listener.port().unwrap_or(9092)
-        ))
-        .await?;
+        for listener in &self.listeners {
+            let security_protocol = listener.security_protocol();
+            debug!(name = listener.name(), bind = %listener.bind, %security_protocol);
+
+            if security_protocol.is_sasl() && !self.authentication.is_enabled() {
+                return Err(Error::Message(format!(
+                    "listener {} is {security_protocol} without a SASL mechanism",
+                    listener.name()
+                )));
+            }
+
+            if security_protocol.is_tls() && self.tls.is_none() {
+                return Err(Error::Message(format!(
+                    "listener {} is {security_protocol} without a certificate",
+                    listener.name()
+                )));
+            }
+
+            let bound = TcpListener::bind(listener.bind_address()).await?;
+            let broker = self.clone();
+            let listener = listener.clone();
+
+            _ = set.spawn(async move { broker.accept(listener, bound).await });
+        }
+
+        while let Some(joined) = set.join_next().await {
+            joined.map_err(|error| Error::Message(error.to_string()))??;
+        }
+
+        Ok(())
+    }
+
+    async fn accept(&self, config: ListenerConfig, listener: TcpListener) -> Result<()> {
+        let security_protocol = config.security_protocol();
+
+        let tls = self
+            .tls
+            .clone()
+            .filter(|_| security_protocol.is_tls())
+            .map(TlsAcceptor::from);
 
         loop {
             let (stream, addr) = listener.accept().await?;
-            debug!(?addr);
+            debug!(?addr, listener = config.name());
 
             let mut broker = self.clone();
+            broker.listener = Some(config.clone());
+
+            // SASL is only required by SASL_PLAINTEXT and SASL_SSL listeners
+            if !security_protocol.is_sasl() {
+                broker.authentication = Authentication::default();
+            }
+
+            let tls = tls.clone();
 
             _ = tokio::spawn(async move {
-                match broker.stream_handler(stream).await {
+                let handled = match tls {
+                    Some(acceptor) => match acceptor.accept(stream).await {
+                        Ok(stream) => broker.stream_handler(stream, addr).await,
+                        Err(error) => Err(error.into()),
+                    },
+
+                    None => broker.stream_handler(stream, addr).await,
+                };
+
+                match handled {
                     Err(ref error @ Error::Io(ref io)) if io.kind() == ErrorKind::UnexpectedEof => {
                         info!(?error);
                     }
@@ -160,52 +388,177 @@ where
         }
     }
 
-    async fn stream_handler(&mut self, mut stream: TcpStream) -> Result<()> {
-        debug!(?stream);
+    pub(crate) async fn stream_handler<T>(&mut self, stream: T, peer: SocketAddr) -> Result<()>
+    where
+        T: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        debug!(?peer);
 
-        let mut size = [0u8; 4];
+        // reported by DescribeGroups in the same form as a Java broker
+        self.client_host = Some(format!("/{}", peer.ip()));
+        self.peer = Some(peer);
 
-        loop {
-            _ = stream
-                .read_exact(&mut size)
-                .await
-                .inspect_err(|error| match error.kind() {
-                    ErrorKind::UnexpectedEof => {
-                        info!(?error);
-                    }
+        // shared by the requests pipelined on this connection
+        self.fetch_sessions = Arc::new(Mutex::new(Sessions::default()));
 
-                    _ => error!(?error),
-                })?;
+        let (mut reader, mut writer) = io::split(stream);
+        let (sender, mut requests) = mpsc::channel(self.max_in_flight);
 
-            if i32::from_be_bytes(size) == 0 {
-                info!("empty read!");
-                continue;
+        // requests are read as they arrive, while earlier requests are handled
+        let reading = tokio::spawn(async move {
+            while let Some(request) = read_request(&mut reader, peer).await.transpose() {
+                let failed = request.is_err();
+
+                if sender.send(request).await.is_err() || failed {
+                    break;
+                }
             }
+        });
 
-            let mut request: Vec<u8> = vec![0u8; i32::from_be_bytes(size) as usize + size.len()];
-            request[0..4].copy_from_slice(&size[..]);
+        let pipelined = self.pipelined(peer, &mut requests, &mut writer).await;
+        reading.abort();
+        pipelined
+    }
 
-            _ = stream
-                .read_exact(&mut request[4..])
-                .await
-                .inspect_err(|error| error!(?size, ?request, ?error))?;
-            debug!(?request);
+    async fn pipelined<W>(
+        &mut self,
+        peer: SocketAddr,
+        requests: &mut mpsc::Receiver<Result<Vec<u8>>>,
+        writer: &mut W,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut pipeline = Pipeline::with_maximum(self.max_in_flight);
+
+        // clients number requests in increasing order, wrapping on overflow
+        let mut last_correlation_id = None;
 
-            let response = self
-                .process_request(&request)
-                .await
-                .inspect_err(|error| error!(?request, ?error))?;
-            debug!(?response);
+        loop {
+            tokio::select! {
+                Some(joined) = pipeline.joined() => joined?,
+
+                request = requests.recv(), if !pipeline.is_full() => {
+                    let Some(request) = request else {
+                        break;
+                    };
+
+                    let request = request?;
+                    debug!(?request);
+
+                    // following a v0 handshake tokens aren't wrapped in a request,
+                    // with each response being size delimited
+                    if self.authentication.is_expecting_raw_token() {
+                        pipeline.settle().await?;
+
+                        let token = self
+                            .authentication
+                            .authenticate_raw(&mut self.storage, &request[4..])
+                            .await
+                            .inspect_err(|error| warn!(%peer, ?error))?;
+
+                        let size = u32::try_from(token.len())?.to_be_bytes();
+                        pipeline.complete(Some([&size[..], &token[..]].concat()));
+                    } else if let Some(response) = ApiVersionsRequest::unsupported_version(&self.registry, &request)? {
+                        pipeline.complete(Some(response));
+                    } else {
+                        let frame = match Frame::request_from_bytes(&request) {
+                            Ok(frame) => {
+                                debug!(%frame);
+                                frame
+                            }
+
+                            // answer the requests in flight before closing
+                            Err(error) => {
+                                warn!(%peer, ?error);
+                                break;
+                            }
+                        };
+
+                        let reused = match frame.header {
+                            Header::Request { correlation_id, .. } => {
+                                let reused = last_correlation_id
+                                    .is_some_and(|last: i32| correlation_id.wrapping_sub(last) <= 0);
+
+                                if reused {
+                                    warn!(%peer, correlation_id, ?last_correlation_id);
+                                } else {
+                                    last_correlation_id = Some(correlation_id);
+                                }
+
+                                reused
+                            }
+
+                            _ => false,
+                        };
+
+                        if reused && self.strict_correlation {
+                            pipeline.complete(self.rejected(frame, ErrorCode::InvalidRequest)?);
+                        } else if matches!(
+                            frame.body,
+                            Body::SaslHandshakeRequest { .. } | Body::SaslAuthenticateRequest { .. }
+                        ) {
+                            // the SASL state of the connection is changed by these,
+                            // which are handled once every earlier request is answered
+                            pipeline.settle().await?;
+
+                            pipeline.complete(
+                                self.process_request(frame)
+                                    .await
+                                    .inspect_err(|error| error!(?error))?,
+                            );
+                        } else {
+                            let read_only = is_read_only(&frame.body);
+                            let mut broker = self.clone();
+
+                            let handler = async move {
+                                broker
+                                    .process_request(frame)
+                                    .await
+                                    .inspect_err(|error| error!(?error))
+                            };
+
+                            if read_only {
+                                pipeline.spawn(handler);
+                            } else {
+                                pipeline.spawn_ordered(handler);
+                            }
+                        }
+                    }
+                }
+            }
 
-            stream
-                .write_all(&response)
-                .await
-                .inspect_err(|error| error!(?request, ?response, ?error))?;
+            write_responses(writer, pipeline.ready()).await?;
         }
+
+        // the client has stopped sending, answer what is in flight
+        pipeline.settle().await?;
+        write_responses(writer, pipeline.ready()).await
     }
 
-    async fn process_request(&mut self, input: &[u8]) -> Result<Vec<u8>> {
-        match Frame::request_from_bytes(input)? {
+    // a request that is not answered, such as a produce with acks=0,
+    // returns no response
+    async fn process_request(&mut self, frame: Frame) -> Result<Option<Vec<u8>>> {
+        // an api that isn't served by a broker listener is answered with an
+        // error, keeping the connection open
+        if let Frame {
+            header:
+                Header::Request {
+                    api_key,
+                    api_version,
+                    correlation_id,
+                    ref client_id,
+                },
+            ..
+        } = frame
+        {
+            if ApiKey::try_from(api_key).is_ok_and(|api_key| !self.registry.serves(api_key)) {
+                warn!(api_key, api_version, correlation_id, ?client_id);
+                return self.rejected(frame, ErrorCode::InvalidRequest);
+            }
+        }
+
+        match frame {
             Frame {
                 header:
                     Header::Request {
@@ -218,461 +571,577 @@ where
                 body,
                 ..
             } => {
-                debug!(?api_key, ?api_version, ?correlation_id);
-                let body = self
-                    .response_for(client_id.as_deref(), body, correlation_id)
-                    .await
-                    .inspect_err(|err| error!(?err))?;
-                debug!(?body, ?correlation_id);
-                Frame::response(
-                    Header::Response { correlation_id },
-                    body,
+                let api_key = ApiKey::try_from(api_key)?;
+
+                if !self.registry.supports(api_key, api_version) {
+                    warn!(%api_key, api_version, correlation_id, ?client_id);
+                    return Err(Error::Api(ErrorCode::UnsupportedVersion));
+                }
+
+                // named and kinded for export as an opentelemetry server span
+                let span = debug_span!(
+                    "request",
+                    otel.name = %api_key,
+                    otel.kind = "server",
+                    otel.status_code = field::Empty,
+                    otel.status_message = field::Empty,
+                    api = %api_key,
+                    v = api_version,
+                    correlation_id,
+                    client_id = client_id.as_deref().unwrap_or_default(),
+                    peer = self.peer.map(|peer| peer.to_string()),
+                );
+
+                let acknowledged = !matches!(body, Body::ProduceRequest { acks: 0, .. });
+                let produced = quota::produced(&body);
+
+                async {
+                    let started = Instant::now();
+
+                    // an authenticated request that fails is answered with its error,
+                    // keeping the connection open
+                    let mut answerable = None;
+
+                    // the SASL state belongs to the connection rather than the broker
+                    let response = match body {
+                        Body::SaslHandshakeRequest { mechanism, .. } => Ok(self
+                            .authentication
+                            .handshake(api_version, mechanism.as_str())),
+
+                        Body::SaslAuthenticateRequest { auth_bytes, .. } => {
+                            self.authentication
+                                .authenticate(&mut self.storage, &auth_bytes[..])
+                                .await
+                        }
+
+                        body => match self.authentication.permits(api_key) {
+                            Ok(()) => {
+                                answerable = ErrorResponse::new(&self.cluster_id, &body);
+
+                                self.authorized_response_for(
+                                    api_key,
+                                    client_id.as_deref(),
+                                    body,
+                                    correlation_id,
+                                )
+                                .await
+                                .inspect_err(|err| error!(?err))
+                            }
+
+                            Err(error) => Err(error),
+                        },
+                    };
+
+                    metrics::request(api_key, &response, started.elapsed());
+
+                    let error_code = metrics::response_error_code(&response);
+                    if error_code != ErrorCode::None {
+                        _ = Span::current()
+                            .record("otel.status_code", "error")
+                            .record("otel.status_message", error_code.to_string());
+                    }
+
+                    let mut body = match (response, answerable) {
+                        (Ok(body), _) => body,
+
+                        (Err(error), Some(answer)) => {
+                            warn!(%api_key, ?error, %error_code);
+                            answer.with(error_code)
+                        }
+
+                        (Err(error), None) => return Err(error),
+                    };
+
+                    debug!(%body);
+
+                    let throttle = self.quotas.throttle(client_id.as_deref(), produced, &body);
+
+                    if !throttle.is_zero() {
+                        quota::with_throttle_time_ms(
+                            &mut body,
+                            i32::try_from(throttle.as_millis()).unwrap_or(i32::MAX),
+                        );
+
+                        sleep(throttle).await;
+                    }
+
+                    if !acknowledged {
+                        return Ok(None);
+                    }
+
+                    Frame::encode_response(
+                        Header::Response { correlation_id },
+                        body,
+                        api_key,
+                        api_version,
+                    )
+                    .map(Some)
+                    .inspect_err(|err| error!(?err))
+                    .map_err(Into::into)
+                }
+                .instrument(span)
+                .await
+            }
+
+            Frame { header, .. } => Err(tansu_kafka_sans_io::Error::Message(format!(
+                "expecting request header, found: {header:?}"
+            ))
+            .into()),
+        }
+    }
+
+    // the request answered with this error without being handled, closing
+    // the connection when it has no error response
+    fn rejected(&self, frame: Frame, error_code: ErrorCode) -> Result<Option<Vec<u8>>> {
+        let Frame {
+            header:
+                Header::Request {
                     api_key,
                     api_version,
-                )
-                .inspect_err(|err| error!(?err))
-                .map_err(Into::into)
-            }
+                    correlation_id,
+                    ..
+                },
+            body,
+            ..
+        } = frame
+        else {
+            return Err(Error::Api(error_code));
+        };
+
+        if matches!(body, Body::ProduceRequest { acks: 0, .. }) {
+            return Ok(None);
+        }
+
+        let api_key = ApiKey::try_from(api_key)?;
 
-            _ => unimplemented!(),
+        let body =
+            error_response(&self.cluster_id, &body, error_code).ok_or(Error::Api(error_code))?;
+
+        Frame::encode_response(
+            Header::Response { correlation_id },
+            body,
+            api_key,
+            api_version,
+        )
+        .map(Some)
+        .map_err(Into::into)
+    }
+
+    /// The context of a request from this client on this connection.
+    fn context(&self, client_id: Option<&str>) -> ConnectionContext<G, S> {
+        ConnectionContext {
+            node_id: self.node_id,
+            cluster_id: self.cluster_id.clone(),
+            listeners: self.listeners.clone(),
+            listener: self.listener.clone(),
+            rack: self.rack.clone(),
+            client_id: client_id.map(ToOwned::to_owned),
+            client_host: self.client_host.clone(),
+            principal: Principal::new(self.principal(), self.client_host.as_deref()),
+            storage: self.storage.clone(),
+            groups: self.groups.clone(),
+            notifications: self.notifications.clone(),
+            fetch_sessions: self.fetch_sessions.clone(),
+            quotas: self.quotas.clone(),
+            authorizer: self.authorizer.clone(),
+            replica_selector: self.replica_selector.clone(),
+            registry: self.registry.clone(),
+            topic_defaults: self.topic_defaults.clone(),
+            recompression: self.recompression,
+            fetch_max_bytes: self.fetch_max_bytes,
         }
     }
 
     pub async fn response_for(
         &mut self,
+        api_key: ApiKey,
         client_id: Option<&str>,
         body: Body,
         correlation_id: i32,
     ) -> Result<Body> {
-        debug!(?body, ?correlation_id);
+        debug!(%api_key, ?body, ?correlation_id);
 
-        match body {
-            Body::ApiVersionsRequest {
-                client_software_name,
-                client_software_version,
-            } => {
-                debug!(?client_software_name, ?client_software_version,);
+        let Some(registration) = self.registry.registration(api_key) else {
+            error!(%api_key, ?correlation_id);
+            return Err(Error::Api(ErrorCode::UnsupportedVersion));
+        };
 
-                let api_versions = ApiVersionsRequest;
-                Ok(api_versions.response(
-                    client_software_name.as_deref(),
-                    client_software_version.as_deref(),
-                ))
-            }
+        registration
+            .handler()
+            .handle(&self.context(client_id), body)
+            .await
+    }
+}
 
-            Body::CreateTopicsRequest {
-                validate_only,
-                topics,
-                ..
-            } => {
-                debug!(?validate_only, ?topics);
-                CreateTopic::with_storage(self.storage.clone())
-                    .response(topics, validate_only.unwrap_or(false))
-                    .await
-                    .map(Some)
-                    .map(|topics| Body::CreateTopicsResponse {
-                        throttle_time_ms: Some(0),
-                        topics,
-                    })
-            }
+// the next size delimited request, or none when the request is too large
+async fn read_request<R>(reader: &mut R, peer: SocketAddr) -> Result<Option<Vec<u8>>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut size = [0u8; 4];
 
-            Body::DeleteRecordsRequest { topics, .. } => {
-                debug!(?topics);
+    loop {
+        _ = reader
+            .read_exact(&mut size)
+            .await
+            .inspect_err(|error| match error.kind() {
+                ErrorKind::UnexpectedEof => {
+                    info!(?error);
+                }
 
-                DeleteRecordsRequest::with_storage(self.storage.clone())
-                    .request(topics.as_deref().unwrap_or(&[]))
-                    .await
-            }
+                _ => error!(?error),
+            })?;
 
-            Body::DeleteTopicsRequest {
-                topics,
-                topic_names,
-                timeout_ms,
-            } => {
-                debug!(?topics, ?topic_names, ?timeout_ms);
-
-                Ok(Body::DeleteTopicsResponse {
-                    throttle_time_ms: Some(0),
-                    responses: DeleteTopicsRequest::with_storage(self.storage.clone())
-                        .response(topics, topic_names)
-                        .await
-                        .map(Some)?,
-                })
-            }
+        let length = match Frame::check_within(&size, DEFAULT_MAX_FRAME_BYTES) {
+            Ok(length) => length,
 
-            Body::DescribeClusterRequest {
-                include_cluster_authorized_operations,
-                endpoint_type,
-            } => {
-                debug!(?include_cluster_authorized_operations, ?endpoint_type);
+            Err(tansu_kafka_sans_io::Error::Incomplete {
+                needed: Some(needed),
+            }) => size.len() + needed,
 
-                DescribeClusterRequest {
-                    cluster_id: self.cluster_id.clone(),
-                    storage: self.storage.clone(),
-                }
-                .response(include_cluster_authorized_operations, endpoint_type)
-                .await
+            Err(tansu_kafka_sans_io::Error::FrameTooLarge { length, maximum }) => {
+                warn!(%peer, length, maximum);
+                return Ok(None);
             }
 
-            Body::DescribeConfigsRequest {
-                resources,
-                include_synonyms,
-                include_documentation,
-            } => {
-                debug!(?resources, ?include_synonyms, ?include_documentation,);
+            Err(error) => return Err(error.into()),
+        };
 
-                DescribeConfigsRequest::with_storage(self.storage.clone())
-                    .response(
-                        resources.as_deref(),
-                        include_synonyms,
-                        include_documentation,
-                    )
-                    .await
-            }
+        if length == size.len() {
+            info!("empty read!");
+            continue;
+        }
 
-            Body::FetchRequest {
-                max_wait_ms,
-                min_bytes,
-                max_bytes,
-                isolation_level,
-                topics,
-                ..
-            } => {
-                debug!(
-                    ?max_wait_ms,
-                    ?min_bytes,
-                    ?max_bytes,
-                    ?isolation_level,
-                    ?topics,
-                );
+        let mut request: Vec<u8> = vec![0u8; length];
+        request[0..4].copy_from_slice(&size[..]);
 
-                FetchRequest::with_storage(self.storage.clone())
-                    .response(
-                        max_wait_ms,
-                        min_bytes,
-                        max_bytes,
-                        isolation_level,
-                        topics.as_deref(),
-                    )
-                    .await
-                    .inspect(|r| debug!(?r))
-                    .inspect_err(|error| error!(?error))
-            }
+        _ = reader
+            .read_exact(&mut request[4..])
+            .await
+            .inspect_err(|error| error!(?size, ?request, ?error))?;
 
-            Body::FindCoordinatorRequest {
-                key,
-                key_type,
-                coordinator_keys,
-            } => {
-                debug!(?key, ?key_type, ?coordinator_keys);
+        return Ok(Some(request));
+    }
+}
 
-                let find_coordinator = FindCoordinatorRequest;
+async fn write_responses<W>(writer: &mut W, responses: Vec<Vec<u8>>) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    for response in responses {
+        debug!(?response);
 
-                Ok(find_coordinator.response(
-                    key.as_deref(),
-                    key_type,
-                    coordinator_keys.as_deref(),
-                    self.node_id,
-                    &self.advertised_listener,
-                ))
-            }
+        writer
+            .write_all(&response)
+            .await
+            .inspect_err(|error| error!(?response, ?error))?;
+    }
 
-            Body::GetTelemetrySubscriptionsRequest { client_instance_id } => {
-                debug!(?client_instance_id);
-                let get_telemetry_subscriptions = GetTelemetrySubscriptionsRequest;
-                Ok(get_telemetry_subscriptions.response(client_instance_id))
-            }
+    Ok(())
+}
 
-            Body::HeartbeatRequest {
-                group_id,
-                generation_id,
-                member_id,
-                group_instance_id,
-            } => {
-                debug!(?group_id, ?generation_id, ?member_id, ?group_instance_id,);
-
-                self.groups
-                    .heartbeat(
-                        &group_id,
-                        generation_id,
-                        &member_id,
-                        group_instance_id.as_deref(),
-                    )
-                    .await
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinator::group::administrator::Controller;
+    use object_store::memory::InMemory;
+    use tansu_storage::dynostore::DynoStore;
+    use tokio::{
+        io::{duplex, DuplexStream},
+        task::JoinHandle,
+    };
+    use url::Url;
+
+    const API_VERSION: i16 = 3;
+
+    fn connect(strict: bool) -> Result<(DuplexStream, JoinHandle<Result<()>>)> {
+        let cluster = "abc";
+        let node = 12321;
+
+        let storage = DynoStore::new(cluster, node, InMemory::new());
+        let listener = Url::parse("tcp://localhost:9092")?;
+
+        let mut broker = Broker::new(
+            node,
+            cluster,
+            vec![ListenerConfig::new("broker", listener.clone(), &listener)],
+            None,
+            storage.clone(),
+            Controller::with_storage(storage)?,
+        );
+
+        if strict {
+            broker = broker.with_strict_correlation();
+        }
 
-            Body::InitProducerIdRequest {
-                transactional_id,
-                transaction_timeout_ms,
-                producer_id,
-                producer_epoch,
-            } => {
-                debug!(
-                    ?transactional_id,
-                    ?transaction_timeout_ms,
-                    ?producer_id,
-                    ?producer_epoch,
-                );
+        let (client, server) = duplex(64 * 1024);
+        let peer = SocketAddr::from(([127, 0, 0, 1], 54321));
 
-                InitProducerIdRequest::with_storage(self.storage.clone())
-                    .response(
-                        transactional_id.as_deref(),
-                        transaction_timeout_ms,
-                        producer_id,
-                        producer_epoch,
-                    )
-                    .await
-                    .map(|response| Body::InitProducerIdResponse {
-                        throttle_time_ms: 0,
-                        error_code: response.error.into(),
-                        producer_id: response.id,
-                        producer_epoch: response.epoch,
-                    })
-            }
+        Ok((
+            client,
+            tokio::spawn(async move { broker.stream_handler(server, peer).await }),
+        ))
+    }
 
-            Body::JoinGroupRequest {
-                group_id,
-                session_timeout_ms,
-                rebalance_timeout_ms,
-                member_id,
-                group_instance_id,
-                protocol_type,
-                protocols,
-                reason,
-            } => {
-                debug!(
-                    ?group_id,
-                    ?session_timeout_ms,
-                    ?rebalance_timeout_ms,
-                    ?member_id,
-                    ?group_instance_id,
-                    ?protocol_type,
-                    ?protocols,
-                    ?reason,
-                );
+    fn api_versions(correlation_id: i32) -> Result<Vec<u8>> {
+        Frame::request(
+            Header::Request {
+                api_key: ApiKey::ApiVersions.into(),
+                api_version: API_VERSION,
+                correlation_id,
+                client_id: Some("conformance".into()),
+            },
+            Body::ApiVersionsRequest {
+                client_software_name: Some("tansu".into()),
+                client_software_version: Some("0.1".into()),
+                unknown_tagged_fields: vec![],
+            },
+        )
+        .map_err(Into::into)
+    }
 
-                self.groups
-                    .join(
-                        client_id,
-                        &group_id,
-                        session_timeout_ms,
-                        rebalance_timeout_ms,
-                        &member_id,
-                        group_instance_id.as_deref(),
-                        &protocol_type,
-                        protocols.as_deref(),
-                        reason.as_deref(),
-                    )
-                    .await
-            }
+    async fn response(
+        client: &mut DuplexStream,
+        api_key: ApiKey,
+        api_version: i16,
+    ) -> Result<Frame> {
+        let mut size = [0u8; 4];
+        _ = client.read_exact(&mut size).await?;
 
-            Body::LeaveGroupRequest {
-                group_id,
-                member_id,
-                members,
-            } => {
-                debug!(?group_id, ?member_id, ?members);
+        let mut response = vec![0u8; size.len() + usize::try_from(u32::from_be_bytes(size))?];
+        response[..4].copy_from_slice(&size);
+        _ = client.read_exact(&mut response[4..]).await?;
 
-                self.groups
-                    .leave(&group_id, member_id.as_deref(), members.as_deref())
-                    .await
-            }
+        Frame::decode_response(&response, api_key, api_version).map_err(Into::into)
+    }
 
-            Body::ListOffsetsRequest {
-                replica_id,
-                isolation_level,
-                topics,
-            } => {
-                debug!(?replica_id, ?isolation_level, ?topics);
+    // the correlation id and error code of the next response
+    async fn api_versions_response(client: &mut DuplexStream) -> Result<(i32, i16)> {
+        match response(client, ApiKey::ApiVersions, API_VERSION).await? {
+            Frame {
+                header: Header::Response { correlation_id },
+                body: Body::ApiVersionsResponse { error_code, .. },
+                ..
+            } => Ok((correlation_id, error_code)),
 
-                ListOffsetsRequest::with_storage(self.storage.clone())
-                    .response(replica_id, isolation_level, topics.as_deref())
-                    .await
-            }
+            otherwise => panic!("expecting an api versions response: {otherwise:?}"),
+        }
+    }
 
-            Body::ListPartitionReassignmentsRequest { topics, .. } => {
-                debug!(?topics);
+    #[tokio::test]
+    async fn reused_correlation_id_is_answered() -> Result<()> {
+        let (mut client, connection) = connect(false)?;
 
-                ListPartitionReassignmentsRequest::with_storage(self.storage.clone())
-                    .response(topics.as_deref())
-                    .await
-            }
+        for correlation_id in [7, 7, 6] {
+            client.write_all(&api_versions(correlation_id)?).await?;
+        }
 
-            Body::MetadataRequest { topics, .. } => {
-                debug!(?topics);
-                MetadataRequest::with_storage(self.storage.clone())
-                    .response(topics)
-                    .await
-            }
+        for correlation_id in [7, 7, 6] {
+            assert_eq!(
+                (correlation_id, ErrorCode::None.into()),
+                api_versions_response(&mut client).await?
+            );
+        }
 
-            Body::OffsetCommitRequest {
-                group_id,
-                generation_id_or_member_epoch,
-                member_id,
-                group_instance_id,
-                retention_time_ms,
-                topics,
-            } => {
-                debug!(
-                    ?group_id,
-                    ?generation_id_or_member_epoch,
-                    ?member_id,
-                    ?group_instance_id,
-                    ?retention_time_ms,
-                    ?topics
-                );
+        connection.abort();
 
-                let detail = crate::coordinator::group::OffsetCommit {
-                    group_id: group_id.as_str(),
-                    generation_id_or_member_epoch,
-                    member_id: member_id.as_deref(),
-                    group_instance_id: group_instance_id.as_deref(),
-                    retention_time_ms,
-                    topics: topics.as_deref(),
-                };
+        Ok(())
+    }
 
-                self.groups.offset_commit(detail).await
-            }
+    #[tokio::test]
+    async fn reused_correlation_id_is_rejected() -> Result<()> {
+        let (mut client, connection) = connect(true)?;
+
+        // the correlation id wraps from i32::MAX to i32::MIN
+        let requests = [
+            i32::MAX - 1,
+            i32::MAX,
+            i32::MIN,
+            i32::MIN,
+            i32::MIN + 1,
+            i32::MAX,
+        ];
+
+        for correlation_id in requests {
+            client.write_all(&api_versions(correlation_id)?).await?;
+        }
 
-            Body::OffsetFetchRequest {
-                group_id,
-                topics,
-                groups,
-                require_stable,
-            } => {
-                debug!(?group_id, ?topics, ?groups, ?require_stable);
-                self.groups
-                    .offset_fetch(
-                        group_id.as_deref(),
-                        topics.as_deref(),
-                        groups.as_deref(),
-                        require_stable,
-                    )
-                    .await
-            }
+        let mut responses = vec![];
+        for _ in requests {
+            responses.push(api_versions_response(&mut client).await?);
+        }
 
-            Body::ProduceRequest {
-                transactional_id,
-                acks,
-                timeout_ms,
-                topic_data,
-            } => {
-                debug!(?transactional_id, ?acks, ?timeout_ms, ?topic_data);
-                ProduceRequest::with_storage(self.storage.clone())
-                    .response(transactional_id, acks, timeout_ms, topic_data)
-                    .await
-                    .map(|response| Body::ProduceResponse {
-                        responses: response.responses,
-                        throttle_time_ms: response.throttle_time_ms,
-                        node_endpoints: response.node_endpoints,
-                    })
-            }
+        connection.abort();
 
-            Body::SyncGroupRequest {
-                group_id,
-                generation_id,
-                member_id,
-                group_instance_id,
-                protocol_type,
-                protocol_name,
-                assignments,
-            } => {
-                self.groups
-                    .sync(
-                        &group_id,
-                        generation_id,
-                        &member_id,
-                        group_instance_id.as_deref(),
-                        protocol_type.as_deref(),
-                        protocol_name.as_deref(),
-                        assignments.as_deref(),
-                    )
-                    .await
-            }
+        let invalid = ErrorCode::InvalidRequest.into();
 
-            Body::AddOffsetsToTxnRequest {
-                transactional_id,
-                producer_id,
-                producer_epoch,
-                group_id,
-            } => {
-                debug!(?transactional_id, ?producer_id, ?producer_epoch, ?group_id);
-
-                AddOffsets::with_storage(self.storage.clone())
-                    .response(
-                        transactional_id.as_str(),
-                        producer_id,
-                        producer_epoch,
-                        group_id.as_str(),
-                    )
-                    .await
-            }
+        assert_eq!(
+            vec![
+                (i32::MAX - 1, 0),
+                (i32::MAX, 0),
+                (i32::MIN, 0),
+                (i32::MIN, invalid),
+                (i32::MIN + 1, 0),
+                (i32::MAX, invalid),
+            ],
+            responses
+        );
 
-            Body::AddPartitionsToTxnRequest {
-                transactions,
-                v_3_and_below_transactional_id,
-                v_3_and_below_producer_id,
-                v_3_and_below_producer_epoch,
-                v_3_and_below_topics,
-            } => {
-                debug!(
-                    ?transactions,
-                    ?v_3_and_below_transactional_id,
-                    ?v_3_and_below_producer_id,
-                    ?v_3_and_below_producer_epoch,
-                    ?v_3_and_below_topics
-                );
+        Ok(())
+    }
 
-                AddPartitions::with_storage(self.storage.clone())
-                    .response(
-                        transactions,
-                        v_3_and_below_transactional_id,
-                        v_3_and_below_producer_id,
-                        v_3_and_below_producer_epoch,
-                        v_3_and_below_topics,
-                    )
-                    .await
-            }
+    #[tokio::test]
+    async fn controller_api_is_answered() -> Result<()> {
+        let (mut client, connection) = connect(false)?;
+
+        client
+            .write_all(&Frame::request(
+                Header::Request {
+                    api_key: ApiKey::AllocateProducerIds.into(),
+                    api_version: 0,
+                    correlation_id: 11,
+                    client_id: Some("conformance".into()),
+                },
+                Body::AllocateProducerIdsRequest {
+                    broker_id: 12321,
+                    broker_epoch: 1,
+                    unknown_tagged_fields: vec![],
+                },
+            )?)
+            .await?;
+
+        client.write_all(&api_versions(12)?).await?;
+
+        // answered with an error, rather than closing the connection
+        assert!(matches!(
+            response(&mut client, ApiKey::AllocateProducerIds, 0).await?,
+            Frame {
+                header: Header::Response { correlation_id: 11 },
+                body: Body::AllocateProducerIdsResponse { error_code, .. },
+                ..
+            } if error_code == i16::from(ErrorCode::InvalidRequest)
+        ));
 
-            Body::TxnOffsetCommitRequest {
-                transactional_id,
-                group_id,
-                producer_id,
-                producer_epoch,
-                generation_id,
-                member_id,
-                group_instance_id,
-                topics,
-            } => {
-                debug!(
-                    ?transactional_id,
-                    ?group_id,
-                    ?producer_id,
-                    ?producer_epoch,
-                    ?generation_id,
-                    ?member_id,
-                    ?group_instance_id,
-                    ?topics,
-                );
+        assert_eq!(
+            (12, ErrorCode::None.into()),
+            api_versions_response(&mut client).await?
+        );
 
-                txn::offset_commit::OffsetCommit::with_storage(self.storage.clone())
-                    .response(
-                        transactional_id.as_str(),
-                        group_id.as_str(),
-                        producer_id,
-                        producer_epoch,
-                        generation_id,
-                        member_id,
-                        group_instance_id,
-                        topics,
-                    )
-                    .await
-            }
+        connection.abort();
 
-            request => {
-                error!(?request);
-                unimplemented!("{request:?}")
-            }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn broker_heartbeat_is_served() -> Result<()> {
+        let (mut client, connection) = connect(false)?;
+
+        client.write_all(&api_versions(31)?).await?;
+
+        let Frame {
+            body:
+                Body::ApiVersionsResponse {
+                    api_keys: Some(api_keys),
+                    ..
+                },
+            ..
+        } = response(&mut client, ApiKey::ApiVersions, API_VERSION).await?
+        else {
+            panic!("expecting an api versions response")
+        };
+
+        assert!(api_keys
+            .iter()
+            .any(|api_version| api_version.api_key == i16::from(ApiKey::BrokerHeartbeat)));
+
+        client
+            .write_all(&Frame::request(
+                Header::Request {
+                    api_key: ApiKey::BrokerHeartbeat.into(),
+                    api_version: 1,
+                    correlation_id: 32,
+                    client_id: Some("conformance".into()),
+                },
+                Body::BrokerHeartbeatRequest {
+                    broker_id: 12321,
+                    broker_epoch: 1,
+                    current_metadata_offset: 0,
+                    want_fence: false,
+                    want_shut_down: false,
+                    offline_log_dirs: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                },
+            )?)
+            .await?;
+
+        // handled rather than rejected by the listener, this broker never
+        // having registered
+        assert!(matches!(
+            response(&mut client, ApiKey::BrokerHeartbeat, 1).await?,
+            Frame {
+                header: Header::Response { correlation_id: 32 },
+                body: Body::BrokerHeartbeatResponse {
+                    error_code,
+                    is_fenced: true,
+                    ..
+                },
+                ..
+            } if error_code == i16::from(ErrorCode::BrokerIdNotRegistered)
+        ));
+
+        connection.abort();
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn broken_header_answers_in_flight() -> Result<()> {
+        let (mut client, connection) = connect(false)?;
+
+        // api versions, followed by one without a correlation id
+        client
+            .write_all(&[&api_versions(41)?[..], &[0, 0, 0, 4, 0, 18, 0, 3][..]].concat())
+            .await?;
+
+        assert_eq!(
+            (41, ErrorCode::None.into()),
+            api_versions_response(&mut client).await?
+        );
+
+        assert!(connection.await.is_ok());
+
+        let mut response = vec![];
+        assert_eq!(0, client.read_to_end(&mut response).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn broken_header_closes_connection() -> Result<()> {
+        for request in [
+            // api versions, without a correlation id
+            &[0, 0, 0, 4, 0, 18, 0, 3][..],
+            // api versions v0, with a client id of 32767 bytes in a 15 byte frame
+            &[
+                0, 0, 0, 15, 0, 18, 0, 0, 0, 0, 0, 3, 127, 255, 99, 111, 110, 115, 111,
+            ][..],
+            // an unknown api key
+            &[0, 0, 0, 10, 3, 231, 0, 0, 0, 0, 0, 3, 255, 255][..],
+            // a negative frame length
+            &[255, 255, 255, 255, 0, 18, 0, 0][..],
+        ] {
+            let (mut client, connection) = connect(false)?;
+
+            client.write_all(request).await?;
+
+            // closed, rather than panicking or answering
+            let handled = connection.await;
+            assert!(handled.is_ok(), "{request:?}");
+
+            let mut response = vec![];
+            assert_eq!(0, client.read_to_end(&mut response).await?, "{request:?}");
         }
+
+        Ok(())
     }
 }
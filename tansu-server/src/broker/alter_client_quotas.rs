@@ -0,0 +1,235 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::broker::quota::{Quotas, CLIENT_ID, KEYS};
+use tansu_kafka_sans_io::{
+    alter_client_quotas_request::{self, EntryData},
+    alter_client_quotas_response::{self, EntityData},
+    Body, ErrorCode,
+};
+use tracing::debug;
+
+#[derive(Clone, Debug, Default)]
+pub struct AlterClientQuotasRequest {
+    quotas: Quotas,
+}
+
+impl AlterClientQuotasRequest {
+    pub fn with_quotas(quotas: Quotas) -> Self {
+        Self { quotas }
+    }
+
+    // the client id of an entity, with none being the default for every client
+    fn client_id(entry: &EntryData) -> Result<Option<&str>, String> {
+        match entry.entity.as_deref() {
+            Some(
+                [alter_client_quotas_request::EntityData {
+                    entity_type,
+                    entity_name,
+                }],
+            ) if entity_type == CLIENT_ID => Ok(entity_name.as_deref()),
+
+            otherwise => Err(format!("unsupported entity: {otherwise:?}")),
+        }
+    }
+
+    fn validate(entry: &EntryData) -> Result<Option<&str>, String> {
+        let client_id = Self::client_id(entry)?;
+
+        for op in entry.ops.as_deref().unwrap_or_default() {
+            if !KEYS.contains(&op.key.as_str()) {
+                return Err(format!("unknown quota: {}", op.key));
+            }
+
+            if !op.remove && (!op.value.is_finite() || op.value <= 0.0) {
+                return Err(format!("invalid quota value: {}", op.value));
+            }
+        }
+
+        Ok(client_id)
+    }
+
+    pub fn response(&self, entries: Option<&[EntryData]>, validate_only: bool) -> Body {
+        debug!(?entries, validate_only);
+
+        let entries = entries
+            .unwrap_or_default()
+            .iter()
+            .map(|entry| {
+                let (error_code, error_message) = match Self::validate(entry) {
+                    Ok(client_id) => {
+                        if !validate_only {
+                            for op in entry.ops.as_deref().unwrap_or_default() {
+                                self.quotas.alter(
+                                    client_id,
+                                    &op.key,
+                                    Some(op.value).filter(|_| !op.remove),
+                                );
+                            }
+                        }
+
+                        (ErrorCode::None, None)
+                    }
+
+                    Err(error_message) => (ErrorCode::InvalidRequest, Some(error_message)),
+                };
+
+                alter_client_quotas_response::EntryData {
+                    error_code: error_code.into(),
+                    error_message,
+                    entity: entry.entity.as_ref().map(|entity| {
+                        entity
+                            .iter()
+                            .map(|entity| EntityData {
+                                entity_type: entity.entity_type.clone(),
+                                entity_name: entity.entity_name.clone(),
+                            })
+                            .collect()
+                    }),
+                }
+            })
+            .collect();
+
+        Body::AlterClientQuotasResponse {
+            throttle_time_ms: 0,
+            entries: Some(entries),
+            unknown_tagged_fields: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broker::{
+        describe_client_quotas::DescribeClientQuotasRequest,
+        quota::{CONSUMER_BYTE_RATE, PRODUCER_BYTE_RATE},
+    };
+    use alter_client_quotas_request::OpData;
+    use tansu_kafka_sans_io::describe_client_quotas_request::ComponentData;
+
+    fn entry(client_id: Option<&str>, ops: Vec<OpData>) -> EntryData {
+        EntryData {
+            entity: Some(vec![alter_client_quotas_request::EntityData {
+                entity_type: CLIENT_ID.into(),
+                entity_name: client_id.map(str::to_owned),
+            }]),
+            ops: Some(ops),
+        }
+    }
+
+    #[test]
+    fn alter_and_describe() {
+        let quotas = Quotas::new();
+
+        let Body::AlterClientQuotasResponse { entries, .. } =
+            AlterClientQuotasRequest::with_quotas(quotas.clone()).response(
+                Some(&[
+                    entry(
+                        None,
+                        vec![OpData {
+                            key: PRODUCER_BYTE_RATE.into(),
+                            value: 1_048_576.0,
+                            remove: false,
+                        }],
+                    ),
+                    entry(
+                        Some("abc"),
+                        vec![OpData {
+                            key: CONSUMER_BYTE_RATE.into(),
+                            value: 1024.0,
+                            remove: false,
+                        }],
+                    ),
+                    entry(
+                        Some("pqr"),
+                        vec![OpData {
+                            key: "leader_replication_throttled_rate".into(),
+                            value: 1024.0,
+                            remove: false,
+                        }],
+                    ),
+                ]),
+                false,
+            )
+        else {
+            panic!("expecting alter client quotas response")
+        };
+
+        assert_eq!(
+            vec![
+                i16::from(ErrorCode::None),
+                ErrorCode::None.into(),
+                ErrorCode::InvalidRequest.into()
+            ],
+            entries
+                .unwrap_or_default()
+                .iter()
+                .map(|entry| entry.error_code)
+                .collect::<Vec<_>>()
+        );
+
+        let Body::DescribeClientQuotasResponse { entries, .. } =
+            DescribeClientQuotasRequest::with_quotas(quotas.clone()).response(
+                Some(&[ComponentData {
+                    entity_type: CLIENT_ID.into(),
+                    match_type: 0,
+                    r#match: Some("abc".into()),
+                }]),
+                false,
+            )
+        else {
+            panic!("expecting describe client quotas response")
+        };
+
+        let entries = entries.unwrap_or_default();
+        assert_eq!(1, entries.len());
+        assert_eq!(
+            Some(vec![(CONSUMER_BYTE_RATE.to_owned(), 1024.0)]),
+            entries[0].values.as_ref().map(|values| values
+                .iter()
+                .map(|value| (value.key.clone(), value.value))
+                .collect::<Vec<_>>())
+        );
+
+        _ = AlterClientQuotasRequest::with_quotas(quotas.clone()).response(
+            Some(&[entry(
+                Some("abc"),
+                vec![OpData {
+                    key: CONSUMER_BYTE_RATE.into(),
+                    value: 0.0,
+                    remove: true,
+                }],
+            )]),
+            false,
+        );
+
+        let Body::DescribeClientQuotasResponse { entries, .. } =
+            DescribeClientQuotasRequest::with_quotas(quotas).response(None, false)
+        else {
+            panic!("expecting describe client quotas response")
+        };
+
+        assert_eq!(
+            vec![None],
+            entries
+                .unwrap_or_default()
+                .into_iter()
+                .flat_map(|entry| entry.entity.unwrap_or_default())
+                .map(|entity| entity.entity_name)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,411 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::list_partition_reassignments::{ongoing, placed_replicas};
+use crate::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use tansu_kafka_sans_io::{
+    alter_partition_reassignments_request::{ReassignablePartition, ReassignableTopic},
+    alter_partition_reassignments_response::{
+        ReassignablePartitionResponse, ReassignableTopicResponse,
+    },
+    Body, ErrorCode,
+};
+use tansu_storage::{Storage, Topition};
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AlterPartitionReassignmentsRequest<S> {
+    storage: S,
+}
+
+impl<S> AlterPartitionReassignmentsRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    fn partition_response(
+        partition_index: i32,
+        error_code: ErrorCode,
+    ) -> ReassignablePartitionResponse {
+        ReassignablePartitionResponse {
+            partition_index,
+            error_code: error_code.into(),
+            error_message: (error_code != ErrorCode::None).then(|| error_code.to_string()),
+        }
+    }
+
+    async fn reassign(
+        &mut self,
+        topition: &Topition,
+        replicas: Option<&[i32]>,
+        placed: &[i32],
+        brokers: &BTreeSet<i32>,
+        in_progress: &BTreeMap<Topition, Vec<i32>>,
+    ) -> Result<ErrorCode> {
+        debug!(?topition, ?replicas, ?placed);
+
+        let Some(replicas) = replicas else {
+            // cancelling a reassignment leaves the replicas as placed
+            return if in_progress.contains_key(topition) {
+                self.storage
+                    .alter_reassignment(topition, None)
+                    .await
+                    .map(|()| ErrorCode::None)
+                    .map_err(Into::into)
+            } else {
+                Ok(ErrorCode::NoReassignmentInProgress)
+            };
+        };
+
+        if replicas.is_empty()
+            || replicas.iter().collect::<BTreeSet<_>>().len() != replicas.len()
+            || replicas.iter().any(|replica| !brokers.contains(replica))
+        {
+            return Ok(ErrorCode::InvalidReplicaAssignment);
+        }
+
+        // a reassignment to the replicas already placed is complete
+        let target = ongoing(topition.partition(), replicas, placed).map(|_| replicas);
+
+        self.storage
+            .alter_reassignment(topition, target)
+            .await
+            .map(|()| ErrorCode::None)
+            .map_err(Into::into)
+    }
+
+    async fn topic_response(
+        &mut self,
+        topic: &ReassignableTopic,
+        brokers: &BTreeSet<i32>,
+        in_progress: &BTreeMap<Topition, Vec<i32>>,
+    ) -> Result<ReassignableTopicResponse> {
+        debug!(?topic);
+
+        let placed = placed_replicas(&mut self.storage, topic.name.as_str())
+            .await?
+            .unwrap_or_default();
+
+        let mut partitions = vec![];
+
+        for ReassignablePartition {
+            partition_index,
+            replicas,
+        } in topic.partitions.as_deref().unwrap_or_default()
+        {
+            let error_code = if let Some(placed) = placed.get(partition_index) {
+                let topition = Topition::new(topic.name.as_str(), *partition_index);
+
+                self.reassign(&topition, replicas.as_deref(), placed, brokers, in_progress)
+                    .await?
+            } else {
+                ErrorCode::UnknownTopicOrPartition
+            };
+
+            partitions.push(Self::partition_response(*partition_index, error_code));
+        }
+
+        Ok(ReassignableTopicResponse {
+            name: topic.name.clone(),
+            partitions: Some(partitions),
+        })
+    }
+
+    pub async fn response(
+        &mut self,
+        timeout_ms: i32,
+        topics: Option<&[ReassignableTopic]>,
+    ) -> Result<Body> {
+        debug!(?timeout_ms, ?topics);
+
+        let brokers = self
+            .storage
+            .brokers(None)
+            .await?
+            .into_iter()
+            .map(|broker| broker.broker_id)
+            .collect::<BTreeSet<_>>();
+
+        let in_progress = self.storage.reassignments().await?;
+
+        let mut responses = vec![];
+
+        for topic in topics.unwrap_or_default() {
+            responses.push(self.topic_response(topic, &brokers, &in_progress).await?);
+        }
+
+        Ok(Body::AlterPartitionReassignmentsResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.into(),
+            error_message: None,
+            responses: Some(responses),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{broker::list_partition_reassignments::ListPartitionReassignmentsRequest, Error};
+    use object_store::{memory::InMemory, ObjectStore};
+    use std::sync::Arc;
+    use tansu_kafka_sans_io::{
+        broker_registration_request::Listener, create_topics_request::CreatableTopic,
+        list_partition_reassignments_response::OngoingPartitionReassignment,
+        list_partition_reassignments_response::OngoingTopicReassignment,
+    };
+    use tansu_storage::{dynostore::DynoStore, BrokerRegistationRequest};
+    use tracing::subscriber::DefaultGuard;
+    use uuid::Uuid;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const TOPIC: &str = "pqr";
+
+    // a topic with a replica on each of the brokers
+    async fn storage_with_brokers(brokers: &[i32]) -> Result<DynoStore> {
+        let cluster = "abc";
+
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+
+        // each broker registers with its own node id
+        for (port, broker_id) in (9092..).zip(brokers) {
+            _ = DynoStore::new(cluster, *broker_id, object_store.clone())
+                .register_broker(BrokerRegistationRequest {
+                    broker_id: *broker_id,
+                    cluster_id: cluster.into(),
+                    incarnation_id: Uuid::new_v4(),
+                    listeners: vec![Listener {
+                        name: "broker".into(),
+                        host: "localhost".into(),
+                        port,
+                        security_protocol: 0,
+                    }],
+                    features: vec![],
+                    rack: None,
+                })
+                .await?;
+        }
+
+        let mut storage = DynoStore::new(cluster, brokers[0], object_store);
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: TOPIC.into(),
+                    num_partitions: 1,
+                    replication_factor: i16::try_from(brokers.len())?,
+                    assignments: Some([].into()),
+                    configs: Some([].into()),
+                },
+                false,
+            )
+            .await?;
+
+        Ok(storage)
+    }
+
+    async fn alter(
+        storage: DynoStore,
+        topic: &str,
+        partitions: &[(i32, Option<&[i32]>)],
+    ) -> Result<Vec<(i32, ErrorCode)>> {
+        let body = AlterPartitionReassignmentsRequest::with_storage(storage)
+            .response(
+                30_000,
+                Some(&[ReassignableTopic {
+                    name: topic.into(),
+                    partitions: Some(
+                        partitions
+                            .iter()
+                            .map(|(partition_index, replicas)| ReassignablePartition {
+                                partition_index: *partition_index,
+                                replicas: replicas.map(Vec::from),
+                            })
+                            .collect(),
+                    ),
+                }]),
+            )
+            .await?;
+
+        let Body::AlterPartitionReassignmentsResponse {
+            error_code,
+            responses: Some(responses),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        assert_eq!(i16::from(ErrorCode::None), error_code);
+
+        responses
+            .into_iter()
+            .flat_map(|response| response.partitions.unwrap_or_default())
+            .map(|partition| {
+                ErrorCode::try_from(partition.error_code)
+                    .map(|error_code| (partition.partition_index, error_code))
+                    .map_err(Into::into)
+            })
+            .collect()
+    }
+
+    async fn list(storage: DynoStore) -> Result<Vec<OngoingTopicReassignment>> {
+        let body = ListPartitionReassignmentsRequest::with_storage(storage)
+            .response(None)
+            .await?;
+
+        let Body::ListPartitionReassignmentsResponse {
+            topics: Some(topics),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        Ok(topics)
+    }
+
+    #[tokio::test]
+    async fn reassignment_to_placed_replicas_is_complete() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let node = 12321;
+        let storage = storage_with_brokers(&[node]).await?;
+
+        assert_eq!(
+            vec![(0, ErrorCode::None)],
+            alter(storage.clone(), TOPIC, &[(0, Some(&[node]))]).await?
+        );
+
+        assert!(list(storage.clone()).await?.is_empty());
+
+        assert_eq!(
+            vec![(0, ErrorCode::NoReassignmentInProgress)],
+            alter(storage, TOPIC, &[(0, None)]).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_reassignment() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let node = 12321;
+        let storage = storage_with_brokers(&[node]).await?;
+
+        assert_eq!(
+            vec![
+                (0, ErrorCode::InvalidReplicaAssignment),
+                (0, ErrorCode::InvalidReplicaAssignment),
+                (0, ErrorCode::InvalidReplicaAssignment),
+                (1, ErrorCode::UnknownTopicOrPartition),
+            ],
+            alter(
+                storage.clone(),
+                TOPIC,
+                &[
+                    (0, Some(&[])),
+                    (0, Some(&[node, node])),
+                    (0, Some(&[node + 1])),
+                    (1, Some(&[node])),
+                ]
+            )
+            .await?
+        );
+
+        assert_eq!(
+            vec![(0, ErrorCode::UnknownTopicOrPartition)],
+            alter(storage.clone(), "xyz", &[(0, Some(&[node]))]).await?
+        );
+
+        assert!(list(storage).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ongoing_reassignment() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let brokers = [12321, 23432];
+        let storage = storage_with_brokers(&brokers).await?;
+
+        assert_eq!(
+            vec![(0, ErrorCode::None)],
+            alter(storage.clone(), TOPIC, &[(0, Some(&brokers[..1]))]).await?
+        );
+
+        let topics = list(storage.clone()).await?;
+        assert_eq!(
+            vec![OngoingTopicReassignment {
+                name: TOPIC.into(),
+                partitions: Some(vec![OngoingPartitionReassignment {
+                    partition_index: 0,
+                    replicas: Some(brokers.into()),
+                    adding_replicas: Some([].into()),
+                    removing_replicas: Some(brokers[1..].into()),
+                }]),
+            }],
+            topics
+        );
+
+        assert_eq!(
+            vec![(0, ErrorCode::None)],
+            alter(storage.clone(), TOPIC, &[(0, None)]).await?
+        );
+
+        assert!(list(storage).await?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,374 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{broker::sasl::scram, Result};
+use std::collections::BTreeMap;
+use tansu_kafka_sans_io::{
+    alter_user_scram_credentials_request::{ScramCredentialDeletion, ScramCredentialUpsertion},
+    alter_user_scram_credentials_response::AlterUserScramCredentialsResult,
+    Body, ErrorCode,
+};
+use tansu_storage::{ScramMechanism, Storage};
+use tracing::{debug, error};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AlterUserScramCredentialsRequest<S> {
+    storage: S,
+}
+
+// the alterations of a user are only applied when all of them are valid
+fn validate(
+    name: &str,
+    mechanism: i8,
+    iterations: Option<i32>,
+    alterations: usize,
+) -> Result<ScramMechanism, (ErrorCode, String)> {
+    if name.is_empty() {
+        return Err((
+            ErrorCode::UnacceptableCredential,
+            "Username must not be empty".into(),
+        ));
+    }
+
+    let mechanism = ScramMechanism::try_from(mechanism).map_err(|_| {
+        (
+            ErrorCode::UnsupportedSaslMechanism,
+            format!("Unknown SCRAM mechanism: {mechanism}"),
+        )
+    })?;
+
+    if alterations > 1 {
+        return Err((
+            ErrorCode::DuplicateResource,
+            "A user credential cannot be altered twice in the same request".into(),
+        ));
+    }
+
+    if let Some(iterations) = iterations
+        .filter(|iterations| !(scram::MIN_ITERATIONS..=scram::MAX_ITERATIONS).contains(iterations))
+    {
+        return Err((
+            ErrorCode::UnacceptableCredential,
+            format!(
+                "Iterations {iterations} must be between {} and {}",
+                scram::MIN_ITERATIONS,
+                scram::MAX_ITERATIONS
+            ),
+        ));
+    }
+
+    Ok(mechanism)
+}
+
+impl<S> AlterUserScramCredentialsRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    pub async fn response(
+        &mut self,
+        deletions: Option<&[ScramCredentialDeletion]>,
+        upsertions: Option<&[ScramCredentialUpsertion]>,
+    ) -> Result<Body> {
+        debug!(?deletions, ?upsertions);
+
+        let deletions = deletions.unwrap_or_default();
+        let upsertions = upsertions.unwrap_or_default();
+
+        let alterations = |name: &str, mechanism: i8| {
+            deletions
+                .iter()
+                .filter(|deletion| deletion.name == name && deletion.mechanism == mechanism)
+                .count()
+                + upsertions
+                    .iter()
+                    .filter(|upsertion| upsertion.name == name && upsertion.mechanism == mechanism)
+                    .count()
+        };
+
+        let mut outcomes: BTreeMap<&str, Result<(), (ErrorCode, String)>> = BTreeMap::new();
+
+        for (name, outcome) in deletions
+            .iter()
+            .map(|deletion| {
+                (
+                    deletion.name.as_str(),
+                    validate(
+                        &deletion.name,
+                        deletion.mechanism,
+                        None,
+                        alterations(&deletion.name, deletion.mechanism),
+                    ),
+                )
+            })
+            .chain(upsertions.iter().map(|upsertion| {
+                (
+                    upsertion.name.as_str(),
+                    validate(
+                        &upsertion.name,
+                        upsertion.mechanism,
+                        Some(upsertion.iterations),
+                        alterations(&upsertion.name, upsertion.mechanism),
+                    ),
+                )
+            }))
+        {
+            let entry = outcomes.entry(name).or_insert(Ok(()));
+
+            if entry.is_ok() {
+                *entry = outcome.map(|_| ());
+            }
+        }
+
+        for (name, outcome) in outcomes.iter_mut().filter(|(_, outcome)| outcome.is_ok()) {
+            *outcome = self.alter(name, deletions, upsertions).await;
+        }
+
+        Ok(Body::AlterUserScramCredentialsResponse {
+            throttle_time_ms: 0,
+            results: Some(
+                outcomes
+                    .into_iter()
+                    .map(|(user, outcome)| {
+                        let (error_code, error_message) = match outcome {
+                            Ok(()) => (ErrorCode::None, None),
+                            Err((error_code, message)) => (error_code, Some(message)),
+                        };
+
+                        AlterUserScramCredentialsResult {
+                            user: user.to_owned(),
+                            error_code: error_code.into(),
+                            error_message,
+                        }
+                    })
+                    .collect(),
+            ),
+            unknown_tagged_fields: vec![],
+        })
+    }
+
+    async fn alter(
+        &mut self,
+        name: &str,
+        deletions: &[ScramCredentialDeletion],
+        upsertions: &[ScramCredentialUpsertion],
+    ) -> Result<(), (ErrorCode, String)> {
+        let reason = |error: crate::Error| match error {
+            crate::Error::Storage(tansu_storage::Error::Api(error_code))
+            | crate::Error::Api(error_code) => (error_code, error_code.to_string()),
+
+            error => {
+                error!(?error);
+                (ErrorCode::UnknownServerError, error.to_string())
+            }
+        };
+
+        for deletion in deletions.iter().filter(|deletion| deletion.name == name) {
+            let mechanism = ScramMechanism::try_from(deletion.mechanism)
+                .map_err(crate::Error::from)
+                .map_err(reason)?;
+
+            self.storage
+                .delete_user_scram_credential(name, mechanism)
+                .await
+                .map_err(crate::Error::from)
+                .map_err(reason)?;
+        }
+
+        for upsertion in upsertions.iter().filter(|upsertion| upsertion.name == name) {
+            let mechanism = ScramMechanism::try_from(upsertion.mechanism)
+                .map_err(crate::Error::from)
+                .map_err(reason)?;
+
+            // only the keys derived from the salted password are kept
+            let credential = scram::credential(
+                mechanism,
+                upsertion.salt.clone(),
+                upsertion.iterations,
+                &upsertion.salted_password[..],
+            )
+            .map_err(reason)?;
+
+            self.storage
+                .upsert_user_scram_credential(name, mechanism, credential)
+                .await
+                .map_err(crate::Error::from)
+                .map_err(reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broker::describe_user_scram_credentials::DescribeUserScramCredentialsRequest;
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        describe_user_scram_credentials_request::UserName,
+        describe_user_scram_credentials_response::CredentialInfo,
+    };
+    use tansu_storage::dynostore::DynoStore;
+
+    fn upsertion(
+        name: &str,
+        mechanism: ScramMechanism,
+        iterations: i32,
+    ) -> ScramCredentialUpsertion {
+        ScramCredentialUpsertion {
+            name: name.into(),
+            mechanism: mechanism.into(),
+            iterations,
+            salt: Bytes::from_static(b"salt"),
+            salted_password: Bytes::from_static(b"salted-password"),
+        }
+    }
+
+    fn errors(body: Body) -> Vec<(String, ErrorCode)> {
+        let Body::AlterUserScramCredentialsResponse {
+            results: Some(results),
+            ..
+        } = body
+        else {
+            panic!("expected an alter user scram credentials response")
+        };
+
+        results
+            .into_iter()
+            .map(|result| {
+                (
+                    result.user,
+                    ErrorCode::try_from(result.error_code).unwrap_or(ErrorCode::UnknownServerError),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn alter_and_describe() -> Result<()> {
+        let storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        assert_eq!(
+            vec![
+                ("alice".to_owned(), ErrorCode::None),
+                ("bob".to_owned(), ErrorCode::UnacceptableCredential),
+                ("carol".to_owned(), ErrorCode::DuplicateResource),
+            ],
+            errors(
+                AlterUserScramCredentialsRequest::with_storage(storage.clone())
+                    .response(
+                        None,
+                        Some(&[
+                            upsertion("alice", ScramMechanism::Sha256, 4096),
+                            upsertion("alice", ScramMechanism::Sha512, 8192),
+                            upsertion("bob", ScramMechanism::Sha256, 1024),
+                            upsertion("carol", ScramMechanism::Sha256, 4096),
+                            upsertion("carol", ScramMechanism::Sha256, 4096),
+                        ]),
+                    )
+                    .await?
+            )
+        );
+
+        let Body::DescribeUserScramCredentialsResponse {
+            results: Some(results),
+            ..
+        } = DescribeUserScramCredentialsRequest::with_storage(storage.clone())
+            .response(None)
+            .await?
+        else {
+            panic!("expected a describe user scram credentials response")
+        };
+
+        assert_eq!(1, results.len());
+        assert_eq!("alice", results[0].user);
+        assert_eq!(
+            Some(vec![
+                CredentialInfo {
+                    mechanism: 1,
+                    iterations: 4096,
+                },
+                CredentialInfo {
+                    mechanism: 2,
+                    iterations: 8192,
+                },
+            ]),
+            results[0].credential_infos
+        );
+
+        assert_eq!(
+            vec![
+                ("alice".to_owned(), ErrorCode::None),
+                ("bob".to_owned(), ErrorCode::ResourceNotFound),
+            ],
+            errors(
+                AlterUserScramCredentialsRequest::with_storage(storage.clone())
+                    .response(
+                        Some(&[
+                            ScramCredentialDeletion {
+                                name: "alice".into(),
+                                mechanism: ScramMechanism::Sha256.into(),
+                            },
+                            ScramCredentialDeletion {
+                                name: "bob".into(),
+                                mechanism: ScramMechanism::Sha256.into(),
+                            },
+                        ]),
+                        None,
+                    )
+                    .await?
+            )
+        );
+
+        let Body::DescribeUserScramCredentialsResponse {
+            results: Some(results),
+            ..
+        } = DescribeUserScramCredentialsRequest::with_storage(storage)
+            .response(Some(&[
+                UserName {
+                    name: "alice".into(),
+                },
+                UserName { name: "bob".into() },
+            ]))
+            .await?
+        else {
+            panic!("expected a describe user scram credentials response")
+        };
+
+        assert_eq!(2, results.len());
+
+        assert_eq!(i16::from(ErrorCode::None), results[0].error_code);
+        assert_eq!(
+            Some(vec![CredentialInfo {
+                mechanism: 2,
+                iterations: 8192,
+            }]),
+            results[0].credential_infos
+        );
+
+        assert_eq!("bob", results[1].user);
+        assert_eq!(
+            i16::from(ErrorCode::ResourceNotFound),
+            results[1].error_code
+        );
+
+        Ok(())
+    }
+}
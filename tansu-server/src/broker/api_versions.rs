@@ -13,20 +13,23 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use tansu_kafka_sans_io::{api_versions_response::ApiVersion, Body, ErrorCode, RootMessageMeta};
+use super::registry::Registry;
+use crate::Result;
+use tansu_kafka_sans_io::{
+    api_versions_response::ApiVersion, ApiKey, Body, ErrorCode, Frame, Header,
+};
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub struct ApiVersionsRequest;
-
-fn is_sasl(api_key: &i16) -> bool {
-    [17].contains(api_key)
-}
-
-fn is_telemetry(api_key: &i16) -> bool {
-    [71, 72, 74].contains(api_key)
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ApiVersionsRequest {
+    api_versions: Vec<(i16, i16, i16)>,
 }
 
 impl ApiVersionsRequest {
+    /// Advertise these api keys, with the minimum and maximum version of each.
+    pub fn with_api_versions(api_versions: Vec<(i16, i16, i16)>) -> Self {
+        Self { api_versions }
+    }
+
     pub fn response(
         &self,
         client_software_name: Option<&str>,
@@ -42,18 +45,221 @@ impl ApiVersionsRequest {
             zk_migration_ready: None,
             error_code: ErrorCode::None.into(),
             api_keys: Some(
-                RootMessageMeta::messages()
-                    .requests()
+                self.api_versions
                     .iter()
-                    .filter(|(api_key, _)| !(is_telemetry(api_key) || is_sasl(api_key)))
-                    .map(|(_, meta)| ApiVersion {
-                        api_key: meta.api_key,
-                        min_version: meta.version.valid.start,
-                        max_version: meta.version.valid.end,
+                    .map(|&(api_key, min_version, max_version)| ApiVersion {
+                        api_key,
+                        min_version,
+                        max_version,
                     })
                     .collect(),
             ),
             throttle_time_ms: Some(0),
+            unknown_tagged_fields: vec![],
+        }
+    }
+
+    /// The response to an ApiVersions request in a version that is not
+    /// supported, which may not be decodable. The response is v0, with the
+    /// supported versions, so that the client can retry with one of them.
+    pub fn unsupported_version<G, S>(
+        registry: &Registry<G, S>,
+        request: &[u8],
+    ) -> Result<Option<Vec<u8>>> {
+        // a request has a size, api key, api version and correlation id
+        let (Some(api_key), Some(api_version), Some(correlation_id)) =
+            (request.get(4..6), request.get(6..8), request.get(8..12))
+        else {
+            return Ok(None);
+        };
+
+        let api_key = i16::from_be_bytes([api_key[0], api_key[1]]);
+        let api_version = i16::from_be_bytes([api_version[0], api_version[1]]);
+
+        if api_key != i16::from(ApiKey::ApiVersions)
+            || registry.supports(ApiKey::ApiVersions, api_version)
+        {
+            return Ok(None);
+        }
+
+        let correlation_id = i32::from_be_bytes([
+            correlation_id[0],
+            correlation_id[1],
+            correlation_id[2],
+            correlation_id[3],
+        ]);
+
+        let Body::ApiVersionsResponse { api_keys, .. } =
+            Self::with_api_versions(registry.api_versions()).response(None, None)
+        else {
+            return Ok(None);
+        };
+
+        Frame::encode_response(
+            Header::Response { correlation_id },
+            Body::ApiVersionsResponse {
+                error_code: ErrorCode::UnsupportedVersion.into(),
+                api_keys,
+                throttle_time_ms: None,
+                supported_features: None,
+                finalized_features_epoch: None,
+                finalized_features: None,
+                zk_migration_ready: None,
+                unknown_tagged_fields: vec![],
+            },
+            ApiKey::ApiVersions,
+            0,
+        )
+        .map(Some)
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinator::group::administrator::Controller;
+    use tansu_kafka_sans_io::RootMessageMeta;
+    use tansu_storage::dynostore::DynoStore;
+
+    fn registry() -> Registry<Controller<DynoStore>, DynoStore> {
+        Registry::default()
+    }
+
+    fn advertised() -> Body {
+        ApiVersionsRequest::with_api_versions(registry().api_versions()).response(None, None)
+    }
+
+    #[test]
+    fn produce_fetch_metadata_and_group() {
+        let Body::ApiVersionsResponse {
+            api_keys: Some(api_keys),
+            ..
+        } = advertised()
+        else {
+            panic!("expected an api versions response with api keys")
+        };
+
+        for api_key in [0, 1, 3, 8, 9, 10, 11, 12, 13, 14] {
+            assert!(
+                api_keys
+                    .iter()
+                    .any(|api_version| api_version.api_key == api_key
+                        && api_version.min_version <= api_version.max_version),
+                "api_key: {api_key}"
+            );
+        }
+
+        assert!(!api_keys.iter().any(|api_version| api_version.api_key == 71));
+    }
+
+    #[test]
+    fn next_generation_consumer_group_advertised() {
+        let Body::ApiVersionsResponse {
+            api_keys: Some(api_keys),
+            ..
+        } = advertised()
+        else {
+            panic!("expected an api versions response with api keys")
+        };
+
+        for api_key in [68, 69] {
+            assert!(
+                api_keys
+                    .iter()
+                    .any(|api_version| api_version.api_key == api_key),
+                "api_key: {api_key}"
+            );
+        }
+    }
+
+    #[test]
+    fn every_advertised_api_has_a_handler() {
+        let Body::ApiVersionsResponse {
+            api_keys: Some(api_keys),
+            ..
+        } = advertised()
+        else {
+            panic!("expected an api versions response with api keys")
+        };
+
+        let registry = registry();
+
+        for api_version in api_keys {
+            let registration = ApiKey::try_from(api_version.api_key)
+                .ok()
+                .and_then(|api_key| registry.registration(api_key))
+                .expect("advertised api has a registered handler");
+
+            assert!(registration.supports(api_version.min_version));
+            assert!(registration.supports(api_version.max_version));
+        }
+    }
+
+    #[test]
+    fn unsupported_version_v0_fallback() -> Result<()> {
+        let correlation_id = 6789i32;
+
+        let mut request = vec![];
+        request.extend_from_slice(&12i32.to_be_bytes());
+        request.extend_from_slice(&i16::from(ApiKey::ApiVersions).to_be_bytes());
+        request.extend_from_slice(&i16::MAX.to_be_bytes());
+        request.extend_from_slice(&correlation_id.to_be_bytes());
+        request.extend_from_slice(&(-1i16).to_be_bytes());
+
+        let response = ApiVersionsRequest::unsupported_version(&registry(), &request)?
+            .expect("unsupported version is answered");
+
+        let Frame {
+            header:
+                Header::Response {
+                    correlation_id: response_correlation_id,
+                },
+            body:
+                Body::ApiVersionsResponse {
+                    error_code,
+                    api_keys,
+                    ..
+                },
+            ..
+        } = Frame::decode_response(&response, ApiKey::ApiVersions, 0)?
+        else {
+            panic!("expected an api versions response")
+        };
+
+        assert_eq!(correlation_id, response_correlation_id);
+        assert_eq!(i16::from(ErrorCode::UnsupportedVersion), error_code);
+        assert!(api_keys.is_some_and(|api_keys| !api_keys.is_empty()));
+
+        request[6..8].copy_from_slice(&3i16.to_be_bytes());
+        assert!(ApiVersionsRequest::unsupported_version(&registry(), &request)?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_broker_listener_apis_advertised() {
+        let Body::ApiVersionsResponse {
+            api_keys: Some(api_keys),
+            ..
+        } = advertised()
+        else {
+            panic!("expected an api versions response with api keys")
+        };
+
+        let broker = RootMessageMeta::messages().broker_requests();
+        let controller = RootMessageMeta::messages().controller_requests();
+
+        // a controller request is only advertised when it has a handler
+        for api_version in api_keys {
+            assert!(
+                broker.contains_key(&api_version.api_key)
+                    || (controller.contains_key(&api_version.api_key)
+                        && ApiKey::try_from(api_version.api_key)
+                            .is_ok_and(|api_key| registry().registration(api_key).is_some())),
+                "api_key: {}",
+                api_version.api_key
+            );
         }
     }
 }
@@ -0,0 +1,1396 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Authorization of each request before it is handled.
+//!
+//! Resources that the principal may not access are removed from a request,
+//! with an authorization failure for each of them merged into the response
+//! of the remaining resources. Requests on a single group, transaction or
+//! the cluster are answered with the failure without being handled.
+
+use crate::{
+    authorizer::{Authorizer, Principal, Resource},
+    coordinator::group::Coordinator,
+    Error, Result,
+};
+use std::sync::Arc;
+use tansu_kafka_sans_io::{
+    add_partitions_to_txn_response::{
+        AddPartitionsToTxnPartitionResult, AddPartitionsToTxnTopicResult,
+    },
+    consumer_group_describe_response,
+    create_partitions_response::CreatePartitionsTopicResult,
+    create_topics_response::CreatableTopicResult,
+    delete_records_response::{DeleteRecordsPartitionResult, DeleteRecordsTopicResult},
+    delete_topics_response::DeletableTopicResult,
+    describe_configs_response::DescribeConfigsResult,
+    describe_groups_response::DescribedGroup,
+    describe_producers_response, describe_transactions_response,
+    find_coordinator_response::Coordinator as FindCoordinator,
+    list_offsets_response::{ListOffsetsPartitionResponse, ListOffsetsTopicResponse},
+    offset_fetch_response::OffsetFetchResponseGroup,
+    primitive::uuid::Uuid,
+    AclOperation, ApiKey, Body, ConfigResource, CoordinatorType, ErrorCode,
+};
+use tansu_storage::{Storage, TopicId, NULL_TOPIC_ID};
+use tracing::debug;
+
+use super::{
+    error_response::{
+        error_response, fetch_error, metadata_error, offset_commit_error, offset_for_leader_error,
+        produce_error, txn_offset_commit_error,
+    },
+    Broker,
+};
+
+/// The response to a request that is denied, or the error when no response
+/// can be built for it.
+fn denied(cluster_id: &str, body: &Body, error_code: ErrorCode) -> Result<Body> {
+    error_response(cluster_id, body, error_code).ok_or(Error::Api(error_code))
+}
+
+/// The authorizer with the principal of a connection.
+#[derive(Clone, Debug)]
+struct Check {
+    authorizer: Arc<dyn Authorizer>,
+    principal: Principal,
+}
+
+impl Check {
+    async fn permits(&self, operation: AclOperation, resource: &Resource) -> bool {
+        let permitted = self
+            .authorizer
+            .authorize(&self.principal, operation, resource)
+            .await;
+
+        if !permitted {
+            debug!(principal = ?self.principal, ?operation, ?resource);
+        }
+
+        permitted
+    }
+
+    /// Split items into those permitted and those denied the operation.
+    async fn split<T>(
+        &self,
+        items: Option<Vec<T>>,
+        operation: AclOperation,
+        resource: impl Fn(&T) -> Option<Resource>,
+    ) -> (Option<Vec<T>>, Vec<T>) {
+        let Some(items) = items else {
+            return (None, vec![]);
+        };
+
+        let mut permitted = vec![];
+        let mut denied = vec![];
+
+        for item in items {
+            // items that can't be named are left for the handler to reject
+            if let Some(resource) = resource(&item) {
+                if !self.permits(operation, &resource).await {
+                    denied.push(item);
+                    continue;
+                }
+            }
+
+            permitted.push(item);
+        }
+
+        (Some(permitted), denied)
+    }
+}
+
+impl<G, S> Broker<G, S>
+where
+    G: Coordinator,
+    S: Storage + Clone + 'static,
+{
+    fn check(&self) -> Check {
+        Check {
+            authorizer: self.authorizer.clone(),
+            principal: Principal::new(self.principal(), self.client_host.as_deref()),
+        }
+    }
+
+    /// The name of a topic known only by its id, if it exists.
+    async fn topic_name(&mut self, topic_id: Uuid) -> Option<String> {
+        self.storage
+            .metadata(Some(&[TopicId::from(topic_id)]))
+            .await
+            .ok()
+            .and_then(|metadata| {
+                metadata
+                    .topics()
+                    .first()
+                    .filter(|topic| topic.error_code == i16::from(ErrorCode::None))
+                    .and_then(|topic| topic.name.clone())
+            })
+    }
+
+    /// Respond to a request, with each of its resources first authorized.
+    pub async fn authorized_response_for(
+        &mut self,
+        api_key: ApiKey,
+        client_id: Option<&str>,
+        mut body: Body,
+        correlation_id: i32,
+    ) -> Result<Body> {
+        let check = self.check();
+
+        let cluster_operation = match body {
+            Body::AlterPartitionReassignmentsRequest { .. }
+            | Body::AlterUserScramCredentialsRequest { .. }
+            | Body::CreateAclsRequest { .. }
+            | Body::DeleteAclsRequest { .. }
+            | Body::ElectLeadersRequest { .. }
+            | Body::UnregisterBrokerRequest { .. } => Some(AclOperation::Alter),
+
+            Body::BrokerHeartbeatRequest { .. } => Some(AclOperation::ClusterAction),
+
+            Body::AlterClientQuotasRequest { .. } => Some(AclOperation::AlterConfigs),
+            Body::DescribeClientQuotasRequest { .. } => Some(AclOperation::DescribeConfigs),
+
+            Body::DescribeAclsRequest { .. }
+            | Body::DescribeClusterRequest { .. }
+            | Body::DescribeUserScramCredentialsRequest { .. }
+            | Body::ListPartitionReassignmentsRequest { .. } => Some(AclOperation::Describe),
+
+            _ => None,
+        };
+
+        // a denied request is never handled, even without a body to answer it
+        if let Some(operation) = cluster_operation {
+            if !check.permits(operation, &Resource::cluster()).await {
+                return denied(
+                    &self.cluster_id,
+                    &body,
+                    ErrorCode::ClusterAuthorizationFailed,
+                );
+            }
+        }
+
+        match &mut body {
+            Body::ConsumerGroupHeartbeatRequest { group_id, .. }
+            | Body::HeartbeatRequest { group_id, .. }
+            | Body::JoinGroupRequest { group_id, .. }
+            | Body::LeaveGroupRequest { group_id, .. }
+            | Body::SyncGroupRequest { group_id, .. } => {
+                if !check
+                    .permits(AclOperation::Read, &Resource::group(group_id))
+                    .await
+                {
+                    return denied(&self.cluster_id, &body, ErrorCode::GroupAuthorizationFailed);
+                }
+
+                self.response_for(api_key, client_id, body, correlation_id)
+                    .await
+            }
+
+            Body::ProduceRequest {
+                transactional_id,
+                topic_data,
+                ..
+            } => {
+                let transaction_permitted = match transactional_id.as_deref() {
+                    Some(transactional_id) => {
+                        check
+                            .permits(
+                                AclOperation::Write,
+                                &Resource::transactional_id(transactional_id),
+                            )
+                            .await
+                    }
+                    None => true,
+                };
+
+                let (permitted, denied) = if transaction_permitted {
+                    check
+                        .split(topic_data.take(), AclOperation::Write, |topic| {
+                            Some(Resource::topic(&topic.name))
+                        })
+                        .await
+                } else {
+                    (Some(vec![]), topic_data.take().unwrap_or_default())
+                };
+
+                *topic_data = permitted;
+
+                let error_code = if transaction_permitted {
+                    ErrorCode::TopicAuthorizationFailed
+                } else {
+                    ErrorCode::TransactionalIdAuthorizationFailed
+                };
+
+                let denied = denied
+                    .into_iter()
+                    .map(|topic| {
+                        produce_error(
+                            topic.name,
+                            topic
+                                .partition_data
+                                .unwrap_or_default()
+                                .iter()
+                                .map(|partition| partition.index)
+                                .collect(),
+                            error_code,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::ProduceResponse { responses, .. } = &mut response {
+                    responses.get_or_insert_default().extend(denied);
+                }
+
+                Ok(response)
+            }
+
+            Body::FetchRequest { topics, .. } => {
+                let mut named = vec![];
+
+                for mut topic in topics.take().unwrap_or_default() {
+                    if topic.topic.is_none() {
+                        if let Some(topic_id) = topic.topic_id {
+                            topic.topic = self.topic_name(topic_id).await;
+                        }
+                    }
+
+                    named.push(topic);
+                }
+
+                let (permitted, denied) = check
+                    .split(Some(named), AclOperation::Read, |topic| {
+                        topic.topic.as_deref().map(Resource::topic)
+                    })
+                    .await;
+
+                *topics = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::FetchResponse { responses, .. } = &mut response {
+                    responses.get_or_insert_default().extend(
+                        denied
+                            .iter()
+                            .map(|fetch| fetch_error(fetch, ErrorCode::TopicAuthorizationFailed)),
+                    );
+                }
+
+                Ok(response)
+            }
+
+            Body::MetadataRequest { topics, .. }
+                if topics.as_ref().is_some_and(|topics| !topics.is_empty()) =>
+            {
+                let mut named = vec![];
+
+                for mut topic in topics.take().unwrap_or_default() {
+                    if topic.name.is_none() {
+                        if let Some(topic_id) = topic.topic_id {
+                            topic.name = self.topic_name(topic_id).await;
+                        }
+                    }
+
+                    named.push(topic);
+                }
+
+                let (permitted, denied) = check
+                    .split(Some(named), AclOperation::Describe, |topic| {
+                        topic.name.as_deref().map(Resource::topic)
+                    })
+                    .await;
+
+                let permitted = permitted.unwrap_or_default();
+
+                let mut response = if permitted.is_empty() {
+                    Body::MetadataResponse {
+                        throttle_time_ms: Some(0),
+                        brokers: Some([].into()),
+                        cluster_id: Some(self.cluster_id.clone()),
+                        controller_id: Some(-1),
+                        topics: Some([].into()),
+                        cluster_authorized_operations: None,
+                        unknown_tagged_fields: vec![],
+                    }
+                } else {
+                    *topics = Some(permitted);
+                    self.response_for(api_key, client_id, body, correlation_id)
+                        .await?
+                };
+
+                if let Body::MetadataResponse { topics, .. } = &mut response {
+                    topics
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|topic| {
+                            metadata_error(
+                                topic.name,
+                                topic.topic_id,
+                                ErrorCode::TopicAuthorizationFailed,
+                            )
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::MetadataRequest { .. } => {
+                // all topics are requested, only those that may be described are listed
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::MetadataResponse {
+                    topics: Some(topics),
+                    ..
+                } = &mut response
+                {
+                    let (permitted, _) = check
+                        .split(
+                            Some(std::mem::take(topics)),
+                            AclOperation::Describe,
+                            |topic| topic.name.as_deref().map(Resource::topic),
+                        )
+                        .await;
+
+                    *topics = permitted.unwrap_or_default();
+                }
+
+                Ok(response)
+            }
+
+            Body::ListOffsetsRequest { topics, .. } => {
+                let (permitted, denied) = check
+                    .split(topics.take(), AclOperation::Describe, |topic| {
+                        Some(Resource::topic(&topic.name))
+                    })
+                    .await;
+
+                *topics = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::ListOffsetsResponse { topics, .. } = &mut response {
+                    topics
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|topic| ListOffsetsTopicResponse {
+                            name: topic.name,
+                            partitions: topic.partitions.map(|partitions| {
+                                partitions
+                                    .iter()
+                                    .map(|partition| ListOffsetsPartitionResponse {
+                                        partition_index: partition.partition_index,
+                                        error_code: ErrorCode::TopicAuthorizationFailed.into(),
+                                        old_style_offsets: None,
+                                        timestamp: Some(-1),
+                                        offset: Some(-1),
+                                        leader_epoch: Some(-1),
+                                    })
+                                    .collect()
+                            }),
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::OffsetForLeaderEpochRequest { topics, .. } => {
+                let (permitted, denied) = check
+                    .split(topics.take(), AclOperation::Describe, |topic| {
+                        Some(Resource::topic(&topic.topic))
+                    })
+                    .await;
+
+                *topics = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::OffsetForLeaderEpochResponse { topics, .. } = &mut response {
+                    topics
+                        .get_or_insert_default()
+                        .extend(denied.iter().map(|topic| {
+                            offset_for_leader_error(topic, ErrorCode::TopicAuthorizationFailed)
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::CreateTopicsRequest { topics, .. } => {
+                // create on the cluster permits the creation of any topic
+                let (permitted, denied) = if check
+                    .permits(AclOperation::Create, &Resource::cluster())
+                    .await
+                {
+                    (topics.take(), vec![])
+                } else {
+                    check
+                        .split(topics.take(), AclOperation::Create, |topic| {
+                            Some(Resource::topic(&topic.name))
+                        })
+                        .await
+                };
+
+                *topics = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::CreateTopicsResponse { topics, .. } = &mut response {
+                    topics
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|topic| CreatableTopicResult {
+                            name: topic.name,
+                            topic_id: Some(NULL_TOPIC_ID),
+                            error_code: ErrorCode::TopicAuthorizationFailed.into(),
+                            error_message: None,
+                            topic_config_error_code: None,
+                            num_partitions: Some(-1),
+                            replication_factor: Some(-1),
+                            configs: Some([].into()),
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::DeleteTopicsRequest {
+                topics,
+                topic_names,
+                ..
+            } => {
+                let mut named = vec![];
+
+                for mut topic in topics.take().unwrap_or_default() {
+                    if topic.name.is_none() && topic.topic_id != NULL_TOPIC_ID {
+                        topic.name = self.topic_name(topic.topic_id).await;
+                    }
+
+                    named.push(topic);
+                }
+
+                let (permitted, denied_topics) = check
+                    .split(Some(named), AclOperation::Delete, |topic| {
+                        topic.name.as_deref().map(Resource::topic)
+                    })
+                    .await;
+
+                *topics = permitted;
+
+                let (permitted, denied_names) = check
+                    .split(topic_names.take(), AclOperation::Delete, |name| {
+                        Some(Resource::topic(name))
+                    })
+                    .await;
+
+                *topic_names = permitted;
+
+                let denied = denied_topics
+                    .into_iter()
+                    .map(|topic| (topic.name, Some(topic.topic_id)))
+                    .chain(denied_names.into_iter().map(|name| (Some(name), None)))
+                    .map(|(name, topic_id)| DeletableTopicResult {
+                        name,
+                        topic_id: topic_id.or(Some(NULL_TOPIC_ID)),
+                        error_code: ErrorCode::TopicAuthorizationFailed.into(),
+                        error_message: None,
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::DeleteTopicsResponse { responses, .. } = &mut response {
+                    responses.get_or_insert_default().extend(denied);
+                }
+
+                Ok(response)
+            }
+
+            Body::DeleteRecordsRequest { topics, .. } => {
+                let (permitted, denied) = check
+                    .split(topics.take(), AclOperation::Delete, |topic| {
+                        Some(Resource::topic(&topic.name))
+                    })
+                    .await;
+
+                *topics = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::DeleteRecordsResponse { topics, .. } = &mut response {
+                    topics
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|topic| DeleteRecordsTopicResult {
+                            name: topic.name,
+                            partitions: topic.partitions.map(|partitions| {
+                                partitions
+                                    .iter()
+                                    .map(|partition| DeleteRecordsPartitionResult {
+                                        partition_index: partition.partition_index,
+                                        low_watermark: -1,
+                                        error_code: ErrorCode::TopicAuthorizationFailed.into(),
+                                    })
+                                    .collect()
+                            }),
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::CreatePartitionsRequest { topics, .. } => {
+                let (permitted, denied) = check
+                    .split(topics.take(), AclOperation::Alter, |topic| {
+                        Some(Resource::topic(&topic.name))
+                    })
+                    .await;
+
+                *topics = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::CreatePartitionsResponse { results, .. } = &mut response {
+                    results
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|topic| CreatePartitionsTopicResult {
+                            name: topic.name,
+                            error_code: ErrorCode::TopicAuthorizationFailed.into(),
+                            error_message: None,
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::DescribeConfigsRequest { resources, .. } => {
+                // broker configuration is described with permission on the cluster
+                let resource =
+                    |resource_type: i8, name: &str| match ConfigResource::from(resource_type) {
+                        ConfigResource::Topic => Some(Resource::topic(name)),
+                        ConfigResource::Broker | ConfigResource::BrokerLogger => {
+                            Some(Resource::cluster())
+                        }
+                        _ => None,
+                    };
+
+                let (permitted, denied) = check
+                    .split(resources.take(), AclOperation::DescribeConfigs, |config| {
+                        resource(config.resource_type, &config.resource_name)
+                    })
+                    .await;
+
+                *resources = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::DescribeConfigsResponse { results, .. } = &mut response {
+                    results
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|config| {
+                            DescribeConfigsResult {
+                                error_code: if ConfigResource::from(config.resource_type)
+                                    == ConfigResource::Topic
+                                {
+                                    ErrorCode::TopicAuthorizationFailed
+                                } else {
+                                    ErrorCode::ClusterAuthorizationFailed
+                                }
+                                .into(),
+                                error_message: None,
+                                resource_type: config.resource_type,
+                                resource_name: config.resource_name,
+                                configs: Some([].into()),
+                            }
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::OffsetCommitRequest {
+                group_id, topics, ..
+            } => {
+                let (permitted, denied, error_code) = if check
+                    .permits(AclOperation::Read, &Resource::group(group_id))
+                    .await
+                {
+                    let (permitted, denied) = check
+                        .split(topics.take(), AclOperation::Read, |topic| {
+                            Some(Resource::topic(&topic.name))
+                        })
+                        .await;
+
+                    (permitted, denied, ErrorCode::TopicAuthorizationFailed)
+                } else {
+                    (
+                        Some(vec![]),
+                        topics.take().unwrap_or_default(),
+                        ErrorCode::GroupAuthorizationFailed,
+                    )
+                };
+
+                let denied = denied
+                    .into_iter()
+                    .map(|topic| {
+                        offset_commit_error(
+                            topic.name,
+                            topic
+                                .partitions
+                                .unwrap_or_default()
+                                .iter()
+                                .map(|partition| partition.partition_index),
+                            error_code,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut response = if permitted.as_ref().is_some_and(Vec::is_empty) {
+                    Body::OffsetCommitResponse {
+                        throttle_time_ms: Some(0),
+                        topics: Some([].into()),
+                        unknown_tagged_fields: vec![],
+                    }
+                } else {
+                    *topics = permitted;
+                    self.response_for(api_key, client_id, body, correlation_id)
+                        .await?
+                };
+
+                if let Body::OffsetCommitResponse { topics, .. } = &mut response {
+                    topics.get_or_insert_default().extend(denied);
+                }
+
+                Ok(response)
+            }
+
+            Body::OffsetFetchRequest {
+                group_id, groups, ..
+            } => {
+                if let Some(group_id) = group_id.as_deref() {
+                    if !check
+                        .permits(AclOperation::Describe, &Resource::group(group_id))
+                        .await
+                    {
+                        return Ok(Body::OffsetFetchResponse {
+                            throttle_time_ms: Some(0),
+                            topics: Some([].into()),
+                            error_code: Some(ErrorCode::GroupAuthorizationFailed.into()),
+                            groups: Some([].into()),
+                            unknown_tagged_fields: vec![],
+                        });
+                    }
+                }
+
+                let (permitted, denied) = check
+                    .split(groups.take(), AclOperation::Describe, |group| {
+                        Some(Resource::group(&group.group_id))
+                    })
+                    .await;
+
+                let denied = denied
+                    .into_iter()
+                    .map(|group| OffsetFetchResponseGroup {
+                        group_id: group.group_id,
+                        topics: Some([].into()),
+                        error_code: ErrorCode::GroupAuthorizationFailed.into(),
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut response = if permitted.as_ref().is_some_and(Vec::is_empty) {
+                    Body::OffsetFetchResponse {
+                        throttle_time_ms: Some(0),
+                        topics: None,
+                        error_code: None,
+                        groups: Some([].into()),
+                        unknown_tagged_fields: vec![],
+                    }
+                } else {
+                    *groups = permitted;
+                    self.response_for(api_key, client_id, body, correlation_id)
+                        .await?
+                };
+
+                // committed offsets are only fetched for topics that may be described
+                if let Body::OffsetFetchResponse { topics, groups, .. } = &mut response {
+                    if let Some(topics) = topics.as_mut() {
+                        let (permitted, _) = check
+                            .split(
+                                Some(std::mem::take(topics)),
+                                AclOperation::Describe,
+                                |topic| Some(Resource::topic(&topic.name)),
+                            )
+                            .await;
+
+                        *topics = permitted.unwrap_or_default();
+                    }
+
+                    for group in groups.iter_mut().flatten() {
+                        let (permitted, _) = check
+                            .split(group.topics.take(), AclOperation::Describe, |topic| {
+                                Some(Resource::topic(&topic.name))
+                            })
+                            .await;
+
+                        group.topics = permitted;
+                    }
+
+                    groups.get_or_insert_default().extend(denied);
+                }
+
+                Ok(response)
+            }
+
+            Body::DescribeGroupsRequest { groups, .. } => {
+                let (permitted, denied) = check
+                    .split(groups.take(), AclOperation::Describe, |group_id| {
+                        Some(Resource::group(group_id))
+                    })
+                    .await;
+
+                *groups = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::DescribeGroupsResponse { groups, .. } = &mut response {
+                    groups
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|group_id| DescribedGroup {
+                            error_code: ErrorCode::GroupAuthorizationFailed.into(),
+                            group_id,
+                            group_state: String::from(""),
+                            protocol_type: String::from(""),
+                            protocol_data: String::from(""),
+                            members: Some([].into()),
+                            authorized_operations: Some(i32::MIN),
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::ConsumerGroupDescribeRequest { group_ids, .. } => {
+                let (permitted, denied) = check
+                    .split(group_ids.take(), AclOperation::Describe, |group_id| {
+                        Some(Resource::group(group_id))
+                    })
+                    .await;
+
+                *group_ids = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::ConsumerGroupDescribeResponse { groups, .. } = &mut response {
+                    groups
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|group_id| {
+                            consumer_group_describe_response::DescribedGroup {
+                                error_code: ErrorCode::GroupAuthorizationFailed.into(),
+                                group_id,
+                                authorized_operations: i32::MIN,
+                                ..Default::default()
+                            }
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::ListGroupsRequest { .. } => {
+                // only groups that may be described are listed
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::ListGroupsResponse {
+                    groups: Some(groups),
+                    ..
+                } = &mut response
+                {
+                    let (permitted, _) = check
+                        .split(
+                            Some(std::mem::take(groups)),
+                            AclOperation::Describe,
+                            |group| Some(Resource::group(&group.group_id)),
+                        )
+                        .await;
+
+                    *groups = permitted.unwrap_or_default();
+                }
+
+                Ok(response)
+            }
+
+            Body::DescribeProducersRequest { topics, .. } => {
+                let (permitted, denied) = check
+                    .split(topics.take(), AclOperation::Read, |topic| {
+                        Some(Resource::topic(&topic.name))
+                    })
+                    .await;
+
+                *topics = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::DescribeProducersResponse { topics, .. } = &mut response {
+                    topics
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|topic| {
+                            describe_producers_response::TopicResponse {
+                                partitions: Some(
+                                    topic
+                                        .partition_indexes
+                                        .unwrap_or_default()
+                                        .into_iter()
+                                        .map(|partition_index| {
+                                            describe_producers_response::PartitionResponse {
+                                                partition_index,
+                                                error_code: ErrorCode::TopicAuthorizationFailed
+                                                    .into(),
+                                                error_message: None,
+                                                active_producers: Some([].into()),
+                                            }
+                                        })
+                                        .collect(),
+                                ),
+                                name: topic.name,
+                            }
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::DescribeTransactionsRequest {
+                transactional_ids, ..
+            } => {
+                let (permitted, denied) = check
+                    .split(
+                        transactional_ids.take(),
+                        AclOperation::Describe,
+                        |transactional_id| Some(Resource::transactional_id(transactional_id)),
+                    )
+                    .await;
+
+                *transactional_ids = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::DescribeTransactionsResponse {
+                    transaction_states, ..
+                } = &mut response
+                {
+                    transaction_states
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|transactional_id| {
+                            describe_transactions_response::TransactionState {
+                                error_code: ErrorCode::TransactionalIdAuthorizationFailed.into(),
+                                transactional_id,
+                                transaction_start_time_ms: -1,
+                                producer_id: -1,
+                                producer_epoch: -1,
+                                topics: Some([].into()),
+                                ..Default::default()
+                            }
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::ListTransactionsRequest { .. } => {
+                // only transactions that may be described are listed
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::ListTransactionsResponse {
+                    transaction_states: Some(transaction_states),
+                    ..
+                } = &mut response
+                {
+                    let (permitted, _) = check
+                        .split(
+                            Some(std::mem::take(transaction_states)),
+                            AclOperation::Describe,
+                            |txn| Some(Resource::transactional_id(&txn.transactional_id)),
+                        )
+                        .await;
+
+                    *transaction_states = permitted.unwrap_or_default();
+                }
+
+                Ok(response)
+            }
+
+            Body::FindCoordinatorRequest {
+                key,
+                key_type,
+                coordinator_keys,
+                ..
+            } => {
+                let (resource, error_code): (fn(&str) -> Resource, ErrorCode) =
+                    match key_type.map(CoordinatorType::try_from) {
+                        Some(Ok(CoordinatorType::Transaction)) => (
+                            Resource::transactional_id,
+                            ErrorCode::TransactionalIdAuthorizationFailed,
+                        ),
+                        _ => (Resource::group, ErrorCode::GroupAuthorizationFailed),
+                    };
+
+                if let Some(key) = key.as_deref() {
+                    if !check.permits(AclOperation::Describe, &resource(key)).await {
+                        return Ok(Body::FindCoordinatorResponse {
+                            throttle_time_ms: Some(0),
+                            error_code: Some(error_code.into()),
+                            error_message: None,
+                            node_id: Some(-1),
+                            host: Some(String::from("")),
+                            port: Some(-1),
+                            coordinators: Some([].into()),
+                            unknown_tagged_fields: vec![],
+                        });
+                    }
+                }
+
+                let (permitted, denied) = check
+                    .split(coordinator_keys.take(), AclOperation::Describe, |key| {
+                        Some(resource(key))
+                    })
+                    .await;
+
+                *coordinator_keys = permitted;
+
+                let mut response = self
+                    .response_for(api_key, client_id, body, correlation_id)
+                    .await?;
+
+                if let Body::FindCoordinatorResponse { coordinators, .. } = &mut response {
+                    coordinators
+                        .get_or_insert_default()
+                        .extend(denied.into_iter().map(|key| FindCoordinator {
+                            key,
+                            node_id: -1,
+                            host: String::from(""),
+                            port: -1,
+                            error_code: error_code.into(),
+                            error_message: None,
+                        }));
+                }
+
+                Ok(response)
+            }
+
+            Body::InitProducerIdRequest {
+                transactional_id, ..
+            } => {
+                // an idempotent producer without a transaction writes to the cluster
+                let (operation, resource, error_code) = match transactional_id.as_deref() {
+                    Some(transactional_id) => (
+                        AclOperation::Write,
+                        Resource::transactional_id(transactional_id),
+                        ErrorCode::TransactionalIdAuthorizationFailed,
+                    ),
+                    None => (
+                        AclOperation::IdempotentWrite,
+                        Resource::cluster(),
+                        ErrorCode::ClusterAuthorizationFailed,
+                    ),
+                };
+
+                if check.permits(operation, &resource).await {
+                    self.response_for(api_key, client_id, body, correlation_id)
+                        .await
+                } else {
+                    Ok(Body::InitProducerIdResponse {
+                        throttle_time_ms: 0,
+                        error_code: error_code.into(),
+                        producer_id: -1,
+                        producer_epoch: -1,
+                        unknown_tagged_fields: vec![],
+                    })
+                }
+            }
+
+            Body::AddPartitionsToTxnRequest {
+                v_3_and_below_transactional_id: Some(transactional_id),
+                v_3_and_below_topics,
+                ..
+            } => {
+                if check
+                    .permits(
+                        AclOperation::Write,
+                        &Resource::transactional_id(transactional_id),
+                    )
+                    .await
+                {
+                    return self
+                        .response_for(api_key, client_id, body, correlation_id)
+                        .await;
+                }
+
+                Ok(Body::AddPartitionsToTxnResponse {
+                    throttle_time_ms: 0,
+                    error_code: Some(ErrorCode::TransactionalIdAuthorizationFailed.into()),
+                    results_by_transaction: Some([].into()),
+                    results_by_topic_v_3_and_below: Some(
+                        v_3_and_below_topics
+                            .iter()
+                            .flatten()
+                            .map(|topic| AddPartitionsToTxnTopicResult {
+                                name: topic.name.clone(),
+                                results_by_partition: topic.partitions.as_ref().map(|partitions| {
+                                    partitions
+                                            .iter()
+                                            .map(|partition| AddPartitionsToTxnPartitionResult {
+                                                partition_index: *partition,
+                                                partition_error_code:
+                                                    ErrorCode::TransactionalIdAuthorizationFailed
+                                                        .into(),
+                                            })
+                                            .collect()
+                                }),
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::AddOffsetsToTxnRequest {
+                transactional_id,
+                group_id,
+                ..
+            } => {
+                let error_code = if !check
+                    .permits(
+                        AclOperation::Write,
+                        &Resource::transactional_id(transactional_id),
+                    )
+                    .await
+                {
+                    ErrorCode::TransactionalIdAuthorizationFailed
+                } else if !check
+                    .permits(AclOperation::Read, &Resource::group(group_id))
+                    .await
+                {
+                    ErrorCode::GroupAuthorizationFailed
+                } else {
+                    return self
+                        .response_for(api_key, client_id, body, correlation_id)
+                        .await;
+                };
+
+                Ok(Body::AddOffsetsToTxnResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::EndTxnRequest {
+                transactional_id, ..
+            } => {
+                // only a writer of the transaction may commit or abort it
+                if check
+                    .permits(
+                        AclOperation::Write,
+                        &Resource::transactional_id(transactional_id),
+                    )
+                    .await
+                {
+                    return self
+                        .response_for(api_key, client_id, body, correlation_id)
+                        .await;
+                }
+
+                Ok(Body::EndTxnResponse {
+                    throttle_time_ms: 0,
+                    error_code: ErrorCode::TransactionalIdAuthorizationFailed.into(),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::TxnOffsetCommitRequest {
+                transactional_id,
+                group_id,
+                topics,
+                ..
+            } => {
+                let error_code = if !check
+                    .permits(
+                        AclOperation::Write,
+                        &Resource::transactional_id(transactional_id),
+                    )
+                    .await
+                {
+                    Some(ErrorCode::TransactionalIdAuthorizationFailed)
+                } else if !check
+                    .permits(AclOperation::Read, &Resource::group(group_id))
+                    .await
+                {
+                    Some(ErrorCode::GroupAuthorizationFailed)
+                } else {
+                    None
+                };
+
+                let (permitted, denied, error_code) = match error_code {
+                    Some(error_code) => {
+                        (Some(vec![]), topics.take().unwrap_or_default(), error_code)
+                    }
+
+                    None => {
+                        let (permitted, denied) = check
+                            .split(topics.take(), AclOperation::Read, |topic| {
+                                Some(Resource::topic(&topic.name))
+                            })
+                            .await;
+
+                        (permitted, denied, ErrorCode::TopicAuthorizationFailed)
+                    }
+                };
+
+                let denied = denied
+                    .into_iter()
+                    .map(|topic| {
+                        txn_offset_commit_error(
+                            topic.name,
+                            topic
+                                .partitions
+                                .unwrap_or_default()
+                                .iter()
+                                .map(|partition| partition.partition_index),
+                            error_code,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut response = if permitted.as_ref().is_some_and(Vec::is_empty) {
+                    Body::TxnOffsetCommitResponse {
+                        throttle_time_ms: 0,
+                        topics: Some([].into()),
+                        unknown_tagged_fields: vec![],
+                    }
+                } else {
+                    *topics = permitted;
+                    self.response_for(api_key, client_id, body, correlation_id)
+                        .await?
+                };
+
+                if let Body::TxnOffsetCommitResponse { topics, .. } = &mut response {
+                    topics.get_or_insert_default().extend(denied);
+                }
+
+                Ok(response)
+            }
+
+            _ => {
+                self.response_for(api_key, client_id, body, correlation_id)
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        authorizer::AclAuthorizer,
+        broker::listener::ListenerConfig,
+        coordinator::group::administrator::Controller,
+        fixture::{ongoing, storage_with_topic, CLUSTER, NODE},
+    };
+    use tansu_kafka_sans_io::offset_for_leader_epoch_request::{
+        OffsetForLeaderPartition, OffsetForLeaderTopic,
+    };
+    use tansu_storage::{dynostore::DynoStore, Topition};
+    use url::Url;
+
+    const TOPIC: &str = "pqr";
+    const TRANSACTIONAL_ID: &str = "xyz";
+
+    fn broker(storage: DynoStore) -> Result<Broker<Controller<DynoStore>, DynoStore>> {
+        let listener = Url::parse("tcp://localhost:9092")?;
+
+        Ok(Broker::new(
+            NODE,
+            CLUSTER,
+            vec![ListenerConfig::new("broker", listener.clone(), &listener)],
+            None,
+            storage.clone(),
+            Controller::with_storage(storage)?,
+        ))
+    }
+
+    async fn end_txn(
+        broker: &mut Broker<Controller<DynoStore>, DynoStore>,
+        producer_id: i64,
+    ) -> Result<ErrorCode> {
+        let body = broker
+            .authorized_response_for(
+                ApiKey::EndTxn,
+                None,
+                Body::EndTxnRequest {
+                    transactional_id: TRANSACTIONAL_ID.into(),
+                    producer_id,
+                    producer_epoch: 0,
+                    committed: true,
+                    unknown_tagged_fields: vec![],
+                },
+                1,
+            )
+            .await?;
+
+        let Body::EndTxnResponse { error_code, .. } = body else {
+            panic!("{body:?}")
+        };
+
+        ErrorCode::try_from(error_code).map_err(Into::into)
+    }
+
+    #[test]
+    fn denied_without_a_response() {
+        // a body that has no error response is refused rather than handled
+        let body = Body::HeartbeatResponse {
+            throttle_time_ms: Some(0),
+            error_code: ErrorCode::None.into(),
+            unknown_tagged_fields: vec![],
+        };
+
+        assert!(matches!(
+            denied(CLUSTER, &body, ErrorCode::ClusterAuthorizationFailed),
+            Err(Error::Api(ErrorCode::ClusterAuthorizationFailed))
+        ));
+    }
+
+    #[tokio::test]
+    async fn offset_for_leader_epoch_requires_describe() -> Result<()> {
+        let storage = storage_with_topic(TOPIC, 1).await?;
+
+        let mut denied =
+            broker(storage.clone())?.with_authorizer(AclAuthorizer::with_storage(storage));
+
+        let body = denied
+            .authorized_response_for(
+                ApiKey::OffsetForLeaderEpoch,
+                None,
+                Body::OffsetForLeaderEpochRequest {
+                    replica_id: Some(-1),
+                    topics: Some(vec![OffsetForLeaderTopic {
+                        topic: TOPIC.into(),
+                        partitions: Some(vec![OffsetForLeaderPartition {
+                            partition: 0,
+                            current_leader_epoch: Some(-1),
+                            leader_epoch: 0,
+                        }]),
+                    }]),
+                    unknown_tagged_fields: vec![],
+                },
+                1,
+            )
+            .await?;
+
+        let Body::OffsetForLeaderEpochResponse {
+            topics: Some(topics),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        assert_eq!(
+            vec![(
+                String::from(TOPIC),
+                0,
+                i16::from(ErrorCode::TopicAuthorizationFailed),
+                -1
+            )],
+            topics
+                .iter()
+                .flat_map(|topic| {
+                    topic.partitions.iter().flatten().map(|partition| {
+                        (
+                            topic.topic.clone(),
+                            partition.partition,
+                            partition.error_code,
+                            partition.end_offset,
+                        )
+                    })
+                })
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn end_txn_requires_write_on_transactional_id() -> Result<()> {
+        let mut storage = storage_with_topic(TOPIC, 1).await?;
+        let producer_id = ongoing(&storage, TRANSACTIONAL_ID, TOPIC).await?;
+        let topition = Topition::new(TOPIC, 0);
+
+        let high_watermark = storage.offset_stage(&topition).await?.high_watermark();
+
+        // without any acl the anonymous principal is denied
+        let mut denied =
+            broker(storage.clone())?.with_authorizer(AclAuthorizer::with_storage(storage.clone()));
+
+        assert_eq!(
+            ErrorCode::TransactionalIdAuthorizationFailed,
+            end_txn(&mut denied, producer_id).await?
+        );
+
+        assert_eq!(
+            high_watermark,
+            storage.offset_stage(&topition).await?.high_watermark()
+        );
+
+        let mut permitted = broker(storage.clone())?;
+        assert_eq!(ErrorCode::None, end_txn(&mut permitted, producer_id).await?);
+
+        // the commit marker is written when the producer is permitted
+        assert_eq!(
+            high_watermark + 1,
+            storage.offset_stage(&topition).await?.high_watermark()
+        );
+
+        Ok(())
+    }
+}
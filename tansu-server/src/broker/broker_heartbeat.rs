@@ -0,0 +1,177 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Result;
+use tansu_kafka_sans_io::{Body, ErrorCode};
+use tansu_storage::Storage;
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BrokerHeartbeatRequest<S> {
+    storage: S,
+}
+
+impl<S> BrokerHeartbeatRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    pub async fn response(
+        &mut self,
+        broker_id: i32,
+        broker_epoch: i64,
+        want_fence: bool,
+        want_shut_down: bool,
+    ) -> Result<Body> {
+        debug!(?broker_id, ?broker_epoch, ?want_fence, ?want_shut_down);
+
+        // a broker shutting down is fenced, so that it isn't advertised
+        let error_code = self
+            .storage
+            .broker_heartbeat(broker_id, broker_epoch, want_fence || want_shut_down)
+            .await?;
+
+        let registered = error_code == ErrorCode::None;
+
+        Ok(Body::BrokerHeartbeatResponse {
+            throttle_time_ms: 0,
+            error_code: error_code.into(),
+            is_caught_up: registered,
+            is_fenced: !registered || want_fence || want_shut_down,
+            should_shut_down: registered && want_shut_down,
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::broker_registration_request::Listener;
+    use tansu_storage::{dynostore::DynoStore, BrokerRegistationRequest};
+    use tracing::subscriber::DefaultGuard;
+    use uuid::Uuid;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn fence_and_unfence() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let cluster = "abc";
+        let node = 12321;
+
+        let mut storage = DynoStore::new(cluster, node, InMemory::new());
+
+        let broker_epoch = storage
+            .register_broker(BrokerRegistationRequest {
+                broker_id: node,
+                cluster_id: cluster.into(),
+                incarnation_id: Uuid::new_v4(),
+                listeners: vec![Listener {
+                    name: "broker".into(),
+                    host: "localhost".into(),
+                    port: 9092,
+                    security_protocol: 0,
+                }],
+                features: vec![],
+                rack: None,
+            })
+            .await?;
+
+        let mut request = BrokerHeartbeatRequest::with_storage(storage.clone());
+
+        assert!(matches!(
+            request.response(node, broker_epoch, true, false).await?,
+            Body::BrokerHeartbeatResponse {
+                error_code: 0,
+                is_fenced: true,
+                should_shut_down: false,
+                ..
+            }
+        ));
+        assert!(storage.metadata(None).await?.brokers().is_empty());
+
+        assert!(matches!(
+            request.response(node, broker_epoch, false, false).await?,
+            Body::BrokerHeartbeatResponse {
+                error_code: 0,
+                is_caught_up: true,
+                is_fenced: false,
+                ..
+            }
+        ));
+        assert_eq!(1, storage.metadata(None).await?.brokers().len());
+
+        assert!(matches!(
+            request.response(node, broker_epoch, false, true).await?,
+            Body::BrokerHeartbeatResponse {
+                error_code: 0,
+                is_fenced: true,
+                should_shut_down: true,
+                ..
+            }
+        ));
+
+        let stale = i16::from(ErrorCode::StaleBrokerEpoch);
+        assert!(matches!(
+            request.response(node, broker_epoch - 1, false, false).await?,
+            Body::BrokerHeartbeatResponse {
+                error_code,
+                is_fenced: true,
+                should_shut_down: false,
+                ..
+            } if error_code == stale
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Result;
+use tansu_kafka_sans_io::{
+    create_acls_request::AclCreation, create_acls_response::AclCreationResult, AclOperation,
+    AclPermissionType, Body, ErrorCode, PatternType, ResourceType,
+};
+use tansu_storage::{AclBinding, Storage};
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CreateAclsRequest<S> {
+    storage: S,
+}
+
+fn binding(creation: &AclCreation) -> Result<AclBinding, String> {
+    let pattern_type = creation
+        .resource_pattern_type
+        .unwrap_or(i8::from(PatternType::Literal));
+
+    if !matches!(
+        ResourceType::try_from(creation.resource_type),
+        Ok(ResourceType::Topic
+            | ResourceType::Group
+            | ResourceType::Cluster
+            | ResourceType::TransactionalId
+            | ResourceType::DelegationToken
+            | ResourceType::User)
+    ) {
+        return Err(format!("invalid resource type: {}", creation.resource_type));
+    }
+
+    if !matches!(
+        PatternType::try_from(pattern_type),
+        Ok(PatternType::Literal | PatternType::Prefixed)
+    ) {
+        return Err(format!("invalid pattern type: {pattern_type}"));
+    }
+
+    if matches!(
+        AclOperation::try_from(creation.operation),
+        Ok(AclOperation::Unknown | AclOperation::Any) | Err(_)
+    ) {
+        return Err(format!("invalid operation: {}", creation.operation));
+    }
+
+    if !matches!(
+        AclPermissionType::try_from(creation.permission_type),
+        Ok(AclPermissionType::Allow | AclPermissionType::Deny)
+    ) {
+        return Err(format!(
+            "invalid permission type: {}",
+            creation.permission_type
+        ));
+    }
+
+    // principals have a type and a name, e.g., "User:alice"
+    if creation
+        .principal
+        .split_once(':')
+        .is_none_or(|(principal_type, name)| principal_type.is_empty() || name.is_empty())
+    {
+        return Err(format!("invalid principal: {}", creation.principal));
+    }
+
+    if creation.resource_name.is_empty() {
+        return Err(String::from("empty resource name"));
+    }
+
+    Ok(AclBinding {
+        resource_type: creation.resource_type,
+        resource_name: creation.resource_name.clone(),
+        pattern_type,
+        principal: creation.principal.clone(),
+        host: creation.host.clone(),
+        operation: creation.operation,
+        permission_type: creation.permission_type,
+    })
+}
+
+impl<S> CreateAclsRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    pub async fn response(&mut self, creations: Option<&[AclCreation]>) -> Result<Body> {
+        debug!(?creations);
+
+        let mut results = vec![];
+
+        for creation in creations.unwrap_or_default() {
+            let result = match binding(creation) {
+                Ok(binding) => self.storage.create_acls(&[binding]).await.map_or_else(
+                    |error| AclCreationResult {
+                        error_code: ErrorCode::UnknownServerError.into(),
+                        error_message: Some(error.to_string()),
+                    },
+                    |()| AclCreationResult {
+                        error_code: ErrorCode::None.into(),
+                        error_message: None,
+                    },
+                ),
+
+                Err(error_message) => AclCreationResult {
+                    error_code: ErrorCode::InvalidRequest.into(),
+                    error_message: Some(error_message),
+                },
+            };
+
+            results.push(result);
+        }
+
+        Ok(Body::CreateAclsResponse {
+            throttle_time_ms: 0,
+            results: Some(results),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
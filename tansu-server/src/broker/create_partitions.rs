@@ -0,0 +1,310 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Result;
+use tansu_kafka_sans_io::{
+    create_partitions_request::CreatePartitionsTopic,
+    create_partitions_response::CreatePartitionsTopicResult, Body, ErrorCode,
+};
+use tansu_storage::{Storage, TopicId};
+use tracing::{debug, error, warn};
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CreatePartitionsRequest<S> {
+    storage: S,
+}
+
+impl<S> CreatePartitionsRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    async fn create_partitions(
+        &mut self,
+        topic: &CreatePartitionsTopic,
+        validate_only: bool,
+    ) -> CreatePartitionsTopicResult {
+        debug!(?topic, ?validate_only);
+
+        if let Some(assignments) = topic
+            .assignments
+            .as_deref()
+            .filter(|assignments| !assignments.is_empty())
+        {
+            // partition leaders are placed on the registered brokers by metadata
+            warn!(
+                name = topic.name.as_str(),
+                ?assignments,
+                "ignoring assignments"
+            );
+        }
+
+        let error_code = match self
+            .storage
+            .create_partitions(
+                &TopicId::Name(topic.name.clone()),
+                topic.count,
+                validate_only,
+            )
+            .await
+        {
+            Ok(()) => ErrorCode::None,
+
+            Err(tansu_storage::Error::Api(error_code)) => error_code,
+
+            Err(error) => {
+                error!(?error);
+                ErrorCode::UnknownServerError
+            }
+        };
+
+        CreatePartitionsTopicResult {
+            name: topic.name.clone(),
+            error_code: error_code.into(),
+            error_message: (error_code != ErrorCode::None).then(|| error_code.to_string()),
+        }
+    }
+
+    pub async fn response(
+        &mut self,
+        topics: Option<&[CreatePartitionsTopic]>,
+        timeout_ms: i32,
+        validate_only: bool,
+    ) -> Result<Body> {
+        debug!(?topics, ?timeout_ms, ?validate_only);
+
+        let topics = topics.unwrap_or_default();
+
+        let mut results = Vec::with_capacity(topics.len());
+
+        for topic in topics {
+            if topics
+                .iter()
+                .filter(|candidate| candidate.name == topic.name)
+                .count()
+                > 1
+            {
+                let error_code = ErrorCode::InvalidRequest;
+
+                results.push(CreatePartitionsTopicResult {
+                    name: topic.name.clone(),
+                    error_code: error_code.into(),
+                    error_message: Some(format!("duplicate topic: {}", topic.name)),
+                });
+
+                continue;
+            }
+
+            results.push(self.create_partitions(topic, validate_only).await);
+        }
+
+        Ok(Body::CreatePartitionsResponse {
+            throttle_time_ms: 0,
+            results: Some(results),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{broker::metadata::MetadataRequest, fixture::storage_with_topic, Error};
+    use bytes::Bytes;
+    use tansu_kafka_sans_io::{
+        metadata_request::MetadataRequestTopic,
+        record::{deflated, inflated, Record},
+        Ack,
+    };
+    use tansu_storage::{dynostore::DynoStore, Topition};
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    async fn partitions(storage: DynoStore, topic: &str) -> Result<Vec<i32>> {
+        let body = MetadataRequest::with_storage(storage)
+            .response(
+                Some(vec![MetadataRequestTopic {
+                    topic_id: None,
+                    name: Some(topic.into()),
+                }]),
+                None,
+            )
+            .await?;
+
+        let Body::MetadataResponse {
+            topics: Some(topics),
+            ..
+        } = body
+        else {
+            return Ok(vec![]);
+        };
+
+        Ok(topics
+            .into_iter()
+            .flat_map(|topic| topic.partitions.unwrap_or_default())
+            .map(|partition| partition.partition_index)
+            .collect())
+    }
+
+    fn error_codes(body: Body) -> Result<Vec<(String, ErrorCode)>> {
+        let Body::CreatePartitionsResponse {
+            results: Some(results),
+            ..
+        } = body
+        else {
+            return Ok(vec![]);
+        };
+
+        results
+            .into_iter()
+            .map(|result| {
+                ErrorCode::try_from(result.error_code)
+                    .map(|error_code| (result.name, error_code))
+                    .map_err(Into::into)
+            })
+            .collect()
+    }
+
+    fn create(name: &str, count: i32) -> CreatePartitionsTopic {
+        CreatePartitionsTopic {
+            name: name.into(),
+            count,
+            assignments: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn consumer_sees_new_partitions() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let mut storage = storage_with_topic(topic, 1).await?;
+
+        assert_eq!(vec![0], partitions(storage.clone(), topic).await?);
+
+        let body = CreatePartitionsRequest::with_storage(storage.clone())
+            .response(Some(&[create(topic, 3)]), 5_000, true)
+            .await?;
+        assert_eq!(vec![(topic.into(), ErrorCode::None)], error_codes(body)?);
+        assert_eq!(vec![0], partitions(storage.clone(), topic).await?);
+
+        let body = CreatePartitionsRequest::with_storage(storage.clone())
+            .response(Some(&[create(topic, 3)]), 5_000, false)
+            .await?;
+        assert_eq!(vec![(topic.into(), ErrorCode::None)], error_codes(body)?);
+
+        // a consumer refreshing metadata finds, and can read from, the new partitions
+        let refreshed = partitions(storage.clone(), topic).await?;
+        assert_eq!(vec![0, 1, 2], refreshed);
+
+        for partition in refreshed {
+            let topition = Topition::new(topic, partition);
+
+            let batch = inflated::Batch::builder()
+                .record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+                .build()
+                .and_then(deflated::Batch::try_from)?;
+
+            let offset = storage.produce(&topition, batch, Ack::FullIsr).await?;
+            assert_eq!(0, offset);
+
+            let fetched = storage.fetch(&topition, offset, 1, 1_024).await?;
+            assert_eq!(1, fetched.record_count);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_partitions() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let storage = storage_with_topic(topic, 3).await?;
+
+        for count in [2, 3] {
+            let body = CreatePartitionsRequest::with_storage(storage.clone())
+                .response(Some(&[create(topic, count)]), 5_000, false)
+                .await?;
+
+            assert_eq!(
+                vec![(topic.into(), ErrorCode::InvalidPartitions)],
+                error_codes(body)?
+            );
+        }
+
+        let body = CreatePartitionsRequest::with_storage(storage.clone())
+            .response(
+                Some(&[
+                    create(topic, 4),
+                    create("abc", 6),
+                    create("xyz", 6),
+                    create("xyz", 9),
+                ]),
+                5_000,
+                false,
+            )
+            .await?;
+
+        assert_eq!(
+            vec![
+                (topic.into(), ErrorCode::None),
+                ("abc".into(), ErrorCode::UnknownTopicOrPartition),
+                ("xyz".into(), ErrorCode::InvalidRequest),
+                ("xyz".into(), ErrorCode::InvalidRequest),
+            ],
+            error_codes(body)?
+        );
+
+        assert_eq!(vec![0, 1, 2, 3], partitions(storage, topic).await?);
+
+        Ok(())
+    }
+}
@@ -14,15 +14,19 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::Result;
+use std::collections::BTreeMap;
 use tansu_kafka_sans_io::{
-    create_topics_request::CreatableTopic, create_topics_response::CreatableTopicResult, ErrorCode,
+    create_topics_request::{CreatableTopic, CreateableTopicConfig},
+    create_topics_response::CreatableTopicResult,
+    ErrorCode,
 };
-use tansu_storage::Storage;
+use tansu_storage::{Storage, NULL_TOPIC_ID};
 use tracing::debug;
 
 #[derive(Clone, Debug)]
 pub struct CreateTopic<S> {
     storage: S,
+    defaults: BTreeMap<String, String>,
 }
 
 impl<S> CreateTopic<S>
@@ -30,7 +34,15 @@ where
     S: Storage,
 {
     pub fn with_storage(storage: S) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            defaults: BTreeMap::new(),
+        }
+    }
+
+    /// Configure topics with these defaults, unless given when created.
+    pub fn with_defaults(self, defaults: BTreeMap<String, String>) -> Self {
+        Self { defaults, ..self }
     }
 
     async fn create_topic(
@@ -48,6 +60,17 @@ where
             topic.replication_factor = 3
         }
 
+        let configs = topic.configs.get_or_insert_with(Vec::new);
+
+        for (name, value) in &self.defaults {
+            if configs.iter().all(|config| &config.name != name) {
+                configs.push(CreateableTopicConfig {
+                    name: name.clone(),
+                    value: Some(value.clone()),
+                });
+            }
+        }
+
         let name = topic.name.clone();
         let num_partitions = Some(topic.num_partitions);
         let replication_factor = Some(topic.replication_factor);
@@ -58,7 +81,7 @@ where
 
                 CreatableTopicResult {
                     name,
-                    topic_id: Some(topic_id.into_bytes()),
+                    topic_id: Some(topic_id.into()),
                     error_code: ErrorCode::None.into(),
                     error_message: None,
                     topic_config_error_code: Some(ErrorCode::None.into()),
@@ -70,7 +93,7 @@ where
 
             Err(tansu_storage::Error::Api(error_code)) => CreatableTopicResult {
                 name,
-                topic_id: Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                topic_id: Some(NULL_TOPIC_ID),
                 error_code: error_code.into(),
                 error_message: Some(error_code.to_string()),
                 topic_config_error_code: None,
@@ -84,7 +107,7 @@ where
 
                 CreatableTopicResult {
                     name,
-                    topic_id: Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+                    topic_id: Some(NULL_TOPIC_ID),
                     error_code: ErrorCode::UnknownServerError.into(),
                     error_message: None,
                     topic_config_error_code: None,
@@ -261,4 +284,42 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn create_with_topic_defaults() -> Result<()> {
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        let mut create_topic = CreateTopic::with_storage(storage.clone()).with_defaults(
+            [
+                ("cleanup.policy".into(), "compact".into()),
+                ("retention.ms".into(), "60000".into()),
+            ]
+            .into(),
+        );
+
+        let r = create_topic
+            .response(
+                Some(vec![CreatableTopic {
+                    name: "pqr".into(),
+                    num_partitions: 1,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some(vec![CreateableTopicConfig {
+                        name: "retention.ms".into(),
+                        value: Some("1000".into()),
+                    }]),
+                }]),
+                false,
+            )
+            .await?;
+
+        assert_eq!(ErrorCode::None, ErrorCode::try_from(r[0].error_code)?);
+
+        let configs = storage.topic_config(&"pqr".into()).await?;
+
+        assert_eq!(Some(&Some("compact".into())), configs.get("cleanup.policy"));
+        assert_eq!(Some(&Some("1000".into())), configs.get("retention.ms"));
+
+        Ok(())
+    }
 }
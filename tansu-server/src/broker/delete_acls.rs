@@ -0,0 +1,206 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{authorizer::AclFilter, Result};
+use tansu_kafka_sans_io::{
+    delete_acls_request::DeleteAclsFilter,
+    delete_acls_response::{DeleteAclsFilterResult, DeleteAclsMatchingAcl},
+    Body, ErrorCode, PatternType,
+};
+use tansu_storage::Storage;
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DeleteAclsRequest<S> {
+    storage: S,
+}
+
+impl From<&DeleteAclsFilter> for AclFilter {
+    fn from(value: &DeleteAclsFilter) -> Self {
+        Self {
+            resource_type: value.resource_type_filter,
+            resource_name: value.resource_name_filter.clone(),
+            pattern_type: value
+                .pattern_type_filter
+                .unwrap_or(i8::from(PatternType::Literal)),
+            principal: value.principal_filter.clone(),
+            host: value.host_filter.clone(),
+            operation: value.operation,
+            permission_type: value.permission_type,
+        }
+    }
+}
+
+impl<S> DeleteAclsRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    pub async fn response(&mut self, filters: Option<&[DeleteAclsFilter]>) -> Result<Body> {
+        debug!(?filters);
+
+        let acls = self.storage.acls().await?;
+
+        let mut deletions = vec![];
+        let mut filter_results = vec![];
+
+        for filter in filters.unwrap_or_default().iter().map(AclFilter::from) {
+            let matching = acls
+                .iter()
+                .filter(|binding| filter.matches(binding))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            filter_results.push(DeleteAclsFilterResult {
+                error_code: ErrorCode::None.into(),
+                error_message: None,
+                matching_acls: Some(
+                    matching
+                        .iter()
+                        .map(|binding| DeleteAclsMatchingAcl {
+                            error_code: ErrorCode::None.into(),
+                            error_message: None,
+                            resource_type: binding.resource_type,
+                            resource_name: binding.resource_name.clone(),
+                            pattern_type: Some(binding.pattern_type),
+                            principal: binding.principal.clone(),
+                            host: binding.host.clone(),
+                            operation: binding.operation,
+                            permission_type: binding.permission_type,
+                        })
+                        .collect(),
+                ),
+            });
+
+            deletions.extend(matching);
+        }
+
+        self.storage.delete_acls(&deletions).await?;
+
+        Ok(Body::DeleteAclsResponse {
+            throttle_time_ms: 0,
+            filter_results: Some(filter_results),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broker::{create_acls::CreateAclsRequest, describe_acls::DescribeAclsRequest};
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        create_acls_request::AclCreation, AclOperation, AclPermissionType, ResourceType,
+    };
+    use tansu_storage::dynostore::DynoStore;
+
+    #[tokio::test]
+    async fn create_describe_delete() -> Result<()> {
+        let storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        let creation = AclCreation {
+            resource_type: ResourceType::Topic.into(),
+            resource_name: "orders".into(),
+            resource_pattern_type: Some(PatternType::Literal.into()),
+            principal: "User:alice".into(),
+            host: "*".into(),
+            operation: AclOperation::Read.into(),
+            permission_type: AclPermissionType::Allow.into(),
+        };
+
+        let invalid = AclCreation {
+            principal: "alice".into(),
+            ..creation.clone()
+        };
+
+        let Body::CreateAclsResponse { results, .. } =
+            CreateAclsRequest::with_storage(storage.clone())
+                .response(Some(&[creation, invalid]))
+                .await?
+        else {
+            panic!("expecting create acls response")
+        };
+
+        assert_eq!(
+            vec![ErrorCode::None, ErrorCode::InvalidRequest],
+            results
+                .unwrap_or_default()
+                .into_iter()
+                .map(|result| ErrorCode::try_from(result.error_code))
+                .collect::<Result<Vec<_>, _>>()?
+        );
+
+        let any = AclFilter {
+            resource_type: ResourceType::Any.into(),
+            pattern_type: PatternType::Any.into(),
+            operation: AclOperation::Any.into(),
+            permission_type: AclPermissionType::Any.into(),
+            ..Default::default()
+        };
+
+        let Body::DescribeAclsResponse { resources, .. } =
+            DescribeAclsRequest::with_storage(storage.clone())
+                .response(&any)
+                .await?
+        else {
+            panic!("expecting describe acls response")
+        };
+
+        let resources = resources.unwrap_or_default();
+        assert_eq!(1, resources.len());
+        assert_eq!("orders", resources[0].resource_name);
+        assert_eq!(1, resources[0].acls.as_ref().map_or(0, Vec::len));
+
+        let Body::DeleteAclsResponse { filter_results, .. } =
+            DeleteAclsRequest::with_storage(storage.clone())
+                .response(Some(&[DeleteAclsFilter {
+                    resource_type_filter: ResourceType::Topic.into(),
+                    resource_name_filter: Some("orders".into()),
+                    pattern_type_filter: Some(PatternType::Literal.into()),
+                    principal_filter: None,
+                    host_filter: None,
+                    operation: AclOperation::Any.into(),
+                    permission_type: AclPermissionType::Any.into(),
+                }]))
+                .await?
+        else {
+            panic!("expecting delete acls response")
+        };
+
+        assert_eq!(
+            1,
+            filter_results.unwrap_or_default()[0]
+                .matching_acls
+                .as_ref()
+                .map_or(0, Vec::len)
+        );
+
+        let Body::DescribeAclsResponse { resources, .. } =
+            DescribeAclsRequest::with_storage(storage)
+                .response(&any)
+                .await?
+        else {
+            panic!("expecting describe acls response")
+        };
+
+        assert_eq!(Some(vec![]), resources);
+
+        Ok(())
+    }
+}
@@ -13,9 +13,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::Result;
-use tansu_kafka_sans_io::{delete_records_request::DeleteRecordsTopic, Body};
-use tansu_storage::Storage;
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{Error, Result};
+use tansu_kafka_sans_io::{
+    delete_records_request::{DeleteRecordsPartition, DeleteRecordsTopic},
+    delete_records_response::{DeleteRecordsPartitionResult, DeleteRecordsTopicResult},
+    Body, ErrorCode,
+};
+use tansu_storage::{Storage, TopicId, Topition};
+use tokio::time::timeout;
 use tracing::debug;
 
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -31,17 +38,391 @@ where
         Self { storage }
     }
 
-    pub async fn request(&mut self, topics: &[DeleteRecordsTopic]) -> Result<Body> {
-        let topics = self
+    fn error(partition_index: i32, error_code: ErrorCode) -> DeleteRecordsPartitionResult {
+        DeleteRecordsPartitionResult {
+            partition_index,
+            low_watermark: -1,
+            error_code: error_code.into(),
+        }
+    }
+
+    // the offset to delete before, with -1 being the high watermark, or an
+    // error when the topition is unknown or the offset is out of range
+    async fn validate(
+        &mut self,
+        topic: &str,
+        partition_count: Option<usize>,
+        partition: &DeleteRecordsPartition,
+    ) -> Result<i64, ErrorCode> {
+        let known = partition_count.is_some_and(|count| {
+            usize::try_from(partition.partition_index).is_ok_and(|index| index < count)
+        });
+
+        if !known {
+            return Err(ErrorCode::UnknownTopicOrPartition);
+        }
+
+        let high_watermark = self
             .storage
-            .delete_records(topics)
+            .offset_stage(&Topition::new(topic, partition.partition_index))
+            .await
+            .map(|offset_stage| offset_stage.high_watermark())
+            .map_err(|error| match error {
+                tansu_storage::Error::Api(error_code) => error_code,
+                _ => ErrorCode::UnknownServerError,
+            })?;
+
+        match partition.offset {
+            -1 => Ok(high_watermark),
+            offset if (0..=high_watermark).contains(&offset) => Ok(offset),
+            _ => Err(ErrorCode::OffsetOutOfRange),
+        }
+    }
+
+    async fn partition_count(&mut self, topic: &str) -> Result<Option<usize>> {
+        self.storage
+            .metadata(Some(&[TopicId::from(topic)]))
             .await
-            .inspect_err(|err| debug!(?err, ?topics))
-            .map(Some)?;
+            .map(|metadata| {
+                metadata
+                    .topics()
+                    .iter()
+                    .find(|response| {
+                        response.error_code == i16::from(ErrorCode::None)
+                            && response.name.as_deref() == Some(topic)
+                    })
+                    .map(|response| response.partitions.as_ref().map_or(0, Vec::len))
+            })
+            .map_err(Into::into)
+    }
+
+    pub async fn request(
+        &mut self,
+        topics: &[DeleteRecordsTopic],
+        timeout_ms: i32,
+    ) -> Result<Body> {
+        debug!(?topics, timeout_ms);
+
+        let mut results: BTreeMap<(String, i32), DeleteRecordsPartitionResult> = BTreeMap::new();
+        let mut valid = vec![];
+
+        for topic in topics {
+            let partition_count = self.partition_count(&topic.name).await?;
+            let mut partitions = vec![];
+
+            for partition in topic.partitions.as_deref().unwrap_or_default() {
+                match self.validate(&topic.name, partition_count, partition).await {
+                    Ok(offset) => partitions.push(DeleteRecordsPartition {
+                        partition_index: partition.partition_index,
+                        offset,
+                    }),
+
+                    Err(error_code) => {
+                        debug!(topic = topic.name, ?partition, ?error_code);
+
+                        _ = results.insert(
+                            (topic.name.clone(), partition.partition_index),
+                            Self::error(partition.partition_index, error_code),
+                        );
+                    }
+                }
+            }
+
+            if !partitions.is_empty() {
+                valid.push(DeleteRecordsTopic {
+                    name: topic.name.clone(),
+                    partitions: Some(partitions),
+                });
+            }
+        }
+
+        if !valid.is_empty() {
+            let deadline = Duration::from_millis(u64::try_from(timeout_ms).unwrap_or_default());
+
+            let deleted = match timeout(deadline, self.storage.delete_records(&valid)).await {
+                Ok(Ok(deleted)) => Ok(deleted),
+                Ok(Err(tansu_storage::Error::Api(error_code))) => Err(error_code),
+                Ok(Err(error)) => return Err(Error::from(error)),
+                Err(elapsed) => {
+                    debug!(?elapsed, timeout_ms);
+                    Err(ErrorCode::RequestTimedOut)
+                }
+            };
+
+            match deleted {
+                Ok(deleted) => {
+                    for topic in deleted {
+                        for partition in topic.partitions.unwrap_or_default() {
+                            _ = results
+                                .insert((topic.name.clone(), partition.partition_index), partition);
+                        }
+                    }
+                }
+
+                Err(error_code) => {
+                    for topic in &valid {
+                        for partition in topic.partitions.as_deref().unwrap_or_default() {
+                            _ = results.insert(
+                                (topic.name.clone(), partition.partition_index),
+                                Self::error(partition.partition_index, error_code),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        // respond in the order of the request
+        let topics = topics
+            .iter()
+            .map(|topic| DeleteRecordsTopicResult {
+                name: topic.name.clone(),
+                partitions: Some(
+                    topic
+                        .partitions
+                        .as_deref()
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|partition| {
+                            results
+                                .remove(&(topic.name.clone(), partition.partition_index))
+                                .unwrap_or(Self::error(
+                                    partition.partition_index,
+                                    ErrorCode::UnknownServerError,
+                                ))
+                        })
+                        .collect(),
+                ),
+            })
+            .collect();
 
         Ok(Body::DeleteRecordsResponse {
             throttle_time_ms: 0,
-            topics,
+            topics: Some(topics),
+            unknown_tagged_fields: vec![],
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        broker_registration_request::Listener,
+        create_topics_request::CreatableTopic,
+        record::{deflated, inflated, Record},
+        Ack, Frame,
+    };
+    use tansu_storage::{dynostore::DynoStore, BrokerRegistationRequest};
+    use tracing::subscriber::DefaultGuard;
+    use uuid::Uuid;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    async fn storage_with_records(topic: &str, records: usize) -> Result<DynoStore> {
+        let cluster = "abc";
+        let node = 12321;
+
+        let mut storage = DynoStore::new(cluster, node, InMemory::new());
+
+        // metadata places partitions on the registered brokers
+        _ = storage
+            .register_broker(BrokerRegistationRequest {
+                broker_id: node,
+                cluster_id: cluster.into(),
+                incarnation_id: Uuid::new_v4(),
+                listeners: vec![Listener {
+                    name: "broker".into(),
+                    host: "localhost".into(),
+                    port: 9092,
+                    security_protocol: 0,
+                }],
+                features: vec![],
+                rack: None,
+            })
+            .await?;
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: topic.into(),
+                    num_partitions: 1,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some([].into()),
+                },
+                false,
+            )
+            .await?;
+
+        for _ in 0..records {
+            let batch = inflated::Batch::builder()
+                .record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+                .build()
+                .and_then(deflated::Batch::try_from)?;
+
+            _ = storage
+                .produce(&Topition::new(topic, 0), batch, Ack::FullIsr)
+                .await?;
+        }
+
+        Ok(storage)
+    }
+
+    fn partition_results(body: Body) -> Vec<(String, DeleteRecordsPartitionResult)> {
+        let Body::DeleteRecordsResponse {
+            topics: Some(topics),
+            ..
+        } = body
+        else {
+            return vec![];
+        };
+
+        topics
+            .into_iter()
+            .flat_map(|topic| {
+                topic
+                    .partitions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |partition| (topic.name.clone(), partition))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn delete_before_offset() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let storage = storage_with_records(topic, 3).await?;
+
+        // delete records v2: pqr, partition 0, before offset 2, timeout 30s
+        let frame = Frame::request_from_bytes(&[
+            0, 0, 0, 49, 0, 21, 0, 2, 0, 0, 0, 5, 0, 13, 97, 100, 109, 105, 110, 99, 108, 105, 101,
+            110, 116, 45, 49, 0, 2, 4, 112, 113, 114, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0,
+            0, 0, 117, 48, 0,
+        ])?;
+
+        let Body::DeleteRecordsRequest {
+            topics: Some(topics),
+            timeout_ms,
+            ..
+        } = frame.body
+        else {
+            return Err(Error::Custom(format!("unexpected: {:?}", frame.body)));
+        };
+
+        assert_eq!(
+            vec![(
+                topic.to_owned(),
+                DeleteRecordsPartitionResult {
+                    partition_index: 0,
+                    low_watermark: 2,
+                    error_code: ErrorCode::None.into(),
+                }
+            )],
+            partition_results(
+                DeleteRecordsRequest::with_storage(storage.clone())
+                    .request(&topics, timeout_ms)
+                    .await?
+            )
+        );
+
+        let mut storage = storage;
+        let tp = Topition::new(topic, 0);
+
+        assert_eq!(2, storage.offset_stage(&tp).await?.log_start());
+        assert_eq!(2, storage.fetch(&tp, 0, 0, 1_048_576).await?.base_offset);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unknown_and_out_of_range() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let storage = storage_with_records(topic, 3).await?;
+
+        let topics = [
+            DeleteRecordsTopic {
+                name: topic.into(),
+                partitions: Some(vec![
+                    DeleteRecordsPartition {
+                        partition_index: 0,
+                        offset: 10,
+                    },
+                    DeleteRecordsPartition {
+                        partition_index: 1,
+                        offset: 0,
+                    },
+                ]),
+            },
+            DeleteRecordsTopic {
+                name: "xyz".into(),
+                partitions: Some(vec![DeleteRecordsPartition {
+                    partition_index: 0,
+                    offset: 0,
+                }]),
+            },
+        ];
+
+        assert_eq!(
+            vec![
+                (
+                    topic.to_owned(),
+                    DeleteRecordsRequest::<DynoStore>::error(0, ErrorCode::OffsetOutOfRange)
+                ),
+                (
+                    topic.to_owned(),
+                    DeleteRecordsRequest::<DynoStore>::error(1, ErrorCode::UnknownTopicOrPartition)
+                ),
+                (
+                    "xyz".to_owned(),
+                    DeleteRecordsRequest::<DynoStore>::error(0, ErrorCode::UnknownTopicOrPartition)
+                ),
+            ],
+            partition_results(
+                DeleteRecordsRequest::with_storage(storage)
+                    .request(&topics, 30_000)
+                    .await?
+            )
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{authorizer::AclFilter, Result};
+use tansu_kafka_sans_io::{
+    describe_acls_response::{AclDescription, DescribeAclsResource},
+    Body, ErrorCode,
+};
+use tansu_storage::Storage;
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeAclsRequest<S> {
+    storage: S,
+}
+
+impl<S> DescribeAclsRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    pub async fn response(&mut self, filter: &AclFilter) -> Result<Body> {
+        debug!(?filter);
+
+        // bindings are ordered by resource, grouping the acls of each resource
+        let mut resources: Vec<DescribeAclsResource> = vec![];
+
+        for binding in self
+            .storage
+            .acls()
+            .await?
+            .into_iter()
+            .filter(|binding| filter.matches(binding))
+        {
+            let acl = AclDescription {
+                principal: binding.principal,
+                host: binding.host,
+                operation: binding.operation,
+                permission_type: binding.permission_type,
+            };
+
+            match resources.last_mut() {
+                Some(resource)
+                    if resource.resource_type == binding.resource_type
+                        && resource.resource_name == binding.resource_name
+                        && resource.pattern_type == Some(binding.pattern_type) =>
+                {
+                    resource.acls.get_or_insert_default().push(acl)
+                }
+
+                _ => resources.push(DescribeAclsResource {
+                    resource_type: binding.resource_type,
+                    resource_name: binding.resource_name,
+                    pattern_type: Some(binding.pattern_type),
+                    acls: Some(vec![acl]),
+                }),
+            }
+        }
+
+        Ok(Body::DescribeAclsResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.into(),
+            error_message: None,
+            resources: Some(resources),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
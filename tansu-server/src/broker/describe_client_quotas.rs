@@ -0,0 +1,114 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::broker::quota::{Quotas, CLIENT_ID};
+use tansu_kafka_sans_io::{
+    describe_client_quotas_request::ComponentData,
+    describe_client_quotas_response::{EntityData, EntryData, ValueData},
+    Body, ErrorCode,
+};
+use tracing::debug;
+
+// how a component matches the name of an entity
+const MATCH_EXACT: i8 = 0;
+const MATCH_DEFAULT: i8 = 1;
+const MATCH_SPECIFIED: i8 = 2;
+
+#[derive(Clone, Debug, Default)]
+pub struct DescribeClientQuotasRequest {
+    quotas: Quotas,
+}
+
+impl DescribeClientQuotasRequest {
+    pub fn with_quotas(quotas: Quotas) -> Self {
+        Self { quotas }
+    }
+
+    fn error(error_code: ErrorCode, error_message: String) -> Body {
+        Body::DescribeClientQuotasResponse {
+            throttle_time_ms: 0,
+            error_code: error_code.into(),
+            error_message: Some(error_message),
+            entries: None,
+            unknown_tagged_fields: vec![],
+        }
+    }
+
+    pub fn response(&self, components: Option<&[ComponentData]>, strict: bool) -> Body {
+        debug!(?components, strict);
+
+        let components = components.unwrap_or_default();
+
+        // quotas are only kept for client ids
+        if let Some(component) = components
+            .iter()
+            .find(|component| component.entity_type != CLIENT_ID)
+        {
+            return Self::error(
+                ErrorCode::InvalidRequest,
+                format!("unsupported entity type: {}", component.entity_type),
+            );
+        }
+
+        if let Some(component) = components.iter().find(|component| {
+            !matches!(
+                component.match_type,
+                MATCH_EXACT | MATCH_DEFAULT | MATCH_SPECIFIED
+            )
+        }) {
+            return Self::error(
+                ErrorCode::InvalidRequest,
+                format!("unknown match type: {}", component.match_type),
+            );
+        }
+
+        let matches = |client_id: Option<&str>| {
+            components
+                .iter()
+                .all(|component| match component.match_type {
+                    MATCH_EXACT => client_id == component.r#match.as_deref(),
+                    MATCH_DEFAULT => client_id.is_none(),
+                    _ => client_id.is_some(),
+                })
+        };
+
+        let entries = self
+            .quotas
+            .configured()
+            .into_iter()
+            .filter(|(client_id, _)| matches(client_id.as_deref()))
+            .map(|(client_id, values)| EntryData {
+                entity: Some(vec![EntityData {
+                    entity_type: CLIENT_ID.into(),
+                    entity_name: client_id,
+                }]),
+                values: Some(
+                    values
+                        .into_iter()
+                        .map(|(key, value)| ValueData { key, value })
+                        .collect(),
+                ),
+            })
+            .collect();
+
+        Body::DescribeClientQuotasResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.into(),
+            error_message: None,
+            entries: Some(entries),
+            unknown_tagged_fields: vec![],
+        }
+    }
+}
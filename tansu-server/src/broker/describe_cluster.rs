@@ -13,14 +13,43 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::Result;
-use tansu_kafka_sans_io::{Body, ErrorCode};
+use crate::{
+    authorizer::{
+        authorized_operations, Authorizer, Principal, Resource, AUTHORIZED_OPERATIONS_OMITTED,
+    },
+    Result,
+};
+use std::sync::Arc;
+use tansu_kafka_sans_io::{AclOperation, Body, ErrorCode};
 use tansu_storage::Storage;
+use tracing::debug;
 
-#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+const ENDPOINT_TYPE_BROKER: i8 = 1;
+const ENDPOINT_TYPE_CONTROLLER: i8 = 2;
+
+/// The operations that may be authorized on a cluster.
+const CLUSTER_OPERATIONS: [AclOperation; 9] = [
+    AclOperation::Alter,
+    AclOperation::AlterConfigs,
+    AclOperation::ClusterAction,
+    AclOperation::Create,
+    AclOperation::CreateTokens,
+    AclOperation::Describe,
+    AclOperation::DescribeConfigs,
+    AclOperation::DescribeTokens,
+    AclOperation::IdempotentWrite,
+];
+
+#[derive(Clone, Debug)]
 pub struct DescribeClusterRequest<S> {
     pub cluster_id: String,
+    /// This broker, which is reported as the controller.
+    pub node_id: i32,
     pub storage: S,
+    /// The listener that the request arrived on, whose addresses are described.
+    pub listener: Option<String>,
+    pub authorizer: Arc<dyn Authorizer>,
+    pub principal: Principal,
 }
 
 impl<S> DescribeClusterRequest<S>
@@ -32,9 +61,45 @@ where
         include_cluster_authorized_operations: bool,
         endpoint_type: Option<i8>,
     ) -> Result<Body> {
-        let _ = include_cluster_authorized_operations;
+        debug!(?include_cluster_authorized_operations, ?endpoint_type);
+
+        // only broker endpoints are described, there are no controllers
+        if let Some(error_code) = endpoint_type
+            .filter(|endpoint_type| *endpoint_type != ENDPOINT_TYPE_BROKER)
+            .map(|endpoint_type| {
+                if endpoint_type == ENDPOINT_TYPE_CONTROLLER {
+                    ErrorCode::MismatchedEndpointType
+                } else {
+                    ErrorCode::UnsupportedEndpointType
+                }
+            })
+        {
+            return Ok(Body::DescribeClusterResponse {
+                throttle_time_ms: 0,
+                error_code: error_code.into(),
+                error_message: Some(error_code.to_string()),
+                endpoint_type,
+                cluster_id: self.cluster_id.clone(),
+                controller_id: -1,
+                brokers: Some([].into()),
+                cluster_authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+                unknown_tagged_fields: vec![],
+            });
+        }
+
+        let brokers = self.storage.brokers(self.listener.as_deref()).await?;
 
-        let brokers = self.storage.brokers().await?;
+        let cluster_authorized_operations = if include_cluster_authorized_operations {
+            authorized_operations(
+                self.authorizer.as_ref(),
+                &self.principal,
+                &Resource::cluster(),
+                &CLUSTER_OPERATIONS,
+            )
+            .await
+        } else {
+            AUTHORIZED_OPERATIONS_OMITTED
+        };
 
         Ok(Body::DescribeClusterResponse {
             throttle_time_ms: 0,
@@ -42,9 +107,252 @@ where
             error_message: None,
             endpoint_type,
             cluster_id: self.cluster_id.clone(),
-            controller_id: -1,
+            controller_id: self.node_id,
             brokers: Some(brokers),
-            cluster_authorized_operations: -2_147_483_648,
+            cluster_authorized_operations,
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        authorizer::{AclAuthorizer, AllowAll, WILDCARD},
+        Error,
+    };
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        broker_registration_request::Listener, describe_cluster_response::DescribeClusterBroker,
+        AclPermissionType, ApiKey, Frame, Header, PatternType, ResourceType,
+    };
+    use tansu_storage::{dynostore::DynoStore, AclBinding, BrokerRegistationRequest};
+    use tracing::subscriber::DefaultGuard;
+    use uuid::Uuid;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const CLUSTER: &str = "abc";
+    const NODE: i32 = 12321;
+
+    async fn request(
+        authorizer: impl Authorizer + 'static,
+        mut storage: DynoStore,
+    ) -> Result<DescribeClusterRequest<DynoStore>> {
+        _ = storage
+            .register_broker(BrokerRegistationRequest {
+                broker_id: NODE,
+                cluster_id: CLUSTER.into(),
+                incarnation_id: Uuid::new_v4(),
+                listeners: vec![Listener {
+                    name: "broker".into(),
+                    host: "localhost".into(),
+                    port: 9092,
+                    security_protocol: 0,
+                }],
+                features: vec![],
+                rack: Some("rack-a".into()),
+            })
+            .await?;
+
+        Ok(DescribeClusterRequest {
+            cluster_id: CLUSTER.into(),
+            node_id: NODE,
+            storage,
+            listener: Some("broker".into()),
+            authorizer: Arc::new(authorizer),
+            principal: Principal::new(Some("alice"), Some("127.0.0.1")),
         })
     }
+
+    // the response is encoded in, and decoded from, an api version
+    fn round_trip(body: Body, api_version: i16) -> Result<Body> {
+        let api_key = ApiKey::DescribeCluster;
+
+        Frame::encode_response(
+            Header::Response { correlation_id: 5 },
+            body,
+            api_key,
+            api_version,
+        )
+        .and_then(|encoded| Frame::decode_response(&encoded, api_key, api_version))
+        .map(|frame| frame.body)
+        .map_err(Into::into)
+    }
+
+    fn broker() -> DescribeClusterBroker {
+        DescribeClusterBroker {
+            broker_id: NODE,
+            host: "localhost".into(),
+            port: 9092,
+            rack: Some("rack-a".into()),
+        }
+    }
+
+    fn bits(operations: &[AclOperation]) -> i32 {
+        operations
+            .iter()
+            .fold(0, |bits, operation| bits | 1 << i8::from(*operation))
+    }
+
+    #[tokio::test]
+    async fn v0() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = DynoStore::new(CLUSTER, NODE, InMemory::new());
+        let body = request(AllowAll, storage)
+            .await?
+            .response(false, None)
+            .await?;
+
+        let Body::DescribeClusterResponse {
+            error_code,
+            endpoint_type,
+            cluster_id,
+            controller_id,
+            brokers,
+            cluster_authorized_operations,
+            ..
+        } = round_trip(body, 0)?
+        else {
+            panic!("expecting a describe cluster response")
+        };
+
+        assert_eq!(i16::from(ErrorCode::None), error_code);
+        assert_eq!(None, endpoint_type);
+        assert_eq!(CLUSTER, cluster_id);
+        assert_eq!(NODE, controller_id);
+        assert_eq!(Some(vec![broker()]), brokers);
+        assert_eq!(AUTHORIZED_OPERATIONS_OMITTED, cluster_authorized_operations);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn v1() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = DynoStore::new(CLUSTER, NODE, InMemory::new());
+        let body = request(AllowAll, storage)
+            .await?
+            .response(true, Some(ENDPOINT_TYPE_BROKER))
+            .await?;
+
+        let Body::DescribeClusterResponse {
+            error_code,
+            endpoint_type,
+            controller_id,
+            brokers,
+            cluster_authorized_operations,
+            ..
+        } = round_trip(body, 1)?
+        else {
+            panic!("expecting a describe cluster response")
+        };
+
+        assert_eq!(i16::from(ErrorCode::None), error_code);
+        assert_eq!(Some(ENDPOINT_TYPE_BROKER), endpoint_type);
+        assert_eq!(NODE, controller_id);
+        assert_eq!(Some(vec![broker()]), brokers);
+        assert_eq!(bits(&CLUSTER_OPERATIONS), cluster_authorized_operations);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn authorized_operations_from_acls() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let mut storage = DynoStore::new(CLUSTER, NODE, InMemory::new());
+
+        storage
+            .create_acls(&[AclBinding {
+                resource_type: ResourceType::Cluster.into(),
+                resource_name: crate::authorizer::CLUSTER.into(),
+                pattern_type: PatternType::Literal.into(),
+                principal: "User:alice".into(),
+                host: WILDCARD.into(),
+                operation: AclOperation::Alter.into(),
+                permission_type: AclPermissionType::Allow.into(),
+            }])
+            .await?;
+
+        let authorizer = AclAuthorizer::with_storage(storage.clone());
+
+        let body = request(authorizer, storage)
+            .await?
+            .response(true, Some(ENDPOINT_TYPE_BROKER))
+            .await?;
+
+        // alter implies describe
+        assert!(matches!(
+            body,
+            Body::DescribeClusterResponse {
+                cluster_authorized_operations,
+                ..
+            } if cluster_authorized_operations
+                == bits(&[AclOperation::Alter, AclOperation::Describe])
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn controller_endpoint() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = DynoStore::new(CLUSTER, NODE, InMemory::new());
+        let mut request = request(AllowAll, storage).await?;
+
+        for (endpoint_type, expected) in [
+            (ENDPOINT_TYPE_CONTROLLER, ErrorCode::MismatchedEndpointType),
+            (3, ErrorCode::UnsupportedEndpointType),
+        ] {
+            let Body::DescribeClusterResponse {
+                error_code,
+                brokers,
+                ..
+            } = request.response(false, Some(endpoint_type)).await?
+            else {
+                panic!("expecting a describe cluster response")
+            };
+
+            assert_eq!(i16::from(expected), error_code);
+            assert_eq!(Some(vec![]), brokers);
+        }
+
+        Ok(())
+    }
 }
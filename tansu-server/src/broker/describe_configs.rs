@@ -13,16 +13,99 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+
 use crate::Result;
 use tansu_kafka_sans_io::{
-    describe_configs_request::DescribeConfigsResource, Body, ConfigResource,
+    describe_configs_request::DescribeConfigsResource,
+    describe_configs_response::{
+        DescribeConfigsResourceResult, DescribeConfigsResult, DescribeConfigsSynonym,
+    },
+    Body, ConfigResource, ConfigSource, ConfigType, ErrorCode,
 };
-use tansu_storage::Storage;
-use tracing::error;
+use tansu_storage::{Storage, TopicId};
+use tracing::{debug, warn};
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct TopicDefault {
+    name: &'static str,
+    value: &'static str,
+    config_type: ConfigType,
+    documentation: &'static str,
+}
+
+// reported for every topic, unless overridden by the topic itself
+const TOPIC_DEFAULTS: [TopicDefault; 10] = [
+    TopicDefault {
+        name: "cleanup.policy",
+        value: "delete",
+        config_type: ConfigType::List,
+        documentation: "The retention policy to use on old log segments.",
+    },
+    TopicDefault {
+        name: "compression.type",
+        value: "producer",
+        config_type: ConfigType::String,
+        documentation: "The final compression type for a given topic.",
+    },
+    TopicDefault {
+        name: "delete.retention.ms",
+        value: "86400000",
+        config_type: ConfigType::Long,
+        documentation:
+            "The amount of time to retain delete tombstone markers for log compacted topics.",
+    },
+    TopicDefault {
+        name: "max.message.bytes",
+        value: "1048588",
+        config_type: ConfigType::Int,
+        documentation: "The largest record batch size allowed.",
+    },
+    TopicDefault {
+        name: "message.timestamp.type",
+        value: "CreateTime",
+        config_type: ConfigType::String,
+        documentation:
+            "Whether the timestamp in the message is message create time or log append time.",
+    },
+    TopicDefault {
+        name: "min.insync.replicas",
+        value: "1",
+        config_type: ConfigType::Int,
+        documentation: "The minimum number of replicas that must acknowledge a write.",
+    },
+    TopicDefault {
+        name: "retention.bytes",
+        value: "-1",
+        config_type: ConfigType::Long,
+        documentation:
+            "The maximum size a partition can grow to before old log segments are discarded.",
+    },
+    TopicDefault {
+        name: "retention.ms",
+        value: "604800000",
+        config_type: ConfigType::Long,
+        documentation: "The maximum time a log is retained before old log segments are discarded.",
+    },
+    TopicDefault {
+        name: "segment.bytes",
+        value: "1073741824",
+        config_type: ConfigType::Int,
+        documentation: "The log segment file size.",
+    },
+    TopicDefault {
+        name: "segment.ms",
+        value: "604800000",
+        config_type: ConfigType::Long,
+        documentation: "The period of time after which a log segment is rolled.",
+    },
+];
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct DescribeConfigsRequest<S> {
     storage: S,
+    node_id: i32,
+    broker: BTreeMap<String, String>,
 }
 
 impl<S> DescribeConfigsRequest<S>
@@ -30,7 +113,20 @@ where
     S: Storage,
 {
     pub fn with_storage(storage: S) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            node_id: Default::default(),
+            broker: Default::default(),
+        }
+    }
+
+    /// The runtime configuration of this broker, reported for a BROKER resource.
+    pub fn with_broker(self, node_id: i32, broker: BTreeMap<String, String>) -> Self {
+        Self {
+            node_id,
+            broker,
+            ..self
+        }
     }
 
     pub async fn response(
@@ -39,29 +135,406 @@ where
         include_synonyms: Option<bool>,
         include_documentation: Option<bool>,
     ) -> Result<Body> {
-        let _ = include_synonyms;
-        let _ = include_documentation;
+        let include_synonyms = include_synonyms.unwrap_or_default();
+        let include_documentation = include_documentation.unwrap_or_default();
 
         let mut results = vec![];
 
-        if let Some(resources) = resources {
-            for resource in resources {
-                results.push(
-                    self.storage
-                        .describe_config(
-                            resource.resource_name.as_str(),
-                            ConfigResource::from(resource.resource_type),
-                            resource.configuration_keys.as_deref(),
-                        )
-                        .await
-                        .inspect_err(|err| error!(?err))?,
-                );
-            }
+        for resource in resources.unwrap_or_default() {
+            results.push(
+                self.describe(resource, include_synonyms, include_documentation)
+                    .await,
+            );
         }
 
         Ok(Body::DescribeConfigsResponse {
             throttle_time_ms: 0,
             results: Some(results),
+            unknown_tagged_fields: vec![],
         })
     }
+
+    async fn describe(
+        &mut self,
+        resource: &DescribeConfigsResource,
+        include_synonyms: bool,
+        include_documentation: bool,
+    ) -> DescribeConfigsResult {
+        debug!(?resource, ?include_synonyms, ?include_documentation);
+
+        let configs = match ConfigResource::from(resource.resource_type) {
+            ConfigResource::Topic => self
+                .topic(
+                    &resource.resource_name,
+                    include_synonyms,
+                    include_documentation,
+                )
+                .await
+                .map_err(|error| {
+                    warn!(resource_name = resource.resource_name.as_str(), ?error);
+
+                    if let crate::Error::Storage(tansu_storage::Error::Api(error_code)) = error {
+                        (error_code, None)
+                    } else {
+                        (ErrorCode::UnknownServerError, None)
+                    }
+                }),
+
+            ConfigResource::Broker => self.broker(&resource.resource_name, include_synonyms),
+
+            otherwise => Err((
+                ErrorCode::InvalidRequest,
+                Some(format!("unsupported resource type: {otherwise:?}")),
+            )),
+        };
+
+        match configs {
+            Ok(configs) => DescribeConfigsResult {
+                error_code: ErrorCode::None.into(),
+                error_message: None,
+                resource_type: resource.resource_type,
+                resource_name: resource.resource_name.clone(),
+                configs: Some(
+                    configs
+                        .into_iter()
+                        .filter(|config| {
+                            resource
+                                .configuration_keys
+                                .as_deref()
+                                .filter(|keys| !keys.is_empty())
+                                .is_none_or(|keys| keys.contains(&config.name))
+                        })
+                        .collect(),
+                ),
+            },
+
+            Err((error_code, error_message)) => DescribeConfigsResult {
+                error_code: error_code.into(),
+                error_message: error_message.or_else(|| Some(error_code.to_string())),
+                resource_type: resource.resource_type,
+                resource_name: resource.resource_name.clone(),
+                configs: Some([].into()),
+            },
+        }
+    }
+
+    async fn topic(
+        &mut self,
+        name: &str,
+        include_synonyms: bool,
+        include_documentation: bool,
+    ) -> Result<Vec<DescribeConfigsResourceResult>> {
+        let mut overrides = self
+            .storage
+            .topic_config(&TopicId::Name(name.into()))
+            .await?;
+
+        let mut configs = vec![];
+
+        for default in TOPIC_DEFAULTS {
+            let mut synonyms = vec![];
+
+            let (value, config_source) = match overrides.remove(default.name) {
+                Some(value) => {
+                    synonyms.push(DescribeConfigsSynonym {
+                        name: default.name.into(),
+                        value: value.clone(),
+                        source: ConfigSource::DynamicTopicConfig.into(),
+                    });
+
+                    (value, ConfigSource::DynamicTopicConfig)
+                }
+
+                None => (Some(default.value.into()), ConfigSource::DefaultConfig),
+            };
+
+            synonyms.push(DescribeConfigsSynonym {
+                name: default.name.into(),
+                value: Some(default.value.into()),
+                source: ConfigSource::DefaultConfig.into(),
+            });
+
+            configs.push(DescribeConfigsResourceResult {
+                name: default.name.into(),
+                value,
+                read_only: false,
+                is_default: Some(config_source == ConfigSource::DefaultConfig),
+                config_source: Some(config_source.into()),
+                is_sensitive: false,
+                synonyms: Some(if include_synonyms { synonyms } else { vec![] }),
+                config_type: Some(default.config_type.into()),
+                documentation: include_documentation.then(|| default.documentation.into()),
+            });
+        }
+
+        // overrides without a known default are reported as they were stored
+        for (name, value) in overrides {
+            configs.push(DescribeConfigsResourceResult {
+                synonyms: Some(if include_synonyms {
+                    vec![DescribeConfigsSynonym {
+                        name: name.clone(),
+                        value: value.clone(),
+                        source: ConfigSource::DynamicTopicConfig.into(),
+                    }]
+                } else {
+                    vec![]
+                }),
+                name,
+                value,
+                read_only: false,
+                is_default: Some(false),
+                config_source: Some(ConfigSource::DynamicTopicConfig.into()),
+                is_sensitive: false,
+                config_type: Some(ConfigType::String.into()),
+                documentation: None,
+            });
+        }
+
+        configs.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(configs)
+    }
+
+    fn broker(
+        &self,
+        name: &str,
+        include_synonyms: bool,
+    ) -> std::result::Result<Vec<DescribeConfigsResourceResult>, (ErrorCode, Option<String>)> {
+        // an empty name refers to the broker receiving the request
+        if !name.is_empty() && name != self.node_id.to_string() {
+            return Err((
+                ErrorCode::InvalidRequest,
+                Some(format!("unexpected broker id: {name}")),
+            ));
+        }
+
+        Ok(self
+            .broker
+            .iter()
+            .map(|(name, value)| DescribeConfigsResourceResult {
+                name: name.clone(),
+                value: Some(value.clone()),
+                read_only: true,
+                is_default: Some(false),
+                config_source: Some(ConfigSource::StaticBrokerConfig.into()),
+                is_sensitive: false,
+                synonyms: Some(if include_synonyms {
+                    vec![DescribeConfigsSynonym {
+                        name: name.clone(),
+                        value: Some(value.clone()),
+                        source: ConfigSource::StaticBrokerConfig.into(),
+                    }]
+                } else {
+                    vec![]
+                }),
+                config_type: Some(ConfigType::String.into()),
+                documentation: None,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        create_topics_request::{CreatableTopic, CreateableTopicConfig},
+        ApiKey, Frame, Header,
+    };
+    use tansu_storage::dynostore::DynoStore;
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn describe_configs_response_v4() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let node_id = 12321;
+        let topic = "test";
+
+        let mut storage = DynoStore::new("abc", node_id, InMemory::new());
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: topic.into(),
+                    num_partitions: 1,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some(
+                        [CreateableTopicConfig {
+                            name: "retention.ms".into(),
+                            value: Some("3600000".into()),
+                        }]
+                        .into(),
+                    ),
+                },
+                false,
+            )
+            .await?;
+
+        let resource =
+            |resource_type: ConfigResource, resource_name: &str| DescribeConfigsResource {
+                resource_type: resource_type.into(),
+                resource_name: resource_name.into(),
+                configuration_keys: None,
+            };
+
+        let body = DescribeConfigsRequest::with_storage(storage)
+            .with_broker(
+                node_id,
+                BTreeMap::from([("node.id".into(), node_id.to_string())]),
+            )
+            .response(
+                Some(&[
+                    DescribeConfigsResource {
+                        configuration_keys: Some(vec![
+                            "cleanup.policy".into(),
+                            "retention.ms".into(),
+                        ]),
+                        ..resource(ConfigResource::Topic, topic)
+                    },
+                    resource(ConfigResource::Topic, "missing"),
+                    resource(ConfigResource::Broker, &node_id.to_string()),
+                    resource(ConfigResource::Group, "abc"),
+                ]),
+                Some(true),
+                None,
+            )
+            .await?;
+
+        let Body::DescribeConfigsResponse {
+            results: Some(ref results),
+            ..
+        } = body
+        else {
+            panic!("unexpected: {body:?}")
+        };
+
+        assert_eq!(4, results.len());
+
+        assert_eq!(ErrorCode::None, ErrorCode::try_from(results[0].error_code)?);
+        assert_eq!(
+            Some(vec![
+                DescribeConfigsResourceResult {
+                    name: "cleanup.policy".into(),
+                    value: Some("delete".into()),
+                    read_only: false,
+                    is_default: Some(true),
+                    config_source: Some(ConfigSource::DefaultConfig.into()),
+                    is_sensitive: false,
+                    synonyms: Some(vec![DescribeConfigsSynonym {
+                        name: "cleanup.policy".into(),
+                        value: Some("delete".into()),
+                        source: ConfigSource::DefaultConfig.into(),
+                    }]),
+                    config_type: Some(ConfigType::List.into()),
+                    documentation: None,
+                },
+                DescribeConfigsResourceResult {
+                    name: "retention.ms".into(),
+                    value: Some("3600000".into()),
+                    read_only: false,
+                    is_default: Some(false),
+                    config_source: Some(ConfigSource::DynamicTopicConfig.into()),
+                    is_sensitive: false,
+                    synonyms: Some(vec![
+                        DescribeConfigsSynonym {
+                            name: "retention.ms".into(),
+                            value: Some("3600000".into()),
+                            source: ConfigSource::DynamicTopicConfig.into(),
+                        },
+                        DescribeConfigsSynonym {
+                            name: "retention.ms".into(),
+                            value: Some("604800000".into()),
+                            source: ConfigSource::DefaultConfig.into(),
+                        }
+                    ]),
+                    config_type: Some(ConfigType::Long.into()),
+                    documentation: None,
+                },
+            ]),
+            results[0].configs
+        );
+
+        assert_eq!(
+            ErrorCode::UnknownTopicOrPartition,
+            ErrorCode::try_from(results[1].error_code)?
+        );
+
+        assert_eq!(ErrorCode::None, ErrorCode::try_from(results[2].error_code)?);
+        assert_eq!(
+            Some(vec![(
+                "node.id".into(),
+                Some(node_id.to_string()),
+                true,
+                i8::from(ConfigSource::StaticBrokerConfig)
+            )]),
+            results[2].configs.as_ref().map(|configs| configs
+                .iter()
+                .map(|config| (
+                    config.name.clone(),
+                    config.value.clone(),
+                    config.read_only,
+                    config.config_source.unwrap_or_default()
+                ))
+                .collect::<Vec<_>>())
+        );
+
+        assert_eq!(
+            ErrorCode::InvalidRequest,
+            ErrorCode::try_from(results[3].error_code)?
+        );
+
+        let api_key = ApiKey::DescribeConfigs;
+        let api_version = 4;
+        let header = Header::Response { correlation_id: 5 };
+
+        let encoded = Frame::encode_response(header.clone(), body, api_key, api_version)?;
+        let decoded = Frame::decode_response(&encoded, api_key, api_version)?;
+
+        assert_eq!(header, decoded.header);
+
+        // is_default is only part of v0, so compare what is encoded at v4
+        assert_eq!(
+            encoded,
+            Frame::encode_response(decoded.header, decoded.body, api_key, api_version)?
+        );
+
+        Ok(())
+    }
 }
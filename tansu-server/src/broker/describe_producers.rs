@@ -0,0 +1,300 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{broker::list_partition_reassignments::placed_replicas, Result};
+use tansu_kafka_sans_io::{
+    describe_producers_request::TopicRequest,
+    describe_producers_response::{PartitionResponse, ProducerState, TopicResponse},
+    to_timestamp, Body, ErrorCode,
+};
+use tansu_storage::{ProducerDetail, Storage, Topition};
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeProducersRequest<S> {
+    storage: S,
+}
+
+impl<S> DescribeProducersRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    async fn partition(&mut self, topition: Topition, exists: bool) -> Result<PartitionResponse> {
+        if !exists {
+            return Ok(PartitionResponse {
+                partition_index: topition.partition(),
+                error_code: ErrorCode::UnknownTopicOrPartition.into(),
+                error_message: None,
+                active_producers: Some([].into()),
+            });
+        }
+
+        let active_producers = self
+            .storage
+            .producers(&topition)
+            .await?
+            .into_iter()
+            .map(producer_state)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(PartitionResponse {
+            partition_index: topition.partition(),
+            error_code: ErrorCode::None.into(),
+            error_message: None,
+            active_producers: Some(active_producers),
+        })
+    }
+
+    pub async fn response(&mut self, topics: Option<&[TopicRequest]>) -> Result<Body> {
+        debug!(?topics);
+
+        let mut responses = vec![];
+
+        for topic in topics.unwrap_or_default() {
+            let placed = placed_replicas(&mut self.storage, &topic.name).await?;
+
+            let mut partitions = vec![];
+
+            for partition_index in topic.partition_indexes.iter().flatten() {
+                let exists = placed
+                    .as_ref()
+                    .is_some_and(|placed| placed.contains_key(partition_index));
+
+                partitions.push(
+                    self.partition(Topition::new(topic.name.as_str(), *partition_index), exists)
+                        .await?,
+                );
+            }
+
+            responses.push(TopicResponse {
+                name: topic.name.clone(),
+                partitions: Some(partitions),
+            });
+        }
+
+        Ok(Body::DescribeProducersResponse {
+            throttle_time_ms: 0,
+            topics: Some(responses),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+// the coordinator epoch isn't tracked, with -1 standing for any absent value
+fn producer_state(producer: ProducerDetail) -> Result<ProducerState> {
+    Ok(ProducerState {
+        producer_id: producer.producer_id,
+        producer_epoch: producer.producer_epoch.into(),
+        last_sequence: producer.last_sequence,
+        last_timestamp: producer.last_timestamp.map_or(Ok(-1), to_timestamp)?,
+        coordinator_epoch: -1,
+        current_txn_start_offset: producer.txn_start_offset.unwrap_or(-1),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{init_producer_id::InitProducerIdRequest, txn::add_partitions::AddPartitions},
+        fixture::storage_with_topic,
+        Error,
+    };
+    use bytes::Bytes;
+    use tansu_kafka_sans_io::{
+        add_partitions_to_txn_request::AddPartitionsToTxnTopic,
+        record::{deflated, inflated, Record},
+        Ack,
+    };
+    use tansu_storage::dynostore::DynoStore;
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const TOPIC: &str = "pqr";
+    const TRANSACTIONAL_ID: &str = "txn-abc";
+
+    async fn produce(
+        storage: &mut DynoStore,
+        producer_id: i64,
+        base_sequence: i32,
+        records: i32,
+        transactional: bool,
+    ) -> Result<i64> {
+        let batch = (0..records)
+            .fold(inflated::Batch::builder(), |builder, _| {
+                builder.record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+            })
+            .last_offset_delta(records - 1)
+            .producer_id(producer_id)
+            .producer_epoch(0)
+            .base_sequence(base_sequence)
+            .transactional(transactional)
+            .build()
+            .and_then(deflated::Batch::try_from)?;
+
+        storage
+            .produce(&Topition::new(TOPIC, 0), batch, Ack::FullIsr)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[tokio::test]
+    async fn active_producers() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let mut storage = storage_with_topic(TOPIC, 2).await?;
+
+        let idempotent = InitProducerIdRequest::with_storage(storage.clone())
+            .response(None, 0, Some(-1), Some(-1))
+            .await?;
+
+        assert_eq!(0, produce(&mut storage, idempotent.id, 0, 3, false).await?);
+        assert_eq!(3, produce(&mut storage, idempotent.id, 3, 2, false).await?);
+
+        let transactional = InitProducerIdRequest::with_storage(storage.clone())
+            .response(Some(TRANSACTIONAL_ID), 60_000, Some(-1), Some(-1))
+            .await?;
+
+        _ = AddPartitions::with_storage(storage.clone())
+            .response(
+                None,
+                Some(TRANSACTIONAL_ID.into()),
+                Some(transactional.id),
+                Some(transactional.epoch),
+                Some(vec![AddPartitionsToTxnTopic {
+                    name: TOPIC.into(),
+                    partitions: Some(vec![0]),
+                }]),
+            )
+            .await?;
+
+        assert_eq!(
+            5,
+            produce(&mut storage, transactional.id, 0, 1, true).await?
+        );
+
+        let body = DescribeProducersRequest::with_storage(storage)
+            .response(Some(&[
+                TopicRequest {
+                    name: TOPIC.into(),
+                    partition_indexes: Some(vec![0, 1, 2]),
+                },
+                TopicRequest {
+                    name: "abc".into(),
+                    partition_indexes: Some(vec![0]),
+                },
+            ]))
+            .await?;
+
+        let Body::DescribeProducersResponse {
+            topics: Some(topics),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        assert_eq!(
+            vec![
+                (TOPIC.to_owned(), 0, ErrorCode::None.into()),
+                (TOPIC.to_owned(), 1, ErrorCode::None.into()),
+                (
+                    TOPIC.to_owned(),
+                    2,
+                    ErrorCode::UnknownTopicOrPartition.into()
+                ),
+                (
+                    "abc".to_owned(),
+                    0,
+                    ErrorCode::UnknownTopicOrPartition.into()
+                ),
+            ],
+            topics
+                .iter()
+                .flat_map(|topic| {
+                    topic.partitions.iter().flatten().map(|partition| {
+                        (
+                            topic.name.clone(),
+                            partition.partition_index,
+                            partition.error_code,
+                        )
+                    })
+                })
+                .collect::<Vec<_>>()
+        );
+
+        let producers = topics[0].partitions.as_deref().unwrap_or_default()[0]
+            .active_producers
+            .clone()
+            .unwrap_or_default();
+
+        assert_eq!(
+            vec![(idempotent.id, 0, 4, -1), (transactional.id, 0, 0, 5)],
+            producers
+                .iter()
+                .map(|producer| {
+                    (
+                        producer.producer_id,
+                        producer.producer_epoch,
+                        producer.last_sequence,
+                        producer.current_txn_start_offset,
+                    )
+                })
+                .collect::<Vec<_>>()
+        );
+
+        assert!(producers
+            .iter()
+            .all(|producer| producer.last_timestamp > 0 && producer.coordinator_epoch == -1));
+
+        Ok(())
+    }
+}
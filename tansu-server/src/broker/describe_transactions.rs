@@ -0,0 +1,206 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use crate::{broker::list_transactions::state, Result};
+use tansu_kafka_sans_io::{
+    describe_transactions_response::{TopicData, TransactionState},
+    to_timestamp, Body, ErrorCode,
+};
+use tansu_storage::{Storage, TxnDetail};
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeTransactionsRequest<S> {
+    storage: S,
+}
+
+impl<S> DescribeTransactionsRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    pub async fn response(&mut self, transactional_ids: Option<&[String]>) -> Result<Body> {
+        debug!(?transactional_ids);
+
+        let transactions = self
+            .storage
+            .transactions()
+            .await?
+            .into_iter()
+            .map(|txn| (txn.transactional_id.clone(), txn))
+            .collect::<BTreeMap<_, _>>();
+
+        let transaction_states = transactional_ids
+            .unwrap_or_default()
+            .iter()
+            .map(|transactional_id| {
+                transactions.get(transactional_id).map_or(
+                    Ok(TransactionState {
+                        error_code: ErrorCode::TransactionalIdNotFound.into(),
+                        transactional_id: transactional_id.clone(),
+                        transaction_state: String::from(""),
+                        transaction_timeout_ms: 0,
+                        transaction_start_time_ms: -1,
+                        producer_id: -1,
+                        producer_epoch: -1,
+                        topics: Some([].into()),
+                    }),
+                    transaction_state,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Body::DescribeTransactionsResponse {
+            throttle_time_ms: 0,
+            transaction_states: Some(transaction_states),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+fn transaction_state(txn: &TxnDetail) -> Result<TransactionState> {
+    let mut topics = BTreeMap::<&str, Vec<i32>>::new();
+
+    for topition in &txn.partitions {
+        topics
+            .entry(topition.topic())
+            .or_default()
+            .push(topition.partition());
+    }
+
+    Ok(TransactionState {
+        error_code: ErrorCode::None.into(),
+        transactional_id: txn.transactional_id.clone(),
+        transaction_state: state(txn).into(),
+        transaction_timeout_ms: txn.timeout_ms,
+        transaction_start_time_ms: txn.started.map_or(Ok(-1), to_timestamp)?,
+        producer_id: txn.producer_id,
+        producer_epoch: txn.producer_epoch,
+        topics: Some(
+            topics
+                .into_iter()
+                .map(|(topic, partitions)| TopicData {
+                    topic: topic.into(),
+                    partitions: Some(partitions),
+                })
+                .collect(),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixture::{ongoing, storage_with_topic},
+        Error,
+    };
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const TOPIC: &str = "pqr";
+    const TRANSACTIONAL_ID: &str = "txn-abc";
+
+    #[tokio::test]
+    async fn ongoing_and_unknown() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_topic(TOPIC, 1).await?;
+        let producer_id = ongoing(&storage, TRANSACTIONAL_ID, TOPIC).await?;
+
+        let body = DescribeTransactionsRequest::with_storage(storage)
+            .response(Some(&[TRANSACTIONAL_ID.into(), "txn-pqr".into()]))
+            .await?;
+
+        let Body::DescribeTransactionsResponse {
+            transaction_states: Some(transaction_states),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        assert_eq!(2, transaction_states.len());
+
+        let txn = &transaction_states[0];
+        assert_eq!(i16::from(ErrorCode::None), txn.error_code);
+        assert_eq!(TRANSACTIONAL_ID, txn.transactional_id);
+        assert_eq!("Ongoing", txn.transaction_state);
+        assert_eq!(60_000, txn.transaction_timeout_ms);
+        assert!(txn.transaction_start_time_ms > 0);
+        assert_eq!(producer_id, txn.producer_id);
+        assert_eq!(0, txn.producer_epoch);
+        assert_eq!(
+            Some(vec![TopicData {
+                topic: TOPIC.into(),
+                partitions: Some(vec![0]),
+            }]),
+            txn.topics
+        );
+
+        assert_eq!(
+            TransactionState {
+                error_code: ErrorCode::TransactionalIdNotFound.into(),
+                transactional_id: "txn-pqr".into(),
+                transaction_state: "".into(),
+                transaction_timeout_ms: 0,
+                transaction_start_time_ms: -1,
+                producer_id: -1,
+                producer_epoch: -1,
+                topics: Some([].into()),
+            },
+            transaction_states[1]
+        );
+
+        Ok(())
+    }
+}
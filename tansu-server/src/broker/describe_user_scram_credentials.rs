@@ -0,0 +1,111 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Result;
+use std::collections::BTreeSet;
+use tansu_kafka_sans_io::{
+    describe_user_scram_credentials_request::UserName,
+    describe_user_scram_credentials_response::{
+        CredentialInfo, DescribeUserScramCredentialsResult,
+    },
+    Body, ErrorCode,
+};
+use tansu_storage::Storage;
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeUserScramCredentialsRequest<S> {
+    storage: S,
+}
+
+impl<S> DescribeUserScramCredentialsRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    pub async fn response(&mut self, users: Option<&[UserName]>) -> Result<Body> {
+        debug!(?users);
+
+        let credentials = self.storage.user_scram_credentials().await?;
+
+        let result = |user: &str, error_code: ErrorCode, error_message: Option<String>| {
+            DescribeUserScramCredentialsResult {
+                user: user.to_owned(),
+                error_code: error_code.into(),
+                error_message,
+                credential_infos: Some(
+                    credentials
+                        .get(user)
+                        .filter(|_| error_code == ErrorCode::None)
+                        .map(|mechanisms| {
+                            mechanisms
+                                .iter()
+                                .map(|(mechanism, iterations)| CredentialInfo {
+                                    mechanism: i8::from(*mechanism),
+                                    iterations: *iterations,
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                ),
+            }
+        };
+
+        // no users, or an empty list, describes every user with a credential
+        let results = match users.filter(|users| !users.is_empty()) {
+            None => credentials
+                .keys()
+                .map(|user| result(user, ErrorCode::None, None))
+                .collect(),
+
+            Some(users) => users
+                .iter()
+                .map(|user| user.name.as_str())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .map(|name| {
+                    if users.iter().filter(|user| user.name == name).count() > 1 {
+                        result(
+                            name,
+                            ErrorCode::DuplicateResource,
+                            Some(format!("Cannot describe the same user twice: {name}")),
+                        )
+                    } else if credentials.contains_key(name) {
+                        result(name, ErrorCode::None, None)
+                    } else {
+                        result(
+                            name,
+                            ErrorCode::ResourceNotFound,
+                            Some(format!(
+                                "Attempt to describe a user credential that does not exist: {name}"
+                            )),
+                        )
+                    }
+                })
+                .collect(),
+        };
+
+        Ok(Body::DescribeUserScramCredentialsResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.into(),
+            error_message: None,
+            results: Some(results),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use tansu_kafka_sans_io::{
+    elect_leaders_request::TopicPartitions,
+    elect_leaders_response::{PartitionResult, ReplicaElectionResult},
+    Body, ErrorCode,
+};
+use tansu_storage::{Storage, TopicId};
+use tracing::debug;
+
+pub const ELECTION_TYPE_PREFERRED: i8 = 0;
+pub const ELECTION_TYPE_UNCLEAN: i8 = 1;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ElectLeadersRequest<S> {
+    storage: S,
+}
+
+impl<S> ElectLeadersRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    fn partition_result(partition_id: i32, error_code: ErrorCode) -> PartitionResult {
+        PartitionResult {
+            partition_id,
+            error_code: error_code.into(),
+            error_message: (error_code != ErrorCode::None).then(|| error_code.to_string()),
+        }
+    }
+
+    // the partitions of each topic known to storage
+    async fn known(
+        &mut self,
+        topic_partitions: Option<&[TopicPartitions]>,
+    ) -> Result<BTreeMap<String, BTreeSet<i32>>> {
+        let topics = topic_partitions.map(|topic_partitions| {
+            topic_partitions
+                .iter()
+                .map(|topic_partition| TopicId::Name(topic_partition.topic.clone()))
+                .collect::<Vec<_>>()
+        });
+
+        let metadata = self.storage.metadata(topics.as_deref()).await?;
+
+        Ok(metadata
+            .topics()
+            .iter()
+            .filter(|topic| topic.error_code == i16::from(ErrorCode::None))
+            .filter_map(|topic| {
+                topic.name.clone().map(|name| {
+                    (
+                        name,
+                        topic
+                            .partitions
+                            .iter()
+                            .flatten()
+                            .map(|partition| partition.partition_index)
+                            .collect(),
+                    )
+                })
+            })
+            .collect())
+    }
+
+    pub async fn response(
+        &mut self,
+        election_type: Option<i8>,
+        topic_partitions: Option<&[TopicPartitions]>,
+        timeout_ms: i32,
+    ) -> Result<Body> {
+        debug!(?election_type, ?topic_partitions, ?timeout_ms);
+
+        let valid = election_type.is_none_or(|election_type| {
+            [ELECTION_TYPE_PREFERRED, ELECTION_TYPE_UNCLEAN].contains(&election_type)
+        });
+
+        let known = self.known(topic_partitions).await?;
+
+        // every broker shares storage, so the leader placed by metadata is
+        // already preferred and in sync: there is no leadership to move
+        let elected = |topic: &str, partition: i32| {
+            if !valid {
+                ErrorCode::InvalidRequest
+            } else if known
+                .get(topic)
+                .is_some_and(|partitions| partitions.contains(&partition))
+            {
+                ErrorCode::ElectionNotNeeded
+            } else {
+                ErrorCode::UnknownTopicOrPartition
+            }
+        };
+
+        // without topic partitions, an election is for every partition
+        let requested = topic_partitions.map_or_else(
+            || {
+                known
+                    .iter()
+                    .map(|(topic, partitions)| {
+                        (topic.clone(), partitions.iter().copied().collect())
+                    })
+                    .collect::<Vec<(String, Vec<i32>)>>()
+            },
+            |topic_partitions| {
+                topic_partitions
+                    .iter()
+                    .map(|topic_partition| {
+                        (
+                            topic_partition.topic.clone(),
+                            topic_partition.partitions.clone().unwrap_or_default(),
+                        )
+                    })
+                    .collect()
+            },
+        );
+
+        let replica_election_results = requested
+            .into_iter()
+            .map(|(topic, partitions)| ReplicaElectionResult {
+                partition_result: Some(
+                    partitions
+                        .into_iter()
+                        .map(|partition| {
+                            Self::partition_result(partition, elected(topic.as_str(), partition))
+                        })
+                        .collect(),
+                ),
+                topic,
+            })
+            .collect();
+
+        Ok(Body::ElectLeadersResponse {
+            throttle_time_ms: 0,
+            error_code: election_type.map(|_| ErrorCode::None.into()),
+            replica_election_results: Some(replica_election_results),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture::storage_with_topic, Error};
+    use tansu_kafka_sans_io::{ApiKey, Frame, Header};
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const TOPIC: &str = "pqr";
+
+    // the response is encoded in, and decoded from, an api version
+    fn round_trip(body: Body, api_version: i16) -> Result<Body> {
+        let api_key = ApiKey::ElectLeaders;
+
+        Frame::encode_response(
+            Header::Response { correlation_id: 7 },
+            body,
+            api_key,
+            api_version,
+        )
+        .and_then(|encoded| Frame::decode_response(&encoded, api_key, api_version))
+        .map(|frame| frame.body)
+        .map_err(Into::into)
+    }
+
+    fn results(body: Body) -> Vec<(String, i32, ErrorCode)> {
+        let Body::ElectLeadersResponse {
+            replica_election_results: Some(replica_election_results),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        replica_election_results
+            .into_iter()
+            .flat_map(|result| {
+                result
+                    .partition_result
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |partition| {
+                        (
+                            result.topic.clone(),
+                            partition.partition_id,
+                            ErrorCode::try_from(partition.error_code).expect("error code"),
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn preferred_v2() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let body = ElectLeadersRequest::with_storage(storage_with_topic(TOPIC, 2).await?)
+            .response(
+                Some(ELECTION_TYPE_PREFERRED),
+                Some(&[
+                    TopicPartitions {
+                        topic: TOPIC.into(),
+                        partitions: Some(vec![0, 2]),
+                    },
+                    TopicPartitions {
+                        topic: "xyz".into(),
+                        partitions: Some(vec![0]),
+                    },
+                ]),
+                60_000,
+            )
+            .await
+            .and_then(|body| round_trip(body, 2))?;
+
+        assert!(matches!(
+            body,
+            Body::ElectLeadersResponse {
+                error_code: Some(0),
+                ..
+            }
+        ));
+
+        assert_eq!(
+            vec![
+                (TOPIC.into(), 0, ErrorCode::ElectionNotNeeded),
+                (TOPIC.into(), 2, ErrorCode::UnknownTopicOrPartition),
+                ("xyz".into(), 0, ErrorCode::UnknownTopicOrPartition),
+            ],
+            results(body)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn all_partitions_v2() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let body = ElectLeadersRequest::with_storage(storage_with_topic(TOPIC, 2).await?)
+            .response(Some(ELECTION_TYPE_UNCLEAN), None, 60_000)
+            .await
+            .and_then(|body| round_trip(body, 2))?;
+
+        assert_eq!(
+            vec![
+                (TOPIC.into(), 0, ErrorCode::ElectionNotNeeded),
+                (TOPIC.into(), 1, ErrorCode::ElectionNotNeeded),
+            ],
+            results(body)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn v0() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let body = ElectLeadersRequest::with_storage(storage_with_topic(TOPIC, 2).await?)
+            .response(
+                None,
+                Some(&[TopicPartitions {
+                    topic: TOPIC.into(),
+                    partitions: Some(vec![1]),
+                }]),
+                60_000,
+            )
+            .await
+            .and_then(|body| round_trip(body, 0))?;
+
+        assert!(matches!(
+            body,
+            Body::ElectLeadersResponse {
+                error_code: None,
+                ..
+            }
+        ));
+
+        assert_eq!(
+            vec![(TOPIC.into(), 1, ErrorCode::ElectionNotNeeded)],
+            results(body)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unknown_election_type() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let body = ElectLeadersRequest::with_storage(storage_with_topic(TOPIC, 2).await?)
+            .response(
+                Some(2),
+                Some(&[TopicPartitions {
+                    topic: TOPIC.into(),
+                    partitions: Some(vec![0]),
+                }]),
+                60_000,
+            )
+            .await?;
+
+        assert_eq!(
+            vec![(TOPIC.into(), 0, ErrorCode::InvalidRequest)],
+            results(body)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1537 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The response to a request that could not be handled.
+//!
+//! Rather than closing the connection, a request that fails is answered
+//! with the smallest valid response to it, having the error code at the top
+//! level or on each of the resources in the request, as the api requires.
+//! Every field that may be encoded is populated, so that the response is
+//! valid in each version of the api.
+
+use bytes::Bytes;
+use std::{collections::BTreeSet, fmt};
+use tansu_kafka_sans_io::{
+    add_partitions_to_txn_response::{
+        AddPartitionsToTxnPartitionResult, AddPartitionsToTxnResult, AddPartitionsToTxnTopicResult,
+    },
+    alter_client_quotas_response,
+    alter_user_scram_credentials_response::AlterUserScramCredentialsResult,
+    consumer_group_describe_response,
+    create_acls_response::AclCreationResult,
+    create_partitions_response::CreatePartitionsTopicResult,
+    create_topics_response::CreatableTopicResult,
+    delete_acls_response::DeleteAclsFilterResult,
+    delete_records_response::{DeleteRecordsPartitionResult, DeleteRecordsTopicResult},
+    delete_topics_response::DeletableTopicResult,
+    describe_configs_response::DescribeConfigsResult,
+    describe_groups_response::DescribedGroup,
+    describe_producers_response, describe_transactions_response,
+    elect_leaders_response::{PartitionResult, ReplicaElectionResult},
+    fetch_request::FetchTopic,
+    fetch_response::{
+        EpochEndOffset, FetchableTopicResponse, LeaderIdAndEpoch, PartitionData, SnapshotId,
+    },
+    list_offsets_response::{ListOffsetsPartitionResponse, ListOffsetsTopicResponse},
+    metadata_response::MetadataResponseTopic,
+    offset_commit_response::{OffsetCommitResponsePartition, OffsetCommitResponseTopic},
+    offset_fetch_request::{OffsetFetchRequestGroup, OffsetFetchRequestTopic},
+    offset_for_leader_epoch_request::OffsetForLeaderTopic,
+    offset_for_leader_epoch_response::{self, OffsetForLeaderTopicResult},
+    primitive::uuid::Uuid,
+    produce_response::{PartitionProduceResponse, TopicProduceResponse},
+    response::{
+        FetchResponse, FindCoordinatorResponse, MetadataResponse, OffsetFetchResponse,
+        OffsetFetchTopic, ProduceResponse,
+    },
+    txn_offset_commit_response::{TxnOffsetCommitResponsePartition, TxnOffsetCommitResponseTopic},
+    Body, ErrorCode,
+};
+use tansu_storage::NULL_TOPIC_ID;
+
+// the partition indexes of each named resource in a request
+type Indexes = Vec<(String, Vec<i32>)>;
+
+pub(super) fn partition_produce_error(
+    index: i32,
+    error_code: ErrorCode,
+) -> PartitionProduceResponse {
+    PartitionProduceResponse {
+        index,
+        error_code: error_code.into(),
+        base_offset: -1,
+        log_append_time_ms: Some(-1),
+        log_start_offset: Some(0),
+        record_errors: Some([].into()),
+        error_message: None,
+        current_leader: None,
+    }
+}
+
+pub(super) fn produce_error(
+    name: String,
+    indexes: Vec<i32>,
+    error_code: ErrorCode,
+) -> TopicProduceResponse {
+    TopicProduceResponse {
+        name,
+        partition_responses: Some(
+            indexes
+                .into_iter()
+                .map(|index| partition_produce_error(index, error_code))
+                .collect(),
+        ),
+    }
+}
+
+pub(super) fn fetch_error(fetch: &FetchTopic, error_code: ErrorCode) -> FetchableTopicResponse {
+    FetchableTopicResponse {
+        topic: fetch.topic.clone(),
+        topic_id: fetch.topic_id.or(Some(NULL_TOPIC_ID)),
+        partitions: fetch.partitions.as_ref().map(|partitions| {
+            partitions
+                .iter()
+                .map(|partition| PartitionData {
+                    partition_index: partition.partition,
+                    error_code: error_code.into(),
+                    high_watermark: -1,
+                    last_stable_offset: Some(-1),
+                    log_start_offset: Some(-1),
+                    diverging_epoch: Some(EpochEndOffset {
+                        epoch: -1,
+                        end_offset: -1,
+                    }),
+                    current_leader: Some(LeaderIdAndEpoch {
+                        leader_id: -1,
+                        leader_epoch: -1,
+                    }),
+                    snapshot_id: Some(SnapshotId {
+                        end_offset: -1,
+                        epoch: -1,
+                    }),
+                    aborted_transactions: Some([].into()),
+                    preferred_read_replica: Some(-1),
+                    records: None,
+                })
+                .collect()
+        }),
+    }
+}
+
+pub(super) fn offset_for_leader_error(
+    topic: &OffsetForLeaderTopic,
+    error_code: ErrorCode,
+) -> OffsetForLeaderTopicResult {
+    OffsetForLeaderTopicResult {
+        topic: topic.topic.clone(),
+        partitions: topic.partitions.as_ref().map(|partitions| {
+            partitions
+                .iter()
+                .map(
+                    |partition| offset_for_leader_epoch_response::EpochEndOffset {
+                        error_code: error_code.into(),
+                        partition: partition.partition,
+                        leader_epoch: Some(-1),
+                        end_offset: -1,
+                    },
+                )
+                .collect()
+        }),
+    }
+}
+
+pub(super) fn metadata_error(
+    name: Option<String>,
+    topic_id: Option<Uuid>,
+    error_code: ErrorCode,
+) -> MetadataResponseTopic {
+    MetadataResponseTopic {
+        error_code: error_code.into(),
+        name,
+        topic_id: topic_id.or(Some(NULL_TOPIC_ID)),
+        is_internal: Some(false),
+        partitions: Some([].into()),
+        topic_authorized_operations: Some(i32::MIN),
+    }
+}
+
+pub(super) fn offset_commit_error(
+    name: String,
+    indexes: impl IntoIterator<Item = i32>,
+    error_code: ErrorCode,
+) -> OffsetCommitResponseTopic {
+    OffsetCommitResponseTopic {
+        name,
+        partitions: Some(
+            indexes
+                .into_iter()
+                .map(|partition_index| OffsetCommitResponsePartition {
+                    partition_index,
+                    error_code: error_code.into(),
+                })
+                .collect(),
+        ),
+    }
+}
+
+pub(super) fn txn_offset_commit_error(
+    name: String,
+    indexes: impl IntoIterator<Item = i32>,
+    error_code: ErrorCode,
+) -> TxnOffsetCommitResponseTopic {
+    TxnOffsetCommitResponseTopic {
+        name,
+        partitions: Some(
+            indexes
+                .into_iter()
+                .map(|partition_index| TxnOffsetCommitResponsePartition {
+                    partition_index,
+                    error_code: error_code.into(),
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Offsets fetched by topic and by group, each partition with this error,
+/// so that the response is valid in every version.
+pub(crate) fn offset_fetch_error(
+    topics: Option<&[OffsetFetchRequestTopic]>,
+    groups: Option<&[OffsetFetchRequestGroup]>,
+    error_code: ErrorCode,
+) -> Body {
+    fn partitions(
+        topic: OffsetFetchTopic,
+        indexes: Option<&[i32]>,
+        error_code: ErrorCode,
+    ) -> OffsetFetchTopic {
+        indexes
+            .unwrap_or_default()
+            .iter()
+            .fold(topic, |topic, partition_index| {
+                topic.partition(*partition_index, -1, error_code)
+            })
+    }
+
+    let builder = OffsetFetchResponse::builder()
+        .error_code(error_code)
+        .topics([])
+        .groups([]);
+
+    let builder = topics
+        .unwrap_or_default()
+        .iter()
+        .fold(builder, |builder, topic| {
+            builder.topic(&topic.name, |offsets| {
+                partitions(offsets, topic.partition_indexes.as_deref(), error_code)
+            })
+        });
+
+    groups
+        .unwrap_or_default()
+        .iter()
+        .fold(builder, |builder, group| {
+            group
+                .topics
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .fold(builder, |builder, topic| {
+                    builder.group(&group.group_id, &topic.name, |offsets| {
+                        partitions(offsets, topic.partition_indexes.as_deref(), error_code)
+                    })
+                })
+                .group_error_code(&group.group_id, error_code)
+        })
+        .build()
+        .expect("offsets are not version checked")
+}
+
+fn add_partitions_to_txn_error(
+    topics: Vec<(String, Option<Vec<i32>>)>,
+    error_code: ErrorCode,
+) -> Vec<AddPartitionsToTxnTopicResult> {
+    topics
+        .into_iter()
+        .map(|(name, partitions)| AddPartitionsToTxnTopicResult {
+            name,
+            results_by_partition: partitions.map(|partitions| {
+                partitions
+                    .into_iter()
+                    .map(|partition_index| AddPartitionsToTxnPartitionResult {
+                        partition_index,
+                        partition_error_code: error_code.into(),
+                    })
+                    .collect()
+            }),
+        })
+        .collect()
+}
+
+/// The response to this request with the error code, or none when the
+/// request is not answered with an error, as with SASL, where the
+/// connection is closed instead.
+pub fn error_response(cluster_id: &str, request: &Body, error_code: ErrorCode) -> Option<Body> {
+    ErrorResponse::new(cluster_id, request).map(|response| response.with(error_code))
+}
+
+/// An error response to a request, taken before the request is handled.
+///
+/// Only the fields of the request needed by the response are kept, so
+/// that the request itself can be handed to its handler.
+pub struct ErrorResponse(Box<dyn FnOnce(ErrorCode) -> Body + Send>);
+
+impl fmt::Debug for ErrorResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(stringify!(ErrorResponse)).finish()
+    }
+}
+
+fn answer<F>(f: F) -> Option<ErrorResponse>
+where
+    F: FnOnce(ErrorCode) -> Body + Send + 'static,
+{
+    Some(ErrorResponse(Box::new(f)))
+}
+
+impl ErrorResponse {
+    /// The error response to this request, or none when the request is not
+    /// answered with an error.
+    pub fn new(cluster_id: &str, request: &Body) -> Option<Self> {
+        match request {
+            Body::AddOffsetsToTxnRequest { .. } => {
+                answer(|error_code| Body::AddOffsetsToTxnResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::AddPartitionsToTxnRequest {
+                transactions,
+                v_3_and_below_topics,
+                ..
+            } => {
+                let transactions = transactions
+                    .iter()
+                    .flatten()
+                    .map(|transaction| {
+                        (
+                            transaction.transactional_id.clone(),
+                            transaction
+                                .topics
+                                .iter()
+                                .flatten()
+                                .map(|topic| (topic.name.clone(), topic.partitions.clone()))
+                                .collect::<Vec<_>>(),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let topics = v_3_and_below_topics
+                    .iter()
+                    .flatten()
+                    .map(|topic| (topic.name.clone(), topic.partitions.clone()))
+                    .collect::<Vec<_>>();
+
+                answer(move |error_code| Body::AddPartitionsToTxnResponse {
+                    throttle_time_ms: 0,
+                    error_code: Some(error_code.into()),
+                    results_by_transaction: Some(
+                        transactions
+                            .into_iter()
+                            .map(|(transactional_id, topics)| AddPartitionsToTxnResult {
+                                transactional_id,
+                                topic_results: Some(add_partitions_to_txn_error(
+                                    topics, error_code,
+                                )),
+                            })
+                            .collect(),
+                    ),
+                    results_by_topic_v_3_and_below: Some(add_partitions_to_txn_error(
+                        topics, error_code,
+                    )),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::AllocateProducerIdsRequest { .. } => {
+                answer(|error_code| Body::AllocateProducerIdsResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    producer_id_start: -1,
+                    producer_id_len: 0,
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::AlterClientQuotasRequest { entries, .. } => {
+                let entities = entries
+                    .iter()
+                    .flatten()
+                    .map(|entry| {
+                        entry.entity.as_ref().map(|entity| {
+                            entity
+                                .iter()
+                                .map(|entity| alter_client_quotas_response::EntityData {
+                                    entity_type: entity.entity_type.clone(),
+                                    entity_name: entity.entity_name.clone(),
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                answer(move |error_code| Body::AlterClientQuotasResponse {
+                    throttle_time_ms: 0,
+                    entries: Some(
+                        entities
+                            .into_iter()
+                            .map(|entity| alter_client_quotas_response::EntryData {
+                                error_code: error_code.into(),
+                                error_message: None,
+                                entity,
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::AlterPartitionRequest { .. } => {
+                answer(|error_code| Body::AlterPartitionResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    topics: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::AlterPartitionReassignmentsRequest { .. } => {
+                answer(|error_code| Body::AlterPartitionReassignmentsResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    error_message: None,
+                    responses: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::AlterUserScramCredentialsRequest {
+                deletions,
+                upsertions,
+                ..
+            } => {
+                let users = deletions
+                    .iter()
+                    .flatten()
+                    .map(|deletion| deletion.name.clone())
+                    .chain(
+                        upsertions
+                            .iter()
+                            .flatten()
+                            .map(|upsertion| upsertion.name.clone()),
+                    )
+                    .collect::<BTreeSet<_>>();
+
+                answer(move |error_code| Body::AlterUserScramCredentialsResponse {
+                    throttle_time_ms: 0,
+                    results: Some(
+                        users
+                            .into_iter()
+                            .map(|user| AlterUserScramCredentialsResult {
+                                user,
+                                error_code: error_code.into(),
+                                error_message: None,
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::ApiVersionsRequest { .. } => answer(|error_code| Body::ApiVersionsResponse {
+                error_code: error_code.into(),
+                api_keys: Some([].into()),
+                throttle_time_ms: Some(0),
+                supported_features: None,
+                finalized_features_epoch: None,
+                finalized_features: None,
+                zk_migration_ready: None,
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::AssignReplicasToDirsRequest { .. } => {
+                answer(|error_code| Body::AssignReplicasToDirsResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    directories: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::BeginQuorumEpochRequest { .. } => {
+                answer(|error_code| Body::BeginQuorumEpochResponse {
+                    error_code: error_code.into(),
+                    topics: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::BrokerHeartbeatRequest { .. } => {
+                answer(|error_code| Body::BrokerHeartbeatResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    is_caught_up: false,
+                    is_fenced: true,
+                    should_shut_down: false,
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::BrokerRegistrationRequest { .. } => {
+                answer(|error_code| Body::BrokerRegistrationResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    broker_epoch: -1,
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::ConsumerGroupDescribeRequest { group_ids, .. } => {
+                let group_ids = group_ids.clone().unwrap_or_default();
+
+                answer(move |error_code| Body::ConsumerGroupDescribeResponse {
+                    throttle_time_ms: 0,
+                    groups: Some(
+                        group_ids
+                            .into_iter()
+                            .map(
+                                |group_id| consumer_group_describe_response::DescribedGroup {
+                                    error_code: error_code.into(),
+                                    group_id,
+                                    authorized_operations: i32::MIN,
+                                    ..Default::default()
+                                },
+                            )
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::ConsumerGroupHeartbeatRequest { .. } => {
+                answer(|error_code| Body::ConsumerGroupHeartbeatResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    error_message: None,
+                    member_id: None,
+                    member_epoch: 0,
+                    heartbeat_interval_ms: 0,
+                    assignment: None,
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::ControlledShutdownRequest { .. } => {
+                answer(|error_code| Body::ControlledShutdownResponse {
+                    error_code: error_code.into(),
+                    remaining_partitions: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::ControllerRegistrationRequest { .. } => {
+                answer(|error_code| Body::ControllerRegistrationResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    error_message: None,
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::CreateAclsRequest { creations, .. } => {
+                let creations = creations.as_ref().map_or(0, Vec::len);
+
+                answer(move |error_code| Body::CreateAclsResponse {
+                    throttle_time_ms: 0,
+                    results: Some(
+                        (0..creations)
+                            .map(|_| AclCreationResult {
+                                error_code: error_code.into(),
+                                error_message: None,
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::CreatePartitionsRequest { topics, .. } => {
+                let names = topics
+                    .iter()
+                    .flatten()
+                    .map(|topic| topic.name.clone())
+                    .collect::<Vec<_>>();
+
+                answer(move |error_code| Body::CreatePartitionsResponse {
+                    throttle_time_ms: 0,
+                    results: Some(
+                        names
+                            .into_iter()
+                            .map(|name| CreatePartitionsTopicResult {
+                                name,
+                                error_code: error_code.into(),
+                                error_message: None,
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::CreateTopicsRequest { topics, .. } => {
+                let names = topics
+                    .iter()
+                    .flatten()
+                    .map(|topic| topic.name.clone())
+                    .collect::<Vec<_>>();
+
+                answer(move |error_code| Body::CreateTopicsResponse {
+                    throttle_time_ms: Some(0),
+                    topics: Some(
+                        names
+                            .into_iter()
+                            .map(|name| CreatableTopicResult {
+                                name,
+                                topic_id: Some(NULL_TOPIC_ID),
+                                error_code: error_code.into(),
+                                error_message: None,
+                                topic_config_error_code: None,
+                                num_partitions: Some(-1),
+                                replication_factor: Some(-1),
+                                configs: Some([].into()),
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DeleteAclsRequest { filters, .. } => {
+                let filters = filters.as_ref().map_or(0, Vec::len);
+
+                answer(move |error_code| Body::DeleteAclsResponse {
+                    throttle_time_ms: 0,
+                    filter_results: Some(
+                        (0..filters)
+                            .map(|_| DeleteAclsFilterResult {
+                                error_code: error_code.into(),
+                                error_message: None,
+                                matching_acls: Some([].into()),
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DeleteRecordsRequest { topics, .. } => {
+                let topics = indexes(topics.iter().flatten().map(|topic| {
+                    (
+                        &topic.name,
+                        topic
+                            .partitions
+                            .iter()
+                            .flatten()
+                            .map(|partition| partition.partition_index),
+                    )
+                }));
+
+                answer(move |error_code| Body::DeleteRecordsResponse {
+                    throttle_time_ms: 0,
+                    topics: Some(
+                        topics
+                            .into_iter()
+                            .map(|(name, indexes)| DeleteRecordsTopicResult {
+                                name,
+                                partitions: Some(
+                                    indexes
+                                        .into_iter()
+                                        .map(|partition_index| DeleteRecordsPartitionResult {
+                                            partition_index,
+                                            low_watermark: -1,
+                                            error_code: error_code.into(),
+                                        })
+                                        .collect(),
+                                ),
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DeleteTopicsRequest {
+                topics,
+                topic_names,
+                ..
+            } => {
+                let topics = topics
+                    .iter()
+                    .flatten()
+                    .map(|topic| (topic.name.clone(), Some(topic.topic_id)))
+                    .chain(
+                        topic_names
+                            .iter()
+                            .flatten()
+                            .map(|name| (Some(name.clone()), None)),
+                    )
+                    .collect::<Vec<_>>();
+
+                answer(move |error_code| Body::DeleteTopicsResponse {
+                    throttle_time_ms: Some(0),
+                    responses: Some(
+                        topics
+                            .into_iter()
+                            .map(|(name, topic_id)| DeletableTopicResult {
+                                name,
+                                topic_id: topic_id.or(Some(NULL_TOPIC_ID)),
+                                error_code: error_code.into(),
+                                error_message: None,
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DescribeAclsRequest { .. } => answer(|error_code| Body::DescribeAclsResponse {
+                throttle_time_ms: 0,
+                error_code: error_code.into(),
+                error_message: None,
+                resources: Some([].into()),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::DescribeClientQuotasRequest { .. } => {
+                answer(|error_code| Body::DescribeClientQuotasResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    error_message: None,
+                    entries: None,
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DescribeClusterRequest { endpoint_type, .. } => {
+                let endpoint_type = *endpoint_type;
+                let cluster_id = cluster_id.to_owned();
+
+                answer(move |error_code| Body::DescribeClusterResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    error_message: None,
+                    endpoint_type,
+                    cluster_id,
+                    controller_id: -1,
+                    brokers: Some([].into()),
+                    cluster_authorized_operations: i32::MIN,
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DescribeConfigsRequest { resources, .. } => {
+                let resources = resources
+                    .iter()
+                    .flatten()
+                    .map(|resource| (resource.resource_type, resource.resource_name.clone()))
+                    .collect::<Vec<_>>();
+
+                answer(move |error_code| Body::DescribeConfigsResponse {
+                    throttle_time_ms: 0,
+                    results: Some(
+                        resources
+                            .into_iter()
+                            .map(|(resource_type, resource_name)| DescribeConfigsResult {
+                                error_code: error_code.into(),
+                                error_message: None,
+                                resource_type,
+                                resource_name,
+                                configs: Some([].into()),
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DescribeGroupsRequest { groups, .. } => {
+                let groups = groups.clone().unwrap_or_default();
+
+                answer(move |error_code| Body::DescribeGroupsResponse {
+                    throttle_time_ms: Some(0),
+                    groups: Some(
+                        groups
+                            .into_iter()
+                            .map(|group_id| DescribedGroup {
+                                error_code: error_code.into(),
+                                group_id,
+                                group_state: String::from(""),
+                                protocol_type: String::from(""),
+                                protocol_data: String::from(""),
+                                members: Some([].into()),
+                                authorized_operations: Some(i32::MIN),
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DescribeProducersRequest { topics, .. } => {
+                let topics = indexes(topics.iter().flatten().map(|topic| {
+                    (
+                        &topic.name,
+                        topic.partition_indexes.iter().flatten().copied(),
+                    )
+                }));
+
+                answer(move |error_code| Body::DescribeProducersResponse {
+                    throttle_time_ms: 0,
+                    topics: Some(
+                        topics
+                            .into_iter()
+                            .map(
+                                |(name, indexes)| describe_producers_response::TopicResponse {
+                                    name,
+                                    partitions: Some(
+                                        indexes
+                                            .into_iter()
+                                            .map(|partition_index| {
+                                                describe_producers_response::PartitionResponse {
+                                                    partition_index,
+                                                    error_code: error_code.into(),
+                                                    error_message: None,
+                                                    active_producers: Some([].into()),
+                                                }
+                                            })
+                                            .collect(),
+                                    ),
+                                },
+                            )
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DescribeTransactionsRequest {
+                transactional_ids, ..
+            } => {
+                let transactional_ids = transactional_ids.clone().unwrap_or_default();
+
+                answer(move |error_code| Body::DescribeTransactionsResponse {
+                    throttle_time_ms: 0,
+                    transaction_states: Some(
+                        transactional_ids
+                            .into_iter()
+                            .map(|transactional_id| {
+                                describe_transactions_response::TransactionState {
+                                    error_code: error_code.into(),
+                                    transactional_id,
+                                    transaction_state: String::from(""),
+                                    transaction_timeout_ms: 0,
+                                    transaction_start_time_ms: -1,
+                                    producer_id: -1,
+                                    producer_epoch: -1,
+                                    topics: Some([].into()),
+                                }
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::DescribeUserScramCredentialsRequest { .. } => {
+                answer(|error_code| Body::DescribeUserScramCredentialsResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    error_message: None,
+                    results: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::ElectLeadersRequest {
+                election_type,
+                topic_partitions,
+                ..
+            } => {
+                let election_type = *election_type;
+
+                let topic_partitions =
+                    indexes(topic_partitions.iter().flatten().map(|topic_partition| {
+                        (
+                            &topic_partition.topic,
+                            topic_partition.partitions.iter().flatten().copied(),
+                        )
+                    }));
+
+                answer(move |error_code| Body::ElectLeadersResponse {
+                    throttle_time_ms: 0,
+                    error_code: election_type.map(|_| error_code.into()),
+                    replica_election_results: Some(
+                        topic_partitions
+                            .into_iter()
+                            .map(|(topic, partitions)| ReplicaElectionResult {
+                                topic,
+                                partition_result: Some(
+                                    partitions
+                                        .into_iter()
+                                        .map(|partition_id| PartitionResult {
+                                            partition_id,
+                                            error_code: error_code.into(),
+                                            error_message: None,
+                                        })
+                                        .collect(),
+                                ),
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::EndQuorumEpochRequest { .. } => {
+                answer(|error_code| Body::EndQuorumEpochResponse {
+                    error_code: error_code.into(),
+                    topics: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::EndTxnRequest { .. } => answer(|error_code| Body::EndTxnResponse {
+                throttle_time_ms: 0,
+                error_code: error_code.into(),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::EnvelopeRequest { .. } => answer(|error_code| Body::EnvelopeResponse {
+                response_data: None,
+                error_code: error_code.into(),
+                unknown_tagged_fields: vec![],
+            }),
+
+            // the top level error code is for the fetch session
+            Body::FetchRequest {
+                session_id, topics, ..
+            } => {
+                let session_id = session_id.unwrap_or_default();
+                let topics = topics.clone().unwrap_or_default();
+
+                answer(move |error_code| {
+                    FetchResponse::builder()
+                        .session_id(session_id)
+                        .responses(topics.iter().map(|topic| fetch_error(topic, error_code)))
+                        .build()
+                        .expect("fetch is not version checked")
+                })
+            }
+
+            Body::FetchSnapshotRequest { .. } => answer(|error_code| Body::FetchSnapshotResponse {
+                throttle_time_ms: 0,
+                error_code: error_code.into(),
+                topics: Some([].into()),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::FindCoordinatorRequest {
+                coordinator_keys, ..
+            } => {
+                let keys = coordinator_keys.clone().unwrap_or_default();
+
+                answer(move |error_code| {
+                    FindCoordinatorResponse::builder()
+                        .error(error_code, None)
+                        .coordinator(-1, "", -1)
+                        .keys(keys)
+                        .build()
+                        .expect("a coordinator is present")
+                })
+            }
+
+            Body::HeartbeatRequest { .. } => answer(|error_code| Body::HeartbeatResponse {
+                throttle_time_ms: Some(0),
+                error_code: error_code.into(),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::InitProducerIdRequest { .. } => {
+                answer(|error_code| Body::InitProducerIdResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    producer_id: -1,
+                    producer_epoch: -1,
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::JoinGroupRequest { .. } => answer(|error_code| Body::JoinGroupResponse {
+                throttle_time_ms: Some(0),
+                error_code: error_code.into(),
+                generation_id: -1,
+                protocol_type: None,
+                protocol_name: None,
+                leader: String::from(""),
+                skip_assignment: Some(false),
+                member_id: String::from(""),
+                members: Some([].into()),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::LeaderAndIsrRequest { .. } => answer(|error_code| Body::LeaderAndIsrResponse {
+                error_code: error_code.into(),
+                partition_errors: Some([].into()),
+                topics: Some([].into()),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::LeaveGroupRequest { .. } => answer(|error_code| Body::LeaveGroupResponse {
+                throttle_time_ms: Some(0),
+                error_code: error_code.into(),
+                members: Some([].into()),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::ListGroupsRequest { .. } => answer(|error_code| Body::ListGroupsResponse {
+                throttle_time_ms: Some(0),
+                error_code: error_code.into(),
+                groups: Some([].into()),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::ListTransactionsRequest { .. } => {
+                answer(|error_code| Body::ListTransactionsResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    unknown_state_filters: Some([].into()),
+                    transaction_states: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::ListOffsetsRequest { topics, .. } => {
+                let topics = indexes(topics.iter().flatten().map(|topic| {
+                    (
+                        &topic.name,
+                        topic
+                            .partitions
+                            .iter()
+                            .flatten()
+                            .map(|partition| partition.partition_index),
+                    )
+                }));
+
+                answer(move |error_code| Body::ListOffsetsResponse {
+                    throttle_time_ms: Some(0),
+                    topics: Some(
+                        topics
+                            .into_iter()
+                            .map(|(name, indexes)| ListOffsetsTopicResponse {
+                                name,
+                                partitions: Some(
+                                    indexes
+                                        .into_iter()
+                                        .map(|partition_index| ListOffsetsPartitionResponse {
+                                            partition_index,
+                                            error_code: error_code.into(),
+                                            old_style_offsets: Some([].into()),
+                                            timestamp: Some(-1),
+                                            offset: Some(-1),
+                                            leader_epoch: Some(-1),
+                                        })
+                                        .collect(),
+                                ),
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::ListPartitionReassignmentsRequest { .. } => {
+                answer(|error_code| Body::ListPartitionReassignmentsResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    error_message: None,
+                    topics: Some([].into()),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::MetadataRequest { topics, .. } => {
+                let cluster_id = cluster_id.to_owned();
+
+                let topics = topics
+                    .iter()
+                    .flatten()
+                    .map(|topic| (topic.name.clone(), topic.topic_id))
+                    .collect::<Vec<_>>();
+
+                answer(move |error_code| {
+                    MetadataResponse::builder()
+                        .cluster_id(Some(cluster_id.as_str()))
+                        .topics(
+                            topics
+                                .into_iter()
+                                .map(|(name, topic_id)| metadata_error(name, topic_id, error_code)),
+                        )
+                        .build()
+                        .expect("metadata is not version checked")
+                })
+            }
+
+            Body::OffsetCommitRequest { topics, .. } => {
+                let topics = indexes(topics.iter().flatten().map(|topic| {
+                    (
+                        &topic.name,
+                        topic
+                            .partitions
+                            .iter()
+                            .flatten()
+                            .map(|partition| partition.partition_index),
+                    )
+                }));
+
+                answer(move |error_code| Body::OffsetCommitResponse {
+                    throttle_time_ms: Some(0),
+                    topics: Some(
+                        topics
+                            .into_iter()
+                            .map(|(name, indexes)| offset_commit_error(name, indexes, error_code))
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::OffsetFetchRequest { topics, groups, .. } => {
+                let (topics, groups) = (topics.clone(), groups.clone());
+
+                answer(move |error_code| {
+                    offset_fetch_error(topics.as_deref(), groups.as_deref(), error_code)
+                })
+            }
+
+            Body::OffsetForLeaderEpochRequest { topics, .. } => {
+                let topics = topics.clone().unwrap_or_default();
+
+                answer(move |error_code| Body::OffsetForLeaderEpochResponse {
+                    throttle_time_ms: Some(0),
+                    topics: Some(
+                        topics
+                            .iter()
+                            .map(|topic| offset_for_leader_error(topic, error_code))
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::ProduceRequest { topic_data, .. } => {
+                let topics = indexes(topic_data.iter().flatten().map(|topic| {
+                    (
+                        &topic.name,
+                        topic
+                            .partition_data
+                            .iter()
+                            .flatten()
+                            .map(|partition| partition.index),
+                    )
+                }));
+
+                answer(move |error_code| {
+                    ProduceResponse::builder()
+                        .responses(
+                            topics
+                                .into_iter()
+                                .map(|(name, indexes)| produce_error(name, indexes, error_code)),
+                        )
+                        .build()
+                        .expect("produce is valid at every version")
+                })
+            }
+
+            Body::StopReplicaRequest { .. } => answer(|error_code| Body::StopReplicaResponse {
+                error_code: error_code.into(),
+                partition_errors: Some([].into()),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::SyncGroupRequest { .. } => answer(|error_code| Body::SyncGroupResponse {
+                throttle_time_ms: Some(0),
+                error_code: error_code.into(),
+                protocol_type: None,
+                protocol_name: None,
+                assignment: Bytes::new(),
+                unknown_tagged_fields: vec![],
+            }),
+
+            Body::TxnOffsetCommitRequest { topics, .. } => {
+                let topics = indexes(topics.iter().flatten().map(|topic| {
+                    (
+                        &topic.name,
+                        topic
+                            .partitions
+                            .iter()
+                            .flatten()
+                            .map(|partition| partition.partition_index),
+                    )
+                }));
+
+                answer(move |error_code| Body::TxnOffsetCommitResponse {
+                    throttle_time_ms: 0,
+                    topics: Some(
+                        topics
+                            .into_iter()
+                            .map(|(name, indexes)| {
+                                txn_offset_commit_error(name, indexes, error_code)
+                            })
+                            .collect(),
+                    ),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::UnregisterBrokerRequest { .. } => {
+                answer(|error_code| Body::UnregisterBrokerResponse {
+                    throttle_time_ms: 0,
+                    error_code: error_code.into(),
+                    error_message: None,
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::UpdateMetadataRequest { .. } => {
+                answer(|error_code| Body::UpdateMetadataResponse {
+                    error_code: error_code.into(),
+                    unknown_tagged_fields: vec![],
+                })
+            }
+
+            Body::VoteRequest { .. } => answer(|error_code| Body::VoteResponse {
+                error_code: error_code.into(),
+                topics: Some([].into()),
+                unknown_tagged_fields: vec![],
+            }),
+
+            _ => None,
+        }
+    }
+
+    /// The response with this error code.
+    pub fn with(self, error_code: ErrorCode) -> Body {
+        (self.0)(error_code)
+    }
+}
+
+fn indexes<'a, I>(topics: impl Iterator<Item = (&'a String, I)>) -> Indexes
+where
+    I: Iterator<Item = i32>,
+{
+    topics
+        .map(|(name, indexes)| (name.clone(), indexes.collect()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{
+            handler::{ConnectionContext, RequestHandler},
+            listener::ListenerConfig,
+            registry::Registry,
+            Broker,
+        },
+        coordinator::group::{administrator::Controller, Coordinator},
+        Error, Result,
+    };
+    use async_trait::async_trait;
+    use object_store::memory::InMemory;
+    use std::{net::SocketAddr, time::Duration};
+    use tansu_kafka_sans_io::{
+        fetch_request::FetchPartition,
+        offset_fetch_request::OffsetFetchRequestTopics,
+        produce_request::{PartitionProduceData, TopicProduceData},
+        ApiKey, Frame, Header,
+    };
+    use tansu_storage::{dynostore::DynoStore, Storage};
+    use tokio::{
+        io::{duplex, AsyncRead, AsyncReadExt, AsyncWriteExt},
+        time::timeout,
+    };
+    use url::Url;
+
+    fn fetch(topic: &str, partition: i32) -> Body {
+        Body::FetchRequest {
+            cluster_id: None,
+            replica_id: Some(-1),
+            replica_state: None,
+            max_wait_ms: 0,
+            min_bytes: 1,
+            max_bytes: Some(52_428_800),
+            isolation_level: Some(0),
+            session_id: Some(0),
+            session_epoch: Some(-1),
+            topics: Some(vec![FetchTopic {
+                topic: Some(topic.into()),
+                topic_id: None,
+                partitions: Some(vec![FetchPartition {
+                    partition,
+                    current_leader_epoch: Some(-1),
+                    fetch_offset: 0,
+                    last_fetched_epoch: Some(-1),
+                    log_start_offset: Some(-1),
+                    partition_max_bytes: 1_048_576,
+                }]),
+            }]),
+            forgotten_topics_data: Some([].into()),
+            rack_id: Some("".into()),
+            unknown_tagged_fields: vec![],
+        }
+    }
+
+    async fn response<R>(reader: &mut R, api_key: ApiKey, api_version: i16) -> Result<Frame>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut size = [0u8; 4];
+        _ = timeout(Duration::from_secs(5), reader.read_exact(&mut size))
+            .await
+            .map_err(|elapsed| Error::Message(elapsed.to_string()))??;
+
+        let mut response = vec![0u8; size.len() + usize::try_from(u32::from_be_bytes(size))?];
+        response[..4].copy_from_slice(&size);
+        _ = reader.read_exact(&mut response[4..]).await?;
+
+        Frame::decode_response(&response, api_key, api_version).map_err(Into::into)
+    }
+
+    fn partition_error_codes(frame: Frame) -> Vec<i16> {
+        let Body::FetchResponse {
+            responses: Some(responses),
+            ..
+        } = frame.body
+        else {
+            panic!("expecting a fetch response")
+        };
+
+        responses
+            .iter()
+            .flat_map(|topic| topic.partitions.iter().flatten())
+            .map(|partition| partition.error_code)
+            .collect()
+    }
+
+    // fails every fetch with an error from storage
+    #[derive(Clone, Copy, Debug)]
+    struct Failing;
+
+    #[async_trait]
+    impl<G, S> RequestHandler<G, S> for Failing
+    where
+        G: Coordinator,
+        S: Storage,
+    {
+        async fn handle(&self, _ctx: &ConnectionContext<G, S>, _body: Body) -> Result<Body> {
+            Err(Error::Storage(tansu_storage::Error::Api(
+                ErrorCode::NotLeaderOrFollower,
+            )))
+        }
+    }
+
+    async fn failed_fetch(
+        registry: Registry<Controller<DynoStore>, DynoStore>,
+    ) -> Result<Vec<i16>> {
+        let cluster = "abc";
+        let node = 12321;
+
+        let storage = DynoStore::new(cluster, node, InMemory::new());
+        let listener = Url::parse("tcp://localhost:9092")?;
+
+        let mut broker = Broker::new(
+            node,
+            cluster,
+            vec![ListenerConfig::new("broker", listener.clone(), &listener)],
+            None,
+            storage.clone(),
+            Controller::with_storage(storage)?,
+        )
+        .with_registry(registry);
+
+        _ = broker.register().await?;
+
+        let (mut client, server) = duplex(64 * 1024);
+        let peer = SocketAddr::from(([127, 0, 0, 1], 54321));
+
+        let connection = tokio::spawn(async move { broker.stream_handler(server, peer).await });
+
+        let api_version = 12;
+        let mut error_codes = vec![];
+
+        // the connection remains open after each failure
+        for correlation_id in [1, 2] {
+            client
+                .write_all(&Frame::request(
+                    Header::Request {
+                        api_key: ApiKey::Fetch.into(),
+                        api_version,
+                        correlation_id,
+                        client_id: Some("error".into()),
+                    },
+                    fetch("pqr", 0),
+                )?)
+                .await?;
+
+            let frame = response(&mut client, ApiKey::Fetch, api_version).await?;
+            assert_eq!(Header::Response { correlation_id }, frame.header);
+
+            error_codes.extend(partition_error_codes(frame));
+        }
+
+        assert!(!connection.is_finished());
+        connection.abort();
+
+        Ok(error_codes)
+    }
+
+    #[tokio::test]
+    async fn unknown_topic() -> Result<()> {
+        assert_eq!(
+            vec![i16::from(ErrorCode::UnknownTopicOrPartition); 2],
+            failed_fetch(Registry::default()).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_handler() -> Result<()> {
+        assert_eq!(
+            vec![i16::from(ErrorCode::NotLeaderOrFollower); 2],
+            failed_fetch(Registry::default().with_handler(ApiKey::Fetch, 0, 16, Failing)).await?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn encoded_in_every_version() -> Result<()> {
+        let produce = Body::ProduceRequest {
+            transactional_id: None,
+            acks: -1,
+            timeout_ms: 1_500,
+            topic_data: Some(vec![TopicProduceData {
+                name: "pqr".into(),
+                partition_data: Some(vec![PartitionProduceData {
+                    index: 2,
+                    records: None,
+                }]),
+            }]),
+            unknown_tagged_fields: vec![],
+        };
+
+        for (api_key, request, versions) in [
+            (ApiKey::Produce, produce, 0..=11),
+            (ApiKey::Fetch, fetch("pqr", 3), 0..=16),
+            (
+                ApiKey::Metadata,
+                Body::MetadataRequest {
+                    topics: Some([].into()),
+                    allow_auto_topic_creation: Some(false),
+                    include_cluster_authorized_operations: Some(false),
+                    include_topic_authorized_operations: Some(false),
+                    unknown_tagged_fields: vec![],
+                },
+                0..=12,
+            ),
+            (
+                ApiKey::OffsetFetch,
+                Body::OffsetFetchRequest {
+                    group_id: Some("abc".into()),
+                    topics: Some(vec![OffsetFetchRequestTopic {
+                        name: "pqr".into(),
+                        partition_indexes: Some(vec![0, 1]),
+                    }]),
+                    groups: Some(vec![OffsetFetchRequestGroup {
+                        group_id: "abc".into(),
+                        member_id: None,
+                        member_epoch: Some(-1),
+                        topics: Some(vec![OffsetFetchRequestTopics {
+                            name: "pqr".into(),
+                            partition_indexes: Some(vec![2]),
+                        }]),
+                    }]),
+                    require_stable: Some(false),
+                    unknown_tagged_fields: vec![],
+                },
+                0..=9,
+            ),
+            (
+                ApiKey::FindCoordinator,
+                Body::FindCoordinatorRequest {
+                    key: Some("abc".into()),
+                    key_type: Some(0),
+                    coordinator_keys: Some(vec!["abc".into()]),
+                    unknown_tagged_fields: vec![],
+                },
+                0..=5,
+            ),
+        ] {
+            for api_version in versions {
+                let body = error_response("abc", &request, ErrorCode::NotLeaderOrFollower)
+                    .expect("an error response");
+
+                let encoded = Frame::encode_response(
+                    Header::Response { correlation_id: 6 },
+                    body,
+                    api_key,
+                    api_version,
+                )?;
+
+                _ = Frame::decode_response(&encoded, api_key, api_version)
+                    .inspect_err(|error| panic!("{api_key}, v{api_version}: {error:?}"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn sasl_is_not_answered() {
+        assert!(error_response(
+            "abc",
+            &Body::SaslAuthenticateRequest {
+                auth_bytes: Bytes::new(),
+                unknown_tagged_fields: vec![],
+            },
+            ErrorCode::SaslAuthenticationFailed
+        )
+        .is_none());
+    }
+}
@@ -13,26 +13,46 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::time::{Duration, Instant};
+pub mod replica;
+pub mod session;
 
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures::future::select_all;
 use tansu_kafka_sans_io::{
     fetch_request::{FetchPartition, FetchTopic},
     fetch_response::{
         EpochEndOffset, FetchableTopicResponse, LeaderIdAndEpoch, PartitionData, SnapshotId,
     },
     metadata_response::MetadataResponseTopic,
-    record::{deflated::Batch, deflated::Frame},
+    record::{deflated::Batch, deflated::Frame, Records},
+    response::FetchResponse,
     Body, ErrorCode, IsolationLevel,
 };
-use tansu_storage::{Storage, Topition};
-use tokio::time::sleep;
+use tansu_storage::{Storage, TopicId, Topition, NULL_TOPIC_ID};
+use tokio::{sync::watch, time::sleep};
 use tracing::{debug, error};
 
-use crate::Result;
+use crate::{broker::notify::Notifications, Result};
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+use replica::{LeaderSelector, ReplicaSelector, ReplicaView};
+
+/// The largest response to a fetch, whatever the max bytes of the request,
+/// being the default fetch.max.bytes of a Kafka broker.
+pub const DEFAULT_FETCH_MAX_BYTES: u32 = 55 * 1024 * 1024;
+
+#[derive(Clone, Debug)]
 pub struct FetchRequest<S> {
     storage: S,
+    notifications: Notifications,
+    subscriptions: Vec<watch::Receiver<u64>>,
+    replica_selector: Arc<dyn ReplicaSelector>,
+    local: ReplicaView,
+    rack_id: Option<String>,
+    max_bytes: u32,
 }
 
 impl<S> FetchRequest<S>
@@ -40,14 +60,138 @@ where
     S: Storage,
 {
     pub fn with_storage(storage: S) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            notifications: Notifications::default(),
+            subscriptions: Vec::new(),
+            replica_selector: Arc::new(LeaderSelector),
+            local: ReplicaView::default(),
+            rack_id: None,
+            max_bytes: DEFAULT_FETCH_MAX_BYTES,
+        }
+    }
+
+    /// The max bytes of a request is honoured up to this limit, which is
+    /// also used when the request has no max bytes.
+    #[must_use]
+    pub fn with_max_bytes(self, max_bytes: u32) -> Self {
+        Self { max_bytes, ..self }
+    }
+
+    /// A consumer with a rack is steered to the replica chosen by this
+    /// selector, when it isn't the local broker.
+    #[must_use]
+    pub fn with_replica_selector(
+        self,
+        replica_selector: Arc<dyn ReplicaSelector>,
+        local: ReplicaView,
+    ) -> Self {
+        Self {
+            replica_selector,
+            local,
+            ..self
+        }
+    }
+
+    /// The rack of the consumer, from the rack id of its request.
+    #[must_use]
+    pub fn with_rack_id(self, rack_id: Option<String>) -> Self {
+        Self { rack_id, ..self }
+    }
+
+    // every broker shares storage, so any live broker is a replica of
+    // every partition
+    async fn replicas(&mut self) -> Result<Vec<ReplicaView>> {
+        if self.rack_id.as_deref().is_none_or(str::is_empty) {
+            return Ok(vec![]);
+        }
+
+        self.storage
+            .brokers(None)
+            .await
+            .map(|brokers| {
+                brokers
+                    .into_iter()
+                    .map(|broker| ReplicaView::new(broker.broker_id, broker.rack))
+                    .collect()
+            })
+            .map_err(Into::into)
+    }
+
+    fn preferred_read_replica(&self, tp: &Topition, replicas: &[ReplicaView]) -> Option<i32> {
+        self.rack_id
+            .as_deref()
+            .filter(|_| !replicas.is_empty())
+            .and_then(|rack_id| {
+                self.replica_selector
+                    .select(tp, rack_id, &self.local, replicas)
+            })
+            .filter(|node_id| *node_id != self.local.node_id)
+    }
+
+    // a consumer steered elsewhere is sent the offsets of the partition
+    // without any records
+    async fn redirect_partition(
+        &mut self,
+        tp: &Topition,
+        preferred_read_replica: i32,
+    ) -> Result<PartitionData> {
+        let offset_stage = self
+            .storage
+            .offset_stage(tp)
+            .await
+            .inspect_err(|error| error!(?error, ?tp))?;
+
+        Ok(PartitionData {
+            partition_index: tp.partition(),
+            error_code: ErrorCode::None.into(),
+            high_watermark: offset_stage.high_watermark(),
+            last_stable_offset: Some(offset_stage.last_stable()),
+            log_start_offset: Some(offset_stage.log_start()),
+            diverging_epoch: None,
+            current_leader: None,
+            snapshot_id: None,
+            aborted_transactions: Some([].into()),
+            preferred_read_replica: Some(preferred_read_replica),
+            records: None,
+        })
+    }
+
+    /// A fetch below min bytes is parked until one of its topitions is
+    /// produced to, as notified by a [`super::produce::ProduceRequest`]
+    /// sharing these notifications, or max wait elapses.
+    #[must_use]
+    pub fn with_notifications(self, notifications: Notifications) -> Self {
+        Self {
+            notifications,
+            ..self
+        }
+    }
+
+    // wait until a subscribed topition is produced to, returning false
+    // when the timeout elapses first
+    async fn parked(&mut self, timeout: Duration) -> bool {
+        if self.subscriptions.is_empty() {
+            sleep(timeout).await;
+            return false;
+        }
+
+        let changes = self
+            .subscriptions
+            .iter_mut()
+            .map(|subscription| Box::pin(subscription.changed()));
+
+        tokio::select! {
+            () = sleep(timeout) => false,
+            _ = select_all(changes) => true,
+        }
     }
 
     async fn fetch_partition(
         &mut self,
         max_wait_ms: Duration,
         min_bytes: u32,
-        max_bytes: &mut u32,
+        budget: &mut Budget,
         isolation: Option<IsolationLevel>,
         topic: &str,
         fetch_partition: &FetchPartition,
@@ -55,7 +199,7 @@ where
         debug!(
             ?max_wait_ms,
             ?min_bytes,
-            ?max_bytes,
+            ?budget,
             ?isolation,
             ?fetch_partition
         );
@@ -63,33 +207,62 @@ where
         let partition_index = fetch_partition.partition;
         let tp = Topition::new(topic, partition_index);
 
-        let mut batches = Vec::new();
+        // subscribe before reading, so that a produce after the read wakes us
+        self.subscriptions.push(self.notifications.subscribe(&tp));
 
-        for offset in fetch_partition.fetch_offset.. {
-            if *max_bytes == 0 {
-                break;
-            }
+        let read_committed = isolation == Some(IsolationLevel::ReadCommitted);
+
+        // a read committed fetch stops at the first batch of an ongoing transaction
+        let last_stable = if read_committed {
+            self.storage
+                .offset_stage(&tp)
+                .await
+                .map(|offset_stage| Some(offset_stage.last_stable()))
+                .inspect_err(|error| error!(?error, ?tp))?
+        } else {
+            None
+        };
+
+        let mut batches = Vec::new();
+        let mut offset = fetch_partition.fetch_offset;
+        let mut partition_max_bytes =
+            u32::try_from(fetch_partition.partition_max_bytes).unwrap_or_default();
 
-            debug!(offset);
+        while budget.is_open(partition_max_bytes) {
+            debug!(offset, partition_max_bytes, ?budget);
 
-            let mut fetched = self
+            let Some(batch) = self
                 .storage
-                .fetch(&tp, offset, min_bytes, *max_bytes)
+                .fetch(
+                    &tp,
+                    offset,
+                    min_bytes,
+                    partition_max_bytes.min(budget.remaining),
+                )
                 .await
                 .inspect(|r| debug!(?tp, ?offset, ?r))
                 .inspect_err(|error| error!(?tp, ?error))
-                .map_or(Vec::new(), |batch| vec![batch]);
-
-            *max_bytes =
-                u32::try_from(fetched.byte_size()).map(|bytes| max_bytes.saturating_sub(bytes))?;
+                .ok()
+                .filter(|batch| batch.record_count > 0)
+                .filter(|batch| {
+                    last_stable.is_none_or(|last_stable| batch.base_offset < last_stable)
+                })
+            else {
+                break;
+            };
 
-            debug!(?offset, ?fetched);
+            let size = u32::try_from(batch.byte_size())?;
 
-            if fetched.is_empty() || fetched.first().is_some_and(|batch| batch.record_count == 0) {
+            if !budget.admits(partition_max_bytes, size) {
                 break;
-            } else {
-                batches.append(&mut fetched);
             }
+
+            budget.spend(size);
+            partition_max_bytes = partition_max_bytes.saturating_sub(size);
+
+            // continue from the batch following the one fetched
+            offset = offset.max(batch.max_offset()) + 1;
+            batches.push(batch);
         }
 
         let offset_stage = self
@@ -107,27 +280,42 @@ where
             diverging_epoch: None,
             current_leader: None,
             snapshot_id: None,
-            aborted_transactions: Some([].into()),
+            aborted_transactions: if read_committed {
+                self.storage
+                    .aborted_transactions(&tp, fetch_partition.fetch_offset)
+                    .await
+                    .map(Some)
+                    .inspect_err(|error| error!(?error, ?tp))?
+            } else {
+                Some([].into())
+            },
             preferred_read_replica: Some(-1),
             records: if batches.is_empty() {
                 None
             } else {
-                Some(Frame { batches })
+                Some(Records::try_from(Frame { batches })?)
             },
         })
         .inspect(|r| debug!(?r))
     }
 
     fn unknown_topic_response(&self, fetch: &FetchTopic) -> Result<FetchableTopicResponse> {
+        // a topic identified by id alone is reported as an unknown id
+        let error_code = if fetch.topic.is_some() {
+            ErrorCode::UnknownTopicOrPartition
+        } else {
+            ErrorCode::UnknownTopicId
+        };
+
         Ok(FetchableTopicResponse {
             topic: fetch.topic.clone(),
-            topic_id: Some([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+            topic_id: fetch.topic_id.or(Some(NULL_TOPIC_ID)),
             partitions: fetch.partitions.as_ref().map(|partitions| {
                 partitions
                     .iter()
                     .map(|partition| PartitionData {
                         partition_index: partition.partition,
-                        error_code: ErrorCode::UnknownTopicOrPartition.into(),
+                        error_code: error_code.into(),
                         high_watermark: 0,
                         last_stable_offset: Some(0),
                         log_start_offset: Some(-1),
@@ -152,34 +340,81 @@ where
         })
     }
 
-    #[allow(clippy::too_many_arguments)]
     async fn fetch_topic(
         &mut self,
         max_wait_ms: Duration,
         min_bytes: u32,
-        max_bytes: &mut u32,
+        budget: &mut Budget,
         isolation: Option<IsolationLevel>,
+        replicas: &[ReplicaView],
         fetch: &FetchTopic,
-        _is_first: bool,
     ) -> Result<FetchableTopicResponse> {
         debug!(?max_wait_ms, ?min_bytes, ?isolation, ?fetch);
 
-        let metadata = self.storage.metadata(Some(&[fetch.into()])).await?;
+        let Ok(topic) = TopicId::try_from(fetch) else {
+            return self.unknown_topic_response(fetch);
+        };
+
+        let metadata = self.storage.metadata(Some(&[topic])).await?;
 
         if let Some(MetadataResponseTopic {
             topic_id,
             name: Some(name),
+            partitions: leaders,
             ..
-        }) = metadata.topics().first()
+        }) = metadata
+            .topics()
+            .first()
+            .filter(|topic| topic.error_code == i16::from(ErrorCode::None))
         {
             let mut partitions = Vec::new();
 
             for fetch_partition in fetch.partitions.as_ref().unwrap_or(&Vec::new()) {
+                let leader = leaders
+                    .as_deref()
+                    .unwrap_or_default()
+                    .iter()
+                    .find(|leader| leader.partition_index == fetch_partition.partition)
+                    .map(|leader| LeaderIdAndEpoch {
+                        leader_id: leader.leader_id,
+                        leader_epoch: leader.leader_epoch.unwrap_or(-1),
+                    });
+
+                if let Some(error_code) = leader.as_ref().and_then(|leader| {
+                    leader_epoch_error(fetch_partition.current_leader_epoch, leader.leader_epoch)
+                }) {
+                    debug!(?name, ?fetch_partition, ?leader, ?error_code);
+
+                    partitions.push(PartitionData {
+                        partition_index: fetch_partition.partition,
+                        error_code: error_code.into(),
+                        high_watermark: -1,
+                        last_stable_offset: Some(-1),
+                        log_start_offset: Some(-1),
+                        diverging_epoch: None,
+                        current_leader: leader,
+                        snapshot_id: None,
+                        aborted_transactions: Some([].into()),
+                        preferred_read_replica: Some(-1),
+                        records: None,
+                    });
+
+                    continue;
+                }
+
+                let tp = Topition::new(name.as_str(), fetch_partition.partition);
+
+                if let Some(replica) = self.preferred_read_replica(&tp, replicas) {
+                    debug!(?tp, ?replica);
+                    partitions.push(self.redirect_partition(&tp, replica).await?);
+                    continue;
+                }
+
                 let partition = self
                     .fetch_partition(
                         max_wait_ms,
                         min_bytes,
-                        max_bytes,
+                        budget,
                         isolation,
                         name,
                         fetch_partition,
@@ -203,7 +438,7 @@ where
         &mut self,
         max_wait: Duration,
         min_bytes: u32,
-        max_bytes: &mut u32,
+        max_bytes: u32,
         isolation: Option<IsolationLevel>,
         topics: &[FetchTopic],
     ) -> Result<Vec<FetchableTopicResponse>> {
@@ -213,29 +448,33 @@ where
             Ok(vec![])
         } else {
             let start = Instant::now();
-            let mut responses = vec![];
             let mut iteration = 0;
-            let mut elapsed = Duration::from_millis(0);
-            let mut bytes = 0;
 
-            while elapsed < max_wait && bytes < min_bytes {
-                debug!(?elapsed, ?max_wait, ?bytes, ?min_bytes);
+            let replicas = self.replicas().await?;
 
-                let enumerate = topics.iter().enumerate();
-                responses.clear();
+            loop {
+                self.subscriptions.clear();
 
-                for (i, fetch) in enumerate {
+                let mut budget = Budget::new(max_bytes);
+                let mut responses = vec![];
+
+                for fetch in topics {
                     let fetch_response = self
-                        .fetch_topic(max_wait, min_bytes, max_bytes, isolation, fetch, i == 0)
+                        .fetch_topic(
+                            max_wait,
+                            min_bytes,
+                            &mut budget,
+                            isolation,
+                            &replicas,
+                            fetch,
+                        )
                         .await?;
 
                     responses.push(fetch_response);
                 }
 
-                bytes += u32::try_from(responses.byte_size())?;
-
-                let now = Instant::now();
-                elapsed = now.duration_since(start);
+                let bytes = u32::try_from(responses.byte_size())?;
+                let elapsed = start.elapsed();
                 let remaining = max_wait.saturating_sub(elapsed);
 
                 debug!(
@@ -247,17 +486,12 @@ where
                     ?min_bytes
                 );
 
-                sleep(if remaining.as_millis() >= 250 {
-                    remaining / 2
-                } else {
-                    remaining
-                })
-                .await;
+                if bytes >= min_bytes || remaining.is_zero() || !self.parked(remaining).await {
+                    return Ok(responses);
+                }
 
                 iteration += 1;
             }
-
-            Ok(responses)
         }
     }
 
@@ -271,7 +505,7 @@ where
     ) -> Result<Body> {
         debug!(?max_wait_ms, ?min_bytes, ?max_bytes, ?topics);
 
-        let responses = Some(if let Some(topics) = topics {
+        let responses = if let Some(topics) = topics {
             let isolation_level = isolation_level.map_or(Ok(None), |isolation| {
                 IsolationLevel::try_from(isolation).map(Some)
             })?;
@@ -280,32 +514,67 @@ where
 
             let min_bytes = u32::try_from(min_bytes)?;
 
-            const DEFAULT_MAX_BYTES: u32 = 5 * 1024 * 1024;
-
-            let mut max_bytes = max_bytes.map_or(Ok(DEFAULT_MAX_BYTES), |max_bytes| {
-                u32::try_from(max_bytes).map(|max_bytes| max_bytes.min(DEFAULT_MAX_BYTES))
+            let max_bytes = max_bytes.map_or(Ok(self.max_bytes), |max_bytes| {
+                u32::try_from(max_bytes).map(|max_bytes| max_bytes.min(self.max_bytes))
             })?;
 
-            self.fetch(
-                max_wait_ms,
-                min_bytes,
-                &mut max_bytes,
-                isolation_level,
-                topics,
-            )
-            .await?
+            self.fetch(max_wait_ms, min_bytes, max_bytes, isolation_level, topics)
+                .await?
         } else {
             vec![]
-        });
-
-        Ok(Body::FetchResponse {
-            throttle_time_ms: Some(0),
-            error_code: Some(ErrorCode::None.into()),
-            session_id: Some(0),
-            node_endpoints: Some([].into()),
-            responses,
-        })
-        .inspect(|r| debug!(?r))
+        };
+
+        FetchResponse::builder()
+            .responses(responses)
+            .build()
+            .inspect(|r| debug!(?r))
+            .map_err(Into::into)
+    }
+}
+
+// the leader epoch of a client that is behind the partition has been fenced,
+// while one ahead of the partition is not yet known here
+fn leader_epoch_error(current_leader_epoch: Option<i32>, leader_epoch: i32) -> Option<ErrorCode> {
+    match current_leader_epoch {
+        Some(current) if current >= 0 && current < leader_epoch => {
+            Some(ErrorCode::FencedLeaderEpoch)
+        }
+
+        Some(current) if current > leader_epoch => Some(ErrorCode::UnknownLeaderEpoch),
+
+        _ => None,
+    }
+}
+
+// the bytes that remain in a response, with its first batch included even
+// when larger than the limits, so that a large batch can't stall a consumer
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Budget {
+    remaining: u32,
+    fetched: bool,
+}
+
+impl Budget {
+    fn new(max_bytes: u32) -> Self {
+        Self {
+            remaining: max_bytes,
+            fetched: false,
+        }
+    }
+
+    // whether another batch might be added to a partition with these bytes
+    // remaining
+    fn is_open(&self, partition_max_bytes: u32) -> bool {
+        !self.fetched || (self.remaining > 0 && partition_max_bytes > 0)
+    }
+
+    fn admits(&self, partition_max_bytes: u32, size: u32) -> bool {
+        !self.fetched || (size <= self.remaining && size <= partition_max_bytes)
+    }
+
+    fn spend(&mut self, size: u32) {
+        self.remaining = self.remaining.saturating_sub(size);
+        self.fetched = true;
     }
 }
 
@@ -331,15 +600,17 @@ where
     }
 }
 
+// as encoded in a response, so that a batch is measured against min bytes
+// as it is against max bytes
 impl ByteSize for Batch {
     fn byte_size(&self) -> u64 {
-        self.record_data.len() as u64
+        self.encoded_len() as u64
     }
 }
 
-impl ByteSize for Frame {
+impl ByteSize for Records {
     fn byte_size(&self) -> u64 {
-        self.batches.byte_size()
+        self.len() as u64
     }
 }
 
@@ -354,3 +625,601 @@ impl ByteSize for FetchableTopicResponse {
         self.partitions.byte_size()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::produce::ProduceRequest,
+        fixture::{storage_with_topic, CLUSTER},
+        Error,
+    };
+    use bytes::Bytes;
+    use replica::RackAwareReplicaSelector;
+    use tansu_kafka_sans_io::{
+        broker_registration_request::Listener,
+        create_topics_request::CreatableTopic,
+        fetch_request::FetchPartition,
+        produce_request::{PartitionProduceData, TopicProduceData},
+        record::{inflated, Record},
+        Ack,
+    };
+    use tansu_storage::{dynostore::DynoStore, BrokerRegistationRequest};
+    use tokio::time::timeout;
+    use tracing::subscriber::DefaultGuard;
+    use uuid::Uuid;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    fn fetch_topics(topic: &str) -> Vec<FetchTopic> {
+        vec![FetchTopic {
+            topic: Some(topic.into()),
+            topic_id: None,
+            partitions: Some(vec![FetchPartition {
+                partition: 0,
+                current_leader_epoch: Some(-1),
+                fetch_offset: 0,
+                last_fetched_epoch: Some(-1),
+                log_start_offset: Some(-1),
+                partition_max_bytes: 1_048_576,
+            }]),
+        }]
+    }
+
+    fn fetched_bytes(body: &Body) -> u64 {
+        if let Body::FetchResponse {
+            responses: Some(responses),
+            ..
+        } = body
+        {
+            responses.byte_size()
+        } else {
+            0
+        }
+    }
+
+    #[tokio::test]
+    async fn idle_fetch_waits_for_max_wait() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let storage = storage_with_topic(topic, 1).await?;
+        let topics = fetch_topics(topic);
+
+        let max_wait_ms = 250;
+        let start = Instant::now();
+
+        let body = FetchRequest::with_storage(storage)
+            .response(max_wait_ms, 1, None, None, Some(&topics))
+            .await?;
+
+        assert!(start.elapsed() >= Duration::from_millis(max_wait_ms as u64));
+        assert_eq!(0, fetched_bytes(&body));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn produce_wakes_parked_fetch() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let storage = storage_with_topic(topic, 1).await?;
+        let notifications = Notifications::new();
+
+        let max_wait_ms = 30_000;
+        let start = Instant::now();
+
+        let parked = {
+            let storage = storage.clone();
+            let notifications = notifications.clone();
+            let topics = fetch_topics(topic);
+
+            tokio::spawn(async move {
+                FetchRequest::with_storage(storage)
+                    .with_notifications(notifications)
+                    .response(max_wait_ms, 1, None, None, Some(&topics))
+                    .await
+            })
+        };
+
+        sleep(Duration::from_millis(100)).await;
+
+        let records = inflated::Batch::builder()
+            .record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+            .build()
+            .and_then(Batch::try_from)
+            .and_then(Records::try_from)?;
+
+        _ = ProduceRequest::with_storage(storage)
+            .with_notifications(notifications)
+            .response(
+                None,
+                -1,
+                0,
+                Some(vec![TopicProduceData {
+                    name: topic.into(),
+                    partition_data: Some(vec![PartitionProduceData {
+                        index: 0,
+                        records: Some(records),
+                    }]),
+                }]),
+            )
+            .await?;
+
+        let body = timeout(Duration::from_secs(5), parked)
+            .await
+            .map_err(|elapsed| Error::Custom(elapsed.to_string()))?
+            .map_err(|join| Error::Custom(join.to_string()))??;
+
+        assert!(start.elapsed() < Duration::from_millis(max_wait_ms as u64));
+        assert!(fetched_bytes(&body) > 0);
+
+        Ok(())
+    }
+
+    async fn produce(
+        storage: &mut DynoStore,
+        topic: &str,
+        partition: i32,
+        size: usize,
+    ) -> Result<()> {
+        let batch = inflated::Batch::builder()
+            .record(Record::builder().value(Bytes::from(vec![0u8; size]).into()))
+            .build()
+            .and_then(Batch::try_from)?;
+
+        _ = storage
+            .produce(&Topition::new(topic, partition), batch, Ack::FullIsr)
+            .await?;
+
+        Ok(())
+    }
+
+    fn fetch_partitions(topic: &str, partitions: i32, partition_max_bytes: i32) -> Vec<FetchTopic> {
+        vec![FetchTopic {
+            topic: Some(topic.into()),
+            topic_id: None,
+            partitions: Some(
+                (0..partitions)
+                    .map(|partition| FetchPartition {
+                        partition,
+                        current_leader_epoch: Some(-1),
+                        fetch_offset: 0,
+                        last_fetched_epoch: Some(-1),
+                        log_start_offset: Some(-1),
+                        partition_max_bytes,
+                    })
+                    .collect(),
+            ),
+        }]
+    }
+
+    // the number of batches fetched from each partition
+    fn fetched_batches(body: Body) -> Result<Vec<usize>> {
+        let Body::FetchResponse {
+            responses: Some(responses),
+            ..
+        } = body
+        else {
+            panic!("expecting a fetch response: {body:?}")
+        };
+
+        let mut batches = vec![];
+
+        for partition in responses
+            .into_iter()
+            .flat_map(|topic| topic.partitions.unwrap_or_default())
+        {
+            assert_eq!(None, partition.diverging_epoch);
+            assert_eq!(Some(-1), partition.preferred_read_replica);
+
+            batches.push(partition.records.map_or(Ok(0), |records| {
+                records.batches().map(|batches| batches.len())
+            })?);
+        }
+
+        Ok(batches)
+    }
+
+    #[tokio::test]
+    async fn batch_larger_than_limits() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let mut storage = storage_with_topic(topic, 2).await?;
+
+        for partition in [0, 1] {
+            produce(&mut storage, topic, partition, 4_096).await?;
+            produce(&mut storage, topic, partition, 4_096).await?;
+        }
+
+        // only the first batch of the response is larger than the limits
+        assert_eq!(
+            vec![1, 0],
+            fetched_batches(
+                FetchRequest::with_storage(storage)
+                    .response(
+                        0,
+                        1,
+                        Some(1_024),
+                        None,
+                        Some(&fetch_partitions(topic, 2, 512))
+                    )
+                    .await?
+            )?
+        );
+
+        Ok(())
+    }
+
+    // a batch is measured as it is encoded, with its header
+    #[tokio::test]
+    async fn limits_measure_encoded_batches() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let mut storage = storage_with_topic(topic, 1).await?;
+
+        for _ in 0..2 {
+            produce(&mut storage, topic, 0, 1_000).await?;
+        }
+
+        let batch = inflated::Batch::builder()
+            .record(Record::builder().value(Bytes::from(vec![0u8; 1_000]).into()))
+            .build()
+            .and_then(Batch::try_from)?;
+
+        for (partition_max_bytes, batches) in [
+            (2 * batch.record_data.len(), vec![1]),
+            (2 * batch.encoded_len(), vec![2]),
+        ] {
+            assert_eq!(
+                batches,
+                fetched_batches(
+                    FetchRequest::with_storage(storage.clone())
+                        .response(
+                            0,
+                            1,
+                            None,
+                            None,
+                            Some(&fetch_partitions(
+                                topic,
+                                1,
+                                i32::try_from(partition_max_bytes)?
+                            ))
+                        )
+                        .await?
+                )?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn limits_across_partitions() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let partitions = 4;
+        let mut storage = storage_with_topic(topic, partitions).await?;
+
+        for partition in 0..partitions {
+            for _ in 0..3 {
+                produce(&mut storage, topic, partition, 1_000).await?;
+            }
+        }
+
+        // each partition is limited to two batches, with the response
+        // exhausted during the third partition
+        assert_eq!(
+            vec![2, 2, 1, 0],
+            fetched_batches(
+                FetchRequest::with_storage(storage.clone())
+                    .response(
+                        0,
+                        1,
+                        Some(5_500),
+                        None,
+                        Some(&fetch_partitions(topic, partitions, 2_500))
+                    )
+                    .await?
+            )?
+        );
+
+        // every batch, within generous limits
+        assert_eq!(
+            vec![3; 4],
+            fetched_batches(
+                FetchRequest::with_storage(storage)
+                    .response(
+                        0,
+                        1,
+                        None,
+                        None,
+                        Some(&fetch_partitions(topic, partitions, 1_048_576))
+                    )
+                    .await?
+            )?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn broker_limits_max_bytes() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let partitions = 4;
+        let mut storage = storage_with_topic(topic, partitions).await?;
+
+        for partition in 0..partitions {
+            for _ in 0..3 {
+                produce(&mut storage, topic, partition, 1_000).await?;
+            }
+        }
+
+        // the generous max bytes of the request is limited by the broker
+        assert_eq!(
+            vec![2, 2, 1, 0],
+            fetched_batches(
+                FetchRequest::with_storage(storage.clone())
+                    .with_max_bytes(5_500)
+                    .response(
+                        0,
+                        1,
+                        Some(52_428_800),
+                        None,
+                        Some(&fetch_partitions(topic, partitions, 2_500))
+                    )
+                    .await?
+            )?
+        );
+
+        // a request without max bytes uses the limit of the broker
+        assert_eq!(
+            vec![2, 2, 1, 0],
+            fetched_batches(
+                FetchRequest::with_storage(storage)
+                    .with_max_bytes(5_500)
+                    .response(
+                        0,
+                        1,
+                        None,
+                        None,
+                        Some(&fetch_partitions(topic, partitions, 2_500))
+                    )
+                    .await?
+            )?
+        );
+
+        Ok(())
+    }
+
+    async fn leader_epochs(storage: &mut DynoStore, topic: &str) -> Result<Vec<Option<i32>>> {
+        let metadata = storage.metadata(Some(&[TopicId::from(topic)])).await?;
+
+        Ok(metadata
+            .topics()
+            .iter()
+            .flat_map(|topic| topic.partitions.as_deref().unwrap_or_default())
+            .map(|partition| partition.leader_epoch)
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn leader_epoch_bumps() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let mut storage = storage_with_topic(topic, 2).await?;
+        assert_eq!(
+            vec![Some(0), Some(0)],
+            leader_epochs(&mut storage, topic).await?
+        );
+
+        let topition = Topition::new(topic, 0);
+
+        storage
+            .alter_reassignment(&topition, Some(&[12321]))
+            .await?;
+        assert_eq!(
+            vec![Some(1), Some(0)],
+            leader_epochs(&mut storage, topic).await?
+        );
+
+        storage.alter_reassignment(&topition, None).await?;
+        assert_eq!(
+            vec![Some(2), Some(0)],
+            leader_epochs(&mut storage, topic).await?
+        );
+
+        storage
+            .create_partitions(&TopicId::from(topic), 3, false)
+            .await?;
+        assert_eq!(
+            vec![Some(2), Some(0), Some(0)],
+            leader_epochs(&mut storage, topic).await?
+        );
+
+        // a recreated topic continues from the epochs of the deleted topic
+        assert_eq!(
+            ErrorCode::None,
+            storage.delete_topic(&TopicId::from(topic)).await?
+        );
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: topic.into(),
+                    num_partitions: 4,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some([].into()),
+                },
+                false,
+            )
+            .await?;
+
+        assert_eq!(
+            vec![Some(3), Some(1), Some(1), Some(0)],
+            leader_epochs(&mut storage, topic).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn current_leader_epoch() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let mut storage = storage_with_topic(topic, 1).await?;
+
+        storage
+            .alter_reassignment(&Topition::new(topic, 0), Some(&[12321]))
+            .await?;
+
+        for (current_leader_epoch, expected) in [
+            (Some(-1), ErrorCode::None),
+            (Some(0), ErrorCode::FencedLeaderEpoch),
+            (Some(1), ErrorCode::None),
+            (Some(2), ErrorCode::UnknownLeaderEpoch),
+        ] {
+            let mut topics = fetch_topics(topic);
+
+            if let Some(partition) = topics[0]
+                .partitions
+                .as_mut()
+                .and_then(|partitions| partitions.first_mut())
+            {
+                partition.current_leader_epoch = current_leader_epoch;
+            }
+
+            let Body::FetchResponse {
+                responses: Some(responses),
+                ..
+            } = FetchRequest::with_storage(storage.clone())
+                .response(0, 0, None, None, Some(&topics))
+                .await?
+            else {
+                panic!("expecting a fetch response")
+            };
+
+            let partition = responses[0].partitions.as_deref().unwrap_or_default()[0].clone();
+
+            assert_eq!(
+                i16::from(expected),
+                partition.error_code,
+                "{current_leader_epoch:?}"
+            );
+
+            if expected != ErrorCode::None {
+                assert_eq!(
+                    Some(LeaderIdAndEpoch {
+                        leader_id: 12321,
+                        leader_epoch: 1
+                    }),
+                    partition.current_leader
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn consumer_steered_to_rack() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let mut storage = storage_with_topic(topic, 1).await?;
+
+        // brokers sharing the storage, each in their own rack
+        for (broker_id, rack) in [(111, "a"), (222, "b")] {
+            _ = storage
+                .register_broker(BrokerRegistationRequest {
+                    broker_id,
+                    cluster_id: CLUSTER.into(),
+                    incarnation_id: Uuid::new_v4(),
+                    listeners: vec![Listener {
+                        name: "broker".into(),
+                        host: "localhost".into(),
+                        port: 9092,
+                        security_protocol: 0,
+                    }],
+                    features: vec![],
+                    rack: Some(rack.into()),
+                })
+                .await?;
+        }
+
+        produce(&mut storage, topic, 0, 16).await?;
+
+        let fetch = |local: ReplicaView| {
+            FetchRequest::with_storage(storage.clone())
+                .with_replica_selector(Arc::new(RackAwareReplicaSelector), local)
+                .with_rack_id(Some("b".into()))
+        };
+
+        let Body::FetchResponse {
+            responses: Some(responses),
+            ..
+        } = fetch(ReplicaView::new(111, Some("a".into())))
+            .response(0, 0, None, None, Some(&fetch_topics(topic)))
+            .await?
+        else {
+            panic!("expecting a fetch response")
+        };
+
+        let partition = &responses[0].partitions.as_deref().unwrap_or_default()[0];
+        assert_eq!(Some(222), partition.preferred_read_replica);
+        assert_eq!(1, partition.high_watermark);
+        assert!(partition.records.is_none());
+
+        // the broker in the rack of the consumer answers with the records
+        assert_eq!(
+            vec![1],
+            fetched_batches(
+                fetch(ReplicaView::new(222, Some("b".into())))
+                    .response(0, 0, None, None, Some(&fetch_topics(topic)))
+                    .await?
+            )?
+        );
+
+        Ok(())
+    }
+}
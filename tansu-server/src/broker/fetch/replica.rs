@@ -0,0 +1,135 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The replica that a consumer is steered to with the preferred read
+//! replica of a fetch response.
+
+use std::fmt::Debug;
+
+use tansu_storage::Topition;
+
+/// A broker that may serve fetches of a partition.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ReplicaView {
+    pub node_id: i32,
+    pub rack: Option<String>,
+}
+
+impl ReplicaView {
+    pub fn new(node_id: i32, rack: Option<String>) -> Self {
+        Self { node_id, rack }
+    }
+}
+
+pub trait ReplicaSelector: Debug + Send + Sync {
+    /// The replica that a consumer in rack_id should fetch the topition
+    /// from, otherwise None to continue fetching from the local broker.
+    fn select(
+        &self,
+        topition: &Topition,
+        rack_id: &str,
+        local: &ReplicaView,
+        replicas: &[ReplicaView],
+    ) -> Option<i32>;
+}
+
+/// Consumers always fetch from the broker that they asked, the behaviour
+/// without a selector.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct LeaderSelector;
+
+impl ReplicaSelector for LeaderSelector {
+    fn select(
+        &self,
+        _topition: &Topition,
+        _rack_id: &str,
+        _local: &ReplicaView,
+        _replicas: &[ReplicaView],
+    ) -> Option<i32> {
+        None
+    }
+}
+
+/// Steers a consumer to a replica in the same rack, unless the local
+/// broker is already in that rack or no replica is.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RackAwareReplicaSelector;
+
+impl ReplicaSelector for RackAwareReplicaSelector {
+    fn select(
+        &self,
+        _topition: &Topition,
+        rack_id: &str,
+        local: &ReplicaView,
+        replicas: &[ReplicaView],
+    ) -> Option<i32> {
+        if rack_id.is_empty() || local.rack.as_deref() == Some(rack_id) {
+            return None;
+        }
+
+        replicas
+            .iter()
+            .find(|replica| {
+                replica.node_id != local.node_id && replica.rack.as_deref() == Some(rack_id)
+            })
+            .map(|replica| replica.node_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replicas() -> Vec<ReplicaView> {
+        vec![
+            ReplicaView::new(1, Some("a".into())),
+            ReplicaView::new(2, Some("b".into())),
+            ReplicaView::new(3, None),
+        ]
+    }
+
+    #[test]
+    fn rack_aware() {
+        let topition = Topition::new("pqr", 0);
+        let local = ReplicaView::new(1, Some("a".into()));
+
+        assert_eq!(
+            Some(2),
+            RackAwareReplicaSelector.select(&topition, "b", &local, &replicas())
+        );
+
+        // the local broker is already in the rack of the consumer
+        assert_eq!(
+            None,
+            RackAwareReplicaSelector.select(&topition, "a", &local, &replicas())
+        );
+
+        // no replica is in the rack of the consumer
+        assert_eq!(
+            None,
+            RackAwareReplicaSelector.select(&topition, "c", &local, &replicas())
+        );
+
+        assert_eq!(
+            None,
+            RackAwareReplicaSelector.select(&topition, "", &local, &replicas())
+        );
+
+        assert_eq!(
+            None,
+            LeaderSelector.select(&topition, "b", &local, &replicas())
+        );
+    }
+}
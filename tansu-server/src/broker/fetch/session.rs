@@ -0,0 +1,588 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use tansu_kafka_sans_io::{
+    fetch_request::{FetchPartition, FetchTopic, ForgottenTopic},
+    fetch_response::{FetchableTopicResponse, PartitionData},
+    primitive::uuid::Uuid,
+    Body, ErrorCode,
+};
+use tansu_storage::TopicId;
+use tracing::debug;
+
+use crate::{Error, Result};
+
+pub const DEFAULT_MAXIMUM_SESSIONS: usize = 1_000;
+
+const SESSIONLESS: i32 = 0;
+const INITIAL_EPOCH: i32 = 0;
+const FINAL_EPOCH: i32 = -1;
+
+// the partition state last sent to the client, an incremental response
+// only includes a partition when this has changed or it has records
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Propagated {
+    error_code: i16,
+    high_watermark: i64,
+    last_stable_offset: Option<i64>,
+    log_start_offset: Option<i64>,
+}
+
+impl From<&PartitionData> for Propagated {
+    fn from(value: &PartitionData) -> Self {
+        Self {
+            error_code: value.error_code,
+            high_watermark: value.high_watermark,
+            last_stable_offset: value.last_stable_offset,
+            log_start_offset: value.log_start_offset,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Cached {
+    partition: FetchPartition,
+    propagated: Option<Propagated>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Session {
+    epoch: i32,
+    last_used: u64,
+    topics: BTreeMap<TopicId, BTreeMap<i32, Cached>>,
+}
+
+impl Session {
+    fn merge(&mut self, topics: Option<&[FetchTopic]>) {
+        for topic in topics.unwrap_or_default() {
+            let Some(id) = topic_id(topic.topic.as_deref(), topic.topic_id) else {
+                continue;
+            };
+
+            let partitions = self.topics.entry(id).or_default();
+
+            for partition in topic.partitions.as_deref().unwrap_or_default() {
+                let propagated = partitions
+                    .get(&partition.partition)
+                    .and_then(|cached| cached.propagated);
+
+                _ = partitions.insert(
+                    partition.partition,
+                    Cached {
+                        partition: partition.clone(),
+                        propagated,
+                    },
+                );
+            }
+        }
+    }
+
+    fn forget(&mut self, forgotten: Option<&[ForgottenTopic]>) {
+        for topic in forgotten.unwrap_or_default() {
+            let Some(id) = topic_id(topic.topic.as_deref(), topic.topic_id) else {
+                continue;
+            };
+
+            if let Some(partitions) = self.topics.get_mut(&id) {
+                for partition in topic.partitions.as_deref().unwrap_or_default() {
+                    _ = partitions.remove(partition);
+                }
+
+                if partitions.is_empty() {
+                    _ = self.topics.remove(&id);
+                }
+            }
+        }
+    }
+
+    fn fetch_topics(&self) -> Vec<FetchTopic> {
+        self.topics
+            .iter()
+            .map(|(id, partitions)| {
+                let (topic, topic_id) = match id {
+                    TopicId::Name(name) => (Some(name.to_owned()), None),
+                    TopicId::Id(id) => (None, Some(Uuid::from(*id))),
+                };
+
+                FetchTopic {
+                    topic,
+                    topic_id,
+                    partitions: Some(
+                        partitions
+                            .values()
+                            .map(|cached| cached.partition.clone())
+                            .collect(),
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    fn propagate(
+        &mut self,
+        incremental: bool,
+        response: FetchableTopicResponse,
+    ) -> Option<FetchableTopicResponse> {
+        let Some(partitions) = topic_id(response.topic.as_deref(), response.topic_id)
+            .and_then(|id| self.topics.get_mut(&id))
+        else {
+            return Some(response);
+        };
+
+        let changed = response
+            .partitions
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|data| {
+                let current = Propagated::from(data);
+
+                let previous = partitions
+                    .get_mut(&data.partition_index)
+                    .and_then(|cached| cached.propagated.replace(current));
+
+                !incremental
+                    || data.records.is_some()
+                    || data.error_code != i16::from(ErrorCode::None)
+                    || previous != Some(current)
+            })
+            .collect::<Vec<_>>();
+
+        if incremental && changed.is_empty() {
+            None
+        } else {
+            Some(FetchableTopicResponse {
+                partitions: Some(changed),
+                ..response
+            })
+        }
+    }
+
+    fn next_epoch(&mut self) {
+        self.epoch = if self.epoch == i32::MAX {
+            1
+        } else {
+            self.epoch + 1
+        };
+    }
+}
+
+fn topic_id(topic: Option<&str>, topic_id: Option<Uuid>) -> Option<TopicId> {
+    topic.map(TopicId::from).or(topic_id.map(TopicId::from))
+}
+
+/// The topics to fetch for a request, after merging it with its session.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    session_id: i32,
+    incremental: bool,
+    topics: Vec<FetchTopic>,
+}
+
+impl Context {
+    #[must_use]
+    pub fn session_id(&self) -> i32 {
+        self.session_id
+    }
+
+    #[must_use]
+    pub fn is_incremental(&self) -> bool {
+        self.incremental
+    }
+
+    #[must_use]
+    pub fn topics(&self) -> &[FetchTopic] {
+        &self.topics
+    }
+}
+
+/// Fetch sessions (KIP-227) held for a connection, so that an incremental
+/// fetch only lists the partitions that have changed since the last
+/// request, with only changed partitions in the response.
+///
+/// The least recently used session is evicted when the maximum number of
+/// sessions is reached.
+#[derive(Clone, Debug)]
+pub struct Sessions {
+    maximum: usize,
+    next_id: i32,
+    tick: u64,
+    sessions: BTreeMap<i32, Session>,
+}
+
+impl Default for Sessions {
+    fn default() -> Self {
+        Self::with_maximum(DEFAULT_MAXIMUM_SESSIONS)
+    }
+}
+
+impl Sessions {
+    #[must_use]
+    pub fn with_maximum(maximum: usize) -> Self {
+        Self {
+            maximum,
+            next_id: 1,
+            tick: 0,
+            sessions: BTreeMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    pub fn context(
+        &mut self,
+        session_id: Option<i32>,
+        session_epoch: Option<i32>,
+        topics: Option<&[FetchTopic]>,
+        forgotten: Option<&[ForgottenTopic]>,
+    ) -> Result<Context> {
+        let session_id = session_id.unwrap_or(SESSIONLESS);
+        let session_epoch = session_epoch.unwrap_or(FINAL_EPOCH);
+
+        debug!(session_id, session_epoch);
+
+        self.tick += 1;
+
+        match (session_id, session_epoch) {
+            (_, FINAL_EPOCH) => {
+                _ = self.sessions.remove(&session_id);
+
+                Ok(Context {
+                    session_id: SESSIONLESS,
+                    incremental: false,
+                    topics: topics.map(<[FetchTopic]>::to_vec).unwrap_or_default(),
+                })
+            }
+
+            (_, INITIAL_EPOCH) => {
+                _ = self.sessions.remove(&session_id);
+
+                let mut session = Session {
+                    epoch: INITIAL_EPOCH,
+                    last_used: self.tick,
+                    topics: BTreeMap::new(),
+                };
+
+                session.merge(topics);
+                session.next_epoch();
+
+                let topics = session.fetch_topics();
+
+                let session_id = self.allocate().map_or(SESSIONLESS, |session_id| {
+                    _ = self.sessions.insert(session_id, session);
+                    session_id
+                });
+
+                Ok(Context {
+                    session_id,
+                    incremental: false,
+                    topics,
+                })
+            }
+
+            (SESSIONLESS, _) => Err(Error::Api(ErrorCode::InvalidFetchSessionEpoch)),
+
+            (session_id, session_epoch) => {
+                let tick = self.tick;
+
+                let session = self
+                    .sessions
+                    .get_mut(&session_id)
+                    .ok_or(Error::Api(ErrorCode::FetchSessionIdNotFound))?;
+
+                if session.epoch != session_epoch {
+                    debug!(session_id, session_epoch, expected = session.epoch);
+                    return Err(Error::Api(ErrorCode::InvalidFetchSessionEpoch));
+                }
+
+                session.last_used = tick;
+                session.merge(topics);
+                session.forget(forgotten);
+                session.next_epoch();
+
+                Ok(Context {
+                    session_id,
+                    incremental: true,
+                    topics: session.fetch_topics(),
+                })
+            }
+        }
+    }
+
+    /// Record the partition state sent in a response, removing any unchanged
+    /// partitions from an incremental response.
+    pub fn propagate(&mut self, context: &Context, body: Body) -> Body {
+        let Some(session) = self.sessions.get_mut(&context.session_id) else {
+            return body;
+        };
+
+        if let Body::FetchResponse {
+            throttle_time_ms,
+            error_code,
+            responses,
+            node_endpoints,
+            unknown_tagged_fields,
+            ..
+        } = body
+        {
+            let responses = responses.map(|responses| {
+                responses
+                    .into_iter()
+                    .filter_map(|response| session.propagate(context.incremental, response))
+                    .collect()
+            });
+
+            Body::FetchResponse {
+                throttle_time_ms,
+                error_code,
+                session_id: Some(context.session_id),
+                responses,
+                node_endpoints,
+                unknown_tagged_fields,
+            }
+        } else {
+            body
+        }
+    }
+
+    // a new session id, evicting the least recently used session when full
+    fn allocate(&mut self) -> Option<i32> {
+        if self.maximum == 0 {
+            return None;
+        }
+
+        while self.sessions.len() >= self.maximum {
+            let lru = self
+                .sessions
+                .iter()
+                .min_by_key(|(_, session)| session.last_used)
+                .map(|(session_id, _)| *session_id)?;
+
+            debug!(evicted = lru);
+            _ = self.sessions.remove(&lru);
+        }
+
+        loop {
+            let session_id = self.next_id;
+
+            self.next_id = if self.next_id == i32::MAX {
+                1
+            } else {
+                self.next_id + 1
+            };
+
+            if !self.sessions.contains_key(&session_id) {
+                return Some(session_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tansu_kafka_sans_io::response::FetchResponse;
+
+    fn fetch_partition(partition: i32) -> FetchPartition {
+        FetchPartition {
+            partition,
+            current_leader_epoch: Some(-1),
+            fetch_offset: 0,
+            last_fetched_epoch: Some(-1),
+            log_start_offset: Some(-1),
+            partition_max_bytes: 1_048_576,
+        }
+    }
+
+    fn fetch_topic(topic: &str, partitions: &[i32]) -> FetchTopic {
+        FetchTopic {
+            topic: Some(topic.into()),
+            topic_id: None,
+            partitions: Some(partitions.iter().copied().map(fetch_partition).collect()),
+        }
+    }
+
+    fn partition_data(partition_index: i32, high_watermark: i64) -> PartitionData {
+        PartitionData {
+            partition_index,
+            error_code: ErrorCode::None.into(),
+            high_watermark,
+            last_stable_offset: Some(high_watermark),
+            log_start_offset: Some(0),
+            diverging_epoch: None,
+            current_leader: None,
+            snapshot_id: None,
+            aborted_transactions: Some([].into()),
+            preferred_read_replica: Some(-1),
+            records: None,
+        }
+    }
+
+    fn response(topic: &str, partitions: &[(i32, i64)]) -> Result<Body> {
+        FetchResponse::builder()
+            .responses([FetchableTopicResponse {
+                topic: Some(topic.into()),
+                topic_id: None,
+                partitions: Some(
+                    partitions
+                        .iter()
+                        .map(|(partition_index, high_watermark)| {
+                            partition_data(*partition_index, *high_watermark)
+                        })
+                        .collect(),
+                ),
+            }])
+            .build()
+            .map_err(Into::into)
+    }
+
+    fn partitions(body: &Body) -> Vec<(String, i32)> {
+        let Body::FetchResponse {
+            responses: Some(responses),
+            ..
+        } = body
+        else {
+            return vec![];
+        };
+
+        responses
+            .iter()
+            .flat_map(|response| {
+                response
+                    .partitions
+                    .iter()
+                    .flatten()
+                    .map(|partition| {
+                        (
+                            response.topic.clone().unwrap_or_default(),
+                            partition.partition_index,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn incremental() -> Result<()> {
+        let topic = "pqr";
+        let mut sessions = Sessions::default();
+
+        let full =
+            sessions.context(Some(0), Some(0), Some(&[fetch_topic(topic, &[0, 1])]), None)?;
+        assert!(!full.is_incremental());
+        assert_ne!(0, full.session_id());
+        assert_eq!(1, sessions.len());
+
+        let body = sessions.propagate(&full, response(topic, &[(0, 0), (1, 0)])?);
+        assert_eq!(
+            vec![(topic.to_owned(), 0), (topic.to_owned(), 1)],
+            partitions(&body)
+        );
+        assert!(matches!(
+            body,
+            Body::FetchResponse {
+                session_id: Some(session_id),
+                ..
+            } if session_id == full.session_id()
+        ));
+
+        // an empty incremental request fetches every partition in the
+        // session, responding with only those that have changed
+        let first = sessions.context(Some(full.session_id()), Some(1), Some(&[]), None)?;
+        assert!(first.is_incremental());
+        assert_eq!(vec![fetch_topic(topic, &[0, 1])], first.topics());
+
+        let body = sessions.propagate(&first, response(topic, &[(0, 0), (1, 5)])?);
+        assert_eq!(vec![(topic.to_owned(), 1)], partitions(&body));
+
+        // forgetting partition 1 leaves partition 0, which is unchanged
+        let second = sessions.context(
+            Some(full.session_id()),
+            Some(2),
+            None,
+            Some(&[ForgottenTopic {
+                topic: Some(topic.into()),
+                topic_id: None,
+                partitions: Some(vec![1]),
+            }]),
+        )?;
+        assert_eq!(vec![fetch_topic(topic, &[0])], second.topics());
+
+        let body = sessions.propagate(&second, response(topic, &[(0, 0)])?);
+        assert!(partitions(&body).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn session_errors() -> Result<()> {
+        let topic = "pqr";
+        let mut sessions = Sessions::default();
+
+        let full = sessions.context(Some(0), Some(0), Some(&[fetch_topic(topic, &[0])]), None)?;
+
+        assert!(matches!(
+            sessions.context(Some(full.session_id()), Some(3), None, None),
+            Err(Error::Api(ErrorCode::InvalidFetchSessionEpoch))
+        ));
+
+        assert!(matches!(
+            sessions.context(Some(full.session_id() + 1), Some(1), None, None),
+            Err(Error::Api(ErrorCode::FetchSessionIdNotFound))
+        ));
+
+        // the final epoch closes the session
+        let sessionless = sessions.context(Some(full.session_id()), Some(-1), None, None)?;
+        assert_eq!(0, sessionless.session_id());
+        assert!(sessions.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn least_recently_used_evicted() -> Result<()> {
+        let topic = "pqr";
+        let mut sessions = Sessions::with_maximum(2);
+
+        let a = sessions.context(Some(0), Some(0), Some(&[fetch_topic(topic, &[0])]), None)?;
+        let b = sessions.context(Some(0), Some(0), Some(&[fetch_topic(topic, &[1])]), None)?;
+
+        // using a, leaves b as the least recently used
+        _ = sessions.context(Some(a.session_id()), Some(1), None, None)?;
+
+        let c = sessions.context(Some(0), Some(0), Some(&[fetch_topic(topic, &[2])]), None)?;
+        assert_eq!(2, sessions.len());
+
+        assert!(matches!(
+            sessions.context(Some(b.session_id()), Some(1), None, None),
+            Err(Error::Api(ErrorCode::FetchSessionIdNotFound))
+        ));
+
+        _ = sessions.context(Some(a.session_id()), Some(2), None, None)?;
+        _ = sessions.context(Some(c.session_id()), Some(1), None, None)?;
+
+        Ok(())
+    }
+}
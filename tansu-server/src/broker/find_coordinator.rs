@@ -13,8 +13,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use tansu_kafka_sans_io::{find_coordinator_response::Coordinator, Body, ErrorCode};
-use url::Url;
+use crate::Result;
+use tansu_kafka_sans_io::{
+    broker_registration_request::Listener, response::FindCoordinatorResponse, Body,
+    CoordinatorType, ErrorCode,
+};
+use tracing::{debug, warn};
 
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct FindCoordinatorRequest;
@@ -26,33 +30,171 @@ impl FindCoordinatorRequest {
         key_type: Option<i8>,
         coordinator_keys: Option<&[String]>,
         node_id: i32,
-        listener: &Url,
-    ) -> Body {
-        let _ = key;
-        let _ = key_type;
-
-        let host = listener.host_str().unwrap_or("localhost");
-        let port = i32::from(listener.port().unwrap_or(9092));
-
-        Body::FindCoordinatorResponse {
-            throttle_time_ms: Some(0),
-            error_code: Some(ErrorCode::None.into()),
-            error_message: Some("NONE".into()),
+        listener: Option<&Listener>,
+    ) -> Result<Body> {
+        // the key type is absent in version 0, which only finds group coordinators
+        let builder = FindCoordinatorResponse::builder()
+            .keys(coordinator_keys.unwrap_or_default().iter().cloned());
+
+        match key_type.map_or(Ok(CoordinatorType::Group), CoordinatorType::try_from) {
+            Ok(coordinator_type @ (CoordinatorType::Group | CoordinatorType::Transaction)) => {
+                debug!(?key, ?coordinator_type, ?coordinator_keys);
+
+                // every key is coordinated by this broker
+                let (host, port) = listener.map_or(("localhost", 9092), |listener| {
+                    (listener.host.as_str(), i32::from(listener.port))
+                });
+
+                builder.coordinator(node_id, host, port).build()
+            }
+
+            Ok(coordinator_type) => {
+                debug!(?key, ?coordinator_type, ?coordinator_keys);
+
+                builder
+                    .error(
+                        ErrorCode::CoordinatorNotAvailable,
+                        Some("unsupported coordinator type"),
+                    )
+                    .coordinator(-1, "", -1)
+                    .build()
+            }
+
+            Err(error) => {
+                warn!(?key, ?key_type, ?coordinator_keys, ?error);
+
+                builder
+                    .error(ErrorCode::InvalidRequest, Some("unknown key type"))
+                    .coordinator(-1, "", -1)
+                    .build()
+            }
+        }
+        .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tansu_kafka_sans_io::find_coordinator_response::Coordinator;
+
+    fn listener() -> Listener {
+        Listener {
+            name: "broker".into(),
+            host: "localhost".into(),
+            port: 9092,
+            security_protocol: 0,
+        }
+    }
+
+    #[test]
+    fn transaction_coordinator_keys() -> Result<()> {
+        let transactional_ids = ["abc".to_owned(), "pqr".to_owned()];
+
+        let Body::FindCoordinatorResponse {
+            coordinators: Some(coordinators),
+            ..
+        } = FindCoordinatorRequest.response(
+            None,
+            Some(CoordinatorType::Transaction.into()),
+            Some(&transactional_ids[..]),
+            111,
+            Some(&listener()),
+        )?
+        else {
+            panic!("expected a find coordinator response with coordinators")
+        };
+
+        assert_eq!(
+            transactional_ids
+                .iter()
+                .map(|key| Coordinator {
+                    key: key.clone(),
+                    node_id: 111,
+                    host: "localhost".into(),
+                    port: 9092,
+                    error_code: ErrorCode::None.into(),
+                    error_message: None,
+                })
+                .collect::<Vec<_>>(),
+            coordinators
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn group_coordinator_key() -> Result<()> {
+        let Body::FindCoordinatorResponse {
+            error_code: Some(error_code),
             node_id: Some(node_id),
-            host: Some(host.into()),
+            host: Some(host),
             port: Some(port),
-            coordinators: coordinator_keys.map(|keys| {
-                keys.iter()
-                    .map(|key| Coordinator {
-                        key: key.to_string(),
-                        node_id,
-                        host: host.into(),
-                        port,
-                        error_code: ErrorCode::None.into(),
-                        error_message: None,
-                    })
-                    .collect()
-            }),
-        }
+            ..
+        } = FindCoordinatorRequest.response(Some("abc"), None, None, 111, Some(&listener()))?
+        else {
+            panic!("expected a find coordinator response with a coordinator")
+        };
+
+        assert_eq!(i16::from(ErrorCode::None), error_code);
+        assert_eq!(111, node_id);
+        assert_eq!("localhost", host);
+        assert_eq!(9092, port);
+
+        Ok(())
+    }
+
+    #[test]
+    fn share_coordinator_not_available() -> Result<()> {
+        let Body::FindCoordinatorResponse {
+            error_code: Some(error_code),
+            coordinators: Some(coordinators),
+            ..
+        } = FindCoordinatorRequest.response(
+            None,
+            Some(CoordinatorType::Share.into()),
+            Some(&["abc".to_owned(), "pqr".to_owned()][..]),
+            111,
+            Some(&listener()),
+        )?
+        else {
+            panic!("expected a find coordinator response with coordinators")
+        };
+
+        assert_eq!(i16::from(ErrorCode::CoordinatorNotAvailable), error_code);
+        assert_eq!(2, coordinators.len());
+        assert!(coordinators
+            .iter()
+            .all(|coordinator| coordinator.node_id == -1
+                && coordinator.error_code == i16::from(ErrorCode::CoordinatorNotAvailable)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_key_type() -> Result<()> {
+        let Body::FindCoordinatorResponse {
+            error_code: Some(error_code),
+            node_id: Some(node_id),
+            coordinators: Some(coordinators),
+            ..
+        } = FindCoordinatorRequest.response(
+            None,
+            Some(7),
+            Some(&["abc".to_owned()][..]),
+            111,
+            Some(&listener()),
+        )?
+        else {
+            panic!("expected a find coordinator response with coordinators")
+        };
+
+        assert_eq!(i16::from(ErrorCode::InvalidRequest), error_code);
+        assert_eq!(-1, node_id);
+        assert!(coordinators
+            .iter()
+            .all(|coordinator| coordinator.error_code == i16::from(ErrorCode::InvalidRequest)));
+
+        Ok(())
     }
 }
@@ -0,0 +1,1833 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A handler for each request answered by the broker.
+//!
+//! Handlers are registered by api key in a [`Registry`](super::registry::Registry),
+//! being called with the context of the connection that the request
+//! arrived on, once the request has been authorized.
+
+use super::{
+    alter_client_quotas::AlterClientQuotasRequest,
+    alter_partition_reassignments::AlterPartitionReassignmentsRequest,
+    alter_user_scram_credentials::AlterUserScramCredentialsRequest,
+    api_versions::ApiVersionsRequest,
+    broker_heartbeat::BrokerHeartbeatRequest,
+    create_acls::CreateAclsRequest,
+    create_partitions::CreatePartitionsRequest,
+    create_topic::CreateTopic,
+    delete_acls::DeleteAclsRequest,
+    delete_records::DeleteRecordsRequest,
+    delete_topics::DeleteTopicsRequest,
+    describe_acls::DescribeAclsRequest,
+    describe_client_quotas::DescribeClientQuotasRequest,
+    describe_cluster::DescribeClusterRequest,
+    describe_configs::DescribeConfigsRequest,
+    describe_producers::DescribeProducersRequest,
+    describe_transactions::DescribeTransactionsRequest,
+    describe_user_scram_credentials::DescribeUserScramCredentialsRequest,
+    elect_leaders::ElectLeadersRequest,
+    fetch::{
+        replica::{LeaderSelector, ReplicaSelector, ReplicaView},
+        session::Sessions,
+        FetchRequest, DEFAULT_FETCH_MAX_BYTES,
+    },
+    find_coordinator::FindCoordinatorRequest,
+    init_producer_id::InitProducerIdRequest,
+    list_offsets::ListOffsetsRequest,
+    list_partition_reassignments::ListPartitionReassignmentsRequest,
+    list_transactions::ListTransactionsRequest,
+    listener::ListenerConfig,
+    metadata::MetadataRequest,
+    notify::Notifications,
+    offset_for_leader_epoch::OffsetForLeaderEpochRequest,
+    produce::ProduceRequest,
+    quota::Quotas,
+    registry::Registry,
+    telemetry::GetTelemetrySubscriptionsRequest,
+    txn::{self, add_offsets::AddOffsets, add_partitions::AddPartitions, end::End},
+    unregister_broker::UnregisterBrokerRequest,
+};
+use crate::{
+    authorizer::{AclFilter, AllowAll, Authorizer, Principal},
+    coordinator::group::{self, Coordinator},
+    Error, Result,
+};
+use async_trait::async_trait;
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+use tansu_kafka_sans_io::{
+    broker_registration_request::Listener,
+    response::{FetchResponse, ProduceResponse},
+    Body, ErrorCode,
+};
+use tansu_storage::Storage;
+use tracing::{debug, error};
+
+/// Answers a request, with the body of the response.
+#[async_trait]
+pub trait RequestHandler<G, S>: Debug + Send + Sync {
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body>;
+}
+
+/// The broker and connection that a request arrived on.
+#[derive(Clone, Debug)]
+pub struct ConnectionContext<G, S> {
+    pub node_id: i32,
+    pub cluster_id: String,
+    pub listeners: Vec<ListenerConfig>,
+    pub listener: Option<ListenerConfig>,
+    pub rack: Option<String>,
+    pub client_id: Option<String>,
+    pub client_host: Option<String>,
+    pub principal: Principal,
+    pub storage: S,
+    pub groups: G,
+    pub notifications: Notifications,
+    pub fetch_sessions: Arc<Mutex<Sessions>>,
+    pub quotas: Quotas,
+    pub authorizer: Arc<dyn Authorizer>,
+    pub replica_selector: Arc<dyn ReplicaSelector>,
+    pub registry: Arc<Registry<G, S>>,
+    pub topic_defaults: BTreeMap<String, String>,
+    pub recompression: bool,
+    pub fetch_max_bytes: u32,
+}
+
+impl<G, S> ConnectionContext<G, S>
+where
+    G: Coordinator,
+    S: Storage,
+{
+    /// An anonymous connection without any listeners, with the default
+    /// handlers.
+    pub fn new(node_id: i32, cluster_id: &str, storage: S, groups: G) -> Self {
+        Self {
+            node_id,
+            cluster_id: cluster_id.to_owned(),
+            listeners: vec![],
+            listener: None,
+            rack: None,
+            client_id: None,
+            client_host: None,
+            principal: Principal::new(None, None),
+            storage,
+            groups,
+            notifications: Notifications::new(),
+            fetch_sessions: Arc::new(Mutex::new(Sessions::default())),
+            quotas: Quotas::default(),
+            authorizer: Arc::new(AllowAll),
+            replica_selector: Arc::new(LeaderSelector),
+            registry: Arc::new(Registry::default()),
+            topic_defaults: BTreeMap::new(),
+            recompression: true,
+            fetch_max_bytes: DEFAULT_FETCH_MAX_BYTES,
+        }
+    }
+}
+
+impl<G, S> ConnectionContext<G, S> {
+    /// The listener advertised to clients, being the one that accepted this
+    /// connection, otherwise the first configured.
+    pub fn advertised(&self) -> Option<&Listener> {
+        self.listener
+            .as_ref()
+            .or(self.listeners.first())
+            .map(|listener| &listener.advertised)
+    }
+
+    /// The runtime configuration of this broker, as reported by DescribeConfigs.
+    pub fn configuration(&self) -> BTreeMap<String, String> {
+        let mut configuration = BTreeMap::from([
+            (
+                "advertised.listeners".into(),
+                self.listeners
+                    .iter()
+                    .map(|listener| {
+                        format!("{}://{}", listener.name(), listener.advertised_address())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            ("broker.id".into(), self.node_id.to_string()),
+            (
+                "listeners".into(),
+                self.listeners
+                    .iter()
+                    .map(|listener| format!("{}://{}", listener.name(), listener.bind_address()))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            ("node.id".into(), self.node_id.to_string()),
+        ]);
+
+        if let Some(ref rack) = self.rack {
+            _ = configuration.insert("broker.rack".into(), rack.clone());
+        }
+
+        configuration
+    }
+}
+
+// a request dispatched to the handler of another api
+fn unexpected(body: &Body) -> Error {
+    error!(request = body.name());
+    Error::Api(ErrorCode::InvalidRequest)
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AlterClientQuotas;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for AlterClientQuotas
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::AlterClientQuotasRequest {
+            entries,
+            validate_only,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?entries, ?validate_only);
+
+        Ok(AlterClientQuotasRequest::with_quotas(ctx.quotas.clone())
+            .response(entries.as_deref(), validate_only))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AlterPartitionReassignments;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for AlterPartitionReassignments
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::AlterPartitionReassignmentsRequest {
+            timeout_ms, topics, ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?timeout_ms, ?topics);
+
+        AlterPartitionReassignmentsRequest::with_storage(ctx.storage.clone())
+            .response(timeout_ms, topics.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AlterUserScramCredentials;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for AlterUserScramCredentials
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::AlterUserScramCredentialsRequest {
+            deletions,
+            upsertions,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?deletions, ?upsertions);
+
+        AlterUserScramCredentialsRequest::with_storage(ctx.storage.clone())
+            .response(deletions.as_deref(), upsertions.as_deref())
+            .await
+    }
+}
+
+/// The versions advertised are those of the registry in the context.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ApiVersions;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for ApiVersions
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::ApiVersionsRequest {
+            client_software_name,
+            client_software_version,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?client_software_name, ?client_software_version);
+
+        Ok(
+            ApiVersionsRequest::with_api_versions(ctx.registry.api_versions()).response(
+                client_software_name.as_deref(),
+                client_software_version.as_deref(),
+            ),
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BrokerHeartbeat;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for BrokerHeartbeat
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::BrokerHeartbeatRequest {
+            broker_id,
+            broker_epoch,
+            want_fence,
+            want_shut_down,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?broker_id, ?broker_epoch, ?want_fence, ?want_shut_down);
+
+        BrokerHeartbeatRequest::with_storage(ctx.storage.clone())
+            .response(broker_id, broker_epoch, want_fence, want_shut_down)
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ConsumerGroupDescribe;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for ConsumerGroupDescribe
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::ConsumerGroupDescribeRequest {
+            group_ids,
+            include_authorized_operations,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?group_ids, ?include_authorized_operations);
+
+        ctx.groups
+            .clone()
+            .consumer_group_describe(group_ids.as_deref(), include_authorized_operations)
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ConsumerGroupHeartbeat;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for ConsumerGroupHeartbeat
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::ConsumerGroupHeartbeatRequest {
+            group_id,
+            member_id,
+            member_epoch,
+            instance_id,
+            rack_id,
+            rebalance_timeout_ms,
+            subscribed_topic_names,
+            server_assignor,
+            topic_partitions,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(
+            ?group_id,
+            ?member_id,
+            ?member_epoch,
+            ?instance_id,
+            ?rack_id,
+            ?rebalance_timeout_ms,
+            ?subscribed_topic_names,
+            ?server_assignor,
+            ?topic_partitions,
+        );
+
+        ctx.groups
+            .clone()
+            .consumer_group_heartbeat(group::ConsumerGroupHeartbeat {
+                client_id: ctx.client_id.as_deref(),
+                client_host: ctx.client_host.as_deref(),
+                group_id: &group_id,
+                member_id: &member_id,
+                member_epoch,
+                instance_id: instance_id.as_deref(),
+                rack_id: rack_id.as_deref(),
+                rebalance_timeout_ms,
+                subscribed_topic_names: subscribed_topic_names.as_deref(),
+                server_assignor: server_assignor.as_deref(),
+                topic_partitions: topic_partitions.as_deref(),
+            })
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CreateAcls;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for CreateAcls
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::CreateAclsRequest { creations, .. } = body else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?creations);
+
+        CreateAclsRequest::with_storage(ctx.storage.clone())
+            .response(creations.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CreatePartitions;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for CreatePartitions
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::CreatePartitionsRequest {
+            topics,
+            timeout_ms,
+            validate_only,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?topics, ?timeout_ms, ?validate_only);
+
+        CreatePartitionsRequest::with_storage(ctx.storage.clone())
+            .response(topics.as_deref(), timeout_ms, validate_only)
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CreateTopics;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for CreateTopics
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::CreateTopicsRequest {
+            validate_only,
+            topics,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?validate_only, ?topics);
+
+        CreateTopic::with_storage(ctx.storage.clone())
+            .with_defaults(ctx.topic_defaults.clone())
+            .response(topics, validate_only.unwrap_or(false))
+            .await
+            .map(Some)
+            .map(|topics| Body::CreateTopicsResponse {
+                throttle_time_ms: Some(0),
+                topics,
+                unknown_tagged_fields: vec![],
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DeleteAcls;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DeleteAcls
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DeleteAclsRequest { filters, .. } = body else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?filters);
+
+        DeleteAclsRequest::with_storage(ctx.storage.clone())
+            .response(filters.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DeleteRecords;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DeleteRecords
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DeleteRecordsRequest {
+            topics, timeout_ms, ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?topics, ?timeout_ms);
+
+        DeleteRecordsRequest::with_storage(ctx.storage.clone())
+            .request(topics.as_deref().unwrap_or(&[]), timeout_ms)
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DeleteTopics;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DeleteTopics
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DeleteTopicsRequest {
+            topics,
+            topic_names,
+            timeout_ms,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?topics, ?topic_names, ?timeout_ms);
+
+        Ok(Body::DeleteTopicsResponse {
+            throttle_time_ms: Some(0),
+            responses: DeleteTopicsRequest::with_storage(ctx.storage.clone())
+                .response(topics, topic_names)
+                .await
+                .map(Some)?,
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeAcls;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DescribeAcls
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DescribeAclsRequest {
+            resource_type_filter,
+            resource_name_filter,
+            pattern_type_filter,
+            principal_filter,
+            host_filter,
+            operation,
+            permission_type,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        let filter = AclFilter {
+            resource_type: resource_type_filter,
+            resource_name: resource_name_filter,
+            pattern_type: pattern_type_filter
+                .unwrap_or(i8::from(tansu_kafka_sans_io::PatternType::Literal)),
+            principal: principal_filter,
+            host: host_filter,
+            operation,
+            permission_type,
+        };
+
+        DescribeAclsRequest::with_storage(ctx.storage.clone())
+            .response(&filter)
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeClientQuotas;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DescribeClientQuotas
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DescribeClientQuotasRequest {
+            components, strict, ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?components, ?strict);
+
+        Ok(DescribeClientQuotasRequest::with_quotas(ctx.quotas.clone())
+            .response(components.as_deref(), strict))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeCluster;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DescribeCluster
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DescribeClusterRequest {
+            include_cluster_authorized_operations,
+            endpoint_type,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?include_cluster_authorized_operations, ?endpoint_type);
+
+        DescribeClusterRequest {
+            cluster_id: ctx.cluster_id.clone(),
+            node_id: ctx.node_id,
+            storage: ctx.storage.clone(),
+            listener: ctx
+                .listener
+                .as_ref()
+                .map(|listener| listener.name().to_owned()),
+            authorizer: ctx.authorizer.clone(),
+            principal: ctx.principal.clone(),
+        }
+        .response(include_cluster_authorized_operations, endpoint_type)
+        .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeConfigs;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DescribeConfigs
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DescribeConfigsRequest {
+            resources,
+            include_synonyms,
+            include_documentation,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?resources, ?include_synonyms, ?include_documentation);
+
+        DescribeConfigsRequest::with_storage(ctx.storage.clone())
+            .with_broker(ctx.node_id, ctx.configuration())
+            .response(
+                resources.as_deref(),
+                include_synonyms,
+                include_documentation,
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeGroups;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DescribeGroups
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DescribeGroupsRequest {
+            groups,
+            include_authorized_operations,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?groups, ?include_authorized_operations);
+
+        ctx.groups
+            .clone()
+            .describe(
+                groups.as_deref(),
+                include_authorized_operations.unwrap_or_default(),
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeProducers;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DescribeProducers
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DescribeProducersRequest { topics, .. } = body else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?topics);
+
+        DescribeProducersRequest::with_storage(ctx.storage.clone())
+            .response(topics.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeTransactions;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DescribeTransactions
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DescribeTransactionsRequest {
+            transactional_ids, ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?transactional_ids);
+
+        DescribeTransactionsRequest::with_storage(ctx.storage.clone())
+            .response(transactional_ids.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DescribeUserScramCredentials;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for DescribeUserScramCredentials
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::DescribeUserScramCredentialsRequest { users, .. } = body else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?users);
+
+        DescribeUserScramCredentialsRequest::with_storage(ctx.storage.clone())
+            .response(users.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ElectLeaders;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for ElectLeaders
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::ElectLeadersRequest {
+            election_type,
+            topic_partitions,
+            timeout_ms,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?election_type, ?topic_partitions, ?timeout_ms);
+
+        ElectLeadersRequest::with_storage(ctx.storage.clone())
+            .response(election_type, topic_partitions.as_deref(), timeout_ms)
+            .await
+    }
+}
+
+/// Fetches within a session use the sessions of the connection in the
+/// context.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Fetch;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for Fetch
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::FetchRequest {
+            max_wait_ms,
+            min_bytes,
+            max_bytes,
+            isolation_level,
+            session_id,
+            session_epoch,
+            topics,
+            forgotten_topics_data,
+            rack_id,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(
+            ?max_wait_ms,
+            ?min_bytes,
+            ?max_bytes,
+            ?isolation_level,
+            ?session_id,
+            ?session_epoch,
+            ?topics,
+            ?forgotten_topics_data,
+            ?rack_id,
+        );
+
+        let context = ctx
+            .fetch_sessions
+            .lock()
+            .map_err(Into::into)
+            .and_then(|mut sessions| {
+                sessions.context(
+                    session_id,
+                    session_epoch,
+                    topics.as_deref(),
+                    forgotten_topics_data.as_deref(),
+                )
+            });
+
+        match context {
+            Ok(context) => FetchRequest::with_storage(ctx.storage.clone())
+                .with_notifications(ctx.notifications.clone())
+                .with_replica_selector(
+                    ctx.replica_selector.clone(),
+                    ReplicaView::new(ctx.node_id, ctx.rack.clone()),
+                )
+                .with_rack_id(rack_id)
+                .with_max_bytes(ctx.fetch_max_bytes)
+                .response(
+                    max_wait_ms,
+                    min_bytes,
+                    max_bytes,
+                    isolation_level,
+                    Some(context.topics()),
+                )
+                .await
+                .and_then(|body| {
+                    ctx.fetch_sessions
+                        .lock()
+                        .map(|mut sessions| sessions.propagate(&context, body))
+                        .map_err(Into::into)
+                })
+                .inspect(|r| debug!(?r))
+                .inspect_err(|error| error!(?error)),
+
+            Err(Error::Api(error_code)) => FetchResponse::builder()
+                .error_code(error_code)
+                .build()
+                .map_err(Into::into),
+
+            Err(error) => Err(error),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FindCoordinator;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for FindCoordinator
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::FindCoordinatorRequest {
+            key,
+            key_type,
+            coordinator_keys,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?key, ?key_type, ?coordinator_keys);
+
+        FindCoordinatorRequest.response(
+            key.as_deref(),
+            key_type,
+            coordinator_keys.as_deref(),
+            ctx.node_id,
+            ctx.advertised(),
+        )
+    }
+}
+
+/// Client telemetry, which is not registered by default.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GetTelemetrySubscriptions;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for GetTelemetrySubscriptions
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, _ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::GetTelemetrySubscriptionsRequest {
+            client_instance_id, ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?client_instance_id);
+
+        Ok(GetTelemetrySubscriptionsRequest.response(client_instance_id))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Heartbeat;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for Heartbeat
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::HeartbeatRequest {
+            group_id,
+            generation_id,
+            member_id,
+            group_instance_id,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?group_id, ?generation_id, ?member_id, ?group_instance_id);
+
+        ctx.groups
+            .clone()
+            .heartbeat(
+                &group_id,
+                generation_id,
+                &member_id,
+                group_instance_id.as_deref(),
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct InitProducerId;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for InitProducerId
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::InitProducerIdRequest {
+            transactional_id,
+            transaction_timeout_ms,
+            producer_id,
+            producer_epoch,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(
+            ?transactional_id,
+            ?transaction_timeout_ms,
+            ?producer_id,
+            ?producer_epoch,
+        );
+
+        InitProducerIdRequest::with_storage(ctx.storage.clone())
+            .response(
+                transactional_id.as_deref(),
+                transaction_timeout_ms,
+                producer_id,
+                producer_epoch,
+            )
+            .await
+            .map(|response| Body::InitProducerIdResponse {
+                throttle_time_ms: 0,
+                error_code: response.error.into(),
+                producer_id: response.id,
+                producer_epoch: response.epoch,
+                unknown_tagged_fields: vec![],
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct JoinGroup;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for JoinGroup
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::JoinGroupRequest {
+            group_id,
+            session_timeout_ms,
+            rebalance_timeout_ms,
+            member_id,
+            group_instance_id,
+            protocol_type,
+            protocols,
+            reason,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(
+            ?group_id,
+            ?session_timeout_ms,
+            ?rebalance_timeout_ms,
+            ?member_id,
+            ?group_instance_id,
+            ?protocol_type,
+            ?protocols,
+            ?reason,
+        );
+
+        ctx.groups
+            .clone()
+            .join(
+                ctx.client_id.as_deref(),
+                ctx.client_host.as_deref(),
+                &group_id,
+                session_timeout_ms,
+                rebalance_timeout_ms,
+                &member_id,
+                group_instance_id.as_deref(),
+                &protocol_type,
+                protocols.as_deref(),
+                reason.as_deref(),
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct LeaveGroup;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for LeaveGroup
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::LeaveGroupRequest {
+            group_id,
+            member_id,
+            members,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?group_id, ?member_id, ?members);
+
+        ctx.groups
+            .clone()
+            .leave(&group_id, member_id.as_deref(), members.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ListGroups;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for ListGroups
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::ListGroupsRequest { states_filter, .. } = body else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?states_filter);
+
+        ctx.groups.clone().list(states_filter.as_deref()).await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ListOffsets;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for ListOffsets
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::ListOffsetsRequest {
+            replica_id,
+            isolation_level,
+            topics,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?replica_id, ?isolation_level, ?topics);
+
+        ListOffsetsRequest::with_storage(ctx.storage.clone())
+            .response(replica_id, isolation_level, topics.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ListPartitionReassignments;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for ListPartitionReassignments
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::ListPartitionReassignmentsRequest { topics, .. } = body else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?topics);
+
+        ListPartitionReassignmentsRequest::with_storage(ctx.storage.clone())
+            .response(topics.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ListTransactions;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for ListTransactions
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::ListTransactionsRequest {
+            state_filters,
+            producer_id_filters,
+            duration_filter,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?state_filters, ?producer_id_filters, ?duration_filter);
+
+        ListTransactionsRequest::with_storage(ctx.storage.clone())
+            .response(
+                state_filters.as_deref(),
+                producer_id_filters.as_deref(),
+                duration_filter,
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Metadata;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for Metadata
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::MetadataRequest { topics, .. } = body else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?topics);
+
+        MetadataRequest::with_storage(ctx.storage.clone())
+            .response(
+                topics,
+                ctx.listener.as_ref().map(|listener| listener.name()),
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct OffsetCommit;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for OffsetCommit
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::OffsetCommitRequest {
+            group_id,
+            generation_id_or_member_epoch,
+            member_id,
+            group_instance_id,
+            retention_time_ms,
+            topics,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(
+            ?group_id,
+            ?generation_id_or_member_epoch,
+            ?member_id,
+            ?group_instance_id,
+            ?retention_time_ms,
+            ?topics
+        );
+
+        let detail = group::OffsetCommit {
+            group_id: group_id.as_str(),
+            generation_id_or_member_epoch,
+            member_id: member_id.as_deref(),
+            group_instance_id: group_instance_id.as_deref(),
+            retention_time_ms,
+            topics: topics.as_deref(),
+        };
+
+        ctx.groups.clone().offset_commit(detail).await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct OffsetFetch;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for OffsetFetch
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::OffsetFetchRequest {
+            group_id,
+            topics,
+            groups,
+            require_stable,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?group_id, ?topics, ?groups, ?require_stable);
+
+        ctx.groups
+            .clone()
+            .offset_fetch(
+                group_id.as_deref(),
+                topics.as_deref(),
+                groups.as_deref(),
+                require_stable,
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct OffsetForLeaderEpoch;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for OffsetForLeaderEpoch
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::OffsetForLeaderEpochRequest {
+            replica_id, topics, ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?replica_id, ?topics);
+
+        OffsetForLeaderEpochRequest::with_storage(ctx.storage.clone())
+            .response(replica_id, topics.as_deref())
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Produce;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for Produce
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::ProduceRequest {
+            transactional_id,
+            acks,
+            timeout_ms,
+            topic_data,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?transactional_id, ?acks, ?timeout_ms, ?topic_data);
+
+        let request = ProduceRequest::with_storage(ctx.storage.clone())
+            .with_notifications(ctx.notifications.clone());
+
+        if ctx.recompression {
+            request
+        } else {
+            request.without_recompression()
+        }
+        .response(transactional_id, acks, timeout_ms, topic_data)
+        .await
+        .and_then(|response| {
+            ProduceResponse::builder()
+                .responses(response.responses.unwrap_or_default())
+                .throttle_time_ms(response.throttle_time_ms.unwrap_or_default())
+                .node_endpoints(response.node_endpoints.unwrap_or_default())
+                .build()
+                .map_err(Into::into)
+        })
+    }
+}
+
+/// SaslHandshake and SaslAuthenticate change the state of the connection,
+/// and are answered by it rather than dispatched to a handler.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Sasl;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for Sasl
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, _ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        error!(request = body.name());
+        Err(Error::Api(ErrorCode::IllegalSaslState))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SyncGroup;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for SyncGroup
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::SyncGroupRequest {
+            group_id,
+            generation_id,
+            member_id,
+            group_instance_id,
+            protocol_type,
+            protocol_name,
+            assignments,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        ctx.groups
+            .clone()
+            .sync(
+                &group_id,
+                generation_id,
+                &member_id,
+                group_instance_id.as_deref(),
+                protocol_type.as_deref(),
+                protocol_name.as_deref(),
+                assignments.as_deref(),
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct UnregisterBroker;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for UnregisterBroker
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::UnregisterBrokerRequest { broker_id, .. } = body else {
+            return Err(unexpected(&body));
+        };
+
+        UnregisterBrokerRequest::with_storage(ctx.storage.clone())
+            .response(broker_id)
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AddOffsetsToTxn;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for AddOffsetsToTxn
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::AddOffsetsToTxnRequest {
+            transactional_id,
+            producer_id,
+            producer_epoch,
+            group_id,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?transactional_id, ?producer_id, ?producer_epoch, ?group_id);
+
+        AddOffsets::with_storage(ctx.storage.clone())
+            .response(
+                transactional_id.as_str(),
+                producer_id,
+                producer_epoch,
+                group_id.as_str(),
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct AddPartitionsToTxn;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for AddPartitionsToTxn
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::AddPartitionsToTxnRequest {
+            transactions,
+            v_3_and_below_transactional_id,
+            v_3_and_below_producer_id,
+            v_3_and_below_producer_epoch,
+            v_3_and_below_topics,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(
+            ?transactions,
+            ?v_3_and_below_transactional_id,
+            ?v_3_and_below_producer_id,
+            ?v_3_and_below_producer_epoch,
+            ?v_3_and_below_topics
+        );
+
+        AddPartitions::with_storage(ctx.storage.clone())
+            .response(
+                transactions,
+                v_3_and_below_transactional_id,
+                v_3_and_below_producer_id,
+                v_3_and_below_producer_epoch,
+                v_3_and_below_topics,
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct EndTxn;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for EndTxn
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::EndTxnRequest {
+            transactional_id,
+            producer_id,
+            producer_epoch,
+            committed,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(?transactional_id, ?producer_id, ?producer_epoch, ?committed);
+
+        End::with_storage(ctx.storage.clone())
+            .response(
+                transactional_id.as_str(),
+                producer_id,
+                producer_epoch,
+                committed,
+            )
+            .await
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct TxnOffsetCommit;
+
+#[async_trait]
+impl<G, S> RequestHandler<G, S> for TxnOffsetCommit
+where
+    G: Coordinator,
+    S: Storage,
+{
+    async fn handle(&self, ctx: &ConnectionContext<G, S>, body: Body) -> Result<Body> {
+        let Body::TxnOffsetCommitRequest {
+            transactional_id,
+            group_id,
+            producer_id,
+            producer_epoch,
+            generation_id,
+            member_id,
+            group_instance_id,
+            topics,
+            ..
+        } = body
+        else {
+            return Err(unexpected(&body));
+        };
+
+        debug!(
+            ?transactional_id,
+            ?group_id,
+            ?producer_id,
+            ?producer_epoch,
+            ?generation_id,
+            ?member_id,
+            ?group_instance_id,
+            ?topics,
+        );
+
+        txn::offset_commit::OffsetCommit::with_storage(ctx.storage.clone())
+            .response(
+                transactional_id.as_str(),
+                group_id.as_str(),
+                producer_id,
+                producer_epoch,
+                generation_id,
+                member_id,
+                group_instance_id,
+                topics,
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinator::group::administrator::Controller;
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        create_topics_request::CreatableTopic, create_topics_response::CreatableTopicResult, ApiKey,
+    };
+    use tansu_storage::dynostore::DynoStore;
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    fn context() -> Result<ConnectionContext<Controller<DynoStore>, DynoStore>> {
+        let storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        Controller::with_storage(storage.clone())
+            .map(|groups| ConnectionContext::new(12321, "abc", storage, groups))
+    }
+
+    fn create_topics(name: &str) -> Body {
+        Body::CreateTopicsRequest {
+            topics: Some(vec![CreatableTopic {
+                name: name.into(),
+                num_partitions: 3,
+                replication_factor: 1,
+                assignments: Some([].into()),
+                configs: Some([].into()),
+            }]),
+            timeout_ms: 30_000,
+            validate_only: Some(false),
+            unknown_tagged_fields: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn create_topics_handler() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let ctx = context()?;
+
+        let Body::CreateTopicsResponse {
+            topics: Some(topics),
+            ..
+        } = CreateTopics.handle(&ctx, create_topics("pqr")).await?
+        else {
+            panic!("expected a create topics response")
+        };
+
+        assert!(matches!(
+            topics.as_slice(),
+            [CreatableTopicResult {
+                name,
+                error_code,
+                num_partitions: Some(3),
+                ..
+            }] if name == "pqr" && *error_code == i16::from(ErrorCode::None)
+        ));
+
+        let Body::CreateTopicsResponse {
+            topics: Some(topics),
+            ..
+        } = CreateTopics.handle(&ctx, create_topics("pqr")).await?
+        else {
+            panic!("expected a create topics response")
+        };
+
+        assert_eq!(
+            vec![i16::from(ErrorCode::TopicAlreadyExists)],
+            topics
+                .iter()
+                .map(|topic| topic.error_code)
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn request_for_another_handler() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let ctx = context()?;
+
+        assert!(matches!(
+            Metadata.handle(&ctx, create_topics("pqr")).await,
+            Err(Error::Api(ErrorCode::InvalidRequest))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn api_versions_from_registry() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let ctx = ConnectionContext {
+            registry: Arc::new(Registry::empty().with_handler(
+                ApiKey::ApiVersions,
+                0,
+                3,
+                ApiVersions,
+            )),
+            ..context()?
+        };
+
+        let Body::ApiVersionsResponse {
+            api_keys: Some(api_keys),
+            ..
+        } = ApiVersions
+            .handle(
+                &ctx,
+                Body::ApiVersionsRequest {
+                    client_software_name: Some("abc".into()),
+                    client_software_version: Some("1.0".into()),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            panic!("expected an api versions response")
+        };
+
+        assert_eq!(
+            vec![(i16::from(ApiKey::ApiVersions), 0, 3)],
+            api_keys
+                .iter()
+                .map(|api_version| (
+                    api_version.api_key,
+                    api_version.min_version,
+                    api_version.max_version
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+}
@@ -60,7 +60,8 @@ where
 mod tests {
     use super::*;
     use crate::Error;
-    use object_store::memory::InMemory;
+    use object_store::{memory::InMemory, ObjectStore};
+    use std::sync::Arc;
     use tansu_kafka_sans_io::ErrorCode;
     use tansu_storage::dynostore::DynoStore;
     use tracing::subscriber::DefaultGuard;
@@ -147,4 +148,90 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn txn_init_producer_id_after_restart() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let cluster = "abc";
+        let node = 12321;
+
+        let transaction_timeout_ms = 60_000;
+        let producer_id = Some(-1);
+        let producer_epoch = Some(-1);
+
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+
+        let mut request = InitProducerIdRequest::with_storage(DynoStore::new(
+            cluster,
+            node,
+            object_store.clone(),
+        ));
+
+        assert_eq!(
+            ProducerIdResponse {
+                error: ErrorCode::None,
+                id: 1,
+                epoch: 0
+            },
+            request
+                .response(
+                    Some("txn-abc"),
+                    transaction_timeout_ms,
+                    producer_id,
+                    producer_epoch,
+                )
+                .await?
+        );
+
+        assert_eq!(
+            ProducerIdResponse {
+                error: ErrorCode::None,
+                id: 2,
+                epoch: 0
+            },
+            request
+                .response(
+                    Some("txn-pqr"),
+                    transaction_timeout_ms,
+                    producer_id,
+                    producer_epoch,
+                )
+                .await?
+        );
+
+        // the transactional id keeps its producer id, with a bumped epoch
+        let mut restarted =
+            InitProducerIdRequest::with_storage(DynoStore::new(cluster, node, object_store));
+
+        assert_eq!(
+            ProducerIdResponse {
+                error: ErrorCode::None,
+                id: 1,
+                epoch: 1
+            },
+            restarted
+                .response(
+                    Some("txn-abc"),
+                    transaction_timeout_ms,
+                    producer_id,
+                    producer_epoch,
+                )
+                .await?
+        );
+
+        // recovering an epoch that has since been bumped
+        assert_eq!(
+            ProducerIdResponse {
+                error: ErrorCode::InvalidProducerEpoch,
+                id: -1,
+                epoch: -1
+            },
+            restarted
+                .response(Some("txn-abc"), transaction_timeout_ms, Some(1), Some(0))
+                .await?
+        );
+
+        Ok(())
+    }
 }
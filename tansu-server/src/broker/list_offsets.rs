@@ -13,14 +13,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::{collections::BTreeSet, ops::Deref};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Deref,
+};
 
 use tansu_kafka_sans_io::{
     list_offsets_request::ListOffsetsTopic,
     list_offsets_response::{ListOffsetsPartitionResponse, ListOffsetsTopicResponse},
-    Body,
+    Body, ErrorCode,
 };
-use tansu_storage::{ListOffsetRequest, Storage, Topition};
+use tansu_storage::{ListOffsetRequest, Storage, TopicId, Topition};
 use tracing::{debug, error};
 
 use crate::Result;
@@ -38,6 +41,37 @@ where
         Self { storage }
     }
 
+    async fn leader_epochs(
+        &mut self,
+        topics: &[ListOffsetsTopic],
+    ) -> Result<BTreeMap<Topition, i32>> {
+        let topics = topics
+            .iter()
+            .map(|topic| TopicId::from(topic.name.as_str()))
+            .collect::<Vec<_>>();
+
+        self.storage
+            .metadata(Some(&topics))
+            .await
+            .map(|metadata| {
+                metadata
+                    .topics()
+                    .iter()
+                    .filter(|topic| topic.error_code == i16::from(ErrorCode::None))
+                    .filter_map(|topic| topic.name.as_deref().zip(topic.partitions.as_deref()))
+                    .flat_map(|(name, partitions)| {
+                        partitions.iter().map(move |partition| {
+                            (
+                                Topition::new(name, partition.partition_index),
+                                partition.leader_epoch.unwrap_or(-1),
+                            )
+                        })
+                    })
+                    .collect()
+            })
+            .map_err(Into::into)
+    }
+
     pub async fn response(
         &mut self,
         replica_id: i32,
@@ -49,6 +83,8 @@ where
         let throttle_time_ms = Some(0);
 
         let topics = if let Some(topics) = topics {
+            let leader_epochs = self.leader_epochs(topics).await?;
+
             let mut offsets = vec![];
 
             for topic in topics {
@@ -92,7 +128,10 @@ where
                                                         .unwrap_or(Some(0))
                                                         .or(Some(0)),
                                                     offset: offset.offset().or(Some(0)),
-                                                    leader_epoch: Some(0),
+                                                    leader_epoch: leader_epochs
+                                                        .get(topition)
+                                                        .copied()
+                                                        .or(Some(-1)),
                                                 })
                                             } else {
                                                 None
@@ -111,6 +150,7 @@ where
         Ok(Body::ListOffsetsResponse {
             throttle_time_ms,
             topics,
+            unknown_tagged_fields: vec![],
         })
         .inspect(|r| debug!(?r))
     }
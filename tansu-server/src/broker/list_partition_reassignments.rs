@@ -14,6 +14,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::Result;
+use std::collections::{btree_map::Entry, BTreeMap};
 use tansu_kafka_sans_io::{
     list_partition_reassignments_request::ListPartitionReassignmentsTopics,
     list_partition_reassignments_response::{
@@ -22,6 +23,76 @@ use tansu_kafka_sans_io::{
     Body, ErrorCode,
 };
 use tansu_storage::{Storage, TopicId};
+use tracing::debug;
+
+/// The replicas of each partition of a topic, as placed by metadata,
+/// or `None` when the topic doesn't exist.
+pub(crate) async fn placed_replicas<S>(
+    storage: &mut S,
+    topic: &str,
+) -> Result<Option<BTreeMap<i32, Vec<i32>>>>
+where
+    S: Storage,
+{
+    let metadata = storage
+        .metadata(Some(&[TopicId::Name(topic.into())]))
+        .await?;
+
+    Ok(metadata
+        .topics()
+        .first()
+        .filter(|metadata| metadata.error_code == i16::from(ErrorCode::None))
+        .map(|metadata| {
+            metadata
+                .partitions
+                .iter()
+                .flatten()
+                .map(|partition| {
+                    let mut replicas = vec![];
+
+                    for replica in partition.replica_nodes.iter().flatten() {
+                        if !replicas.contains(replica) {
+                            replicas.push(*replica);
+                        }
+                    }
+
+                    (partition.partition_index, replicas)
+                })
+                .collect()
+        }))
+}
+
+/// A reassignment to the target replicas, which is ongoing while they
+/// differ from those placed. Storage is shared by every broker, so once
+/// placed there is no data to be moved.
+pub(crate) fn ongoing(
+    partition_index: i32,
+    target: &[i32],
+    placed: &[i32],
+) -> Option<OngoingPartitionReassignment> {
+    let adding = target
+        .iter()
+        .filter(|replica| !placed.contains(replica))
+        .copied()
+        .collect::<Vec<_>>();
+
+    let removing = placed
+        .iter()
+        .filter(|replica| !target.contains(replica))
+        .copied()
+        .collect::<Vec<_>>();
+
+    if adding.is_empty() && removing.is_empty() {
+        return None;
+    }
+
+    Some(OngoingPartitionReassignment {
+        partition_index,
+        replicas: Some(target.iter().chain(removing.iter()).copied().collect()),
+        adding_replicas: Some(adding),
+        removing_replicas: Some(removing),
+    })
+}
 
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct ListPartitionReassignmentsRequest<S> {
@@ -40,41 +111,60 @@ where
         &mut self,
         topics: Option<&[ListPartitionReassignmentsTopics]>,
     ) -> Result<Body> {
-        let topics = topics.map(|topics| {
-            topics
-                .iter()
-                .map(|topic| TopicId::Name(topic.name.as_str().into()))
-                .collect::<Vec<_>>()
-        });
-
-        let metadata = self.storage.metadata(topics.as_deref()).await?;
-
-        let mut ongoing = vec![];
-
-        for topic in metadata.topics() {
-            let Some(ref name) = topic.name else { continue };
-
-            ongoing.push(OngoingTopicReassignment {
-                name: name.into(),
-                partitions: topic.partitions.as_ref().map(|partitions| {
-                    partitions
-                        .iter()
-                        .map(|partition| OngoingPartitionReassignment {
-                            partition_index: partition.partition_index,
-                            replicas: partition.replica_nodes.clone(),
-                            adding_replicas: Some(vec![]),
-                            removing_replicas: Some(vec![]),
-                        })
-                        .collect::<Vec<_>>()
-                }),
-            });
+        debug!(?topics);
+
+        let mut placed = BTreeMap::new();
+        let mut reassignments = BTreeMap::<String, Vec<_>>::new();
+
+        for (topition, target) in self.storage.reassignments().await? {
+            // without topics, every ongoing reassignment is listed
+            if topics.is_some_and(|topics| {
+                !topics.iter().any(|topic| {
+                    topic.name == topition.topic()
+                        && topic
+                            .partition_indexes
+                            .as_ref()
+                            .is_none_or(|indexes| indexes.contains(&topition.partition()))
+                })
+            }) {
+                continue;
+            }
+
+            let replicas = match placed.entry(topition.topic().to_owned()) {
+                Entry::Occupied(occupied) => occupied.into_mut(),
+                Entry::Vacant(vacant) => {
+                    vacant.insert(placed_replicas(&mut self.storage, topition.topic()).await?)
+                }
+            };
+
+            let Some(reassignment) = replicas
+                .as_ref()
+                .and_then(|replicas| replicas.get(&topition.partition()))
+                .and_then(|replicas| ongoing(topition.partition(), &target, replicas))
+            else {
+                continue;
+            };
+
+            reassignments
+                .entry(topition.topic().to_owned())
+                .or_default()
+                .push(reassignment);
         }
 
         Ok(Body::ListPartitionReassignmentsResponse {
             throttle_time_ms: 0,
             error_code: ErrorCode::None.into(),
             error_message: None,
-            topics: Some(ongoing),
+            topics: Some(
+                reassignments
+                    .into_iter()
+                    .map(|(name, partitions)| OngoingTopicReassignment {
+                        name,
+                        partitions: Some(partitions),
+                    })
+                    .collect(),
+            ),
+            unknown_tagged_fields: vec![],
         })
     }
 }
@@ -0,0 +1,246 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, SystemTime};
+
+use crate::Result;
+use tansu_kafka_sans_io::{list_transactions_response::TransactionState, Body, ErrorCode};
+use tansu_storage::{Storage, TxnDetail};
+use tracing::debug;
+
+/// The states that a transaction may be filtered by, although only
+/// those of [`state`] are ever listed.
+pub(crate) const STATES: [&str; 8] = [
+    "Empty",
+    "Ongoing",
+    "PrepareCommit",
+    "PrepareAbort",
+    "CompleteCommit",
+    "CompleteAbort",
+    "Dead",
+    "PrepareEpochFence",
+];
+
+/// The state of a transaction, which is ongoing once it has been started,
+/// and otherwise empty. A transaction ends as it is committed or aborted.
+pub(crate) fn state(txn: &TxnDetail) -> &'static str {
+    if txn.started.is_some() {
+        "Ongoing"
+    } else {
+        "Empty"
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ListTransactionsRequest<S> {
+    storage: S,
+}
+
+impl<S> ListTransactionsRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    pub async fn response(
+        &mut self,
+        state_filters: Option<&[String]>,
+        producer_id_filters: Option<&[i64]>,
+        duration_filter: Option<i64>,
+    ) -> Result<Body> {
+        debug!(?state_filters, ?producer_id_filters, ?duration_filter);
+
+        let state_filters = state_filters.unwrap_or_default();
+        let producer_id_filters = producer_id_filters.unwrap_or_default();
+
+        // only transactions running for longer than the duration are listed
+        let started_before = duration_filter
+            .and_then(|duration| u64::try_from(duration).ok())
+            .map(|duration| SystemTime::now() - Duration::from_millis(duration));
+
+        let transaction_states = self
+            .storage
+            .transactions()
+            .await?
+            .into_iter()
+            .filter(|txn| {
+                state_filters.is_empty() || state_filters.iter().any(|filter| filter == state(txn))
+            })
+            .filter(|txn| {
+                producer_id_filters.is_empty() || producer_id_filters.contains(&txn.producer_id)
+            })
+            .filter(|txn| {
+                started_before.is_none_or(|started_before| {
+                    txn.started.is_some_and(|started| started <= started_before)
+                })
+            })
+            .map(|txn| TransactionState {
+                transaction_state: state(&txn).into(),
+                transactional_id: txn.transactional_id,
+                producer_id: txn.producer_id,
+            })
+            .collect();
+
+        Ok(Body::ListTransactionsResponse {
+            throttle_time_ms: 0,
+            error_code: ErrorCode::None.into(),
+            unknown_state_filters: Some(
+                state_filters
+                    .iter()
+                    .filter(|filter| !STATES.contains(&filter.as_str()))
+                    .cloned()
+                    .collect(),
+            ),
+            transaction_states: Some(transaction_states),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::init_producer_id::InitProducerIdRequest,
+        fixture::{ongoing, storage_with_topic},
+        Error,
+    };
+    use tansu_storage::dynostore::DynoStore;
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const TOPIC: &str = "pqr";
+    const TRANSACTIONAL_ID: &str = "txn-abc";
+
+    async fn listed(
+        storage: &DynoStore,
+        state_filters: &[&str],
+        producer_id_filters: &[i64],
+        duration_filter: Option<i64>,
+    ) -> Result<(Vec<String>, Vec<(String, i64, String)>)> {
+        let state_filters = state_filters
+            .iter()
+            .map(|state| String::from(*state))
+            .collect::<Vec<_>>();
+
+        let body = ListTransactionsRequest::with_storage(storage.clone())
+            .response(
+                Some(&state_filters),
+                Some(producer_id_filters),
+                duration_filter,
+            )
+            .await?;
+
+        let Body::ListTransactionsResponse {
+            error_code,
+            unknown_state_filters: Some(unknown_state_filters),
+            transaction_states: Some(transaction_states),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        assert_eq!(i16::from(ErrorCode::None), error_code);
+
+        Ok((
+            unknown_state_filters,
+            transaction_states
+                .into_iter()
+                .map(|txn| (txn.transactional_id, txn.producer_id, txn.transaction_state))
+                .collect(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn filters() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_topic(TOPIC, 1).await?;
+
+        _ = InitProducerIdRequest::with_storage(storage.clone())
+            .response(Some("txn-empty"), 60_000, Some(-1), Some(-1))
+            .await?;
+
+        let producer_id = ongoing(&storage, TRANSACTIONAL_ID, TOPIC).await?;
+        let ongoing = (
+            TRANSACTIONAL_ID.to_owned(),
+            producer_id,
+            "Ongoing".to_owned(),
+        );
+
+        let (unknown, listed_all) = listed(&storage, &[], &[], None).await?;
+        assert!(unknown.is_empty());
+        assert_eq!(2, listed_all.len());
+        assert!(listed_all.contains(&ongoing));
+
+        assert_eq!(
+            (vec!["Lost".to_owned()], vec![ongoing.clone()]),
+            listed(&storage, &["Ongoing", "Lost"], &[], None).await?
+        );
+
+        assert_eq!(
+            (vec![], vec![ongoing.clone()]),
+            listed(&storage, &[], &[producer_id], Some(-1)).await?
+        );
+
+        assert_eq!(
+            (vec![], vec![]),
+            listed(&storage, &["PrepareCommit"], &[], None).await?
+        );
+
+        // the transaction has not been running for an hour
+        assert_eq!(
+            (vec![], vec![]),
+            listed(&storage, &[], &[], Some(3_600_000)).await?
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Error, Result};
+use std::{fmt, str::FromStr};
+use tansu_kafka_sans_io::broker_registration_request::Listener;
+use url::Url;
+
+/// The security protocol of a listener, using the same ids as Kafka.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SecurityProtocol {
+    #[default]
+    Plaintext,
+    Ssl,
+    SaslPlaintext,
+    SaslSsl,
+}
+
+impl SecurityProtocol {
+    /// Connections must authenticate with SASL before any other request.
+    pub fn is_sasl(&self) -> bool {
+        matches!(self, Self::SaslPlaintext | Self::SaslSsl)
+    }
+
+    /// Connections are encrypted with TLS.
+    pub fn is_tls(&self) -> bool {
+        matches!(self, Self::Ssl | Self::SaslSsl)
+    }
+}
+
+impl From<SecurityProtocol> for i16 {
+    fn from(value: SecurityProtocol) -> Self {
+        match value {
+            SecurityProtocol::Plaintext => 0,
+            SecurityProtocol::Ssl => 1,
+            SecurityProtocol::SaslPlaintext => 2,
+            SecurityProtocol::SaslSsl => 3,
+        }
+    }
+}
+
+impl TryFrom<i16> for SecurityProtocol {
+    type Error = Error;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Plaintext),
+            1 => Ok(Self::Ssl),
+            2 => Ok(Self::SaslPlaintext),
+            3 => Ok(Self::SaslSsl),
+            otherwise => Err(Error::Message(format!(
+                "unknown security protocol: {otherwise}"
+            ))),
+        }
+    }
+}
+
+impl FromStr for SecurityProtocol {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "PLAINTEXT" => Ok(Self::Plaintext),
+            "SSL" => Ok(Self::Ssl),
+            "SASL_PLAINTEXT" => Ok(Self::SaslPlaintext),
+            "SASL_SSL" => Ok(Self::SaslSsl),
+            _ => Err(Error::Message(format!("unknown security protocol: {s}"))),
+        }
+    }
+}
+
+impl fmt::Display for SecurityProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Plaintext => "PLAINTEXT",
+            Self::Ssl => "SSL",
+            Self::SaslPlaintext => "SASL_PLAINTEXT",
+            Self::SaslSsl => "SASL_SSL",
+        })
+    }
+}
+
+/// A named listener, bound to one address while advertising another to
+/// clients, e.g., a broker bound to 0.0.0.0 in a container that is reached
+/// as kafka:9092.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ListenerConfig {
+    pub bind: Url,
+    pub advertised: Listener,
+}
+
+impl ListenerConfig {
+    pub fn new(name: &str, bind: Url, advertised: &Url) -> Self {
+        let advertised = Listener {
+            name: name.to_owned(),
+            host: advertised.host_str().unwrap_or("localhost").to_owned(),
+            port: advertised.port().unwrap_or(9092),
+            security_protocol: SecurityProtocol::default().into(),
+        };
+
+        Self { bind, advertised }
+    }
+
+    pub fn with_security_protocol(self, security_protocol: SecurityProtocol) -> Self {
+        Self {
+            advertised: Listener {
+                security_protocol: security_protocol.into(),
+                ..self.advertised
+            },
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.advertised.name.as_str()
+    }
+
+    pub fn security_protocol(&self) -> SecurityProtocol {
+        SecurityProtocol::try_from(self.advertised.security_protocol).unwrap_or_default()
+    }
+
+    /// The socket address that this listener binds.
+    pub fn bind_address(&self) -> String {
+        format!(
+            "{}:{}",
+            self.bind.host_str().unwrap_or("0.0.0.0"),
+            self.bind.port().unwrap_or(9092)
+        )
+    }
+
+    /// The host:port advertised to clients.
+    pub fn advertised_address(&self) -> String {
+        format!("{}:{}", self.advertised.host, self.advertised.port)
+    }
+}
+
+impl FromStr for ListenerConfig {
+    type Err = Error;
+
+    /// Parse "name=bind" or "name=bind,advertised", advertising the bind
+    /// address when no other is given.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, urls) = s.split_once('=').ok_or_else(|| {
+            Error::Message(format!("expecting name=bind[,advertised], found: {s}"))
+        })?;
+
+        if name.is_empty() {
+            return Err(Error::Message(format!("listener without a name: {s}")));
+        }
+
+        let (bind, advertised) = urls
+            .split_once(',')
+            .map_or((urls, urls), |(bind, advertised)| (bind, advertised));
+
+        Url::parse(bind)
+            .and_then(|bind| Url::parse(advertised).map(|advertised| (bind, advertised)))
+            .map(|(bind, advertised)| Self::new(name, bind, &advertised))
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{broker::Broker, coordinator::group::administrator::Controller};
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        metadata_response::MetadataResponseBroker, ApiKey, Body, Frame, Header,
+    };
+    use tansu_storage::dynostore::DynoStore;
+
+    #[test]
+    fn parse() -> Result<()> {
+        let listener = ListenerConfig::from_str("external=tcp://0.0.0.0:9093,tcp://kafka:19093")?;
+
+        assert_eq!("external", listener.name());
+        assert_eq!("0.0.0.0:9093", listener.bind_address());
+        assert_eq!("kafka:19093", listener.advertised_address());
+
+        let listener = ListenerConfig::from_str("internal=tcp://localhost:9092")?;
+        assert_eq!("localhost:9092", listener.bind_address());
+        assert_eq!("localhost:9092", listener.advertised_address());
+
+        assert!(ListenerConfig::from_str("tcp://localhost:9092").is_err());
+        assert!(ListenerConfig::from_str("=tcp://localhost:9092").is_err());
+
+        Ok(())
+    }
+
+    fn broker(listeners: Vec<ListenerConfig>) -> Result<Broker<Controller<DynoStore>, DynoStore>> {
+        let storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        Ok(Broker::new(
+            12321,
+            "abc",
+            listeners,
+            None,
+            storage.clone(),
+            Controller::with_storage(storage)?,
+        ))
+    }
+
+    async fn metadata_brokers(
+        broker: &mut Broker<Controller<DynoStore>, DynoStore>,
+    ) -> Result<Vec<MetadataResponseBroker>> {
+        let api_version = 12;
+
+        let frame = Frame {
+            size: 0,
+            header: Header::Request {
+                api_key: ApiKey::Metadata.into(),
+                api_version,
+                correlation_id: 6,
+                client_id: None,
+            },
+            body: Body::MetadataRequest {
+                topics: Some([].into()),
+                allow_auto_topic_creation: Some(false),
+                include_cluster_authorized_operations: None,
+                include_topic_authorized_operations: Some(false),
+                unknown_tagged_fields: vec![],
+            },
+        };
+
+        let response = broker
+            .process_request(frame)
+            .await?
+            .expect("metadata is answered");
+
+        let Frame {
+            body:
+                Body::MetadataResponse {
+                    brokers: Some(brokers),
+                    ..
+                },
+            ..
+        } = Frame::decode_response(&response, ApiKey::Metadata, api_version)?
+        else {
+            panic!("expecting metadata response with brokers")
+        };
+
+        Ok(brokers)
+    }
+
+    #[tokio::test]
+    async fn metadata_advertises_listener() -> Result<()> {
+        let mut broker = broker(vec![ListenerConfig::from_str(
+            "external=tcp://0.0.0.0:9092,tcp://kafka.example.com:19092",
+        )?])?;
+
+        _ = broker.register().await?;
+
+        let brokers = metadata_brokers(&mut broker).await?;
+
+        assert_eq!(1, brokers.len());
+        assert_eq!(12321, brokers[0].node_id);
+        assert_eq!("kafka.example.com", brokers[0].host);
+        assert_eq!(19092, brokers[0].port);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn metadata_advertises_listener_of_connection() -> Result<()> {
+        let internal =
+            ListenerConfig::from_str("internal=tcp://0.0.0.0:9092,tcp://localhost:9092")?;
+        let external =
+            ListenerConfig::from_str("external=tcp://0.0.0.0:9093,tcp://kafka.example.com:19093")?
+                .with_security_protocol(SecurityProtocol::Ssl);
+
+        let mut broker = broker(vec![internal.clone(), external.clone()])?;
+        _ = broker.register().await?;
+
+        broker.listener = Some(internal);
+        let brokers = metadata_brokers(&mut broker).await?;
+        assert_eq!(1, brokers.len());
+        assert_eq!("localhost", brokers[0].host);
+        assert_eq!(9092, brokers[0].port);
+
+        broker.listener = Some(external);
+        let brokers = metadata_brokers(&mut broker).await?;
+        assert_eq!(1, brokers.len());
+        assert_eq!("kafka.example.com", brokers[0].host);
+        assert_eq!(19093, brokers[0].port);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sasl_listener_without_mechanism() -> Result<()> {
+        let listener = ListenerConfig::from_str("external=tcp://127.0.0.1:0")?
+            .with_security_protocol(SecurityProtocol::SaslPlaintext);
+
+        assert!(broker(vec![listener])?.listen().await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tls_listener_without_certificate() -> Result<()> {
+        let listener = ListenerConfig::from_str("external=tcp://127.0.0.1:0")?
+            .with_security_protocol(SecurityProtocol::Ssl);
+
+        assert!(broker(vec![listener])?.listen().await.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn security_protocol() -> Result<()> {
+        for protocol in [
+            SecurityProtocol::Plaintext,
+            SecurityProtocol::Ssl,
+            SecurityProtocol::SaslPlaintext,
+            SecurityProtocol::SaslSsl,
+        ] {
+            assert_eq!(protocol, SecurityProtocol::from_str(&protocol.to_string())?);
+            assert_eq!(protocol, SecurityProtocol::try_from(i16::from(protocol))?);
+        }
+
+        assert!(SecurityProtocol::from_str("TLS").is_err());
+
+        Ok(())
+    }
+}
@@ -13,7 +13,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use tansu_kafka_sans_io::{metadata_request::MetadataRequestTopic, Body};
+use tansu_kafka_sans_io::{
+    metadata_request::MetadataRequestTopic, metadata_response::MetadataResponseBroker,
+    response::MetadataResponse, Body,
+};
 use tansu_storage::{Storage, TopicId};
 use tracing::error;
 
@@ -32,29 +35,51 @@ where
         Self { storage }
     }
 
-    pub async fn response(&mut self, topics: Option<Vec<MetadataRequestTopic>>) -> Result<Body> {
-        let throttle_time_ms = Some(0);
-
-        let topics = topics.map(|topics| topics.iter().map(TopicId::from).collect::<Vec<_>>());
+    /// Describe the topics, with brokers at their advertised address for the
+    /// listener that the request arrived on.
+    pub async fn response(
+        &mut self,
+        topics: Option<Vec<MetadataRequestTopic>>,
+        listener: Option<&str>,
+    ) -> Result<Body> {
+        let topics = topics
+            .map(|topics| {
+                topics
+                    .iter()
+                    .map(TopicId::try_from)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
 
         let response = self
             .storage
             .metadata(topics.as_deref())
             .await
             .inspect_err(|err| error!(?err))?;
-        let brokers = Some(response.brokers().to_owned());
-        let cluster_id = response.cluster().map(|s| s.into());
-        let controller_id = response.controller();
-        let topics = Some(response.topics().to_owned());
-        let cluster_authorized_operations = None;
-
-        Ok(Body::MetadataResponse {
-            throttle_time_ms,
-            brokers,
-            cluster_id,
-            controller_id,
-            topics,
-            cluster_authorized_operations,
-        })
+
+        let brokers = if listener.is_some() {
+            self.storage
+                .brokers(listener)
+                .await
+                .inspect_err(|err| error!(?err))?
+                .into_iter()
+                .map(|broker| MetadataResponseBroker {
+                    node_id: broker.broker_id,
+                    host: broker.host,
+                    port: broker.port,
+                    rack: broker.rack,
+                })
+                .collect()
+        } else {
+            response.brokers().to_vec()
+        };
+
+        MetadataResponse::builder()
+            .brokers(brokers)
+            .cluster_id(response.cluster())
+            .controller_id(response.controller().unwrap_or(-1))
+            .topics(response.topics().iter().cloned())
+            .build()
+            .map_err(Into::into)
     }
 }
@@ -0,0 +1,71 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use tansu_storage::Topition;
+use tokio::sync::watch;
+use tracing::debug;
+
+/// Wakes fetches that are parked on a topition when a batch is produced to it.
+///
+/// A fetch subscribes to each topition before reading it, so that a batch
+/// produced between the read and parking is not missed.
+#[derive(Clone, Debug, Default)]
+pub struct Notifications {
+    topitions: Arc<Mutex<BTreeMap<Topition, watch::Sender<u64>>>>,
+}
+
+impl Notifications {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, topition: &Topition) -> watch::Receiver<u64> {
+        let mut topitions = self
+            .topitions
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+
+        if let Some(sender) = topitions.get(topition) {
+            sender.subscribe()
+        } else {
+            let (sender, receiver) = watch::channel(0);
+            _ = topitions.insert(topition.to_owned(), sender);
+            receiver
+        }
+    }
+
+    pub fn produced(&self, topition: &Topition) {
+        let mut topitions = self
+            .topitions
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+
+        if let Some(sender) = topitions.get(topition) {
+            debug!(?topition, receivers = sender.receiver_count());
+
+            if sender.receiver_count() == 0 {
+                _ = topitions.remove(topition);
+            } else {
+                sender.send_modify(|produced| *produced = produced.wrapping_add(1));
+            }
+        }
+    }
+}
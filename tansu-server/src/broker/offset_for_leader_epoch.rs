@@ -0,0 +1,434 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+
+use crate::Result;
+use tansu_kafka_sans_io::{
+    metadata_response::MetadataResponseTopic,
+    offset_for_leader_epoch_request::{OffsetForLeaderPartition, OffsetForLeaderTopic},
+    offset_for_leader_epoch_response::{EpochEndOffset, OffsetForLeaderTopicResult},
+    Body, ErrorCode,
+};
+use tansu_storage::{Storage, TopicId, Topition};
+use tracing::{debug, error};
+
+const UNDEFINED_EPOCH: i32 = -1;
+const UNDEFINED_EPOCH_OFFSET: i64 = -1;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct OffsetForLeaderEpochRequest<S> {
+    storage: S,
+}
+
+impl<S> OffsetForLeaderEpochRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    fn epoch_end_offset(partition: i32, error_code: ErrorCode) -> EpochEndOffset {
+        EpochEndOffset {
+            error_code: error_code.into(),
+            partition,
+            leader_epoch: Some(UNDEFINED_EPOCH),
+            end_offset: UNDEFINED_EPOCH_OFFSET,
+        }
+    }
+
+    // records are never truncated when the leader epoch of a partition is
+    // advanced, so every epoch up to the current one ends at the high watermark
+    async fn end_offset(
+        &mut self,
+        topic: &str,
+        leader_epoch: i32,
+        partition: &OffsetForLeaderPartition,
+    ) -> Result<EpochEndOffset> {
+        debug!(?topic, ?leader_epoch, ?partition);
+
+        match partition.current_leader_epoch {
+            Some(current) if current > leader_epoch => {
+                return Ok(Self::epoch_end_offset(
+                    partition.partition,
+                    ErrorCode::UnknownLeaderEpoch,
+                ))
+            }
+
+            Some(current) if current >= 0 && current < leader_epoch => {
+                return Ok(Self::epoch_end_offset(
+                    partition.partition,
+                    ErrorCode::FencedLeaderEpoch,
+                ))
+            }
+
+            _ => (),
+        }
+
+        // an epoch that isn't known has no end offset
+        if partition.leader_epoch > leader_epoch {
+            return Ok(Self::epoch_end_offset(partition.partition, ErrorCode::None));
+        }
+
+        let topition = Topition::new(topic, partition.partition);
+
+        self.storage
+            .offset_stage(&topition)
+            .await
+            .map(|offset_stage| EpochEndOffset {
+                error_code: ErrorCode::None.into(),
+                partition: partition.partition,
+                leader_epoch: Some(partition.leader_epoch),
+                end_offset: offset_stage.high_watermark(),
+            })
+            .inspect_err(|error| error!(?error, ?topition))
+            .map_err(Into::into)
+    }
+
+    async fn topic_result(
+        &mut self,
+        topic: &OffsetForLeaderTopic,
+    ) -> Result<OffsetForLeaderTopicResult> {
+        debug!(?topic);
+
+        let metadata = self
+            .storage
+            .metadata(Some(&[TopicId::Name(topic.topic.clone())]))
+            .await?;
+
+        let known = metadata
+            .topics()
+            .first()
+            .filter(|metadata| metadata.error_code == i16::from(ErrorCode::None))
+            .and_then(|MetadataResponseTopic { partitions, .. }| partitions.as_deref())
+            .map(|partitions| {
+                partitions
+                    .iter()
+                    .map(|partition| {
+                        (
+                            partition.partition_index,
+                            partition.leader_epoch.unwrap_or(UNDEFINED_EPOCH),
+                        )
+                    })
+                    .collect::<BTreeMap<_, _>>()
+            })
+            .unwrap_or_default();
+
+        let mut partitions = vec![];
+
+        for partition in topic.partitions.as_deref().unwrap_or_default() {
+            partitions.push(
+                if let Some(leader_epoch) = known.get(&partition.partition) {
+                    self.end_offset(topic.topic.as_str(), *leader_epoch, partition)
+                        .await?
+                } else {
+                    Self::epoch_end_offset(partition.partition, ErrorCode::UnknownTopicOrPartition)
+                },
+            );
+        }
+
+        Ok(OffsetForLeaderTopicResult {
+            topic: topic.topic.clone(),
+            partitions: Some(partitions),
+        })
+    }
+
+    pub async fn response(
+        &mut self,
+        replica_id: Option<i32>,
+        topics: Option<&[OffsetForLeaderTopic]>,
+    ) -> Result<Body> {
+        debug!(?replica_id, ?topics);
+
+        let mut results = vec![];
+
+        for topic in topics.unwrap_or_default() {
+            results.push(self.topic_result(topic).await?);
+        }
+
+        Ok(Body::OffsetForLeaderEpochResponse {
+            throttle_time_ms: Some(0),
+            topics: Some(results),
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixture::storage_with_topic, Error};
+    use bytes::Bytes;
+    use tansu_kafka_sans_io::{
+        record::{deflated, inflated, Record},
+        Ack,
+    };
+    use tansu_storage::dynostore::DynoStore;
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const TOPIC: &str = "pqr";
+    const LEADER_EPOCH: i32 = 0;
+
+    async fn storage_with_records(records: usize) -> Result<DynoStore> {
+        let mut storage = storage_with_topic(TOPIC, 1).await?;
+
+        for _ in 0..records {
+            let batch = inflated::Batch::builder()
+                .record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+                .build()
+                .and_then(deflated::Batch::try_from)?;
+
+            _ = storage
+                .produce(&Topition::new(TOPIC, 0), batch, Ack::FullIsr)
+                .await?;
+        }
+
+        Ok(storage)
+    }
+
+    async fn end_offsets(
+        storage: DynoStore,
+        topic: &str,
+        partitions: &[(i32, Option<i32>, i32)],
+    ) -> Result<Vec<EpochEndOffset>> {
+        let body = OffsetForLeaderEpochRequest::with_storage(storage)
+            .response(
+                Some(-1),
+                Some(&[OffsetForLeaderTopic {
+                    topic: topic.into(),
+                    partitions: Some(
+                        partitions
+                            .iter()
+                            .map(|(partition, current_leader_epoch, leader_epoch)| {
+                                OffsetForLeaderPartition {
+                                    partition: *partition,
+                                    current_leader_epoch: *current_leader_epoch,
+                                    leader_epoch: *leader_epoch,
+                                }
+                            })
+                            .collect(),
+                    ),
+                }]),
+            )
+            .await?;
+
+        let Body::OffsetForLeaderEpochResponse {
+            topics: Some(topics),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        assert_eq!(
+            vec![topic],
+            topics
+                .iter()
+                .map(|topic| topic.topic.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        Ok(topics
+            .into_iter()
+            .flat_map(|topic| topic.partitions.unwrap_or_default())
+            .collect())
+    }
+
+    #[tokio::test]
+    async fn known_epoch() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_records(3).await?;
+
+        assert_eq!(
+            vec![EpochEndOffset {
+                error_code: ErrorCode::None.into(),
+                partition: 0,
+                leader_epoch: Some(LEADER_EPOCH),
+                end_offset: 3,
+            }],
+            end_offsets(
+                storage.clone(),
+                TOPIC,
+                &[(0, Some(LEADER_EPOCH), LEADER_EPOCH)]
+            )
+            .await?
+        );
+
+        // a replica without a current leader epoch isn't validated
+        assert_eq!(
+            vec![EpochEndOffset {
+                error_code: ErrorCode::None.into(),
+                partition: 0,
+                leader_epoch: Some(LEADER_EPOCH),
+                end_offset: 3,
+            }],
+            end_offsets(storage, TOPIC, &[(0, Some(-1), LEADER_EPOCH)]).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unknown_epoch() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_records(3).await?;
+
+        assert_eq!(
+            vec![
+                EpochEndOffset {
+                    error_code: ErrorCode::None.into(),
+                    partition: 0,
+                    leader_epoch: Some(UNDEFINED_EPOCH),
+                    end_offset: UNDEFINED_EPOCH_OFFSET,
+                },
+                EpochEndOffset {
+                    error_code: ErrorCode::UnknownLeaderEpoch.into(),
+                    partition: 0,
+                    leader_epoch: Some(UNDEFINED_EPOCH),
+                    end_offset: UNDEFINED_EPOCH_OFFSET,
+                },
+            ],
+            end_offsets(
+                storage,
+                TOPIC,
+                &[
+                    (0, Some(LEADER_EPOCH), LEADER_EPOCH + 1),
+                    (0, Some(LEADER_EPOCH + 1), LEADER_EPOCH),
+                ]
+            )
+            .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fenced_epoch() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let mut storage = storage_with_records(3).await?;
+
+        storage
+            .alter_reassignment(&Topition::new(TOPIC, 0), Some(&[12321]))
+            .await?;
+
+        // an earlier epoch ends at the high watermark of the current epoch
+        assert_eq!(
+            vec![
+                EpochEndOffset {
+                    error_code: ErrorCode::FencedLeaderEpoch.into(),
+                    partition: 0,
+                    leader_epoch: Some(UNDEFINED_EPOCH),
+                    end_offset: UNDEFINED_EPOCH_OFFSET,
+                },
+                EpochEndOffset {
+                    error_code: ErrorCode::None.into(),
+                    partition: 0,
+                    leader_epoch: Some(LEADER_EPOCH),
+                    end_offset: 3,
+                },
+            ],
+            end_offsets(
+                storage,
+                TOPIC,
+                &[
+                    (0, Some(LEADER_EPOCH), LEADER_EPOCH),
+                    (0, Some(LEADER_EPOCH + 1), LEADER_EPOCH),
+                ]
+            )
+            .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unknown_topic_or_partition() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_records(0).await?;
+
+        assert_eq!(
+            vec![EpochEndOffset {
+                error_code: ErrorCode::UnknownTopicOrPartition.into(),
+                partition: 0,
+                leader_epoch: Some(UNDEFINED_EPOCH),
+                end_offset: UNDEFINED_EPOCH_OFFSET,
+            }],
+            end_offsets(storage.clone(), "xyz", &[(0, None, LEADER_EPOCH)]).await?
+        );
+
+        assert_eq!(
+            vec![
+                EpochEndOffset {
+                    error_code: ErrorCode::None.into(),
+                    partition: 0,
+                    leader_epoch: Some(LEADER_EPOCH),
+                    end_offset: 0,
+                },
+                EpochEndOffset {
+                    error_code: ErrorCode::UnknownTopicOrPartition.into(),
+                    partition: 1,
+                    leader_epoch: Some(UNDEFINED_EPOCH),
+                    end_offset: UNDEFINED_EPOCH_OFFSET,
+                },
+            ],
+            end_offsets(
+                storage,
+                TOPIC,
+                &[(0, None, LEADER_EPOCH), (1, None, LEADER_EPOCH)]
+            )
+            .await?
+        );
+
+        Ok(())
+    }
+}
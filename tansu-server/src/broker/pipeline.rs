@@ -0,0 +1,464 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{Error, Result};
+use std::{collections::BTreeMap, future::Future};
+use tansu_kafka_sans_io::Body;
+use tokio::{sync::watch, task::JoinSet};
+
+/// The maximum number of requests handled concurrently on a connection,
+/// matching the default `max.in.flight.requests.per.connection` of the
+/// Java client.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 5;
+
+/// Whether a request only reads state, so that it may be handled
+/// concurrently with other requests on its connection. Any other request,
+/// such as a produce, commit or group membership, is ordered.
+pub fn is_read_only(body: &Body) -> bool {
+    matches!(
+        body,
+        Body::ApiVersionsRequest { .. }
+            | Body::ConsumerGroupDescribeRequest { .. }
+            | Body::DescribeAclsRequest { .. }
+            | Body::DescribeClientQuotasRequest { .. }
+            | Body::DescribeClusterRequest { .. }
+            | Body::DescribeConfigsRequest { .. }
+            | Body::DescribeGroupsRequest { .. }
+            | Body::DescribeProducersRequest { .. }
+            | Body::DescribeTransactionsRequest { .. }
+            | Body::DescribeUserScramCredentialsRequest { .. }
+            | Body::FetchRequest { .. }
+            | Body::FindCoordinatorRequest { .. }
+            | Body::ListGroupsRequest { .. }
+            | Body::ListOffsetsRequest { .. }
+            | Body::ListPartitionReassignmentsRequest { .. }
+            | Body::ListTransactionsRequest { .. }
+            | Body::MetadataRequest { .. }
+            | Body::OffsetFetchRequest { .. }
+            | Body::OffsetForLeaderEpochRequest { .. }
+    )
+}
+
+/// Requests pipelined by a client on a connection.
+///
+/// Up to a maximum number of requests are in flight, with each response
+/// held until every earlier request has been answered, so that responses
+/// are written in the order the requests arrived. A request without a
+/// response, such as a produce with acks=0, completes with none and
+/// doesn't hold up those that follow it.
+///
+/// An ordered request is handled once every earlier ordered request has
+/// been handled, so that requests changing state, such as produces to a
+/// partition, take effect in the order they were sent. A read only request
+/// waits for the ordered requests before it, but is otherwise handled
+/// concurrently.
+#[derive(Debug)]
+pub struct Pipeline {
+    maximum: usize,
+    arrivals: u64,
+    next: u64,
+    in_flight: JoinSet<(u64, Result<Option<Vec<u8>>>)>,
+    completed: BTreeMap<u64, Option<Vec<u8>>>,
+    ordered: Option<watch::Receiver<bool>>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::with_maximum(DEFAULT_MAX_IN_FLIGHT)
+    }
+}
+
+impl Pipeline {
+    pub fn with_maximum(maximum: usize) -> Self {
+        Self {
+            maximum: maximum.max(1),
+            arrivals: 0,
+            next: 0,
+            in_flight: JoinSet::new(),
+            completed: BTreeMap::new(),
+            ordered: None,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.in_flight.len() >= self.maximum
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.in_flight.is_empty()
+    }
+
+    fn arrival(&mut self) -> u64 {
+        let arrival = self.arrivals;
+        self.arrivals += 1;
+        arrival
+    }
+
+    /// Handle the next request concurrently with those already in flight,
+    /// once the earlier ordered requests have been handled.
+    pub fn spawn<F>(&mut self, handler: F)
+    where
+        F: Future<Output = Result<Option<Vec<u8>>>> + Send + 'static,
+    {
+        let arrival = self.arrival();
+        let previous = self.ordered.clone();
+
+        _ = self.in_flight.spawn(async move {
+            handled(previous).await;
+            (arrival, handler.await)
+        });
+    }
+
+    /// Handle the next request once every earlier ordered request has been
+    /// handled.
+    pub fn spawn_ordered<F>(&mut self, handler: F)
+    where
+        F: Future<Output = Result<Option<Vec<u8>>>> + Send + 'static,
+    {
+        let arrival = self.arrival();
+        let (done, ordered) = watch::channel(false);
+        let previous = self.ordered.replace(ordered);
+
+        _ = self.in_flight.spawn(async move {
+            handled(previous).await;
+            let response = handler.await;
+            _ = done.send(true);
+            (arrival, response)
+        });
+    }
+
+    /// Answer the next request without handling it concurrently.
+    pub fn complete(&mut self, response: Option<Vec<u8>>) {
+        let arrival = self.arrival();
+        _ = self.completed.insert(arrival, response);
+    }
+
+    /// Wait for the next request in flight to be handled, returning `None`
+    /// when there are none.
+    pub async fn joined(&mut self) -> Option<Result<()>> {
+        self.in_flight.join_next().await.map(|joined| {
+            joined
+                .map_err(|error| Error::Message(error.to_string()))
+                .and_then(|(arrival, response)| {
+                    response.map(|response| {
+                        _ = self.completed.insert(arrival, response);
+                    })
+                })
+        })
+    }
+
+    /// Wait for every request in flight to be handled.
+    pub async fn settle(&mut self) -> Result<()> {
+        while let Some(joined) = self.joined().await {
+            joined?;
+        }
+
+        Ok(())
+    }
+
+    /// The responses that are ready to be written, in request order.
+    pub fn ready(&mut self) -> Vec<Vec<u8>> {
+        let mut ready = vec![];
+
+        while let Some(response) = self.completed.remove(&self.next) {
+            self.next += 1;
+            ready.extend(response);
+        }
+
+        ready
+    }
+}
+
+// an ordered request is handled once it is done, or it was dropped
+async fn handled(ordered: Option<watch::Receiver<bool>>) {
+    if let Some(mut ordered) = ordered {
+        _ = ordered.wait_for(|done| *done).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{listener::ListenerConfig, Broker},
+        coordinator::group::administrator::Controller,
+    };
+    use object_store::memory::InMemory;
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tansu_kafka_sans_io::{
+        create_topics_request::CreatableTopic,
+        fetch_request::{FetchPartition, FetchTopic},
+        ApiKey, Body, Frame, Header,
+    };
+    use tansu_storage::{dynostore::DynoStore, Storage};
+    use tokio::{
+        io::{duplex, AsyncReadExt, AsyncWriteExt},
+        time::{sleep, Instant},
+    };
+    use url::Url;
+
+    async fn handler(delay_ms: u64, response: Option<&'static [u8]>) -> Result<Option<Vec<u8>>> {
+        sleep(Duration::from_millis(delay_ms)).await;
+        Ok(response.map(Vec::from))
+    }
+
+    #[tokio::test]
+    async fn responses_in_request_order() -> Result<()> {
+        let mut pipeline = Pipeline::default();
+
+        pipeline.spawn(handler(200, Some(b"slow")));
+        pipeline.spawn(handler(10, Some(b"fast")));
+        pipeline.spawn(handler(0, None));
+        pipeline.complete(Some(b"immediate".to_vec()));
+
+        // responses that are ready first are held behind the slow one
+        assert_eq!(Some(()), pipeline.joined().await.transpose()?);
+        assert!(pipeline.ready().is_empty());
+
+        pipeline.settle().await?;
+
+        assert_eq!(
+            vec![b"slow".to_vec(), b"fast".to_vec(), b"immediate".to_vec()],
+            pipeline.ready()
+        );
+        assert!(pipeline.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn no_response_does_not_stall() -> Result<()> {
+        let mut pipeline = Pipeline::default();
+
+        pipeline.spawn(handler(0, None));
+        pipeline.spawn(handler(10, Some(b"acknowledged")));
+
+        pipeline.settle().await?;
+
+        assert_eq!(vec![b"acknowledged".to_vec()], pipeline.ready());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_latency_is_the_maximum() -> Result<()> {
+        let mut pipeline = Pipeline::with_maximum(2);
+
+        let start = Instant::now();
+
+        pipeline.spawn(handler(300, Some(b"first")));
+        pipeline.spawn(handler(300, Some(b"second")));
+        assert!(pipeline.is_full());
+
+        pipeline.settle().await?;
+
+        assert!(start.elapsed() < Duration::from_millis(600));
+        assert_eq!(
+            vec![b"first".to_vec(), b"second".to_vec()],
+            pipeline.ready()
+        );
+
+        Ok(())
+    }
+
+    async fn logged(
+        log: Arc<Mutex<Vec<String>>>,
+        name: &'static str,
+        delay_ms: u64,
+    ) -> Result<Option<Vec<u8>>> {
+        log.lock()?.push(format!("{name} started"));
+        sleep(Duration::from_millis(delay_ms)).await;
+        log.lock()?.push(format!("{name} finished"));
+        Ok(Some(name.as_bytes().to_vec()))
+    }
+
+    #[tokio::test]
+    async fn ordered_requests_are_serialized() -> Result<()> {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut pipeline = Pipeline::default();
+
+        pipeline.spawn_ordered(logged(log.clone(), "first", 200));
+        pipeline.spawn_ordered(logged(log.clone(), "second", 0));
+        pipeline.spawn(logged(log.clone(), "read", 0));
+
+        pipeline.settle().await?;
+
+        // the second waits for the first, with the read waiting for both
+        assert_eq!(
+            vec![
+                "first started",
+                "first finished",
+                "second started",
+                "second finished",
+                "read started",
+                "read finished"
+            ],
+            log.lock()?.clone()
+        );
+
+        assert_eq!(
+            vec![b"first".to_vec(), b"second".to_vec(), b"read".to_vec()],
+            pipeline.ready()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_only() {
+        assert!(is_read_only(&Body::ListGroupsRequest {
+            states_filter: None,
+            unknown_tagged_fields: vec![],
+        }));
+
+        assert!(!is_read_only(&Body::HeartbeatRequest {
+            group_id: "abc".into(),
+            generation_id: 1,
+            member_id: "pqr".into(),
+            group_instance_id: None,
+            unknown_tagged_fields: vec![],
+        }));
+    }
+
+    #[tokio::test]
+    async fn handler_error() {
+        let mut pipeline = Pipeline::default();
+
+        pipeline.spawn(async { Err(Error::Message("failed".into())) });
+
+        assert!(pipeline.settle().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn pipelined_fetches() -> Result<()> {
+        let cluster = "abc";
+        let node = 12321;
+        let topic = "pqr";
+
+        let mut storage = DynoStore::new(cluster, node, InMemory::new());
+        let listener = Url::parse("tcp://localhost:9092")?;
+
+        let mut broker = Broker::new(
+            node,
+            cluster,
+            vec![ListenerConfig::new("broker", listener.clone(), &listener)],
+            None,
+            storage.clone(),
+            Controller::with_storage(storage.clone())?,
+        );
+
+        _ = broker.register().await?;
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: topic.into(),
+                    num_partitions: 1,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some([].into()),
+                },
+                false,
+            )
+            .await?;
+
+        let (mut client, server) = duplex(64 * 1024);
+        let peer = SocketAddr::from(([127, 0, 0, 1], 54321));
+
+        let connection = tokio::spawn(async move { broker.stream_handler(server, peer).await });
+
+        // each fetch of the empty partition waits for max wait
+        let max_wait_ms = 500;
+        let api_version = 12;
+
+        let start = Instant::now();
+
+        for correlation_id in [1, 2] {
+            let request = Frame::request(
+                Header::Request {
+                    api_key: ApiKey::Fetch.into(),
+                    api_version,
+                    correlation_id,
+                    client_id: Some("pipeline".into()),
+                },
+                Body::FetchRequest {
+                    cluster_id: None,
+                    replica_id: Some(-1),
+                    replica_state: None,
+                    max_wait_ms,
+                    min_bytes: 1,
+                    max_bytes: Some(52_428_800),
+                    isolation_level: Some(0),
+                    session_id: Some(0),
+                    session_epoch: Some(-1),
+                    topics: Some(vec![FetchTopic {
+                        topic: Some(topic.into()),
+                        topic_id: None,
+                        partitions: Some(vec![FetchPartition {
+                            partition: 0,
+                            current_leader_epoch: Some(-1),
+                            fetch_offset: 0,
+                            last_fetched_epoch: Some(-1),
+                            log_start_offset: Some(-1),
+                            partition_max_bytes: 1_048_576,
+                        }]),
+                    }]),
+                    forgotten_topics_data: Some([].into()),
+                    rack_id: Some("".into()),
+                    unknown_tagged_fields: vec![],
+                },
+            )?;
+
+            client.write_all(&request).await?;
+        }
+
+        let mut correlation_ids = vec![];
+
+        for _ in 0..2 {
+            let mut size = [0u8; 4];
+            _ = client.read_exact(&mut size).await?;
+
+            let mut response = vec![0u8; size.len() + usize::try_from(u32::from_be_bytes(size))?];
+            response[..4].copy_from_slice(&size);
+            _ = client.read_exact(&mut response[4..]).await?;
+
+            let Frame {
+                header: Header::Response { correlation_id },
+                ..
+            } = Frame::decode_response(&response, ApiKey::Fetch, api_version)?
+            else {
+                panic!("expecting a fetch response")
+            };
+
+            correlation_ids.push(correlation_id);
+        }
+
+        let elapsed = start.elapsed();
+        connection.abort();
+
+        assert_eq!(vec![1, 2], correlation_ids);
+
+        // the fetches wait concurrently, rather than one after the other
+        let max_wait = Duration::from_millis(u64::try_from(max_wait_ms)?);
+        assert!(elapsed >= max_wait);
+        assert!(elapsed < 2 * max_wait);
+
+        Ok(())
+    }
+}
@@ -13,18 +13,27 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{Error, Result};
+use crate::{
+    broker::{error_response::partition_produce_error, notify::Notifications},
+    Error, Result,
+};
+use std::collections::{BTreeMap, BTreeSet};
 use tansu_kafka_sans_io::{
     produce_request::{PartitionProduceData, TopicProduceData},
-    produce_response::{NodeEndpoint, PartitionProduceResponse, TopicProduceResponse},
-    ErrorCode,
+    produce_response::{
+        LeaderIdAndEpoch, NodeEndpoint, PartitionProduceResponse, TopicProduceResponse,
+    },
+    record::{deflated, inflated, legacy::MessageSet},
+    Ack, Compression, ErrorCode, DEFAULT_MAX_FRAME_BYTES,
 };
-use tansu_storage::{Storage, Topition};
+use tansu_storage::{Storage, TopicId, Topition};
 use tracing::{debug, error};
 
-#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Default)]
 pub struct ProduceRequest<S> {
     storage: S,
+    notifications: Notifications,
+    recompression: bool,
 }
 
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -39,70 +48,249 @@ where
     S: Storage,
 {
     pub fn with_storage(storage: S) -> Self {
-        Self { storage }
-    }
-
-    fn error(&self, index: i32, error_code: ErrorCode) -> PartitionProduceResponse {
-        PartitionProduceResponse {
-            index,
-            error_code: error_code.into(),
-            base_offset: -1,
-            log_append_time_ms: Some(-1),
-            log_start_offset: Some(0),
-            record_errors: Some([].into()),
-            error_message: None,
-            current_leader: None,
+        Self {
+            storage,
+            notifications: Notifications::default(),
+            recompression: true,
+        }
+    }
+
+    /// Fetches parked on a topition are woken by these notifications
+    /// when a batch is produced to it.
+    #[must_use]
+    pub fn with_notifications(self, notifications: Notifications) -> Self {
+        Self {
+            notifications,
+            ..self
         }
     }
 
+    /// Store batches as they were produced, ignoring the compression.type
+    /// of their topic.
+    #[must_use]
+    pub fn without_recompression(self) -> Self {
+        Self {
+            recompression: false,
+            ..self
+        }
+    }
+
+    // the codec that batches produced to this topic are stored with, when
+    // it isn't the one chosen by the producer, and the largest size that
+    // their records may inflate to
+    async fn limits(&mut self, name: &str) -> (Option<(Compression, deflated::Options)>, usize) {
+        let config = self
+            .storage
+            .topic_config(&TopicId::from(name))
+            .await
+            .inspect_err(|error| debug!(?error, ?name))
+            .unwrap_or_default();
+
+        let compression = if self.recompression {
+            topic_compression(&config)
+        } else {
+            None
+        };
+
+        (compression, max_message_bytes(&config))
+    }
+
     async fn partition(
         &mut self,
+        txn: &mut Transaction,
         name: &str,
+        ack: Ack,
+        compression: Option<&(Compression, deflated::Options)>,
+        max_message_bytes: usize,
         partition: PartitionProduceData,
     ) -> PartitionProduceResponse {
-        match partition.records {
-            Some(mut records) if records.batches.len() == 1 => {
-                let batch = records.batches.remove(0);
+        // the records are only parsed once their length and CRC are verified,
+        // with a legacy message set upgraded into a single record batch
+        let batches = partition
+            .records
+            .as_ref()
+            .ok_or(ErrorCode::UnknownServerError)
+            .and_then(|records| {
+                if MessageSet::is_legacy(records.as_bytes()) {
+                    MessageSet::decode_within(records.as_bytes().clone(), max_message_bytes)
+                        .and_then(inflated::Batch::try_from)
+                        .and_then(deflated::Batch::try_from)
+                        .map(|batch| vec![batch])
+                } else {
+                    records.verify().and_then(|()| records.batches())
+                }
+                .inspect_err(|err| error!(?err))
+                .map_err(|error| match error {
+                    tansu_kafka_sans_io::Error::InflatedTooLarge { .. } => {
+                        ErrorCode::MessageTooLarge
+                    }
+                    _ => ErrorCode::CorruptMessage,
+                })
+            });
+
+        let batch = match batches {
+            Ok(mut batches) if batches.len() == 1 => batches.remove(0),
+            Ok(_) => {
+                return partition_produce_error(partition.index, ErrorCode::UnknownServerError)
+            }
+            Err(error_code) => return partition_produce_error(partition.index, error_code),
+        };
+
+        let tp = Topition::new(name, partition.index);
 
-                let tp = Topition::new(name, partition.index);
+        // the records are inflated once, both to validate them and for any
+        // recompression
+        let inflated = match inflate(&batch, max_message_bytes) {
+            Ok(inflated) => inflated,
+            Err(error_code) => {
+                debug!(?tp, ?error_code);
+                return partition_produce_error(partition.index, error_code);
+            }
+        };
+
+        if let Err(error_code) = self.validate(txn, &tp, &batch).await {
+            debug!(?tp, ?error_code);
+            return partition_produce_error(partition.index, error_code);
+        }
 
-                match self
+        let batch = match recompress(batch, inflated, compression) {
+            Ok(batch) => batch,
+            Err(error_code) => return partition_produce_error(partition.index, error_code),
+        };
+
+        match self
+            .storage
+            .produce(&tp, batch, ack)
+            .await
+            .map_err(Into::into)
+            .inspect_err(|err| error!(?err))
+        {
+            Ok(base_offset) => {
+                self.notifications.produced(&tp);
+
+                PartitionProduceResponse {
+                    index: partition.index,
+                    error_code: ErrorCode::None.into(),
+                    base_offset,
+                    log_append_time_ms: Some(-1),
+                    log_start_offset: Some(0),
+                    record_errors: Some([].into()),
+                    error_message: None,
+                    current_leader: None,
+                }
+            }
+
+            Err(Error::Storage(tansu_storage::Error::Api(error_code))) => {
+                debug!(?self, ?error_code);
+                partition_produce_error(partition.index, error_code)
+            }
+
+            Err(_) => partition_produce_error(partition.index, ErrorCode::UnknownServerError),
+        }
+    }
+
+    // a transactional batch is only appended to a partition added to its
+    // transaction
+    async fn validate(
+        &mut self,
+        txn: &mut Transaction,
+        tp: &Topition,
+        batch: &deflated::Batch,
+    ) -> Result<(), ErrorCode> {
+        let Some(transactional_id) = txn.transactional_id.as_deref() else {
+            return if batch.is_transactional() {
+                Err(ErrorCode::InvalidTxnState)
+            } else {
+                Ok(())
+            };
+        };
+
+        if !batch.is_transactional() {
+            return Err(ErrorCode::InvalidTxnState);
+        }
+
+        let producer = (batch.producer_id, batch.producer_epoch);
+
+        match txn.producer {
+            Some(current) if current != producer => return Err(ErrorCode::InvalidTxnState),
+
+            Some(_) => (),
+
+            None => {
+                txn.partitions = self
                     .storage
-                    .produce(&tp, batch)
+                    .txn_partitions(transactional_id, producer.0, producer.1)
                     .await
-                    .map_err(Into::into)
-                    .inspect_err(|err| error!(?err))
-                {
-                    Ok(base_offset) => PartitionProduceResponse {
-                        index: partition.index,
-                        error_code: ErrorCode::None.into(),
-                        base_offset,
-                        log_append_time_ms: Some(-1),
-                        log_start_offset: Some(0),
-                        record_errors: Some([].into()),
-                        error_message: None,
-                        current_leader: None,
-                    },
+                    .map_err(|error| match error {
+                        tansu_storage::Error::Api(
+                            error_code @ (ErrorCode::ProducerFenced
+                            | ErrorCode::InvalidProducerEpoch),
+                        ) => error_code,
 
-                    Err(Error::Storage(tansu_storage::Error::Api(error_code))) => {
-                        debug!(?self, ?error_code);
-                        self.error(partition.index, error_code)
-                    }
+                        tansu_storage::Error::Api(_) => ErrorCode::InvalidTxnState,
 
-                    Err(_) => self.error(partition.index, ErrorCode::UnknownServerError),
-                }
+                        error => {
+                            error!(?error);
+                            ErrorCode::UnknownServerError
+                        }
+                    })?;
+
+                txn.producer = Some(producer);
             }
+        }
 
-            _otherwise => self.error(partition.index, ErrorCode::UnknownServerError),
+        if txn.partitions.contains(tp) {
+            Ok(())
+        } else {
+            Err(ErrorCode::InvalidTxnState)
         }
     }
 
-    async fn topic(&mut self, topic: TopicProduceData) -> TopicProduceResponse {
+    async fn topic(
+        &mut self,
+        txn: &mut Transaction,
+        ack: Option<Ack>,
+        topic: TopicProduceData,
+    ) -> TopicProduceResponse {
         let mut partitions = vec![];
 
+        let (compression, max_message_bytes) = if ack.is_some() {
+            self.limits(&topic.name).await
+        } else {
+            (None, DEFAULT_MAX_FRAME_BYTES)
+        };
+
         if let Some(partition_data) = topic.partition_data {
             for partition in partition_data {
-                partitions.push(self.partition(&topic.name, partition).await)
+                partitions.push(if let Some(ack) = ack {
+                    self.partition(
+                        txn,
+                        &topic.name,
+                        ack,
+                        compression.as_ref(),
+                        max_message_bytes,
+                        partition,
+                    )
+                    .await
+                } else {
+                    partition_produce_error(partition.index, ErrorCode::InvalidRequiredAcks)
+                })
+            }
+        }
+
+        // a partition in error carries its current leader, so that a client
+        // can tell whether its metadata is stale
+        if partitions
+            .iter()
+            .any(|partition| partition.error_code != i16::from(ErrorCode::None))
+        {
+            let leaders = self.leaders(&topic.name).await;
+
+            for partition in partitions
+                .iter_mut()
+                .filter(|partition| partition.error_code != i16::from(ErrorCode::None))
+            {
+                partition.current_leader = leaders.get(&partition.index).cloned();
             }
         }
 
@@ -112,13 +300,48 @@ where
         }
     }
 
+    async fn leaders(&mut self, name: &str) -> BTreeMap<i32, LeaderIdAndEpoch> {
+        self.storage
+            .metadata(Some(&[TopicId::from(name)]))
+            .await
+            .inspect_err(|error| error!(?error, ?name))
+            .map(|metadata| {
+                metadata
+                    .topics()
+                    .iter()
+                    .filter(|topic| topic.error_code == i16::from(ErrorCode::None))
+                    .flat_map(|topic| topic.partitions.as_deref().unwrap_or_default())
+                    .map(|partition| {
+                        (
+                            partition.partition_index,
+                            LeaderIdAndEpoch {
+                                leader_id: partition.leader_id,
+                                leader_epoch: partition.leader_epoch.unwrap_or(-1),
+                            },
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub async fn response(
         &mut self,
-        _transactional_id: Option<String>,
-        _acks: i16,
+        transactional_id: Option<String>,
+        acks: i16,
         _timeout_ms: i32,
         topic_data: Option<Vec<TopicProduceData>>,
     ) -> Result<ProduceResponse> {
+        // an unknown acks value fails every partition in the request
+        let ack = Ack::try_from(acks)
+            .inspect_err(|err| debug!(?err, acks))
+            .ok();
+
+        let mut txn = Transaction {
+            transactional_id,
+            ..Default::default()
+        };
+
         let mut responses =
             Vec::with_capacity(topic_data.as_ref().map_or(0, |topic_data| topic_data.len()));
 
@@ -126,7 +349,7 @@ where
             for topic in topics {
                 debug!(?topic);
 
-                responses.push(self.topic(topic).await)
+                responses.push(self.topic(&mut txn, ack, topic).await)
             }
         }
 
@@ -138,17 +361,139 @@ where
     }
 }
 
+// the transaction of a request, with every transactional batch in the
+// request from the same producer
+#[derive(Clone, Debug, Default)]
+struct Transaction {
+    transactional_id: Option<String>,
+    producer: Option<(i64, i16)>,
+    partitions: BTreeSet<Topition>,
+}
+
+// the compression.type of a topic, with None when batches are stored with
+// the codec chosen by the producer
+fn topic_compression(
+    config: &BTreeMap<String, Option<String>>,
+) -> Option<(Compression, deflated::Options)> {
+    let (compression, level) = match config.get("compression.type")?.as_deref()? {
+        "uncompressed" => (Compression::None, None),
+        "gzip" => (Compression::Gzip, Some("compression.gzip.level")),
+        "snappy" => (Compression::Snappy, None),
+        "lz4" => (Compression::Lz4, Some("compression.lz4.level")),
+        "zstd" => (Compression::Zstd, Some("compression.zstd.level")),
+        _ => return None,
+    };
+
+    let level = level
+        .and_then(|level| config.get(level))
+        .and_then(|value| value.as_deref())
+        .and_then(|value| value.parse().ok());
+
+    Some((compression, deflated::Options::default().level(level)))
+}
+
+// the max.message.bytes of a topic, otherwise the largest frame
+fn max_message_bytes(config: &BTreeMap<String, Option<String>>) -> usize {
+    config
+        .get("max.message.bytes")
+        .and_then(|value| value.as_deref())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_FRAME_BYTES)
+}
+
+// a batch is only deflated from its inflated records with the codec of its
+// topic when it was produced with a different codec
+fn recompress(
+    batch: deflated::Batch,
+    mut inflated: inflated::Batch,
+    compression: Option<&(Compression, deflated::Options)>,
+) -> Result<deflated::Batch, ErrorCode> {
+    let Some((compression, options)) = compression else {
+        return Ok(batch);
+    };
+
+    if batch
+        .batch_attributes()
+        .compression()
+        .is_ok_and(|existing| existing == *compression)
+    {
+        return Ok(batch);
+    }
+
+    inflated.attributes = inflated
+        .batch_attributes()
+        .with_compression(compression.to_owned())
+        .into();
+
+    deflated::Batch::deflate(inflated, *options)
+        .inspect_err(|error| error!(?error, ?compression))
+        .map_err(|_| ErrorCode::UnknownServerError)
+}
+
+// a batch is only appended when the record count and last offset delta of
+// its header agree with its records, which don't inflate beyond the maximum,
+// with control batches only written by the broker
+fn inflate(
+    batch: &deflated::Batch,
+    max_message_bytes: usize,
+) -> Result<inflated::Batch, ErrorCode> {
+    let record_count = i32::try_from(batch.record_count).map_err(|_| ErrorCode::InvalidRecord)?;
+
+    if batch.is_control() || record_count == 0 || batch.last_offset_delta != record_count - 1 {
+        debug!(
+            record_count,
+            batch.last_offset_delta,
+            control = batch.is_control()
+        );
+        return Err(ErrorCode::InvalidRecord);
+    }
+
+    let mut records = batch.records_within(max_message_bytes);
+    let mut inflated = Vec::with_capacity(records.size_hint().0);
+
+    for record in records.by_ref() {
+        inflated.push(record.inspect_err(|err| debug!(?err)).map_err(|error| {
+            if matches!(error, tansu_kafka_sans_io::Error::InflatedTooLarge { .. }) {
+                ErrorCode::MessageTooLarge
+            } else {
+                ErrorCode::InvalidRecord
+            }
+        })?);
+    }
+
+    if records.remaining_bytes() != 0 {
+        debug!(record_count, remaining_bytes = records.remaining_bytes());
+        return Err(ErrorCode::InvalidRecord);
+    }
+
+    Ok(inflated::Batch {
+        base_offset: batch.base_offset,
+        batch_length: batch.batch_length,
+        partition_leader_epoch: batch.partition_leader_epoch,
+        magic: batch.magic,
+        crc: batch.crc,
+        attributes: batch.attributes,
+        last_offset_delta: batch.last_offset_delta,
+        base_timestamp: batch.base_timestamp,
+        max_timestamp: batch.max_timestamp,
+        producer_id: batch.producer_id,
+        producer_epoch: batch.producer_epoch,
+        base_sequence: batch.base_sequence,
+        records: inflated,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{broker::init_producer_id::InitProducerIdRequest, Error};
-    use bytes::Bytes;
+    use crate::{
+        broker::init_producer_id::InitProducerIdRequest, fixture::storage_with_configs, Error,
+    };
+    use bytes::{Bytes, BytesMut};
     use object_store::memory::InMemory;
     use tansu_kafka_sans_io::{
-        record::{
-            deflated::{self, Frame},
-            inflated, Record,
-        },
+        create_topics_request::{CreatableTopic, CreateableTopicConfig},
+        record::{deflated, inflated, Record, Records},
         ErrorCode,
     };
     use tansu_storage::dynostore::DynoStore;
@@ -195,12 +540,20 @@ mod tests {
         builder
             .build()
             .and_then(deflated::Batch::try_from)
-            .map(|deflated| {
+            .map_err(Into::into)
+            .and_then(|batch| batch_data(topic, index, batch))
+    }
+
+    fn batch_data(
+        topic: &str,
+        index: i32,
+        batch: deflated::Batch,
+    ) -> Result<Option<Vec<TopicProduceData>>> {
+        Records::try_from(batch)
+            .map(|records| {
                 let partition_data = PartitionProduceData {
                     index,
-                    records: Some(Frame {
-                        batches: vec![deflated],
-                    }),
+                    records: Some(records),
                 };
 
                 Some(vec![TopicProduceData {
@@ -211,6 +564,26 @@ mod tests {
             .map_err(Into::into)
     }
 
+    fn error_codes(response: ProduceResponse) -> Vec<ErrorCode> {
+        response
+            .responses
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|topic| topic.partition_responses.unwrap_or_default())
+            .map(|partition| ErrorCode::try_from(partition.error_code).expect("a known error code"))
+            .collect()
+    }
+
+    fn lorem(records: usize) -> inflated::Builder {
+        (0..records).fold(inflated::Batch::builder(), |builder, offset_delta| {
+            builder.record(
+                Record::builder()
+                    .offset_delta(i32::try_from(offset_delta).expect("an offset delta"))
+                    .value(Bytes::from_static(b"lorem").into()),
+            )
+        })
+    }
+
     #[tokio::test]
     async fn non_txn_idempotent_unknown_producer_id() -> Result<()> {
         let _guard = init_tracing()?;
@@ -263,6 +636,165 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn invalid_required_acks() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let cluster = "abc";
+        let node = 12321;
+        let topic = "pqr";
+        let index = 0;
+
+        let storage = DynoStore::new(cluster, node, InMemory::new());
+
+        let transactional_id = None;
+        let acks = 2;
+        let timeout_ms = 0;
+
+        assert_eq!(
+            ProduceResponse {
+                responses: Some(vec![TopicProduceResponse {
+                    name: topic.into(),
+                    partition_responses: Some(vec![PartitionProduceResponse {
+                        index,
+                        error_code: ErrorCode::InvalidRequiredAcks.into(),
+                        base_offset: -1,
+                        log_append_time_ms: Some(-1),
+                        log_start_offset: Some(0),
+                        record_errors: Some(vec![]),
+                        error_message: None,
+                        current_leader: None,
+                    }]),
+                }]),
+                throttle_time_ms: Some(0),
+                node_endpoints: None
+            },
+            ProduceRequest::with_storage(storage)
+                .response(
+                    transactional_id,
+                    acks,
+                    timeout_ms,
+                    topic_data(
+                        topic,
+                        index,
+                        inflated::Batch::builder()
+                            .record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+                    )?
+                )
+                .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn corrupt_records() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let cluster = "abc";
+        let node = 12321;
+        let topic = "pqr";
+        let index = 0;
+
+        let storage = DynoStore::new(cluster, node, InMemory::new());
+
+        let records = inflated::Batch::builder()
+            .record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+            .build()
+            .and_then(deflated::Batch::try_from)
+            .and_then(Records::try_from)?;
+
+        // flip the bits of the last byte of record data, invalidating the CRC
+        let mut corrupted = BytesMut::from(&records.as_bytes()[..]);
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+
+        let topic_data = Some(vec![TopicProduceData {
+            name: topic.into(),
+            partition_data: Some(vec![PartitionProduceData {
+                index,
+                records: Some(Records::new(corrupted.freeze())),
+            }]),
+        }]);
+
+        assert_eq!(
+            ProduceResponse {
+                responses: Some(vec![TopicProduceResponse {
+                    name: topic.into(),
+                    partition_responses: Some(vec![PartitionProduceResponse {
+                        index,
+                        error_code: ErrorCode::CorruptMessage.into(),
+                        base_offset: -1,
+                        log_append_time_ms: Some(-1),
+                        log_start_offset: Some(0),
+                        record_errors: Some(vec![]),
+                        error_message: None,
+                        current_leader: None,
+                    }]),
+                }]),
+                throttle_time_ms: Some(0),
+                node_endpoints: None
+            },
+            ProduceRequest::with_storage(storage)
+                .response(None, -1, 0, topic_data)
+                .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn legacy_message_set() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let cluster = "abc";
+        let node = 12321;
+        let topic = "pqr";
+        let index = 0;
+
+        let storage = DynoStore::new(cluster, node, InMemory::new());
+
+        // magic 0 messages: a null key with "abc", then "k" with "def"
+        let message_set = Bytes::from_static(&[
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 17, 67, 220, 63, 175, 0, 0, 255, 255, 255, 255, 0, 0,
+            0, 3, 97, 98, 99, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 18, 170, 113, 70, 244, 0, 0, 0, 0,
+            0, 1, 107, 0, 0, 0, 3, 100, 101, 102,
+        ]);
+
+        let topic_data = Some(vec![TopicProduceData {
+            name: topic.into(),
+            partition_data: Some(vec![PartitionProduceData {
+                index,
+                records: Some(Records::new(message_set)),
+            }]),
+        }]);
+
+        assert_eq!(
+            ProduceResponse {
+                responses: Some(vec![TopicProduceResponse {
+                    name: topic.into(),
+                    partition_responses: Some(vec![PartitionProduceResponse {
+                        index,
+                        error_code: ErrorCode::None.into(),
+                        base_offset: 0,
+                        log_append_time_ms: Some(-1),
+                        log_start_offset: Some(0),
+                        record_errors: Some(vec![]),
+                        error_message: None,
+                        current_leader: None,
+                    }]),
+                }]),
+                throttle_time_ms: Some(0),
+                node_endpoints: None
+            },
+            ProduceRequest::with_storage(storage)
+                .response(None, -1, 0, topic_data)
+                .await?
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn non_txn_idempotent() -> Result<()> {
         let _guard = init_tracing()?;
@@ -607,4 +1139,290 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn record_count_mismatch() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let index = 0;
+
+        let storage = DynoStore::new("abc", 12321, InMemory::new());
+        let mut request = ProduceRequest::with_storage(storage);
+
+        let batch = lorem(3).last_offset_delta(2).build()?;
+        let deflated = deflated::Batch::try_from(batch)?;
+
+        // claiming fewer and more records than are present, with a valid crc
+        for record_count in [2, 4, 1000] {
+            let mut tampered = deflated::Batch {
+                record_count,
+                last_offset_delta: i32::try_from(record_count)? - 1,
+                ..deflated.clone()
+            };
+            tampered.crc = tampered.computed_crc()?;
+
+            assert_eq!(
+                vec![ErrorCode::InvalidRecord],
+                error_codes(
+                    request
+                        .response(None, -1, 0, batch_data(topic, index, tampered)?)
+                        .await?
+                ),
+                "{record_count}"
+            );
+        }
+
+        assert_eq!(
+            vec![ErrorCode::None],
+            error_codes(
+                request
+                    .response(None, -1, 0, batch_data(topic, index, deflated)?)
+                    .await?
+            )
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn last_offset_delta_mismatch() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = DynoStore::new("abc", 12321, InMemory::new());
+        let mut request = ProduceRequest::with_storage(storage);
+
+        for (records, last_offset_delta) in [(1, 5), (3, 0), (2, -1)] {
+            assert_eq!(
+                vec![ErrorCode::InvalidRecord],
+                error_codes(
+                    request
+                        .response(
+                            None,
+                            -1,
+                            0,
+                            topic_data(
+                                "pqr",
+                                0,
+                                lorem(records).last_offset_delta(last_offset_delta)
+                            )?
+                        )
+                        .await?
+                ),
+                "{records}, {last_offset_delta}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn inflated_beyond_max_message_bytes() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let max_message_bytes = 1_048_588;
+
+        let storage = storage_with_configs(topic, 1, &[("max.message.bytes", "1048588")]).await?;
+
+        let mut request = ProduceRequest::with_storage(storage);
+
+        for compression in [Compression::Gzip, Compression::Snappy, Compression::Zstd] {
+            // a few megabytes of zeros that deflate well within the limit
+            let deflated = inflated::Batch::builder()
+                .record(Record::builder().value(Bytes::from(vec![0; 4 * max_message_bytes]).into()))
+                .compression(compression.clone())
+                .build()
+                .and_then(deflated::Batch::try_from)?;
+
+            assert!(
+                deflated.record_data.len() < max_message_bytes / 4,
+                "{compression:?}: {}",
+                deflated.record_data.len()
+            );
+
+            assert_eq!(
+                vec![ErrorCode::MessageTooLarge],
+                error_codes(
+                    request
+                        .response(None, -1, 0, batch_data(topic, 0, deflated)?)
+                        .await?
+                ),
+                "{compression:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn txn_state() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let topic = "pqr";
+        let index = 0;
+        let transactional_id = "txn";
+
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        let producer = InitProducerIdRequest::with_storage(storage.clone())
+            .response(Some(transactional_id), 10_000, Some(-1), Some(-1))
+            .await?;
+
+        let mut request = ProduceRequest::with_storage(storage.clone());
+
+        let txn = |producer_id, producer_epoch| {
+            lorem(1)
+                .producer_id(producer_id)
+                .producer_epoch(producer_epoch)
+                .transactional(true)
+        };
+
+        for (transactional_id, builder) in [
+            // a transactional batch without a transactional id
+            (None, txn(producer.id, producer.epoch)),
+            // a batch outside the transaction of the request
+            (
+                Some(transactional_id),
+                lorem(1)
+                    .producer_id(producer.id)
+                    .producer_epoch(producer.epoch),
+            ),
+            // a producer that is not known to the transactional id
+            (Some(transactional_id), txn(producer.id + 1, producer.epoch)),
+            // a partition that has not been added to the transaction
+            (Some(transactional_id), txn(producer.id, producer.epoch)),
+        ] {
+            assert_eq!(
+                vec![ErrorCode::InvalidTxnState],
+                error_codes(
+                    request
+                        .response(
+                            transactional_id.map(ToOwned::to_owned),
+                            -1,
+                            0,
+                            topic_data(topic, index, builder)?
+                        )
+                        .await?
+                )
+            );
+        }
+
+        assert_eq!(
+            ErrorCode::None,
+            storage
+                .txn_add_partitions(
+                    transactional_id,
+                    producer.id,
+                    producer.epoch,
+                    &[Topition::new(topic, index)],
+                )
+                .await?
+        );
+
+        assert_eq!(
+            vec![ErrorCode::None],
+            error_codes(
+                request
+                    .response(
+                        Some(transactional_id.into()),
+                        -1,
+                        0,
+                        topic_data(topic, index, txn(producer.id, producer.epoch))?
+                    )
+                    .await?
+            )
+        );
+
+        Ok(())
+    }
+
+    async fn stored_compression(
+        request: ProduceRequest<DynoStore>,
+        compression_type: &str,
+    ) -> Result<Compression> {
+        let topic = "pqr";
+        let mut storage = request.storage.clone();
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: topic.into(),
+                    num_partitions: 1,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some(vec![CreateableTopicConfig {
+                        name: "compression.type".into(),
+                        value: Some(compression_type.into()),
+                    }]),
+                },
+                false,
+            )
+            .await?;
+
+        let produced = lorem(3)
+            .last_offset_delta(2)
+            .compression(Compression::Gzip)
+            .build()?;
+
+        assert_eq!(
+            vec![ErrorCode::None],
+            error_codes(
+                request
+                    .clone()
+                    .response(
+                        None,
+                        -1,
+                        0,
+                        batch_data(topic, 0, deflated::Batch::try_from(produced.clone())?)?
+                    )
+                    .await?
+            )
+        );
+
+        let stored = storage
+            .fetch(&Topition::new(topic, 0), 0, 0, 1_048_576)
+            .await?;
+
+        // the records are unchanged by any recompression
+        assert_eq!(
+            produced.records,
+            inflated::Batch::try_from(stored.clone())?.records
+        );
+
+        stored.batch_attributes().compression().map_err(Into::into)
+    }
+
+    #[tokio::test]
+    async fn recompression() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = || DynoStore::new("abc", 12321, InMemory::new());
+
+        assert_eq!(
+            Compression::Zstd,
+            stored_compression(ProduceRequest::with_storage(storage()), "zstd").await?
+        );
+
+        assert_eq!(
+            Compression::None,
+            stored_compression(ProduceRequest::with_storage(storage()), "uncompressed").await?
+        );
+
+        assert_eq!(
+            Compression::Gzip,
+            stored_compression(ProduceRequest::with_storage(storage()), "producer").await?
+        );
+
+        assert_eq!(
+            Compression::Gzip,
+            stored_compression(
+                ProduceRequest::with_storage(storage()).without_recompression(),
+                "zstd"
+            )
+            .await?
+        );
+
+        Ok(())
+    }
 }
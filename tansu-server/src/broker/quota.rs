@@ -0,0 +1,605 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Client quotas, enforced with a token bucket for each client and quota.
+//!
+//! A client that exceeds a quota has its response delayed until the bucket
+//! has refilled, with the delay reported in the throttle time of the
+//! response.
+
+use crate::{Error, Result};
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tansu_kafka_sans_io::Body;
+use tokio::time::Instant;
+use tracing::debug;
+
+pub const CLIENT_ID: &str = "client-id";
+
+/// Records produced, in bytes per second.
+pub const PRODUCER_BYTE_RATE: &str = "producer_byte_rate";
+
+/// Records fetched, in bytes per second.
+pub const CONSUMER_BYTE_RATE: &str = "consumer_byte_rate";
+
+/// Requests per second.
+pub const REQUEST_RATE: &str = "request_rate";
+
+pub const KEYS: [&str; 3] = [PRODUCER_BYTE_RATE, CONSUMER_BYTE_RATE, REQUEST_RATE];
+
+// a full bucket holds a second of usage at the quota rate
+const BURST_SECS: f64 = 1.0;
+
+/// A quota for a client, or with no client id the default for every client
+/// without a quota of its own.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct ClientQuota {
+    pub client_id: Option<String>,
+    pub key: String,
+    pub value: f64,
+}
+
+impl FromStr for ClientQuota {
+    type Err = Error;
+
+    /// Parse "key=value" as a default quota, or "client:key=value" as the
+    /// quota of a client.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (entity, value) = s
+            .split_once('=')
+            .ok_or_else(|| Error::Message(format!("expecting [client:]key=value, found: {s}")))?;
+
+        let (client_id, key) = entity
+            .rsplit_once(':')
+            .map_or((None, entity), |(client_id, key)| {
+                (Some(client_id.to_owned()), key)
+            });
+
+        if !KEYS.contains(&key) {
+            return Err(Error::Message(format!("unknown quota: {key}")));
+        }
+
+        f64::from_str(value)
+            .ok()
+            .filter(|value| value.is_finite() && *value > 0.0)
+            .map(|value| Self {
+                client_id,
+                key: key.to_owned(),
+                value,
+            })
+            .ok_or_else(|| Error::Message(format!("invalid quota value: {value}")))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(rate: f64, now: Instant) -> Self {
+        Self {
+            tokens: rate * BURST_SECS,
+            updated: now,
+        }
+    }
+
+    // take the amount from the bucket, returning the time until it is no
+    // longer in debt
+    fn take(&mut self, rate: f64, amount: f64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * rate).min(rate * BURST_SECS) - amount;
+        self.updated = now;
+
+        if self.tokens < 0.0 {
+            Duration::from_secs_f64(-self.tokens / rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    configured: BTreeMap<Option<String>, BTreeMap<String, f64>>,
+    buckets: BTreeMap<(Option<String>, &'static str), Bucket>,
+}
+
+impl Inner {
+    fn rate(&self, client_id: Option<&str>, key: &str) -> Option<f64> {
+        client_id
+            .and_then(|client_id| self.configured.get(&Some(client_id.to_owned())))
+            .and_then(|quotas| quotas.get(key))
+            .or_else(|| {
+                self.configured
+                    .get(&None)
+                    .and_then(|quotas| quotas.get(key))
+            })
+            .copied()
+    }
+}
+
+/// The quotas of every client, shared by all connections to the broker.
+#[derive(Clone, Debug, Default)]
+pub struct Quotas {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Quotas {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_quotas(self, quotas: impl IntoIterator<Item = ClientQuota>) -> Self {
+        for quota in quotas {
+            self.alter(quota.client_id.as_deref(), &quota.key, Some(quota.value));
+        }
+
+        self
+    }
+
+    /// Set or with no value remove a quota, for a client or the default.
+    pub fn alter(&self, client_id: Option<&str>, key: &str, value: Option<f64>) {
+        let mut inner = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+
+        let client_id = client_id.map(str::to_owned);
+
+        match value {
+            Some(value) => {
+                _ = inner
+                    .configured
+                    .entry(client_id)
+                    .or_default()
+                    .insert(key.to_owned(), value);
+            }
+
+            None => {
+                if let Some(quotas) = inner.configured.get_mut(&client_id) {
+                    _ = quotas.remove(key);
+
+                    if quotas.is_empty() {
+                        _ = inner.configured.remove(&client_id);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The configured quotas, by client with `None` being the default.
+    pub fn configured(&self) -> BTreeMap<Option<String>, BTreeMap<String, f64>> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .configured
+            .clone()
+    }
+
+    fn record(&self, client_id: Option<&str>, key: &'static str, amount: f64) -> Duration {
+        let mut inner = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+
+        let Some(rate) = inner.rate(client_id, key) else {
+            return Duration::ZERO;
+        };
+
+        let now = Instant::now();
+
+        inner
+            .buckets
+            .entry((client_id.map(str::to_owned), key))
+            .or_insert_with(|| Bucket::full(rate, now))
+            .take(rate, amount, now)
+    }
+
+    /// Record a request by a client, with the bytes produced by the request
+    /// and the response to it, returning how long the response is throttled.
+    pub fn throttle(&self, client_id: Option<&str>, produced: usize, response: &Body) -> Duration {
+        [
+            (REQUEST_RATE, 1.0),
+            (PRODUCER_BYTE_RATE, produced as f64),
+            (CONSUMER_BYTE_RATE, fetched(response) as f64),
+        ]
+        .into_iter()
+        .filter(|(_, amount)| *amount > 0.0)
+        .map(|(key, amount)| self.record(client_id, key, amount))
+        .max()
+        .inspect(|throttle| debug!(?client_id, ?throttle))
+        .unwrap_or_default()
+    }
+}
+
+/// The bytes of records produced by a request.
+pub fn produced(request: &Body) -> usize {
+    match request {
+        Body::ProduceRequest {
+            topic_data: Some(topic_data),
+            ..
+        } => topic_data
+            .iter()
+            .flat_map(|topic| topic.partition_data.iter().flatten())
+            .flat_map(|partition| partition.records.iter())
+            .map(|records| records.as_bytes().len())
+            .sum(),
+
+        _ => 0,
+    }
+}
+
+fn fetched(response: &Body) -> usize {
+    match response {
+        Body::FetchResponse {
+            responses: Some(responses),
+            ..
+        } => responses
+            .iter()
+            .flat_map(|topic| topic.partitions.iter().flatten())
+            .flat_map(|partition| partition.records.iter())
+            .map(|records| records.as_bytes().len())
+            .sum(),
+
+        _ => 0,
+    }
+}
+
+/// Report the throttle time in a response. Versions of a response without a
+/// throttle time are unchanged.
+pub fn with_throttle_time_ms(response: &mut Body, throttle_time_ms: i32) {
+    match response {
+        Body::AddOffsetsToTxnResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::AddPartitionsToTxnResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::AlterClientQuotasResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::AlterUserScramCredentialsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::CreateAclsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::CreatePartitionsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::DeleteAclsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::DeleteRecordsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::DescribeAclsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::DescribeClientQuotasResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::DescribeClusterResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::DescribeConfigsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::DescribeUserScramCredentialsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::GetTelemetrySubscriptionsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::InitProducerIdResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::ListPartitionReassignmentsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::DescribeProducersResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::DescribeTransactionsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::ListTransactionsResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::UnregisterBrokerResponse {
+            throttle_time_ms: throttle,
+            ..
+        }
+        | Body::TxnOffsetCommitResponse {
+            throttle_time_ms: throttle,
+            ..
+        } => *throttle = throttle_time_ms,
+
+        Body::ApiVersionsResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::CreateTopicsResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::DeleteTopicsResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::DescribeGroupsResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::FetchResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::FindCoordinatorResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::HeartbeatResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::JoinGroupResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::LeaveGroupResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::ListGroupsResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::ListOffsetsResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::MetadataResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::OffsetCommitResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::OffsetFetchResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::ProduceResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        }
+        | Body::SyncGroupResponse {
+            throttle_time_ms: Some(throttle),
+            ..
+        } => *throttle = throttle_time_ms,
+
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{listener::ListenerConfig, Broker},
+        coordinator::group::administrator::Controller,
+    };
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        create_topics_request::CreatableTopic,
+        produce_request::{PartitionProduceData, TopicProduceData},
+        record::{deflated, inflated, Record, Records},
+        ApiKey, Frame, Header,
+    };
+    use tansu_storage::{dynostore::DynoStore, Storage};
+    use url::Url;
+
+    #[test]
+    fn client_quota_from_str() -> Result<()> {
+        assert_eq!(
+            ClientQuota {
+                client_id: None,
+                key: PRODUCER_BYTE_RATE.into(),
+                value: 1024.0
+            },
+            ClientQuota::from_str("producer_byte_rate=1024")?
+        );
+
+        assert_eq!(
+            ClientQuota {
+                client_id: Some("console-producer".into()),
+                key: REQUEST_RATE.into(),
+                value: 5.0
+            },
+            ClientQuota::from_str("console-producer:request_rate=5")?
+        );
+
+        assert!(ClientQuota::from_str("byte_rate=1024").is_err());
+        assert!(ClientQuota::from_str("request_rate=-1").is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn override_and_default() {
+        let quotas = Quotas::new().with_quotas([
+            ClientQuota {
+                client_id: None,
+                key: REQUEST_RATE.into(),
+                value: 1.0,
+            },
+            ClientQuota {
+                client_id: Some("abc".into()),
+                key: REQUEST_RATE.into(),
+                value: 100.0,
+            },
+        ]);
+
+        let response = Body::HeartbeatResponse {
+            throttle_time_ms: Some(0),
+            error_code: 0,
+            unknown_tagged_fields: vec![],
+        };
+
+        // a full bucket permits a burst of a second at the quota rate
+        assert!(quotas.throttle(None, 0, &response).is_zero());
+        assert!(!quotas.throttle(None, 0, &response).is_zero());
+
+        assert!(quotas.throttle(Some("abc"), 0, &response).is_zero());
+        assert!(quotas.throttle(Some("abc"), 0, &response).is_zero());
+
+        // each client has a bucket of its own
+        assert!(quotas.throttle(Some("pqr"), 0, &response).is_zero());
+    }
+
+    #[tokio::test]
+    async fn produce_over_quota() -> Result<()> {
+        let cluster = "abc";
+        let node = 12321;
+        let topic = "pqr";
+        let client_id = "console-producer";
+
+        let mut storage = DynoStore::new(cluster, node, InMemory::new());
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: topic.into(),
+                    num_partitions: 1,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some([].into()),
+                },
+                false,
+            )
+            .await?;
+
+        let value = Bytes::from(vec![0u8; 512]);
+
+        let records = inflated::Batch::builder()
+            .record(Record::builder().value(value.into()))
+            .build()
+            .and_then(deflated::Batch::try_from)
+            .and_then(Records::try_from)?;
+
+        let quotas = Quotas::new().with_quotas([ClientQuota {
+            client_id: Some(client_id.into()),
+            key: PRODUCER_BYTE_RATE.into(),
+            value: 4096.0,
+        }]);
+
+        let listener = Url::parse("tcp://localhost:9092")?;
+
+        let mut broker = Broker::new(
+            node,
+            cluster,
+            vec![ListenerConfig::new("broker", listener.clone(), &listener)],
+            None,
+            storage.clone(),
+            Controller::with_storage(storage)?,
+        )
+        .with_quotas(quotas);
+
+        let api_version = 9;
+        let mut throttles = vec![];
+
+        // a second of the quota is produced without throttling
+        for correlation_id in 0..12 {
+            let frame = Frame {
+                size: 0,
+                header: Header::Request {
+                    api_key: ApiKey::Produce.into(),
+                    api_version,
+                    correlation_id,
+                    client_id: Some(client_id.into()),
+                },
+                body: Body::ProduceRequest {
+                    transactional_id: None,
+                    acks: -1,
+                    timeout_ms: 1_000,
+                    topic_data: Some(vec![TopicProduceData {
+                        name: topic.into(),
+                        partition_data: Some(vec![PartitionProduceData {
+                            index: 0,
+                            records: Some(records.clone()),
+                        }]),
+                    }]),
+                    unknown_tagged_fields: vec![],
+                },
+            };
+
+            let response = broker
+                .process_request(frame)
+                .await?
+                .expect("produce with acks is answered");
+
+            let Frame {
+                body:
+                    Body::ProduceResponse {
+                        throttle_time_ms, ..
+                    },
+                ..
+            } = Frame::decode_response(&response, ApiKey::Produce, api_version)?
+            else {
+                panic!("expecting produce response")
+            };
+
+            throttles.push(throttle_time_ms.unwrap_or_default());
+        }
+
+        assert_eq!(0, throttles[0]);
+        assert!(throttles
+            .iter()
+            .any(|throttle_time_ms| *throttle_time_ms > 0));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The requests that the broker handles, with the versions of each that it
+//! implements. Requests are only dispatched when registered here, and
+//! ApiVersions advertises the same table.
+//!
+//! Each request is answered by a [`RequestHandler`], with the default
+//! registry having the handlers of the broker. An embedding application
+//! may register handlers of its own, replacing a built in handler or
+//! answering an API that the broker does not.
+
+use super::handler::{self, RequestHandler};
+use crate::coordinator::group::Coordinator;
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
+use tansu_kafka_model::Listener;
+use tansu_kafka_sans_io::{ApiKey, RootMessageMeta};
+use tansu_storage::Storage;
+
+/// A request handled by the broker, in versions from `min_version` to
+/// `max_version` inclusive.
+#[derive(Clone, Debug)]
+pub struct Registration<G, S> {
+    pub min_version: i16,
+    pub max_version: i16,
+    handler: Arc<dyn RequestHandler<G, S>>,
+}
+
+impl<G, S> Registration<G, S> {
+    pub fn supports(&self, api_version: i16) -> bool {
+        (self.min_version..=self.max_version).contains(&api_version)
+    }
+
+    pub fn handler(&self) -> &dyn RequestHandler<G, S> {
+        self.handler.as_ref()
+    }
+}
+
+/// The handler of each request, by api key.
+#[derive(Clone, Debug)]
+pub struct Registry<G, S> {
+    registrations: BTreeMap<ApiKey, Registration<G, S>>,
+}
+
+impl<G, S> Registry<G, S> {
+    /// A registry without any handlers.
+    pub fn empty() -> Self {
+        Self {
+            registrations: BTreeMap::new(),
+        }
+    }
+
+    /// Handle requests with this api key in versions from `min_version` to
+    /// `max_version` inclusive, replacing any existing handler.
+    pub fn with_handler(
+        mut self,
+        api_key: ApiKey,
+        min_version: i16,
+        max_version: i16,
+        handler: impl RequestHandler<G, S> + 'static,
+    ) -> Self {
+        _ = self.registrations.insert(
+            api_key,
+            Registration {
+                min_version,
+                max_version,
+                handler: Arc::new(handler),
+            },
+        );
+
+        self
+    }
+
+    pub fn registration(&self, api_key: ApiKey) -> Option<&Registration<G, S>> {
+        self.registrations.get(&api_key)
+    }
+
+    /// Whether a request in this version is handled by the broker.
+    pub fn supports(&self, api_key: ApiKey, api_version: i16) -> bool {
+        self.registration(api_key)
+            .is_some_and(|registration| registration.supports(api_version))
+    }
+
+    /// Whether a request is accepted by a broker listener. Every broker is
+    /// also a controller, so that a controller request is accepted when it
+    /// has a handler.
+    pub fn serves(&self, api_key: ApiKey) -> bool {
+        let meta = api_key.request_meta();
+
+        meta.is_listening(Listener::Broker)
+            || (meta.is_listening(Listener::Controller) && self.registration(api_key).is_some())
+    }
+
+    /// The api key, minimum and maximum version of each handled request that
+    /// is served by a broker listener and valid for the codec, ordered by
+    /// api key.
+    pub fn api_versions(&self) -> Vec<(i16, i16, i16)> {
+        RootMessageMeta::messages()
+            .api_versions()
+            .into_iter()
+            .filter_map(|(api_key, min_version, max_version)| {
+                ApiKey::try_from(api_key)
+                    .ok()
+                    .filter(|api_key| self.serves(*api_key))
+                    .and_then(|api_key| self.registration(api_key))
+                    .map(|registration| {
+                        (
+                            api_key,
+                            min_version.max(registration.min_version),
+                            max_version.min(registration.max_version),
+                        )
+                    })
+            })
+            .filter(|(_, min_version, max_version)| min_version <= max_version)
+            .collect()
+    }
+}
+
+// excluding telemetry
+impl<G, S> Default for Registry<G, S>
+where
+    G: Coordinator,
+    S: Storage,
+{
+    fn default() -> Self {
+        Self::empty()
+            .with_handler(ApiKey::Produce, 0, 11, handler::Produce)
+            .with_handler(ApiKey::Fetch, 0, 16, handler::Fetch)
+            .with_handler(ApiKey::ListOffsets, 0, 8, handler::ListOffsets)
+            .with_handler(ApiKey::Metadata, 0, 12, handler::Metadata)
+            .with_handler(ApiKey::OffsetCommit, 0, 9, handler::OffsetCommit)
+            .with_handler(ApiKey::OffsetFetch, 0, 9, handler::OffsetFetch)
+            .with_handler(ApiKey::FindCoordinator, 0, 5, handler::FindCoordinator)
+            .with_handler(ApiKey::JoinGroup, 0, 9, handler::JoinGroup)
+            .with_handler(ApiKey::Heartbeat, 0, 4, handler::Heartbeat)
+            .with_handler(ApiKey::LeaveGroup, 0, 5, handler::LeaveGroup)
+            .with_handler(ApiKey::SyncGroup, 0, 5, handler::SyncGroup)
+            .with_handler(ApiKey::DescribeGroups, 0, 5, handler::DescribeGroups)
+            .with_handler(ApiKey::ListGroups, 0, 4, handler::ListGroups)
+            .with_handler(ApiKey::SaslHandshake, 0, 1, handler::Sasl)
+            .with_handler(ApiKey::ApiVersions, 0, 3, handler::ApiVersions)
+            .with_handler(ApiKey::CreateTopics, 0, 7, handler::CreateTopics)
+            .with_handler(ApiKey::DeleteTopics, 0, 6, handler::DeleteTopics)
+            .with_handler(ApiKey::DeleteRecords, 0, 2, handler::DeleteRecords)
+            .with_handler(ApiKey::InitProducerId, 0, 5, handler::InitProducerId)
+            .with_handler(
+                ApiKey::OffsetForLeaderEpoch,
+                0,
+                4,
+                handler::OffsetForLeaderEpoch,
+            )
+            // batched transactions, from v4, are not implemented
+            .with_handler(
+                ApiKey::AddPartitionsToTxn,
+                0,
+                3,
+                handler::AddPartitionsToTxn,
+            )
+            .with_handler(ApiKey::AddOffsetsToTxn, 0, 4, handler::AddOffsetsToTxn)
+            .with_handler(ApiKey::EndTxn, 0, 4, handler::EndTxn)
+            .with_handler(ApiKey::TxnOffsetCommit, 0, 4, handler::TxnOffsetCommit)
+            .with_handler(ApiKey::DescribeAcls, 0, 3, handler::DescribeAcls)
+            .with_handler(ApiKey::CreateAcls, 0, 3, handler::CreateAcls)
+            .with_handler(ApiKey::DeleteAcls, 0, 3, handler::DeleteAcls)
+            .with_handler(ApiKey::DescribeConfigs, 0, 4, handler::DescribeConfigs)
+            .with_handler(ApiKey::SaslAuthenticate, 0, 2, handler::Sasl)
+            .with_handler(ApiKey::CreatePartitions, 0, 3, handler::CreatePartitions)
+            .with_handler(ApiKey::ElectLeaders, 0, 2, handler::ElectLeaders)
+            .with_handler(
+                ApiKey::AlterPartitionReassignments,
+                0,
+                0,
+                handler::AlterPartitionReassignments,
+            )
+            .with_handler(
+                ApiKey::ListPartitionReassignments,
+                0,
+                0,
+                handler::ListPartitionReassignments,
+            )
+            .with_handler(
+                ApiKey::DescribeClientQuotas,
+                0,
+                1,
+                handler::DescribeClientQuotas,
+            )
+            .with_handler(ApiKey::AlterClientQuotas, 0, 1, handler::AlterClientQuotas)
+            .with_handler(
+                ApiKey::DescribeUserScramCredentials,
+                0,
+                0,
+                handler::DescribeUserScramCredentials,
+            )
+            .with_handler(
+                ApiKey::AlterUserScramCredentials,
+                0,
+                0,
+                handler::AlterUserScramCredentials,
+            )
+            .with_handler(ApiKey::DescribeCluster, 0, 1, handler::DescribeCluster)
+            .with_handler(ApiKey::BrokerHeartbeat, 0, 1, handler::BrokerHeartbeat)
+            .with_handler(ApiKey::UnregisterBroker, 0, 0, handler::UnregisterBroker)
+            .with_handler(ApiKey::DescribeProducers, 0, 0, handler::DescribeProducers)
+            .with_handler(
+                ApiKey::DescribeTransactions,
+                0,
+                0,
+                handler::DescribeTransactions,
+            )
+            .with_handler(ApiKey::ListTransactions, 0, 1, handler::ListTransactions)
+            .with_handler(
+                ApiKey::ConsumerGroupHeartbeat,
+                0,
+                0,
+                handler::ConsumerGroupHeartbeat,
+            )
+            .with_handler(
+                ApiKey::ConsumerGroupDescribe,
+                0,
+                0,
+                handler::ConsumerGroupDescribe,
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{coordinator::group::administrator::Controller, Result};
+    use async_trait::async_trait;
+    use tansu_kafka_sans_io::Body;
+    use tansu_storage::dynostore::DynoStore;
+
+    type Broker = Registry<Controller<DynoStore>, DynoStore>;
+
+    #[test]
+    fn advertised_within_handler_and_codec() {
+        let registry = Broker::default();
+        let requests = RootMessageMeta::messages().requests();
+
+        for (api_key, min_version, max_version) in registry.api_versions() {
+            let registration = ApiKey::try_from(api_key)
+                .ok()
+                .and_then(|api_key| registry.registration(api_key))
+                .expect("advertised api has a registered handler");
+
+            assert!(
+                registration.supports(min_version),
+                "{api_key}: {min_version}"
+            );
+            assert!(
+                registration.supports(max_version),
+                "{api_key}: {max_version}"
+            );
+
+            let valid = &requests[&api_key].version.valid;
+            assert!(valid.start <= min_version && max_version <= valid.end);
+        }
+    }
+
+    #[test]
+    fn controller_requests_with_a_handler() {
+        let registry = Broker::default();
+
+        assert!(registry.serves(ApiKey::Produce));
+        assert!(registry.serves(ApiKey::BrokerHeartbeat));
+        assert!(!registry.serves(ApiKey::BrokerRegistration));
+        assert!(!registry.serves(ApiKey::LeaderAndIsr));
+
+        let advertised = registry
+            .api_versions()
+            .into_iter()
+            .map(|(api_key, _, _)| api_key)
+            .collect::<Vec<_>>();
+
+        assert!(advertised.contains(&ApiKey::BrokerHeartbeat.into()));
+        assert!(!advertised.contains(&ApiKey::BrokerRegistration.into()));
+    }
+
+    #[test]
+    fn unsupported() {
+        let registry = Broker::default();
+
+        assert!(registry.supports(ApiKey::AddPartitionsToTxn, 3));
+        assert!(!registry.supports(ApiKey::AddPartitionsToTxn, 4));
+        assert!(!registry.supports(ApiKey::Produce, -1));
+        assert!(!registry.supports(ApiKey::GetTelemetrySubscriptions, 0));
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    struct Custom;
+
+    #[async_trait]
+    impl<G, S> RequestHandler<G, S> for Custom
+    where
+        G: Coordinator,
+        S: Storage,
+    {
+        async fn handle(
+            &self,
+            _ctx: &handler::ConnectionContext<G, S>,
+            body: Body,
+        ) -> Result<Body> {
+            Ok(body)
+        }
+    }
+
+    #[test]
+    fn custom_handler() {
+        let registry = Broker::default()
+            .with_handler(ApiKey::GetTelemetrySubscriptions, 0, 0, Custom)
+            .with_handler(ApiKey::Produce, 3, 9, Custom);
+
+        assert!(registry.supports(ApiKey::GetTelemetrySubscriptions, 0));
+        assert!(!registry.supports(ApiKey::Produce, 2));
+        assert!(registry.supports(ApiKey::Produce, 9));
+
+        assert!(registry
+            .api_versions()
+            .contains(&(i16::from(ApiKey::Produce), 3, 9)));
+
+        let empty = Broker::empty();
+        assert!(empty.api_versions().is_empty());
+        assert!(!empty.supports(ApiKey::ApiVersions, 0));
+    }
+}
@@ -0,0 +1,732 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+pub mod scram;
+
+use crate::{Error, Result};
+use bytes::Bytes;
+use scram::{constant_time_eq, ClientFirst, Conversation};
+use std::{collections::BTreeMap, fmt, fs, path::Path, str::FromStr, sync::Arc};
+use tansu_kafka_sans_io::{ApiKey, Body, ErrorCode};
+use tansu_storage::{ScramMechanism, Storage};
+use tracing::{debug, warn};
+
+const PLAIN: &str = "PLAIN";
+
+/// Username and password pairs that may authenticate with the broker.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct Credentials(Arc<BTreeMap<String, String>>);
+
+// only the usernames are logged, never their passwords
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+impl Credentials {
+    /// Read credentials from a file of `username:password` lines, ignoring
+    /// blank lines and those starting with `#`.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        fs::read_to_string(path)
+            .map_err(Into::into)
+            .and_then(|contents| contents.parse())
+    }
+
+    pub fn verify(&self, username: &str, password: &str) -> bool {
+        self.0
+            .get(username)
+            .is_some_and(|expected| constant_time_eq(expected.as_bytes(), password.as_bytes()))
+    }
+}
+
+impl FromStr for Credentials {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                line.split_once(':')
+                    .filter(|(username, _)| !username.is_empty())
+                    .map(|(username, password)| (username.to_owned(), password.to_owned()))
+                    .ok_or_else(|| Error::Message(format!("malformed credential: {line}")))
+            })
+            .collect()
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for Credentials
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self(Arc::new(
+            iter.into_iter()
+                .map(|(username, password)| (username.into(), password.into()))
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Mechanism {
+    Plain,
+    Scram(ScramMechanism),
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+enum State {
+    #[default]
+    Start,
+    Handshake {
+        version: i16,
+        mechanism: Mechanism,
+    },
+    Scram {
+        version: i16,
+        conversation: Box<Conversation>,
+    },
+    Authenticated {
+        principal: String,
+    },
+}
+
+/// The SASL state of a connection.
+///
+/// With neither PLAIN credentials nor SCRAM enabled, authentication is
+/// disabled and every request is permitted. Otherwise only ApiVersions and
+/// the SASL APIs are permitted until the client has authenticated.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Authentication {
+    credentials: Option<Credentials>,
+    scram: bool,
+    state: State,
+}
+
+impl Authentication {
+    /// Accept PLAIN with these credentials.
+    pub fn with_credentials(self, credentials: Credentials) -> Self {
+        Self {
+            credentials: Some(credentials),
+            ..self
+        }
+    }
+
+    /// Accept SCRAM-SHA-256 and SCRAM-SHA-512 with the credentials in storage.
+    pub fn with_scram(self) -> Self {
+        Self {
+            scram: true,
+            ..self
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.credentials.is_some() || self.scram
+    }
+
+    fn mechanisms(&self) -> Vec<String> {
+        self.credentials
+            .iter()
+            .map(|_| PLAIN.to_owned())
+            .chain(
+                [ScramMechanism::Sha256, ScramMechanism::Sha512]
+                    .into_iter()
+                    .filter(|_| self.scram)
+                    .map(|mechanism| mechanism.name().to_owned()),
+            )
+            .collect()
+    }
+
+    // the handshake version, while the conversation is in progress
+    fn version(&self) -> Option<i16> {
+        match self.state {
+            State::Handshake { version, .. } | State::Scram { version, .. } => Some(version),
+            _ => None,
+        }
+    }
+
+    pub fn principal(&self) -> Option<&str> {
+        if let State::Authenticated { ref principal } = self.state {
+            Some(principal.as_str())
+        } else {
+            None
+        }
+    }
+
+    pub fn permits(&self, api_key: ApiKey) -> Result<()> {
+        if !self.is_enabled()
+            || self.principal().is_some()
+            || matches!(
+                api_key,
+                ApiKey::ApiVersions | ApiKey::SaslHandshake | ApiKey::SaslAuthenticate
+            )
+        {
+            Ok(())
+        } else {
+            warn!(%api_key, state = ?self.state);
+            Err(Error::Api(ErrorCode::IllegalSaslState))
+        }
+    }
+
+    pub fn handshake(&mut self, api_version: i16, mechanism: &str) -> Body {
+        debug!(api_version, mechanism, state = ?self.state);
+
+        let mechanism = if mechanism == PLAIN {
+            self.credentials.as_ref().map(|_| Mechanism::Plain)
+        } else {
+            ScramMechanism::from_str(mechanism)
+                .ok()
+                .filter(|_| self.scram)
+                .map(Mechanism::Scram)
+        };
+
+        let error_code = match mechanism {
+            None => ErrorCode::UnsupportedSaslMechanism,
+
+            Some(_) if self.state != State::Start => ErrorCode::IllegalSaslState,
+
+            Some(mechanism) => {
+                self.state = State::Handshake {
+                    version: api_version,
+                    mechanism,
+                };
+                ErrorCode::None
+            }
+        };
+
+        Body::SaslHandshakeResponse {
+            error_code: error_code.into(),
+            mechanisms: Some(self.mechanisms()),
+            unknown_tagged_fields: vec![],
+        }
+    }
+
+    pub async fn authenticate<S>(&mut self, storage: &mut S, auth_bytes: &[u8]) -> Result<Body>
+    where
+        S: Storage,
+    {
+        let outcome = if self.version().is_some_and(|version| version > 0) {
+            self.exchange(storage, auth_bytes).await
+        } else {
+            warn!(state = ?self.state);
+            Err(Error::Api(ErrorCode::IllegalSaslState))
+        };
+
+        let (error_code, error_message, auth_bytes) = match outcome {
+            Ok(auth_bytes) => (ErrorCode::None, None, auth_bytes),
+
+            Err(Error::Api(error_code @ ErrorCode::SaslAuthenticationFailed)) => (
+                error_code,
+                Some("Authentication failed: Invalid username or password".into()),
+                Bytes::new(),
+            ),
+
+            Err(Error::Api(error_code)) => (error_code, None, Bytes::new()),
+
+            Err(error) => return Err(error),
+        };
+
+        Ok(Body::SaslAuthenticateResponse {
+            error_code: error_code.into(),
+            error_message,
+            auth_bytes,
+            session_lifetime_ms: Some(0),
+            unknown_tagged_fields: vec![],
+        })
+    }
+
+    /// After a version 0 handshake, the client sends each token without a
+    /// Kafka request header.
+    pub fn is_expecting_raw_token(&self) -> bool {
+        self.version() == Some(0)
+    }
+
+    /// Exchange a token sent without a Kafka request header, returning the
+    /// bytes of the response.
+    pub async fn authenticate_raw<S>(&mut self, storage: &mut S, token: &[u8]) -> Result<Bytes>
+    where
+        S: Storage,
+    {
+        if self.is_expecting_raw_token() {
+            self.exchange(storage, token).await
+        } else {
+            Err(Error::Api(ErrorCode::IllegalSaslState))
+        }
+    }
+
+    // a failed exchange returns to the start, before any handshake
+    async fn exchange<S>(&mut self, storage: &mut S, token: &[u8]) -> Result<Bytes>
+    where
+        S: Storage,
+    {
+        match std::mem::take(&mut self.state) {
+            State::Handshake {
+                mechanism: Mechanism::Plain,
+                ..
+            } => {
+                let Some((username, _)) = plain(token).filter(|(username, password)| {
+                    self.credentials
+                        .as_ref()
+                        .is_some_and(|credentials| credentials.verify(username, password))
+                }) else {
+                    warn!(username = ?plain(token).map(|(username, _)| username));
+                    return Err(Error::Api(ErrorCode::SaslAuthenticationFailed));
+                };
+
+                debug!(username);
+
+                self.state = State::Authenticated {
+                    principal: username.to_owned(),
+                };
+
+                Ok(Bytes::new())
+            }
+
+            State::Handshake {
+                version,
+                mechanism: Mechanism::Scram(mechanism),
+            } => {
+                let client_first = std::str::from_utf8(token)
+                    .map_err(|_| Error::Api(ErrorCode::SaslAuthenticationFailed))
+                    .and_then(ClientFirst::from_str)?;
+
+                let Some(credential) = storage
+                    .user_scram_credential(client_first.username(), mechanism)
+                    .await?
+                else {
+                    warn!(username = client_first.username(), ?mechanism);
+                    return Err(Error::Api(ErrorCode::SaslAuthenticationFailed));
+                };
+
+                let conversation = Conversation::new(mechanism, client_first, credential);
+                let server_first = Bytes::from(conversation.server_first().to_owned());
+
+                self.state = State::Scram {
+                    version,
+                    conversation: Box::new(conversation),
+                };
+
+                Ok(server_first)
+            }
+
+            State::Scram { conversation, .. } => {
+                let server_final = std::str::from_utf8(token)
+                    .map_err(|_| Error::Api(ErrorCode::SaslAuthenticationFailed))
+                    .and_then(|client_final| conversation.finish(client_final))?;
+
+                debug!(username = conversation.username());
+
+                self.state = State::Authenticated {
+                    principal: conversation.username().to_owned(),
+                };
+
+                Ok(Bytes::from(server_final))
+            }
+
+            otherwise => {
+                warn!(state = ?otherwise);
+                self.state = otherwise;
+                Err(Error::Api(ErrorCode::IllegalSaslState))
+            }
+        }
+    }
+}
+
+// a PLAIN token is authzid NUL authcid NUL password (RFC 4616), where
+// the authorization identity is either absent or the same as authcid
+fn plain(token: &[u8]) -> Option<(&str, &str)> {
+    let mut fields = std::str::from_utf8(token).ok()?.split('\0');
+
+    let (authzid, authcid, password) = (fields.next()?, fields.next()?, fields.next()?);
+
+    (fields.next().is_none() && !authcid.is_empty() && (authzid.is_empty() || authzid == authcid))
+        .then_some((authcid, password))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose::STANDARD, Engine as _};
+    use object_store::memory::InMemory;
+    use tansu_storage::dynostore::DynoStore;
+
+    fn credentials() -> Result<Credentials> {
+        "# users\nalice:alice-secret\n\nbob:bob:secret\n".parse()
+    }
+
+    fn storage() -> DynoStore {
+        DynoStore::new("abc", 12321, InMemory::new())
+    }
+
+    fn error_code(body: &Body) -> Option<ErrorCode> {
+        match body {
+            Body::SaslHandshakeResponse { error_code, .. }
+            | Body::SaslAuthenticateResponse { error_code, .. } => {
+                ErrorCode::try_from(*error_code).ok()
+            }
+            _ => None,
+        }
+    }
+
+    fn auth_bytes(body: &Body) -> Option<&[u8]> {
+        if let Body::SaslAuthenticateResponse { auth_bytes, .. } = body {
+            Some(&auth_bytes[..])
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn parse_credentials() -> Result<()> {
+        let credentials = credentials()?;
+
+        assert!(credentials.verify("alice", "alice-secret"));
+        assert!(credentials.verify("bob", "bob:secret"));
+        assert!(!credentials.verify("alice", "bob:secret"));
+        assert!(!credentials.verify("carol", ""));
+
+        assert!("alice".parse::<Credentials>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn secrets_are_not_logged() -> Result<()> {
+        let authentication = Authentication {
+            credentials: Some(credentials()?),
+            scram: true,
+            state: State::Scram {
+                version: 1,
+                conversation: Box::new(scram::tests::rfc7677()?),
+            },
+        };
+
+        let logged = format!("{authentication:?}");
+
+        assert!(logged.contains("alice"));
+        assert!(logged.contains("Sha256"));
+
+        for secret in [
+            "alice-secret",
+            "bob:secret",
+            "hvYDpWUa2RaTCAfuxFIlj",
+            "stored_key",
+            "server_key",
+        ] {
+            assert!(!logged.contains(secret), "{secret} in {logged}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn plain_token() {
+        assert_eq!(Some(("alice", "pw")), plain(b"\0alice\0pw"));
+        assert_eq!(Some(("alice", "pw")), plain(b"alice\0alice\0pw"));
+        assert_eq!(None, plain(b"bob\0alice\0pw"));
+        assert_eq!(None, plain(b"\0\0pw"));
+        assert_eq!(None, plain(b"alice\0pw"));
+        assert_eq!(None, plain(b"\0alice\0pw\0"));
+    }
+
+    #[test]
+    fn disabled() {
+        let mut authentication = Authentication::default();
+
+        assert!(authentication.permits(ApiKey::Metadata).is_ok());
+        assert_eq!(
+            Some(ErrorCode::UnsupportedSaslMechanism),
+            error_code(&authentication.handshake(1, PLAIN))
+        );
+    }
+
+    #[tokio::test]
+    async fn authenticate() -> Result<()> {
+        let mut storage = storage();
+        let mut authentication = Authentication::default().with_credentials(credentials()?);
+
+        assert!(authentication.permits(ApiKey::ApiVersions).is_ok());
+        assert!(matches!(
+            authentication.permits(ApiKey::Metadata),
+            Err(Error::Api(ErrorCode::IllegalSaslState))
+        ));
+
+        assert_eq!(
+            Some(ErrorCode::IllegalSaslState),
+            error_code(
+                &authentication
+                    .authenticate(&mut storage, b"\0alice\0alice-secret")
+                    .await?
+            )
+        );
+
+        assert_eq!(
+            Some(ErrorCode::UnsupportedSaslMechanism),
+            error_code(&authentication.handshake(1, "SCRAM-SHA-256"))
+        );
+
+        assert_eq!(
+            Some(ErrorCode::None),
+            error_code(&authentication.handshake(1, PLAIN))
+        );
+
+        assert_eq!(
+            Some(ErrorCode::None),
+            error_code(
+                &authentication
+                    .authenticate(&mut storage, b"\0alice\0alice-secret")
+                    .await?
+            )
+        );
+
+        assert_eq!(Some("alice"), authentication.principal());
+        assert!(authentication.permits(ApiKey::Metadata).is_ok());
+
+        assert_eq!(
+            Some(ErrorCode::IllegalSaslState),
+            error_code(&authentication.handshake(1, PLAIN))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn authentication_failed() -> Result<()> {
+        let mut storage = storage();
+        let mut authentication = Authentication::default().with_credentials(credentials()?);
+
+        assert_eq!(
+            Some(ErrorCode::None),
+            error_code(&authentication.handshake(1, PLAIN))
+        );
+
+        assert_eq!(
+            Some(ErrorCode::SaslAuthenticationFailed),
+            error_code(
+                &authentication
+                    .authenticate(&mut storage, b"\0alice\0bob:secret")
+                    .await?
+            )
+        );
+
+        assert_eq!(None, authentication.principal());
+        assert!(authentication.permits(ApiKey::Metadata).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn legacy_raw_token() -> Result<()> {
+        let mut storage = storage();
+        let mut authentication = Authentication::default().with_credentials(credentials()?);
+        assert!(!authentication.is_expecting_raw_token());
+
+        assert_eq!(
+            Some(ErrorCode::None),
+            error_code(&authentication.handshake(0, PLAIN))
+        );
+        assert!(authentication.is_expecting_raw_token());
+
+        let response = authentication
+            .authenticate_raw(&mut storage, b"bob\0bob\0bob:secret")
+            .await?;
+        assert!(response.is_empty());
+
+        assert!(!authentication.is_expecting_raw_token());
+        assert_eq!(Some("bob"), authentication.principal());
+
+        Ok(())
+    }
+
+    // the client side of a SCRAM conversation, returning the client-final-message
+    fn client_final(
+        mechanism: ScramMechanism,
+        password: &str,
+        client_first_bare: &str,
+        server_first: &str,
+    ) -> Result<String> {
+        let attributes = server_first
+            .split(',')
+            .filter_map(|attribute| attribute.split_once('='))
+            .collect::<BTreeMap<_, _>>();
+
+        let salt = STANDARD
+            .decode(attributes["s"])
+            .map_err(|error| Error::Message(error.to_string()))?;
+
+        let salted_password =
+            scram::tests::salted_password(mechanism, password, &salt, attributes["i"].parse()?)?;
+
+        let credential = scram::credential(mechanism, Bytes::from(salt), 0, &salted_password)?;
+
+        let without_proof = format!("c=biws,r={}", attributes["r"]);
+        let auth_message = format!("{client_first_bare},{server_first},{without_proof}");
+
+        let client_key = scram::hmac(mechanism, &salted_password, b"Client Key")?;
+        let client_signature =
+            scram::hmac(mechanism, &credential.stored_key, auth_message.as_bytes())?;
+
+        let proof = client_key
+            .iter()
+            .zip(client_signature)
+            .map(|(key, signature)| key ^ signature)
+            .collect::<Vec<_>>();
+
+        Ok(format!("{without_proof},p={}", STANDARD.encode(proof)))
+    }
+
+    async fn scram_credential(
+        storage: &mut DynoStore,
+        username: &str,
+        mechanism: ScramMechanism,
+        password: &str,
+    ) -> Result<()> {
+        let salt = b"tansu-salt";
+        let iterations = scram::MIN_ITERATIONS;
+
+        let credential = scram::credential(
+            mechanism,
+            Bytes::from_static(salt),
+            iterations,
+            &scram::tests::salted_password(mechanism, password, salt, iterations)?,
+        )?;
+
+        storage
+            .upsert_user_scram_credential(username, mechanism, credential)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[tokio::test]
+    async fn scram_sha_512() -> Result<()> {
+        let mechanism = ScramMechanism::Sha512;
+
+        let mut storage = storage();
+        scram_credential(&mut storage, "alice", mechanism, "alice-secret").await?;
+
+        let mut authentication = Authentication::default().with_scram();
+
+        assert_eq!(
+            Some(ErrorCode::UnsupportedSaslMechanism),
+            error_code(&authentication.handshake(1, PLAIN))
+        );
+
+        let Body::SaslHandshakeResponse {
+            error_code: handshake,
+            mechanisms,
+            ..
+        } = authentication.handshake(1, mechanism.name())
+        else {
+            panic!("expected a handshake response")
+        };
+
+        assert_eq!(i16::from(ErrorCode::None), handshake);
+        assert_eq!(
+            Some(vec!["SCRAM-SHA-256".to_owned(), "SCRAM-SHA-512".to_owned()]),
+            mechanisms
+        );
+
+        let client_first_bare = "n=alice,r=fyko+d2lbbFgONRv9qkxdawL";
+
+        let response = authentication
+            .authenticate(&mut storage, format!("n,,{client_first_bare}").as_bytes())
+            .await?;
+        assert_eq!(Some(ErrorCode::None), error_code(&response));
+
+        let server_first = auth_bytes(&response)
+            .map(|server_first| String::from_utf8(server_first.to_vec()))
+            .transpose()?
+            .unwrap_or_default();
+        assert!(server_first.starts_with("r=fyko+d2lbbFgONRv9qkxdawL"));
+        assert!(authentication.permits(ApiKey::Metadata).is_err());
+
+        let response = authentication
+            .authenticate(
+                &mut storage,
+                client_final(mechanism, "alice-secret", client_first_bare, &server_first)?
+                    .as_bytes(),
+            )
+            .await?;
+        assert_eq!(Some(ErrorCode::None), error_code(&response));
+        assert!(auth_bytes(&response).is_some_and(|server_final| server_final.starts_with(b"v=")));
+
+        assert_eq!(Some("alice"), authentication.principal());
+        assert!(authentication.permits(ApiKey::Metadata).is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scram_wrong_password() -> Result<()> {
+        let mechanism = ScramMechanism::Sha256;
+
+        let mut storage = storage();
+        scram_credential(&mut storage, "alice", mechanism, "alice-secret").await?;
+
+        let mut authentication = Authentication::default().with_scram();
+
+        assert_eq!(
+            Some(ErrorCode::None),
+            error_code(&authentication.handshake(0, mechanism.name()))
+        );
+
+        let client_first_bare = "n=alice,r=rOprNGfwEbeRWgbNEkqO";
+
+        let server_first = authentication
+            .authenticate_raw(&mut storage, format!("n,,{client_first_bare}").as_bytes())
+            .await
+            .map(|server_first| String::from_utf8(server_first.to_vec()))??;
+
+        assert!(matches!(
+            authentication
+                .authenticate_raw(
+                    &mut storage,
+                    client_final(mechanism, "bob-secret", client_first_bare, &server_first)?
+                        .as_bytes(),
+                )
+                .await,
+            Err(Error::Api(ErrorCode::SaslAuthenticationFailed))
+        ));
+
+        assert_eq!(None, authentication.principal());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scram_unknown_user() -> Result<()> {
+        let mut storage = storage();
+        let mut authentication = Authentication::default().with_scram();
+
+        assert_eq!(
+            Some(ErrorCode::None),
+            error_code(&authentication.handshake(1, "SCRAM-SHA-256"))
+        );
+
+        assert_eq!(
+            Some(ErrorCode::SaslAuthenticationFailed),
+            error_code(
+                &authentication
+                    .authenticate(&mut storage, b"n,,n=carol,r=rOprNGfwEbeRWgbNEkqO")
+                    .await?
+            )
+        );
+
+        Ok(())
+    }
+}
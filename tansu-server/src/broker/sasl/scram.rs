@@ -0,0 +1,444 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The server side of a SCRAM conversation (RFC 5802), without channel binding.
+
+use crate::{Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, RngCore};
+use sha2::{Digest, Sha256, Sha512};
+use std::{fmt, str::FromStr};
+use tansu_kafka_sans_io::ErrorCode;
+use tansu_storage::{ScramCredential, ScramMechanism};
+use tracing::warn;
+
+/// The iterations accepted for a credential, as enforced by Kafka.
+pub const MIN_ITERATIONS: i32 = 4096;
+pub const MAX_ITERATIONS: i32 = 16384;
+
+const NONCE_BYTES: usize = 32;
+
+fn failed() -> Error {
+    Error::Api(ErrorCode::SaslAuthenticationFailed)
+}
+
+pub(super) fn hmac(mechanism: ScramMechanism, key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+    match mechanism {
+        ScramMechanism::Sha256 => Hmac::<Sha256>::new_from_slice(key).map(|mut mac| {
+            mac.update(message);
+            mac.finalize().into_bytes().to_vec()
+        }),
+
+        ScramMechanism::Sha512 => Hmac::<Sha512>::new_from_slice(key).map(|mut mac| {
+            mac.update(message);
+            mac.finalize().into_bytes().to_vec()
+        }),
+    }
+    .map_err(|error| Error::Message(error.to_string()))
+}
+
+fn hash(mechanism: ScramMechanism, message: &[u8]) -> Vec<u8> {
+    match mechanism {
+        ScramMechanism::Sha256 => Sha256::digest(message).to_vec(),
+        ScramMechanism::Sha512 => Sha512::digest(message).to_vec(),
+    }
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Derive the stored and server keys from a salted password, which is
+/// then discarded.
+pub fn credential(
+    mechanism: ScramMechanism,
+    salt: Bytes,
+    iterations: i32,
+    salted_password: &[u8],
+) -> Result<ScramCredential> {
+    let client_key = hmac(mechanism, salted_password, b"Client Key")?;
+
+    Ok(ScramCredential {
+        salt,
+        iterations,
+        stored_key: Bytes::from(hash(mechanism, &client_key)),
+        server_key: hmac(mechanism, salted_password, b"Server Key").map(Bytes::from)?,
+    })
+}
+
+// a saslname escapes "," as "=2C" and "=" as "=3D", any other "=" is invalid
+fn saslname(encoded: &str) -> Option<String> {
+    let mut decoded = String::with_capacity(encoded.len());
+    let mut remaining = encoded;
+
+    while let Some(position) = remaining.find('=') {
+        decoded.push_str(&remaining[..position]);
+        remaining = &remaining[position..];
+
+        if let Some(escaped) = remaining.strip_prefix("=2C") {
+            decoded.push(',');
+            remaining = escaped;
+        } else if let Some(escaped) = remaining.strip_prefix("=3D") {
+            decoded.push('=');
+            remaining = escaped;
+        } else {
+            return None;
+        }
+    }
+
+    decoded.push_str(remaining);
+    Some(decoded).filter(|decoded| !decoded.is_empty())
+}
+
+fn is_printable(nonce: &str) -> bool {
+    !nonce.is_empty()
+        && nonce
+            .bytes()
+            .all(|byte| (0x21..=0x7e).contains(&byte) && byte != b',')
+}
+
+/// A client-first-message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClientFirst {
+    gs2_header: String,
+    username: String,
+    nonce: String,
+    bare: String,
+}
+
+impl ClientFirst {
+    pub fn username(&self) -> &str {
+        self.username.as_str()
+    }
+}
+
+impl FromStr for ClientFirst {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut gs2 = s.splitn(3, ',');
+
+        let (Some(cbind_flag), Some(authzid), Some(bare)) = (gs2.next(), gs2.next(), gs2.next())
+        else {
+            return Err(failed());
+        };
+
+        match cbind_flag {
+            "n" | "y" => (),
+
+            flag if flag.starts_with("p=") => {
+                warn!(flag, "channel binding is not supported");
+                return Err(failed());
+            }
+
+            _ => return Err(failed()),
+        }
+
+        // any mandatory extension ("m=") precedes the username, and is unsupported
+        let mut attributes = bare.split(',');
+
+        let username = attributes
+            .next()
+            .and_then(|attribute| attribute.strip_prefix("n="))
+            .and_then(saslname)
+            .ok_or_else(failed)?;
+
+        let nonce = attributes
+            .next()
+            .and_then(|attribute| attribute.strip_prefix("r="))
+            .filter(|nonce| is_printable(nonce))
+            .ok_or_else(failed)?;
+
+        if !authzid.is_empty()
+            && authzid
+                .strip_prefix("a=")
+                .and_then(saslname)
+                .is_none_or(|authzid| authzid != username)
+        {
+            warn!(authzid, username, "authorization identity differs");
+            return Err(failed());
+        }
+
+        Ok(Self {
+            gs2_header: format!("{cbind_flag},{authzid},"),
+            username,
+            nonce: nonce.to_owned(),
+            bare: bare.to_owned(),
+        })
+    }
+}
+
+/// A conversation that has sent the server-first-message, and is
+/// waiting for the client-final-message.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Conversation {
+    mechanism: ScramMechanism,
+    client_first: ClientFirst,
+    server_first: String,
+    nonce: String,
+    credential: ScramCredential,
+}
+
+// only the mechanism and user of a conversation are logged, never its
+// nonce or the keys of the credential
+impl fmt::Debug for Conversation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(stringify!(Conversation))
+            .field("mechanism", &self.mechanism)
+            .field("username", &self.username())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Conversation {
+    pub fn new(
+        mechanism: ScramMechanism,
+        client_first: ClientFirst,
+        credential: ScramCredential,
+    ) -> Self {
+        let mut server_nonce = [0u8; NONCE_BYTES];
+        thread_rng().fill_bytes(&mut server_nonce);
+
+        Self::with_server_nonce(
+            mechanism,
+            client_first,
+            credential,
+            &STANDARD.encode(server_nonce),
+        )
+    }
+
+    fn with_server_nonce(
+        mechanism: ScramMechanism,
+        client_first: ClientFirst,
+        credential: ScramCredential,
+        server_nonce: &str,
+    ) -> Self {
+        let nonce = format!("{}{server_nonce}", client_first.nonce);
+
+        let server_first = format!(
+            "r={nonce},s={},i={}",
+            STANDARD.encode(&credential.salt),
+            credential.iterations
+        );
+
+        Self {
+            mechanism,
+            client_first,
+            server_first,
+            nonce,
+            credential,
+        }
+    }
+
+    pub fn username(&self) -> &str {
+        self.client_first.username()
+    }
+
+    pub fn server_first(&self) -> &str {
+        self.server_first.as_str()
+    }
+
+    /// Verify the proof in the client-final-message, returning the
+    /// server-final-message with the server signature.
+    pub fn finish(&self, client_final: &str) -> Result<String> {
+        let (without_proof, proof) = client_final.rsplit_once(",p=").ok_or_else(failed)?;
+
+        let mut attributes = without_proof.split(',');
+
+        if attributes
+            .next()
+            .and_then(|attribute| attribute.strip_prefix("c="))
+            .and_then(|channel_binding| STANDARD.decode(channel_binding).ok())
+            .is_none_or(|channel_binding| {
+                channel_binding != self.client_first.gs2_header.as_bytes()
+            })
+        {
+            warn!(username = self.username(), "channel binding mismatch");
+            return Err(failed());
+        }
+
+        if attributes
+            .next()
+            .and_then(|attribute| attribute.strip_prefix("r="))
+            .is_none_or(|nonce| nonce != self.nonce)
+        {
+            warn!(username = self.username(), "nonce mismatch");
+            return Err(failed());
+        }
+
+        let proof = STANDARD.decode(proof).map_err(|_| failed())?;
+
+        let auth_message = format!(
+            "{},{},{without_proof}",
+            self.client_first.bare, self.server_first
+        );
+
+        let client_signature = hmac(
+            self.mechanism,
+            &self.credential.stored_key,
+            auth_message.as_bytes(),
+        )?;
+
+        if proof.len() != client_signature.len() {
+            return Err(failed());
+        }
+
+        let client_key = proof
+            .iter()
+            .zip(client_signature)
+            .map(|(proof, signature)| proof ^ signature)
+            .collect::<Vec<_>>();
+
+        if !constant_time_eq(
+            &hash(self.mechanism, &client_key),
+            &self.credential.stored_key,
+        ) {
+            warn!(username = self.username(), "invalid proof");
+            return Err(failed());
+        }
+
+        hmac(
+            self.mechanism,
+            &self.credential.server_key,
+            auth_message.as_bytes(),
+        )
+        .map(|server_signature| format!("v={}", STANDARD.encode(server_signature)))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Hi() of RFC 5802, which is PBKDF2 with HMAC as the PRF
+    pub(crate) fn salted_password(
+        mechanism: ScramMechanism,
+        password: &str,
+        salt: &[u8],
+        iterations: i32,
+    ) -> Result<Vec<u8>> {
+        let mut u = hmac(
+            mechanism,
+            password.as_bytes(),
+            &[salt, &1u32.to_be_bytes()[..]].concat(),
+        )?;
+        let mut hi = u.clone();
+
+        for _ in 1..iterations {
+            u = hmac(mechanism, password.as_bytes(), &u)?;
+
+            hi.iter_mut().zip(&u).for_each(|(hi, u)| *hi ^= u);
+        }
+
+        Ok(hi)
+    }
+
+    // the example conversation of RFC 7677
+    pub(crate) fn rfc7677() -> Result<Conversation> {
+        let salt = STANDARD
+            .decode("W22ZaJ0SNY7soEsUEjb6gQ==")
+            .map_err(|error| Error::Message(error.to_string()))?;
+
+        let credential = credential(
+            ScramMechanism::Sha256,
+            Bytes::from(salt.clone()),
+            4096,
+            &salted_password(ScramMechanism::Sha256, "pencil", &salt, 4096)?,
+        )?;
+
+        Ok(Conversation::with_server_nonce(
+            ScramMechanism::Sha256,
+            "n,,n=user,r=rOprNGfwEbeRWgbNEkqO".parse()?,
+            credential,
+            "%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0",
+        ))
+    }
+
+    #[test]
+    fn sha256_conversation() -> Result<()> {
+        let conversation = rfc7677()?;
+
+        assert_eq!("user", conversation.username());
+        assert_eq!(
+            "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+            conversation.server_first()
+        );
+
+        assert_eq!(
+            "v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=",
+            conversation.finish(concat!(
+                "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0",
+                ",p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+            ))?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_proof() -> Result<()> {
+        assert!(matches!(
+            rfc7677()?.finish(concat!(
+                "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0",
+                ",p=AHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+            )),
+            Err(Error::Api(ErrorCode::SaslAuthenticationFailed))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn nonce_mismatch() -> Result<()> {
+        assert!(rfc7677()?
+            .finish(concat!(
+                "c=biws,r=rOprNGfwEbeRWgbNEkqO,",
+                "p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+            ))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn channel_binding_mismatch() -> Result<()> {
+        // "eSws" is "y,,"
+        assert!(rfc7677()?
+            .finish(concat!(
+                "c=eSws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0",
+                ",p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+            ))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn client_first() -> Result<()> {
+        let client_first = "y,a=a=3Db=2Cc,n=a=3Db=2Cc,r=abc".parse::<ClientFirst>()?;
+        assert_eq!("a=b,c", client_first.username());
+        assert_eq!("y,a=a=3Db=2Cc,", client_first.gs2_header);
+
+        assert!("p=tls-unique,,n=user,r=abc".parse::<ClientFirst>().is_err());
+        assert!("n,a=other,n=user,r=abc".parse::<ClientFirst>().is_err());
+        assert!("n,,m=ext,n=user,r=abc".parse::<ClientFirst>().is_err());
+        assert!("n,,n=us=er,r=abc".parse::<ClientFirst>().is_err());
+        assert!("n,,n=user,r=".parse::<ClientFirst>().is_err());
+
+        Ok(())
+    }
+}
@@ -13,17 +13,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use tansu_kafka_sans_io::{Body, ErrorCode};
+use tansu_kafka_sans_io::{primitive::uuid::Uuid as KafkaUuid, Body, ErrorCode};
 use uuid::Uuid;
 
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct GetTelemetrySubscriptionsRequest;
 
 impl GetTelemetrySubscriptionsRequest {
-    pub fn response(&self, client_instance_id: [u8; 16]) -> Body {
+    pub fn response(&self, client_instance_id: KafkaUuid) -> Body {
         let _ = client_instance_id;
 
-        let client_instance_id = *Uuid::new_v4().as_bytes();
+        let client_instance_id = Uuid::new_v4().into();
 
         Body::GetTelemetrySubscriptionsResponse {
             throttle_time_ms: 0,
@@ -35,6 +35,7 @@ impl GetTelemetrySubscriptionsRequest {
             telemetry_max_bytes: 1_024,
             delta_temporality: false,
             requested_metrics: Some([].into()),
+            unknown_tagged_fields: vec![],
         }
     }
 }
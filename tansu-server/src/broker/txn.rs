@@ -15,4 +15,5 @@
 
 pub mod add_offsets;
 pub mod add_partitions;
+pub mod end;
 pub mod offset_commit;
@@ -13,7 +13,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use tansu_kafka_sans_io::{Body, ErrorCode};
+use tansu_kafka_sans_io::Body;
 use tansu_storage::Storage;
 use tracing::debug;
 
@@ -33,7 +33,7 @@ where
     }
 
     pub async fn response(
-        &self,
+        &mut self,
         transactional_id: &str,
         producer_id: i64,
         producer_epoch: i16,
@@ -41,9 +41,14 @@ where
     ) -> Result<Body> {
         debug!(?transactional_id, ?producer_id, ?producer_epoch, ?group_id);
 
-        Ok(Body::AddOffsetsToTxnResponse {
-            throttle_time_ms: 0,
-            error_code: ErrorCode::None.into(),
-        })
+        self.storage
+            .txn_add_offsets(transactional_id, producer_id, producer_epoch, group_id)
+            .await
+            .map(|error_code| Body::AddOffsetsToTxnResponse {
+                throttle_time_ms: 0,
+                error_code: error_code.into(),
+                unknown_tagged_fields: vec![],
+            })
+            .map_err(Into::into)
     }
 }
@@ -20,7 +20,7 @@ use tansu_kafka_sans_io::{
     },
     Body, ErrorCode,
 };
-use tansu_storage::Storage;
+use tansu_storage::{Storage, Topition};
 use tracing::debug;
 
 use crate::Result;
@@ -39,7 +39,7 @@ where
     }
 
     pub async fn response(
-        &self,
+        &mut self,
         transactions: Option<Vec<AddPartitionsToTxnTransaction>>,
         v_3_and_below_transactional_id: Option<String>,
         v_3_and_below_producer_id: Option<i64>,
@@ -68,20 +68,39 @@ where
                 Some(v_3_and_below_producer_epoch),
                 Some(v_3_and_below_topics),
             ) => {
-                let _ = v_3_and_below_producer_id;
-                let _ = v_3_and_below_producer_epoch;
+                let partitions = v_3_and_below_topics
+                    .iter()
+                    .flat_map(|topic| {
+                        topic
+                            .partitions
+                            .iter()
+                            .flatten()
+                            .map(|partition| Topition::new(topic.name.as_str(), *partition))
+                    })
+                    .collect::<Vec<_>>();
+
+                // the outcome is the same for every partition of the transaction
+                let error_code = self
+                    .storage
+                    .txn_add_partitions(
+                        v_3_and_below_transactional_id.as_str(),
+                        v_3_and_below_producer_id,
+                        v_3_and_below_producer_epoch,
+                        &partitions,
+                    )
+                    .await?;
 
                 let results_by_topic_v_3_and_below = Some(
                     v_3_and_below_topics
                         .iter()
                         .map(|topic| AddPartitionsToTxnTopicResult {
-                            name: v_3_and_below_transactional_id.clone(),
+                            name: topic.name.clone(),
                             results_by_partition: topic.partitions.as_ref().map(|partitions| {
                                 partitions
                                     .iter()
                                     .map(|partition| AddPartitionsToTxnPartitionResult {
                                         partition_index: *partition,
-                                        partition_error_code: ErrorCode::None.into(),
+                                        partition_error_code: error_code.into(),
                                     })
                                     .collect()
                             }),
@@ -94,6 +113,7 @@ where
                     error_code: Some(ErrorCode::None.into()),
                     results_by_transaction: Some([].into()),
                     results_by_topic_v_3_and_below,
+                    unknown_tagged_fields: vec![],
                 })
             }
 
@@ -102,6 +122,7 @@ where
                 error_code: Some(ErrorCode::UnknownServerError.into()),
                 results_by_transaction: Some([].into()),
                 results_by_topic_v_3_and_below: Some([].into()),
+                unknown_tagged_fields: vec![],
             }),
 
             (_, _, _, _, _) => Ok(Body::AddPartitionsToTxnResponse {
@@ -109,6 +130,7 @@ where
                 error_code: Some(ErrorCode::UnknownServerError.into()),
                 results_by_transaction: Some([].into()),
                 results_by_topic_v_3_and_below: Some([].into()),
+                unknown_tagged_fields: vec![],
             }),
         }
     }
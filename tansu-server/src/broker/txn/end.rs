@@ -0,0 +1,369 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use tansu_kafka_sans_io::Body;
+use tansu_storage::Storage;
+use tracing::debug;
+
+use crate::Result;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct End<S> {
+    storage: S,
+}
+
+impl<S> End<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    pub async fn response(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed: bool,
+    ) -> Result<Body> {
+        debug!(?transactional_id, ?producer_id, ?producer_epoch, ?committed);
+
+        self.storage
+            .txn_end(transactional_id, producer_id, producer_epoch, committed)
+            .await
+            .map(|error_code| Body::EndTxnResponse {
+                throttle_time_ms: 0,
+                error_code: error_code.into(),
+                unknown_tagged_fields: vec![],
+            })
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{
+            fetch::FetchRequest, init_producer_id::InitProducerIdRequest,
+            txn::add_partitions::AddPartitions,
+        },
+        fixture::storage_with_topic,
+        Error,
+    };
+    use bytes::Bytes;
+    use tansu_kafka_sans_io::{
+        add_partitions_to_txn_request::AddPartitionsToTxnTopic,
+        fetch_request::{FetchPartition, FetchTopic},
+        fetch_response::{AbortedTransaction, PartitionData},
+        record::{deflated, inflated, Record},
+        Ack, ErrorCode,
+    };
+    use tansu_storage::{dynostore::DynoStore, Topition};
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const TOPIC: &str = "pqr";
+    const TRANSACTIONAL_ID: &str = "txn-abc";
+
+    async fn add_partition(
+        storage: DynoStore,
+        producer_id: i64,
+        producer_epoch: i16,
+    ) -> Result<()> {
+        let body = AddPartitions::with_storage(storage)
+            .response(
+                None,
+                Some(TRANSACTIONAL_ID.into()),
+                Some(producer_id),
+                Some(producer_epoch),
+                Some(vec![AddPartitionsToTxnTopic {
+                    name: TOPIC.into(),
+                    partitions: Some(vec![0]),
+                }]),
+            )
+            .await?;
+
+        let Body::AddPartitionsToTxnResponse {
+            results_by_topic_v_3_and_below: Some(topics),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        assert_eq!(
+            vec![(TOPIC.to_owned(), 0, ErrorCode::None.into())],
+            topics
+                .into_iter()
+                .flat_map(|topic| {
+                    topic
+                        .results_by_partition
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(move |partition| {
+                            (
+                                topic.name.clone(),
+                                partition.partition_index,
+                                partition.partition_error_code,
+                            )
+                        })
+                })
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    async fn produce(
+        storage: &mut DynoStore,
+        producer_id: i64,
+        producer_epoch: i16,
+        base_sequence: i32,
+    ) -> Result<i64> {
+        let batch = inflated::Batch::builder()
+            .record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+            .producer_id(producer_id)
+            .producer_epoch(producer_epoch)
+            .base_sequence(base_sequence)
+            .transactional(true)
+            .build()
+            .and_then(deflated::Batch::try_from)?;
+
+        storage
+            .produce(&Topition::new(TOPIC, 0), batch, Ack::FullIsr)
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn end(
+        storage: DynoStore,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed: bool,
+    ) -> Result<Option<ErrorCode>> {
+        let body = End::with_storage(storage)
+            .response(TRANSACTIONAL_ID, producer_id, producer_epoch, committed)
+            .await?;
+
+        let Body::EndTxnResponse { error_code, .. } = body else {
+            panic!("{body:?}")
+        };
+
+        Ok(ErrorCode::try_from(error_code).ok())
+    }
+
+    async fn fetch(storage: DynoStore, offset: i64, isolation_level: i8) -> Result<PartitionData> {
+        let topics = [FetchTopic {
+            topic: Some(TOPIC.into()),
+            topic_id: None,
+            partitions: Some(vec![FetchPartition {
+                partition: 0,
+                current_leader_epoch: Some(-1),
+                fetch_offset: offset,
+                last_fetched_epoch: Some(-1),
+                log_start_offset: Some(-1),
+                partition_max_bytes: 1_048_576,
+            }]),
+        }];
+
+        let body = FetchRequest::with_storage(storage)
+            .response(0, 0, None, Some(isolation_level), Some(&topics))
+            .await?;
+
+        let Body::FetchResponse {
+            responses: Some(responses),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        Ok(responses
+            .into_iter()
+            .flat_map(|topic| topic.partitions.unwrap_or_default())
+            .next()
+            .expect("partition"))
+    }
+
+    const READ_UNCOMMITTED: i8 = 0;
+    const READ_COMMITTED: i8 = 1;
+
+    #[tokio::test]
+    async fn produce_commit_consume_read_committed() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let mut storage = storage_with_topic(TOPIC, 1).await?;
+
+        let producer = InitProducerIdRequest::with_storage(storage.clone())
+            .response(Some(TRANSACTIONAL_ID), 60_000, Some(-1), Some(-1))
+            .await?;
+        assert_eq!(ErrorCode::None, producer.error);
+
+        add_partition(storage.clone(), producer.id, producer.epoch).await?;
+        assert_eq!(
+            0,
+            produce(&mut storage, producer.id, producer.epoch, 0).await?
+        );
+
+        // the ongoing transaction is only visible to a read uncommitted consumer
+        let uncommitted = fetch(storage.clone(), 0, READ_UNCOMMITTED).await?;
+        assert!(uncommitted.records.is_some());
+
+        let committed = fetch(storage.clone(), 0, READ_COMMITTED).await?;
+        assert_eq!(Some(0), committed.last_stable_offset);
+        assert!(committed.records.is_none());
+
+        assert_eq!(
+            Some(ErrorCode::None),
+            end(storage.clone(), producer.id, producer.epoch, true).await?
+        );
+
+        // the commit marker follows the record of the transaction
+        let committed = fetch(storage.clone(), 0, READ_COMMITTED).await?;
+        assert_eq!(2, committed.high_watermark);
+        assert_eq!(Some(2), committed.last_stable_offset);
+        assert_eq!(Some(vec![]), committed.aborted_transactions);
+        assert!(committed.records.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn aborted_transaction() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let mut storage = storage_with_topic(TOPIC, 1).await?;
+
+        let producer = InitProducerIdRequest::with_storage(storage.clone())
+            .response(Some(TRANSACTIONAL_ID), 60_000, Some(-1), Some(-1))
+            .await?;
+
+        add_partition(storage.clone(), producer.id, producer.epoch).await?;
+        assert_eq!(
+            0,
+            produce(&mut storage, producer.id, producer.epoch, 0).await?
+        );
+        assert_eq!(
+            1,
+            produce(&mut storage, producer.id, producer.epoch, 1).await?
+        );
+
+        assert_eq!(
+            Some(ErrorCode::None),
+            end(storage.clone(), producer.id, producer.epoch, false).await?
+        );
+
+        // a read committed consumer is told which records to skip
+        let committed = fetch(storage.clone(), 0, READ_COMMITTED).await?;
+        assert_eq!(Some(3), committed.last_stable_offset);
+        assert_eq!(
+            Some(vec![AbortedTransaction {
+                producer_id: producer.id,
+                first_offset: 0,
+            }]),
+            committed.aborted_transactions
+        );
+
+        // which are behind a consumer that has passed the abort marker
+        let committed = fetch(storage.clone(), 3, READ_COMMITTED).await?;
+        assert_eq!(Some(vec![]), committed.aborted_transactions);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fenced_producer() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let mut storage = storage_with_topic(TOPIC, 1).await?;
+
+        let zombie = InitProducerIdRequest::with_storage(storage.clone())
+            .response(Some(TRANSACTIONAL_ID), 60_000, Some(-1), Some(-1))
+            .await?;
+
+        add_partition(storage.clone(), zombie.id, zombie.epoch).await?;
+        assert_eq!(0, produce(&mut storage, zombie.id, zombie.epoch, 0).await?);
+
+        // another instance with the same transactional id aborts the
+        // ongoing transaction, fencing the earlier instance
+        let producer = InitProducerIdRequest::with_storage(storage.clone())
+            .response(Some(TRANSACTIONAL_ID), 60_000, Some(-1), Some(-1))
+            .await?;
+        assert_eq!((zombie.id, zombie.epoch + 1), (producer.id, producer.epoch));
+
+        let committed = fetch(storage.clone(), 0, READ_COMMITTED).await?;
+        assert_eq!(Some(2), committed.last_stable_offset);
+
+        assert_eq!(
+            Some(ErrorCode::ProducerFenced),
+            end(storage.clone(), zombie.id, zombie.epoch, true).await?
+        );
+
+        assert!(matches!(
+            produce(&mut storage, zombie.id, zombie.epoch, 1).await,
+            Err(Error::Storage(tansu_storage::Error::Api(
+                ErrorCode::ProducerFenced
+            )))
+        ));
+
+        // while the new instance starts its sequence again
+        add_partition(storage.clone(), producer.id, producer.epoch).await?;
+        assert_eq!(
+            2,
+            produce(&mut storage, producer.id, producer.epoch, 0).await?
+        );
+
+        assert_eq!(
+            Some(ErrorCode::None),
+            end(storage.clone(), producer.id, producer.epoch, true).await?
+        );
+
+        Ok(())
+    }
+}
@@ -16,9 +16,10 @@
 use tansu_kafka_sans_io::{
     txn_offset_commit_request::TxnOffsetCommitRequestTopic,
     txn_offset_commit_response::{TxnOffsetCommitResponsePartition, TxnOffsetCommitResponseTopic},
-    Body, ErrorCode,
+    Body,
 };
-use tansu_storage::Storage;
+use tansu_storage::{OffsetCommitRequest, Storage, Topition};
+use tracing::debug;
 
 use crate::Result;
 
@@ -37,7 +38,7 @@ where
 
     #[allow(clippy::too_many_arguments)]
     pub async fn response(
-        &self,
+        &mut self,
         transactional_id: &str,
         group_id: &str,
         producer_id: i64,
@@ -47,36 +48,371 @@ where
         group_instance_id: Option<String>,
         topics: Option<Vec<TxnOffsetCommitRequestTopic>>,
     ) -> Result<Body> {
-        let _ = transactional_id;
-        let _ = group_id;
-        let _ = producer_id;
-        let _ = producer_epoch;
-        let _ = generation_id;
-        let _ = member_id;
-        let _ = group_instance_id;
-        let _ = topics;
-
-        let topics = topics.as_ref().map(|topics| {
+        debug!(
+            ?transactional_id,
+            ?group_id,
+            ?producer_id,
+            ?producer_epoch,
+            ?generation_id,
+            ?member_id,
+            ?group_instance_id,
+            ?topics
+        );
+
+        let topics = topics.unwrap_or_default();
+
+        let offsets = topics
+            .iter()
+            .flat_map(|topic| {
+                topic
+                    .partitions
+                    .as_deref()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|partition| {
+                        (
+                            Topition::new(topic.name.as_str(), partition.partition_index),
+                            OffsetCommitRequest::from(partition),
+                        )
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        // offsets are staged until the transaction ends, with every
+        // partition sharing the outcome
+        let error_code = self
+            .storage
+            .txn_offset_commit(
+                transactional_id,
+                producer_id,
+                producer_epoch,
+                group_id,
+                &offsets,
+            )
+            .await?;
+
+        let topics = Some(
             topics
-                .iter()
+                .into_iter()
                 .map(|topic| TxnOffsetCommitResponseTopic {
-                    name: topic.name.clone(),
-                    partitions: topic.partitions.as_ref().map(|partitions| {
+                    name: topic.name,
+                    partitions: topic.partitions.map(|partitions| {
                         partitions
-                            .iter()
+                            .into_iter()
                             .map(|partition| TxnOffsetCommitResponsePartition {
                                 partition_index: partition.partition_index,
-                                error_code: ErrorCode::None.into(),
+                                error_code: error_code.into(),
                             })
                             .collect()
                     }),
                 })
-                .collect()
-        });
+                .collect(),
+        );
 
         Ok(Body::TxnOffsetCommitResponse {
             throttle_time_ms: 0,
             topics,
+            unknown_tagged_fields: vec![],
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{
+            group::offset_fetch::OffsetFetchRequest,
+            init_producer_id::InitProducerIdRequest,
+            txn::{add_offsets::AddOffsets, end::End},
+        },
+        coordinator::group::administrator::Controller,
+        Error,
+    };
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        create_topics_request::CreatableTopic,
+        offset_fetch_request::{
+            OffsetFetchRequestGroup, OffsetFetchRequestTopic, OffsetFetchRequestTopics,
+        },
+        txn_offset_commit_request::TxnOffsetCommitRequestPartition,
+        ErrorCode,
+    };
+    use tansu_storage::{dynostore::DynoStore, ProducerIdResponse};
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const TOPIC: &str = "pqr";
+    const GROUP_ID: &str = "xyz";
+    const TRANSACTIONAL_ID: &str = "txn-abc";
+
+    async fn transaction() -> Result<(DynoStore, ProducerIdResponse)> {
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: TOPIC.into(),
+                    num_partitions: 1,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some([].into()),
+                },
+                false,
+            )
+            .await?;
+
+        let producer = InitProducerIdRequest::with_storage(storage.clone())
+            .response(Some(TRANSACTIONAL_ID), 60_000, Some(-1), Some(-1))
+            .await?;
+        assert_eq!(ErrorCode::None, producer.error);
+
+        let body = AddOffsets::with_storage(storage.clone())
+            .response(TRANSACTIONAL_ID, producer.id, producer.epoch, GROUP_ID)
+            .await?;
+        assert!(matches!(
+            body,
+            Body::AddOffsetsToTxnResponse { error_code, .. }
+            if error_code == i16::from(ErrorCode::None)
+        ));
+
+        Ok((storage, producer))
+    }
+
+    async fn commit(
+        storage: DynoStore,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed_offset: i64,
+    ) -> Result<Vec<i16>> {
+        let body = OffsetCommit::with_storage(storage)
+            .response(
+                TRANSACTIONAL_ID,
+                GROUP_ID,
+                producer_id,
+                producer_epoch,
+                Some(-1),
+                Some("".into()),
+                None,
+                Some(vec![TxnOffsetCommitRequestTopic {
+                    name: TOPIC.into(),
+                    partitions: Some(vec![TxnOffsetCommitRequestPartition {
+                        partition_index: 0,
+                        committed_offset,
+                        committed_leader_epoch: Some(-1),
+                        committed_metadata: None,
+                    }]),
+                }]),
+            )
+            .await?;
+
+        let Body::TxnOffsetCommitResponse {
+            topics: Some(topics),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        Ok(topics
+            .into_iter()
+            .flat_map(|topic| topic.partitions.unwrap_or_default())
+            .map(|partition| partition.error_code)
+            .collect())
+    }
+
+    async fn end(
+        storage: DynoStore,
+        producer: &ProducerIdResponse,
+        committed: bool,
+    ) -> Result<Body> {
+        End::with_storage(storage)
+            .response(TRANSACTIONAL_ID, producer.id, producer.epoch, committed)
+            .await
+    }
+
+    async fn fetch_offset(storage: DynoStore, require_stable: bool) -> Result<(i64, i16)> {
+        let body = OffsetFetchRequest::with_coordinator(Controller::with_storage(storage)?)
+            .response(
+                Some(GROUP_ID),
+                Some(&[OffsetFetchRequestTopic {
+                    name: TOPIC.into(),
+                    partition_indexes: Some(vec![0]),
+                }]),
+                None,
+                Some(require_stable),
+            )
+            .await?;
+
+        let Body::OffsetFetchResponse {
+            topics: Some(topics),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        Ok(topics
+            .into_iter()
+            .flat_map(|topic| topic.partitions.unwrap_or_default())
+            .map(|partition| (partition.committed_offset, partition.error_code))
+            .next()
+            .expect("partition"))
+    }
+
+    #[tokio::test]
+    async fn offsets_published_on_commit() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let (storage, producer) = transaction().await?;
+
+        assert_eq!(
+            vec![i16::from(ErrorCode::None)],
+            commit(storage.clone(), producer.id, producer.epoch, 32123).await?
+        );
+
+        // staged offsets are not visible until the transaction commits
+        assert_eq!(
+            (-1, ErrorCode::None.into()),
+            fetch_offset(storage.clone(), false).await?
+        );
+
+        _ = end(storage.clone(), &producer, true).await?;
+
+        assert_eq!(
+            (32123, ErrorCode::None.into()),
+            fetch_offset(storage.clone(), true).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn offsets_discarded_on_abort() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let (storage, producer) = transaction().await?;
+
+        _ = commit(storage.clone(), producer.id, producer.epoch, 32123).await?;
+        _ = end(storage.clone(), &producer, false).await?;
+
+        assert_eq!(
+            (-1, ErrorCode::None.into()),
+            fetch_offset(storage.clone(), true).await?
+        );
+
+        // a later transaction does not commit the discarded offsets
+        _ = AddOffsets::with_storage(storage.clone())
+            .response(TRANSACTIONAL_ID, producer.id, producer.epoch, GROUP_ID)
+            .await?;
+        _ = end(storage.clone(), &producer, true).await?;
+
+        assert_eq!(
+            (-1, ErrorCode::None.into()),
+            fetch_offset(storage.clone(), true).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_during_open_transaction() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let (storage, producer) = transaction().await?;
+
+        _ = commit(storage.clone(), producer.id, producer.epoch, 32123).await?;
+
+        // a consumer requiring stable offsets is told to retry
+        assert_eq!(
+            (-1, ErrorCode::UnstableOffsetCommit.into()),
+            fetch_offset(storage.clone(), true).await?
+        );
+
+        let body = OffsetFetchRequest::with_coordinator(Controller::with_storage(storage.clone())?)
+            .response(
+                None,
+                None,
+                Some(&[OffsetFetchRequestGroup {
+                    group_id: GROUP_ID.into(),
+                    member_id: None,
+                    member_epoch: None,
+                    topics: Some(vec![OffsetFetchRequestTopics {
+                        name: TOPIC.into(),
+                        partition_indexes: Some(vec![0]),
+                    }]),
+                }]),
+                Some(true),
+            )
+            .await?;
+
+        let Body::OffsetFetchResponse {
+            groups: Some(groups),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        assert_eq!(
+            vec![(-1, ErrorCode::UnstableOffsetCommit.into())],
+            groups
+                .into_iter()
+                .flat_map(|group| group.topics.unwrap_or_default())
+                .flat_map(|topic| topic.partitions.unwrap_or_default())
+                .map(|partition| (partition.committed_offset, partition.error_code))
+                .collect::<Vec<(i64, i16)>>()
+        );
+
+        // a fenced producer cannot commit offsets in the transaction
+        let fenced = InitProducerIdRequest::with_storage(storage.clone())
+            .response(Some(TRANSACTIONAL_ID), 60_000, Some(-1), Some(-1))
+            .await?;
+        assert_eq!(producer.epoch + 1, fenced.epoch);
+
+        assert_eq!(
+            vec![i16::from(ErrorCode::ProducerFenced)],
+            commit(storage.clone(), producer.id, producer.epoch, 43234).await?
+        );
+
+        // the new instance aborted the transaction, leaving the offsets stable
+        assert_eq!(
+            (-1, ErrorCode::None.into()),
+            fetch_offset(storage.clone(), true).await?
+        );
+
+        Ok(())
+    }
+}
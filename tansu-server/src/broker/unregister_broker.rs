@@ -0,0 +1,247 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::Result;
+use tansu_kafka_sans_io::{Body, ErrorCode};
+use tansu_storage::Storage;
+use tracing::debug;
+
+/// Removes the registration of a decommissioned broker. A broker must be
+/// fenced, and so leads no partition, and may not be a replica of an
+/// ongoing reassignment.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct UnregisterBrokerRequest<S> {
+    storage: S,
+}
+
+impl<S> UnregisterBrokerRequest<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    async fn unregister(&mut self, broker_id: i32) -> Result<(ErrorCode, Option<String>)> {
+        if self
+            .storage
+            .reassignments()
+            .await?
+            .values()
+            .any(|replicas| replicas.contains(&broker_id))
+        {
+            return Ok((
+                ErrorCode::ReassignmentInProgress,
+                Some(format!("broker {broker_id} is a replica of a reassignment")),
+            ));
+        }
+
+        let error_code = self.storage.unregister_broker(broker_id).await?;
+
+        Ok((
+            error_code,
+            (error_code == ErrorCode::InvalidRequest)
+                .then(|| format!("broker {broker_id} is not fenced")),
+        ))
+    }
+
+    pub async fn response(&mut self, broker_id: i32) -> Result<Body> {
+        debug!(?broker_id);
+
+        let (error_code, error_message) = self.unregister(broker_id).await?;
+
+        Ok(Body::UnregisterBrokerResponse {
+            throttle_time_ms: 0,
+            error_code: error_code.into(),
+            error_message,
+            unknown_tagged_fields: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use object_store::{memory::InMemory, ObjectStore};
+    use std::sync::Arc;
+    use tansu_kafka_sans_io::broker_registration_request::Listener;
+    use tansu_storage::{dynostore::DynoStore, BrokerRegistationRequest, Topition};
+    use tracing::subscriber::DefaultGuard;
+    use uuid::Uuid;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const CLUSTER: &str = "abc";
+
+    async fn register(object_store: Arc<dyn ObjectStore>, broker_id: i32) -> Result<i64> {
+        DynoStore::new(CLUSTER, broker_id, object_store)
+            .register_broker(BrokerRegistationRequest {
+                broker_id,
+                cluster_id: CLUSTER.into(),
+                incarnation_id: Uuid::new_v4(),
+                listeners: vec![Listener {
+                    name: "broker".into(),
+                    host: "localhost".into(),
+                    port: 9092,
+                    security_protocol: 0,
+                }],
+                features: vec![],
+                rack: None,
+            })
+            .await
+            .map_err(Into::into)
+    }
+
+    async fn unregister(storage: DynoStore, broker_id: i32) -> Result<(ErrorCode, bool)> {
+        let body = UnregisterBrokerRequest::with_storage(storage)
+            .response(broker_id)
+            .await?;
+
+        let Body::UnregisterBrokerResponse {
+            error_code,
+            error_message,
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        Ok((ErrorCode::try_from(error_code)?, error_message.is_some()))
+    }
+
+    async fn listed(storage: &mut DynoStore) -> Result<Vec<i32>> {
+        let described = storage
+            .brokers(None)
+            .await?
+            .into_iter()
+            .map(|broker| broker.broker_id)
+            .collect::<Vec<_>>();
+
+        let metadata = storage
+            .metadata(None)
+            .await?
+            .brokers()
+            .iter()
+            .map(|broker| broker.node_id)
+            .collect::<Vec<_>>();
+
+        assert_eq!(described, metadata);
+
+        Ok(described)
+    }
+
+    #[tokio::test]
+    async fn register_fence_unregister_register() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+
+        assert_eq!(0, register(object_store.clone(), 1).await?);
+        assert_eq!(0, register(object_store.clone(), 2).await?);
+
+        let mut storage = DynoStore::new(CLUSTER, 2, object_store.clone());
+        assert_eq!(vec![1, 2], listed(&mut storage).await?);
+
+        // a live broker can't be unregistered
+        assert_eq!(
+            (ErrorCode::InvalidRequest, true),
+            unregister(storage.clone(), 1).await?
+        );
+
+        assert_eq!(
+            (ErrorCode::BrokerIdNotRegistered, false),
+            unregister(storage.clone(), 3).await?
+        );
+
+        assert_eq!(ErrorCode::None, storage.broker_heartbeat(1, 0, true).await?);
+        assert_eq!(vec![2], listed(&mut storage).await?);
+
+        // nor one that is a replica of an ongoing reassignment
+        let topition = Topition::new("pqr", 0);
+        storage.alter_reassignment(&topition, Some(&[1, 2])).await?;
+
+        assert_eq!(
+            (ErrorCode::ReassignmentInProgress, true),
+            unregister(storage.clone(), 1).await?
+        );
+
+        storage.alter_reassignment(&topition, None).await?;
+
+        assert_eq!(
+            (ErrorCode::None, false),
+            unregister(storage.clone(), 1).await?
+        );
+        assert_eq!(vec![2], listed(&mut storage).await?);
+
+        // the removed registration can't heartbeat or be removed again
+        assert_eq!(
+            ErrorCode::BrokerIdNotRegistered,
+            storage.broker_heartbeat(1, 0, false).await?
+        );
+        assert_eq!(vec![2], listed(&mut storage).await?);
+
+        assert_eq!(
+            (ErrorCode::BrokerIdNotRegistered, false),
+            unregister(storage.clone(), 1).await?
+        );
+
+        // a new incarnation continues from the epoch of the removed broker
+        assert_eq!(1, register(object_store.clone(), 1).await?);
+        assert_eq!(vec![1, 2], listed(&mut storage).await?);
+
+        assert_eq!(
+            ErrorCode::StaleBrokerEpoch,
+            storage.broker_heartbeat(1, 0, false).await?
+        );
+        assert_eq!(
+            ErrorCode::None,
+            storage.broker_heartbeat(1, 1, false).await?
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,441 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A minimal client speaking the Kafka protocol to a broker.
+
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use tansu_kafka_sans_io::{
+    list_offsets_request::{ListOffsetsPartition, ListOffsetsTopic},
+    metadata_request::MetadataRequestTopic,
+    metadata_response::MetadataResponseTopic,
+    offset_commit_request::{OffsetCommitRequestPartition, OffsetCommitRequestTopic},
+    offset_fetch_request::OffsetFetchRequestTopic,
+    ApiKey, Body, ErrorCode, Frame, Header,
+};
+use tansu_storage::NULL_TOPIC_ID;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+use tracing::debug;
+
+use crate::{Error, Result};
+
+pub mod consumer;
+pub mod group;
+pub mod producer;
+pub mod topic;
+
+const API_VERSIONS_VERSION: i16 = 3;
+const METADATA_VERSIONS: RangeInclusive<i16> = 1..=12;
+const FIND_COORDINATOR_VERSIONS: RangeInclusive<i16> = 1..=3;
+const LIST_OFFSETS_VERSIONS: RangeInclusive<i16> = 1..=8;
+const OFFSET_FETCH_VERSIONS: RangeInclusive<i16> = 1..=7;
+const OFFSET_COMMIT_VERSIONS: RangeInclusive<i16> = 2..=8;
+
+/// The timestamp used by list offsets for the earliest offset of a partition.
+pub const EARLIEST_TIMESTAMP: i64 = -2;
+
+/// The timestamp used by list offsets for the log end offset of a partition.
+pub const LATEST_TIMESTAMP: i64 = -1;
+
+/// A connection to a broker, sending one request at a time.
+#[derive(Debug)]
+pub struct Connection<S> {
+    stream: S,
+    client_id: Option<String>,
+    correlation_id: i32,
+    api_versions: BTreeMap<i16, RangeInclusive<i16>>,
+}
+
+impl Connection<TcpStream> {
+    /// Connect to a broker at `host:port`.
+    pub async fn connect(broker: &str) -> Result<Self> {
+        TcpStream::connect(broker)
+            .await
+            .map(Self::new)
+            .map_err(Into::into)
+    }
+}
+
+impl<S> Connection<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            client_id: Some(env!("CARGO_PKG_NAME").into()),
+            correlation_id: 0,
+            api_versions: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_client_id(self, client_id: Option<String>) -> Self {
+        Self { client_id, ..self }
+    }
+
+    /// Send a request, returning the body of the response.
+    pub async fn call(&mut self, api_key: ApiKey, api_version: i16, body: Body) -> Result<Body> {
+        self.correlation_id += 1;
+        let correlation_id = self.correlation_id;
+
+        let request = Frame::request(
+            Header::Request {
+                api_key: api_key.into(),
+                api_version,
+                correlation_id,
+                client_id: self.client_id.clone(),
+            },
+            body,
+        )?;
+
+        self.stream.write_all(&request).await?;
+
+        let mut size = [0u8; 4];
+        _ = self.stream.read_exact(&mut size).await?;
+
+        let mut response = vec![0u8; size.len() + usize::try_from(u32::from_be_bytes(size))?];
+        response[..size.len()].copy_from_slice(&size);
+        _ = self.stream.read_exact(&mut response[size.len()..]).await?;
+
+        let frame = Frame::decode_response(&response, api_key, api_version)?;
+        debug!(?api_key, api_version, ?frame);
+
+        match frame.header {
+            Header::Response {
+                correlation_id: received,
+            } if received == correlation_id => Ok(frame.body),
+
+            header => Err(Error::Message(format!(
+                "expecting correlation: {correlation_id}, received: {header:?}"
+            ))),
+        }
+    }
+
+    /// Ask the broker which versions of each API it supports.
+    pub async fn api_versions(&mut self) -> Result<()> {
+        let Body::ApiVersionsResponse {
+            error_code,
+            api_keys,
+            ..
+        } = self
+            .call(
+                ApiKey::ApiVersions,
+                API_VERSIONS_VERSION,
+                Body::ApiVersionsRequest {
+                    client_software_name: Some(env!("CARGO_PKG_NAME").into()),
+                    client_software_version: Some(env!("CARGO_PKG_VERSION").into()),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting an api versions response".into()));
+        };
+
+        match ErrorCode::try_from(error_code)? {
+            ErrorCode::None => {
+                self.api_versions = api_keys
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|api| (api.api_key, api.min_version..=api.max_version))
+                    .collect();
+
+                Ok(())
+            }
+
+            error_code => Err(Error::Api(error_code)),
+        }
+    }
+
+    /// The highest version of an API supported by both this client and the broker.
+    pub fn version(&self, api_key: ApiKey, supported: RangeInclusive<i16>) -> Result<i16> {
+        self.api_versions
+            .get(&i16::from(api_key))
+            .and_then(|broker| {
+                let version = *broker.end().min(supported.end());
+                (broker.contains(&version) && supported.contains(&version)).then_some(version)
+            })
+            .ok_or(Error::Api(ErrorCode::UnsupportedVersion))
+    }
+
+    /// The metadata of some topics, or every topic when none are named.
+    pub async fn metadata(
+        &mut self,
+        topics: Option<&[&str]>,
+    ) -> Result<Vec<MetadataResponseTopic>> {
+        let api_version = self.version(ApiKey::Metadata, METADATA_VERSIONS)?;
+
+        let Body::MetadataResponse { topics, .. } = self
+            .call(
+                ApiKey::Metadata,
+                api_version,
+                Body::MetadataRequest {
+                    topics: topics.map(|topics| {
+                        topics
+                            .iter()
+                            .map(|topic| MetadataRequestTopic {
+                                topic_id: Some(NULL_TOPIC_ID),
+                                name: Some((*topic).into()),
+                            })
+                            .collect()
+                    }),
+                    allow_auto_topic_creation: Some(false),
+                    include_cluster_authorized_operations: Some(false),
+                    include_topic_authorized_operations: Some(false),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting a metadata response".into()));
+        };
+
+        Ok(topics.unwrap_or_default())
+    }
+
+    /// The number of partitions in a topic.
+    pub async fn partitions(&mut self, topic: &str) -> Result<i32> {
+        let metadata = self
+            .metadata(Some(&[topic]))
+            .await?
+            .into_iter()
+            .find(|metadata| metadata.name.as_deref() == Some(topic))
+            .ok_or(Error::Api(ErrorCode::UnknownTopicOrPartition))?;
+
+        match ErrorCode::try_from(metadata.error_code)? {
+            ErrorCode::None => metadata
+                .partitions
+                .map_or(Ok(0), |partitions| i32::try_from(partitions.len()))
+                .map_err(Into::into)
+                .and_then(|partitions| {
+                    if partitions > 0 {
+                        Ok(partitions)
+                    } else {
+                        Err(Error::Api(ErrorCode::UnknownTopicOrPartition))
+                    }
+                }),
+
+            error_code => Err(Error::Api(error_code)),
+        }
+    }
+
+    /// Find the coordinator of a group.
+    pub async fn find_coordinator(&mut self, group_id: &str) -> Result<()> {
+        let api_version = self.version(ApiKey::FindCoordinator, FIND_COORDINATOR_VERSIONS)?;
+
+        let Body::FindCoordinatorResponse {
+            error_code,
+            node_id,
+            host,
+            port,
+            ..
+        } = self
+            .call(
+                ApiKey::FindCoordinator,
+                api_version,
+                Body::FindCoordinatorRequest {
+                    key: Some(group_id.into()),
+                    key_type: Some(0),
+                    coordinator_keys: Some(vec![]),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message(
+                "expecting a find coordinator response".into(),
+            ));
+        };
+
+        // group state is shared through storage by every broker, so the
+        // remaining group requests use this connection
+        debug!(group_id, ?node_id, ?host, ?port);
+
+        match ErrorCode::try_from(error_code.unwrap_or_default())? {
+            ErrorCode::None => Ok(()),
+            error_code => Err(Error::Api(error_code)),
+        }
+    }
+
+    /// The offset of each partition at a timestamp, or -1 when there is none.
+    pub async fn list_offsets(
+        &mut self,
+        topic: &str,
+        partitions: &[i32],
+        timestamp: i64,
+    ) -> Result<BTreeMap<i32, i64>> {
+        let api_version = self.version(ApiKey::ListOffsets, LIST_OFFSETS_VERSIONS)?;
+
+        let Body::ListOffsetsResponse { topics, .. } = self
+            .call(
+                ApiKey::ListOffsets,
+                api_version,
+                Body::ListOffsetsRequest {
+                    replica_id: -1,
+                    isolation_level: Some(0),
+                    topics: Some(vec![ListOffsetsTopic {
+                        name: topic.into(),
+                        partitions: Some(
+                            partitions
+                                .iter()
+                                .map(|partition| ListOffsetsPartition {
+                                    partition_index: *partition,
+                                    current_leader_epoch: Some(-1),
+                                    timestamp,
+                                    max_num_offsets: Some(1),
+                                })
+                                .collect(),
+                        ),
+                    }]),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting a list offsets response".into()));
+        };
+
+        topics
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|response| response.name == topic)
+            .flat_map(|response| response.partitions.unwrap_or_default())
+            .map(
+                |partition| match ErrorCode::try_from(partition.error_code)? {
+                    ErrorCode::None => {
+                        Ok((partition.partition_index, partition.offset.unwrap_or(-1)))
+                    }
+                    error_code => Err(Error::Api(error_code)),
+                },
+            )
+            .collect()
+    }
+
+    /// The offset committed by a group for each partition, or -1 when there is none.
+    pub async fn offset_fetch(
+        &mut self,
+        group_id: &str,
+        topic: &str,
+        partitions: &[i32],
+    ) -> Result<BTreeMap<i32, i64>> {
+        let api_version = self.version(ApiKey::OffsetFetch, OFFSET_FETCH_VERSIONS)?;
+
+        let Body::OffsetFetchResponse {
+            topics, error_code, ..
+        } = self
+            .call(
+                ApiKey::OffsetFetch,
+                api_version,
+                Body::OffsetFetchRequest {
+                    group_id: Some(group_id.into()),
+                    topics: Some(vec![OffsetFetchRequestTopic {
+                        name: topic.into(),
+                        partition_indexes: Some(partitions.to_vec()),
+                    }]),
+                    groups: Some(vec![]),
+                    require_stable: Some(false),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting an offset fetch response".into()));
+        };
+
+        match ErrorCode::try_from(error_code.unwrap_or_default())? {
+            ErrorCode::None => (),
+            error_code => return Err(Error::Api(error_code)),
+        }
+
+        let mut committed = partitions
+            .iter()
+            .map(|partition| (*partition, -1))
+            .collect::<BTreeMap<_, _>>();
+
+        for partition in topics
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|response| response.name == topic)
+            .flat_map(|response| response.partitions.unwrap_or_default())
+        {
+            if let Some(offset) = committed.get_mut(&partition.partition_index) {
+                *offset = partition.committed_offset;
+            }
+        }
+
+        Ok(committed)
+    }
+
+    /// Commit an offset for each partition of a topic on behalf of a group.
+    ///
+    /// A member outside of the group uses a generation of -1 without a member id.
+    pub async fn offset_commit(
+        &mut self,
+        group_id: &str,
+        generation_id: i32,
+        member_id: Option<&str>,
+        topic: &str,
+        offsets: &BTreeMap<i32, i64>,
+    ) -> Result<()> {
+        let api_version = self.version(ApiKey::OffsetCommit, OFFSET_COMMIT_VERSIONS)?;
+
+        let Body::OffsetCommitResponse { topics, .. } = self
+            .call(
+                ApiKey::OffsetCommit,
+                api_version,
+                Body::OffsetCommitRequest {
+                    group_id: group_id.into(),
+                    generation_id_or_member_epoch: Some(generation_id),
+                    member_id: Some(member_id.unwrap_or_default().into()),
+                    group_instance_id: None,
+                    retention_time_ms: Some(-1),
+                    topics: Some(vec![OffsetCommitRequestTopic {
+                        name: topic.into(),
+                        partitions: Some(
+                            offsets
+                                .iter()
+                                .map(|(partition, offset)| OffsetCommitRequestPartition {
+                                    partition_index: *partition,
+                                    committed_offset: *offset,
+                                    committed_leader_epoch: Some(-1),
+                                    commit_timestamp: Some(-1),
+                                    committed_metadata: None,
+                                })
+                                .collect(),
+                        ),
+                    }]),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting an offset commit response".into()));
+        };
+
+        topics
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|topic| topic.partitions.unwrap_or_default())
+            .try_for_each(
+                |partition| match ErrorCode::try_from(partition.error_code)? {
+                    ErrorCode::None => Ok(()),
+                    error_code => Err(Error::Api(error_code)),
+                },
+            )
+    }
+}
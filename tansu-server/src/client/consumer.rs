@@ -0,0 +1,884 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Consume records from a partition, or as a member of a consumer group,
+//! writing each record as a line of output.
+
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    ops::RangeInclusive,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use futures::FutureExt;
+use serde_json::json;
+use tansu_kafka_sans_io::{
+    consumer::{
+        ConsumerProtocolAssignment, ConsumerProtocolSubscription, TopicPartition, PROTOCOL_TYPE,
+    },
+    fetch_request::{FetchPartition, FetchTopic},
+    join_group_request::JoinGroupRequestProtocol,
+    leave_group_request::MemberIdentity,
+    record::{deflated, inflated, Resolved},
+    sync_group_request::SyncGroupRequestAssignment,
+    to_timestamp, ApiKey, Body, ErrorCode,
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    time::Instant,
+};
+use tracing::{debug, info};
+
+use crate::{
+    client::{Connection, EARLIEST_TIMESTAMP, LATEST_TIMESTAMP},
+    Error, Result,
+};
+
+const FETCH_VERSIONS: RangeInclusive<i16> = 4..=12;
+const JOIN_GROUP_VERSIONS: RangeInclusive<i16> = 1..=9;
+const SYNC_GROUP_VERSIONS: RangeInclusive<i16> = 0..=5;
+const HEARTBEAT_VERSIONS: RangeInclusive<i16> = 0..=4;
+const LEAVE_GROUP_VERSIONS: RangeInclusive<i16> = 0..=5;
+
+const ASSIGNOR: &str = "range";
+
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_millis(500);
+pub const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a consumer starts reading a partition without a committed offset.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Start {
+    #[default]
+    Earliest,
+    Latest,
+    Offset(i64),
+    Timestamp(SystemTime),
+}
+
+impl FromStr for Start {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "earliest" => Ok(Self::Earliest),
+            "latest" => Ok(Self::Latest),
+
+            timestamp if timestamp.starts_with('@') => {
+                datetime(&timestamp[1..]).map(Self::Timestamp)
+            }
+
+            offset => offset.parse().map(Self::Offset).map_err(Into::into),
+        }
+    }
+}
+
+/// Parse an RFC 3339 date and time, e.g. `2023-11-14T22:13:20Z`.
+pub fn datetime(s: &str) -> Result<SystemTime> {
+    OffsetDateTime::parse(s, &Rfc3339)
+        .map(SystemTime::from)
+        .map_err(|error| Error::Custom(format!("datetime: {s}: {error}")))
+}
+
+/// How each consumed record is written.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Format {
+    /// `offset<TAB>key<TAB>value`
+    #[default]
+    Text,
+
+    /// A JSON object including the partition, timestamp and headers
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            otherwise => Err(Error::Custom(format!("format: {otherwise}"))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Membership {
+    group_id: String,
+    member_id: String,
+    generation_id: i32,
+    partitions: Vec<i32>,
+}
+
+/// Consumes records from a topic until shutdown.
+///
+/// Without a group a single partition is consumed. With a group the
+/// partitions are those assigned by the coordinator, starting from any
+/// committed offsets, which are committed again on shutdown.
+#[derive(Debug)]
+pub struct Consumer<S> {
+    connection: Connection<S>,
+    topic: String,
+    partition: i32,
+    start: Start,
+    format: Format,
+    group: Option<String>,
+    max_wait: Duration,
+    session_timeout: Duration,
+}
+
+impl<S> Consumer<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(connection: Connection<S>, topic: &str) -> Self {
+        Self {
+            connection,
+            topic: topic.into(),
+            partition: 0,
+            start: Start::default(),
+            format: Format::default(),
+            group: None,
+            max_wait: DEFAULT_MAX_WAIT,
+            session_timeout: DEFAULT_SESSION_TIMEOUT,
+        }
+    }
+
+    pub fn with_partition(self, partition: i32) -> Self {
+        Self { partition, ..self }
+    }
+
+    pub fn with_start(self, start: Start) -> Self {
+        Self { start, ..self }
+    }
+
+    pub fn with_format(self, format: Format) -> Self {
+        Self { format, ..self }
+    }
+
+    pub fn with_group(self, group: Option<String>) -> Self {
+        Self { group, ..self }
+    }
+
+    pub fn with_max_wait(self, max_wait: Duration) -> Self {
+        Self { max_wait, ..self }
+    }
+
+    pub fn with_session_timeout(self, session_timeout: Duration) -> Self {
+        Self {
+            session_timeout,
+            ..self
+        }
+    }
+
+    /// Write each record consumed to output until shutdown completes.
+    ///
+    /// Shutdown is checked between fetches, so that the connection is left
+    /// in a state where the group offsets can be committed before leaving.
+    pub async fn consume<W, F>(&mut self, mut output: W, shutdown: F) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+        F: Future<Output = ()>,
+    {
+        let mut shutdown = Box::pin(shutdown);
+
+        self.connection.api_versions().await?;
+
+        let mut membership = self.join().await?;
+        let mut positions = self.positions(membership.as_ref()).await?;
+
+        let heartbeat_interval = self.session_timeout / 3;
+        let mut heartbeat = Instant::now() + heartbeat_interval;
+
+        while shutdown.as_mut().now_or_never().is_none() {
+            if let Some(ref member) = membership {
+                if Instant::now() >= heartbeat {
+                    heartbeat = Instant::now() + heartbeat_interval;
+
+                    if !self.heartbeat(member).await? {
+                        self.commit(member, &positions).await?;
+
+                        membership = self.join().await?;
+                        positions = self.positions(membership.as_ref()).await?;
+                        continue;
+                    }
+                }
+            }
+
+            self.fetch(&mut positions, &mut output).await?;
+            output.flush().await?;
+        }
+
+        if let Some(ref member) = membership {
+            self.commit(member, &positions).await?;
+            self.leave(member).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn join(&mut self) -> Result<Option<Membership>> {
+        let Some(group_id) = self.group.clone() else {
+            return Ok(None);
+        };
+
+        self.connection.find_coordinator(&group_id).await?;
+
+        let api_version = self
+            .connection
+            .version(ApiKey::JoinGroup, JOIN_GROUP_VERSIONS)?;
+
+        let session_timeout_ms = i32::try_from(self.session_timeout.as_millis())?;
+
+        let metadata = ConsumerProtocolSubscription {
+            topics: vec![self.topic.clone()],
+            ..Default::default()
+        }
+        .encode()?;
+
+        let mut member_id = String::new();
+
+        let (generation_id, protocol_name, leader, members) = loop {
+            let Body::JoinGroupResponse {
+                error_code,
+                generation_id,
+                protocol_name,
+                leader,
+                member_id: assigned,
+                members,
+                ..
+            } = self
+                .connection
+                .call(
+                    ApiKey::JoinGroup,
+                    api_version,
+                    Body::JoinGroupRequest {
+                        group_id: group_id.clone(),
+                        session_timeout_ms,
+                        rebalance_timeout_ms: Some(session_timeout_ms),
+                        member_id: member_id.clone(),
+                        group_instance_id: None,
+                        protocol_type: PROTOCOL_TYPE.into(),
+                        protocols: Some(vec![JoinGroupRequestProtocol {
+                            name: ASSIGNOR.into(),
+                            metadata: metadata.clone(),
+                        }]),
+                        reason: None,
+                        unknown_tagged_fields: vec![],
+                    },
+                )
+                .await?
+            else {
+                return Err(Error::Message("expecting a join group response".into()));
+            };
+
+            member_id = assigned;
+
+            match ErrorCode::try_from(error_code)? {
+                ErrorCode::None => {
+                    break (
+                        generation_id,
+                        protocol_name.unwrap_or(ASSIGNOR.into()),
+                        leader,
+                        members.unwrap_or_default(),
+                    )
+                }
+
+                ErrorCode::MemberIdRequired => continue,
+
+                error_code => return Err(Error::Api(error_code)),
+            }
+        };
+
+        debug!(group_id, member_id, generation_id, leader);
+
+        let assignments = if leader == member_id {
+            let subscribed = members
+                .iter()
+                .filter(|member| {
+                    ConsumerProtocolSubscription::try_from(member.metadata.clone())
+                        .is_ok_and(|subscription| subscription.topics.contains(&self.topic))
+                })
+                .map(|member| member.member_id.clone())
+                .collect::<Vec<_>>();
+
+            self.assign(&subscribed).await?
+        } else {
+            vec![]
+        };
+
+        let api_version = self
+            .connection
+            .version(ApiKey::SyncGroup, SYNC_GROUP_VERSIONS)?;
+
+        let Body::SyncGroupResponse {
+            error_code,
+            assignment,
+            ..
+        } = self
+            .connection
+            .call(
+                ApiKey::SyncGroup,
+                api_version,
+                Body::SyncGroupRequest {
+                    group_id: group_id.clone(),
+                    generation_id,
+                    member_id: member_id.clone(),
+                    group_instance_id: None,
+                    protocol_type: Some(PROTOCOL_TYPE.into()),
+                    protocol_name: Some(protocol_name),
+                    assignments: Some(assignments),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting a sync group response".into()));
+        };
+
+        match ErrorCode::try_from(error_code)? {
+            ErrorCode::None => (),
+            error_code => return Err(Error::Api(error_code)),
+        }
+
+        let partitions = if assignment.is_empty() {
+            vec![]
+        } else {
+            ConsumerProtocolAssignment::try_from(assignment)?
+                .assigned_partitions
+                .into_iter()
+                .filter(|assigned| assigned.topic == self.topic)
+                .flat_map(|assigned| assigned.partitions)
+                .collect()
+        };
+
+        info!(group_id, member_id, generation_id, ?partitions);
+
+        Ok(Some(Membership {
+            group_id,
+            member_id,
+            generation_id,
+            partitions,
+        }))
+    }
+
+    // range assignment of the partitions over the subscribed members
+    async fn assign(&mut self, members: &[String]) -> Result<Vec<SyncGroupRequestAssignment>> {
+        let partitions = self.connection.partitions(&self.topic).await?;
+
+        let mut members = members.to_vec();
+        members.sort();
+
+        let count = i32::try_from(members.len())?;
+
+        members
+            .into_iter()
+            .zip(0..)
+            .map(|(member_id, index)| {
+                let assigned = (0..partitions)
+                    .filter(|partition| partition % count == index)
+                    .collect::<Vec<_>>();
+
+                ConsumerProtocolAssignment {
+                    assigned_partitions: vec![TopicPartition::new(&self.topic, &assigned)],
+                    ..Default::default()
+                }
+                .encode()
+                .map(|assignment| SyncGroupRequestAssignment {
+                    member_id,
+                    assignment,
+                })
+                .map_err(Into::into)
+            })
+            .collect()
+    }
+
+    async fn heartbeat(&mut self, member: &Membership) -> Result<bool> {
+        let api_version = self
+            .connection
+            .version(ApiKey::Heartbeat, HEARTBEAT_VERSIONS)?;
+
+        let Body::HeartbeatResponse { error_code, .. } = self
+            .connection
+            .call(
+                ApiKey::Heartbeat,
+                api_version,
+                Body::HeartbeatRequest {
+                    group_id: member.group_id.clone(),
+                    generation_id: member.generation_id,
+                    member_id: member.member_id.clone(),
+                    group_instance_id: None,
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting a heartbeat response".into()));
+        };
+
+        match ErrorCode::try_from(error_code)? {
+            ErrorCode::None => Ok(true),
+            ErrorCode::RebalanceInProgress => Ok(false),
+            error_code => Err(Error::Api(error_code)),
+        }
+    }
+
+    /// The offset to fetch next for each partition being consumed.
+    async fn positions(&mut self, membership: Option<&Membership>) -> Result<BTreeMap<i32, i64>> {
+        let mut positions = match membership {
+            Some(member) => self.committed(member).await?,
+            None => BTreeMap::from([(self.partition, -1)]),
+        };
+
+        let unknown = positions
+            .iter()
+            .filter(|(_, offset)| **offset < 0)
+            .map(|(partition, _)| *partition)
+            .collect::<Vec<_>>();
+
+        if !unknown.is_empty() {
+            let resolved = match self.start {
+                Start::Offset(offset) => unknown
+                    .into_iter()
+                    .map(|partition| (partition, offset))
+                    .collect(),
+
+                Start::Earliest => self.list_offsets(&unknown, EARLIEST_TIMESTAMP).await?,
+                Start::Latest => self.list_offsets(&unknown, LATEST_TIMESTAMP).await?,
+
+                Start::Timestamp(system_time) => {
+                    let by_timestamp = self
+                        .list_offsets(&unknown, to_timestamp(system_time)?)
+                        .await?;
+
+                    // no record at or after the timestamp, start at the end
+                    let after = by_timestamp
+                        .iter()
+                        .filter(|(_, offset)| **offset < 0)
+                        .map(|(partition, _)| *partition)
+                        .collect::<Vec<_>>();
+
+                    let mut resolved = by_timestamp;
+
+                    if !after.is_empty() {
+                        resolved.extend(self.list_offsets(&after, LATEST_TIMESTAMP).await?);
+                    }
+
+                    resolved
+                }
+            };
+
+            positions.extend(resolved);
+        }
+
+        debug!(?positions);
+        Ok(positions)
+    }
+
+    async fn committed(&mut self, member: &Membership) -> Result<BTreeMap<i32, i64>> {
+        self.connection
+            .offset_fetch(&member.group_id, &self.topic, &member.partitions)
+            .await
+    }
+
+    async fn list_offsets(
+        &mut self,
+        partitions: &[i32],
+        timestamp: i64,
+    ) -> Result<BTreeMap<i32, i64>> {
+        self.connection
+            .list_offsets(&self.topic, partitions, timestamp)
+            .await
+    }
+
+    async fn fetch<W>(&mut self, positions: &mut BTreeMap<i32, i64>, output: &mut W) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        if positions.is_empty() {
+            tokio::time::sleep(self.max_wait).await;
+            return Ok(());
+        }
+
+        let api_version = self.connection.version(ApiKey::Fetch, FETCH_VERSIONS)?;
+
+        let Body::FetchResponse {
+            error_code,
+            responses,
+            ..
+        } = self
+            .connection
+            .call(
+                ApiKey::Fetch,
+                api_version,
+                Body::FetchRequest {
+                    cluster_id: None,
+                    replica_id: Some(-1),
+                    replica_state: None,
+                    max_wait_ms: i32::try_from(self.max_wait.as_millis())?,
+                    min_bytes: 1,
+                    max_bytes: Some(52_428_800),
+                    isolation_level: Some(0),
+                    session_id: Some(0),
+                    session_epoch: Some(-1),
+                    topics: Some(vec![FetchTopic {
+                        topic: Some(self.topic.clone()),
+                        topic_id: None,
+                        partitions: Some(
+                            positions
+                                .iter()
+                                .map(|(partition, offset)| FetchPartition {
+                                    partition: *partition,
+                                    current_leader_epoch: Some(-1),
+                                    fetch_offset: *offset,
+                                    last_fetched_epoch: Some(-1),
+                                    log_start_offset: Some(-1),
+                                    partition_max_bytes: 1_048_576,
+                                })
+                                .collect(),
+                        ),
+                    }]),
+                    forgotten_topics_data: Some(vec![]),
+                    rack_id: Some("".into()),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting a fetch response".into()));
+        };
+
+        match ErrorCode::try_from(error_code.unwrap_or_default())? {
+            ErrorCode::None => (),
+            error_code => return Err(Error::Api(error_code)),
+        }
+
+        for partition in responses
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|topic| topic.topic.as_deref() == Some(self.topic.as_str()))
+            .flat_map(|topic| topic.partitions.unwrap_or_default())
+        {
+            match ErrorCode::try_from(partition.error_code)? {
+                ErrorCode::None => (),
+                error_code => return Err(Error::Api(error_code)),
+            }
+
+            let Some(position) = positions.get_mut(&partition.partition_index) else {
+                continue;
+            };
+
+            for batch in partition
+                .records
+                .map(deflated::Frame::try_from)
+                .transpose()?
+                .map(|frame| frame.batches)
+                .unwrap_or_default()
+            {
+                let batch = inflated::Batch::try_from(batch)?;
+
+                if !batch.is_control() {
+                    for record in batch
+                        .resolved()
+                        .filter(|record| record.offset() >= *position)
+                    {
+                        output
+                            .write_all(self.format(partition.partition_index, &record)?.as_bytes())
+                            .await?;
+                    }
+                }
+
+                *position = (*position).max(batch.max_offset() + 1);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn format(&self, partition: i32, record: &Resolved<'_>) -> Result<String> {
+        let key = record.key().map(String::from_utf8_lossy);
+        let value = record.value().map(String::from_utf8_lossy);
+
+        match self.format {
+            Format::Text => Ok(format!(
+                "{}\t{}\t{}\n",
+                record.offset(),
+                key.unwrap_or_default(),
+                value.unwrap_or_default()
+            )),
+
+            Format::Json => serde_json::to_string(&json!({
+                "partition": partition,
+                "offset": record.offset(),
+                "timestamp": record.timestamp(),
+                "key": key,
+                "value": value,
+                "headers": record
+                    .headers()
+                    .iter()
+                    .map(|header| json!({
+                        "key": header.key().map(String::from_utf8_lossy),
+                        "value": header.value().map(String::from_utf8_lossy),
+                    }))
+                    .collect::<Vec<_>>(),
+            }))
+            .map(|mut line| {
+                line.push('\n');
+                line
+            })
+            .map_err(Into::into),
+        }
+    }
+
+    async fn commit(&mut self, member: &Membership, positions: &BTreeMap<i32, i64>) -> Result<()> {
+        if positions.is_empty() {
+            return Ok(());
+        }
+
+        self.connection
+            .offset_commit(
+                &member.group_id,
+                member.generation_id,
+                Some(&member.member_id),
+                &self.topic,
+                positions,
+            )
+            .await
+            .inspect(|()| info!(group_id = member.group_id, ?positions))
+    }
+
+    async fn leave(&mut self, member: &Membership) -> Result<()> {
+        let api_version = self
+            .connection
+            .version(ApiKey::LeaveGroup, LEAVE_GROUP_VERSIONS)?;
+
+        let Body::LeaveGroupResponse { error_code, .. } = self
+            .connection
+            .call(
+                ApiKey::LeaveGroup,
+                api_version,
+                Body::LeaveGroupRequest {
+                    group_id: member.group_id.clone(),
+                    member_id: Some(member.member_id.clone()),
+                    members: Some(vec![MemberIdentity {
+                        member_id: member.member_id.clone(),
+                        group_instance_id: None,
+                        reason: None,
+                    }]),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting a leave group response".into()));
+        };
+
+        match ErrorCode::try_from(error_code)? {
+            ErrorCode::None => Ok(()),
+            error_code => Err(Error::Api(error_code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{listener::ListenerConfig, Broker},
+        client::producer::Producer,
+        coordinator::group::administrator::Controller,
+    };
+    use object_store::memory::InMemory;
+    use std::net::SocketAddr;
+    use tansu_kafka_sans_io::create_topics_request::CreatableTopic;
+    use tansu_storage::{dynostore::DynoStore, Storage, Topition};
+    use tokio::{
+        io::{duplex, DuplexStream},
+        time::sleep,
+    };
+    use url::Url;
+
+    #[test]
+    fn start() -> Result<()> {
+        assert_eq!(Start::Earliest, "earliest".parse()?);
+        assert_eq!(Start::Latest, "latest".parse()?);
+        assert_eq!(Start::Offset(32), "32".parse()?);
+        assert_eq!(
+            Start::Timestamp(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            "@2023-11-14T22:13:20Z".parse()?
+        );
+        assert!("@yesterday".parse::<Start>().is_err());
+
+        Ok(())
+    }
+
+    struct Cluster {
+        storage: DynoStore,
+        broker: Broker<Controller<DynoStore>, DynoStore>,
+    }
+
+    impl Cluster {
+        async fn new(topic: &str, num_partitions: i32) -> Result<Self> {
+            let cluster = "abc";
+            let node = 12321;
+
+            let mut storage = DynoStore::new(cluster, node, InMemory::new());
+
+            _ = storage
+                .create_topic(
+                    CreatableTopic {
+                        name: topic.into(),
+                        num_partitions,
+                        replication_factor: 1,
+                        assignments: Some([].into()),
+                        configs: Some([].into()),
+                    },
+                    false,
+                )
+                .await?;
+
+            let listener = Url::parse("tcp://localhost:9092")?;
+
+            let mut broker = Broker::new(
+                node,
+                cluster,
+                vec![ListenerConfig::new("broker", listener.clone(), &listener)],
+                None,
+                storage.clone(),
+                Controller::with_storage(storage.clone())?
+                    .with_initial_rebalance_delay(Duration::ZERO),
+            );
+
+            _ = broker.register().await?;
+
+            Ok(Self { storage, broker })
+        }
+
+        fn connect(&self) -> Connection<DuplexStream> {
+            let (client, server) = duplex(64 * 1024);
+            let peer = SocketAddr::from(([127, 0, 0, 1], 54321));
+
+            let mut broker = self.broker.clone();
+            _ = tokio::spawn(async move { broker.stream_handler(server, peer).await });
+
+            Connection::new(client)
+        }
+
+        async fn produce(&self, topic: &str, partition: i32, input: &str) -> Result<()> {
+            Producer::new(self.connect(), topic)
+                .with_partition(Some(partition))
+                .with_key_separator(Some(":".into()))
+                .produce(input.as_bytes(), vec![])
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn from_offset() -> Result<()> {
+        let topic = "pqr";
+        let cluster = Cluster::new(topic, 1).await?;
+        cluster.produce(topic, 0, "a:1\nb:2\nc:3\n").await?;
+
+        let mut output = vec![];
+
+        Consumer::new(cluster.connect(), topic)
+            .with_start(Start::Offset(1))
+            .with_max_wait(Duration::from_millis(10))
+            .consume(&mut output, sleep(Duration::from_millis(250)))
+            .await?;
+
+        assert_eq!("1\tb\t2\n2\tc\t3\n", String::from_utf8(output)?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn json() -> Result<()> {
+        let topic = "pqr";
+        let cluster = Cluster::new(topic, 2).await?;
+        cluster.produce(topic, 1, "no key\n").await?;
+
+        let mut output = vec![];
+
+        Consumer::new(cluster.connect(), topic)
+            .with_partition(1)
+            .with_format(Format::Json)
+            .with_max_wait(Duration::from_millis(10))
+            .consume(&mut output, sleep(Duration::from_millis(250)))
+            .await?;
+
+        let record: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(1, record["partition"]);
+        assert_eq!(0, record["offset"]);
+        assert_eq!(serde_json::Value::Null, record["key"]);
+        assert_eq!("no key", record["value"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn group_commits_on_shutdown() -> Result<()> {
+        let topic = "pqr";
+        let group = "grp";
+        let cluster = Cluster::new(topic, 2).await?;
+        cluster.produce(topic, 0, "a:1\nb:2\n").await?;
+        cluster.produce(topic, 1, "c:3\n").await?;
+
+        let mut output = vec![];
+
+        Consumer::new(cluster.connect(), topic)
+            .with_group(Some(group.into()))
+            .with_max_wait(Duration::from_millis(10))
+            .consume(&mut output, sleep(Duration::from_millis(500)))
+            .await?;
+
+        let mut lines = String::from_utf8(output)?
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        lines.sort();
+        assert_eq!(vec!["0\ta\t1", "0\tc\t3", "1\tb\t2"], lines);
+
+        let mut storage = cluster.storage.clone();
+        let committed = storage
+            .offset_fetch(
+                Some(group),
+                &[Topition::new(topic, 0), Topition::new(topic, 1)],
+                None,
+            )
+            .await?;
+
+        assert_eq!(Some(&2), committed.get(&Topition::new(topic, 0)));
+        assert_eq!(Some(&1), committed.get(&Topition::new(topic, 1)));
+
+        // a member joining later starts from the committed offsets
+        cluster.produce(topic, 1, "d:4\n").await?;
+
+        let mut output = vec![];
+
+        Consumer::new(cluster.connect(), topic)
+            .with_group(Some(group.into()))
+            .with_max_wait(Duration::from_millis(10))
+            .consume(&mut output, sleep(Duration::from_millis(500)))
+            .await?;
+
+        assert_eq!("1\td\t4\n", String::from_utf8(output)?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,569 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Describe consumer groups and reset their committed offsets.
+
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use serde_json::json;
+use tansu_kafka_sans_io::{
+    consumer::{ConsumerProtocolAssignment, PROTOCOL_TYPE},
+    describe_groups_response::{DescribedGroup, DescribedGroupMember},
+    to_timestamp, ApiKey, Body, ErrorCode,
+};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tracing::error;
+
+use crate::{
+    client::{consumer::Start, Connection, EARLIEST_TIMESTAMP, LATEST_TIMESTAMP},
+    Error, Result,
+};
+
+const DESCRIBE_GROUPS_VERSIONS: RangeInclusive<i16> = 0..=5;
+
+const DESCRIBE_HEADER: &str =
+    "GROUP\tTOPIC\tPARTITION\tCURRENT-OFFSET\tLOG-END-OFFSET\tLAG\tCONSUMER-ID\tHOST\tCLIENT-ID";
+const RESET_HEADER: &str = "GROUP\tTOPIC\tPARTITION\tNEW-OFFSET";
+
+/// The position of a group in a partition, with the member it is assigned to.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Position {
+    topic: String,
+    partition: i32,
+    committed: Option<i64>,
+    log_end: Option<i64>,
+    member: Option<usize>,
+}
+
+impl Position {
+    fn lag(&self) -> Option<i64> {
+        self.committed
+            .zip(self.log_end)
+            .map(|(committed, log_end)| (log_end - committed).max(0))
+    }
+}
+
+/// Describes consumer groups and resets their offsets, writing a table like
+/// the Kafka tools, or a JSON object for each row in JSON mode.
+#[derive(Debug)]
+pub struct Groups<S> {
+    connection: Connection<S>,
+    json: bool,
+}
+
+impl<S> Groups<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(connection: Connection<S>) -> Self {
+        Self {
+            connection,
+            json: false,
+        }
+    }
+
+    pub fn with_json(self, json: bool) -> Self {
+        Self { json, ..self }
+    }
+
+    /// Describe the members of a group, with the committed offset, log end
+    /// offset and lag of each partition that is assigned or committed.
+    pub async fn describe<W>(&mut self, group_id: &str, mut output: W) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.connection.api_versions().await?;
+        self.connection.find_coordinator(group_id).await?;
+
+        let group = self.group(group_id).await?;
+        let members = group.members.clone().unwrap_or_default();
+
+        let mut assigned = BTreeMap::new();
+
+        if group.protocol_type == PROTOCOL_TYPE {
+            for (index, member) in members.iter().enumerate() {
+                if member.member_assignment.is_empty() {
+                    continue;
+                }
+
+                let assignment =
+                    ConsumerProtocolAssignment::try_from(member.member_assignment.clone())?;
+
+                for topic in assignment.assigned_partitions {
+                    for partition in topic.partitions {
+                        _ = assigned.insert((topic.topic.clone(), partition), index);
+                    }
+                }
+            }
+        }
+
+        let mut positions = vec![];
+
+        for topic in self.connection.metadata(None).await? {
+            let Some(name) = topic
+                .name
+                .filter(|_| topic.error_code == i16::from(ErrorCode::None))
+            else {
+                continue;
+            };
+
+            let partitions = topic
+                .partitions
+                .unwrap_or_default()
+                .into_iter()
+                .map(|partition| partition.partition_index)
+                .collect::<Vec<_>>();
+
+            let committed = self
+                .connection
+                .offset_fetch(group_id, &name, &partitions)
+                .await?;
+
+            let mut described = partitions
+                .iter()
+                .map(|partition| Position {
+                    topic: name.clone(),
+                    partition: *partition,
+                    committed: committed
+                        .get(partition)
+                        .copied()
+                        .filter(|offset| *offset >= 0),
+                    log_end: None,
+                    member: assigned.get(&(name.clone(), *partition)).copied(),
+                })
+                .filter(|position| position.committed.is_some() || position.member.is_some())
+                .collect::<Vec<_>>();
+
+            if described.is_empty() {
+                continue;
+            }
+
+            let log_end = self
+                .connection
+                .list_offsets(
+                    &name,
+                    &described
+                        .iter()
+                        .map(|position| position.partition)
+                        .collect::<Vec<_>>(),
+                    LATEST_TIMESTAMP,
+                )
+                .await?;
+
+            for position in &mut described {
+                position.log_end = log_end
+                    .get(&position.partition)
+                    .copied()
+                    .filter(|offset| *offset >= 0);
+            }
+
+            positions.append(&mut described);
+        }
+
+        let lines = if self.json {
+            vec![self.describe_json(&group, &members, &positions)]
+        } else {
+            describe_text(group_id, &members, &positions)
+        };
+
+        for line in lines {
+            output.write_all(format!("{line}\n").as_bytes()).await?;
+        }
+
+        output.flush().await.map_err(Into::into)
+    }
+
+    /// Reset the offsets committed by an inactive group for every partition
+    /// of a topic, writing the new offsets.
+    ///
+    /// Without execute the new offsets are only written, not committed.
+    pub async fn reset_offsets<W>(
+        &mut self,
+        group_id: &str,
+        topic: &str,
+        to: Start,
+        execute: bool,
+        mut output: W,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.connection.api_versions().await?;
+        self.connection.find_coordinator(group_id).await?;
+
+        let group = self.group(group_id).await?;
+
+        if !matches!(group.group_state.as_str(), "Empty" | "Dead") {
+            error!(group_id, state = group.group_state);
+            return Err(Error::Api(ErrorCode::NonEmptyGroup));
+        }
+
+        let partitions = (0..self.connection.partitions(topic).await?).collect::<Vec<_>>();
+
+        let earliest = self
+            .connection
+            .list_offsets(topic, &partitions, EARLIEST_TIMESTAMP)
+            .await?;
+
+        let latest = self
+            .connection
+            .list_offsets(topic, &partitions, LATEST_TIMESTAMP)
+            .await?;
+
+        let offsets = match to {
+            Start::Earliest => earliest,
+            Start::Latest => latest,
+
+            // an offset outside of the partition is moved to the nearest end
+            Start::Offset(offset) => partitions
+                .iter()
+                .map(|partition| {
+                    let lower = earliest.get(partition).copied().unwrap_or_default();
+                    let upper = latest.get(partition).copied().unwrap_or(lower);
+                    (*partition, offset.clamp(lower, upper.max(lower)))
+                })
+                .collect(),
+
+            // no record at or after the timestamp, reset to the end
+            Start::Timestamp(system_time) => self
+                .connection
+                .list_offsets(topic, &partitions, to_timestamp(system_time)?)
+                .await?
+                .into_iter()
+                .map(|(partition, offset)| {
+                    if offset < 0 {
+                        (partition, latest.get(&partition).copied().unwrap_or(offset))
+                    } else {
+                        (partition, offset)
+                    }
+                })
+                .collect(),
+        };
+
+        if execute {
+            self.connection
+                .offset_commit(group_id, -1, None, topic, &offsets)
+                .await?;
+        }
+
+        let lines = if self.json {
+            offsets
+                .iter()
+                .map(|(partition, offset)| {
+                    json!({
+                        "group": group_id,
+                        "topic": topic,
+                        "partition": partition,
+                        "offset": offset,
+                    })
+                    .to_string()
+                })
+                .collect()
+        } else {
+            [RESET_HEADER.into()]
+                .into_iter()
+                .chain(offsets.iter().map(|(partition, offset)| {
+                    format!("{group_id}\t{topic}\t{partition}\t{offset}")
+                }))
+                .collect::<Vec<_>>()
+        };
+
+        for line in lines {
+            output.write_all(format!("{line}\n").as_bytes()).await?;
+        }
+
+        output.flush().await.map_err(Into::into)
+    }
+
+    async fn group(&mut self, group_id: &str) -> Result<DescribedGroup> {
+        let api_version = self
+            .connection
+            .version(ApiKey::DescribeGroups, DESCRIBE_GROUPS_VERSIONS)?;
+
+        let Body::DescribeGroupsResponse { groups, .. } = self
+            .connection
+            .call(
+                ApiKey::DescribeGroups,
+                api_version,
+                Body::DescribeGroupsRequest {
+                    groups: Some(vec![group_id.into()]),
+                    include_authorized_operations: Some(false),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message(
+                "expecting a describe groups response".into(),
+            ));
+        };
+
+        let group = groups
+            .unwrap_or_default()
+            .into_iter()
+            .find(|group| group.group_id == group_id)
+            .ok_or(Error::Message(format!(
+                "missing described group: {group_id}"
+            )))?;
+
+        match ErrorCode::try_from(group.error_code)? {
+            ErrorCode::None => Ok(group),
+            error_code => Err(Error::Api(error_code)),
+        }
+    }
+
+    fn describe_json(
+        &self,
+        group: &DescribedGroup,
+        members: &[DescribedGroupMember],
+        positions: &[Position],
+    ) -> String {
+        json!({
+            "group": group.group_id,
+            "state": group.group_state,
+            "members": members.iter().map(|member| json!({
+                "member_id": member.member_id,
+                "client_id": member.client_id,
+                "client_host": member.client_host,
+            })).collect::<Vec<_>>(),
+            "partitions": positions.iter().map(|position| json!({
+                "topic": position.topic,
+                "partition": position.partition,
+                "committed": position.committed,
+                "log_end": position.log_end,
+                "lag": position.lag(),
+                "member_id": position.member.map(|index| &members[index].member_id),
+            })).collect::<Vec<_>>(),
+        })
+        .to_string()
+    }
+}
+
+fn describe_text(
+    group_id: &str,
+    members: &[DescribedGroupMember],
+    positions: &[Position],
+) -> Vec<String> {
+    let dash = || String::from("-");
+
+    let member = |index: Option<usize>| {
+        index.map_or(format!("{}\t{}\t{}", dash(), dash(), dash()), |index| {
+            format!(
+                "{}\t{}\t{}",
+                members[index].member_id, members[index].client_host, members[index].client_id
+            )
+        })
+    };
+
+    // members without an assignment are listed without a partition
+    let unassigned = (0..members.len())
+        .filter(|index| {
+            positions
+                .iter()
+                .all(|position| position.member != Some(*index))
+        })
+        .map(|index| format!("{group_id}\t-\t-\t-\t-\t-\t{}", member(Some(index))));
+
+    [DESCRIBE_HEADER.into()]
+        .into_iter()
+        .chain(positions.iter().map(|position| {
+            format!(
+                "{group_id}\t{}\t{}\t{}\t{}\t{}\t{}",
+                position.topic,
+                position.partition,
+                position
+                    .committed
+                    .map_or_else(dash, |offset| offset.to_string()),
+                position
+                    .log_end
+                    .map_or_else(dash, |offset| offset.to_string()),
+                position.lag().map_or_else(dash, |lag| lag.to_string()),
+                member(position.member),
+            )
+        }))
+        .chain(unassigned)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{listener::ListenerConfig, Broker},
+        client::{consumer::Consumer, producer::Producer},
+        coordinator::group::administrator::Controller,
+    };
+    use object_store::memory::InMemory;
+    use std::{net::SocketAddr, time::Duration};
+    use tansu_kafka_sans_io::create_topics_request::CreatableTopic;
+    use tansu_storage::{dynostore::DynoStore, Storage, Topition};
+    use tokio::{
+        io::{duplex, DuplexStream},
+        time::sleep,
+    };
+    use url::Url;
+
+    struct Cluster {
+        storage: DynoStore,
+        broker: Broker<Controller<DynoStore>, DynoStore>,
+    }
+
+    impl Cluster {
+        async fn new(topic: &str, num_partitions: i32) -> Result<Self> {
+            let cluster = "abc";
+            let node = 12321;
+
+            let mut storage = DynoStore::new(cluster, node, InMemory::new());
+
+            _ = storage
+                .create_topic(
+                    CreatableTopic {
+                        name: topic.into(),
+                        num_partitions,
+                        replication_factor: 1,
+                        assignments: Some([].into()),
+                        configs: Some([].into()),
+                    },
+                    false,
+                )
+                .await?;
+
+            let listener = Url::parse("tcp://localhost:9092")?;
+
+            let mut broker = Broker::new(
+                node,
+                cluster,
+                vec![ListenerConfig::new("broker", listener.clone(), &listener)],
+                None,
+                storage.clone(),
+                Controller::with_storage(storage.clone())?
+                    .with_initial_rebalance_delay(Duration::ZERO),
+            );
+
+            _ = broker.register().await?;
+
+            Ok(Self { storage, broker })
+        }
+
+        fn connect(&self) -> Connection<DuplexStream> {
+            let (client, server) = duplex(64 * 1024);
+            let peer = SocketAddr::from(([127, 0, 0, 1], 54321));
+
+            let mut broker = self.broker.clone();
+            _ = tokio::spawn(async move { broker.stream_handler(server, peer).await });
+
+            Connection::new(client)
+        }
+
+        async fn produce(&self, topic: &str, partition: i32, input: &str) -> Result<()> {
+            Producer::new(self.connect(), topic)
+                .with_partition(Some(partition))
+                .produce(input.as_bytes(), vec![])
+                .await
+        }
+
+        async fn consume(&self, topic: &str, group: &str) -> Result<()> {
+            Consumer::new(self.connect(), topic)
+                .with_group(Some(group.into()))
+                .with_max_wait(Duration::from_millis(10))
+                .consume(vec![], sleep(Duration::from_millis(500)))
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn describe_lag() -> Result<()> {
+        let topic = "pqr";
+        let group = "grp";
+        let cluster = Cluster::new(topic, 2).await?;
+        cluster.produce(topic, 0, "a\nb\n").await?;
+        cluster.consume(topic, group).await?;
+        cluster.produce(topic, 0, "c\n").await?;
+        cluster.produce(topic, 1, "d\n").await?;
+
+        let mut output = vec![];
+        Groups::new(cluster.connect())
+            .describe(group, &mut output)
+            .await?;
+
+        assert_eq!(
+            vec![
+                DESCRIBE_HEADER,
+                "grp\tpqr\t0\t2\t3\t1\t-\t-\t-",
+                "grp\tpqr\t1\t0\t1\t1\t-\t-\t-",
+            ],
+            String::from_utf8(output)?.lines().collect::<Vec<_>>()
+        );
+
+        let mut output = vec![];
+        Groups::new(cluster.connect())
+            .with_json(true)
+            .describe(group, &mut output)
+            .await?;
+
+        let described: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!("Empty", described["state"]);
+        assert_eq!(1, described["partitions"][0]["lag"]);
+        assert_eq!(
+            serde_json::Value::Null,
+            described["partitions"][0]["member_id"]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reset_offsets() -> Result<()> {
+        let topic = "pqr";
+        let group = "grp";
+        let cluster = Cluster::new(topic, 2).await?;
+        cluster.produce(topic, 0, "a\nb\nc\n").await?;
+        cluster.consume(topic, group).await?;
+
+        let committed = |mut storage: DynoStore| async move {
+            storage
+                .offset_fetch(
+                    Some(group),
+                    &[Topition::new(topic, 0), Topition::new(topic, 1)],
+                    None,
+                )
+                .await
+        };
+
+        let mut output = vec![];
+        Groups::new(cluster.connect())
+            .reset_offsets(group, topic, Start::Offset(1), false, &mut output)
+            .await?;
+
+        assert_eq!(
+            vec![RESET_HEADER, "grp\tpqr\t0\t1", "grp\tpqr\t1\t0"],
+            String::from_utf8(output)?.lines().collect::<Vec<_>>()
+        );
+
+        // a dry run leaves the committed offsets unchanged
+        let offsets = committed(cluster.storage.clone()).await?;
+        assert_eq!(Some(&3), offsets.get(&Topition::new(topic, 0)));
+
+        Groups::new(cluster.connect())
+            .reset_offsets(group, topic, Start::Earliest, true, vec![])
+            .await?;
+
+        let offsets = committed(cluster.storage.clone()).await?;
+        assert_eq!(Some(&0), offsets.get(&Topition::new(topic, 0)));
+        assert_eq!(Some(&0), offsets.get(&Topition::new(topic, 1)));
+
+        Ok(())
+    }
+}
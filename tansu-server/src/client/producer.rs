@@ -0,0 +1,506 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Produce records read a line at a time, batching lines that arrive within
+//! the linger of each other.
+
+use std::{
+    collections::BTreeMap,
+    ops::RangeInclusive,
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use tansu_kafka_sans_io::{
+    produce_request::{PartitionProduceData, TopicProduceData},
+    record::{deflated, inflated, Header, Record, Records},
+    to_timestamp, ApiKey, Body, Compression, ErrorCode,
+};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt},
+    time::{timeout_at, Instant},
+};
+use tracing::{debug, error};
+
+use crate::{client::Connection, Error, Result};
+
+const PRODUCE_VERSIONS: RangeInclusive<i16> = 3..=11;
+
+const ACKS_ALL: i16 = -1;
+const PRODUCE_TIMEOUT_MS: i32 = 30_000;
+
+pub const DEFAULT_LINGER: Duration = Duration::from_millis(5);
+pub const DEFAULT_MAX_BATCH_RECORDS: usize = 1_000;
+
+/// Produces each line of input as a record to a topic.
+///
+/// Records with a key are partitioned using the same hash as the Java client,
+/// the remainder stick to one partition for each batch.
+#[derive(Debug)]
+pub struct Producer<S> {
+    connection: Connection<S>,
+    topic: String,
+    partition: Option<i32>,
+    key_separator: Option<String>,
+    headers: Vec<(String, String)>,
+    compression: Compression,
+    linger: Duration,
+    max_batch_records: usize,
+}
+
+impl<S> Producer<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(connection: Connection<S>, topic: &str) -> Self {
+        Self {
+            connection,
+            topic: topic.into(),
+            partition: None,
+            key_separator: None,
+            headers: vec![],
+            compression: Compression::None,
+            linger: DEFAULT_LINGER,
+            max_batch_records: DEFAULT_MAX_BATCH_RECORDS,
+        }
+    }
+
+    pub fn with_partition(self, partition: Option<i32>) -> Self {
+        Self { partition, ..self }
+    }
+
+    pub fn with_key_separator(self, key_separator: Option<String>) -> Self {
+        Self {
+            key_separator,
+            ..self
+        }
+    }
+
+    pub fn with_headers(self, headers: Vec<(String, String)>) -> Self {
+        Self { headers, ..self }
+    }
+
+    pub fn with_compression(self, compression: Compression) -> Self {
+        Self {
+            compression,
+            ..self
+        }
+    }
+
+    pub fn with_linger(self, linger: Duration) -> Self {
+        Self { linger, ..self }
+    }
+
+    pub fn with_max_batch_records(self, max_batch_records: usize) -> Self {
+        Self {
+            max_batch_records,
+            ..self
+        }
+    }
+
+    /// Produce every line of input, writing the offset assigned to each
+    /// record to output as `topic-partition@offset`.
+    ///
+    /// Lines are produced until the input is exhausted, failing with the
+    /// first error returned for any partition.
+    pub async fn produce<R, W>(&mut self, input: R, mut output: W) -> Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        self.connection.api_versions().await?;
+
+        let produce_version = self.connection.version(ApiKey::Produce, PRODUCE_VERSIONS)?;
+        let partitions = self.connection.partitions(&self.topic).await?;
+
+        if self
+            .partition
+            .is_some_and(|partition| !(0..partitions).contains(&partition))
+        {
+            return Err(Error::Api(ErrorCode::UnknownTopicOrPartition));
+        }
+
+        let mut lines = input.lines();
+        let mut sticky = 0;
+        let mut failure = None;
+
+        while let Some(line) = lines.next_line().await? {
+            let mut batch = vec![line];
+            let deadline = Instant::now() + self.linger;
+
+            while batch.len() < self.max_batch_records {
+                match timeout_at(deadline, lines.next_line()).await {
+                    Ok(Ok(Some(line))) => batch.push(line),
+                    Ok(Ok(None)) | Err(_) => break,
+                    Ok(Err(error)) => return Err(error.into()),
+                }
+            }
+
+            let mut by_partition: BTreeMap<i32, Vec<(Option<Bytes>, Bytes)>> = BTreeMap::new();
+
+            for line in batch {
+                let (key, value) = self.key_value(line);
+                let partition = self.partition.unwrap_or_else(|| {
+                    key.as_deref()
+                        .map_or(sticky, |key| (murmur2(key) & 0x7fff_ffff) % partitions)
+                });
+
+                by_partition
+                    .entry(partition)
+                    .or_default()
+                    .push((key, value));
+            }
+
+            sticky = (sticky + 1) % partitions;
+
+            for (partition, base_offset, records) in
+                self.send(produce_version, by_partition).await?
+            {
+                match base_offset {
+                    Ok(base_offset) => {
+                        for offset in base_offset..base_offset + i64::try_from(records)? {
+                            output
+                                .write_all(
+                                    format!("{}-{partition}@{offset}\n", self.topic).as_bytes(),
+                                )
+                                .await?;
+                        }
+                    }
+
+                    Err(error_code) => {
+                        error!(topic = self.topic, partition, records, ?error_code);
+                        _ = failure.get_or_insert(error_code);
+                    }
+                }
+            }
+
+            output.flush().await?;
+        }
+
+        failure.map_or(Ok(()), |error_code| Err(Error::Api(error_code)))
+    }
+
+    fn key_value(&self, line: String) -> (Option<Bytes>, Bytes) {
+        match self
+            .key_separator
+            .as_deref()
+            .and_then(|separator| line.split_once(separator))
+        {
+            Some((key, value)) => (
+                Some(Bytes::copy_from_slice(key.as_bytes())),
+                Bytes::copy_from_slice(value.as_bytes()),
+            ),
+
+            None => (None, Bytes::from(line)),
+        }
+    }
+
+    fn batch(&self, records: &[(Option<Bytes>, Bytes)]) -> Result<Records> {
+        let timestamp = to_timestamp(SystemTime::now())?;
+
+        let mut builder = inflated::Batch::builder()
+            .compression(self.compression.clone())
+            .base_timestamp(timestamp)
+            .max_timestamp(timestamp)
+            .last_offset_delta(i32::try_from(records.len())? - 1);
+
+        for (offset_delta, (key, value)) in records.iter().enumerate() {
+            let mut record = Record::builder()
+                .offset_delta(i32::try_from(offset_delta)?)
+                .key(key.clone().into())
+                .value(value.clone().into());
+
+            for (key, value) in &self.headers {
+                record = record.header(
+                    Header::builder()
+                        .key(key.clone().into_bytes())
+                        .value(value.clone().into_bytes()),
+                );
+            }
+
+            builder = builder.record(record);
+        }
+
+        builder
+            .build()
+            .and_then(deflated::Batch::try_from)
+            .and_then(Records::try_from)
+            .map_err(Into::into)
+    }
+
+    async fn send(
+        &mut self,
+        api_version: i16,
+        by_partition: BTreeMap<i32, Vec<(Option<Bytes>, Bytes)>>,
+    ) -> Result<Vec<(i32, Result<i64, ErrorCode>, usize)>> {
+        let mut partition_data = vec![];
+
+        for (partition, records) in &by_partition {
+            partition_data.push(PartitionProduceData {
+                index: *partition,
+                records: Some(self.batch(records)?),
+            });
+        }
+
+        let Body::ProduceResponse { responses, .. } = self
+            .connection
+            .call(
+                ApiKey::Produce,
+                api_version,
+                Body::ProduceRequest {
+                    transactional_id: None,
+                    acks: ACKS_ALL,
+                    timeout_ms: PRODUCE_TIMEOUT_MS,
+                    topic_data: Some(vec![TopicProduceData {
+                        name: self.topic.clone(),
+                        partition_data: Some(partition_data),
+                    }]),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting a produce response".into()));
+        };
+
+        debug!(?responses);
+
+        let base_offsets = responses
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|topic| topic.name == self.topic)
+            .flat_map(|topic| topic.partition_responses.unwrap_or_default())
+            .map(|partition| {
+                (
+                    partition.index,
+                    ErrorCode::try_from(partition.error_code)
+                        .unwrap_or(ErrorCode::UnknownServerError),
+                    partition.base_offset,
+                )
+            })
+            .fold(
+                BTreeMap::new(),
+                |mut acc, (index, error_code, base_offset)| {
+                    _ = acc.insert(
+                        index,
+                        if error_code == ErrorCode::None {
+                            Ok(base_offset)
+                        } else {
+                            Err(error_code)
+                        },
+                    );
+                    acc
+                },
+            );
+
+        Ok(by_partition
+            .into_iter()
+            .map(|(partition, records)| {
+                (
+                    partition,
+                    base_offsets
+                        .get(&partition)
+                        .copied()
+                        .unwrap_or(Err(ErrorCode::UnknownServerError)),
+                    records.len(),
+                )
+            })
+            .collect())
+    }
+}
+
+/// The murmur2 hash used by the Java client to partition keyed records.
+fn murmur2(data: &[u8]) -> i32 {
+    const SEED: u32 = 0x9747_b28c;
+    const M: u32 = 0x5bd1_e995;
+    const R: u32 = 24;
+
+    let mut h = SEED ^ data.len() as u32;
+
+    let chunks = data.chunks_exact(4);
+    let remainder = chunks.remainder();
+
+    for chunk in chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+
+    if !remainder.is_empty() {
+        for (i, byte) in remainder.iter().enumerate().rev() {
+            h ^= u32::from(*byte) << (8 * i);
+        }
+
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+
+    h as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{listener::ListenerConfig, Broker},
+        coordinator::group::administrator::Controller,
+    };
+    use object_store::memory::InMemory;
+    use std::net::SocketAddr;
+    use tansu_kafka_sans_io::create_topics_request::CreatableTopic;
+    use tansu_storage::{dynostore::DynoStore, ListOffsetRequest, Storage, Topition};
+    use tokio::io::duplex;
+    use url::Url;
+
+    #[test]
+    fn java_client_murmur2() {
+        assert_eq!(-973_932_308, murmur2(b"21"));
+        assert_eq!(-790_332_482, murmur2(b"foobar"));
+        assert_eq!(-985_981_536, murmur2(b"a-little-bit-long-string"));
+        assert_eq!(-1_486_304_829, murmur2(b"a-little-bit-longer-string"));
+        assert_eq!(
+            -58_897_971,
+            murmur2(b"lkjh234lh9fiuh90y23oiuhsafujhadof229phr9h19h89h8")
+        );
+        assert_eq!(479_470_107, murmur2(b"abc"));
+    }
+
+    async fn producer(
+        topic: &str,
+        num_partitions: i32,
+    ) -> Result<(DynoStore, Producer<impl AsyncRead + AsyncWrite + Unpin>)> {
+        let cluster = "abc";
+        let node = 12321;
+
+        let mut storage = DynoStore::new(cluster, node, InMemory::new());
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: topic.into(),
+                    num_partitions,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some([].into()),
+                },
+                false,
+            )
+            .await?;
+
+        let listener = Url::parse("tcp://localhost:9092")?;
+
+        let mut broker = Broker::new(
+            node,
+            cluster,
+            vec![ListenerConfig::new("broker", listener.clone(), &listener)],
+            None,
+            storage.clone(),
+            Controller::with_storage(storage.clone())?,
+        );
+
+        _ = broker.register().await?;
+
+        let (client, server) = duplex(64 * 1024);
+        let peer = SocketAddr::from(([127, 0, 0, 1], 54321));
+
+        _ = tokio::spawn(async move { broker.stream_handler(server, peer).await });
+
+        Ok((storage, Producer::new(Connection::new(client), topic)))
+    }
+
+    #[tokio::test]
+    async fn produce_lines() -> Result<()> {
+        let topic = "pqr";
+        let (mut storage, producer) = producer(topic, 3).await?;
+
+        let mut producer = producer
+            .with_partition(Some(1))
+            .with_key_separator(Some(":".into()))
+            .with_headers(vec![("h".into(), "v".into())])
+            .with_compression(Compression::Gzip);
+
+        let mut output = vec![];
+        producer
+            .produce(&b"a:1\nb:2\nno key\n"[..], &mut output)
+            .await?;
+
+        assert_eq!("pqr-1@0\npqr-1@1\npqr-1@2\n", String::from_utf8(output)?);
+
+        let offsets = storage
+            .list_offsets(&[(Topition::new(topic, 1), ListOffsetRequest::Latest)])
+            .await?;
+
+        assert_eq!(Some(3), offsets[0].1.offset());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keyed_records_are_partitioned() -> Result<()> {
+        let (_, producer) = producer("pqr", 3).await?;
+
+        let mut producer = producer.with_key_separator(Some(":".into()));
+
+        let mut output = vec![];
+        producer.produce(&b"foobar:1\n"[..], &mut output).await?;
+
+        // partition of "foobar" in the java client
+        assert_eq!(
+            format!("pqr-{}@0\n", (murmur2(b"foobar") & 0x7fff_ffff) % 3),
+            String::from_utf8(output)?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unknown_partition() -> Result<()> {
+        let (_, producer) = producer("pqr", 3).await?;
+
+        let mut output = vec![];
+
+        assert!(matches!(
+            producer
+                .with_partition(Some(3))
+                .produce(&b"abc\n"[..], &mut output)
+                .await,
+            Err(Error::Api(ErrorCode::UnknownTopicOrPartition))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unknown_topic() -> Result<()> {
+        let (_, producer) = producer("pqr", 3).await?;
+
+        let mut producer = Producer::new(producer.connection, "xyz");
+        let mut output = vec![];
+
+        assert!(matches!(
+            producer.produce(&b"abc\n"[..], &mut output).await,
+            Err(Error::Api(ErrorCode::UnknownTopicOrPartition))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Create, delete, list and describe topics using the admin APIs.
+
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use serde_json::json;
+use tansu_kafka_sans_io::{
+    create_topics_request::{CreatableTopic, CreateableTopicConfig},
+    delete_topics_request::DeleteTopicState,
+    describe_configs_request::DescribeConfigsResource,
+    metadata_response::MetadataResponseTopic,
+    ApiKey, Body, ConfigResource, ErrorCode,
+};
+use tansu_storage::NULL_TOPIC_ID;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{client::Connection, Error, Result};
+
+const CREATE_TOPICS_VERSIONS: RangeInclusive<i16> = 2..=7;
+const DELETE_TOPICS_VERSIONS: RangeInclusive<i16> = 1..=5;
+const DESCRIBE_CONFIGS_VERSIONS: RangeInclusive<i16> = 1..=4;
+
+const TIMEOUT_MS: i32 = 30_000;
+
+/// Manages topics, writing a line of output for each, or a JSON object
+/// for each in JSON mode.
+#[derive(Debug)]
+pub struct Topics<S> {
+    connection: Connection<S>,
+    json: bool,
+}
+
+impl<S> Topics<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(connection: Connection<S>) -> Self {
+        Self {
+            connection,
+            json: false,
+        }
+    }
+
+    pub fn with_json(self, json: bool) -> Self {
+        Self { json, ..self }
+    }
+
+    /// Create a topic, writing its id.
+    pub async fn create<W>(
+        &mut self,
+        name: &str,
+        partitions: i32,
+        replication_factor: i16,
+        configs: Vec<(String, String)>,
+        mut output: W,
+    ) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.connection.api_versions().await?;
+
+        let api_version = self
+            .connection
+            .version(ApiKey::CreateTopics, CREATE_TOPICS_VERSIONS)?;
+
+        let Body::CreateTopicsResponse { topics, .. } = self
+            .connection
+            .call(
+                ApiKey::CreateTopics,
+                api_version,
+                Body::CreateTopicsRequest {
+                    topics: Some(vec![CreatableTopic {
+                        name: name.into(),
+                        num_partitions: partitions,
+                        replication_factor,
+                        assignments: Some(vec![]),
+                        configs: Some(
+                            configs
+                                .into_iter()
+                                .map(|(name, value)| CreateableTopicConfig {
+                                    name,
+                                    value: Some(value),
+                                })
+                                .collect(),
+                        ),
+                    }]),
+                    timeout_ms: TIMEOUT_MS,
+                    validate_only: Some(false),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting a create topics response".into()));
+        };
+
+        let created = topics
+            .unwrap_or_default()
+            .into_iter()
+            .find(|topic| topic.name == name)
+            .ok_or(Error::Message(format!(
+                "missing create topic result: {name}"
+            )))?;
+
+        match ErrorCode::try_from(created.error_code)? {
+            ErrorCode::None => (),
+
+            error_code => {
+                error!(name, ?error_code, error_message = created.error_message);
+                return Err(Error::Api(error_code));
+            }
+        }
+
+        let topic_id = created.topic_id.map(Uuid::from).unwrap_or_default();
+
+        let line = if self.json {
+            json!({
+                "name": name,
+                "topic_id": topic_id,
+                "partitions": created.num_partitions,
+            })
+            .to_string()
+        } else {
+            format!("{name}\t{topic_id}")
+        };
+
+        output.write_all(format!("{line}\n").as_bytes()).await?;
+        output.flush().await.map_err(Into::into)
+    }
+
+    /// Delete topics, writing the outcome for each, failing with the
+    /// first error.
+    pub async fn delete<W>(&mut self, names: &[String], mut output: W) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.connection.api_versions().await?;
+
+        let api_version = self
+            .connection
+            .version(ApiKey::DeleteTopics, DELETE_TOPICS_VERSIONS)?;
+
+        let Body::DeleteTopicsResponse { responses, .. } = self
+            .connection
+            .call(
+                ApiKey::DeleteTopics,
+                api_version,
+                Body::DeleteTopicsRequest {
+                    topics: Some(
+                        names
+                            .iter()
+                            .map(|name| DeleteTopicState {
+                                name: Some(name.clone()),
+                                topic_id: NULL_TOPIC_ID,
+                            })
+                            .collect(),
+                    ),
+                    topic_names: Some(names.to_vec()),
+                    timeout_ms: TIMEOUT_MS,
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message("expecting a delete topics response".into()));
+        };
+
+        let mut failure = None;
+
+        for response in responses.unwrap_or_default() {
+            let name = response.name.unwrap_or_default();
+            let error_code = ErrorCode::try_from(response.error_code)?;
+
+            let line = if self.json {
+                json!({
+                    "name": name,
+                    "error": (error_code != ErrorCode::None).then(|| format!("{error_code:?}")),
+                })
+                .to_string()
+            } else if error_code == ErrorCode::None {
+                format!("{name}\tdeleted")
+            } else {
+                format!("{name}\t{error_code:?}")
+            };
+
+            output.write_all(format!("{line}\n").as_bytes()).await?;
+
+            if error_code != ErrorCode::None {
+                _ = failure.get_or_insert(error_code);
+            }
+        }
+
+        output.flush().await?;
+
+        failure.map_or(Ok(()), |error_code| Err(Error::Api(error_code)))
+    }
+
+    /// List every topic with its partition count.
+    pub async fn list<W>(&mut self, mut output: W) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.connection.api_versions().await?;
+
+        let mut topics = self.connection.metadata(None).await?;
+        topics.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for topic in topics {
+            let name = topic.name.unwrap_or_default();
+            let partitions = topic.partitions.map_or(0, |partitions| partitions.len());
+
+            let line = if self.json {
+                json!({"name": name, "partitions": partitions}).to_string()
+            } else {
+                format!("{name}\t{partitions}")
+            };
+
+            output.write_all(format!("{line}\n").as_bytes()).await?;
+        }
+
+        output.flush().await.map_err(Into::into)
+    }
+
+    /// Describe a topic with its id, partition count and configuration.
+    pub async fn describe<W>(&mut self, name: &str, mut output: W) -> Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.connection.api_versions().await?;
+
+        let MetadataResponseTopic {
+            error_code,
+            topic_id,
+            partitions,
+            ..
+        } = self
+            .connection
+            .metadata(Some(&[name]))
+            .await?
+            .into_iter()
+            .find(|topic| topic.name.as_deref() == Some(name))
+            .ok_or(Error::Api(ErrorCode::UnknownTopicOrPartition))?;
+
+        match ErrorCode::try_from(error_code)? {
+            ErrorCode::None => (),
+            error_code => return Err(Error::Api(error_code)),
+        }
+
+        let topic_id = topic_id.map(Uuid::from).unwrap_or_default();
+        let partitions = partitions.map_or(0, |partitions| partitions.len());
+        let configs = self.configs(name).await?;
+
+        let lines = if self.json {
+            vec![json!({
+                "name": name,
+                "topic_id": topic_id,
+                "partitions": partitions,
+                "configs": configs,
+            })
+            .to_string()]
+        } else {
+            [
+                format!("name\t{name}"),
+                format!("topic_id\t{topic_id}"),
+                format!("partitions\t{partitions}"),
+            ]
+            .into_iter()
+            .chain(configs.iter().map(|(name, value)| {
+                format!("config\t{name}={}", value.as_deref().unwrap_or_default())
+            }))
+            .collect()
+        };
+
+        for line in lines {
+            output.write_all(format!("{line}\n").as_bytes()).await?;
+        }
+
+        output.flush().await.map_err(Into::into)
+    }
+
+    async fn configs(&mut self, name: &str) -> Result<BTreeMap<String, Option<String>>> {
+        let api_version = self
+            .connection
+            .version(ApiKey::DescribeConfigs, DESCRIBE_CONFIGS_VERSIONS)?;
+
+        let Body::DescribeConfigsResponse { results, .. } = self
+            .connection
+            .call(
+                ApiKey::DescribeConfigs,
+                api_version,
+                Body::DescribeConfigsRequest {
+                    resources: Some(vec![DescribeConfigsResource {
+                        resource_type: ConfigResource::Topic.into(),
+                        resource_name: name.into(),
+                        configuration_keys: None,
+                    }]),
+                    include_synonyms: Some(false),
+                    include_documentation: Some(false),
+                    unknown_tagged_fields: vec![],
+                },
+            )
+            .await?
+        else {
+            return Err(Error::Message(
+                "expecting a describe configs response".into(),
+            ));
+        };
+
+        let mut configs = BTreeMap::new();
+
+        for result in results.unwrap_or_default() {
+            match ErrorCode::try_from(result.error_code)? {
+                ErrorCode::None => configs.extend(
+                    result
+                        .configs
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|config| (config.name, config.value)),
+                ),
+
+                error_code => return Err(Error::Api(error_code)),
+            }
+        }
+
+        Ok(configs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        broker::{listener::ListenerConfig, Broker},
+        coordinator::group::administrator::Controller,
+    };
+    use object_store::memory::InMemory;
+    use std::net::SocketAddr;
+    use tansu_storage::dynostore::DynoStore;
+    use tokio::io::{duplex, DuplexStream};
+    use url::Url;
+
+    async fn broker() -> Result<Broker<Controller<DynoStore>, DynoStore>> {
+        let cluster = "abc";
+        let node = 12321;
+
+        let storage = DynoStore::new(cluster, node, InMemory::new());
+        let listener = Url::parse("tcp://localhost:9092")?;
+
+        let mut broker = Broker::new(
+            node,
+            cluster,
+            vec![ListenerConfig::new("broker", listener.clone(), &listener)],
+            None,
+            storage.clone(),
+            Controller::with_storage(storage)?,
+        );
+
+        _ = broker.register().await?;
+
+        Ok(broker)
+    }
+
+    fn topics(broker: &Broker<Controller<DynoStore>, DynoStore>) -> Topics<DuplexStream> {
+        let (client, server) = duplex(64 * 1024);
+        let peer = SocketAddr::from(([127, 0, 0, 1], 54321));
+
+        let mut broker = broker.clone();
+        _ = tokio::spawn(async move { broker.stream_handler(server, peer).await });
+
+        Topics::new(Connection::new(client))
+    }
+
+    #[tokio::test]
+    async fn lifecycle() -> Result<()> {
+        let broker = broker().await?;
+
+        let mut created = vec![];
+        topics(&broker)
+            .create(
+                "pqr",
+                3,
+                1,
+                vec![("cleanup.policy".into(), "compact".into())],
+                &mut created,
+            )
+            .await?;
+
+        let created = String::from_utf8(created)?;
+        assert!(created.starts_with("pqr\t"));
+
+        topics(&broker)
+            .create("abc", -1, -1, vec![], vec![])
+            .await?;
+
+        let mut listed = vec![];
+        topics(&broker).list(&mut listed).await?;
+        assert_eq!("abc\t1\npqr\t3\n", String::from_utf8(listed)?);
+
+        let mut described = vec![];
+        topics(&broker)
+            .with_json(true)
+            .describe("pqr", &mut described)
+            .await?;
+
+        let described: serde_json::Value = serde_json::from_slice(&described)?;
+        assert_eq!(3, described["partitions"]);
+        assert_eq!("compact", described["configs"]["cleanup.policy"]);
+        assert_eq!(
+            created.trim_end().split_once('\t').map(|(_, id)| id),
+            described["topic_id"].as_str()
+        );
+
+        let mut deleted = vec![];
+        topics(&broker)
+            .delete(&["pqr".into()], &mut deleted)
+            .await?;
+        assert_eq!("pqr\tdeleted\n", String::from_utf8(deleted)?);
+
+        let mut listed = vec![];
+        topics(&broker).list(&mut listed).await?;
+        assert_eq!("abc\t1\n", String::from_utf8(listed)?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn errors_are_named() -> Result<()> {
+        let broker = broker().await?;
+
+        topics(&broker).create("pqr", 1, 1, vec![], vec![]).await?;
+
+        assert!(matches!(
+            topics(&broker).create("pqr", 1, 1, vec![], vec![]).await,
+            Err(Error::Api(ErrorCode::TopicAlreadyExists))
+        ));
+
+        let mut output = vec![];
+
+        assert!(matches!(
+            topics(&broker)
+                .with_json(true)
+                .delete(&["xyz".into()], &mut output)
+                .await,
+            Err(Error::Api(ErrorCode::UnknownTopicOrPartition))
+        ));
+
+        let deleted: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!("UnknownTopicOrPartition", deleted["error"]);
+
+        assert!(matches!(
+            topics(&broker).describe("xyz", vec![]).await,
+            Err(Error::Api(ErrorCode::UnknownTopicOrPartition))
+        ));
+
+        Ok(())
+    }
+}
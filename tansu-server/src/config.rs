@@ -0,0 +1,337 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Broker configuration, read from a properties file in the same format as
+//! Kafka's server.properties.
+
+use crate::{
+    broker::listener::{ListenerConfig, SecurityProtocol},
+    Error, Result,
+};
+use broker::{BrokerConfig, ListenerSettings, TlsSettings};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use url::Url;
+
+pub mod broker;
+
+pub const LISTENERS: &str = "listeners";
+pub const ADVERTISED_LISTENERS: &str = "advertised.listeners";
+pub const LISTENER_SECURITY_PROTOCOL_MAP: &str = "listener.security.protocol.map";
+
+/// A PEM file with the certificate chain presented by TLS listeners.
+pub const SSL_CERTIFICATE_LOCATION: &str = "ssl.certificate.location";
+
+/// A PEM file with the private key of the certificate.
+pub const SSL_KEY_LOCATION: &str = "ssl.key.location";
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Config(BTreeMap<String, String>);
+
+impl Config {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        fs::read_to_string(path)
+            .map_err(Into::into)
+            .and_then(|contents| contents.parse())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// The listeners, each with the address that it binds, the address that
+    /// it advertises (defaulting to the bound address) and its security
+    /// protocol. Without a security protocol map, the name of a listener is
+    /// its protocol, e.g. `SASL_SSL://0.0.0.0:9093`.
+    pub fn listeners(&self) -> Result<Vec<ListenerConfig>> {
+        let bound = self.endpoints(LISTENERS)?;
+        let advertised = self.endpoints(ADVERTISED_LISTENERS)?;
+        let protocols = self.security_protocols()?;
+
+        let names = bound
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<BTreeSet<_>>();
+
+        for name in advertised
+            .iter()
+            .map(|(name, _)| name)
+            .chain(protocols.keys())
+        {
+            if !names.contains(name.as_str()) {
+                return Err(Error::Message(format!(
+                    "{name} is not one of the {LISTENERS}"
+                )));
+            }
+        }
+
+        bound
+            .iter()
+            .map(|(name, bind)| {
+                let security_protocol = protocols
+                    .get(name)
+                    .copied()
+                    .map_or_else(|| SecurityProtocol::from_str(name), Ok)
+                    .map_err(|_| {
+                        Error::Message(format!(
+                            "{name} is missing from {LISTENER_SECURITY_PROTOCOL_MAP}"
+                        ))
+                    })?;
+
+                let advertised = advertised
+                    .iter()
+                    .find(|(advertised, _)| advertised == name)
+                    .map_or(bind, |(_, url)| url);
+
+                Ok(ListenerConfig::new(name, bind.clone(), advertised)
+                    .with_security_protocol(security_protocol))
+            })
+            .collect()
+    }
+
+    /// The listeners and TLS of these properties as broker settings, which
+    /// take precedence over those of a broker configuration file.
+    pub fn broker_config(&self) -> Result<BrokerConfig> {
+        let advertised = self.endpoints(ADVERTISED_LISTENERS)?;
+
+        let listeners = self
+            .listeners()?
+            .into_iter()
+            .map(|listener| ListenerSettings {
+                name: listener.name().to_owned(),
+                advertised_url: advertised
+                    .iter()
+                    .find(|(name, _)| name == listener.name())
+                    .map(|(_, url)| url.clone()),
+                security_protocol: Some(listener.security_protocol().to_string()),
+                url: listener.bind,
+            })
+            .collect();
+
+        Ok(BrokerConfig {
+            listeners,
+            tls: TlsSettings {
+                certificate_location: self.get(SSL_CERTIFICATE_LOCATION).map(PathBuf::from),
+                key_location: self.get(SSL_KEY_LOCATION).map(PathBuf::from),
+            },
+            ..Default::default()
+        })
+    }
+
+    // a comma separated list of NAME://host:port, with each name unique
+    fn endpoints(&self, key: &str) -> Result<Vec<(String, Url)>> {
+        let mut names = BTreeSet::new();
+
+        self.get(key)
+            .into_iter()
+            .flat_map(|endpoints| endpoints.split(','))
+            .map(str::trim)
+            .filter(|endpoint| !endpoint.is_empty())
+            .map(|endpoint| {
+                let (name, address) = endpoint
+                    .split_once("://")
+                    .filter(|(name, _)| !name.is_empty())
+                    .ok_or_else(|| {
+                        Error::Message(format!("expecting NAME://host:port in {key}: {endpoint}"))
+                    })?;
+
+                if !names.insert(name.to_owned()) {
+                    return Err(Error::Message(format!("duplicate {name} in {key}")));
+                }
+
+                // an empty host binds every interface
+                let address = if address.starts_with(':') {
+                    format!("0.0.0.0{address}")
+                } else {
+                    address.to_owned()
+                };
+
+                Url::parse(&format!("tcp://{address}"))
+                    .map(|url| (name.to_owned(), url))
+                    .map_err(Into::into)
+            })
+            .collect()
+    }
+
+    // a comma separated list of NAME:PROTOCOL
+    fn security_protocols(&self) -> Result<BTreeMap<String, SecurityProtocol>> {
+        self.get(LISTENER_SECURITY_PROTOCOL_MAP)
+            .into_iter()
+            .flat_map(|mapping| mapping.split(','))
+            .map(str::trim)
+            .filter(|mapping| !mapping.is_empty())
+            .map(|mapping| {
+                mapping
+                    .split_once(':')
+                    .ok_or_else(|| {
+                        Error::Message(format!(
+                            "expecting NAME:PROTOCOL in {LISTENER_SECURITY_PROTOCOL_MAP}: {mapping}"
+                        ))
+                    })
+                    .and_then(|(name, protocol)| {
+                        SecurityProtocol::from_str(protocol)
+                            .map(|protocol| (name.to_owned(), protocol))
+                    })
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    /// Parse `key=value` lines, ignoring blank lines and comments starting
+    /// with `#` or `!`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with(['#', '!']))
+            .map(|line| {
+                line.split_once('=')
+                    .map(|(key, value)| (key.trim().to_owned(), value.trim().to_owned()))
+                    .ok_or_else(|| Error::Message(format!("malformed property: {line}")))
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listeners() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            # sidecars connect in plaintext, everyone else with TLS and SASL
+            listeners=INTERNAL://:9092,EXTERNAL://0.0.0.0:9093
+            advertised.listeners=INTERNAL://localhost:9092,EXTERNAL://kafka.example.com:19093
+            listener.security.protocol.map=INTERNAL:PLAINTEXT,EXTERNAL:SASL_SSL
+            "#,
+        )?;
+
+        let listeners = config.listeners()?;
+        assert_eq!(2, listeners.len());
+
+        assert_eq!("INTERNAL", listeners[0].name());
+        assert_eq!("0.0.0.0:9092", listeners[0].bind_address());
+        assert_eq!("localhost:9092", listeners[0].advertised_address());
+        assert_eq!(
+            SecurityProtocol::Plaintext,
+            listeners[0].security_protocol()
+        );
+
+        assert_eq!("EXTERNAL", listeners[1].name());
+        assert_eq!("0.0.0.0:9093", listeners[1].bind_address());
+        assert_eq!("kafka.example.com:19093", listeners[1].advertised_address());
+        assert_eq!(SecurityProtocol::SaslSsl, listeners[1].security_protocol());
+
+        Ok(())
+    }
+
+    #[test]
+    fn protocol_from_name() -> Result<()> {
+        let listeners =
+            Config::from_str("listeners=SASL_PLAINTEXT://localhost:9092")?.listeners()?;
+
+        assert_eq!(1, listeners.len());
+        assert_eq!(
+            SecurityProtocol::SaslPlaintext,
+            listeners[0].security_protocol()
+        );
+        assert_eq!("localhost:9092", listeners[0].advertised_address());
+
+        Ok(())
+    }
+
+    #[test]
+    fn duplicate_listener() -> Result<()> {
+        let config = Config::from_str("listeners=PLAINTEXT://:9092,PLAINTEXT://:9093")?;
+        assert!(config.listeners().is_err());
+
+        let config = Config::from_str(
+            "listeners=PLAINTEXT://:9092\nadvertised.listeners=PLAINTEXT://a:9092,PLAINTEXT://b:9092",
+        )?;
+        assert!(config.listeners().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_listener() -> Result<()> {
+        let config =
+            Config::from_str("listeners=INTERNAL://:9092\nadvertised.listeners=OTHER://a:9092")?;
+        assert!(config.listeners().is_err());
+
+        let config = Config::from_str(
+            "listeners=INTERNAL://:9092\nlistener.security.protocol.map=INTERNAL:PLAINTEXT,OTHER:SSL",
+        )?;
+        assert!(config.listeners().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn broker_config() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            listeners=INTERNAL://:9092,EXTERNAL://0.0.0.0:9093
+            advertised.listeners=EXTERNAL://kafka.example.com:19093
+            listener.security.protocol.map=INTERNAL:PLAINTEXT,EXTERNAL:SSL
+            ssl.certificate.location=/etc/tansu/broker.pem
+            ssl.key.location=/etc/tansu/broker.key
+            "#,
+        )?
+        .broker_config()?;
+
+        config.validate()?;
+
+        let listeners = config.listeners(SecurityProtocol::SaslPlaintext)?;
+        assert_eq!(2, listeners.len());
+        assert_eq!("0.0.0.0:9092", listeners[0].advertised_address());
+        assert_eq!(
+            SecurityProtocol::Plaintext,
+            listeners[0].security_protocol()
+        );
+        assert_eq!("kafka.example.com:19093", listeners[1].advertised_address());
+        assert_eq!(SecurityProtocol::Ssl, listeners[1].security_protocol());
+
+        assert_eq!(
+            Some(PathBuf::from("/etc/tansu/broker.pem")),
+            config.tls.certificate_location
+        );
+        assert_eq!(
+            Some(PathBuf::from("/etc/tansu/broker.key")),
+            config.tls.key_location
+        );
+
+        assert_eq!(BrokerConfig::default(), Config::default().broker_config()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn missing_security_protocol() -> Result<()> {
+        let config = Config::from_str("listeners=INTERNAL://:9092")?;
+        assert!(config.listeners().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,497 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Broker settings read from a TOML file, for example:
+//!
+//! ```toml
+//! node_id = 111
+//! cluster_id = "tansu"
+//!
+//! [[listeners]]
+//! name = "broker"
+//! url = "tcp://0.0.0.0:9092"
+//! advertised_url = "tcp://kafka.example.com:9092"
+//!
+//! [storage]
+//! url = "s3://tansu/"
+//! options = { aws_endpoint = "http://localhost:9000", aws_allow_http = "true" }
+//!
+//! [topic]
+//! "retention.ms" = "86400000"
+//!
+//! [group]
+//! initial_rebalance_delay_ms = 0
+//!
+//! [tls]
+//! certificate_location = "/etc/tansu/broker.pem"
+//! key_location = "/etc/tansu/broker.key"
+//! ```
+//!
+//! A setting given as a command line flag takes precedence over its
+//! `TANSU_*` environment variable, which takes precedence over the file,
+//! with the built-in default used when a setting is given nowhere.
+//! Unknown keys in the file are an error.
+//!
+//! The listeners and TLS of a properties file are read into these settings,
+//! taking precedence over the same settings of this file.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use object_store::aws::AmazonS3ConfigKey;
+use serde::{Deserialize, Serialize};
+use tokio_rustls::rustls::ServerConfig;
+use tracing_subscriber::EnvFilter;
+use url::Url;
+
+use crate::{
+    broker::{
+        listener::{ListenerConfig, SecurityProtocol},
+        quota::ClientQuota,
+    },
+    Error, Result,
+};
+
+/// The storage engines that may be used by a broker, by URL scheme.
+pub const STORAGE_SCHEMES: [&str; 4] = ["postgres", "postgresql", "s3", "memory"];
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrokerConfig {
+    pub node_id: Option<i32>,
+    pub cluster_id: Option<String>,
+    pub rack: Option<String>,
+    pub max_in_flight_requests: Option<usize>,
+    pub broker_session_timeout_ms: Option<u64>,
+
+    /// Quotas in the same form as `--quota`, e.g. `producer_byte_rate=1048576`.
+    pub quotas: Vec<String>,
+
+    pub listeners: Vec<ListenerSettings>,
+    pub storage: StorageSettings,
+
+    /// The configuration of a topic that is not given when it is created.
+    pub topic: BTreeMap<String, String>,
+
+    pub group: GroupSettings,
+    pub log: LogSettings,
+    pub metrics: MetricsSettings,
+    pub tls: TlsSettings,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerSettings {
+    pub name: String,
+    pub url: Url,
+
+    /// The address given to clients, when different to the bound address.
+    pub advertised_url: Option<Url>,
+
+    /// Defaults to SASL_PLAINTEXT when SASL is enabled, otherwise PLAINTEXT.
+    pub security_protocol: Option<String>,
+}
+
+impl FromStr for ListenerSettings {
+    type Err = Error;
+
+    /// Parse "name=bind" or "name=bind,advertised", as with `--listener`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, urls) = s.split_once('=').ok_or_else(|| {
+            Error::Message(format!("expecting name=bind[,advertised], found: {s}"))
+        })?;
+
+        if name.is_empty() {
+            return Err(Error::Message(format!("listener without a name: {s}")));
+        }
+
+        let (url, advertised_url) = urls
+            .split_once(',')
+            .map_or((urls, None), |(url, advertised_url)| {
+                (url, Some(advertised_url))
+            });
+
+        Ok(Self {
+            name: name.to_owned(),
+            url: Url::parse(url)?,
+            advertised_url: advertised_url.map(Url::parse).transpose()?,
+            security_protocol: None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    pub url: Option<Url>,
+
+    /// Options of the object store, e.g. `aws_endpoint` for S3.
+    pub options: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GroupSettings {
+    pub initial_rebalance_delay_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSettings {
+    /// A filter in the same form as `RUST_LOG`, used when it is unset.
+    pub filter: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsSettings {
+    pub prometheus_listener_url: Option<Url>,
+}
+
+/// The certificate and key of SSL and SASL_SSL listeners, in PEM.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsSettings {
+    pub certificate_location: Option<PathBuf>,
+    pub key_location: Option<PathBuf>,
+}
+
+impl BrokerConfig {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        fs::read_to_string(path)
+            .map_err(Into::into)
+            .and_then(|contents| contents.parse())
+            .map_err(|error| Error::Message(format!("{}: {error}", path.display())))
+    }
+
+    /// These settings, replaced by any that are present in overrides.
+    pub fn merge(self, overrides: Self) -> Self {
+        Self {
+            node_id: overrides.node_id.or(self.node_id),
+            cluster_id: overrides.cluster_id.or(self.cluster_id),
+            rack: overrides.rack.or(self.rack),
+            max_in_flight_requests: overrides
+                .max_in_flight_requests
+                .or(self.max_in_flight_requests),
+            broker_session_timeout_ms: overrides
+                .broker_session_timeout_ms
+                .or(self.broker_session_timeout_ms),
+            quotas: if overrides.quotas.is_empty() {
+                self.quotas
+            } else {
+                overrides.quotas
+            },
+            listeners: if overrides.listeners.is_empty() {
+                self.listeners
+            } else {
+                overrides.listeners
+            },
+            storage: StorageSettings {
+                url: overrides.storage.url.or(self.storage.url),
+                options: self
+                    .storage
+                    .options
+                    .into_iter()
+                    .chain(overrides.storage.options)
+                    .collect(),
+            },
+            topic: self.topic.into_iter().chain(overrides.topic).collect(),
+            group: GroupSettings {
+                initial_rebalance_delay_ms: overrides
+                    .group
+                    .initial_rebalance_delay_ms
+                    .or(self.group.initial_rebalance_delay_ms),
+            },
+            log: LogSettings {
+                filter: overrides.log.filter.or(self.log.filter),
+            },
+            metrics: MetricsSettings {
+                prometheus_listener_url: overrides
+                    .metrics
+                    .prometheus_listener_url
+                    .or(self.metrics.prometheus_listener_url),
+            },
+            tls: TlsSettings {
+                certificate_location: overrides
+                    .tls
+                    .certificate_location
+                    .or(self.tls.certificate_location),
+                key_location: overrides.tls.key_location.or(self.tls.key_location),
+            },
+        }
+    }
+
+    /// The listeners, using the default security protocol for those without one.
+    pub fn listeners(&self, default: SecurityProtocol) -> Result<Vec<ListenerConfig>> {
+        let mut listeners: Vec<ListenerConfig> = vec![];
+
+        for listener in &self.listeners {
+            if listeners
+                .iter()
+                .any(|existing| existing.name() == listener.name)
+            {
+                return Err(Error::Message(format!(
+                    "duplicate listener: {}",
+                    listener.name
+                )));
+            }
+
+            let security_protocol = listener
+                .security_protocol
+                .as_deref()
+                .map_or(Ok(default), SecurityProtocol::from_str)?;
+
+            listeners.push(
+                ListenerConfig::new(
+                    &listener.name,
+                    listener.url.clone(),
+                    listener.advertised_url.as_ref().unwrap_or(&listener.url),
+                )
+                .with_security_protocol(security_protocol),
+            );
+        }
+
+        Ok(listeners)
+    }
+
+    /// The TLS configuration of SSL and SASL_SSL listeners, if a certificate
+    /// and key are configured.
+    pub fn tls(&self) -> Result<Option<Arc<ServerConfig>>> {
+        let (Some(certificate), Some(key)) = (
+            self.tls.certificate_location.as_ref(),
+            self.tls.key_location.as_ref(),
+        ) else {
+            return Ok(None);
+        };
+
+        let certificates = File::open(certificate)
+            .map(BufReader::new)
+            .and_then(|mut reader| {
+                rustls_pemfile::certs(&mut reader).collect::<Result<Vec<_>, _>>()
+            })?;
+
+        let key = File::open(key)
+            .map(BufReader::new)
+            .and_then(|mut reader| rustls_pemfile::private_key(&mut reader))?
+            .ok_or_else(|| Error::Message(format!("no private key in: {}", key.display())))?;
+
+        ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certificates, key)
+            .map(Arc::new)
+            .map(Some)
+            .map_err(Into::into)
+    }
+
+    pub fn quotas(&self) -> Result<Vec<ClientQuota>> {
+        self.quotas
+            .iter()
+            .map(|quota| ClientQuota::from_str(quota))
+            .collect()
+    }
+
+    /// Check the settings that are not checked when they are read.
+    pub fn validate(&self) -> Result<()> {
+        _ = self.listeners(SecurityProtocol::default())?;
+        _ = self.quotas()?;
+
+        if let Some(ref url) = self.storage.url {
+            if !STORAGE_SCHEMES.contains(&url.scheme()) {
+                return Err(Error::UnsupportedStorageUrl(url.clone()));
+            }
+
+            if !self.storage.options.is_empty() && url.scheme() != "s3" {
+                return Err(Error::Message(format!(
+                    "storage options are not supported by: {url}"
+                )));
+            }
+        }
+
+        if self
+            .storage
+            .url
+            .as_ref()
+            .is_some_and(|url| url.scheme() == "s3")
+        {
+            for key in self.storage.options.keys() {
+                _ = AmazonS3ConfigKey::from_str(key)
+                    .map_err(|_| Error::Message(format!("unknown storage option: {key}")))?;
+            }
+        }
+
+        if self.tls.certificate_location.is_some() != self.tls.key_location.is_some() {
+            return Err(Error::Message(
+                "a TLS certificate and key are configured together".into(),
+            ));
+        }
+
+        if let Some(ref filter) = self.log.filter {
+            _ = EnvFilter::try_new(filter)
+                .map_err(|error| Error::Message(format!("log filter: {filter}: {error}")))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for BrokerConfig {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(|error| Error::Message(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"
+        node_id = 111
+        cluster_id = "tansu"
+        quotas = ["producer_byte_rate=1048576"]
+
+        [[listeners]]
+        name = "internal"
+        url = "tcp://0.0.0.0:9092"
+
+        [[listeners]]
+        name = "external"
+        url = "tcp://0.0.0.0:9093"
+        advertised_url = "tcp://kafka.example.com:19093"
+        security_protocol = "SASL_SSL"
+
+        [storage]
+        url = "s3://tansu/"
+        options = { aws_endpoint = "http://localhost:9000" }
+
+        [topic]
+        "retention.ms" = "86400000"
+
+        [group]
+        initial_rebalance_delay_ms = 0
+
+        [log]
+        filter = "warn,tansu_server=debug"
+    "#;
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let config = BrokerConfig::from_str(EXAMPLE)?;
+        config.validate()?;
+
+        assert_eq!(Some(111), config.node_id);
+        assert_eq!(2, config.listeners.len());
+        assert_eq!(
+            Some("86400000"),
+            config.topic.get("retention.ms").map(String::as_str)
+        );
+
+        let encoded = toml::to_string(&config).map_err(|error| Error::Custom(error.to_string()))?;
+        assert_eq!(config, BrokerConfig::from_str(&encoded)?);
+
+        let listeners = config.listeners(SecurityProtocol::Plaintext)?;
+        assert_eq!(
+            SecurityProtocol::Plaintext,
+            listeners[0].security_protocol()
+        );
+        assert_eq!("kafka.example.com:19093", listeners[1].advertised_address());
+        assert_eq!(SecurityProtocol::SaslSsl, listeners[1].security_protocol());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_keys() {
+        assert!(BrokerConfig::from_str("node = 1").is_err());
+        assert!(BrokerConfig::from_str("[group]\nrebalance_delay_ms = 0").is_err());
+        assert!(BrokerConfig::from_str(
+            "[[listeners]]\nname = \"a\"\nurl = \"tcp://:9092\"\nport = 1"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn invalid() -> Result<()> {
+        for invalid in [
+            "quotas = [\"bytes=1\"]",
+            "[storage]\nurl = \"ftp://tansu/\"",
+            "[storage]\nurl = \"memory://\"\noptions = { a = \"b\" }",
+            "[storage]\nurl = \"s3://tansu/\"\noptions = { a = \"b\" }",
+            "[[listeners]]\nname = \"a\"\nurl = \"tcp://localhost:9092\"\nsecurity_protocol = \"TLS\"",
+            "[tls]\ncertificate_location = \"broker.pem\"",
+        ] {
+            assert!(BrokerConfig::from_str(invalid)?.validate().is_err(), "{invalid}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn overrides_take_precedence() -> Result<()> {
+        let file = BrokerConfig::from_str(EXAMPLE)?;
+
+        let merged = file.clone().merge(BrokerConfig {
+            node_id: Some(222),
+            storage: StorageSettings {
+                url: Some(Url::parse("s3://other/")?),
+                options: BTreeMap::from([("aws_region".into(), "eu-west-2".into())]),
+            },
+            group: GroupSettings {
+                initial_rebalance_delay_ms: Some(3_000),
+            },
+            ..Default::default()
+        });
+
+        assert_eq!(Some(222), merged.node_id);
+        assert_eq!(Some("tansu"), merged.cluster_id.as_deref());
+        assert_eq!(Some(3_000), merged.group.initial_rebalance_delay_ms);
+        assert_eq!(file.listeners, merged.listeners);
+        assert_eq!(Some(Url::parse("s3://other/")?), merged.storage.url);
+        assert_eq!(2, merged.storage.options.len());
+
+        assert_eq!(file, file.clone().merge(BrokerConfig::default()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn listener_settings() -> Result<()> {
+        let listener = ListenerSettings::from_str("external=tcp://0.0.0.0:9093,tcp://kafka:19093")?;
+        assert_eq!("external", listener.name);
+        assert_eq!(Url::parse("tcp://0.0.0.0:9093")?, listener.url);
+        assert_eq!(
+            Some(Url::parse("tcp://kafka:19093")?),
+            listener.advertised_url
+        );
+        assert_eq!(None, listener.security_protocol);
+
+        let listener = ListenerSettings::from_str("internal=tcp://localhost:9092")?;
+        assert_eq!(None, listener.advertised_url);
+
+        assert!(ListenerSettings::from_str("tcp://localhost:9092").is_err());
+        assert!(ListenerSettings::from_str("=tcp://localhost:9092").is_err());
+
+        Ok(())
+    }
+}
@@ -14,4 +14,5 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod group;
+pub mod liveness;
 pub mod tx;
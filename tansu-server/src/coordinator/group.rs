@@ -15,11 +15,13 @@
 
 pub mod administrator;
 pub mod consumer;
+pub mod consumer_group;
 
 use crate::Result;
 use async_trait::async_trait;
 use std::fmt::Debug;
 use tansu_kafka_sans_io::{
+    consumer_group_heartbeat_request::TopicPartitions,
     join_group_request::JoinGroupRequestProtocol,
     leave_group_request::MemberIdentity,
     offset_commit_request::OffsetCommitRequestTopic,
@@ -38,12 +40,28 @@ pub struct OffsetCommit<'a> {
     pub topics: Option<&'a [OffsetCommitRequestTopic]>,
 }
 
+#[derive(Debug)]
+pub struct ConsumerGroupHeartbeat<'a> {
+    pub client_id: Option<&'a str>,
+    pub client_host: Option<&'a str>,
+    pub group_id: &'a str,
+    pub member_id: &'a str,
+    pub member_epoch: i32,
+    pub instance_id: Option<&'a str>,
+    pub rack_id: Option<&'a str>,
+    pub rebalance_timeout_ms: i32,
+    pub subscribed_topic_names: Option<&'a [String]>,
+    pub server_assignor: Option<&'a str>,
+    pub topic_partitions: Option<&'a [TopicPartitions]>,
+}
+
 #[async_trait]
 pub trait Coordinator: Clone + Debug + Send + Sync + 'static {
     #[allow(clippy::too_many_arguments)]
     async fn join(
         &mut self,
         client_id: Option<&str>,
+        client_host: Option<&str>,
         group_id: &str,
         session_timeout_ms: i32,
         rebalance_timeout_ms: Option<i32>,
@@ -90,4 +108,32 @@ pub trait Coordinator: Clone + Debug + Send + Sync + 'static {
         groups: Option<&[OffsetFetchRequestGroup]>,
         require_stable: Option<bool>,
     ) -> Result<Body>;
+
+    /// A snapshot of each group's state, protocol and members. An unknown
+    /// group is described as "Dead" with no members.
+    async fn describe(
+        &mut self,
+        group_ids: Option<&[String]>,
+        include_authorized_operations: bool,
+    ) -> Result<Body>;
+
+    /// The groups known to storage, optionally restricted to those in one of
+    /// the named states. Groups that only have committed offsets are "Empty".
+    async fn list(&mut self, states_filter: Option<&[String]>) -> Result<Body>;
+
+    /// Heartbeat of a member of a group using the consumer rebalance
+    /// protocol of KIP-848, answered with its epoch and any change to the
+    /// partitions it is assigned.
+    async fn consumer_group_heartbeat(
+        &mut self,
+        heartbeat: ConsumerGroupHeartbeat<'_>,
+    ) -> Result<Body>;
+
+    /// A snapshot of the epochs, members and assignments of each group using
+    /// the consumer rebalance protocol.
+    async fn consumer_group_describe(
+        &mut self,
+        group_ids: Option<&[String]>,
+        include_authorized_operations: bool,
+    ) -> Result<Body>;
 }
@@ -14,25 +14,32 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::{
+    cmp::Reverse,
     collections::{BTreeMap, BTreeSet},
     fmt::Debug,
+    iter,
     ops::Deref,
+    sync::{Arc, Mutex},
     time::SystemTime,
 };
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use tansu_kafka_sans_io::{
+    consumer::{self, ConsumerProtocolAssignment, ConsumerProtocolSubscription},
+    describe_groups_response::{DescribedGroup, DescribedGroupMember},
     join_group_request::JoinGroupRequestProtocol,
     join_group_response::JoinGroupResponseMember,
     leave_group_request::MemberIdentity,
     leave_group_response::MemberResponse,
+    list_groups_response::ListedGroup,
     offset_commit_response::{OffsetCommitResponsePartition, OffsetCommitResponseTopic},
     offset_fetch_request::{OffsetFetchRequestGroup, OffsetFetchRequestTopic},
     offset_fetch_response::{
         OffsetFetchResponseGroup, OffsetFetchResponsePartition, OffsetFetchResponsePartitions,
         OffsetFetchResponseTopic, OffsetFetchResponseTopics,
     },
+    response::OffsetFetchResponse,
     sync_group_request::SyncGroupRequestAssignment,
     Body, ErrorCode,
 };
@@ -40,16 +47,31 @@ use tansu_storage::{
     GroupDetail, GroupMember, GroupState, OffsetCommitRequest, Storage, Topition, UpdateError,
     Version,
 };
-use tokio::time::{sleep, Duration};
-use tracing::{debug, info};
+use tokio::{
+    sync::Notify,
+    time::{interval, sleep, Duration, Instant},
+};
+use tracing::{debug, info, instrument, warn};
 use uuid::Uuid;
 
-use crate::{Error, Result};
+use crate::{broker::error_response::offset_fetch_error, metrics, Error, Result};
 
-use super::{Coordinator, OffsetCommit};
+use super::{consumer_group::ConsumerGroups, ConsumerGroupHeartbeat, Coordinator, OffsetCommit};
 
 const PAUSE_MS: u64 = 3_000;
 
+// how often a follower waiting for its assignment checks on the group, when
+// the leader may have synced with another broker
+const SYNC_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often members are checked for an expired session.
+pub const SESSION_EXPIRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The default of `group.initial.rebalance.delay.ms`.
+pub const DEFAULT_INITIAL_REBALANCE_DELAY: Duration = Duration::from_secs(3);
+
+const AUTHORIZED_OPERATIONS_OMITTED: i32 = -2_147_483_648;
+
 #[async_trait]
 pub trait Group: Debug + Send {
     type JoinState;
@@ -64,6 +86,7 @@ pub trait Group: Debug + Send {
         self,
         now: SystemTime,
         client_id: Option<&str>,
+        client_host: Option<&str>,
         group_id: &str,
         session_timeout_ms: i32,
         rebalance_timeout_ms: Option<i32>,
@@ -172,6 +195,11 @@ where
                             GroupMember {
                                 join_response: member.join_response.clone(),
                                 last_contact: member.last_contact,
+                                session_timeout_ms: member.session_timeout_ms,
+                                joined: member.joined,
+                                client_id: member.client_id.clone(),
+                                client_host: member.client_host.clone(),
+                                protocols: member.protocols.clone(),
                             },
                         )
                     })
@@ -182,6 +210,8 @@ where
                     protocol_type: state.protocol_type.clone(),
                     protocol_name: state.protocol_name.clone(),
                     leader: state.leader.clone(),
+                    rebalance_started: state.rebalance_started,
+                    delayed_until: state.delayed_until,
                 },
             },
             Wrapper::Formed(Inner {
@@ -205,6 +235,11 @@ where
                             GroupMember {
                                 join_response: member.join_response.clone(),
                                 last_contact: member.last_contact,
+                                session_timeout_ms: member.session_timeout_ms,
+                                joined: member.joined,
+                                client_id: member.client_id.clone(),
+                                client_host: member.client_host.clone(),
+                                protocols: member.protocols.clone(),
                             },
                         )
                     })
@@ -232,6 +267,8 @@ where
                 protocol_type,
                 protocol_name,
                 mut leader,
+                rebalance_started,
+                delayed_until,
             } => {
                 if let Some(ref leader_id) = leader {
                     if !gd
@@ -256,6 +293,11 @@ where
                                 Member {
                                     join_response: member.join_response.clone(),
                                     last_contact: member.last_contact,
+                                    session_timeout_ms: member.session_timeout_ms,
+                                    joined: member.joined,
+                                    client_id: member.client_id.clone(),
+                                    client_host: member.client_host.clone(),
+                                    protocols: member.protocols.clone(),
                                 },
                             )
                         })
@@ -265,6 +307,8 @@ where
                         protocol_type,
                         protocol_name,
                         leader,
+                        rebalance_started,
+                        delayed_until,
                     },
                     storage,
                     skip_assignment: gd.skip_assignment,
@@ -288,6 +332,11 @@ where
                             Member {
                                 join_response: member.join_response.clone(),
                                 last_contact: member.last_contact,
+                                session_timeout_ms: member.session_timeout_ms,
+                                joined: member.joined,
+                                client_id: member.client_id.clone(),
+                                client_host: member.client_host.clone(),
+                                protocols: member.protocols.clone(),
                             },
                         )
                     })
@@ -347,6 +396,126 @@ where
         }
     }
 
+    // the end of the initial rebalance delay of a forming group
+    fn delayed_until(&self) -> Option<SystemTime> {
+        match self {
+            Self::Forming(inner) => inner.state.delayed_until,
+            Self::Formed(_) => None,
+        }
+    }
+
+    fn delay_rebalance(self, until: SystemTime) -> Self {
+        match self {
+            Self::Forming(mut inner) if inner.members.is_empty() => {
+                _ = inner.state.delayed_until.replace(until);
+                Self::Forming(inner)
+            }
+
+            otherwise => otherwise,
+        }
+    }
+
+    // a new session for every member, with any rebalance restarted
+    fn resume(mut self, now: SystemTime) -> Self {
+        match self {
+            Self::Forming(ref mut inner) => {
+                inner.resume(now);
+
+                if inner.state.rebalance_started.is_some() {
+                    _ = inner.state.rebalance_started.replace(now);
+                }
+            }
+
+            Self::Formed(ref mut inner) => inner.resume(now),
+        }
+
+        self
+    }
+
+    // a successful join response for an existing member, with the members
+    // of the group only being given to the leader
+    fn join_response(&self, member_id: &str) -> Option<Body> {
+        self.members()
+            .iter()
+            .any(|member| member.member_id == member_id)
+            .then(|| Body::JoinGroupResponse {
+                throttle_time_ms: Some(0),
+                error_code: ErrorCode::None.into(),
+                generation_id: self.generation_id(),
+                protocol_type: self.protocol_type().map(ToOwned::to_owned),
+                protocol_name: self.protocol_name().map(ToOwned::to_owned),
+                leader: self.leader().unwrap_or_default().to_owned(),
+                skip_assignment: self.skip_assignment().copied(),
+                member_id: member_id.into(),
+                members: Some(if self.leader().is_some_and(|leader| leader == member_id) {
+                    self.members()
+                } else {
+                    [].into()
+                }),
+                unknown_tagged_fields: vec![],
+            })
+    }
+
+    // a follower that has synced before the leader, waiting for its assignment
+    fn awaiting_assignment(&self, generation_id: i32, member_id: &str) -> bool {
+        matches!(
+            self,
+            Self::Forming(inner)
+            if inner.generation_id == generation_id
+                && inner.members.contains_key(member_id)
+                && inner
+                    .state
+                    .leader
+                    .as_deref()
+                    .is_some_and(|leader| leader != member_id)
+        )
+    }
+
+    // the sync response of a waiting follower, once the leader has synced
+    // or a new rebalance has started
+    fn sync_response(&self, generation_id: i32, member_id: &str) -> Option<Body> {
+        if self.awaiting_assignment(generation_id, member_id) {
+            return None;
+        }
+
+        let (error_code, assignment) = match self {
+            Self::Formed(inner)
+                if inner.generation_id == generation_id
+                    && inner.members.contains_key(member_id) =>
+            {
+                (
+                    ErrorCode::None,
+                    inner.state.assignments.get(member_id).cloned(),
+                )
+            }
+
+            Self::Forming(Inner { members, .. }) | Self::Formed(Inner { members, .. })
+                if !members.contains_key(member_id) =>
+            {
+                (ErrorCode::UnknownMemberId, None)
+            }
+
+            _ => (ErrorCode::RebalanceInProgress, None),
+        };
+
+        Some(Body::SyncGroupResponse {
+            throttle_time_ms: Some(0),
+            error_code: error_code.into(),
+            protocol_type: self.protocol_type().map(ToOwned::to_owned),
+            protocol_name: self.protocol_name().map(ToOwned::to_owned),
+            assignment: assignment.unwrap_or(Bytes::from_static(b"")),
+            unknown_tagged_fields: vec![],
+        })
+    }
+
+    // the time allowed for the members to rejoin, and the leader to sync
+    fn rebalance_timeout(&self) -> Duration {
+        self.rebalance_timeout_ms()
+            .unwrap_or(self.session_timeout_ms())
+            .try_into()
+            .map_or(Duration::from_millis(300_000), Duration::from_millis)
+    }
+
     pub fn skip_assignment(&self) -> Option<&bool> {
         match self {
             Self::Forming(inner) => inner.skip_assignment.as_ref(),
@@ -372,6 +541,52 @@ where
         }
     }
 
+    fn state(&self) -> &'static str {
+        match self {
+            Wrapper::Forming(inner) if inner.members.is_empty() => "Empty",
+            Wrapper::Forming(inner) if inner.state.leader.is_some() => "CompletingRebalance",
+            Wrapper::Forming(_) => "PreparingRebalance",
+            Wrapper::Formed(_) => "Stable",
+        }
+    }
+
+    fn describe(&self, group_id: &str) -> DescribedGroup {
+        let (protocol_data, members, assignments) = match self {
+            Wrapper::Forming(inner) => ("", &inner.members, None),
+
+            Wrapper::Formed(inner) => (
+                inner.state.protocol_name.as_str(),
+                &inner.members,
+                Some(&inner.state.assignments),
+            ),
+        };
+
+        DescribedGroup {
+            error_code: ErrorCode::None.into(),
+            group_id: group_id.into(),
+            group_state: self.state().into(),
+            protocol_type: self.protocol_type().unwrap_or_default().into(),
+            protocol_data: protocol_data.into(),
+            members: Some(
+                members
+                    .iter()
+                    .map(|(member_id, member)| DescribedGroupMember {
+                        member_id: member_id.clone(),
+                        group_instance_id: member.join_response.group_instance_id.clone(),
+                        client_id: member.client_id.clone().unwrap_or_default(),
+                        client_host: member.client_host.clone().unwrap_or_default(),
+                        member_metadata: member.join_response.metadata.clone(),
+                        member_assignment: assignments
+                            .and_then(|assignments| assignments.get(member_id))
+                            .cloned()
+                            .unwrap_or_default(),
+                    })
+                    .collect(),
+            ),
+            authorized_operations: Some(AUTHORIZED_OPERATIONS_OMITTED),
+        }
+    }
+
     #[cfg(test)]
     fn assignments(&self) -> Option<BTreeMap<String, Bytes>> {
         match self {
@@ -385,23 +600,31 @@ where
 
         match self {
             Wrapper::Forming(mut inner) => {
-                _ = inner.missed_heartbeat(group_id, now);
+                // any assignment in progress includes the expired members
+                if inner.missed_heartbeat(group_id, now) | inner.missed_rebalance(group_id, now) {
+                    inner.generation_id += 1;
+                    _ = inner.state.rebalance_started.replace(now);
+                }
+
                 Wrapper::Forming(inner)
             }
             Wrapper::Formed(mut inner) => {
                 if inner.missed_heartbeat(group_id, now) {
                     info!("missed heartbeat for {group_id} in {}", inner.generation_id);
 
+                    // survivors rejoin on the rebalance in progress of their next heartbeat
                     Wrapper::Forming(Inner {
                         session_timeout_ms: inner.session_timeout_ms,
                         rebalance_timeout_ms: inner.rebalance_timeout_ms,
                         group_instance_id: inner.group_instance_id,
                         members: inner.members,
-                        generation_id: inner.generation_id,
+                        generation_id: inner.generation_id + 1,
                         state: Forming {
                             protocol_type: Some(inner.state.protocol_type),
                             protocol_name: Some(inner.state.protocol_name),
                             leader: None,
+                            rebalance_started: Some(now),
+                            delayed_until: None,
                         },
                         storage: inner.storage,
                         skip_assignment: inner.skip_assignment,
@@ -431,6 +654,7 @@ where
         self,
         now: SystemTime,
         client_id: Option<&str>,
+        client_host: Option<&str>,
         group_id: &str,
         session_timeout_ms: i32,
         rebalance_timeout_ms: Option<i32>,
@@ -446,6 +670,7 @@ where
                     .join(
                         now,
                         client_id,
+                        client_host,
                         group_id,
                         session_timeout_ms,
                         rebalance_timeout_ms,
@@ -464,6 +689,7 @@ where
                     .join(
                         now,
                         client_id,
+                        client_host,
                         group_id,
                         session_timeout_ms,
                         rebalance_timeout_ms,
@@ -611,19 +837,29 @@ where
             }
 
             Wrapper::Formed(inner) => {
-                let (state, body) = inner
+                inner
                     .heartbeat(now, group_id, generation_id, member_id, group_instance_id)
-                    .await;
-                (state.into(), body)
+                    .await
             }
         }
     }
 }
 
+/// The group coordinator.
+///
+/// There is no lock shared between groups: each request is handled by its
+/// own clone, with a cache of the groups that it has seen, and every change
+/// to a group is a conditional update of that group in storage. A request
+/// that finds its group outdated reloads and retries, so a slow rebalance in
+/// one group never holds up requests for another.
 #[derive(Clone, Debug)]
 pub struct Controller<O: Storage> {
     storage: O,
     wrappers: BTreeMap<String, (Wrapper<O>, Option<Version>)>,
+    initial_rebalance_delay: Duration,
+    resumed: Arc<Mutex<BTreeSet<String>>>,
+    changed: Arc<Notify>,
+    consumer_groups: ConsumerGroups<O>,
 }
 
 impl<O> Controller<O>
@@ -632,10 +868,252 @@ where
 {
     pub fn with_storage(storage: O) -> Result<Self> {
         Ok(Self {
+            consumer_groups: ConsumerGroups::with_storage(storage.clone()),
             storage,
             wrappers: BTreeMap::new(),
+            initial_rebalance_delay: DEFAULT_INITIAL_REBALANCE_DELAY,
+            resumed: Arc::new(Mutex::new(BTreeSet::new())),
+            changed: Arc::new(Notify::new()),
         })
     }
+
+    /// Hold the first joins to an empty group for this delay, so that
+    /// consumers starting together join the same generation.
+    pub fn with_initial_rebalance_delay(self, initial_rebalance_delay: Duration) -> Self {
+        Self {
+            initial_rebalance_delay,
+            ..self
+        }
+    }
+
+    // the join response of a member once the initial rebalance delay has passed
+    async fn delayed_join(&mut self, group_id: &str, member_id: &str, body: Body) -> Result<Body> {
+        // the group may have moved on in the meantime
+        _ = self.wrappers.remove(group_id);
+
+        Ok(self
+            .storage
+            .group_detail(group_id)
+            .await?
+            .map(|detail| Wrapper::with_storage_group_detail(self.storage.clone(), detail))
+            .and_then(|wrapper| wrapper.join_response(member_id))
+            .unwrap_or(body))
+    }
+
+    // a group persisted before this broker started is resumed when first
+    // touched, giving its members a new session in which to reconnect
+    // rather than expiring them for the time the broker was down
+    async fn resume(&mut self, group_id: &str, now: SystemTime) -> Result<()> {
+        if self.resumed.lock()?.contains(group_id) {
+            return Ok(());
+        }
+
+        let Some(mut detail) = self.storage.group_detail(group_id).await? else {
+            _ = self.resumed.lock()?.insert(group_id.to_owned());
+            return Ok(());
+        };
+
+        let mut version = None;
+
+        loop {
+            let wrapper =
+                Wrapper::with_storage_group_detail(self.storage.clone(), detail).resume(now);
+
+            debug!(?group_id, ?wrapper, ?version);
+
+            match self
+                .storage
+                .update_group(group_id, GroupDetail::from(&wrapper), version)
+                .await
+            {
+                Ok(version) => {
+                    info!(
+                        "resumed: {group_id} in generation: {}",
+                        wrapper.generation_id()
+                    );
+
+                    _ = self
+                        .wrappers
+                        .insert(group_id.to_owned(), (wrapper, Some(version)));
+
+                    _ = self.resumed.lock()?.insert(group_id.to_owned());
+
+                    return Ok(());
+                }
+
+                Err(UpdateError::Outdated {
+                    current,
+                    version: outdated,
+                }) => {
+                    debug!(?group_id, ?current, ?outdated);
+
+                    detail = current;
+                    version = Some(outdated);
+                }
+
+                Err(UpdateError::Error(error)) => return Err(error.into()),
+
+                Err(UpdateError::ObjectStore(error)) => return Err(error.into()),
+
+                Err(UpdateError::SerdeJson(error)) => return Err(error.into()),
+
+                Err(UpdateError::TokioPostgres(error)) => return Err(error.into()),
+
+                Err(UpdateError::MissingEtag) => {
+                    return Err(Error::Message(String::from("missing e-tag")))
+                }
+
+                Err(UpdateError::Uuid(uuid)) => {
+                    return Err(Error::Message(format!("uuid: {uuid}")))
+                }
+            }
+        }
+    }
+
+    // a follower that syncs before the leader waits for its assignment, until
+    // a new rebalance starts or the rebalance times out
+    async fn awaited_sync(
+        &mut self,
+        group_id: &str,
+        generation_id: i32,
+        member_id: &str,
+        body: Body,
+        timeout: Duration,
+    ) -> Result<Body> {
+        let deadline = Instant::now() + timeout;
+
+        while Instant::now() < deadline {
+            let changed = self.changed.notified();
+
+            tokio::select! {
+                _ = changed => (),
+                _ = sleep(SYNC_POLL_INTERVAL) => (),
+            }
+
+            // the group may have moved on in the meantime
+            _ = self.wrappers.remove(group_id);
+
+            let Some(wrapper) = self
+                .storage
+                .group_detail(group_id)
+                .await?
+                .map(|detail| Wrapper::with_storage_group_detail(self.storage.clone(), detail))
+            else {
+                break;
+            };
+
+            if let Some(body) = wrapper.sync_response(generation_id, member_id) {
+                return Ok(body);
+            }
+        }
+
+        debug!(?group_id, ?member_id, ?timeout);
+        Ok(body)
+    }
+
+    /// Periodically expire the members of every group that have missed their
+    /// session timeout, so that a group without any other activity still
+    /// starts a rebalance.
+    pub async fn expire_sessions(mut self, every: Duration) -> Result<()> {
+        let mut interval = interval(every);
+
+        loop {
+            _ = interval.tick().await;
+
+            if let Err(error) = self.expire(SystemTime::now()).await {
+                warn!(?error);
+            }
+        }
+    }
+
+    /// Expire the members of every group that have missed their session
+    /// timeout by `now`, returning the groups that are now rebalancing.
+    pub async fn expire(&mut self, now: SystemTime) -> Result<Vec<String>> {
+        let mut expired = vec![];
+
+        for group_id in self.storage.list_groups().await? {
+            self.resume(&group_id, now).await?;
+
+            let mut iteration = 0;
+
+            loop {
+                let (wrapper, version) = match self.wrappers.remove(&group_id) {
+                    Some(cached) => cached,
+                    None => match self.storage.group_detail(&group_id).await? {
+                        // only the version of an update is conditional
+                        Some(detail) => (
+                            Wrapper::with_storage_group_detail(self.storage.clone(), detail),
+                            None,
+                        ),
+
+                        None => break,
+                    },
+                };
+
+                let original = GroupDetail::from(&wrapper);
+                let wrapper = wrapper.missed_heartbeat(&group_id, now);
+                let detail = GroupDetail::from(&wrapper);
+
+                if version.is_some() && original == detail {
+                    _ = self.wrappers.insert(group_id.clone(), (wrapper, version));
+                    break;
+                }
+
+                debug!(?group_id, ?wrapper, ?version, ?iteration);
+
+                match self.storage.update_group(&group_id, detail, version).await {
+                    Ok(version) => {
+                        debug!(?group_id, ?version);
+
+                        if original.generation_id < wrapper.generation_id() {
+                            expired.push(group_id.clone());
+                        }
+
+                        _ = self
+                            .wrappers
+                            .insert(group_id.clone(), (wrapper, Some(version)));
+
+                        break;
+                    }
+
+                    Err(UpdateError::Outdated { current, version }) => {
+                        debug!(?group_id, ?current, ?version, ?iteration);
+
+                        _ = self.wrappers.insert(
+                            group_id.clone(),
+                            (
+                                Wrapper::with_storage_group_detail(self.storage.clone(), current),
+                                Some(version),
+                            ),
+                        );
+
+                        iteration += 1;
+                        continue;
+                    }
+
+                    Err(UpdateError::Error(error)) => return Err(error.into()),
+
+                    Err(UpdateError::ObjectStore(error)) => return Err(error.into()),
+
+                    Err(UpdateError::SerdeJson(error)) => return Err(error.into()),
+
+                    Err(UpdateError::TokioPostgres(error)) => return Err(error.into()),
+
+                    Err(UpdateError::MissingEtag) => {
+                        return Err(Error::Message(String::from("missing e-tag")))
+                    }
+
+                    Err(UpdateError::Uuid(uuid)) => {
+                        return Err(Error::Message(format!("uuid: {uuid}")))
+                    }
+                }
+            }
+        }
+
+        expired.extend(self.consumer_groups.expire(now).await?);
+
+        Ok(expired)
+    }
 }
 
 #[async_trait]
@@ -643,9 +1121,11 @@ impl<O> Coordinator for Controller<O>
 where
     O: Storage + Clone,
 {
+    #[instrument(skip_all, fields(group_id, member_id))]
     async fn join(
         &mut self,
         client_id: Option<&str>,
+        client_host: Option<&str>,
         group_id: &str,
         session_timeout_ms: i32,
         rebalance_timeout_ms: Option<i32>,
@@ -667,6 +1147,24 @@ where
             ?reason,
         );
 
+        // a group id is not used by both protocols at the same time
+        if self.consumer_groups.is_active(group_id).await? {
+            return Ok(Body::JoinGroupResponse {
+                throttle_time_ms: Some(0),
+                error_code: ErrorCode::InconsistentGroupProtocol.into(),
+                generation_id: -1,
+                protocol_type: Some(protocol_type.into()),
+                protocol_name: None,
+                leader: "".into(),
+                skip_assignment: Some(false),
+                member_id: "".into(),
+                members: Some([].into()),
+                unknown_tagged_fields: vec![],
+            });
+        }
+
+        self.resume(group_id, SystemTime::now()).await?;
+
         let mut iteration = 0;
 
         loop {
@@ -688,7 +1186,9 @@ where
             });
 
             let now = SystemTime::now();
-            let wrapper = wrapper.missed_heartbeat(group_id, now);
+            let wrapper = wrapper
+                .missed_heartbeat(group_id, now)
+                .delay_rebalance(now + self.initial_rebalance_delay);
 
             if iteration == 0
                 && !member_id.is_empty()
@@ -700,10 +1200,13 @@ where
 
             debug!(?group_id, ?wrapper, ?version, ?iteration);
 
+            let generation_id = wrapper.generation_id();
+
             let (wrapper, body) = wrapper
                 .join(
                     now,
                     client_id,
+                    client_host,
                     group_id,
                     session_timeout_ms,
                     rebalance_timeout_ms,
@@ -725,10 +1228,33 @@ where
                 Ok(version) => {
                     debug!(?group_id, ?version);
 
+                    if wrapper.generation_id() > generation_id {
+                        metrics::rebalanced(group_id);
+                        self.changed.notify_waiters();
+                    }
+
+                    let delayed = wrapper
+                        .delayed_until()
+                        .and_then(|until| until.duration_since(now).ok())
+                        .filter(|_| {
+                            matches!(
+                                body,
+                                Body::JoinGroupResponse { error_code, .. }
+                                if error_code == i16::from(ErrorCode::None)
+                            )
+                        });
+
                     _ = self
                         .wrappers
                         .insert(group_id.to_owned(), (wrapper, Some(version)));
 
+                    if let Some(delay) = delayed {
+                        debug!(?group_id, ?member_id, ?delay);
+                        sleep(delay).await;
+
+                        return self.delayed_join(group_id, member_id, body).await;
+                    }
+
                     return Ok(body);
                 }
 
@@ -766,6 +1292,7 @@ where
         }
     }
 
+    #[instrument(skip_all, fields(group_id, generation_id, member_id))]
     async fn sync(
         &mut self,
         group_id: &str,
@@ -786,6 +1313,8 @@ where
             ?assignments
         );
 
+        self.resume(group_id, SystemTime::now()).await?;
+
         let mut iteration = 0;
 
         loop {
@@ -834,10 +1363,24 @@ where
                 Ok(version) => {
                     debug!(?group_id, ?version);
 
+                    if matches!(wrapper, Wrapper::Formed(_)) {
+                        self.changed.notify_waiters();
+                    }
+
+                    let awaiting = wrapper
+                        .awaiting_assignment(generation_id, member_id)
+                        .then(|| wrapper.rebalance_timeout());
+
                     _ = self
                         .wrappers
                         .insert(group_id.to_owned(), (wrapper, Some(version)));
 
+                    if let Some(timeout) = awaiting {
+                        return self
+                            .awaited_sync(group_id, generation_id, member_id, body, timeout)
+                            .await;
+                    }
+
                     return Ok(body);
                 }
 
@@ -875,6 +1418,7 @@ where
         }
     }
 
+    #[instrument(skip_all, fields(group_id))]
     async fn leave(
         &mut self,
         group_id: &str,
@@ -883,6 +1427,8 @@ where
     ) -> Result<Body> {
         debug!(?group_id, ?member_id, ?members);
 
+        self.resume(group_id, SystemTime::now()).await?;
+
         let mut iteration = 0;
 
         loop {
@@ -961,8 +1507,11 @@ where
         }
     }
 
+    #[instrument(skip_all, fields(group_id = offset_commit.group_id))]
     async fn offset_commit(&mut self, offset_commit: OffsetCommit<'_>) -> Result<Body> {
         let group_id = offset_commit.group_id;
+        self.resume(group_id, SystemTime::now()).await?;
+
         let mut iteration = 0;
 
         loop {
@@ -1041,6 +1590,7 @@ where
         }
     }
 
+    #[instrument(skip_all, fields(group_id))]
     async fn offset_fetch(
         &mut self,
         group_id: Option<&str>,
@@ -1068,45 +1618,152 @@ where
         Ok(body)
     }
 
-    async fn heartbeat(
+    #[instrument(skip_all)]
+    async fn describe(
         &mut self,
-        group_id: &str,
-        generation_id: i32,
-        member_id: &str,
-        group_instance_id: Option<&str>,
+        group_ids: Option<&[String]>,
+        include_authorized_operations: bool,
     ) -> Result<Body> {
-        debug!(?group_id, ?generation_id, ?member_id, ?group_instance_id);
+        debug!(?group_ids, ?include_authorized_operations);
 
-        let mut iteration = 0;
-
-        loop {
-            let (wrapper, version) = self.wrappers.remove(group_id).unwrap_or_else(|| {
-                debug!(?group_id, ?iteration);
+        let now = SystemTime::now();
 
-                let inner = Inner {
-                    session_timeout_ms: Default::default(),
-                    rebalance_timeout_ms: Default::default(),
-                    group_instance_id: Default::default(),
-                    members: Default::default(),
-                    generation_id: -1,
-                    state: Forming::default(),
-                    skip_assignment: Some(false),
-                    storage: self.storage.clone(),
-                };
+        let mut groups = vec![];
 
-                (Wrapper::Forming(inner), None)
-            });
+        for group_id in group_ids.unwrap_or_default() {
+            // the stored detail is authoritative, the cached wrapper may be outdated
+            groups.push(match self.storage.group_detail(group_id).await {
+                Ok(Some(detail)) => {
+                    Wrapper::with_storage_group_detail(self.storage.clone(), detail)
+                        .missed_heartbeat(group_id, now)
+                        .describe(group_id)
+                }
 
-            debug!(?group_id, ?wrapper, ?version, ?iteration);
+                Ok(None) => DescribedGroup {
+                    error_code: ErrorCode::None.into(),
+                    group_id: group_id.clone(),
+                    group_state: "Dead".into(),
+                    protocol_type: "".into(),
+                    protocol_data: "".into(),
+                    members: Some([].into()),
+                    authorized_operations: Some(AUTHORIZED_OPERATIONS_OMITTED),
+                },
 
-            let now = SystemTime::now();
+                Err(error) => {
+                    warn!(group_id = group_id.as_str(), ?error);
+
+                    DescribedGroup {
+                        error_code: ErrorCode::UnknownServerError.into(),
+                        group_id: group_id.clone(),
+                        group_state: "".into(),
+                        protocol_type: "".into(),
+                        protocol_data: "".into(),
+                        members: Some([].into()),
+                        authorized_operations: Some(AUTHORIZED_OPERATIONS_OMITTED),
+                    }
+                }
+            });
+        }
+
+        Ok(Body::DescribeGroupsResponse {
+            throttle_time_ms: Some(0),
+            groups: Some(groups),
+            unknown_tagged_fields: vec![],
+        })
+    }
+
+    #[instrument(skip_all)]
+    async fn list(&mut self, states_filter: Option<&[String]>) -> Result<Body> {
+        debug!(?states_filter);
+
+        let now = SystemTime::now();
+
+        let mut groups = vec![];
+
+        for group_id in self.storage.list_groups().await? {
+            let (group_state, protocol_type) = match self.storage.group_detail(&group_id).await? {
+                Some(detail) => {
+                    let wrapper = Wrapper::with_storage_group_detail(self.storage.clone(), detail)
+                        .missed_heartbeat(&group_id, now);
+
+                    (
+                        wrapper.state(),
+                        wrapper.protocol_type().unwrap_or_default().to_owned(),
+                    )
+                }
+
+                // only has committed offsets
+                None => ("Empty", String::new()),
+            };
+
+            // state names are matched without regard to case
+            if states_filter
+                .filter(|states| !states.is_empty())
+                .is_some_and(|states| {
+                    !states
+                        .iter()
+                        .any(|state| state.eq_ignore_ascii_case(group_state))
+                })
+            {
+                continue;
+            }
+
+            groups.push(ListedGroup {
+                group_id,
+                protocol_type,
+                group_state: Some(group_state.into()),
+            });
+        }
+
+        Ok(Body::ListGroupsResponse {
+            throttle_time_ms: Some(0),
+            error_code: ErrorCode::None.into(),
+            groups: Some(groups),
+            unknown_tagged_fields: vec![],
+        })
+    }
+
+    #[instrument(skip_all, fields(group_id, generation_id, member_id))]
+    async fn heartbeat(
+        &mut self,
+        group_id: &str,
+        generation_id: i32,
+        member_id: &str,
+        group_instance_id: Option<&str>,
+    ) -> Result<Body> {
+        debug!(?group_id, ?generation_id, ?member_id, ?group_instance_id);
+
+        self.resume(group_id, SystemTime::now()).await?;
+
+        let mut iteration = 0;
+
+        loop {
+            let (wrapper, version) = self.wrappers.remove(group_id).unwrap_or_else(|| {
+                debug!(?group_id, ?iteration);
+
+                let inner = Inner {
+                    session_timeout_ms: Default::default(),
+                    rebalance_timeout_ms: Default::default(),
+                    group_instance_id: Default::default(),
+                    members: Default::default(),
+                    generation_id: -1,
+                    state: Forming::default(),
+                    skip_assignment: Some(false),
+                    storage: self.storage.clone(),
+                };
+
+                (Wrapper::Forming(inner), None)
+            });
+
+            debug!(?group_id, ?wrapper, ?version, ?iteration);
+
+            let now = SystemTime::now();
+            let wrapper = wrapper.missed_heartbeat(group_id, now);
 
             let (wrapper, body) = wrapper
                 .heartbeat(now, group_id, generation_id, member_id, group_instance_id)
                 .await;
 
-            let wrapper = wrapper.missed_heartbeat(group_id, now);
-
             debug!(?group_id, ?wrapper, ?version, ?iteration);
 
             match self
@@ -1157,6 +1814,27 @@ where
             }
         }
     }
+
+    #[instrument(skip_all, fields(group_id, member_id))]
+    async fn consumer_group_heartbeat(
+        &mut self,
+        heartbeat: ConsumerGroupHeartbeat<'_>,
+    ) -> Result<Body> {
+        self.consumer_groups
+            .heartbeat(&heartbeat, SystemTime::now())
+            .await
+    }
+
+    #[instrument(skip_all)]
+    async fn consumer_group_describe(
+        &mut self,
+        group_ids: Option<&[String]>,
+        include_authorized_operations: bool,
+    ) -> Result<Body> {
+        self.consumer_groups
+            .describe(group_ids, include_authorized_operations)
+            .await
+    }
 }
 
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1164,6 +1842,8 @@ pub struct Forming {
     protocol_type: Option<String>,
     protocol_name: Option<String>,
     leader: Option<String>,
+    rebalance_started: Option<SystemTime>,
+    delayed_until: Option<SystemTime>,
 }
 
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -1174,6 +1854,24 @@ pub struct Formed {
     assignments: BTreeMap<String, Bytes>,
 }
 
+// a leave of a single member, before v3, only has a top level error code,
+// while a batch has an error code for each member
+fn leave_error_code(member_id: Option<&str>, members: &[MemberResponse]) -> i16 {
+    member_id
+        .and(members.first())
+        .map_or(ErrorCode::None.into(), |member| member.error_code)
+}
+
+// the committed offset of a partition, with a partition that has offsets
+// staged by an ongoing transaction being unstable
+fn stable(unstable: &BTreeSet<Topition>, topition: &Topition, offset: i64) -> (i64, ErrorCode) {
+    if unstable.contains(topition) {
+        (-1, ErrorCode::UnstableOffsetCommit)
+    } else {
+        (offset, ErrorCode::None)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Inner<O, S> {
     session_timeout_ms: i32,
@@ -1194,39 +1892,75 @@ where
         let original = self.members.len();
 
         self.members.retain(|member_id, member| {
-            member
-                .last_contact
-                .map(|last_contact| now.duration_since(last_contact).unwrap_or_default())
-                .inspect(|duration| {
-                    debug!(
-                        "{member_id}, since last contact: {}ms",
-                        duration.as_millis()
-                    )
-                })
-                .is_some_and(|duration| {
-                    if duration.as_millis()
-                        > u128::try_from(self.session_timeout_ms).unwrap_or(45_000)
-                    {
+            let Some(duration) = member.expired(self.session_timeout_ms, now) else {
+                return true;
+            };
 
-                        if self.state.leader.as_ref().is_some_and(|leader|leader == member_id){
-                            info!(
-                                "missed heartbeat for leader {member_id} for {group_id} in generation: {}, after {}ms",
-                                self.generation_id, duration.as_millis()
-                            );
+            if self
+                .state
+                .leader
+                .as_ref()
+                .is_some_and(|leader| leader == member_id)
+            {
+                info!(
+                    "missed heartbeat for leader {member_id} for {group_id} in generation: {}, after {}ms",
+                    self.generation_id,
+                    duration.as_millis()
+                );
 
-                            _ = self.state.leader.take();
-                        } else {
-                            info!(
-                                "missed heartbeat for {member_id} for {group_id} in generation: {}, after {}ms",
-                                self.generation_id, duration.as_millis()
-                            );
-                        }
+                _ = self.state.leader.take();
+            } else {
+                info!(
+                    "missed heartbeat for {member_id} for {group_id} in generation: {}, after {}ms",
+                    self.generation_id,
+                    duration.as_millis()
+                );
+            }
 
-                        false
-                    } else {
-                        true
-                    }
-                })
+            false
+        });
+
+        original > self.members.len()
+    }
+
+    fn missed_rebalance(&mut self, group_id: &str, now: SystemTime) -> bool {
+        let Some(started) = self.state.rebalance_started else {
+            return false;
+        };
+
+        let rebalance_timeout = self
+            .rebalance_timeout_ms
+            .unwrap_or(self.session_timeout_ms)
+            .try_into()
+            .map_or(Duration::from_millis(300_000), Duration::from_millis);
+
+        if now.duration_since(started).unwrap_or_default() <= rebalance_timeout {
+            return false;
+        }
+
+        let original = self.members.len();
+
+        self.members.retain(|member_id, member| {
+            if member.joined.is_some_and(|joined| joined >= started) {
+                return true;
+            }
+
+            if self
+                .state
+                .leader
+                .as_ref()
+                .is_some_and(|leader| leader == member_id)
+            {
+                _ = self.state.leader.take();
+            }
+
+            info!(
+                "{member_id} did not rejoin {group_id} in generation: {}, within {}ms",
+                self.generation_id,
+                rebalance_timeout.as_millis()
+            );
+
+            false
         });
 
         original > self.members.len()
@@ -1245,29 +1979,17 @@ where
         self.members.retain(|member_id, member| {
             debug!(?member_id, ?member);
 
-            member
-                .last_contact
-                .map(|last_contact| now.duration_since(last_contact).unwrap_or_default())
-                .inspect(|duration| {
-                    debug!(
-                        "{member_id}, since last contact: {}ms",
-                        duration.as_millis()
-                    )
-                })
-                .is_some_and(|duration| {
-                    if duration.as_millis()
-                        > u128::try_from(self.session_timeout_ms).unwrap_or(45_000)
-                    {
-                        info!(
-                            "missed heartbeat for {member_id} for {group_id} in generation: {}, after {}ms",
-                            self.generation_id, duration.as_millis()
-                        );
+            let Some(duration) = member.expired(self.session_timeout_ms, now) else {
+                return true;
+            };
 
-                        false
-                    } else {
-                        true
-                    }
-                })
+            info!(
+                "missed heartbeat for {member_id} for {group_id} in generation: {}, after {}ms",
+                self.generation_id,
+                duration.as_millis()
+            );
+
+            false
         });
 
         original > self.members.len()
@@ -1279,6 +2001,216 @@ where
     O: Storage,
     S: Debug,
 {
+    fn resume(&mut self, now: SystemTime) {
+        for member in self.members.values_mut() {
+            _ = member.last_contact.replace(now);
+        }
+    }
+
+    // the member currently registered for a static instance
+    fn static_member_id(&self, group_instance_id: &str) -> Option<&str> {
+        self.members
+            .iter()
+            .find(|(_, member)| {
+                member.join_response.group_instance_id.as_deref() == Some(group_instance_id)
+            })
+            .map(|(member_id, _)| member_id.as_str())
+    }
+
+    // a static instance that has since been registered by a different member
+    fn fenced(&self, member_id: &str, group_instance_id: Option<&str>) -> bool {
+        !member_id.is_empty()
+            && group_instance_id
+                .and_then(|group_instance_id| self.static_member_id(group_instance_id))
+                .is_some_and(|registered| registered != member_id)
+    }
+
+    // a new member id for a static instance, that takes over any existing
+    // membership of that instance, returning the member id it replaced
+    fn replace_static_member(
+        &mut self,
+        client_id: Option<&str>,
+        group_instance_id: &str,
+    ) -> (String, Option<String>) {
+        let member_id = format!(
+            "{}-{}",
+            client_id.unwrap_or(group_instance_id),
+            Uuid::new_v4()
+        );
+
+        let replaced = self
+            .static_member_id(group_instance_id)
+            .map(ToOwned::to_owned);
+
+        if let Some(mut member) = replaced
+            .as_deref()
+            .and_then(|replaced| self.members.remove(replaced))
+        {
+            info!("{group_instance_id} replaces {replaced:?} with {member_id}");
+
+            member.join_response.member_id = member_id.clone();
+            _ = self.members.insert(member_id.clone(), member);
+        }
+
+        (member_id, replaced)
+    }
+
+    // the protocol offered by a joining member that is most preferred by the
+    // members of this group, with ties kept by the current protocol, or none
+    // when the members have no protocol in common
+    fn select_protocol<'a>(
+        &self,
+        member_id: &str,
+        protocols: &'a [JoinGroupRequestProtocol],
+        current: Option<&str>,
+    ) -> Option<&'a JoinGroupRequestProtocol> {
+        let offered = self
+            .members
+            .iter()
+            .filter(|(id, _)| id.as_str() != member_id)
+            .filter_map(|(_, member)| member.protocols.as_deref())
+            .chain(iter::once(protocols))
+            .collect::<Vec<_>>();
+
+        let candidates = offered
+            .iter()
+            .map(|protocols| {
+                protocols
+                    .iter()
+                    .map(|protocol| protocol.name.as_str())
+                    .collect::<BTreeSet<_>>()
+            })
+            .reduce(|candidates, names| candidates.intersection(&names).copied().collect())
+            .unwrap_or_default();
+
+        // each member votes for its most preferred candidate
+        let mut votes = BTreeMap::new();
+
+        for protocols in offered {
+            if let Some(protocol) = protocols
+                .iter()
+                .find(|protocol| candidates.contains(protocol.name.as_str()))
+            {
+                *votes.entry(protocol.name.as_str()).or_insert(0) += 1;
+            }
+        }
+
+        debug!(member_id, ?candidates, ?votes, ?current);
+
+        protocols
+            .iter()
+            .enumerate()
+            .filter_map(|(position, protocol)| {
+                votes.get(protocol.name.as_str()).map(|votes| {
+                    (
+                        (
+                            *votes,
+                            current == Some(protocol.name.as_str()),
+                            Reverse(position),
+                        ),
+                        protocol,
+                    )
+                })
+            })
+            .max_by_key(|(preference, _)| *preference)
+            .map(|(_, protocol)| protocol)
+    }
+
+    // the metadata of each member for the protocol selected by the group
+    fn use_protocol(&mut self, protocol_name: &str) {
+        for member in self.members.values_mut() {
+            if let Some(protocol) = member
+                .protocols
+                .iter()
+                .flatten()
+                .find(|protocol| protocol.name == protocol_name)
+            {
+                member.join_response.metadata = protocol.metadata.clone();
+            }
+        }
+    }
+
+    // a member that isn't known, or a static member that has been replaced
+    fn unknown_member(
+        &self,
+        member_id: &str,
+        group_instance_id: Option<&str>,
+    ) -> Option<ErrorCode> {
+        if self.fenced(member_id, group_instance_id) {
+            Some(ErrorCode::FencedInstanceId)
+        } else if !self.members.contains_key(member_id) {
+            Some(ErrorCode::UnknownMemberId)
+        } else {
+            None
+        }
+    }
+
+    // remove each leaving member independently, with an error code for each
+    fn leave_members(
+        &mut self,
+        member_id: Option<&str>,
+        members: Option<&[MemberIdentity]>,
+    ) -> Vec<MemberResponse> {
+        if let Some(member_id) = member_id {
+            vec![MemberResponse {
+                member_id: member_id.to_owned(),
+                group_instance_id: None,
+                error_code: self.leave_member(member_id, None).into(),
+            }]
+        } else {
+            members.map_or(vec![], |members| {
+                members
+                    .iter()
+                    .map(|member| MemberResponse {
+                        member_id: member.member_id.clone(),
+                        group_instance_id: member.group_instance_id.clone(),
+                        error_code: self
+                            .leave_member(&member.member_id, member.group_instance_id.as_deref())
+                            .into(),
+                    })
+                    .collect()
+            })
+        }
+    }
+
+    // remove a leaving member, that may be identified by its static instance
+    fn leave_member(&mut self, member_id: &str, group_instance_id: Option<&str>) -> ErrorCode {
+        if self.fenced(member_id, group_instance_id) {
+            return ErrorCode::FencedInstanceId;
+        }
+
+        let member_id = if member_id.is_empty() {
+            group_instance_id
+                .and_then(|group_instance_id| self.static_member_id(group_instance_id))
+                .unwrap_or(member_id)
+                .to_owned()
+        } else {
+            member_id.to_owned()
+        };
+
+        if self.members.remove(&member_id).is_some() {
+            ErrorCode::None
+        } else {
+            ErrorCode::UnknownMemberId
+        }
+    }
+
+    // the partitions of a group with offsets staged by an ongoing
+    // transaction, when a stable offset is required
+    async fn unstable_offsets(
+        &mut self,
+        group_id: Option<&str>,
+        require_stable: Option<bool>,
+    ) -> tansu_storage::Result<BTreeSet<Topition>> {
+        match group_id {
+            Some(group_id) if require_stable.unwrap_or_default() => {
+                self.storage.txn_unstable_offsets(group_id).await
+            }
+
+            _ => Ok(BTreeSet::new()),
+        }
+    }
+
     async fn fetch_offset(
         &mut self,
         group_id: Option<&str>,
@@ -1287,6 +2219,8 @@ where
         require_stable: Option<bool>,
     ) -> Result<Body> {
         let topics = if let Some(topics) = topics {
+            let unstable = self.unstable_offsets(group_id, require_stable).await?;
+
             let topics: Vec<Topition> = topics
                 .iter()
                 .flat_map(|topic| {
@@ -1323,12 +2257,15 @@ where
                                     .iter()
                                     .filter_map(|(topition, offset)| {
                                         if topition.topic() == *topic_name {
+                                            let (committed_offset, error_code) =
+                                                stable(&unstable, topition, *offset);
+
                                             Some(OffsetFetchResponsePartition {
                                                 partition_index: topition.partition(),
-                                                committed_offset: *offset,
-                                                committed_leader_epoch: None,
+                                                committed_offset,
+                                                committed_leader_epoch: Some(-1),
                                                 metadata: None,
-                                                error_code: ErrorCode::None.into(),
+                                                error_code: error_code.into(),
                                             })
                                         } else {
                                             None
@@ -1337,7 +2274,7 @@ where
                                     .collect(),
                             ),
                         })
-                        .collect()
+                        .collect::<Vec<_>>()
                 })
                 .map(Some)?
         } else {
@@ -1348,26 +2285,37 @@ where
             let mut responses = vec![];
 
             for group in groups {
-                if let Some(topics) = group.topics.as_ref().map(|topics| {
-                    topics
-                        .iter()
-                        .flat_map(|topic| {
-                            topic
-                                .partition_indexes
-                                .as_ref()
-                                .map(|partition_indexes| {
-                                    partition_indexes
-                                        .iter()
-                                        .map(|partition_index| {
-                                            Topition::new(topic.name.clone(), *partition_index)
-                                        })
-                                        .collect::<Vec<_>>()
-                                })
-                                .unwrap_or_default()
-                        })
-                        .collect::<Vec<_>>()
-                }) {
-                    let response = self
+                let topics = group
+                    .topics
+                    .as_ref()
+                    .map(|topics| {
+                        topics
+                            .iter()
+                            .flat_map(|topic| {
+                                topic
+                                    .partition_indexes
+                                    .as_ref()
+                                    .map(|partition_indexes| {
+                                        partition_indexes
+                                            .iter()
+                                            .map(|partition_index| {
+                                                Topition::new(topic.name.clone(), *partition_index)
+                                            })
+                                            .collect::<Vec<_>>()
+                                    })
+                                    .unwrap_or_default()
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+
+                // each group is answered independently, a failure in
+                // one group does not fail the whole request
+                let fetched = match self
+                    .unstable_offsets(Some(group.group_id.as_str()), require_stable)
+                    .await
+                {
+                    Ok(unstable) => self
                         .storage
                         .offset_fetch(
                             Some(group.group_id.as_str()),
@@ -1375,62 +2323,99 @@ where
                             require_stable,
                         )
                         .await
-                        .map(|offsets| OffsetFetchResponseGroup {
-                            group_id: group.group_id.clone(),
-                            topics: Some(
-                                offsets
-                                    .iter()
-                                    .fold(BTreeSet::new(), |mut topics, (topition, _)| {
-                                        _ = topics.insert(topition.topic());
-                                        topics
-                                    })
-                                    .iter()
-                                    .map(|topic_name| OffsetFetchResponseTopics {
-                                        name: (*topic_name).into(),
-                                        partitions: Some(
-                                            offsets
-                                                .iter()
-                                                .filter_map(|(topition, offset)| {
-                                                    if topition.topic() == *topic_name {
-                                                        Some(OffsetFetchResponsePartitions {
-                                                            partition_index: topition.partition(),
-                                                            committed_offset: *offset,
-                                                            committed_leader_epoch: -1,
-                                                            metadata: None,
-                                                            error_code: ErrorCode::None.into(),
-                                                        })
-                                                    } else {
-                                                        None
-                                                    }
-                                                })
-                                                .collect(),
-                                        ),
-                                    })
-                                    .collect(),
-                            ),
-                            error_code: ErrorCode::None.into(),
-                        })?;
+                        .map(|offsets| (unstable, offsets)),
 
-                    responses.push(response);
-                }
-            }
+                    Err(error) => Err(error),
+                };
+
+                let response = match fetched {
+                    Ok((unstable, offsets)) => OffsetFetchResponseGroup {
+                        group_id: group.group_id.clone(),
+                        topics: Some(
+                            offsets
+                                .iter()
+                                .fold(BTreeSet::new(), |mut topics, (topition, _)| {
+                                    _ = topics.insert(topition.topic());
+                                    topics
+                                })
+                                .iter()
+                                .map(|topic_name| OffsetFetchResponseTopics {
+                                    name: (*topic_name).into(),
+                                    partitions: Some(
+                                        offsets
+                                            .iter()
+                                            .filter_map(|(topition, offset)| {
+                                                if topition.topic() == *topic_name {
+                                                    let (committed_offset, error_code) =
+                                                        stable(&unstable, topition, *offset);
+
+                                                    Some(OffsetFetchResponsePartitions {
+                                                        partition_index: topition.partition(),
+                                                        committed_offset,
+                                                        committed_leader_epoch: -1,
+                                                        metadata: None,
+                                                        error_code: error_code.into(),
+                                                    })
+                                                } else {
+                                                    None
+                                                }
+                                            })
+                                            .collect(),
+                                    ),
+                                })
+                                .collect(),
+                        ),
+                        error_code: ErrorCode::None.into(),
+                    },
+
+                    Err(error) => {
+                        warn!(group_id = group.group_id.as_str(), ?error);
+
+                        OffsetFetchResponseGroup {
+                            group_id: group.group_id.clone(),
+                            topics: Some([].into()),
+                            error_code: match error {
+                                tansu_storage::Error::Api(error_code) => error_code,
+                                _ => ErrorCode::UnknownServerError,
+                            }
+                            .into(),
+                        }
+                    }
+                };
+
+                responses.push(response);
+            }
 
             Some(responses)
         } else {
             None
         };
 
-        let body = Body::OffsetFetchResponse {
-            throttle_time_ms: Some(0),
-            topics,
-            error_code: Some(ErrorCode::None.into()),
-            groups,
+        let builder = OffsetFetchResponse::builder();
+
+        let builder = if let Some(topics) = topics {
+            builder.topics(topics)
+        } else {
+            builder
         };
 
-        Ok(body)
+        let builder = if let Some(groups) = groups {
+            builder.groups(groups)
+        } else {
+            builder
+        };
+
+        builder.build().map_err(Into::into)
     }
 
     async fn commit_offset(&mut self, detail: &OffsetCommit<'_>) -> Result<Body> {
+        if detail
+            .member_id
+            .is_some_and(|member_id| self.fenced(member_id, detail.group_instance_id))
+        {
+            return Err(Error::Api(ErrorCode::FencedInstanceId));
+        }
+
         let retention_time_ms = detail
             .retention_time_ms
             .map_or(Ok(None), |ms| {
@@ -1486,6 +2471,7 @@ where
                     Body::OffsetCommitResponse {
                         throttle_time_ms: Some(0),
                         topics: Some(topics),
+                        unknown_tagged_fields: vec![],
                     }
                 })
                 .map_err(Into::into)
@@ -1510,15 +2496,67 @@ where
                         })
                         .collect()
                 }),
+                unknown_tagged_fields: vec![],
             })
         }
     }
 }
 
+// the subscription in the metadata of a consumer protocol join, other
+// protocol types are opaque to the coordinator
+fn subscription(protocol_type: &str, metadata: &Bytes) -> Option<ConsumerProtocolSubscription> {
+    if protocol_type != consumer::PROTOCOL_TYPE {
+        return None;
+    }
+
+    ConsumerProtocolSubscription::try_from(metadata.clone())
+        .inspect_err(|err| warn!(?err, ?metadata))
+        .ok()
+}
+
+// the partitions assigned by the leader in a consumer protocol sync
+fn assignment(protocol_type: Option<&str>, assigned: &Bytes) -> Option<ConsumerProtocolAssignment> {
+    if protocol_type.is_some_and(|protocol_type| protocol_type != consumer::PROTOCOL_TYPE) {
+        return None;
+    }
+
+    ConsumerProtocolAssignment::try_from(assigned.clone())
+        .inspect_err(|err| warn!(?err, ?assigned))
+        .ok()
+}
+
 #[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Member {
     join_response: JoinGroupResponseMember,
     last_contact: Option<SystemTime>,
+    session_timeout_ms: Option<i32>,
+    joined: Option<SystemTime>,
+    client_id: Option<String>,
+    client_host: Option<String>,
+    protocols: Option<Vec<JoinGroupRequestProtocol>>,
+}
+
+impl Member {
+    /// The time since last contact, when that is longer than the session
+    /// timeout of this member, or that of the group when it joined without one.
+    fn expired(&self, session_timeout_ms: i32, now: SystemTime) -> Option<Duration> {
+        let session_timeout = self
+            .session_timeout_ms
+            .unwrap_or(session_timeout_ms)
+            .try_into()
+            .map_or(Duration::from_millis(45_000), Duration::from_millis);
+
+        self.last_contact
+            .map(|last_contact| now.duration_since(last_contact).unwrap_or_default())
+            .inspect(|duration| {
+                debug!(
+                    "{}, since last contact: {}ms",
+                    self.join_response.member_id,
+                    duration.as_millis()
+                )
+            })
+            .filter(|duration| *duration > session_timeout)
+    }
 }
 
 #[async_trait::async_trait]
@@ -1537,6 +2575,7 @@ where
         mut self,
         now: SystemTime,
         client_id: Option<&str>,
+        client_host: Option<&str>,
         group_id: &str,
         session_timeout_ms: i32,
         rebalance_timeout_ms: Option<i32>,
@@ -1559,40 +2598,103 @@ where
                 skip_assignment: self.skip_assignment,
                 member_id: "".into(),
                 members: Some([].into()),
+                unknown_tagged_fields: vec![],
             };
 
             return (self, body);
         };
 
-        let protocol = if let Some(protocol_name) = self.state.protocol_name.as_deref() {
-            if let Some(protocol) = protocols
-                .iter()
-                .find(|protocol| protocol.name == protocol_name)
-            {
-                protocol
-            } else {
-                let body = Body::JoinGroupResponse {
-                    throttle_time_ms: Some(0),
-                    error_code: ErrorCode::InconsistentGroupProtocol.into(),
-                    generation_id: self.generation_id,
-                    protocol_type: Some(protocol_type.into()),
-                    protocol_name: self.state.protocol_name.clone(),
-                    leader: "".into(),
-                    skip_assignment: self.skip_assignment,
-                    member_id: "".into(),
-                    members: Some([].into()),
-                };
+        // a group only has members of the same protocol type
+        let inconsistent_type = self
+            .state
+            .protocol_type
+            .as_deref()
+            .is_some_and(|existing| existing != protocol_type)
+            && self.members.keys().any(|id| id != member_id);
+
+        let Some(protocol) = (!inconsistent_type)
+            .then(|| {
+                self.select_protocol(member_id, protocols, self.state.protocol_name.as_deref())
+            })
+            .flatten()
+        else {
+            let body = Body::JoinGroupResponse {
+                throttle_time_ms: Some(0),
+                error_code: ErrorCode::InconsistentGroupProtocol.into(),
+                generation_id: self.generation_id,
+                protocol_type: Some(protocol_type.into()),
+                protocol_name: self.state.protocol_name.clone(),
+                leader: "".into(),
+                skip_assignment: self.skip_assignment,
+                member_id: "".into(),
+                members: Some([].into()),
+                unknown_tagged_fields: vec![],
+            };
 
-                return (self, body);
-            }
-        } else {
-            self.state.protocol_type = Some(protocol_type.to_owned());
-            self.state.protocol_name = Some(protocols[0].name.as_str().to_owned());
+            return (self, body);
+        };
 
+        if self.state.protocol_name.is_none() {
             self.session_timeout_ms = session_timeout_ms;
             self.rebalance_timeout_ms = rebalance_timeout_ms;
+        }
+
+        self.state.protocol_type = Some(protocol_type.to_owned());
+
+        if self.state.protocol_name.as_deref() != Some(protocol.name.as_str()) {
+            debug!(?self.state.protocol_name, selected = protocol.name);
+
+            self.use_protocol(&protocol.name);
+            _ = self.state.protocol_name.replace(protocol.name.clone());
+        }
+
+        // the rebalance timeout runs from the join that started this rebalance
+        _ = self.state.rebalance_started.get_or_insert(now);
+
+        if let Some(subscription) = subscription(protocol_type, &protocol.metadata) {
+            debug!(
+                member_id,
+                topics = ?subscription.topics,
+                owned = ?subscription.owned_partitions,
+                generation_id = subscription.generation_id,
+            );
+        }
+
+        if self.fenced(member_id, group_instance_id) {
+            let body = Body::JoinGroupResponse {
+                throttle_time_ms: Some(0),
+                error_code: ErrorCode::FencedInstanceId.into(),
+                generation_id: self.generation_id,
+                protocol_type: Some(protocol_type.into()),
+                protocol_name: Some("".into()),
+                leader: "".into(),
+                skip_assignment: self.skip_assignment,
+                member_id: member_id.into(),
+                members: Some([].into()),
+                unknown_tagged_fields: vec![],
+            };
+
+            return (self, body);
+        }
+
+        // a static member rejoining takes over its existing membership
+        let static_member_id;
+        let member_id = match group_instance_id {
+            Some(group_instance_id) if member_id.is_empty() => {
+                let (replacement, replaced) =
+                    self.replace_static_member(client_id, group_instance_id);
+
+                if let Some(replaced) = replaced {
+                    if self.state.leader.as_deref() == Some(replaced.as_str()) {
+                        _ = self.state.leader.replace(replacement.clone());
+                    }
+                }
 
-            &protocols[0]
+                static_member_id = replacement;
+                static_member_id.as_str()
+            }
+
+            _ => member_id,
         };
 
         if let Some(client_id) = client_id {
@@ -1610,6 +2712,7 @@ where
                     skip_assignment: self.skip_assignment,
                     member_id: member_id.clone(),
                     members: Some([].into()),
+                    unknown_tagged_fields: vec![],
                 };
 
                 _ = self.members.insert(
@@ -1621,6 +2724,11 @@ where
                             metadata: protocol.metadata.clone(),
                         },
                         last_contact: Some(now),
+                        session_timeout_ms: Some(session_timeout_ms),
+                        joined: Some(now),
+                        client_id: Some(client_id.to_owned()),
+                        client_host: client_host.map(ToOwned::to_owned),
+                        protocols: Some(protocols.to_vec()),
                     },
                 );
 
@@ -1641,6 +2749,11 @@ where
                     metadata: protocol.metadata.clone(),
                 },
                 last_contact: Some(now),
+                session_timeout_ms: Some(session_timeout_ms),
+                joined: Some(now),
+                client_id: client_id.map(ToOwned::to_owned),
+                client_host: client_host.map(ToOwned::to_owned),
+                protocols: Some(protocols.to_vec()),
             },
         ) {
             Some(Member {
@@ -1714,6 +2827,7 @@ where
                         [].into()
                     },
                 ),
+                unknown_tagged_fields: vec![],
             }
         };
 
@@ -1732,17 +2846,17 @@ where
         assignments: Option<&[SyncGroupRequestAssignment]>,
     ) -> (Self::SyncState, Body) {
         let _ = group_id;
-        let _ = group_instance_id;
         let _ = protocol_type;
         let _ = protocol_name;
 
-        if !self.members.contains_key(member_id) {
+        if let Some(error_code) = self.unknown_member(member_id, group_instance_id) {
             let body = Body::SyncGroupResponse {
                 throttle_time_ms: Some(0),
-                error_code: ErrorCode::UnknownMemberId.into(),
+                error_code: error_code.into(),
                 protocol_type: self.state.protocol_type.clone(),
                 protocol_name: self.state.protocol_name.clone(),
                 assignment: Bytes::from_static(b""),
+                unknown_tagged_fields: vec![],
             };
 
             return (self.into(), body);
@@ -1757,6 +2871,7 @@ where
                 protocol_type: self.state.protocol_type.clone(),
                 protocol_name: self.state.protocol_name.clone(),
                 assignment: Bytes::from_static(b""),
+                unknown_tagged_fields: vec![],
             };
 
             return (self.into(), body);
@@ -1769,6 +2884,7 @@ where
                 protocol_type: self.state.protocol_type.clone(),
                 protocol_name: self.state.protocol_name.clone(),
                 assignment: Bytes::from_static(b""),
+                unknown_tagged_fields: vec![],
             };
 
             return (self.into(), body);
@@ -1791,6 +2907,7 @@ where
                 protocol_type: self.state.protocol_type.clone(),
                 protocol_name: self.state.protocol_name.clone(),
                 assignment: Bytes::from_static(b""),
+                unknown_tagged_fields: vec![],
             };
 
             return (self.into(), body);
@@ -1803,6 +2920,7 @@ where
                 protocol_type: self.state.protocol_type.clone(),
                 protocol_name: self.state.protocol_name.clone(),
                 assignment: Bytes::from_static(b""),
+                unknown_tagged_fields: vec![],
             };
 
             return (self.into(), body);
@@ -1817,6 +2935,12 @@ where
 
         debug!(?assignments);
 
+        for (member_id, assigned) in &assignments {
+            if let Some(assignment) = assignment(self.state.protocol_type.as_deref(), assigned) {
+                debug!(member_id, assigned = ?assignment.assigned_partitions);
+            }
+        }
+
         let body = Body::SyncGroupResponse {
             throttle_time_ms: Some(0),
             error_code: ErrorCode::None.into(),
@@ -1826,6 +2950,7 @@ where
                 .get(member_id)
                 .cloned()
                 .unwrap_or(Bytes::from_static(b"")),
+            unknown_tagged_fields: vec![],
         };
 
         let state = Inner {
@@ -1865,24 +2990,39 @@ where
             ?group_instance_id
         );
 
-        let _ = group_instance_id;
-
-        if !self.members.contains_key(member_id) {
+        if let Some(error_code) = self.unknown_member(member_id, group_instance_id) {
             return (
                 self,
                 Body::HeartbeatResponse {
                     throttle_time_ms: Some(0),
-                    error_code: ErrorCode::UnknownMemberId.into(),
+                    error_code: error_code.into(),
+                    unknown_tagged_fields: vec![],
                 },
             );
         }
 
+        // a member ahead of a group that was persisted before a restart
+        // rejoins a generation that is newer than either
         if generation_id > self.generation_id {
+            info!(
+                "{member_id} is ahead of: {group_id} in generation: {generation_id}, rather than: {}",
+                self.generation_id
+            );
+
+            self.generation_id = generation_id + 1;
+            _ = self.state.rebalance_started.get_or_insert(now);
+
+            _ = self
+                .members
+                .entry(member_id.to_owned())
+                .and_modify(|member| _ = member.last_contact.replace(now));
+
             return (
                 self,
                 Body::HeartbeatResponse {
                     throttle_time_ms: Some(0),
-                    error_code: ErrorCode::IllegalGeneration.into(),
+                    error_code: ErrorCode::RebalanceInProgress.into(),
+                    unknown_tagged_fields: vec![],
                 },
             );
         }
@@ -1892,19 +3032,13 @@ where
             .entry(member_id.to_owned())
             .and_modify(|member| _ = member.last_contact.replace(now));
 
-        if self.missed_heartbeat(group_id, now) || (generation_id < self.generation_id) {
-            return (
-                self,
-                Body::HeartbeatResponse {
-                    throttle_time_ms: Some(0),
-                    error_code: ErrorCode::RebalanceInProgress.into(),
-                },
-            );
-        }
+        _ = self.missed_heartbeat(group_id, now);
 
+        // a member heartbeating while the group is forming must (re)join
         let body = Body::HeartbeatResponse {
             throttle_time_ms: Some(0),
-            error_code: ErrorCode::None.into(),
+            error_code: ErrorCode::RebalanceInProgress.into(),
+            unknown_tagged_fields: vec![],
         };
 
         (self, body)
@@ -1918,38 +3052,8 @@ where
         members: Option<&[MemberIdentity]>,
     ) -> (Self::LeaveState, Body) {
         let _ = group_id;
-        let _ = now;
 
-        let members = if let Some(member_id) = member_id {
-            vec![MemberResponse {
-                member_id: member_id.to_owned(),
-                group_instance_id: None,
-                error_code: {
-                    if self.members.remove(member_id).is_some() {
-                        ErrorCode::None.into()
-                    } else {
-                        ErrorCode::UnknownMemberId.into()
-                    }
-                },
-            }]
-        } else {
-            members.map_or(vec![], |members| {
-                members
-                    .iter()
-                    .map(|member| MemberResponse {
-                        member_id: member.member_id.clone(),
-                        group_instance_id: member.group_instance_id.clone(),
-                        error_code: {
-                            if self.members.remove(&member.member_id).is_some() {
-                                ErrorCode::None.into()
-                            } else {
-                                ErrorCode::UnknownMemberId.into()
-                            }
-                        },
-                    })
-                    .collect::<Vec<MemberResponse>>()
-            })
-        };
+        let members = self.leave_members(member_id, members);
 
         if members.iter().any(|member| {
             let error_code = i16::from(ErrorCode::None);
@@ -1957,14 +3061,23 @@ where
             member.error_code == error_code
         }) {
             self.generation_id += 1;
-        }
+            _ = self.state.rebalance_started.replace(now);
 
-        let body = {
-            Body::LeaveGroupResponse {
-                throttle_time_ms: Some(0),
-                error_code: ErrorCode::None.into(),
-                members: Some(members),
+            if self
+                .state
+                .leader
+                .as_ref()
+                .is_some_and(|leader| !self.members.contains_key(leader))
+            {
+                _ = self.state.leader.take();
             }
+        }
+
+        let body = Body::LeaveGroupResponse {
+            throttle_time_ms: Some(0),
+            error_code: leave_error_code(member_id, &members),
+            members: Some(members),
+            unknown_tagged_fields: vec![],
         };
 
         (self, body)
@@ -1975,12 +3088,24 @@ where
         now: SystemTime,
         detail: &OffsetCommit<'_>,
     ) -> (Self::OffsetCommitState, Body) {
-        let _ = now;
+        // a commit is as good as a heartbeat for the session of a member
+        if let Some(member_id) = detail.member_id {
+            _ = self
+                .members
+                .entry(member_id.to_owned())
+                .and_modify(|member| _ = member.last_contact.replace(now));
+        }
 
         match self.commit_offset(detail).await {
             Ok(body) => (self, body),
             Err(reason) => {
                 debug!(?reason);
+
+                let error_code = match reason {
+                    Error::Api(error_code) => error_code,
+                    _ => ErrorCode::UnknownMemberId,
+                };
+
                 (
                     self,
                     Body::OffsetCommitResponse {
@@ -1996,13 +3121,14 @@ where
                                             .iter()
                                             .map(|partition| OffsetCommitResponsePartition {
                                                 partition_index: partition.partition_index,
-                                                error_code: ErrorCode::UnknownMemberId.into(),
+                                                error_code: error_code.into(),
                                             })
                                             .collect()
                                     }),
                                 })
                                 .collect()
                         }),
+                        unknown_tagged_fields: vec![],
                     },
                 )
             }
@@ -2025,7 +3151,14 @@ where
             Ok(body) => (self, body),
             Err(error) => {
                 debug!(?error);
-                todo!()
+
+                let error_code = match error {
+                    Error::Api(error_code)
+                    | Error::Storage(tansu_storage::Error::Api(error_code)) => error_code,
+                    _ => ErrorCode::UnknownServerError,
+                };
+
+                (self, offset_fetch_error(topics, groups, error_code))
             }
         }
     }
@@ -2038,7 +3171,7 @@ where
 {
     type JoinState = Wrapper<O>;
     type SyncState = Inner<O, Formed>;
-    type HeartbeatState = Inner<O, Formed>;
+    type HeartbeatState = Wrapper<O>;
     type LeaveState = Wrapper<O>;
     type OffsetCommitState = Inner<O, Formed>;
     type OffsetFetchState = Inner<O, Formed>;
@@ -2047,6 +3180,7 @@ where
         mut self,
         now: SystemTime,
         client_id: Option<&str>,
+        client_host: Option<&str>,
         group_id: &str,
         session_timeout_ms: i32,
         rebalance_timeout_ms: Option<i32>,
@@ -2057,7 +3191,6 @@ where
         reason: Option<&str>,
     ) -> (Self::JoinState, Body) {
         let _ = group_id;
-        let _ = session_timeout_ms;
         let _ = rebalance_timeout_ms;
         let _ = reason;
 
@@ -2072,14 +3205,15 @@ where
                 skip_assignment: self.skip_assignment,
                 member_id: "".into(),
                 members: Some([].into()),
+                unknown_tagged_fields: vec![],
             };
 
             return (self.into(), body);
         };
 
-        let Some(protocol) = protocols
-            .iter()
-            .find(|protocol| protocol.name == self.state.protocol_name)
+        let Some(protocol) = (self.state.protocol_type == protocol_type)
+            .then(|| self.select_protocol(member_id, protocols, Some(&self.state.protocol_name)))
+            .flatten()
         else {
             let body = Body::JoinGroupResponse {
                 throttle_time_ms: Some(0),
@@ -2091,9 +3225,73 @@ where
                 skip_assignment: self.skip_assignment,
                 member_id: "".into(),
                 members: Some([].into()),
+                unknown_tagged_fields: vec![],
+            };
+
+            return (self.into(), body);
+        };
+
+        // a change of protocol requires a rebalance
+        let reselected = protocol.name != self.state.protocol_name;
+
+        if reselected {
+            debug!(self.state.protocol_name, selected = protocol.name);
+
+            self.use_protocol(&protocol.name);
+            self.state.protocol_name = protocol.name.clone();
+        }
+
+        if let Some(subscription) = subscription(protocol_type, &protocol.metadata) {
+            debug!(
+                member_id,
+                topics = ?subscription.topics,
+                owned = ?subscription.owned_partitions,
+                generation_id = subscription.generation_id,
+            );
+        }
+
+        if self.fenced(member_id, group_instance_id) {
+            let body = Body::JoinGroupResponse {
+                throttle_time_ms: Some(0),
+                error_code: ErrorCode::FencedInstanceId.into(),
+                generation_id: self.generation_id,
+                protocol_type: Some(protocol_type.into()),
+                protocol_name: Some("".into()),
+                leader: "".into(),
+                skip_assignment: self.skip_assignment,
+                member_id: member_id.into(),
+                members: Some([].into()),
+                unknown_tagged_fields: vec![],
             };
 
             return (self.into(), body);
+        }
+
+        // a static member rejoining takes over its existing membership
+        let static_member_id;
+        let member_id = match group_instance_id {
+            Some(group_instance_id) if member_id.is_empty() => {
+                let (replacement, replaced) =
+                    self.replace_static_member(client_id, group_instance_id);
+
+                if let Some(replaced) = replaced {
+                    if self.state.leader == replaced {
+                        self.state.leader = replacement.clone();
+                    }
+
+                    if let Some(assignment) = self.state.assignments.remove(&replaced) {
+                        _ = self
+                            .state
+                            .assignments
+                            .insert(replacement.clone(), assignment);
+                    }
+                }
+
+                static_member_id = replacement;
+                static_member_id.as_str()
+            }
+
+            _ => member_id,
         };
 
         if let Some(client_id) = client_id {
@@ -2110,6 +3308,7 @@ where
                     skip_assignment: self.skip_assignment,
                     member_id: member_id.clone(),
                     members: Some([].into()),
+                    unknown_tagged_fields: vec![],
                 };
 
                 _ = self.members.insert(
@@ -2121,6 +3320,11 @@ where
                             metadata: protocol.metadata.clone(),
                         },
                         last_contact: Some(now),
+                        session_timeout_ms: Some(session_timeout_ms),
+                        joined: Some(now),
+                        client_id: Some(client_id.to_owned()),
+                        client_host: client_host.map(ToOwned::to_owned),
+                        protocols: Some(protocols.to_vec()),
                     },
                 );
 
@@ -2135,6 +3339,8 @@ where
                             protocol_type: Some(self.state.protocol_type),
                             protocol_name: Some(self.state.protocol_name),
                             leader: Some(self.state.leader),
+                            rebalance_started: Some(now),
+                            delayed_until: None,
                         },
                         storage: self.storage,
                         skip_assignment: self.skip_assignment,
@@ -2154,12 +3360,17 @@ where
                     metadata: protocol.metadata.clone(),
                 },
                 last_contact: Some(now),
+                session_timeout_ms: Some(session_timeout_ms),
+                joined: Some(now),
+                client_id: client_id.map(ToOwned::to_owned),
+                client_host: client_host.map(ToOwned::to_owned),
+                protocols: Some(protocols.to_vec()),
             },
         ) {
             Some(Member {
                 join_response: JoinGroupResponseMember { metadata, .. },
                 ..
-            }) if metadata == protocol.metadata => {
+            }) if metadata == protocol.metadata && !reselected => {
                 let state: Wrapper<O> = self.into();
 
                 let body = {
@@ -2186,6 +3397,7 @@ where
                         skip_assignment: state.skip_assignment().map(ToOwned::to_owned),
                         member_id: member_id.into(),
                         members,
+                        unknown_tagged_fields: vec![],
                     }
                 };
 
@@ -2214,6 +3426,8 @@ where
                         protocol_type: Some(self.state.protocol_type),
                         protocol_name: Some(self.state.protocol_name),
                         leader: Some(self.state.leader),
+                        rebalance_started: Some(now),
+                        delayed_until: None,
                     },
                     storage: self.storage,
                     skip_assignment: self.skip_assignment,
@@ -2244,6 +3458,7 @@ where
                         skip_assignment: self.skip_assignment,
                         member_id: member_id.into(),
                         members,
+                        unknown_tagged_fields: vec![],
                     }
                 };
 
@@ -2267,6 +3482,8 @@ where
                         protocol_type: Some(self.state.protocol_type),
                         protocol_name: Some(self.state.protocol_name),
                         leader: Some(self.state.leader),
+                        rebalance_started: Some(now),
+                        delayed_until: None,
                     },
                     storage: self.storage,
                     skip_assignment: self.skip_assignment,
@@ -2298,6 +3515,7 @@ where
                         skip_assignment: self.skip_assignment,
                         member_id: member_id.into(),
                         members,
+                        unknown_tagged_fields: vec![],
                     }
                 };
 
@@ -2318,18 +3536,18 @@ where
         assignments: Option<&[SyncGroupRequestAssignment]>,
     ) -> (Self::SyncState, Body) {
         let _ = group_id;
-        let _ = group_instance_id;
         let _ = protocol_type;
         let _ = protocol_name;
         let _ = assignments;
 
-        if !self.members.contains_key(member_id) {
+        if let Some(error_code) = self.unknown_member(member_id, group_instance_id) {
             let body = Body::SyncGroupResponse {
                 throttle_time_ms: Some(0),
-                error_code: ErrorCode::UnknownMemberId.into(),
+                error_code: error_code.into(),
                 protocol_type: Some(self.state.protocol_type.clone()),
                 protocol_name: Some(self.state.protocol_name.clone()),
                 assignment: Bytes::from_static(b""),
+                unknown_tagged_fields: vec![],
             };
 
             return (self, body);
@@ -2344,18 +3562,21 @@ where
                 protocol_type: Some(self.state.protocol_type.clone()),
                 protocol_name: Some(self.state.protocol_name.clone()),
                 assignment: Bytes::from_static(b""),
+                unknown_tagged_fields: vec![],
             };
 
             return (self, body);
         }
 
+        // a member of an earlier generation has missed a rebalance
         if generation_id < self.generation_id {
             let body = Body::SyncGroupResponse {
                 throttle_time_ms: Some(0),
-                error_code: ErrorCode::RebalanceInProgress.into(),
+                error_code: ErrorCode::IllegalGeneration.into(),
                 protocol_type: Some(self.state.protocol_type.clone()),
                 protocol_name: Some(self.state.protocol_name.clone()),
                 assignment: Bytes::from_static(b""),
+                unknown_tagged_fields: vec![],
             };
 
             return (self, body);
@@ -2377,6 +3598,7 @@ where
                 .get(member_id)
                 .cloned()
                 .unwrap_or(Bytes::from_static(b"")),
+            unknown_tagged_fields: vec![],
         };
 
         (self, body)
@@ -2392,32 +3614,75 @@ where
     ) -> (Self::HeartbeatState, Body) {
         debug!(?group_id, ?generation_id, ?member_id, ?group_instance_id);
 
-        if !self.members.contains_key(member_id) {
+        if let Some(error_code) = self.unknown_member(member_id, group_instance_id) {
             return (
-                self,
+                self.into(),
                 Body::HeartbeatResponse {
                     throttle_time_ms: Some(0),
-                    error_code: ErrorCode::UnknownMemberId.into(),
+                    error_code: error_code.into(),
+                    unknown_tagged_fields: vec![],
                 },
             );
         }
 
+        // a member ahead of a group that was persisted before a restart
+        // rejoins a generation that is newer than either
         if generation_id > self.generation_id {
+            info!(
+                "{member_id} is ahead of: {group_id} in generation: {generation_id}, rather than: {}",
+                self.generation_id
+            );
+
+            _ = self
+                .members
+                .entry(member_id.to_owned())
+                .and_modify(|member| _ = member.last_contact.replace(now));
+
             return (
-                self,
+                Inner {
+                    generation_id: generation_id + 1,
+                    session_timeout_ms: self.session_timeout_ms,
+                    rebalance_timeout_ms: self.rebalance_timeout_ms,
+                    group_instance_id: self.group_instance_id,
+                    members: self.members,
+                    state: Forming {
+                        protocol_type: Some(self.state.protocol_type),
+                        protocol_name: Some(self.state.protocol_name),
+                        leader: Some(self.state.leader),
+                        rebalance_started: Some(now),
+                        delayed_until: None,
+                    },
+                    storage: self.storage,
+                    skip_assignment: self.skip_assignment,
+                }
+                .into(),
+                Body::HeartbeatResponse {
+                    throttle_time_ms: Some(0),
+                    error_code: ErrorCode::RebalanceInProgress.into(),
+                    unknown_tagged_fields: vec![],
+                },
+            );
+        }
+
+        // a member of an earlier generation has missed a rebalance
+        if generation_id < self.generation_id {
+            return (
+                self.into(),
                 Body::HeartbeatResponse {
                     throttle_time_ms: Some(0),
                     error_code: ErrorCode::IllegalGeneration.into(),
+                    unknown_tagged_fields: vec![],
                 },
             );
         }
 
-        if self.missed_heartbeat(group_id, now) || (generation_id < self.generation_id) {
+        if self.missed_heartbeat(group_id, now) {
             return (
-                self,
+                self.into(),
                 Body::HeartbeatResponse {
                     throttle_time_ms: Some(0),
                     error_code: ErrorCode::RebalanceInProgress.into(),
+                    unknown_tagged_fields: vec![],
                 },
             );
         }
@@ -2430,9 +3695,10 @@ where
         let body = Body::HeartbeatResponse {
             throttle_time_ms: Some(0),
             error_code: ErrorCode::None.into(),
+            unknown_tagged_fields: vec![],
         };
 
-        (self, body)
+        (self.into(), body)
     }
 
     async fn leave(
@@ -2442,39 +3708,9 @@ where
         member_id: Option<&str>,
         members: Option<&[MemberIdentity]>,
     ) -> (Self::LeaveState, Body) {
-        let _ = now;
         let _ = group_id;
 
-        let members = if let Some(member_id) = member_id {
-            vec![MemberResponse {
-                member_id: member_id.to_owned(),
-                group_instance_id: None,
-                error_code: {
-                    if self.members.remove(member_id).is_some() {
-                        ErrorCode::None.into()
-                    } else {
-                        ErrorCode::UnknownMemberId.into()
-                    }
-                },
-            }]
-        } else {
-            members.map_or(vec![], |members| {
-                members
-                    .iter()
-                    .map(|member| MemberResponse {
-                        member_id: member.member_id.clone(),
-                        group_instance_id: member.group_instance_id.clone(),
-                        error_code: {
-                            if self.members.remove(&member.member_id).is_some() {
-                                ErrorCode::None.into()
-                            } else {
-                                ErrorCode::UnknownMemberId.into()
-                            }
-                        },
-                    })
-                    .collect::<Vec<MemberResponse>>()
-            })
-        };
+        let members = self.leave_members(member_id, members);
 
         let state: Wrapper<O> = if members
             .iter()
@@ -2496,6 +3732,8 @@ where
                     protocol_type: Some(self.state.protocol_type),
                     protocol_name: Some(self.state.protocol_name),
                     leader,
+                    rebalance_started: Some(now),
+                    delayed_until: None,
                 },
                 storage: self.storage,
                 skip_assignment: self.skip_assignment,
@@ -2505,12 +3743,11 @@ where
             self.into()
         };
 
-        let body = {
-            Body::LeaveGroupResponse {
-                throttle_time_ms: Some(0),
-                error_code: ErrorCode::None.into(),
-                members: Some(members),
-            }
+        let body = Body::LeaveGroupResponse {
+            throttle_time_ms: Some(0),
+            error_code: leave_error_code(member_id, &members),
+            members: Some(members),
+            unknown_tagged_fields: vec![],
         };
 
         (state, body)
@@ -2521,12 +3758,24 @@ where
         now: SystemTime,
         detail: &OffsetCommit<'_>,
     ) -> (Self::OffsetCommitState, Body) {
-        let _ = now;
+        // a commit is as good as a heartbeat for the session of a member
+        if let Some(member_id) = detail.member_id {
+            _ = self
+                .members
+                .entry(member_id.to_owned())
+                .and_modify(|member| _ = member.last_contact.replace(now));
+        }
 
         match self.commit_offset(detail).await {
             Ok(body) => (self, body),
             Err(reason) => {
                 debug!(?reason);
+
+                let error_code = match reason {
+                    Error::Api(error_code) => error_code,
+                    _ => ErrorCode::UnknownMemberId,
+                };
+
                 (
                     self,
                     Body::OffsetCommitResponse {
@@ -2542,13 +3791,14 @@ where
                                             .iter()
                                             .map(|partition| OffsetCommitResponsePartition {
                                                 partition_index: partition.partition_index,
-                                                error_code: ErrorCode::UnknownMemberId.into(),
+                                                error_code: error_code.into(),
                                             })
                                             .collect()
                                     }),
                                 })
                                 .collect()
                         }),
+                        unknown_tagged_fields: vec![],
                     },
                 )
             }
@@ -2572,7 +3822,14 @@ where
             Ok(body) => (self, body),
             Err(error) => {
                 debug!(?error);
-                todo!()
+
+                let error_code = match error {
+                    Error::Api(error_code)
+                    | Error::Storage(tansu_storage::Error::Api(error_code)) => error_code,
+                    _ => ErrorCode::UnknownServerError,
+                };
+
+                (self, offset_fetch_error(topics, groups, error_code))
             }
         }
     }
@@ -2587,6 +3844,7 @@ mod tests {
         OffsetCommitRequestPartition, OffsetCommitRequestTopic,
     };
     use tansu_storage::dynostore::DynoStore;
+    use tokio::time::Instant;
     use tracing::subscriber::DefaultGuard;
 
     #[cfg(miri)]
@@ -2661,6 +3919,7 @@ mod tests {
         let first_member_id = match s
             .join(
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -2682,6 +3941,7 @@ mod tests {
                 skip_assignment: Some(false),
                 members: Some(members),
                 member_id,
+                ..
             } => {
                 assert_eq!(error_code, i16::from(ErrorCode::MemberIdRequired));
                 assert_eq!("consumer", protocol_type);
@@ -2693,6 +3953,7 @@ mod tests {
                 let join_response = s
                     .join(
                         Some(CLIENT_ID),
+                        None,
                         GROUP_ID,
                         session_timeout_ms,
                         rebalance_timeout_ms,
@@ -2721,6 +3982,7 @@ mod tests {
                         }]
                         .into(),
                     ),
+                    unknown_tagged_fields: vec![],
                 };
 
                 assert_eq!(join_response_expected, join_response);
@@ -2741,10 +4003,11 @@ mod tests {
         assert_eq!(
             Body::SyncGroupResponse {
                 throttle_time_ms: Some(0),
-                error_code: 0,
+                error_code: ErrorCode::None.code(),
                 protocol_type: Some(PROTOCOL_TYPE.into()),
                 protocol_name: Some(RANGE.into()),
                 assignment: first_member_assignment_01,
+                unknown_tagged_fields: vec![],
             },
             s.sync(
                 GROUP_ID,
@@ -2762,6 +4025,7 @@ mod tests {
             Body::HeartbeatResponse {
                 throttle_time_ms: Some(0),
                 error_code: ErrorCode::None.into(),
+                unknown_tagged_fields: vec![],
             },
             s.heartbeat(GROUP_ID, 0, &first_member_id, group_instance_id)
                 .await?
@@ -2784,6 +4048,7 @@ mod tests {
         let second_member_id = match s
             .join(
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -2805,6 +4070,7 @@ mod tests {
                 skip_assignment: Some(false),
                 members: Some(members),
                 member_id,
+                ..
             } => {
                 assert_eq!(error_code, i16::from(ErrorCode::MemberIdRequired));
                 assert_eq!("", protocol_name);
@@ -2815,6 +4081,7 @@ mod tests {
                 let join_response = s
                     .join(
                         Some(CLIENT_ID),
+                        None,
                         GROUP_ID,
                         session_timeout_ms,
                         rebalance_timeout_ms,
@@ -2836,6 +4103,7 @@ mod tests {
                     skip_assignment: Some(false),
                     member_id: member_id.clone(),
                     members: Some([].into()),
+                    unknown_tagged_fields: vec![],
                 };
 
                 assert_eq!(join_response_expected, join_response);
@@ -2850,6 +4118,7 @@ mod tests {
             Body::HeartbeatResponse {
                 throttle_time_ms: Some(0),
                 error_code: i16::from(ErrorCode::RebalanceInProgress),
+                unknown_tagged_fields: vec![],
             },
             s.heartbeat(GROUP_ID, 0, &first_member_id, group_instance_id,)
                 .await?
@@ -2872,6 +4141,7 @@ mod tests {
                     }]
                     .into()
                 ),
+                unknown_tagged_fields: vec![],
             },
             s.offset_commit(OffsetCommit {
                 group_id: GROUP_ID,
@@ -2915,6 +4185,7 @@ mod tests {
             match s
                 .join(
                     Some(CLIENT_ID),
+                    None,
                     GROUP_ID,
                     session_timeout_ms,
                     rebalance_timeout_ms,
@@ -2936,6 +4207,7 @@ mod tests {
                     skip_assignment: Some(false),
                     member_id,
                     members: Some(members),
+                    ..
                 } => {
                     assert_eq!(i16::from(ErrorCode::None), error_code);
                     assert_eq!(2, generation_id);
@@ -2980,6 +4252,7 @@ mod tests {
             match s
                 .join(
                     Some(CLIENT_ID),
+                    None,
                     GROUP_ID,
                     session_timeout_ms,
                     rebalance_timeout_ms,
@@ -3001,6 +4274,7 @@ mod tests {
                     skip_assignment: Some(false),
                     member_id,
                     members: Some(members),
+                    ..
                 } => {
                     assert_eq!(i16::from(ErrorCode::None), error_code);
                     assert_eq!(2, generation_id);
@@ -3038,6 +4312,7 @@ mod tests {
                     protocol_type: Some(PROTOCOL_TYPE.into()),
                     protocol_name: Some(RANGE.into()),
                     assignment: first_member_assignment_02,
+                    unknown_tagged_fields: vec![],
                 },
                 s.sync(
                     GROUP_ID,
@@ -3059,6 +4334,7 @@ mod tests {
                 protocol_type: Some(PROTOCOL_TYPE.into()),
                 protocol_name: Some(RANGE.into()),
                 assignment: second_member_assignment_02,
+                unknown_tagged_fields: vec![],
             },
             s.sync(
                 GROUP_ID,
@@ -3076,6 +4352,7 @@ mod tests {
             Body::HeartbeatResponse {
                 throttle_time_ms: Some(0),
                 error_code: ErrorCode::None.into(),
+                unknown_tagged_fields: vec![],
             },
             s.heartbeat(GROUP_ID, 2, &first_member_id, group_instance_id,)
                 .await?
@@ -3085,6 +4362,7 @@ mod tests {
             Body::HeartbeatResponse {
                 throttle_time_ms: Some(0),
                 error_code: ErrorCode::None.into(),
+                unknown_tagged_fields: vec![],
             },
             s.heartbeat(GROUP_ID, 2, &second_member_id, group_instance_id,)
                 .await?
@@ -3102,6 +4380,7 @@ mod tests {
                     }]
                     .into()
                 ),
+                unknown_tagged_fields: vec![],
             },
             s.leave(
                 GROUP_ID,
@@ -3119,6 +4398,7 @@ mod tests {
             Body::HeartbeatResponse {
                 throttle_time_ms: Some(0),
                 error_code: ErrorCode::RebalanceInProgress.into(),
+                unknown_tagged_fields: vec![],
             },
             s.heartbeat(GROUP_ID, 2, &second_member_id, group_instance_id,)
                 .await?
@@ -3154,9 +4434,11 @@ mod tests {
                         },]
                         .into()
                     ),
+                    unknown_tagged_fields: vec![],
                 },
                 s.join(
                     Some(CLIENT_ID),
+                    None,
                     GROUP_ID,
                     session_timeout_ms,
                     rebalance_timeout_ms,
@@ -3185,6 +4467,7 @@ mod tests {
                     protocol_type: Some(PROTOCOL_TYPE.into()),
                     protocol_name: Some(RANGE.into()),
                     assignment: second_member_assignment_03,
+                    unknown_tagged_fields: vec![],
                 },
                 s.sync(
                     GROUP_ID,
@@ -3240,6 +4523,7 @@ mod tests {
         let first_member_id = match s
             .join(
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -3284,9 +4568,11 @@ mod tests {
                             }]
                             .into(),
                         ),
+                        unknown_tagged_fields: vec![],
                     },
                     s.join(
                         Some(CLIENT_ID),
+                        None,
                         GROUP_ID,
                         session_timeout_ms,
                         rebalance_timeout_ms,
@@ -3322,6 +4608,7 @@ mod tests {
         let second_member_id = match s
             .join(
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -3359,9 +4646,11 @@ mod tests {
                         skip_assignment: Some(false),
                         member_id: member_id.clone(),
                         members: Some([].into()),
+                        unknown_tagged_fields: vec![],
                     },
                     s.join(
                         Some(CLIENT_ID),
+                        None,
                         GROUP_ID,
                         session_timeout_ms,
                         rebalance_timeout_ms,
@@ -3383,6 +4672,7 @@ mod tests {
         match s
             .join(
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -3404,6 +4694,7 @@ mod tests {
                 skip_assignment: Some(false),
                 member_id,
                 members: Some(members),
+                ..
             } => {
                 assert_eq!(i16::from(ErrorCode::None), error_code);
                 assert_eq!(Some(PROTOCOL_TYPE.into()), protocol_type);
@@ -3437,9 +4728,11 @@ mod tests {
                 skip_assignment: Some(false),
                 member_id: second_member_id.clone(),
                 members: Some([].into(),),
+                unknown_tagged_fields: vec![],
             },
             s.join(
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -3484,6 +4777,8 @@ mod tests {
                     protocol_type: Some(PROTOCOL_TYPE.into()),
                     protocol_name: Some(RANGE.into()),
                     leader: None,
+                    rebalance_started: None,
+                    delayed_until: None,
                 },
                 ..Default::default()
             },
@@ -3511,6 +4806,7 @@ mod tests {
             .join(
                 now,
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -3576,6 +4872,8 @@ mod tests {
                     protocol_type: Some(PROTOCOL_TYPE.into()),
                     protocol_name: Some(RANGE.into()),
                     leader: None,
+                    rebalance_started: None,
+                    delayed_until: None,
                 },
                 ..Default::default()
             },
@@ -3603,6 +4901,7 @@ mod tests {
             .join(
                 now,
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -3694,6 +4993,8 @@ mod tests {
                     protocol_type: Some(PROTOCOL_TYPE.into()),
                     protocol_name: Some(RANGE.into()),
                     leader: None,
+                    rebalance_started: None,
+                    delayed_until: None,
                 },
                 ..Default::default()
             },
@@ -3717,6 +5018,7 @@ mod tests {
             .join(
                 now,
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -3754,6 +5056,7 @@ mod tests {
             .join(
                 now,
                 Some(CLIENT_ID),
+                None,
                 GROUP_ID,
                 session_timeout_ms,
                 rebalance_timeout_ms,
@@ -3882,4 +5185,1468 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn describe_members_and_assignments() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let session_timeout_ms = 45_000;
+        let rebalance_timeout_ms = Some(300_000);
+
+        const CLIENT_ID: &str = "console-consumer";
+        const CLIENT_HOST: &str = "/127.0.0.1";
+        const GROUP_ID: &str = "test-consumer-group";
+        const RANGE: &str = "range";
+        const PROTOCOL_TYPE: &str = "consumer";
+
+        let mut s = Controller::with_storage(DynoStore::new("abc", 12321, InMemory::new()))?;
+
+        let metadata = Bytes::from_static(b"range_meta_01");
+        let protocols = [JoinGroupRequestProtocol {
+            name: RANGE.into(),
+            metadata: metadata.clone(),
+        }];
+
+        let Body::JoinGroupResponse { member_id, .. } = s
+            .join(
+                Some(CLIENT_ID),
+                Some(CLIENT_HOST),
+                GROUP_ID,
+                session_timeout_ms,
+                rebalance_timeout_ms,
+                "",
+                None,
+                PROTOCOL_TYPE,
+                Some(&protocols[..]),
+                None,
+            )
+            .await?
+        else {
+            panic!("expected join group response")
+        };
+
+        let Body::JoinGroupResponse { generation_id, .. } = s
+            .join(
+                Some(CLIENT_ID),
+                Some(CLIENT_HOST),
+                GROUP_ID,
+                session_timeout_ms,
+                rebalance_timeout_ms,
+                &member_id,
+                None,
+                PROTOCOL_TYPE,
+                Some(&protocols[..]),
+                None,
+            )
+            .await?
+        else {
+            panic!("expected join group response")
+        };
+
+        let assignment = Bytes::from_static(b"assignment_01");
+
+        _ = s
+            .sync(
+                GROUP_ID,
+                generation_id,
+                &member_id,
+                None,
+                Some(PROTOCOL_TYPE),
+                Some(RANGE),
+                Some(&[SyncGroupRequestAssignment {
+                    member_id: member_id.clone(),
+                    assignment: assignment.clone(),
+                }]),
+            )
+            .await?;
+
+        assert_eq!(
+            Body::DescribeGroupsResponse {
+                throttle_time_ms: Some(0),
+                groups: Some(vec![
+                    DescribedGroup {
+                        error_code: ErrorCode::None.into(),
+                        group_id: GROUP_ID.into(),
+                        group_state: "Stable".into(),
+                        protocol_type: PROTOCOL_TYPE.into(),
+                        protocol_data: RANGE.into(),
+                        members: Some(vec![DescribedGroupMember {
+                            member_id: member_id.clone(),
+                            group_instance_id: None,
+                            client_id: CLIENT_ID.into(),
+                            client_host: CLIENT_HOST.into(),
+                            member_metadata: metadata,
+                            member_assignment: assignment,
+                        }]),
+                        authorized_operations: Some(AUTHORIZED_OPERATIONS_OMITTED),
+                    },
+                    DescribedGroup {
+                        error_code: ErrorCode::None.into(),
+                        group_id: "unknown".into(),
+                        group_state: "Dead".into(),
+                        protocol_type: "".into(),
+                        protocol_data: "".into(),
+                        members: Some(vec![]),
+                        authorized_operations: Some(AUTHORIZED_OPERATIONS_OMITTED),
+                    },
+                ]),
+                unknown_tagged_fields: vec![],
+            },
+            s.describe(Some(&[GROUP_ID.into(), "unknown".into()]), false)
+                .await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_groups_by_state() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        const CLIENT_ID: &str = "console-consumer";
+        const LIVE: &str = "live-consumer-group";
+        const OFFSETS_ONLY: &str = "offsets-only-consumer-group";
+        const PROTOCOL_TYPE: &str = "consumer";
+
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+        let mut s = Controller::with_storage(storage.clone())?;
+
+        _ = s
+            .join(
+                Some(CLIENT_ID),
+                None,
+                LIVE,
+                45_000,
+                Some(300_000),
+                "",
+                None,
+                PROTOCOL_TYPE,
+                Some(&[JoinGroupRequestProtocol {
+                    name: "range".into(),
+                    metadata: Bytes::from_static(b"range_meta_01"),
+                }]),
+                None,
+            )
+            .await?;
+
+        _ = storage
+            .offset_commit(
+                OFFSETS_ONLY,
+                None,
+                &[(
+                    Topition::new("test", 0),
+                    OffsetCommitRequest::try_from(&OffsetCommitRequestPartition {
+                        partition_index: 0,
+                        committed_offset: 1,
+                        committed_leader_epoch: Some(0),
+                        commit_timestamp: None,
+                        committed_metadata: Some("".into()),
+                    })?,
+                )],
+            )
+            .await?;
+
+        let listed = |body: Body| match body {
+            Body::ListGroupsResponse {
+                groups: Some(groups),
+                ..
+            } => groups
+                .into_iter()
+                .map(|group| (group.group_id, group.group_state))
+                .collect::<Vec<_>>(),
+
+            otherwise => panic!("{otherwise:?}"),
+        };
+
+        assert_eq!(
+            vec![
+                (LIVE.into(), Some("PreparingRebalance".into())),
+                (OFFSETS_ONLY.into(), Some("Empty".into())),
+            ],
+            listed(s.list(None).await?)
+        );
+
+        assert_eq!(
+            vec![(OFFSETS_ONLY.into(), Some("Empty".into()))],
+            listed(s.list(Some(&["Empty".into()])).await?)
+        );
+
+        assert_eq!(
+            vec![(LIVE.into(), Some("PreparingRebalance".into()))],
+            listed(s.list(Some(&["preparingrebalance".into()])).await?)
+        );
+
+        Ok(())
+    }
+
+    const SESSION_TIMEOUT_MS: i32 = 10_000;
+    const REBALANCE_TIMEOUT_MS: i32 = 5_000;
+
+    fn error_code(body: &Body) -> Option<ErrorCode> {
+        match body {
+            Body::HeartbeatResponse { error_code, .. }
+            | Body::JoinGroupResponse { error_code, .. }
+            | Body::SyncGroupResponse { error_code, .. } => ErrorCode::try_from(*error_code).ok(),
+            _ => None,
+        }
+    }
+
+    async fn join(
+        s: Wrapper<DynoStore>,
+        now: SystemTime,
+        member_id: &str,
+    ) -> (Wrapper<DynoStore>, Body) {
+        s.join(
+            now,
+            Some("consumer"),
+            None,
+            "test-consumer-group",
+            SESSION_TIMEOUT_MS,
+            Some(REBALANCE_TIMEOUT_MS),
+            member_id,
+            None,
+            "consumer",
+            Some(&[JoinGroupRequestProtocol {
+                name: "range".into(),
+                metadata: Bytes::from(format!("{member_id}_range_meta")),
+            }]),
+            None,
+        )
+        .await
+    }
+
+    async fn sync(
+        s: Wrapper<DynoStore>,
+        now: SystemTime,
+        generation_id: i32,
+        member_id: &str,
+        assignments: &[(&str, &'static [u8])],
+    ) -> (Wrapper<DynoStore>, Body) {
+        let assignments = assignments
+            .iter()
+            .map(|(member_id, assignment)| SyncGroupRequestAssignment {
+                member_id: (*member_id).into(),
+                assignment: Bytes::from_static(assignment),
+            })
+            .collect::<Vec<_>>();
+
+        s.sync(
+            now,
+            "test-consumer-group",
+            generation_id,
+            member_id,
+            None,
+            Some("consumer"),
+            Some("range"),
+            Some(&assignments),
+        )
+        .await
+    }
+
+    // a formed group of two members, with the first as leader
+    async fn formed(now: SystemTime, leader: &str, follower: &str) -> Wrapper<DynoStore> {
+        let s = Wrapper::with_storage_group_detail(
+            DynoStore::new("abc", 12321, InMemory::new()),
+            GroupDetail {
+                session_timeout_ms: SESSION_TIMEOUT_MS,
+                rebalance_timeout_ms: Some(REBALANCE_TIMEOUT_MS),
+                state: GroupState::Forming {
+                    protocol_type: Some("consumer".into()),
+                    protocol_name: Some("range".into()),
+                    leader: None,
+                    rebalance_started: None,
+                    delayed_until: None,
+                },
+                ..Default::default()
+            },
+        );
+
+        let (s, _) = join(s, now, leader).await;
+        let (s, _) = join(s, now, follower).await;
+        assert_eq!(Some(leader), s.leader());
+
+        let generation_id = s.generation_id();
+
+        let (s, body) = sync(
+            s,
+            now,
+            generation_id,
+            leader,
+            &[(leader, b"p0"), (follower, b"p1")],
+        )
+        .await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+
+        let (s, body) = sync(s, now, generation_id, follower, &[]).await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_eq!("Stable", s.state());
+
+        s
+    }
+
+    async fn survivor_takes_over(dead: &str, survivor: &str, leader: &str) -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let follower = if leader == dead { survivor } else { dead };
+
+        let now = SystemTime::now();
+        let s = formed(now, leader, follower).await;
+        let generation_id = s.generation_id();
+
+        let session_timeout = Duration::from_millis(u64::try_from(SESSION_TIMEOUT_MS)?);
+
+        // only the survivor heartbeats within the session timeout
+        let heartbeat = now + session_timeout / 2;
+        let (s, body) = s
+            .missed_heartbeat("test-consumer-group", heartbeat)
+            .heartbeat(
+                heartbeat,
+                "test-consumer-group",
+                generation_id,
+                survivor,
+                None,
+            )
+            .await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+
+        let expired = now + session_timeout + Duration::from_millis(1);
+        let s = s.missed_heartbeat("test-consumer-group", expired);
+        assert_eq!("PreparingRebalance", s.state());
+        assert_eq!(1, s.members().len());
+
+        let (s, body) = s
+            .heartbeat(
+                expired,
+                "test-consumer-group",
+                generation_id,
+                survivor,
+                None,
+            )
+            .await;
+        assert_eq!(Some(ErrorCode::RebalanceInProgress), error_code(&body));
+
+        let (s, body) = join(s, expired, survivor).await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_eq!(Some(survivor), s.leader());
+        assert!(s.generation_id() > generation_id);
+        let rejoined = s.generation_id();
+
+        let (s, body) = sync(s, expired, rejoined, survivor, &[(survivor, b"p0,p1")]).await;
+
+        let Body::SyncGroupResponse { assignment, .. } = body else {
+            panic!("{body:?}")
+        };
+
+        assert_eq!(Bytes::from_static(b"p0,p1"), assignment);
+        assert_eq!("Stable", s.state());
+
+        let (_, body) = s
+            .heartbeat(expired, "test-consumer-group", rejoined, dead, None)
+            .await;
+        assert_eq!(Some(ErrorCode::UnknownMemberId), error_code(&body));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn follower_session_expires() -> Result<()> {
+        survivor_takes_over("consumer-b", "consumer-a", "consumer-a").await
+    }
+
+    #[tokio::test]
+    async fn leader_session_expires() -> Result<()> {
+        survivor_takes_over("consumer-a", "consumer-b", "consumer-a").await
+    }
+
+    #[tokio::test]
+    async fn commit_refreshes_session() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let s = formed(now, "consumer-a", "consumer-b").await;
+        let generation_id = s.generation_id();
+
+        let session_timeout = Duration::from_millis(u64::try_from(SESSION_TIMEOUT_MS)?);
+
+        let committed = now + session_timeout / 2;
+        let (s, _) = s
+            .offset_commit(
+                committed,
+                &OffsetCommit {
+                    group_id: "test-consumer-group",
+                    generation_id_or_member_epoch: Some(generation_id),
+                    member_id: Some("consumer-b"),
+                    group_instance_id: None,
+                    retention_time_ms: None,
+                    topics: Some(&[]),
+                },
+            )
+            .await;
+
+        let s = s.missed_heartbeat(
+            "test-consumer-group",
+            now + session_timeout + Duration::from_millis(1),
+        );
+
+        assert_eq!(vec!["consumer-b"], member_ids(&s));
+
+        Ok(())
+    }
+
+    fn member_ids(s: &Wrapper<DynoStore>) -> Vec<String> {
+        s.members()
+            .into_iter()
+            .map(|member| member.member_id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn expire_idle_group() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let s = formed(now, "consumer-a", "consumer-b").await;
+        let generation_id = s.generation_id();
+
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+        _ = storage
+            .update_group("test-consumer-group", GroupDetail::from(&s), None)
+            .await
+            .map_err(|error| Error::Message(format!("{error:?}")))?;
+
+        let mut controller = Controller::with_storage(storage.clone())?;
+
+        assert!(controller.expire(now).await?.is_empty());
+
+        let session_timeout = Duration::from_millis(u64::try_from(SESSION_TIMEOUT_MS)?);
+        assert_eq!(
+            vec![String::from("test-consumer-group")],
+            controller
+                .expire(now + session_timeout + Duration::from_millis(1))
+                .await?
+        );
+
+        let detail = storage
+            .group_detail("test-consumer-group")
+            .await?
+            .expect("group");
+
+        assert!(detail.members.is_empty());
+        assert_eq!(generation_id + 1, detail.generation_id);
+        assert!(matches!(detail.state, GroupState::Forming { .. }));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn straggling_leader_is_evicted() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let s = formed(now, "consumer-a", "consumer-b").await;
+        let formed_generation = s.generation_id();
+
+        // a new member starts a rebalance
+        let started = now + Duration::from_secs(1);
+        let (s, _) = join(s, started, "consumer-c").await;
+        assert_eq!(formed_generation + 1, s.generation_id());
+
+        let (s, body) = s
+            .heartbeat(
+                started,
+                "test-consumer-group",
+                formed_generation,
+                "consumer-b",
+                None,
+            )
+            .await;
+        assert_eq!(Some(ErrorCode::RebalanceInProgress), error_code(&body));
+
+        let (s, _) = join(s, started, "consumer-b").await;
+
+        // the leader keeps its session alive, without rejoining
+        let heartbeat = started + Duration::from_secs(2);
+        let (s, body) = s
+            .missed_heartbeat("test-consumer-group", heartbeat)
+            .heartbeat(
+                heartbeat,
+                "test-consumer-group",
+                formed_generation,
+                "consumer-a",
+                None,
+            )
+            .await;
+        assert_eq!(Some(ErrorCode::RebalanceInProgress), error_code(&body));
+
+        let generation_id = s.generation_id();
+        let rebalance_timeout = Duration::from_millis(u64::try_from(REBALANCE_TIMEOUT_MS)?);
+
+        let s = s.missed_heartbeat(
+            "test-consumer-group",
+            started + rebalance_timeout - Duration::from_millis(1),
+        );
+        assert_eq!(3, s.members().len());
+
+        let expired = started + rebalance_timeout + Duration::from_millis(1);
+        let s = s.missed_heartbeat("test-consumer-group", expired);
+
+        assert_eq!(vec!["consumer-b", "consumer-c"], member_ids(&s));
+        assert_eq!(None, s.leader());
+        assert_eq!(generation_id + 1, s.generation_id());
+
+        // the survivors rejoin the new generation, with a new leader
+        let (s, _) = join(s, expired, "consumer-b").await;
+        let (s, body) = join(s, expired, "consumer-c").await;
+        assert_eq!(Some("consumer-b"), s.leader());
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+
+        let (s, body) = sync(
+            s,
+            expired,
+            generation_id + 1,
+            "consumer-b",
+            &[("consumer-b", b"p0"), ("consumer-c", b"p1")],
+        )
+        .await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_eq!("Stable", s.state());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn initial_rebalance_delay() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let controller = Controller::with_storage(DynoStore::new("abc", 12321, InMemory::new()))?
+            .with_initial_rebalance_delay(Duration::from_millis(500));
+
+        let protocols = |member_id: &str| {
+            [JoinGroupRequestProtocol {
+                name: "range".into(),
+                metadata: Bytes::from(format!("{member_id}_range_meta")),
+            }]
+        };
+
+        let joins = ["consumer-a", "consumer-b", "consumer-c"].map(|member_id| {
+            let mut controller = controller.clone();
+
+            tokio::spawn(async move {
+                controller
+                    .join(
+                        Some("consumer"),
+                        None,
+                        "test-consumer-group",
+                        SESSION_TIMEOUT_MS,
+                        Some(REBALANCE_TIMEOUT_MS),
+                        member_id,
+                        None,
+                        "consumer",
+                        Some(&protocols(member_id)),
+                        None,
+                    )
+                    .await
+            })
+        });
+
+        let mut generations = vec![];
+        let mut members = vec![];
+
+        for join in joins {
+            let Body::JoinGroupResponse {
+                error_code,
+                generation_id,
+                members: Some(joined),
+                ..
+            } = join
+                .await
+                .map_err(|error| Error::Message(error.to_string()))??
+            else {
+                panic!("expecting a join group response")
+            };
+
+            assert_eq!(i16::from(ErrorCode::None), error_code);
+            generations.push(generation_id);
+            members.extend(joined.into_iter().map(|member| member.member_id));
+        }
+
+        // every member joins the same generation, with the leader given all of them
+        generations.dedup();
+        assert_eq!(1, generations.len());
+
+        members.sort();
+        assert_eq!(vec!["consumer-a", "consumer-b", "consumer-c"], members);
+
+        Ok(())
+    }
+
+    async fn static_join(
+        s: Wrapper<DynoStore>,
+        now: SystemTime,
+        member_id: &str,
+        group_instance_id: &str,
+    ) -> (Wrapper<DynoStore>, String, Body) {
+        let (s, body) = s
+            .join(
+                now,
+                Some("consumer"),
+                None,
+                "test-consumer-group",
+                SESSION_TIMEOUT_MS,
+                Some(REBALANCE_TIMEOUT_MS),
+                member_id,
+                Some(group_instance_id),
+                "consumer",
+                Some(&[JoinGroupRequestProtocol {
+                    name: "range".into(),
+                    metadata: Bytes::from(format!("{group_instance_id}_range_meta")),
+                }]),
+                None,
+            )
+            .await;
+
+        let Body::JoinGroupResponse { ref member_id, .. } = body else {
+            panic!("{body:?}")
+        };
+
+        (s, member_id.to_owned(), body)
+    }
+
+    // a formed group of two static members, returning their member ids
+    async fn static_formed(now: SystemTime) -> (Wrapper<DynoStore>, String, String) {
+        let s = Wrapper::with_storage_group_detail(
+            DynoStore::new("abc", 12321, InMemory::new()),
+            GroupDetail {
+                session_timeout_ms: SESSION_TIMEOUT_MS,
+                rebalance_timeout_ms: Some(REBALANCE_TIMEOUT_MS),
+                state: GroupState::Forming {
+                    protocol_type: Some("consumer".into()),
+                    protocol_name: Some("range".into()),
+                    leader: None,
+                    rebalance_started: None,
+                    delayed_until: None,
+                },
+                ..Default::default()
+            },
+        );
+
+        // static members are given a member id without being asked to rejoin
+        let (s, leader, body) = static_join(s, now, "", "instance-a").await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+
+        let (s, follower, body) = static_join(s, now, "", "instance-b").await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_eq!(Some(leader.as_str()), s.leader());
+
+        let generation_id = s.generation_id();
+
+        let (s, _) = sync(
+            s,
+            now,
+            generation_id,
+            &leader,
+            &[(&leader, b"p0"), (&follower, b"p1")],
+        )
+        .await;
+        let (s, _) = sync(s, now, generation_id, &follower, &[]).await;
+        assert_eq!("Stable", s.state());
+
+        (s, leader, follower)
+    }
+
+    #[tokio::test]
+    async fn static_member_takeover() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let (s, leader, follower) = static_formed(now).await;
+        let generation_id = s.generation_id();
+
+        // a restarted follower takes over its membership without a rebalance
+        let (s, restarted, body) = static_join(s, now, "", "instance-b").await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_ne!(follower, restarted);
+        assert_eq!(generation_id, s.generation_id());
+        assert_eq!("Stable", s.state());
+        let mut expected = vec![leader.clone(), restarted.clone()];
+        expected.sort();
+        assert_eq!(expected, member_ids(&s));
+
+        let (s, body) = sync(s, now, generation_id, &restarted, &[]).await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+
+        let Body::SyncGroupResponse { assignment, .. } = body else {
+            panic!("{body:?}")
+        };
+        assert_eq!(Bytes::from_static(b"p1"), assignment);
+
+        // as does a restarted leader, remaining leader
+        let (s, restarted, body) = static_join(s, now, "", "instance-a").await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_eq!(Some(restarted.as_str()), s.leader());
+        assert_eq!(generation_id, s.generation_id());
+        assert_eq!("Stable", s.state());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn static_member_fenced() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let (s, _, follower) = static_formed(now).await;
+        let generation_id = s.generation_id();
+
+        let (s, _, _) = static_join(s, now, "", "instance-b").await;
+
+        // the replaced member is fenced
+        let (s, body) = s
+            .heartbeat(
+                now,
+                "test-consumer-group",
+                generation_id,
+                &follower,
+                Some("instance-b"),
+            )
+            .await;
+        assert_eq!(Some(ErrorCode::FencedInstanceId), error_code(&body));
+
+        let (s, body) = s
+            .sync(
+                now,
+                "test-consumer-group",
+                generation_id,
+                &follower,
+                Some("instance-b"),
+                Some("consumer"),
+                Some("range"),
+                Some(&[]),
+            )
+            .await;
+        assert_eq!(Some(ErrorCode::FencedInstanceId), error_code(&body));
+
+        let (s, _, body) = static_join(s, now, &follower, "instance-b").await;
+        assert_eq!(Some(ErrorCode::FencedInstanceId), error_code(&body));
+
+        let (s, body) = s
+            .leave(
+                now,
+                "test-consumer-group",
+                None,
+                Some(&[MemberIdentity {
+                    member_id: follower.clone(),
+                    group_instance_id: Some("instance-b".into()),
+                    reason: None,
+                }]),
+            )
+            .await;
+        let Body::LeaveGroupResponse {
+            members: Some(members),
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+        assert_eq!(
+            vec![i16::from(ErrorCode::FencedInstanceId)],
+            members
+                .iter()
+                .map(|member| member.error_code)
+                .collect::<Vec<_>>()
+        );
+
+        // the group is unaffected by the fenced member
+        assert_eq!(generation_id, s.generation_id());
+        assert_eq!("Stable", s.state());
+        assert_eq!(2, s.members().len());
+
+        Ok(())
+    }
+
+    async fn join_with_protocols(
+        s: Wrapper<DynoStore>,
+        now: SystemTime,
+        member_id: &str,
+        protocol_type: &str,
+        protocols: &[&str],
+    ) -> (Wrapper<DynoStore>, Body) {
+        let protocols = protocols
+            .iter()
+            .map(|name| JoinGroupRequestProtocol {
+                name: (*name).into(),
+                metadata: Bytes::from(format!("{member_id}_{name}_meta")),
+            })
+            .collect::<Vec<_>>();
+
+        s.join(
+            now,
+            Some("consumer"),
+            None,
+            "test-consumer-group",
+            SESSION_TIMEOUT_MS,
+            Some(REBALANCE_TIMEOUT_MS),
+            member_id,
+            None,
+            protocol_type,
+            Some(&protocols),
+            None,
+        )
+        .await
+    }
+
+    // a new group, before any member has joined
+    fn forming() -> Wrapper<DynoStore> {
+        Wrapper::Forming(Inner {
+            session_timeout_ms: SESSION_TIMEOUT_MS,
+            rebalance_timeout_ms: Some(REBALANCE_TIMEOUT_MS),
+            group_instance_id: None,
+            members: Default::default(),
+            generation_id: -1,
+            state: Forming::default(),
+            skip_assignment: Some(false),
+            storage: DynoStore::new("abc", 12321, InMemory::new()),
+        })
+    }
+
+    #[tokio::test]
+    async fn most_preferred_common_protocol() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+
+        let (s, body) = join_with_protocols(
+            forming(),
+            now,
+            "consumer-a",
+            "consumer",
+            &["range", "cooperative-sticky"],
+        )
+        .await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_eq!(Some("range"), s.protocol_name());
+
+        let (s, body) = join_with_protocols(
+            s,
+            now,
+            "consumer-b",
+            "consumer",
+            &["cooperative-sticky", "roundrobin"],
+        )
+        .await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_eq!(Some("cooperative-sticky"), s.protocol_name());
+
+        // every member has metadata for the selected protocol
+        assert_eq!(
+            vec![
+                Bytes::from_static(b"consumer-a_cooperative-sticky_meta"),
+                Bytes::from_static(b"consumer-b_cooperative-sticky_meta")
+            ],
+            s.members()
+                .into_iter()
+                .map(|member| member.metadata)
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn no_common_protocol_while_forming() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+
+        let (s, _) =
+            join_with_protocols(forming(), now, "consumer-a", "consumer", &["range"]).await;
+
+        let (s, body) =
+            join_with_protocols(s, now, "consumer-b", "consumer", &["cooperative-sticky"]).await;
+        assert_eq!(
+            Some(ErrorCode::InconsistentGroupProtocol),
+            error_code(&body)
+        );
+        assert_eq!(vec!["consumer-a"], member_ids(&s));
+        assert_eq!(Some("range"), s.protocol_name());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn no_common_protocol_when_formed() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let s = formed(now, "consumer-a", "consumer-b").await;
+        let generation_id = s.generation_id();
+
+        let (s, body) =
+            join_with_protocols(s, now, "consumer-c", "consumer", &["cooperative-sticky"]).await;
+        assert_eq!(
+            Some(ErrorCode::InconsistentGroupProtocol),
+            error_code(&body)
+        );
+
+        // the group is undisturbed
+        assert_eq!(generation_id, s.generation_id());
+        assert_eq!("Stable", s.state());
+        assert_eq!(vec!["consumer-a", "consumer-b"], member_ids(&s));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn inconsistent_protocol_type() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+
+        let (s, _) =
+            join_with_protocols(forming(), now, "consumer-a", "consumer", &["range"]).await;
+
+        let (s, body) = join_with_protocols(s, now, "worker-a", "connect", &["range"]).await;
+        assert_eq!(
+            Some(ErrorCode::InconsistentGroupProtocol),
+            error_code(&body)
+        );
+        assert_eq!(vec!["consumer-a"], member_ids(&s));
+
+        let s = formed(now, "consumer-a", "consumer-b").await;
+
+        let (s, body) = join_with_protocols(s, now, "worker-a", "connect", &["range"]).await;
+        assert_eq!(
+            Some(ErrorCode::InconsistentGroupProtocol),
+            error_code(&body)
+        );
+        assert_eq!("Stable", s.state());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resumed_after_restart() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        // a group persisted by a broker that stopped an hour ago
+        let stopped = SystemTime::now() - Duration::from_secs(3_600);
+        let s = formed(stopped, "consumer-a", "consumer-b").await;
+        let generation_id = s.generation_id();
+
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+        assert!(storage
+            .update_group("test-consumer-group", GroupDetail::from(&s), None)
+            .await
+            .is_ok());
+
+        let mut controller = Controller::with_storage(storage.clone())?;
+
+        // members are given a new session to reconnect, rather than expiring
+        assert!(controller.expire(SystemTime::now()).await?.is_empty());
+
+        let detail = storage
+            .group_detail("test-consumer-group")
+            .await?
+            .expect("group detail");
+        assert_eq!(generation_id, detail.generation_id);
+        assert_eq!(2, detail.members.len());
+        assert!(matches!(detail.state, GroupState::Formed { .. }));
+
+        let body = controller
+            .heartbeat("test-consumer-group", generation_id, "consumer-b", None)
+            .await?;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn member_ahead_of_resumed_group() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let s = formed(now, "consumer-a", "consumer-b").await;
+        let generation_id = s.generation_id();
+
+        // a member in a later generation than the group persisted
+        let ahead = generation_id + 2;
+
+        let (s, body) = s
+            .heartbeat(now, "test-consumer-group", ahead, "consumer-b", None)
+            .await;
+        assert_eq!(Some(ErrorCode::RebalanceInProgress), error_code(&body));
+        assert_eq!(ahead + 1, s.generation_id());
+        assert_eq!("CompletingRebalance", s.state());
+
+        // every member rejoins the new generation
+        let (s, body) = join(s, now, "consumer-a").await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+
+        let (s, body) = join(s, now, "consumer-b").await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_eq!(ahead + 1, s.generation_id());
+
+        let (s, body) = sync(
+            s,
+            now,
+            ahead + 1,
+            "consumer-a",
+            &[("consumer-a", b"p0"), ("consumer-b", b"p1")],
+        )
+        .await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+
+        let (s, body) = sync(s, now, ahead + 1, "consumer-b", &[]).await;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert_eq!("Stable", s.state());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn groups_progress_concurrently() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        let s = formed(SystemTime::now(), "consumer-a", "consumer-b").await;
+        let generation_id = s.generation_id();
+
+        assert!(storage
+            .update_group("stable-group", GroupDetail::from(&s), None)
+            .await
+            .is_ok());
+
+        let initial_rebalance_delay = Duration::from_millis(500);
+        let controller = Controller::with_storage(storage.clone())?
+            .with_initial_rebalance_delay(initial_rebalance_delay);
+
+        // the first join to a new group is held for the initial rebalance delay
+        let started = Instant::now();
+        let rebalancing = {
+            let mut controller = controller.clone();
+
+            tokio::spawn(async move {
+                controller
+                    .join(
+                        Some("consumer"),
+                        None,
+                        "rebalancing-group",
+                        SESSION_TIMEOUT_MS,
+                        Some(REBALANCE_TIMEOUT_MS),
+                        "consumer-c",
+                        None,
+                        "consumer",
+                        Some(&[JoinGroupRequestProtocol {
+                            name: "range".into(),
+                            metadata: Bytes::from_static(b"consumer-c_range_meta"),
+                        }]),
+                        None,
+                    )
+                    .await
+            })
+        };
+
+        // while members of another group continue to heartbeat
+        for member_id in ["consumer-a", "consumer-b"] {
+            let body = controller
+                .clone()
+                .heartbeat("stable-group", generation_id, member_id, None)
+                .await?;
+            assert_eq!(Some(ErrorCode::None), error_code(&body));
+        }
+
+        assert!(started.elapsed() < initial_rebalance_delay);
+        assert!(!rebalancing.is_finished());
+
+        let body = rebalancing
+            .await
+            .map_err(|error| Error::Message(error.to_string()))??;
+        assert_eq!(Some(ErrorCode::None), error_code(&body));
+        assert!(started.elapsed() >= initial_rebalance_delay);
+
+        Ok(())
+    }
+
+    fn leave_error_codes(body: &Body) -> (Option<ErrorCode>, Vec<ErrorCode>) {
+        let Body::LeaveGroupResponse {
+            error_code,
+            members,
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        (
+            ErrorCode::try_from(*error_code).ok(),
+            members
+                .iter()
+                .flatten()
+                .flat_map(|member| ErrorCode::try_from(member.error_code))
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn leave_batch_of_members() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let s = formed(now, "consumer-a", "consumer-b").await;
+        let s = join(s, now, "consumer-c").await.0;
+        let generation_id = s.generation_id();
+
+        let (s, body) = s
+            .leave(
+                now,
+                "test-consumer-group",
+                None,
+                Some(&[
+                    MemberIdentity {
+                        member_id: "consumer-a".into(),
+                        group_instance_id: None,
+                        reason: None,
+                    },
+                    MemberIdentity {
+                        member_id: "consumer-z".into(),
+                        group_instance_id: None,
+                        reason: None,
+                    },
+                    MemberIdentity {
+                        member_id: "".into(),
+                        group_instance_id: Some("instance-z".into()),
+                        reason: None,
+                    },
+                    MemberIdentity {
+                        member_id: "consumer-c".into(),
+                        group_instance_id: None,
+                        reason: Some("shutdown".into()),
+                    },
+                ]),
+            )
+            .await;
+
+        assert_eq!(
+            (
+                Some(ErrorCode::None),
+                vec![
+                    ErrorCode::None,
+                    ErrorCode::UnknownMemberId,
+                    ErrorCode::UnknownMemberId,
+                    ErrorCode::None
+                ]
+            ),
+            leave_error_codes(&body)
+        );
+
+        // a single rebalance, without the leader that left
+        assert_eq!(generation_id + 1, s.generation_id());
+        assert_eq!(vec!["consumer-b"], member_ids(&s));
+        assert_eq!(None, s.leader());
+
+        // before v3, a single member has only the top level error code
+        let (_, body) = s
+            .leave(now, "test-consumer-group", Some("consumer-a"), None)
+            .await;
+
+        assert_eq!(
+            (
+                Some(ErrorCode::UnknownMemberId),
+                vec![ErrorCode::UnknownMemberId]
+            ),
+            leave_error_codes(&body)
+        );
+
+        Ok(())
+    }
+
+    async fn heartbeat_error_code(
+        s: Wrapper<DynoStore>,
+        now: SystemTime,
+        generation_id: i32,
+        member_id: &str,
+    ) -> (Wrapper<DynoStore>, Option<ErrorCode>) {
+        let (s, body) = s
+            .heartbeat(now, "test-consumer-group", generation_id, member_id, None)
+            .await;
+
+        (s, error_code(&body))
+    }
+
+    #[tokio::test]
+    async fn heartbeat_while_empty() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let s = forming();
+        assert_eq!("Empty", s.state());
+
+        let (_, error_code) = heartbeat_error_code(s, now, -1, "consumer-a").await;
+        assert_eq!(Some(ErrorCode::UnknownMemberId), error_code);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn heartbeat_while_preparing_rebalance() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let (s, _) = formed(now, "consumer-a", "consumer-b")
+            .await
+            .leave(now, "test-consumer-group", Some("consumer-a"), None)
+            .await;
+        assert_eq!("PreparingRebalance", s.state());
+        let generation_id = s.generation_id();
+
+        let (s, error_code) = heartbeat_error_code(s, now, generation_id, "consumer-b").await;
+        assert_eq!(Some(ErrorCode::RebalanceInProgress), error_code);
+
+        let (s, error_code) = heartbeat_error_code(s, now, generation_id - 1, "consumer-b").await;
+        assert_eq!(Some(ErrorCode::RebalanceInProgress), error_code);
+
+        let (_, error_code) = heartbeat_error_code(s, now, generation_id, "consumer-a").await;
+        assert_eq!(Some(ErrorCode::UnknownMemberId), error_code);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn heartbeat_while_completing_rebalance() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let (s, _) = join(forming(), now, "consumer-a").await;
+        assert_eq!("CompletingRebalance", s.state());
+        let generation_id = s.generation_id();
+
+        let (s, error_code) = heartbeat_error_code(s, now, generation_id, "consumer-a").await;
+        assert_eq!(Some(ErrorCode::RebalanceInProgress), error_code);
+
+        let (_, error_code) = heartbeat_error_code(s, now, generation_id, "consumer-z").await;
+        assert_eq!(Some(ErrorCode::UnknownMemberId), error_code);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn heartbeat_while_stable() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let s = formed(now, "consumer-a", "consumer-b").await;
+        let generation_id = s.generation_id();
+
+        let (s, error_code) = heartbeat_error_code(s, now, generation_id, "consumer-a").await;
+        assert_eq!(Some(ErrorCode::None), error_code);
+
+        let (s, error_code) = heartbeat_error_code(s, now, generation_id - 1, "consumer-b").await;
+        assert_eq!(Some(ErrorCode::IllegalGeneration), error_code);
+
+        let (s, error_code) = heartbeat_error_code(s, now, generation_id, "consumer-z").await;
+        assert_eq!(Some(ErrorCode::UnknownMemberId), error_code);
+        assert_eq!("Stable", s.state());
+
+        Ok(())
+    }
+
+    // a forming group of these members, with the first as leader, persisted
+    // in storage for a controller
+    async fn persisted_forming(
+        storage: &mut DynoStore,
+        now: SystemTime,
+        member_ids: &[&str],
+    ) -> Result<(Controller<DynoStore>, i32)> {
+        let mut s = forming();
+
+        for member_id in member_ids {
+            s = join(s, now, member_id).await.0;
+        }
+
+        assert_eq!(Some(member_ids[0]), s.leader());
+        assert_eq!("CompletingRebalance", s.state());
+
+        assert!(storage
+            .update_group("test-consumer-group", GroupDetail::from(&s), None)
+            .await
+            .is_ok());
+
+        Ok((
+            Controller::with_storage(storage.clone())?.with_initial_rebalance_delay(Duration::ZERO),
+            s.generation_id(),
+        ))
+    }
+
+    fn follower_sync(
+        controller: &Controller<DynoStore>,
+        generation_id: i32,
+        member_id: &'static str,
+    ) -> tokio::task::JoinHandle<Result<Body>> {
+        let mut controller = controller.clone();
+
+        tokio::spawn(async move {
+            controller
+                .sync(
+                    "test-consumer-group",
+                    generation_id,
+                    member_id,
+                    None,
+                    Some("consumer"),
+                    Some("range"),
+                    Some(&[]),
+                )
+                .await
+        })
+    }
+
+    fn assigned(body: &Body) -> (Option<ErrorCode>, Bytes) {
+        let Body::SyncGroupResponse {
+            error_code,
+            assignment,
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        (ErrorCode::try_from(*error_code).ok(), assignment.clone())
+    }
+
+    #[tokio::test]
+    async fn followers_wait_for_leader_sync() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+        let (mut controller, generation_id) = persisted_forming(
+            &mut storage,
+            now,
+            &["consumer-a", "consumer-b", "consumer-c"],
+        )
+        .await?;
+
+        let followers = [
+            follower_sync(&controller, generation_id, "consumer-b"),
+            follower_sync(&controller, generation_id, "consumer-c"),
+        ];
+
+        sleep(SYNC_POLL_INTERVAL).await;
+        assert!(followers.iter().all(|follower| !follower.is_finished()));
+
+        // the leader syncs last, with the assignment of every member
+        let body = controller
+            .sync(
+                "test-consumer-group",
+                generation_id,
+                "consumer-a",
+                None,
+                Some("consumer"),
+                Some("range"),
+                Some(&[
+                    SyncGroupRequestAssignment {
+                        member_id: "consumer-a".into(),
+                        assignment: Bytes::from_static(b"p0"),
+                    },
+                    SyncGroupRequestAssignment {
+                        member_id: "consumer-b".into(),
+                        assignment: Bytes::from_static(b"p1"),
+                    },
+                    SyncGroupRequestAssignment {
+                        member_id: "consumer-c".into(),
+                        assignment: Bytes::from_static(b"p2"),
+                    },
+                ]),
+            )
+            .await?;
+        assert_eq!(
+            (Some(ErrorCode::None), Bytes::from_static(b"p0")),
+            assigned(&body)
+        );
+
+        let mut assignments = vec![];
+
+        for follower in followers {
+            let body = follower
+                .await
+                .map_err(|error| Error::Message(error.to_string()))??;
+            assignments.push(assigned(&body));
+        }
+
+        assert_eq!(
+            vec![
+                (Some(ErrorCode::None), Bytes::from_static(b"p1")),
+                (Some(ErrorCode::None), Bytes::from_static(b"p2")),
+            ],
+            assignments
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn waiting_follower_rebalances() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let now = SystemTime::now();
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+        let (mut controller, generation_id) =
+            persisted_forming(&mut storage, now, &["consumer-a", "consumer-b"]).await?;
+
+        let follower = follower_sync(&controller, generation_id, "consumer-b");
+
+        // a new member starts another rebalance before the leader syncs
+        let body = controller
+            .join(
+                Some("consumer"),
+                None,
+                "test-consumer-group",
+                SESSION_TIMEOUT_MS,
+                Some(REBALANCE_TIMEOUT_MS),
+                "",
+                None,
+                "consumer",
+                Some(&[JoinGroupRequestProtocol {
+                    name: "range".into(),
+                    metadata: Bytes::from_static(b"consumer-d_range_meta"),
+                }]),
+                None,
+            )
+            .await?;
+        assert_eq!(Some(ErrorCode::MemberIdRequired), error_code(&body));
+
+        let body = follower
+            .await
+            .map_err(|error| Error::Message(error.to_string()))??;
+        assert_eq!(Some(ErrorCode::RebalanceInProgress), assigned(&body).0);
+
+        // while a member of an earlier generation may not sync
+        let body = controller
+            .sync(
+                "test-consumer-group",
+                generation_id - 1,
+                "consumer-b",
+                None,
+                Some("consumer"),
+                Some("range"),
+                Some(&[]),
+            )
+            .await?;
+        assert_eq!(Some(ErrorCode::RebalanceInProgress), assigned(&body).0);
+
+        let body = controller
+            .sync(
+                "test-consumer-group",
+                generation_id,
+                "consumer-z",
+                None,
+                Some("consumer"),
+                Some("range"),
+                Some(&[]),
+            )
+            .await?;
+        assert_eq!(Some(ErrorCode::UnknownMemberId), assigned(&body).0);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,1149 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Groups using the consumer rebalance protocol of KIP-848, where the
+//! coordinator computes the assignment and each member reconciles towards
+//! it with ConsumerGroupHeartbeat alone. A member revokes the partitions
+//! that it is no longer assigned before moving to the new assignment epoch,
+//! and is only given a partition once its previous owner has released it.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, SystemTime},
+};
+
+use tansu_kafka_sans_io::{
+    consumer_group_describe_response::{self, DescribedGroup, Member},
+    consumer_group_heartbeat_request::TopicPartitions,
+    consumer_group_heartbeat_response::{self, Assignment},
+    primitive::uuid::Uuid as KafkaUuid,
+    Body, ErrorCode,
+};
+use tansu_storage::{ConsumerGroupDetail, ConsumerGroupMember, Storage, UpdateError};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::{authorizer::AUTHORIZED_OPERATIONS_OMITTED, Error, Result};
+
+use super::ConsumerGroupHeartbeat;
+
+/// The default of `group.consumer.session.timeout.ms`.
+pub const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_millis(45_000);
+
+/// The default of `group.consumer.heartbeat.interval.ms`.
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(5_000);
+
+/// The only server side assignor, giving each subscriber of a topic a
+/// contiguous range of its partitions.
+pub const RANGE_ASSIGNOR: &str = "range";
+
+const JOIN_GROUP_MEMBER_EPOCH: i32 = 0;
+const LEAVE_GROUP_MEMBER_EPOCH: i32 = -1;
+const LEAVE_GROUP_STATIC_MEMBER_EPOCH: i32 = -2;
+
+const UNKNOWN_REBALANCE_TIMEOUT_MS: i32 = -1;
+
+// partitions by topic name
+type Partitions = BTreeMap<String, BTreeSet<i32>>;
+
+// the id and partitions of every topic, by name
+type Topics = BTreeMap<String, (KafkaUuid, BTreeSet<i32>)>;
+
+#[derive(Clone, Debug)]
+pub struct ConsumerGroups<O> {
+    storage: O,
+    session_timeout: Duration,
+    heartbeat_interval: Duration,
+}
+
+impl<O> ConsumerGroups<O>
+where
+    O: Storage,
+{
+    pub fn with_storage(storage: O) -> Self {
+        Self {
+            storage,
+            session_timeout: DEFAULT_SESSION_TIMEOUT,
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
+        }
+    }
+
+    /// Remove members that haven't heartbeat within this timeout.
+    pub fn with_session_timeout(self, session_timeout: Duration) -> Self {
+        Self {
+            session_timeout,
+            ..self
+        }
+    }
+
+    /// The interval between heartbeats asked of each member.
+    pub fn with_heartbeat_interval(self, heartbeat_interval: Duration) -> Self {
+        Self {
+            heartbeat_interval,
+            ..self
+        }
+    }
+
+    async fn topics(&mut self) -> Result<Topics> {
+        self.storage
+            .metadata(None)
+            .await
+            .map(|metadata| {
+                metadata
+                    .topics()
+                    .iter()
+                    .filter(|topic| topic.error_code == i16::from(ErrorCode::None))
+                    .filter_map(|topic| {
+                        topic.name.clone().map(|name| {
+                            (
+                                name,
+                                (
+                                    topic.topic_id.unwrap_or(KafkaUuid::nil()),
+                                    topic
+                                        .partitions
+                                        .as_deref()
+                                        .unwrap_or_default()
+                                        .iter()
+                                        .map(|partition| partition.partition_index)
+                                        .collect(),
+                                ),
+                            )
+                        })
+                    })
+                    .collect()
+            })
+            .map_err(Into::into)
+    }
+
+    fn error(error_code: ErrorCode) -> Body {
+        Body::ConsumerGroupHeartbeatResponse {
+            throttle_time_ms: 0,
+            error_code: error_code.into(),
+            error_message: None,
+            member_id: None,
+            member_epoch: JOIN_GROUP_MEMBER_EPOCH,
+            heartbeat_interval_ms: 0,
+            assignment: None,
+            unknown_tagged_fields: vec![],
+        }
+    }
+
+    fn invalid(heartbeat: &ConsumerGroupHeartbeat<'_>) -> Option<ErrorCode> {
+        if heartbeat.group_id.is_empty() {
+            return Some(ErrorCode::InvalidRequest);
+        }
+
+        if heartbeat.member_epoch == JOIN_GROUP_MEMBER_EPOCH {
+            // a joining member subscribes, and doesn't own any partitions
+            if heartbeat.subscribed_topic_names.is_none()
+                || heartbeat.rebalance_timeout_ms == UNKNOWN_REBALANCE_TIMEOUT_MS
+                || heartbeat
+                    .topic_partitions
+                    .is_some_and(|topic_partitions| !topic_partitions.is_empty())
+            {
+                return Some(ErrorCode::InvalidRequest);
+            }
+        } else if heartbeat.member_id.is_empty() {
+            return Some(ErrorCode::InvalidRequest);
+        }
+
+        heartbeat
+            .server_assignor
+            .is_some_and(|server_assignor| server_assignor != RANGE_ASSIGNOR)
+            .then_some(ErrorCode::UnsupportedAssignor)
+    }
+
+    pub async fn heartbeat(
+        &mut self,
+        heartbeat: &ConsumerGroupHeartbeat<'_>,
+        now: SystemTime,
+    ) -> Result<Body> {
+        debug!(?heartbeat);
+
+        if let Some(error_code) = Self::invalid(heartbeat) {
+            return Ok(Self::error(error_code));
+        }
+
+        // a group id is not used by both protocols at the same time
+        if self
+            .storage
+            .group_detail(heartbeat.group_id)
+            .await?
+            .is_some_and(|classic| !classic.members.is_empty())
+        {
+            return Ok(Self::error(ErrorCode::GroupIdNotFound));
+        }
+
+        let topics = self.topics().await?;
+
+        let member_id = if heartbeat.member_id.is_empty() {
+            Uuid::new_v4().to_string()
+        } else {
+            heartbeat.member_id.to_owned()
+        };
+
+        let mut detail = self
+            .storage
+            .consumer_group_detail(heartbeat.group_id)
+            .await?
+            .unwrap_or_default();
+
+        let mut version = None;
+
+        loop {
+            let (updated, member_epoch, assignment) =
+                match beat(detail.clone(), heartbeat, &member_id, &topics, now) {
+                    Ok(outcome) => outcome,
+                    Err(error_code) => return Ok(Self::error(error_code)),
+                };
+
+            let group_epoch = updated.group_epoch;
+
+            match self
+                .storage
+                .update_consumer_group(heartbeat.group_id, updated, version)
+                .await
+            {
+                Ok(version) => {
+                    debug!(?version, ?group_epoch, ?member_epoch, ?assignment);
+
+                    return Ok(Body::ConsumerGroupHeartbeatResponse {
+                        throttle_time_ms: 0,
+                        error_code: ErrorCode::None.into(),
+                        error_message: None,
+                        member_id: Some(member_id),
+                        member_epoch,
+                        heartbeat_interval_ms: i32::try_from(self.heartbeat_interval.as_millis())?,
+                        assignment: assignment.map(|assignment| Assignment {
+                            topic_partitions: Some(
+                                assignment
+                                    .into_iter()
+                                    .filter_map(|(topic, partitions)| {
+                                        topics.get(&topic).map(|(topic_id, _)| {
+                                            consumer_group_heartbeat_response::TopicPartitions {
+                                                topic_id: *topic_id,
+                                                partitions: Some(partitions.into_iter().collect()),
+                                            }
+                                        })
+                                    })
+                                    .collect(),
+                            ),
+                        }),
+                        unknown_tagged_fields: vec![],
+                    });
+                }
+
+                Err(UpdateError::Outdated {
+                    current,
+                    version: outdated,
+                }) => {
+                    debug!(?current, ?outdated);
+
+                    detail = current;
+                    version = Some(outdated);
+                }
+
+                Err(UpdateError::Error(error)) => return Err(error.into()),
+
+                Err(UpdateError::ObjectStore(error)) => return Err(error.into()),
+
+                Err(UpdateError::SerdeJson(error)) => return Err(error.into()),
+
+                Err(UpdateError::TokioPostgres(error)) => return Err(error.into()),
+
+                Err(UpdateError::MissingEtag) => {
+                    return Err(Error::Message(String::from("missing e-tag")))
+                }
+
+                Err(UpdateError::Uuid(uuid)) => {
+                    return Err(Error::Message(format!("uuid: {uuid}")))
+                }
+            }
+        }
+    }
+
+    /// Whether this group has members using the consumer rebalance protocol.
+    pub async fn is_active(&mut self, group_id: &str) -> Result<bool> {
+        self.storage
+            .consumer_group_detail(group_id)
+            .await
+            .map(|detail| detail.is_some_and(|detail| !detail.members.is_empty()))
+            .map_err(Into::into)
+    }
+
+    pub async fn describe(
+        &mut self,
+        group_ids: Option<&[String]>,
+        include_authorized_operations: bool,
+    ) -> Result<Body> {
+        debug!(?group_ids, ?include_authorized_operations);
+
+        let topics = self.topics().await?;
+
+        let mut groups = vec![];
+
+        for group_id in group_ids.unwrap_or_default() {
+            groups.push(match self.storage.consumer_group_detail(group_id).await? {
+                Some(detail) => DescribedGroup {
+                    error_code: ErrorCode::None.into(),
+                    error_message: None,
+                    group_id: group_id.clone(),
+                    group_state: state(&detail).into(),
+                    group_epoch: detail.group_epoch,
+                    assignment_epoch: detail.assignment_epoch,
+                    assignor_name: RANGE_ASSIGNOR.into(),
+                    members: Some(
+                        detail
+                            .members
+                            .iter()
+                            .map(|(member_id, member)| describe_member(member_id, member, &topics))
+                            .collect(),
+                    ),
+                    authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+                },
+
+                None => DescribedGroup {
+                    error_code: ErrorCode::GroupIdNotFound.into(),
+                    error_message: None,
+                    group_id: group_id.clone(),
+                    group_state: "Dead".into(),
+                    group_epoch: 0,
+                    assignment_epoch: 0,
+                    assignor_name: "".into(),
+                    members: Some([].into()),
+                    authorized_operations: AUTHORIZED_OPERATIONS_OMITTED,
+                },
+            });
+        }
+
+        Ok(Body::ConsumerGroupDescribeResponse {
+            throttle_time_ms: 0,
+            groups: Some(groups),
+            unknown_tagged_fields: vec![],
+        })
+    }
+
+    /// Remove the members of every group that have missed their session
+    /// timeout by `now`, returning the groups that had members removed.
+    pub async fn expire(&mut self, now: SystemTime) -> Result<Vec<String>> {
+        let mut expired = vec![];
+
+        let group_ids = self.storage.list_consumer_groups().await?;
+
+        if group_ids.is_empty() {
+            return Ok(expired);
+        }
+
+        let topics = self.topics().await?;
+
+        for group_id in group_ids {
+            let Some(mut detail) = self.storage.consumer_group_detail(&group_id).await? else {
+                continue;
+            };
+
+            let mut version = None;
+
+            loop {
+                let mut updated = detail.clone();
+
+                updated.members.retain(|_, member| {
+                    member.last_contact.is_none_or(|last_contact| {
+                        now.duration_since(last_contact)
+                            .map_or(true, |elapsed| elapsed <= self.session_timeout)
+                    })
+                });
+
+                if updated.members.len() == detail.members.len() {
+                    break;
+                }
+
+                updated.group_epoch += 1;
+                retarget(&mut updated, &topics);
+
+                match self
+                    .storage
+                    .update_consumer_group(&group_id, updated, version)
+                    .await
+                {
+                    Ok(version) => {
+                        info!("expired: {group_id}, version: {version:?}");
+                        expired.push(group_id);
+                        break;
+                    }
+
+                    Err(UpdateError::Outdated {
+                        current,
+                        version: outdated,
+                    }) => {
+                        debug!(?group_id, ?current, ?outdated);
+
+                        detail = current;
+                        version = Some(outdated);
+                    }
+
+                    Err(UpdateError::Error(error)) => return Err(error.into()),
+
+                    Err(UpdateError::ObjectStore(error)) => return Err(error.into()),
+
+                    Err(UpdateError::SerdeJson(error)) => return Err(error.into()),
+
+                    Err(UpdateError::TokioPostgres(error)) => return Err(error.into()),
+
+                    Err(UpdateError::MissingEtag) => {
+                        return Err(Error::Message(String::from("missing e-tag")))
+                    }
+
+                    Err(UpdateError::Uuid(uuid)) => {
+                        return Err(Error::Message(format!("uuid: {uuid}")))
+                    }
+                }
+            }
+        }
+
+        Ok(expired)
+    }
+}
+
+fn flatten(partitions: &Partitions) -> BTreeSet<(String, i32)> {
+    partitions
+        .iter()
+        .flat_map(|(topic, partitions)| {
+            partitions
+                .iter()
+                .map(|partition| (topic.clone(), *partition))
+        })
+        .collect()
+}
+
+fn nest(flattened: BTreeSet<(String, i32)>) -> Partitions {
+    flattened
+        .into_iter()
+        .fold(Partitions::new(), |mut partitions, (topic, partition)| {
+            _ = partitions.entry(topic).or_default().insert(partition);
+            partitions
+        })
+}
+
+// the partitions that a member has been told that it may own, which
+// excludes any that it is being asked to revoke
+fn told(member: &ConsumerGroupMember) -> Partitions {
+    nest(
+        flatten(&member.assigned)
+            .intersection(&flatten(&member.target))
+            .cloned()
+            .collect(),
+    )
+}
+
+// the partitions that a member reports that it owns, by topic name
+fn owned(topic_partitions: &[TopicPartitions], topics: &Topics) -> Partitions {
+    topic_partitions
+        .iter()
+        .filter_map(|owned| {
+            topics
+                .iter()
+                .find(|(_, (topic_id, _))| *topic_id == owned.topic_id)
+                .map(|(topic, _)| {
+                    (
+                        topic.clone(),
+                        owned
+                            .partitions
+                            .as_deref()
+                            .unwrap_or_default()
+                            .iter()
+                            .copied()
+                            .collect::<BTreeSet<_>>(),
+                    )
+                })
+        })
+        .filter(|(_, partitions)| !partitions.is_empty())
+        .collect()
+}
+
+// give each subscriber of a topic a contiguous range of its partitions,
+// with the first subscribers taking any remainder
+fn range(
+    members: &BTreeMap<String, ConsumerGroupMember>,
+    topics: &Topics,
+) -> BTreeMap<String, Partitions> {
+    let mut targets = members
+        .keys()
+        .map(|member_id| (member_id.clone(), Partitions::new()))
+        .collect::<BTreeMap<_, _>>();
+
+    for (topic, (_, partitions)) in topics {
+        let subscribers = members
+            .iter()
+            .filter(|(_, member)| member.subscribed_topic_names.contains(topic))
+            .map(|(member_id, _)| member_id)
+            .collect::<Vec<_>>();
+
+        if subscribers.is_empty() {
+            continue;
+        }
+
+        let quotient = partitions.len() / subscribers.len();
+        let remainder = partitions.len() % subscribers.len();
+
+        let mut partitions = partitions.iter().copied();
+
+        for (index, member_id) in subscribers.into_iter().enumerate() {
+            let range = partitions
+                .by_ref()
+                .take(quotient + usize::from(index < remainder))
+                .collect::<BTreeSet<_>>();
+
+            if !range.is_empty() {
+                _ = targets
+                    .entry(member_id.clone())
+                    .or_default()
+                    .insert(topic.clone(), range);
+            }
+        }
+    }
+
+    targets
+}
+
+// recompute the target of every member, with a new assignment epoch when
+// the group epoch has moved on, or the topics subscribed have changed
+fn retarget(detail: &mut ConsumerGroupDetail, topics: &Topics) {
+    let mut targets = range(&detail.members, topics);
+
+    let changed = detail
+        .members
+        .iter()
+        .any(|(member_id, member)| targets.get(member_id) != Some(&member.target));
+
+    if changed && detail.group_epoch == detail.assignment_epoch {
+        detail.group_epoch += 1;
+    }
+
+    if detail.group_epoch > detail.assignment_epoch {
+        detail.assignment_epoch = detail.group_epoch;
+
+        for (member_id, member) in detail.members.iter_mut() {
+            member.target = targets.remove(member_id).unwrap_or_default();
+        }
+    }
+}
+
+// reconcile a member towards its target, returning its epoch. A partition
+// that is no longer targeted is held until the member reports that it no
+// longer owns it, with the member only moving to the assignment epoch once
+// all of them have been revoked. A newly targeted partition is only given
+// once released by any other member.
+fn reconcile(detail: &mut ConsumerGroupDetail, member_id: &str, owned: Option<&Partitions>) -> i32 {
+    let held = detail
+        .members
+        .iter()
+        .filter(|(id, _)| id.as_str() != member_id)
+        .flat_map(|(_, member)| flatten(&member.assigned))
+        .collect::<BTreeSet<_>>();
+
+    let assignment_epoch = detail.assignment_epoch;
+
+    let Some(member) = detail.members.get_mut(member_id) else {
+        return LEAVE_GROUP_MEMBER_EPOCH;
+    };
+
+    let target = flatten(&member.target);
+    let mut assigned = flatten(&member.assigned);
+
+    if let Some(owned) = owned.map(flatten) {
+        assigned.retain(|partition| target.contains(partition) || owned.contains(partition));
+    }
+
+    if assigned.is_subset(&target) {
+        member.member_epoch = assignment_epoch;
+        assigned.extend(target.difference(&held).cloned());
+    }
+
+    member.assigned = nest(assigned);
+    member.member_epoch
+}
+
+// a heartbeat on the detail of a group, returning the updated detail with
+// the epoch of the member, and its assignment when it has changed
+fn beat(
+    mut detail: ConsumerGroupDetail,
+    heartbeat: &ConsumerGroupHeartbeat<'_>,
+    member_id: &str,
+    topics: &Topics,
+    now: SystemTime,
+) -> std::result::Result<(ConsumerGroupDetail, i32, Option<Partitions>), ErrorCode> {
+    let mut changed = false;
+
+    match heartbeat.member_epoch {
+        LEAVE_GROUP_MEMBER_EPOCH | LEAVE_GROUP_STATIC_MEMBER_EPOCH => {
+            if detail.members.remove(member_id).is_none() {
+                return Err(ErrorCode::UnknownMemberId);
+            }
+
+            detail.group_epoch += 1;
+            retarget(&mut detail, topics);
+
+            return Ok((detail, heartbeat.member_epoch, None));
+        }
+
+        JOIN_GROUP_MEMBER_EPOCH => {
+            // a member that rejoins has lost any partitions that it owned
+            _ = detail
+                .members
+                .insert(member_id.to_owned(), ConsumerGroupMember::default());
+
+            changed = true;
+        }
+
+        member_epoch => match detail.members.get(member_id) {
+            None => return Err(ErrorCode::UnknownMemberId),
+
+            Some(member) if member.member_epoch != member_epoch => {
+                return Err(ErrorCode::FencedMemberEpoch)
+            }
+
+            Some(_) => (),
+        },
+    }
+
+    let Some(member) = detail.members.get_mut(member_id) else {
+        return Err(ErrorCode::UnknownMemberId);
+    };
+
+    let previously = (member.member_epoch, member.assigned.clone());
+
+    member.last_contact = Some(now);
+    member.client_id = heartbeat.client_id.map(ToOwned::to_owned);
+    member.client_host = heartbeat.client_host.map(ToOwned::to_owned);
+
+    if heartbeat.rebalance_timeout_ms != UNKNOWN_REBALANCE_TIMEOUT_MS {
+        member.rebalance_timeout_ms = heartbeat.rebalance_timeout_ms;
+    }
+
+    if let Some(instance_id) = heartbeat.instance_id {
+        member.instance_id = Some(instance_id.to_owned());
+    }
+
+    if let Some(rack_id) = heartbeat.rack_id {
+        member.rack_id = Some(rack_id.to_owned());
+    }
+
+    if let Some(server_assignor) = heartbeat.server_assignor {
+        member.server_assignor = Some(server_assignor.to_owned());
+    }
+
+    if let Some(subscribed_topic_names) = heartbeat.subscribed_topic_names {
+        let subscribed_topic_names = subscribed_topic_names
+            .iter()
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        if member.subscribed_topic_names != subscribed_topic_names {
+            member.subscribed_topic_names = subscribed_topic_names;
+            changed = true;
+        }
+    }
+
+    if changed {
+        detail.group_epoch += 1;
+    }
+
+    retarget(&mut detail, topics);
+
+    let owned = heartbeat
+        .topic_partitions
+        .map(|topic_partitions| owned(topic_partitions, topics));
+
+    let member_epoch = reconcile(&mut detail, member_id, owned.as_ref());
+
+    // the assignment is sent while partitions are being revoked, or when the
+    // member has moved on, or doesn't own what it was previously told
+    let assignment = detail.members.get(member_id).and_then(|member| {
+        let assignment = told(member);
+
+        (heartbeat.member_epoch == JOIN_GROUP_MEMBER_EPOCH
+            || (member.member_epoch, &member.assigned) != (previously.0, &previously.1)
+            || assignment != member.assigned
+            || owned.as_ref().is_some_and(|owned| *owned != assignment))
+        .then_some(assignment)
+    });
+
+    Ok((detail, member_epoch, assignment))
+}
+
+// the state of a group, as "Empty", "Reconciling" or "Stable"
+fn state(detail: &ConsumerGroupDetail) -> &'static str {
+    if detail.members.is_empty() {
+        "Empty"
+    } else if detail.members.values().all(|member| {
+        member.member_epoch == detail.assignment_epoch && member.assigned == member.target
+    }) {
+        "Stable"
+    } else {
+        "Reconciling"
+    }
+}
+
+fn describe_assignment(
+    partitions: &Partitions,
+    topics: &Topics,
+) -> consumer_group_describe_response::Assignment {
+    consumer_group_describe_response::Assignment {
+        topic_partitions: Some(
+            partitions
+                .iter()
+                .map(
+                    |(topic, partitions)| consumer_group_describe_response::TopicPartitions {
+                        topic_id: topics
+                            .get(topic)
+                            .map_or(KafkaUuid::nil(), |(topic_id, _)| *topic_id),
+                        topic_name: topic.clone(),
+                        partitions: Some(partitions.iter().copied().collect()),
+                    },
+                )
+                .collect(),
+        ),
+    }
+}
+
+fn describe_member(member_id: &str, member: &ConsumerGroupMember, topics: &Topics) -> Member {
+    Member {
+        member_id: member_id.to_owned(),
+        instance_id: member.instance_id.clone(),
+        rack_id: member.rack_id.clone(),
+        member_epoch: member.member_epoch,
+        client_id: member.client_id.clone().unwrap_or_default(),
+        client_host: member.client_host.clone().unwrap_or_default(),
+        subscribed_topic_names: Some(member.subscribed_topic_names.clone()),
+        subscribed_topic_regex: None,
+        assignment: describe_assignment(&member.assigned, topics),
+        target_assignment: describe_assignment(&member.target, topics),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        coordinator::group::{administrator::Controller, Coordinator},
+        fixture::storage_with_topic,
+    };
+    use tansu_kafka_sans_io::join_group_request::JoinGroupRequestProtocol;
+    use tansu_storage::{dynostore::DynoStore, GroupDetail, GroupMember, TopicId};
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const GROUP_ID: &str = "abc";
+    const TOPIC: &str = "pqr";
+
+    async fn topic_id(storage: &mut DynoStore) -> Result<KafkaUuid> {
+        storage
+            .metadata(Some(&[TopicId::Name(TOPIC.into())]))
+            .await
+            .map(|metadata| {
+                metadata
+                    .topics()
+                    .first()
+                    .and_then(|topic| topic.topic_id)
+                    .unwrap_or(KafkaUuid::nil())
+            })
+            .map_err(Into::into)
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Beat {
+        error_code: ErrorCode,
+        member_id: Option<String>,
+        member_epoch: i32,
+        assignment: Option<Vec<i32>>,
+    }
+
+    async fn heartbeat(
+        groups: &mut ConsumerGroups<DynoStore>,
+        member_id: &str,
+        member_epoch: i32,
+        owned: Option<&[i32]>,
+    ) -> Result<Beat> {
+        let topic_id = topic_id(&mut groups.storage).await?;
+        let subscribed_topic_names = [String::from(TOPIC)];
+
+        let topic_partitions = owned.map(|owned| {
+            if owned.is_empty() {
+                vec![]
+            } else {
+                vec![TopicPartitions {
+                    topic_id,
+                    partitions: Some(owned.to_vec()),
+                }]
+            }
+        });
+
+        let body = groups
+            .heartbeat(
+                &ConsumerGroupHeartbeat {
+                    client_id: Some("console-consumer"),
+                    client_host: Some("localhost"),
+                    group_id: GROUP_ID,
+                    member_id,
+                    member_epoch,
+                    instance_id: None,
+                    rack_id: None,
+                    rebalance_timeout_ms: 300_000,
+                    subscribed_topic_names: (member_epoch == JOIN_GROUP_MEMBER_EPOCH)
+                        .then_some(&subscribed_topic_names[..]),
+                    server_assignor: None,
+                    topic_partitions: topic_partitions.as_deref(),
+                },
+                SystemTime::now(),
+            )
+            .await?;
+
+        let Body::ConsumerGroupHeartbeatResponse {
+            error_code,
+            member_id,
+            member_epoch,
+            assignment,
+            ..
+        } = body
+        else {
+            panic!("{body:?}")
+        };
+
+        Ok(Beat {
+            error_code: ErrorCode::try_from(error_code)?,
+            member_id,
+            member_epoch,
+            assignment: assignment.map(|assignment| {
+                assignment
+                    .topic_partitions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .inspect(|topic_partitions| assert_eq!(topic_id, topic_partitions.topic_id))
+                    .flat_map(|topic_partitions| topic_partitions.partitions.unwrap_or_default())
+                    .collect()
+            }),
+        })
+    }
+
+    async fn join(
+        groups: &mut ConsumerGroups<DynoStore>,
+        member_id: &str,
+    ) -> Result<(String, Beat)> {
+        let beat = heartbeat(groups, member_id, JOIN_GROUP_MEMBER_EPOCH, Some(&[])).await?;
+        assert_eq!(ErrorCode::None, beat.error_code);
+
+        beat.member_id
+            .clone()
+            .map(|member_id| (member_id, beat))
+            .ok_or(Error::Message(String::from("member id")))
+    }
+
+    #[tokio::test]
+    async fn join_and_assign() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_topic(TOPIC, 3).await?;
+        let mut groups = ConsumerGroups::with_storage(storage);
+
+        // a member id is given to a member that joins without one
+        let (member_id, beat) = join(&mut groups, "").await?;
+        assert_eq!(1, beat.member_epoch);
+        assert_eq!(Some(vec![0, 1, 2]), beat.assignment);
+
+        // an unchanged assignment isn't sent again
+        assert_eq!(
+            Beat {
+                error_code: ErrorCode::None,
+                member_id: Some(member_id.clone()),
+                member_epoch: 1,
+                assignment: None,
+            },
+            heartbeat(&mut groups, &member_id, 1, Some(&[0, 1, 2])).await?
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn revoke_before_reassign() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_topic(TOPIC, 3).await?;
+        let mut groups = ConsumerGroups::with_storage(storage);
+
+        let (first, beat) = join(&mut groups, "first").await?;
+        assert_eq!(Some(vec![0, 1, 2]), beat.assignment);
+
+        // partition 2 is targeted to the second member, but is still owned by the first
+        let (second, beat) = join(&mut groups, "second").await?;
+        assert_eq!(2, beat.member_epoch);
+        assert_eq!(Some(vec![]), beat.assignment);
+
+        // the first member is asked to revoke partition 2 in its current epoch
+        let beat = heartbeat(&mut groups, &first, 1, None).await?;
+        assert_eq!(1, beat.member_epoch);
+        assert_eq!(Some(vec![0, 1]), beat.assignment);
+
+        let beat = heartbeat(&mut groups, &second, 2, None).await?;
+        assert_eq!(None, beat.assignment);
+
+        // once revoked, the first member moves to the assignment epoch
+        let beat = heartbeat(&mut groups, &first, 1, Some(&[0, 1])).await?;
+        assert_eq!(2, beat.member_epoch);
+        assert_eq!(Some(vec![0, 1]), beat.assignment);
+
+        let beat = heartbeat(&mut groups, &first, 2, Some(&[0, 1])).await?;
+        assert_eq!(None, beat.assignment);
+
+        // with partition 2 released to the second member
+        let beat = heartbeat(&mut groups, &second, 2, None).await?;
+        assert_eq!(2, beat.member_epoch);
+        assert_eq!(Some(vec![2]), beat.assignment);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fenced_and_unknown_members() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_topic(TOPIC, 3).await?;
+        let mut groups = ConsumerGroups::with_storage(storage);
+
+        let (member_id, _) = join(&mut groups, "first").await?;
+
+        assert_eq!(
+            ErrorCode::FencedMemberEpoch,
+            heartbeat(&mut groups, &member_id, 5, None)
+                .await?
+                .error_code
+        );
+
+        assert_eq!(
+            ErrorCode::UnknownMemberId,
+            heartbeat(&mut groups, "unknown", 1, None).await?.error_code
+        );
+
+        assert_eq!(
+            ErrorCode::InvalidRequest,
+            heartbeat(&mut groups, "", 1, None).await?.error_code
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn leave_reassigns() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_topic(TOPIC, 2).await?;
+        let mut groups = ConsumerGroups::with_storage(storage);
+
+        let (first, _) = join(&mut groups, "first").await?;
+        let (second, _) = join(&mut groups, "second").await?;
+
+        let beat = heartbeat(&mut groups, &first, 1, Some(&[0])).await?;
+        assert_eq!(2, beat.member_epoch);
+
+        let beat = heartbeat(&mut groups, &second, LEAVE_GROUP_MEMBER_EPOCH, None).await?;
+        assert_eq!(ErrorCode::None, beat.error_code);
+        assert_eq!(LEAVE_GROUP_MEMBER_EPOCH, beat.member_epoch);
+
+        let beat = heartbeat(&mut groups, &first, 2, None).await?;
+        assert_eq!(3, beat.member_epoch);
+        assert_eq!(Some(vec![0, 1]), beat.assignment);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn one_protocol_per_group() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let mut storage = storage_with_topic(TOPIC, 1).await?;
+
+        // a classic group with a member
+        _ = storage
+            .update_group(
+                "classic",
+                GroupDetail {
+                    members: [(String::from("member"), GroupMember::default())].into(),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .map_err(|error| Error::Message(format!("{error:?}")))?;
+
+        let body = ConsumerGroups::with_storage(storage.clone())
+            .heartbeat(
+                &ConsumerGroupHeartbeat {
+                    client_id: None,
+                    client_host: None,
+                    group_id: "classic",
+                    member_id: "",
+                    member_epoch: JOIN_GROUP_MEMBER_EPOCH,
+                    instance_id: None,
+                    rack_id: None,
+                    rebalance_timeout_ms: 300_000,
+                    subscribed_topic_names: Some(&[String::from(TOPIC)]),
+                    server_assignor: None,
+                    topic_partitions: Some(&[]),
+                },
+                SystemTime::now(),
+            )
+            .await?;
+
+        assert!(matches!(
+            body,
+            Body::ConsumerGroupHeartbeatResponse { error_code, .. }
+            if error_code == i16::from(ErrorCode::GroupIdNotFound)
+        ));
+
+        // a classic join of a group with consumer protocol members
+        _ = join(&mut ConsumerGroups::with_storage(storage.clone()), "first").await?;
+
+        let body = Controller::with_storage(storage)?
+            .join(
+                Some("console-consumer"),
+                None,
+                GROUP_ID,
+                45_000,
+                Some(300_000),
+                "",
+                None,
+                "consumer",
+                Some(&[JoinGroupRequestProtocol {
+                    name: "range".into(),
+                    metadata: Default::default(),
+                }]),
+                None,
+            )
+            .await?;
+
+        assert!(matches!(
+            body,
+            Body::JoinGroupResponse { error_code, .. }
+            if error_code == i16::from(ErrorCode::InconsistentGroupProtocol)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn describe_and_expire() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let storage = storage_with_topic(TOPIC, 2).await?;
+        let mut groups =
+            ConsumerGroups::with_storage(storage).with_session_timeout(Duration::from_secs(10));
+
+        let (first, _) = join(&mut groups, "first").await?;
+        let (second, _) = join(&mut groups, "second").await?;
+
+        let describe = |groups: &mut ConsumerGroups<DynoStore>| {
+            let mut groups = groups.clone();
+
+            async move {
+                let body = groups
+                    .describe(Some(&[GROUP_ID.into(), "unknown".into()]), false)
+                    .await?;
+
+                let Body::ConsumerGroupDescribeResponse {
+                    groups: Some(groups),
+                    ..
+                } = body
+                else {
+                    panic!("{body:?}")
+                };
+
+                Ok::<_, Error>(groups)
+            }
+        };
+
+        let described = describe(&mut groups).await?;
+        assert_eq!(2, described.len());
+
+        assert_eq!(i16::from(ErrorCode::None), described[0].error_code);
+        assert_eq!("Reconciling", described[0].group_state);
+        assert_eq!(2, described[0].group_epoch);
+        assert_eq!(2, described[0].assignment_epoch);
+        assert_eq!(RANGE_ASSIGNOR, described[0].assignor_name);
+
+        let members = described[0].members.as_deref().unwrap_or_default();
+        assert_eq!(
+            BTreeSet::from([first.as_str(), second.as_str()]),
+            members
+                .iter()
+                .map(|member| member.member_id.as_str())
+                .collect()
+        );
+
+        assert_eq!(
+            i16::from(ErrorCode::GroupIdNotFound),
+            described[1].error_code
+        );
+
+        // both members expire once their session times out
+        assert!(groups.expire(SystemTime::now()).await?.is_empty());
+
+        assert_eq!(
+            vec![String::from(GROUP_ID)],
+            groups
+                .expire(SystemTime::now() + Duration::from_secs(11))
+                .await?
+        );
+
+        let described = describe(&mut groups).await?;
+        assert_eq!("Empty", described[0].group_state);
+        assert_eq!(3, described[0].group_epoch);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Broker liveness. Each broker heartbeats its registration in storage,
+//! with those that miss their `broker.session.timeout.ms` being fenced, so
+//! that a crashed broker is no longer advertised in metadata.
+
+use std::time::{Duration, SystemTime};
+
+use tansu_kafka_sans_io::ErrorCode;
+use tansu_storage::Storage;
+use tokio::time::interval;
+use tracing::{debug, warn};
+
+use crate::{Error, Result};
+
+pub const BROKER_SESSION_TIMEOUT: Duration = Duration::from_millis(9_000);
+pub const BROKER_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(2_000);
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Liveness<S> {
+    storage: S,
+    session_timeout: Duration,
+}
+
+impl<S> Liveness<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self {
+            storage,
+            session_timeout: BROKER_SESSION_TIMEOUT,
+        }
+    }
+
+    /// Fence brokers that haven't heartbeat within this timeout.
+    pub fn with_session_timeout(self, session_timeout: Duration) -> Self {
+        Self {
+            session_timeout,
+            ..self
+        }
+    }
+
+    /// Periodically heartbeat the registration of this broker, until it is
+    /// replaced by another registration.
+    pub async fn heartbeat(
+        mut self,
+        broker_id: i32,
+        broker_epoch: i64,
+        every: Duration,
+    ) -> Result<()> {
+        let mut interval = interval(every);
+
+        loop {
+            _ = interval.tick().await;
+
+            match self
+                .storage
+                .broker_heartbeat(broker_id, broker_epoch, false)
+                .await
+            {
+                Ok(ErrorCode::None) => (),
+                Ok(error_code) => return Err(Error::Api(error_code)),
+                Err(error) => warn!(?error),
+            }
+        }
+    }
+
+    /// Periodically fence the brokers that have missed their session timeout.
+    pub async fn fence_brokers(mut self, every: Duration) -> Result<()> {
+        let mut interval = interval(every);
+
+        loop {
+            _ = interval.tick().await;
+
+            if let Err(error) = self.fence(SystemTime::now()).await {
+                warn!(?error);
+            }
+        }
+    }
+
+    /// Fence the brokers without a heartbeat within the session timeout by
+    /// `now`, returning their ids.
+    pub async fn fence(&mut self, now: SystemTime) -> Result<Vec<i32>> {
+        self.storage
+            .fence_brokers(now, self.session_timeout)
+            .await
+            .inspect(|fenced| debug!(?fenced))
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::{memory::InMemory, ObjectStore};
+    use std::sync::Arc;
+    use tansu_kafka_sans_io::broker_registration_request::Listener;
+    use tansu_storage::{dynostore::DynoStore, BrokerRegistationRequest};
+    use tracing::subscriber::DefaultGuard;
+    use uuid::Uuid;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    const CLUSTER: &str = "abc";
+
+    async fn register(
+        object_store: Arc<dyn ObjectStore>,
+        broker_id: i32,
+    ) -> Result<(DynoStore, i64)> {
+        let mut storage = DynoStore::new(CLUSTER, broker_id, object_store);
+
+        let broker_epoch = storage
+            .register_broker(BrokerRegistationRequest {
+                broker_id,
+                cluster_id: CLUSTER.into(),
+                incarnation_id: Uuid::new_v4(),
+                listeners: vec![Listener {
+                    name: "broker".into(),
+                    host: "localhost".into(),
+                    port: 9092,
+                    security_protocol: 0,
+                }],
+                features: vec![],
+                rack: None,
+            })
+            .await?;
+
+        Ok((storage, broker_epoch))
+    }
+
+    async fn advertised(storage: &mut DynoStore) -> Result<Vec<i32>> {
+        storage
+            .metadata(None)
+            .await
+            .map(|metadata| {
+                metadata
+                    .brokers()
+                    .iter()
+                    .map(|broker| broker.node_id)
+                    .collect()
+            })
+            .map_err(Into::into)
+    }
+
+    #[tokio::test]
+    async fn expired_broker_fenced() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+
+        let (mut alive, alive_epoch) = register(object_store.clone(), 111).await?;
+        let (_crashed, _) = register(object_store, 222).await?;
+
+        assert_eq!(vec![111, 222], advertised(&mut alive).await?);
+
+        let session_timeout = Duration::from_millis(500);
+        let mut liveness =
+            Liveness::with_storage(alive.clone()).with_session_timeout(session_timeout);
+
+        let now = SystemTime::now();
+        assert!(liveness.fence(now).await?.is_empty());
+
+        tokio::time::sleep(session_timeout).await;
+
+        assert_eq!(
+            ErrorCode::None,
+            alive.broker_heartbeat(111, alive_epoch, false).await?
+        );
+
+        assert_eq!(vec![222], liveness.fence(SystemTime::now()).await?);
+        assert_eq!(vec![111], advertised(&mut alive).await?);
+        assert_eq!(
+            vec![111],
+            alive
+                .brokers(None)
+                .await?
+                .into_iter()
+                .map(|broker| broker.broker_id)
+                .collect::<Vec<_>>()
+        );
+
+        // already fenced
+        assert!(liveness.fence(SystemTime::now()).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reregistration_fences_earlier_incarnation() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let object_store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+
+        let (mut earlier, earlier_epoch) = register(object_store.clone(), 111).await?;
+        let (_later, later_epoch) = register(object_store, 111).await?;
+
+        assert!(later_epoch > earlier_epoch);
+
+        assert_eq!(
+            ErrorCode::StaleBrokerEpoch,
+            earlier.broker_heartbeat(111, earlier_epoch, false).await?
+        );
+
+        assert!(matches!(
+            Liveness::with_storage(earlier.clone())
+                .heartbeat(111, earlier_epoch, Duration::from_millis(10))
+                .await,
+            Err(Error::Api(ErrorCode::StaleBrokerEpoch))
+        ));
+
+        assert_eq!(
+            ErrorCode::BrokerIdNotRegistered,
+            earlier.broker_heartbeat(333, 0, false).await?
+        );
+
+        Ok(())
+    }
+}
@@ -12,3 +12,188 @@
 //
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The transaction coordinator. Transactions are held in storage, with
+//! their producers fenced by epoch, so that this only needs to abort those
+//! that exceed their `transaction.timeout.ms`.
+
+use std::time::{Duration, SystemTime};
+
+use tansu_storage::Storage;
+use tokio::time::interval;
+use tracing::{debug, warn};
+
+use crate::Result;
+
+pub const TRANSACTION_EXPIRY_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Transactions<S> {
+    storage: S,
+}
+
+impl<S> Transactions<S>
+where
+    S: Storage,
+{
+    pub fn with_storage(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Periodically abort the transactions that have timed out, so that a
+    /// hung producer doesn't hold back the last stable offset.
+    pub async fn expire_transactions(mut self, every: Duration) -> Result<()> {
+        let mut interval = interval(every);
+
+        loop {
+            _ = interval.tick().await;
+
+            if let Err(error) = self.expire(SystemTime::now()).await {
+                warn!(?error);
+            }
+        }
+    }
+
+    /// Abort the transactions that have timed out by `now`, returning their
+    /// transactional ids.
+    pub async fn expire(&mut self, now: SystemTime) -> Result<Vec<String>> {
+        self.storage
+            .txn_expire(now)
+            .await
+            .inspect(|expired| debug!(?expired))
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use bytes::Bytes;
+    use object_store::memory::InMemory;
+    use tansu_kafka_sans_io::{
+        create_topics_request::CreatableTopic,
+        record::{deflated, inflated, Record},
+        Ack, ErrorCode,
+    };
+    use tansu_storage::{dynostore::DynoStore, Topition};
+    use tracing::subscriber::DefaultGuard;
+
+    #[cfg(miri)]
+    fn init_tracing() -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    fn init_tracing() -> Result<DefaultGuard> {
+        use std::{fs::File, sync::Arc, thread};
+
+        use tracing::Level;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        Ok(tracing::subscriber::set_default(
+            tracing_subscriber::fmt()
+                .with_level(true)
+                .with_line_number(true)
+                .with_thread_names(false)
+                .with_max_level(Level::DEBUG)
+                .with_span_events(FmtSpan::ACTIVE)
+                .with_writer(
+                    thread::current()
+                        .name()
+                        .ok_or(Error::Custom(String::from("unnamed thread")))
+                        .and_then(|name| {
+                            File::create(format!("../logs/{}/{name}.log", env!("CARGO_PKG_NAME")))
+                                .map_err(Into::into)
+                        })
+                        .map(Arc::new)?,
+                )
+                .finish(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn hung_transaction_aborted() -> Result<()> {
+        let _guard = init_tracing()?;
+
+        let transactional_id = "txn-abc";
+        let topition = Topition::new("pqr", 0);
+        let transaction_timeout_ms = 100;
+
+        let mut storage = DynoStore::new("abc", 12321, InMemory::new());
+
+        _ = storage
+            .create_topic(
+                CreatableTopic {
+                    name: topition.topic().into(),
+                    num_partitions: 1,
+                    replication_factor: 1,
+                    assignments: Some([].into()),
+                    configs: Some([].into()),
+                },
+                false,
+            )
+            .await?;
+
+        let producer = storage
+            .init_producer(
+                Some(transactional_id),
+                transaction_timeout_ms,
+                Some(-1),
+                Some(-1),
+            )
+            .await?;
+
+        assert_eq!(
+            ErrorCode::None,
+            storage
+                .txn_add_partitions(
+                    transactional_id,
+                    producer.id,
+                    producer.epoch,
+                    std::slice::from_ref(&topition),
+                )
+                .await?
+        );
+
+        let batch = inflated::Batch::builder()
+            .record(Record::builder().value(Bytes::from_static(b"lorem").into()))
+            .producer_id(producer.id)
+            .producer_epoch(producer.epoch)
+            .base_sequence(0)
+            .transactional(true)
+            .build()
+            .and_then(deflated::Batch::try_from)?;
+
+        _ = storage.produce(&topition, batch, Ack::FullIsr).await?;
+        assert_eq!(0, storage.offset_stage(&topition).await?.last_stable());
+
+        let mut transactions = Transactions::with_storage(storage.clone());
+
+        let now = SystemTime::now();
+        assert!(transactions.expire(now).await?.is_empty());
+
+        let timeout = Duration::from_millis(u64::try_from(transaction_timeout_ms)?);
+        assert_eq!(
+            vec![transactional_id.to_owned()],
+            transactions.expire(now + timeout).await?
+        );
+
+        // the abort marker advances the last stable offset past the transaction
+        let offset_stage = storage.offset_stage(&topition).await?;
+        assert_eq!(2, offset_stage.high_watermark());
+        assert_eq!(2, offset_stage.last_stable());
+
+        // with the producer of the transaction fenced
+        assert_eq!(
+            ErrorCode::ProducerFenced,
+            storage
+                .txn_end(transactional_id, producer.id, producer.epoch, true)
+                .await?
+        );
+
+        assert!(transactions.expire(now + timeout).await?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Storage shared by the tests of the broker and its coordinators.
+
+use crate::{
+    broker::{init_producer_id::InitProducerIdRequest, txn::add_partitions::AddPartitions},
+    Result,
+};
+use object_store::memory::InMemory;
+use tansu_kafka_sans_io::{
+    add_partitions_to_txn_request::AddPartitionsToTxnTopic,
+    broker_registration_request::Listener,
+    create_topics_request::{CreatableTopic, CreateableTopicConfig},
+};
+use tansu_storage::{dynostore::DynoStore, BrokerRegistationRequest, Storage};
+use uuid::Uuid;
+
+pub(crate) const CLUSTER: &str = "abc";
+pub(crate) const NODE: i32 = 12321;
+
+/// In memory storage with this broker registered, so that metadata places
+/// partitions on it, and a topic with these partitions.
+pub(crate) async fn storage_with_topic(topic: &str, num_partitions: i32) -> Result<DynoStore> {
+    storage_with_configs(topic, num_partitions, &[]).await
+}
+
+/// As [`storage_with_topic`], with the topic created with these configs.
+pub(crate) async fn storage_with_configs(
+    topic: &str,
+    num_partitions: i32,
+    configs: &[(&str, &str)],
+) -> Result<DynoStore> {
+    let mut storage = DynoStore::new(CLUSTER, NODE, InMemory::new());
+
+    _ = storage
+        .register_broker(BrokerRegistationRequest {
+            broker_id: NODE,
+            cluster_id: CLUSTER.into(),
+            incarnation_id: Uuid::new_v4(),
+            listeners: vec![Listener {
+                name: "broker".into(),
+                host: "localhost".into(),
+                port: 9092,
+                security_protocol: 0,
+            }],
+            features: vec![],
+            rack: None,
+        })
+        .await?;
+
+    _ = storage
+        .create_topic(
+            CreatableTopic {
+                name: topic.into(),
+                num_partitions,
+                replication_factor: 1,
+                assignments: Some([].into()),
+                configs: Some(
+                    configs
+                        .iter()
+                        .map(|(name, value)| CreateableTopicConfig {
+                            name: (*name).into(),
+                            value: Some((*value).into()),
+                        })
+                        .collect(),
+                ),
+            },
+            false,
+        )
+        .await?;
+
+    Ok(storage)
+}
+
+/// A transactional producer that has added the first partition of this
+/// topic to its transaction, returning its producer id.
+pub(crate) async fn ongoing(
+    storage: &DynoStore,
+    transactional_id: &str,
+    topic: &str,
+) -> Result<i64> {
+    let producer = InitProducerIdRequest::with_storage(storage.clone())
+        .response(Some(transactional_id), 60_000, Some(-1), Some(-1))
+        .await?;
+
+    _ = AddPartitions::with_storage(storage.clone())
+        .response(
+            None,
+            Some(transactional_id.into()),
+            Some(producer.id),
+            Some(producer.epoch),
+            Some(vec![AddPartitionsToTxnTopic {
+                name: topic.into(),
+                partitions: Some(vec![0]),
+            }]),
+        )
+        .await?;
+
+    Ok(producer.id)
+}
@@ -38,8 +38,16 @@ use thiserror::Error;
 use url::Url;
 use uuid::Uuid;
 
+pub mod authorizer;
 pub mod broker;
+pub mod client;
+pub mod config;
 pub mod coordinator;
+#[cfg(test)]
+mod fixture;
+pub mod metrics;
+#[cfg(feature = "otlp")]
+pub mod otel;
 
 #[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum TopicId {
@@ -47,14 +55,18 @@ pub enum TopicId {
     Id(Uuid),
 }
 
-impl From<&FetchTopic> for TopicId {
-    fn from(value: &FetchTopic) -> Self {
+impl TryFrom<&FetchTopic> for TopicId {
+    type Error = Error;
+
+    fn try_from(value: &FetchTopic) -> Result<Self, Self::Error> {
         if let Some(ref topic) = value.topic {
-            Self::Name(topic.to_string())
-        } else if let Some(topic_id) = value.topic_id {
-            Self::Id(Uuid::from_bytes(topic_id))
+            Ok(Self::Name(topic.to_string()))
         } else {
-            todo!()
+            value
+                .topic_id
+                .filter(|topic_id| !topic_id.is_nil())
+                .map(|topic_id| Self::Id(topic_id.into()))
+                .ok_or(Error::Api(ErrorCode::UnknownTopicId))
         }
     }
 }
@@ -159,8 +171,11 @@ pub enum Error {
     ParseInt(#[from] std::num::ParseIntError),
     Poison,
     Pool(#[from] deadpool_postgres::PoolError),
+    Prometheus(#[from] prometheus::Error),
     Storage(#[from] tansu_storage::Error),
     StringUtf8(#[from] FromUtf8Error),
+    Tls(#[from] tokio_rustls::rustls::Error),
+    Trace(#[from] opentelemetry::trace::TraceError),
     TokioPostgres(#[from] tokio_postgres::error::Error),
     TryFromInt(#[from] TryFromIntError),
     UnsupportedStorageUrl(Url),
@@ -0,0 +1,320 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Request and group coordinator metrics, served with those of storage in
+//! the Prometheus text format. Names follow their kafka.network and
+//! kafka.coordinator equivalents, so that existing dashboards may be adapted.
+
+use crate::{Error, Result};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramVec,
+    IntCounterVec, IntGauge, TextEncoder,
+};
+use std::time::Duration;
+use tansu_kafka_sans_io::{ApiKey, Body, ErrorCode};
+use tansu_storage::Storage;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, warn};
+use url::Url;
+
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+lazy_static! {
+    static ref REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "kafka_network_requestmetrics_requests_total",
+        "Requests handled, by API and the error code of the response",
+        &["request", "error"]
+    )
+    .expect("requests");
+    static ref REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "kafka_network_requestmetrics_totaltime_seconds",
+        "Time taken to handle a request, by API",
+        &["request"]
+    )
+    .expect("request duration");
+    static ref GROUPS: IntGauge = register_int_gauge!(
+        "kafka_coordinator_group_groupmetadatamanager_numgroups",
+        "Consumer groups known to storage"
+    )
+    .expect("groups");
+    static ref REBALANCES: IntCounterVec = register_int_counter_vec!(
+        "kafka_coordinator_group_rebalances_total",
+        "Completed joins that started a new generation of a group",
+        &["group"]
+    )
+    .expect("rebalances");
+}
+
+/// Record a handled request, with the top level error code of its response.
+pub fn request(api_key: ApiKey, response: &Result<Body>, elapsed: Duration) {
+    let error_code = response_error_code(response);
+
+    let request = api_key.to_string();
+
+    REQUESTS
+        .with_label_values(&[request.as_str(), format!("{error_code:?}").as_str()])
+        .inc();
+
+    REQUEST_DURATION
+        .with_label_values(&[request.as_str()])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Record a new generation of a group.
+pub fn rebalanced(group_id: &str) {
+    REBALANCES.with_label_values(&[group_id]).inc();
+}
+
+/// The error code of a response, or of the error raised in its place.
+pub fn response_error_code(response: &Result<Body>) -> ErrorCode {
+    match response {
+        Ok(body) => error_code(body),
+        Err(Error::Api(error_code))
+        | Err(Error::Storage(tansu_storage::Error::Api(error_code))) => *error_code,
+        Err(_) => ErrorCode::UnknownServerError,
+    }
+}
+
+/// The top level error code of a response, with responses that only have
+/// errors per resource, such as produce, being [`ErrorCode::None`].
+pub fn error_code(body: &Body) -> ErrorCode {
+    let error_code = match body {
+        Body::AddOffsetsToTxnResponse { error_code, .. }
+        | Body::AlterPartitionReassignmentsResponse { error_code, .. }
+        | Body::ApiVersionsResponse { error_code, .. }
+        | Body::BrokerHeartbeatResponse { error_code, .. }
+        | Body::ConsumerGroupHeartbeatResponse { error_code, .. }
+        | Body::DescribeAclsResponse { error_code, .. }
+        | Body::DescribeClientQuotasResponse { error_code, .. }
+        | Body::DescribeClusterResponse { error_code, .. }
+        | Body::DescribeUserScramCredentialsResponse { error_code, .. }
+        | Body::EndTxnResponse { error_code, .. }
+        | Body::GetTelemetrySubscriptionsResponse { error_code, .. }
+        | Body::HeartbeatResponse { error_code, .. }
+        | Body::InitProducerIdResponse { error_code, .. }
+        | Body::JoinGroupResponse { error_code, .. }
+        | Body::LeaveGroupResponse { error_code, .. }
+        | Body::ListGroupsResponse { error_code, .. }
+        | Body::ListPartitionReassignmentsResponse { error_code, .. }
+        | Body::ListTransactionsResponse { error_code, .. }
+        | Body::SaslAuthenticateResponse { error_code, .. }
+        | Body::SaslHandshakeResponse { error_code, .. }
+        | Body::SyncGroupResponse { error_code, .. }
+        | Body::UnregisterBrokerResponse { error_code, .. } => Some(*error_code),
+
+        Body::AddPartitionsToTxnResponse { error_code, .. }
+        | Body::ElectLeadersResponse { error_code, .. }
+        | Body::FetchResponse { error_code, .. }
+        | Body::FindCoordinatorResponse { error_code, .. }
+        | Body::OffsetFetchResponse { error_code, .. } => *error_code,
+
+        _ => None,
+    };
+
+    error_code
+        .and_then(|error_code| ErrorCode::try_from(error_code).ok())
+        .unwrap_or(ErrorCode::None)
+}
+
+/// Serve metrics at `/metrics` on this listener.
+pub async fn serve<S>(listener: Url, storage: S) -> Result<()>
+where
+    S: Storage,
+{
+    debug!(%listener);
+
+    let listener = TcpListener::bind(format!(
+        "{}:{}",
+        listener.host_str().unwrap_or("0.0.0.0"),
+        listener.port().unwrap_or(9100)
+    ))
+    .await?;
+
+    accept(listener, storage).await
+}
+
+async fn accept<S>(listener: TcpListener, storage: S) -> Result<()>
+where
+    S: Storage,
+{
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        debug!(?addr);
+
+        let storage = storage.clone();
+
+        _ = tokio::spawn(async move {
+            if let Err(error) = scrape(stream, storage).await {
+                warn!(?addr, ?error);
+            }
+        });
+    }
+}
+
+// answer a single HTTP request, closing the connection afterwards
+async fn scrape<S>(mut stream: TcpStream, mut storage: S) -> Result<()>
+where
+    S: Storage,
+{
+    let mut head = vec![0u8; MAX_REQUEST_HEAD];
+    let mut received = 0;
+
+    while received < head.len() && !head[..received].windows(4).any(|end| end == b"\r\n\r\n") {
+        match stream.read(&mut head[received..]).await? {
+            0 => break,
+            n => received += n,
+        }
+    }
+
+    let head = String::from_utf8_lossy(&head[..received]);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+
+    let method = request_line.next();
+    let path = request_line
+        .next()
+        .and_then(|target| target.split('?').next());
+
+    debug!(?method, ?path);
+
+    let (status, content_type, body) = match (method, path) {
+        (Some("GET"), Some("/metrics")) => {
+            GROUPS.set(i64::try_from(storage.list_groups().await?.len())?);
+
+            let encoder = TextEncoder::new();
+            let mut body = vec![];
+            encoder.encode(&prometheus::gather(), &mut body)?;
+
+            ("200 OK", encoder.format_type().to_owned(), body)
+        }
+
+        (Some(_), Some("/metrics")) => (
+            "405 Method Not Allowed",
+            "text/plain".to_owned(),
+            b"method not allowed\n".to_vec(),
+        ),
+
+        _ => (
+            "404 Not Found",
+            "text/plain".to_owned(),
+            b"not found\n".to_vec(),
+        ),
+    };
+
+    let response_head = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        body.len()
+    );
+
+    stream.write_all(response_head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.shutdown().await.map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+    use tansu_storage::dynostore::DynoStore;
+
+    async fn get(path: &str) -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let server = tokio::spawn(accept(
+            listener,
+            DynoStore::new("abc", 12321, InMemory::new()),
+        ));
+
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nhost: localhost\r\n\r\n").as_bytes())
+            .await?;
+
+        let mut response = String::new();
+        _ = stream.read_to_string(&mut response).await?;
+
+        server.abort();
+        Ok(response)
+    }
+
+    #[test]
+    fn top_level_error_code() {
+        assert_eq!(
+            ErrorCode::UnknownMemberId,
+            error_code(&Body::HeartbeatResponse {
+                throttle_time_ms: Some(0),
+                error_code: ErrorCode::UnknownMemberId.into(),
+                unknown_tagged_fields: vec![],
+            })
+        );
+
+        assert_eq!(
+            ErrorCode::None,
+            error_code(&Body::ProduceResponse {
+                responses: Some([].into()),
+                throttle_time_ms: Some(0),
+                node_endpoints: None,
+                unknown_tagged_fields: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn error_in_place_of_response() {
+        assert_eq!(
+            ErrorCode::UnsupportedVersion,
+            response_error_code(&Err(Error::Api(ErrorCode::UnsupportedVersion)))
+        );
+
+        assert_eq!(
+            ErrorCode::UnknownServerError,
+            response_error_code(&Err(Error::Poison))
+        );
+    }
+
+    #[tokio::test]
+    async fn scrape_metrics() -> Result<()> {
+        request(
+            ApiKey::Heartbeat,
+            &Ok(Body::HeartbeatResponse {
+                throttle_time_ms: Some(0),
+                error_code: ErrorCode::RebalanceInProgress.into(),
+                unknown_tagged_fields: vec![],
+            }),
+            Duration::from_millis(3),
+        );
+
+        let response = get("/metrics").await?;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(
+            r#"kafka_network_requestmetrics_requests_total{error="RebalanceInProgress",request="Heartbeat"}"#
+        ));
+        assert!(response.contains("kafka_network_requestmetrics_totaltime_seconds_bucket"));
+        assert!(response.contains("kafka_coordinator_group_groupmetadatamanager_numgroups 0"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn not_found() -> Result<()> {
+        assert!(get("/").await?.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Export of request spans, together with their storage and coordinator
+//! children, to an OpenTelemetry collector over OTLP.
+
+use crate::Result;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use std::env;
+use tracing::{debug, Subscriber};
+use tracing_subscriber::{filter, registry::LookupSpan, Layer};
+
+/// The collector endpoint, with OTLP export disabled when unset.
+pub const ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// A layer exporting spans when [`ENDPOINT`] is set.
+pub fn layer<S>() -> Result<Option<impl Layer<S>>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let Ok(endpoint) = env::var(ENDPOINT) else {
+        return Ok(None);
+    };
+
+    debug!(%endpoint);
+
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(trace::config().with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            env!("CARGO_PKG_NAME"),
+        )])))
+        .install_batch(runtime::Tokio)
+        .map(|tracer| {
+            Some(
+                tracing_opentelemetry::layer()
+                    .with_tracer(tracer)
+                    .with_filter(filter::filter_fn(|metadata| {
+                        metadata.is_span() && metadata.target().starts_with("tansu")
+                    })),
+            )
+        })
+        .map_err(Into::into)
+}
+
+/// Flush any spans that have yet to be exported.
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider()
+}
@@ -18,7 +18,9 @@ use std::collections::BTreeSet;
 use std::io::BufReader;
 use std::sync::Arc;
 use std::time::SystemTime;
-use std::{collections::BTreeMap, fmt::Debug, io::Cursor, str::FromStr, time::Duration};
+use std::{
+    collections::BTreeMap, fmt::Debug, io::Cursor, ops::Range, str::FromStr, time::Duration,
+};
 
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -36,21 +38,23 @@ use tansu_kafka_sans_io::describe_configs_response::DescribeConfigsResourceResul
 use tansu_kafka_sans_io::{
     create_topics_request::CreatableTopic,
     delete_records_request::DeleteRecordsTopic,
-    delete_records_response::DeleteRecordsTopicResult,
+    delete_records_response::{DeleteRecordsPartitionResult, DeleteRecordsTopicResult},
     describe_cluster_response::DescribeClusterBroker,
     describe_configs_response::DescribeConfigsResult,
+    fetch_response::AbortedTransaction,
     metadata_response::{MetadataResponseBroker, MetadataResponsePartition, MetadataResponseTopic},
-    record::{deflated, inflated},
-    ConfigResource, Encoder, ErrorCode,
+    record::{control::EndTransactionMarker, deflated, inflated, ControlRecord},
+    to_timestamp, Ack, ConfigResource, Encoder, ErrorCode,
 };
 use tansu_kafka_sans_io::{ConfigSource, ConfigType, Decoder};
 use tracing::{debug, error};
 use uuid::Uuid;
 
 use crate::{
-    BrokerRegistationRequest, Error, GroupDetail, ListOffsetRequest, ListOffsetResponse,
-    MetadataResponse, OffsetCommitRequest, OffsetStage, ProducerIdResponse, Result, Storage,
-    TopicId, Topition, UpdateError, Version, NULL_TOPIC_ID,
+    AclBinding, BrokerRegistationRequest, ConsumerGroupDetail, Error, GroupDetail,
+    ListOffsetRequest, ListOffsetResponse, MetadataResponse, OffsetCommitRequest, OffsetStage,
+    ProducerDetail, ProducerIdResponse, Result, ScramCredential, ScramMechanism, Storage, TopicId,
+    Topition, TxnDetail, UpdateError, Version, NULL_TOPIC_ID,
 };
 
 const APPLICATION_JSON: &str = "application/json";
@@ -61,6 +65,11 @@ pub struct DynoStore {
     node: i32,
     watermarks: BTreeMap<Topition, ConditionData<Watermark>>,
     producers: ConditionData<BTreeMap<i64, Producer>>,
+    transactions: ConditionData<BTreeMap<String, Txn>>,
+    acls: ConditionData<BTreeSet<AclBinding>>,
+    reassignments: ConditionData<BTreeMap<String, BTreeMap<i32, Vec<i32>>>>,
+    leader_epochs: ConditionData<BTreeMap<String, BTreeMap<i32, i32>>>,
+    liveness: ConditionData<BTreeMap<i32, Liveness>>,
 
     object_store: Arc<DynObjectStore>,
 }
@@ -245,6 +254,27 @@ struct Watermark {
     high: i64,
     stable: i64,
     producers: BTreeMap<i64, WatermarkSequence>,
+
+    // the first offset of the ongoing transaction of each producer
+    #[serde(default)]
+    ongoing: BTreeMap<i64, i64>,
+
+    #[serde(default)]
+    aborted: Vec<AbortedTxn>,
+}
+
+impl Watermark {
+    // the offset before which every transaction has ended
+    fn last_stable(&self) -> i64 {
+        self.ongoing.values().min().copied().unwrap_or(self.high)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+struct AbortedTxn {
+    producer: i64,
+    first: i64,
+    last: i64,
 }
 
 impl ConditionData<Watermark> {
@@ -292,6 +322,57 @@ impl Default for Producer {
     }
 }
 
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+struct Txn {
+    producer: i64,
+    epoch: i16,
+    timeout_ms: i32,
+    started: Option<SystemTime>,
+    partitions: BTreeSet<Topition>,
+    #[serde(default)]
+    offsets: BTreeMap<String, Vec<(Topition, OffsetCommitRequest)>>,
+}
+
+impl Txn {
+    // the error for a request from this producer and epoch, if any
+    fn fenced(&self, producer_id: i64, producer_epoch: i16) -> Option<ErrorCode> {
+        if producer_id != self.producer {
+            return Some(ErrorCode::InvalidProducerIdMapping);
+        }
+
+        match producer_epoch.cmp(&self.epoch) {
+            Ordering::Equal => None,
+            Ordering::Less => Some(ErrorCode::ProducerFenced),
+            Ordering::Greater => Some(ErrorCode::InvalidProducerEpoch),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.partitions.is_empty() && self.offsets.is_empty()
+    }
+
+    fn expired(&self, now: SystemTime) -> bool {
+        self.started.is_some_and(|started| {
+            u64::try_from(self.timeout_ms)
+                .map(Duration::from_millis)
+                .is_ok_and(|timeout| started + timeout <= now)
+        })
+    }
+}
+
+// the registration of a broker, which is fenced without a heartbeat
+// within the session timeout, and retained once unregistered so that
+// its epoch continues should it register again
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+struct Liveness {
+    incarnation_id: Uuid,
+    epoch: i64,
+    heartbeat: SystemTime,
+    fenced: bool,
+    #[serde(default)]
+    unregistered: bool,
+}
+
 fn json_content_type() -> Attributes {
     let mut attributes = Attributes::new();
     _ = attributes.insert(
@@ -314,10 +395,228 @@ impl DynoStore {
                 tags: TagSet::default(),
                 data: BTreeMap::new(),
             },
+            transactions: ConditionData {
+                path: Path::from(format!("clusters/{}/transactions.json", cluster)),
+                version: None,
+                attributes: Attributes::new(),
+                tags: TagSet::default(),
+                data: BTreeMap::new(),
+            },
+            acls: ConditionData {
+                path: Path::from(format!("clusters/{}/acls.json", cluster)),
+                version: None,
+                attributes: Attributes::new(),
+                tags: TagSet::default(),
+                data: BTreeSet::new(),
+            },
+            reassignments: ConditionData {
+                path: Path::from(format!("clusters/{}/reassignments.json", cluster)),
+                version: None,
+                attributes: Attributes::new(),
+                tags: TagSet::default(),
+                data: BTreeMap::new(),
+            },
+            leader_epochs: ConditionData {
+                path: Path::from(format!("clusters/{}/leader_epochs.json", cluster)),
+                version: None,
+                attributes: Attributes::new(),
+                tags: TagSet::default(),
+                data: BTreeMap::new(),
+            },
+            liveness: ConditionData {
+                path: Path::from(format!("clusters/{}/liveness.json", cluster)),
+                version: None,
+                attributes: Attributes::new(),
+                tags: TagSet::default(),
+                data: BTreeMap::new(),
+            },
             object_store: Arc::new(object_store),
         }
     }
 
+    // brokers that are registered, but fenced
+    async fn fenced(&mut self) -> Result<BTreeSet<i32>> {
+        self.liveness
+            .with(&self.object_store, |liveness| {
+                Ok(liveness
+                    .iter()
+                    .filter(|(_, registered)| registered.fenced)
+                    .map(|(broker_id, _)| *broker_id)
+                    .collect())
+            })
+            .await
+    }
+
+    // partitions without a leader epoch start at zero, those of a
+    // topic that was deleted continue from their previous epoch
+    async fn insert_leader_epochs(&mut self, topic: &str, partitions: Range<i32>) -> Result<()> {
+        self.leader_epochs
+            .with_mut(&self.object_store, |leader_epochs| {
+                let epochs = leader_epochs.entry(topic.to_owned()).or_default();
+
+                for partition in partitions.clone() {
+                    _ = epochs.entry(partition).or_insert(0);
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    // advance the leader epoch of a partition, or all partitions of the topic
+    async fn advance_leader_epochs(&mut self, topic: &str, partition: Option<i32>) -> Result<()> {
+        self.leader_epochs
+            .with_mut(&self.object_store, |leader_epochs| {
+                if let Some(epochs) = leader_epochs.get_mut(topic) {
+                    for (_, epoch) in epochs
+                        .iter_mut()
+                        .filter(|(index, _)| partition.is_none_or(|partition| partition == **index))
+                    {
+                        *epoch += 1;
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    async fn put_batch(
+        &self,
+        topition: &Topition,
+        offset: i64,
+        deflated: deflated::Batch,
+    ) -> Result<()> {
+        let location = Path::from(format!(
+            "clusters/{}/topics/{}/partitions/{:0>10}/records/{:0>20}.batch",
+            self.cluster, topition.topic, topition.partition, offset,
+        ));
+
+        let payload = self.encode(deflated)?;
+        let attributes = Attributes::new();
+
+        let options = PutOptions {
+            mode: PutMode::Create,
+            tags: TagSet::default(),
+            attributes,
+        };
+
+        _ = self
+            .object_store
+            .put_opts(&location, payload, options)
+            .await
+            .inspect_err(|error| error!(?error))?;
+
+        Ok(())
+    }
+
+    // the transaction of a transactional id, as most recently stored
+    async fn transaction(&mut self, transactional_id: &str) -> Result<Option<Txn>> {
+        self.transactions
+            .with(&self.object_store, |transactions| {
+                Ok(transactions.get(transactional_id).cloned())
+            })
+            .await
+    }
+
+    // stage the offsets of a group in the ongoing transaction of a
+    // producer, replacing those previously staged for the same topitions
+    async fn txn_offsets(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+        offsets: &[(Topition, OffsetCommitRequest)],
+    ) -> Result<ErrorCode> {
+        let Some(txn) = self.transaction(transactional_id).await? else {
+            return Ok(ErrorCode::InvalidProducerIdMapping);
+        };
+
+        if let Some(error_code) = txn.fenced(producer_id, producer_epoch) {
+            return Ok(error_code);
+        }
+
+        let now = SystemTime::now();
+
+        self.transactions
+            .with_mut(&self.object_store, |transactions| {
+                let txn = transactions
+                    .get_mut(transactional_id)
+                    .filter(|txn| txn.fenced(producer_id, producer_epoch).is_none())
+                    .ok_or(Error::Api(ErrorCode::ProducerFenced))?;
+
+                _ = txn.started.get_or_insert(now);
+
+                let staged = txn.offsets.entry(group_id.to_owned()).or_default();
+                staged.retain(|(topition, _)| {
+                    offsets.iter().all(|(replacing, _)| replacing != topition)
+                });
+                staged.extend(offsets.iter().cloned());
+
+                Ok(ErrorCode::None)
+            })
+            .await
+            .or_else(|error| match error {
+                Error::Api(error_code) => Ok(error_code),
+                otherwise => Err(otherwise),
+            })
+    }
+
+    // end the transaction of a producer in a topition with a commit or
+    // abort marker, returning the offset of the marker
+    async fn write_marker(
+        &mut self,
+        topition: &Topition,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed: bool,
+    ) -> Result<i64> {
+        debug!(?topition, ?producer_id, ?producer_epoch, ?committed);
+
+        let marker = EndTransactionMarker::default();
+
+        let control = if committed {
+            ControlRecord::Commit(marker)
+        } else {
+            ControlRecord::Abort(marker)
+        };
+
+        let batch = to_timestamp(SystemTime::now())
+            .and_then(|timestamp| control.batch(producer_id, producer_epoch, timestamp))?;
+
+        let offset = self
+            .watermarks
+            .entry(topition.to_owned())
+            .or_insert(ConditionData::<Watermark>::new(
+                self.cluster.as_str(),
+                topition,
+            ))
+            .with_mut(&self.object_store, |watermark| {
+                let offset = watermark.high;
+                watermark.high += 1;
+
+                if let Some(first) = watermark.ongoing.remove(&producer_id) {
+                    if !committed {
+                        watermark.aborted.push(AbortedTxn {
+                            producer: producer_id,
+                            first,
+                            last: offset,
+                        });
+                    }
+                }
+
+                watermark.stable = watermark.last_stable();
+
+                Ok(offset)
+            })
+            .await?;
+
+        self.put_batch(topition, offset, batch).await?;
+
+        Ok(offset)
+    }
+
     async fn topic_metadata(&self, topic: &TopicId) -> Result<TopicMetadata> {
         debug!(?topic);
 
@@ -341,6 +640,111 @@ impl DynoStore {
             .map_err(Into::into)
     }
 
+    // the base offsets of the batches stored for a topition
+    async fn batch_offsets(&self, topition: &Topition) -> Result<BTreeSet<i64>> {
+        let location = Path::from(format!(
+            "clusters/{}/topics/{}/partitions/{:0>10}/records/",
+            self.cluster, topition.topic, topition.partition
+        ));
+
+        let mut offsets = BTreeSet::new();
+
+        let mut list_stream = self.object_store.list(Some(&location));
+
+        while let Some(meta) = list_stream
+            .next()
+            .await
+            .inspect(|meta| debug!(?meta))
+            .transpose()
+            .inspect_err(|error| error!(?error, ?topition))
+            .map_err(|_| Error::Api(ErrorCode::UnknownServerError))?
+        {
+            let Some(offset) = meta.location.parts().last() else {
+                continue;
+            };
+
+            let offset = i64::from_str(&offset.as_ref()[0..20])?;
+            _ = offsets.insert(offset);
+        }
+
+        Ok(offsets)
+    }
+
+    // the batch stored for a topition at a base offset
+    async fn batch(&self, topition: &Topition, base_offset: i64) -> Result<deflated::Batch> {
+        let location = Path::from(format!(
+            "clusters/{}/topics/{}/partitions/{:0>10}/records/{:0>20}.batch",
+            self.cluster, topition.topic, topition.partition, base_offset,
+        ));
+
+        let get_result = self
+            .object_store
+            .get(&location)
+            .await
+            .inspect_err(|error| error!(?error, ?location))
+            .map_err(|_| Error::Api(ErrorCode::UnknownServerError))?;
+
+        get_result
+            .bytes()
+            .await
+            .inspect_err(|error| error!(?error, ?location))
+            .map_err(|_| Error::Api(ErrorCode::UnknownServerError))
+            .and_then(|encoded| self.decode(encoded))
+            .map(|mut deflated| {
+                deflated.base_offset = base_offset;
+                deflated
+            })
+    }
+
+    // advance the log start of a topition to offset, with -1 being the high
+    // watermark, deleting any batch that lies entirely before it
+    async fn truncate(&mut self, topition: &Topition, offset: i64) -> Result<i64> {
+        debug!(?topition, offset);
+
+        let (low, high) = self
+            .watermarks
+            .entry(topition.to_owned())
+            .or_insert(ConditionData::<Watermark>::new(
+                self.cluster.as_str(),
+                topition,
+            ))
+            .with_mut(&self.object_store, |watermark| {
+                let offset = if offset == -1 { watermark.high } else { offset };
+
+                if offset < 0 || offset > watermark.high {
+                    Err(Error::Api(ErrorCode::OffsetOutOfRange))
+                } else {
+                    watermark.low = watermark.low.max(offset);
+                    Ok((watermark.low, watermark.high))
+                }
+            })
+            .await?;
+
+        let offsets = self.batch_offsets(topition).await?;
+
+        // a batch ends where the next one starts, with the last ending at
+        // the high watermark
+        let ends = offsets.iter().skip(1).copied().chain([high]);
+
+        for (base_offset, end) in offsets.iter().zip(ends) {
+            if end <= low {
+                let location = Path::from(format!(
+                    "clusters/{}/topics/{}/partitions/{:0>10}/records/{:0>20}.batch",
+                    self.cluster, topition.topic, topition.partition, base_offset,
+                ));
+
+                debug!(?location);
+
+                self.object_store
+                    .delete(&location)
+                    .await
+                    .inspect_err(|error| error!(?error, ?location))?;
+            }
+        }
+
+        Ok(low)
+    }
+
     fn encode(&self, deflated: deflated::Batch) -> Result<PutPayload> {
         let mut encoded = Cursor::new(vec![]);
         let mut encoder = Encoder::new(&mut encoded);
@@ -421,16 +825,38 @@ impl Storage for DynoStore {
     async fn register_broker(
         &mut self,
         broker_registration: BrokerRegistationRequest,
-    ) -> Result<()> {
+    ) -> Result<i64> {
         debug!(?broker_registration);
 
+        let epoch = self
+            .liveness
+            .with_mut(&self.object_store, |liveness| {
+                let epoch = liveness
+                    .get(&broker_registration.broker_id)
+                    .map_or(0, |registered| registered.epoch + 1);
+
+                _ = liveness.insert(
+                    broker_registration.broker_id,
+                    Liveness {
+                        incarnation_id: broker_registration.incarnation_id,
+                        epoch,
+                        heartbeat: SystemTime::now(),
+                        fenced: false,
+                        unregistered: false,
+                    },
+                );
+
+                Ok(epoch)
+            })
+            .await?;
+
         let payload = serde_json::to_vec(&broker_registration)
             .map(Bytes::from)
             .map(PutPayload::from)?;
 
         let location = Path::from(format!(
             "clusters/{}/brokers/{}.json",
-            self.cluster, self.node
+            self.cluster, broker_registration.broker_id
         ));
 
         let options = PutOptions {
@@ -446,7 +872,7 @@ impl Storage for DynoStore {
 
         debug!(?location, ?put_result);
 
-        Ok(())
+        Ok(epoch)
     }
 
     async fn create_topic(&mut self, topic: CreatableTopic, validate_only: bool) -> Result<Uuid> {
@@ -543,6 +969,9 @@ impl Storage for DynoStore {
                     }
                 }
 
+                self.insert_leader_epochs(&td.topic.name, 0..td.topic.num_partitions)
+                    .await?;
+
                 Ok(id)
             }
 
@@ -554,39 +983,178 @@ impl Storage for DynoStore {
         }
     }
 
-    async fn delete_records(
+    async fn create_partitions(
         &mut self,
-        topics: &[DeleteRecordsTopic],
-    ) -> Result<Vec<DeleteRecordsTopicResult>> {
-        debug!(?topics);
-        todo!()
-    }
+        topic: &TopicId,
+        count: i32,
+        validate_only: bool,
+    ) -> Result<()> {
+        debug!(?topic, ?count, ?validate_only);
 
-    async fn delete_topic(&mut self, topic: &TopicId) -> Result<ErrorCode> {
-        debug!(?topic);
+        let mut td = match self.topic_metadata(topic).await {
+            Ok(td) => td,
 
-        if let Ok(metadata) = self.topic_metadata(topic).await {
-            let prefix = Path::from(format!(
-                "clusters/{}/topics/{}/",
-                self.cluster, metadata.topic.name,
-            ));
+            Err(Error::ObjectStore(object_store::Error::NotFound { .. })) => {
+                return Err(Error::Api(ErrorCode::UnknownTopicOrPartition))
+            }
 
-            let locations = self
-                .object_store
-                .list(Some(&prefix))
-                .map_ok(|m| m.location)
-                .boxed();
+            Err(otherwise) => return Err(otherwise),
+        };
 
-            _ = self
-                .object_store
-                .delete_stream(locations)
-                .try_collect::<Vec<Path>>()
-                .await?;
+        if count <= td.topic.num_partitions {
+            return Err(Error::Api(ErrorCode::InvalidPartitions));
+        }
 
-            let prefix = Path::from(format!("clusters/{}/groups/consumers/", self.cluster));
+        if validate_only {
+            return Ok(());
+        }
 
-            let locations = self
-                .object_store
+        // watermarks for the new partitions exist before they are advertised
+        let payload = serde_json::to_vec(&Watermark::default())
+            .map(Bytes::from)
+            .map(PutPayload::from)?;
+
+        for partition in td.topic.num_partitions..count {
+            let location = Path::from(format!(
+                "clusters/{}/topics/{}/partitions/{:0>10}/watermark.json",
+                self.cluster, td.topic.name, partition,
+            ));
+
+            let options = PutOptions {
+                mode: PutMode::Create,
+                tags: TagSet::default(),
+                attributes: json_content_type(),
+            };
+
+            match self
+                .object_store
+                .put_opts(&location, payload.clone(), options)
+                .await
+                .inspect(|put_result| debug!(?location, ?put_result))
+            {
+                Ok(_) | Err(object_store::Error::AlreadyExists { .. }) => continue,
+
+                Err(error) => {
+                    error!(?error, ?location);
+                    return Err(error.into());
+                }
+            }
+        }
+
+        self.insert_leader_epochs(&td.topic.name, td.topic.num_partitions..count)
+            .await?;
+
+        td.topic.num_partitions = count;
+
+        let payload = serde_json::to_vec(&td)
+            .map(Bytes::from)
+            .map(PutPayload::from)?;
+
+        for location in [
+            Path::from(format!(
+                "clusters/{}/topics/{}.json",
+                self.cluster, td.topic.name,
+            )),
+            Path::from(format!(
+                "clusters/{}/topics/uuids/{}.json",
+                self.cluster, td.id,
+            )),
+        ] {
+            let options = PutOptions {
+                mode: PutMode::Overwrite,
+                tags: TagSet::default(),
+                attributes: json_content_type(),
+            };
+
+            _ = self
+                .object_store
+                .put_opts(&location, payload.clone(), options)
+                .await
+                .inspect(|put_result| debug!(?location, ?put_result))
+                .inspect_err(|error| error!(?error, ?location))?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete_records(
+        &mut self,
+        topics: &[DeleteRecordsTopic],
+    ) -> Result<Vec<DeleteRecordsTopicResult>> {
+        debug!(?topics);
+
+        let mut responses = vec![];
+
+        for topic in topics {
+            let metadata = self
+                .topic_metadata(&TopicId::from(topic.name.as_str()))
+                .await
+                .ok();
+
+            let mut partitions = vec![];
+
+            for partition in topic.partitions.as_deref().unwrap_or_default() {
+                let topition = Topition::new(topic.name.as_str(), partition.partition_index);
+
+                let outcome = if metadata.as_ref().is_some_and(|metadata| {
+                    (0..metadata.topic.num_partitions).contains(&partition.partition_index)
+                }) {
+                    self.truncate(&topition, partition.offset).await
+                } else {
+                    Err(Error::Api(ErrorCode::UnknownTopicOrPartition))
+                };
+
+                partitions.push(match outcome {
+                    Ok(low_watermark) => DeleteRecordsPartitionResult {
+                        partition_index: partition.partition_index,
+                        low_watermark,
+                        error_code: ErrorCode::None.into(),
+                    },
+
+                    Err(Error::Api(error_code)) => DeleteRecordsPartitionResult {
+                        partition_index: partition.partition_index,
+                        low_watermark: -1,
+                        error_code: error_code.into(),
+                    },
+
+                    Err(error) => return Err(error),
+                });
+            }
+
+            responses.push(DeleteRecordsTopicResult {
+                name: topic.name.clone(),
+                partitions: Some(partitions),
+            });
+        }
+
+        Ok(responses)
+    }
+
+    async fn delete_topic(&mut self, topic: &TopicId) -> Result<ErrorCode> {
+        debug!(?topic);
+
+        if let Ok(metadata) = self.topic_metadata(topic).await {
+            let prefix = Path::from(format!(
+                "clusters/{}/topics/{}/",
+                self.cluster, metadata.topic.name,
+            ));
+
+            let locations = self
+                .object_store
+                .list(Some(&prefix))
+                .map_ok(|m| m.location)
+                .boxed();
+
+            _ = self
+                .object_store
+                .delete_stream(locations)
+                .try_collect::<Vec<Path>>()
+                .await?;
+
+            let prefix = Path::from(format!("clusters/{}/groups/consumers/", self.cluster));
+
+            let locations = self
+                .object_store
                 .list(Some(&prefix))
                 .filter_map(|m| async {
                     m.map_or(None, |m| {
@@ -632,16 +1200,21 @@ impl Storage for DynoStore {
                 )))
                 .await?;
 
+            self.advance_leader_epochs(&metadata.topic.name, None)
+                .await?;
+
             Ok(ErrorCode::None)
         } else {
             Ok(ErrorCode::UnknownTopicOrPartition)
         }
     }
 
-    async fn brokers(&mut self) -> Result<Vec<DescribeClusterBroker>> {
+    async fn brokers(&mut self, listener: Option<&str>) -> Result<Vec<DescribeClusterBroker>> {
         let location = Path::from(format!("clusters/{}/brokers/", self.cluster));
         debug!(?location);
 
+        let fenced = self.fenced().await?;
+
         let mut brokers = vec![];
 
         let mut list_stream = self.object_store.list(Some(&location));
@@ -676,18 +1249,22 @@ impl Storage for DynoStore {
                 continue;
             };
 
-            let Some(listener) = broker_registration
+            if fenced.contains(&broker_registration.broker_id) {
+                continue;
+            }
+
+            let Some(advertised) = broker_registration
                 .listeners
                 .iter()
-                .find(|listener| listener.name.as_str() == "broker")
+                .find(|advertised| listener.is_none_or(|name| advertised.name == name))
             else {
                 continue;
             };
 
             brokers.push(DescribeClusterBroker {
                 broker_id: broker_registration.broker_id,
-                host: listener.host.clone(),
-                port: listener.port as i32,
+                host: advertised.host.clone(),
+                port: advertised.port as i32,
                 rack: broker_registration.rack,
             });
         }
@@ -695,8 +1272,105 @@ impl Storage for DynoStore {
         Ok(brokers)
     }
 
-    async fn produce(&mut self, topition: &Topition, deflated: deflated::Batch) -> Result<i64> {
-        debug!(?topition, ?deflated);
+    async fn broker_heartbeat(
+        &mut self,
+        broker_id: i32,
+        broker_epoch: i64,
+        want_fence: bool,
+    ) -> Result<ErrorCode> {
+        debug!(?broker_id, ?broker_epoch, ?want_fence);
+
+        let now = SystemTime::now();
+
+        self.liveness
+            .with_mut(&self.object_store, |liveness| {
+                let Some(registered) = liveness
+                    .get_mut(&broker_id)
+                    .filter(|registered| !registered.unregistered)
+                else {
+                    return Ok(ErrorCode::BrokerIdNotRegistered);
+                };
+
+                if registered.epoch != broker_epoch {
+                    return Ok(ErrorCode::StaleBrokerEpoch);
+                }
+
+                registered.heartbeat = now;
+                registered.fenced = want_fence;
+
+                Ok(ErrorCode::None)
+            })
+            .await
+    }
+
+    async fn fence_brokers(
+        &mut self,
+        now: SystemTime,
+        session_timeout: Duration,
+    ) -> Result<Vec<i32>> {
+        debug!(?now, ?session_timeout);
+
+        self.liveness
+            .with_mut(&self.object_store, |liveness| {
+                let mut fenced = vec![];
+
+                for (broker_id, registered) in liveness.iter_mut() {
+                    if !registered.fenced && registered.heartbeat + session_timeout <= now {
+                        registered.fenced = true;
+                        fenced.push(*broker_id);
+                    }
+                }
+
+                Ok(fenced)
+            })
+            .await
+    }
+
+    async fn unregister_broker(&mut self, broker_id: i32) -> Result<ErrorCode> {
+        debug!(?broker_id);
+
+        let error_code = self
+            .liveness
+            .with_mut(&self.object_store, |liveness| {
+                let Some(registered) = liveness
+                    .get_mut(&broker_id)
+                    .filter(|registered| !registered.unregistered)
+                else {
+                    return Ok(ErrorCode::BrokerIdNotRegistered);
+                };
+
+                if !registered.fenced {
+                    return Ok(ErrorCode::InvalidRequest);
+                }
+
+                registered.unregistered = true;
+
+                Ok(ErrorCode::None)
+            })
+            .await?;
+
+        if error_code == ErrorCode::None {
+            let location = Path::from(format!(
+                "clusters/{}/brokers/{}.json",
+                self.cluster, broker_id
+            ));
+
+            match self.object_store.delete(&location).await {
+                Ok(()) | Err(object_store::Error::NotFound { .. }) => (),
+                Err(otherwise) => return Err(otherwise.into()),
+            }
+        }
+
+        Ok(error_code)
+    }
+
+    async fn produce(
+        &mut self,
+        topition: &Topition,
+        deflated: deflated::Batch,
+        ack: Ack,
+    ) -> Result<i64> {
+        debug!(?topition, ?deflated, ?ack);
 
         if deflated.producer_id > 0 {
             self.producers
@@ -726,7 +1400,7 @@ impl Storage for DynoStore {
             .with_mut(&self.object_store, |watermark| {
                 debug!(?watermark);
 
-                if deflated.producer_id > 0 {
+                let offset = if deflated.producer_id > 0 {
                     if let Some(ws) = watermark.producers.get_mut(&deflated.producer_id) {
                         debug!(?ws);
 
@@ -736,6 +1410,7 @@ impl Storage for DynoStore {
                                     debug!(?ws, ?deflated.base_sequence);
 
                                     ws.sequence += deflated.last_offset_delta + 1;
+                                    ws.updated = SystemTime::now();
 
                                     let offset = watermark.high;
                                     watermark.high += deflated.last_offset_delta as i64 + 1i64;
@@ -755,7 +1430,18 @@ impl Storage for DynoStore {
 
                             Ordering::Greater => Err(Error::Api(ErrorCode::ProducerFenced)),
 
-                            Ordering::Less => Err(Error::Api(ErrorCode::InvalidProducerEpoch)),
+                            // a new epoch of the producer restarts its sequence
+                            Ordering::Less if deflated.base_sequence == 0 => {
+                                ws.epoch = deflated.producer_epoch;
+                                ws.sequence = deflated.last_offset_delta + 1;
+                                ws.updated = SystemTime::now();
+
+                                let offset = watermark.high;
+                                watermark.high += deflated.last_offset_delta as i64 + 1i64;
+                                Ok(offset)
+                            }
+
+                            Ordering::Less => Err(Error::Api(ErrorCode::OutOfOrderSequenceNumber)),
                         }
                     } else {
                         let offset = watermark.high;
@@ -774,29 +1460,23 @@ impl Storage for DynoStore {
                     let offset = watermark.high;
                     watermark.high += deflated.last_offset_delta as i64 + 1i64;
                     Ok(offset)
-                }
-            })
-            .await?;
+                }?;
 
-        let location = Path::from(format!(
-            "clusters/{}/topics/{}/partitions/{:0>10}/records/{:0>20}.batch",
-            self.cluster, topition.topic, topition.partition, offset,
-        ));
+                // a transactional batch is unstable until its transaction ends
+                if deflated.is_transactional() {
+                    _ = watermark
+                        .ongoing
+                        .entry(deflated.producer_id)
+                        .or_insert(offset);
+                }
 
-        let payload = self.encode(deflated)?;
-        let attributes = Attributes::new();
+                watermark.stable = watermark.last_stable();
 
-        let options = PutOptions {
-            mode: PutMode::Create,
-            tags: TagSet::default(),
-            attributes,
-        };
+                Ok(offset)
+            })
+            .await?;
 
-        _ = self
-            .object_store
-            .put_opts(&location, payload, options)
-            .await
-            .inspect_err(|error| error!(?error))?;
+        self.put_batch(topition, offset, deflated).await?;
 
         Ok(offset)
     }
@@ -810,62 +1490,31 @@ impl Storage for DynoStore {
     ) -> Result<deflated::Batch> {
         debug!(?topition, ?offset, ?min_bytes, ?max_bytes);
 
-        let location = Path::from(format!(
-            "clusters/{}/topics/{}/partitions/{:0>10}/records/",
-            self.cluster, topition.topic, topition.partition
-        ));
-
-        let mut offsets = BTreeSet::new();
+        let offsets = self
+            .batch_offsets(topition)
+            .await
+            .inspect_err(|error| error!(?error, ?topition, ?offset, ?min_bytes, ?max_bytes))?;
 
-        let mut list_stream = self.object_store.list(Some(&location));
+        // the batch containing the offset, otherwise the first batch after it
+        let containing = offsets.range(..=offset).next_back().copied();
+        let following = offsets.range(offset + 1..).next().copied();
 
-        while let Some(meta) = list_stream
-            .next()
-            .await
-            .inspect(|meta| debug!(?meta))
-            .transpose()
-            .inspect_err(|error| error!(?error, ?topition, ?offset, ?min_bytes, ?max_bytes))
-            .map_err(|_| Error::Api(ErrorCode::UnknownServerError))?
-        {
-            let Some(offset) = meta.location.parts().last() else {
-                continue;
-            };
+        if let Some(base_offset) = containing {
+            let batch = self.batch(topition, base_offset).await?;
 
-            let offset = i64::from_str(&offset.as_ref()[0..20])?;
-            _ = offsets.insert(offset);
+            if batch.max_offset() >= offset {
+                return Ok(batch);
+            }
         }
 
-        let greater_or_equal = offsets.split_off(&offset);
-
-        let Some(offset) = greater_or_equal.first() else {
-            return inflated::Batch::builder()
+        if let Some(base_offset) = following {
+            self.batch(topition, base_offset).await
+        } else {
+            inflated::Batch::builder()
                 .build()
                 .and_then(TryInto::try_into)
-                .map_err(Into::into);
-        };
-
-        let location = Path::from(format!(
-            "clusters/{}/topics/{}/partitions/{:0>10}/records/{:0>20}.batch",
-            self.cluster, topition.topic, topition.partition, offset,
-        ));
-
-        let get_result = self
-            .object_store
-            .get(&location)
-            .await
-            .inspect_err(|error| error!(?error, ?topition, ?offset, ?min_bytes, ?max_bytes))
-            .map_err(|_| Error::Api(ErrorCode::UnknownServerError))?;
-
-        get_result
-            .bytes()
-            .await
-            .inspect_err(|error| error!(?error, ?location))
-            .map_err(|_| Error::Api(ErrorCode::UnknownServerError))
-            .and_then(|encoded| self.decode(encoded))
-            .map(|mut deflated| {
-                deflated.base_offset = *offset;
-                deflated
-            })
+                .map_err(Into::into)
+        }
     }
 
     async fn offset_stage(&mut self, topition: &Topition) -> Result<OffsetStage> {
@@ -879,7 +1528,7 @@ impl Storage for DynoStore {
             ))
             .with(&self.object_store, |watermark| {
                 Ok(OffsetStage {
-                    last_stable: watermark.stable,
+                    last_stable: watermark.last_stable(),
                     high_watermark: watermark.high,
                     log_start: watermark.low,
                 })
@@ -1026,6 +1675,24 @@ impl Storage for DynoStore {
 
         let location = Path::from(format!("clusters/{}/brokers/", self.cluster));
 
+        let fenced = self.fenced().await?;
+
+        let leader_epochs = self
+            .leader_epochs
+            .with(
+                &self.object_store,
+                |leader_epochs| Ok(leader_epochs.clone()),
+            )
+            .await?;
+
+        let leader_epoch = |topic: Option<&str>, partition: i32| {
+            topic
+                .and_then(|topic| leader_epochs.get(topic))
+                .and_then(|epochs| epochs.get(&partition))
+                .copied()
+                .or(Some(0))
+        };
+
         let mut brokers = vec![];
 
         let mut list_stream = self.object_store.list(Some(&location));
@@ -1060,11 +1727,11 @@ impl Storage for DynoStore {
                 continue;
             };
 
-            let Some(listener) = broker_registration
-                .listeners
-                .iter()
-                .find(|listener| listener.name.as_str() == "broker")
-            else {
+            if fenced.contains(&broker_registration.broker_id) {
+                continue;
+            }
+
+            let Some(listener) = broker_registration.listeners.first() else {
                 continue;
             };
 
@@ -1089,7 +1756,7 @@ impl Storage for DynoStore {
                         Ok(topic_metadata) => {
                             let name = Some(topic_metadata.topic.name.to_owned());
                             let error_code = ErrorCode::None.into();
-                            let topic_id = Some(topic_metadata.id.into_bytes());
+                            let topic_id = Some(topic_metadata.id.into());
                             let is_internal = Some(false);
                             let partitions = topic_metadata.topic.num_partitions;
                             let replication_factor = topic_metadata.topic.replication_factor;
@@ -1126,7 +1793,10 @@ impl Storage for DynoStore {
                                             error_code,
                                             partition_index,
                                             leader_id,
-                                            leader_epoch: Some(-1),
+                                            leader_epoch: leader_epoch(
+                                                name.as_deref(),
+                                                partition_index,
+                                            ),
                                             replica_nodes,
                                             isr_nodes,
                                             offline_replicas: Some([].into()),
@@ -1147,14 +1817,18 @@ impl Storage for DynoStore {
 
                         Err(Error::ObjectStore(object_store::Error::NotFound { .. })) => {
                             MetadataResponseTopic {
-                                error_code: ErrorCode::UnknownTopicOrPartition.into(),
+                                error_code: match topic {
+                                    TopicId::Name(_) => ErrorCode::UnknownTopicOrPartition,
+                                    TopicId::Id(_) => ErrorCode::UnknownTopicId,
+                                }
+                                .into(),
                                 name: match topic {
                                     TopicId::Name(name) => Some(name.into()),
                                     TopicId::Id(_) => Some("".into()),
                                 },
                                 topic_id: Some(match topic {
                                     TopicId::Name(_) => NULL_TOPIC_ID,
-                                    TopicId::Id(id) => id.into_bytes(),
+                                    TopicId::Id(id) => (*id).into(),
                                 }),
                                 is_internal: Some(false),
                                 partitions: Some([].into()),
@@ -1170,7 +1844,7 @@ impl Storage for DynoStore {
                             },
                             topic_id: Some(match topic {
                                 TopicId::Name(_) => NULL_TOPIC_ID,
-                                TopicId::Id(id) => id.into_bytes(),
+                                TopicId::Id(id) => (*id).into(),
                             }),
                             is_internal: Some(false),
                             partitions: Some([].into()),
@@ -1234,7 +1908,7 @@ impl Storage for DynoStore {
 
                     let name = Some(topic_metadata.topic.name.to_owned());
                     let error_code = ErrorCode::None.into();
-                    let topic_id = Some(topic_metadata.id.into_bytes());
+                    let topic_id = Some(topic_metadata.id.into());
                     let is_internal = Some(false);
                     let partitions = topic_metadata.topic.num_partitions;
                     let replication_factor = topic_metadata.topic.replication_factor;
@@ -1271,7 +1945,7 @@ impl Storage for DynoStore {
                                     error_code,
                                     partition_index,
                                     leader_id,
-                                    leader_epoch: Some(-1),
+                                    leader_epoch: leader_epoch(name.as_deref(), partition_index),
                                     replica_nodes,
                                     isr_nodes,
                                     offline_replicas: Some([].into()),
@@ -1341,6 +2015,26 @@ impl Storage for DynoStore {
         }
     }
 
+    async fn topic_config(&mut self, topic: &TopicId) -> Result<BTreeMap<String, Option<String>>> {
+        debug!(?topic);
+
+        match self.topic_metadata(topic).await {
+            Ok(topic_metadata) => Ok(topic_metadata
+                .topic
+                .configs
+                .unwrap_or_default()
+                .into_iter()
+                .map(|config| (config.name, config.value))
+                .collect()),
+
+            Err(Error::ObjectStore(object_store::Error::NotFound { .. })) => {
+                Err(Error::Api(ErrorCode::UnknownTopicOrPartition))
+            }
+
+            Err(otherwise) => Err(otherwise),
+        }
+    }
+
     async fn update_group(
         &mut self,
         group_id: &str,
@@ -1364,83 +2058,226 @@ impl Storage for DynoStore {
         .map(Into::into)
     }
 
-    async fn init_producer(
-        &mut self,
-        transaction_id: Option<&str>,
-        transaction_timeout_ms: i32,
-        producer_id: Option<i64>,
-        producer_epoch: Option<i16>,
-    ) -> Result<ProducerIdResponse> {
-        debug!(
-            ?transaction_id,
-            ?transaction_timeout_ms,
-            ?producer_id,
-            ?producer_epoch,
-        );
-
-        if let Some(_transaction_id) = transaction_id {
-            self.producers
-                .with_mut(&self.object_store, |producers| {
-                    debug!(?producers);
-                    match (producer_id, producer_epoch) {
-                        (Some(-1), Some(-1)) => {
-                            let id = producers.last_key_value().map_or(1, |(k, _)| k + 1);
-                            _ = producers.insert(id, Producer::default());
+    async fn group_detail(&mut self, group_id: &str) -> Result<Option<GroupDetail>> {
+        debug!(?group_id);
 
-                            Ok(ProducerIdResponse {
-                                id,
-                                epoch: 0,
-                                ..Default::default()
-                            })
-                        }
+        let location = Path::from(format!(
+            "clusters/{}/groups/consumers/{}.json",
+            self.cluster, group_id,
+        ));
 
-                        (Some(producer_id), Some(producer_epoch))
-                            if producer_id > 0 && producer_epoch >= 0 =>
-                        {
-                            match producers.get(&producer_id) {
-                                Some(Producer { epoch, .. }) if producer_epoch == *epoch => {
-                                    if let Some(epoch) = epoch.checked_add(1) {
-                                        _ = producers.insert(
-                                            producer_id,
-                                            Producer {
-                                                epoch,
-                                                ..Default::default()
-                                            },
-                                        );
+        match self.object_store.get(&location).await {
+            Ok(get_result) => {
+                let encoded = get_result.bytes().await?;
 
-                                        Ok(ProducerIdResponse {
-                                            id: producer_id,
-                                            epoch,
-                                            ..Default::default()
-                                        })
-                                    } else {
-                                        let id =
-                                            producers.last_key_value().map_or(1, |(k, _)| k + 1);
-                                        _ = producers.insert(id, Producer::default());
-
-                                        Ok(ProducerIdResponse {
-                                            id,
-                                            epoch: 0,
-                                            ..Default::default()
-                                        })
-                                    }
-                                }
+                serde_json::from_slice::<GroupDetail>(&encoded[..])
+                    .map(Some)
+                    .map_err(Into::into)
+            }
 
-                                Some(_) | None => Ok(ProducerIdResponse {
-                                    id: -1,
-                                    epoch: -1,
-                                    error: ErrorCode::InvalidProducerEpoch,
-                                }),
-                            }
-                        }
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
 
-                        (None, None) => todo!(),
-                        (None, Some(_)) => todo!(),
-                        (Some(_), None) => todo!(),
-                        (_, _) => todo!(),
-                    }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn list_groups(&mut self) -> Result<Vec<String>> {
+        let location = Path::from(format!("clusters/{}/groups/consumers/", self.cluster));
+        debug!(?location);
+
+        let list_result = self
+            .object_store
+            .list_with_delimiter(Some(&location))
+            .await
+            .inspect_err(|error| error!(?error, ?location))?;
+
+        // a group has detail in "{group}.json", and committed offsets under "{group}/"
+        let groups =
+            list_result
+                .objects
+                .iter()
+                .filter_map(|meta| {
+                    meta.location
+                        .filename()
+                        .and_then(|filename| filename.strip_suffix(".json"))
+                        .map(ToOwned::to_owned)
                 })
-                .await
+                .chain(list_result.common_prefixes.iter().filter_map(|prefix| {
+                    prefix.parts().last().map(|part| part.as_ref().to_owned())
+                }))
+                .collect::<BTreeSet<_>>();
+
+        Ok(groups.into_iter().collect())
+    }
+
+    async fn update_consumer_group(
+        &mut self,
+        group_id: &str,
+        detail: ConsumerGroupDetail,
+        version: Option<Version>,
+    ) -> Result<Version, UpdateError<ConsumerGroupDetail>> {
+        debug!(?group_id, ?detail, ?version);
+
+        let location = Path::from(format!(
+            "clusters/{}/groups/consumer-protocol/{}.json",
+            self.cluster, group_id,
+        ));
+
+        self.put(
+            &location,
+            detail,
+            json_content_type(),
+            version.map(Into::into),
+        )
+        .await
+        .map(Into::into)
+    }
+
+    async fn consumer_group_detail(
+        &mut self,
+        group_id: &str,
+    ) -> Result<Option<ConsumerGroupDetail>> {
+        debug!(?group_id);
+
+        let location = Path::from(format!(
+            "clusters/{}/groups/consumer-protocol/{}.json",
+            self.cluster, group_id,
+        ));
+
+        match self.object_store.get(&location).await {
+            Ok(get_result) => {
+                let encoded = get_result.bytes().await?;
+
+                serde_json::from_slice::<ConsumerGroupDetail>(&encoded[..])
+                    .map(Some)
+                    .map_err(Into::into)
+            }
+
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn list_consumer_groups(&mut self) -> Result<Vec<String>> {
+        let location = Path::from(format!(
+            "clusters/{}/groups/consumer-protocol/",
+            self.cluster
+        ));
+        debug!(?location);
+
+        self.object_store
+            .list_with_delimiter(Some(&location))
+            .await
+            .inspect_err(|error| error!(?error, ?location))
+            .map(|list_result| {
+                list_result
+                    .objects
+                    .iter()
+                    .filter_map(|meta| {
+                        meta.location
+                            .filename()
+                            .and_then(|filename| filename.strip_suffix(".json"))
+                            .map(ToOwned::to_owned)
+                    })
+                    .collect()
+            })
+            .map_err(Into::into)
+    }
+
+    async fn init_producer(
+        &mut self,
+        transaction_id: Option<&str>,
+        transaction_timeout_ms: i32,
+        producer_id: Option<i64>,
+        producer_epoch: Option<i16>,
+    ) -> Result<ProducerIdResponse> {
+        debug!(
+            ?transaction_id,
+            ?transaction_timeout_ms,
+            ?producer_id,
+            ?producer_epoch,
+        );
+
+        if let Some(transactional_id) = transaction_id {
+            let existing = self.transaction(transactional_id).await?;
+
+            // a producer recovering its epoch must still hold the transactional id
+            if let (Some(txn), Some(producer_id), Some(producer_epoch)) =
+                (existing.as_ref(), producer_id, producer_epoch)
+            {
+                if producer_id != -1 && txn.fenced(producer_id, producer_epoch).is_some() {
+                    return Ok(ProducerIdResponse {
+                        id: -1,
+                        epoch: -1,
+                        error: ErrorCode::InvalidProducerEpoch,
+                    });
+                }
+            }
+
+            let response = self
+                .producers
+                .with_mut(&self.object_store, |producers| {
+                    debug!(?producers);
+
+                    // the epoch of a known producer is bumped, fencing any
+                    // earlier instance, until it is exhausted
+                    let (id, epoch) = existing
+                        .as_ref()
+                        .and_then(|txn| {
+                            producers
+                                .get(&txn.producer)
+                                .map_or(txn.epoch, |producer| producer.epoch.max(txn.epoch))
+                                .checked_add(1)
+                                .map(|epoch| (txn.producer, epoch))
+                        })
+                        .unwrap_or_else(|| {
+                            (producers.last_key_value().map_or(1, |(k, _)| k + 1), 0)
+                        });
+
+                    _ = producers.insert(
+                        id,
+                        Producer {
+                            epoch,
+                            ..Default::default()
+                        },
+                    );
+
+                    Ok(ProducerIdResponse {
+                        id,
+                        epoch,
+                        ..Default::default()
+                    })
+                })
+                .await?;
+
+            if let Some(txn) = existing {
+                for topition in &txn.partitions {
+                    _ = self
+                        .write_marker(topition, txn.producer, txn.epoch, false)
+                        .await?;
+                }
+            }
+
+            self.transactions
+                .with_mut(&self.object_store, |transactions| {
+                    _ = transactions.insert(
+                        transactional_id.to_owned(),
+                        Txn {
+                            producer: response.id,
+                            epoch: response.epoch,
+                            timeout_ms: transaction_timeout_ms,
+                            started: None,
+                            partitions: BTreeSet::new(),
+                            offsets: BTreeMap::new(),
+                        },
+                    );
+
+                    Ok(())
+                })
+                .await?;
+
+            Ok(response)
         } else {
             self.producers
                 .with_mut(&self.object_store, |producers| {
@@ -1466,4 +2303,540 @@ impl Storage for DynoStore {
                 .await
         }
     }
+
+    async fn txn_add_partitions(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        partitions: &[Topition],
+    ) -> Result<ErrorCode> {
+        debug!(
+            ?transactional_id,
+            ?producer_id,
+            ?producer_epoch,
+            ?partitions
+        );
+
+        let Some(txn) = self.transaction(transactional_id).await? else {
+            return Ok(ErrorCode::InvalidProducerIdMapping);
+        };
+
+        if let Some(error_code) = txn.fenced(producer_id, producer_epoch) {
+            return Ok(error_code);
+        }
+
+        let now = SystemTime::now();
+
+        self.transactions
+            .with_mut(&self.object_store, |transactions| {
+                let txn = transactions
+                    .get_mut(transactional_id)
+                    .filter(|txn| txn.fenced(producer_id, producer_epoch).is_none())
+                    .ok_or(Error::Api(ErrorCode::ProducerFenced))?;
+
+                _ = txn.started.get_or_insert(now);
+                txn.partitions.extend(partitions.iter().cloned());
+
+                Ok(ErrorCode::None)
+            })
+            .await
+            .or_else(|error| match error {
+                Error::Api(error_code) => Ok(error_code),
+                otherwise => Err(otherwise),
+            })
+    }
+
+    async fn txn_end(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed: bool,
+    ) -> Result<ErrorCode> {
+        debug!(?transactional_id, ?producer_id, ?producer_epoch, ?committed);
+
+        let Some(txn) = self.transaction(transactional_id).await? else {
+            return Ok(ErrorCode::InvalidProducerIdMapping);
+        };
+
+        if let Some(error_code) = txn.fenced(producer_id, producer_epoch) {
+            return Ok(error_code);
+        }
+
+        // markers are written before the transaction is ended, so that an
+        // interrupted end is completed when retried
+        for topition in &txn.partitions {
+            _ = self
+                .write_marker(topition, producer_id, producer_epoch, committed)
+                .await?;
+        }
+
+        if committed {
+            for (group_id, offsets) in &txn.offsets {
+                for (topition, error_code) in self.offset_commit(group_id, None, offsets).await? {
+                    if error_code != ErrorCode::None {
+                        error!(?transactional_id, ?group_id, ?topition, ?error_code);
+                        return Ok(error_code);
+                    }
+                }
+            }
+        }
+
+        self.transactions
+            .with_mut(&self.object_store, |transactions| {
+                if let Some(current) = transactions
+                    .get_mut(transactional_id)
+                    .filter(|current| current.fenced(producer_id, producer_epoch).is_none())
+                {
+                    current
+                        .partitions
+                        .retain(|topition| !txn.partitions.contains(topition));
+
+                    current
+                        .offsets
+                        .retain(|group_id, offsets| txn.offsets.get(group_id) != Some(offsets));
+
+                    if current.is_empty() {
+                        current.started = None;
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+            .map(|()| ErrorCode::None)
+    }
+
+    async fn txn_expire(&mut self, now: SystemTime) -> Result<Vec<String>> {
+        debug!(?now);
+
+        let expired = self
+            .transactions
+            .with(&self.object_store, |transactions| {
+                Ok(transactions
+                    .iter()
+                    .filter(|(_, txn)| txn.expired(now))
+                    .map(|(transactional_id, txn)| (transactional_id.to_owned(), txn.to_owned()))
+                    .collect::<Vec<_>>())
+            })
+            .await?;
+
+        let mut aborted = vec![];
+
+        for (transactional_id, txn) in expired {
+            debug!(?transactional_id, ?txn);
+
+            // bumping the epoch fences the producer of the aborted transaction
+            let epoch = txn.epoch.checked_add(1).unwrap_or(txn.epoch);
+
+            self.producers
+                .with_mut(&self.object_store, |producers| {
+                    if let Some(producer) = producers.get_mut(&txn.producer) {
+                        if producer.epoch < epoch {
+                            *producer = Producer {
+                                epoch,
+                                ..Default::default()
+                            };
+                        }
+                    }
+
+                    Ok(())
+                })
+                .await?;
+
+            for topition in &txn.partitions {
+                _ = self
+                    .write_marker(topition, txn.producer, epoch, false)
+                    .await?;
+            }
+
+            self.transactions
+                .with_mut(&self.object_store, |transactions| {
+                    if let Some(current) = transactions
+                        .get_mut(&transactional_id)
+                        .filter(|current| current.fenced(txn.producer, txn.epoch).is_none())
+                    {
+                        current.epoch = epoch;
+                        current.started = None;
+                        current.partitions.clear();
+                        current.offsets.clear();
+                    }
+
+                    Ok(())
+                })
+                .await?;
+
+            aborted.push(transactional_id);
+        }
+
+        Ok(aborted)
+    }
+
+    async fn txn_add_offsets(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+    ) -> Result<ErrorCode> {
+        debug!(?transactional_id, ?producer_id, ?producer_epoch, ?group_id);
+
+        self.txn_offsets(transactional_id, producer_id, producer_epoch, group_id, &[])
+            .await
+    }
+
+    async fn txn_offset_commit(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+        offsets: &[(Topition, OffsetCommitRequest)],
+    ) -> Result<ErrorCode> {
+        debug!(
+            ?transactional_id,
+            ?producer_id,
+            ?producer_epoch,
+            ?group_id,
+            ?offsets
+        );
+
+        self.txn_offsets(
+            transactional_id,
+            producer_id,
+            producer_epoch,
+            group_id,
+            offsets,
+        )
+        .await
+    }
+
+    async fn txn_unstable_offsets(&mut self, group_id: &str) -> Result<BTreeSet<Topition>> {
+        debug!(?group_id);
+
+        self.transactions
+            .with(&self.object_store, |transactions| {
+                Ok(transactions
+                    .values()
+                    .filter_map(|txn| txn.offsets.get(group_id))
+                    .flatten()
+                    .map(|(topition, _)| topition.to_owned())
+                    .collect())
+            })
+            .await
+    }
+
+    async fn txn_partitions(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+    ) -> Result<BTreeSet<Topition>> {
+        debug!(?transactional_id, ?producer_id, ?producer_epoch);
+
+        let Some(txn) = self.transaction(transactional_id).await? else {
+            return Err(Error::Api(ErrorCode::InvalidProducerIdMapping));
+        };
+
+        if let Some(error_code) = txn.fenced(producer_id, producer_epoch) {
+            return Err(Error::Api(error_code));
+        }
+
+        Ok(txn.partitions)
+    }
+
+    async fn aborted_transactions(
+        &mut self,
+        topition: &Topition,
+        offset: i64,
+    ) -> Result<Vec<AbortedTransaction>> {
+        debug!(?topition, ?offset);
+
+        self.watermarks
+            .entry(topition.to_owned())
+            .or_insert(ConditionData::<Watermark>::new(
+                self.cluster.as_str(),
+                topition,
+            ))
+            .with(&self.object_store, |watermark| {
+                Ok(watermark
+                    .aborted
+                    .iter()
+                    .filter(|aborted| aborted.last >= offset)
+                    .map(|aborted| AbortedTransaction {
+                        producer_id: aborted.producer,
+                        first_offset: aborted.first,
+                    })
+                    .collect())
+            })
+            .await
+    }
+
+    async fn producers(&mut self, topition: &Topition) -> Result<Vec<ProducerDetail>> {
+        debug!(?topition);
+
+        self.watermarks
+            .entry(topition.to_owned())
+            .or_insert(ConditionData::<Watermark>::new(
+                self.cluster.as_str(),
+                topition,
+            ))
+            .with(&self.object_store, |watermark| {
+                Ok(watermark
+                    .producers
+                    .iter()
+                    .map(|(producer_id, ws)| ProducerDetail {
+                        producer_id: *producer_id,
+                        producer_epoch: ws.epoch,
+                        last_sequence: ws.sequence - 1,
+                        last_timestamp: Some(ws.updated),
+                        txn_start_offset: watermark.ongoing.get(producer_id).copied(),
+                    })
+                    .collect())
+            })
+            .await
+    }
+
+    async fn transactions(&mut self) -> Result<Vec<TxnDetail>> {
+        debug!(cluster = self.cluster);
+
+        self.transactions
+            .with(&self.object_store, |transactions| {
+                Ok(transactions
+                    .iter()
+                    .map(|(transactional_id, txn)| TxnDetail {
+                        transactional_id: transactional_id.to_owned(),
+                        producer_id: txn.producer,
+                        producer_epoch: txn.epoch,
+                        timeout_ms: txn.timeout_ms,
+                        started: txn.started,
+                        partitions: txn.partitions.clone(),
+                    })
+                    .collect())
+            })
+            .await
+    }
+
+    async fn upsert_user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+        credential: ScramCredential,
+    ) -> Result<()> {
+        debug!(?username, ?mechanism);
+
+        let payload = serde_json::to_vec(&credential)
+            .map(Bytes::from)
+            .map(PutPayload::from)?;
+
+        let location = Path::from(format!(
+            "clusters/{}/scram/{}/{}.json",
+            self.cluster,
+            username,
+            mechanism.name(),
+        ));
+
+        let options = PutOptions {
+            mode: PutMode::Overwrite,
+            tags: TagSet::default(),
+            attributes: json_content_type(),
+        };
+
+        let put_result = self
+            .object_store
+            .put_opts(&location, payload, options)
+            .await?;
+
+        debug!(?location, ?put_result);
+
+        Ok(())
+    }
+
+    async fn delete_user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+    ) -> Result<()> {
+        debug!(?username, ?mechanism);
+
+        let location = Path::from(format!(
+            "clusters/{}/scram/{}/{}.json",
+            self.cluster,
+            username,
+            mechanism.name(),
+        ));
+
+        // deleting an absent object isn't an error for every object store
+        match self.object_store.head(&location).await {
+            Ok(_) => self
+                .object_store
+                .delete(&location)
+                .await
+                .map_err(Into::into),
+
+            Err(object_store::Error::NotFound { .. }) => {
+                Err(Error::Api(ErrorCode::ResourceNotFound))
+            }
+
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+    ) -> Result<Option<ScramCredential>> {
+        debug!(?username, ?mechanism);
+
+        let location = Path::from(format!(
+            "clusters/{}/scram/{}/{}.json",
+            self.cluster,
+            username,
+            mechanism.name(),
+        ));
+
+        match self.object_store.get(&location).await {
+            Ok(get_result) => {
+                let encoded = get_result.bytes().await?;
+
+                serde_json::from_slice::<ScramCredential>(&encoded[..])
+                    .map(Some)
+                    .map_err(Into::into)
+            }
+
+            Err(object_store::Error::NotFound { .. }) => Ok(None),
+
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn user_scram_credentials(
+        &mut self,
+    ) -> Result<BTreeMap<String, BTreeMap<ScramMechanism, i32>>> {
+        let location = Path::from(format!("clusters/{}/scram/", self.cluster));
+        debug!(?location);
+
+        let mut credentials: BTreeMap<String, BTreeMap<ScramMechanism, i32>> = BTreeMap::new();
+
+        let mut list_stream = self.object_store.list(Some(&location));
+
+        while let Some(meta) = list_stream
+            .next()
+            .await
+            .inspect(|meta| debug!(?meta))
+            .transpose()?
+        {
+            let parts = meta
+                .location
+                .parts()
+                .map(|part| part.as_ref().to_owned())
+                .collect::<Vec<_>>();
+
+            // ".../scram/{username}/{mechanism}.json"
+            let [.., username, filename] = &parts[..] else {
+                continue;
+            };
+
+            let Some(mechanism) = filename
+                .strip_suffix(".json")
+                .and_then(|name| ScramMechanism::from_str(name).ok())
+            else {
+                continue;
+            };
+
+            let get_result = self
+                .object_store
+                .get(&meta.location)
+                .await
+                .inspect_err(|error| error!(?error, ?location))?;
+
+            let encoded = get_result.bytes().await?;
+
+            let credential = serde_json::from_slice::<ScramCredential>(&encoded[..])?;
+
+            _ = credentials
+                .entry(username.clone())
+                .or_default()
+                .insert(mechanism, credential.iterations);
+        }
+
+        Ok(credentials)
+    }
+
+    async fn create_acls(&mut self, bindings: &[AclBinding]) -> Result<()> {
+        debug!(?bindings);
+
+        self.acls
+            .with_mut(&self.object_store, |acls| {
+                acls.extend(bindings.iter().cloned());
+                Ok(())
+            })
+            .await
+    }
+
+    async fn delete_acls(&mut self, bindings: &[AclBinding]) -> Result<()> {
+        debug!(?bindings);
+
+        self.acls
+            .with_mut(&self.object_store, |acls| {
+                acls.retain(|acl| !bindings.contains(acl));
+                Ok(())
+            })
+            .await
+    }
+
+    async fn acls(&mut self) -> Result<Vec<AclBinding>> {
+        self.acls
+            .with(&self.object_store, |acls| {
+                Ok(acls.iter().cloned().collect())
+            })
+            .await
+    }
+
+    async fn alter_reassignment(
+        &mut self,
+        topition: &Topition,
+        replicas: Option<&[i32]>,
+    ) -> Result<()> {
+        debug!(?topition, ?replicas);
+
+        self.reassignments
+            .with_mut(&self.object_store, |reassignments| {
+                if let Some(replicas) = replicas {
+                    _ = reassignments
+                        .entry(topition.topic().to_owned())
+                        .or_default()
+                        .insert(topition.partition(), replicas.to_vec());
+                } else if let Some(partitions) = reassignments.get_mut(topition.topic()) {
+                    _ = partitions.remove(&topition.partition());
+
+                    if partitions.is_empty() {
+                        _ = reassignments.remove(topition.topic());
+                    }
+                }
+
+                Ok(())
+            })
+            .await?;
+
+        self.advance_leader_epochs(topition.topic(), Some(topition.partition()))
+            .await
+    }
+
+    async fn reassignments(&mut self) -> Result<BTreeMap<Topition, Vec<i32>>> {
+        self.reassignments
+            .with(&self.object_store, |reassignments| {
+                Ok(reassignments
+                    .iter()
+                    .flat_map(|(topic, partitions)| {
+                        partitions.iter().map(|(partition, replicas)| {
+                            (Topition::new(topic.as_str(), *partition), replicas.clone())
+                        })
+                    })
+                    .collect())
+            })
+            .await
+    }
 }
@@ -22,9 +22,9 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
     array::TryFromSliceError,
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     ffi::OsString,
-    fmt::Debug,
+    fmt::{self, Debug},
     fs::DirEntry,
     io,
     num::{ParseIntError, TryFromIntError},
@@ -43,23 +43,29 @@ use tansu_kafka_sans_io::{
     describe_cluster_response::DescribeClusterBroker,
     describe_configs_response::DescribeConfigsResult,
     fetch_request::FetchTopic,
+    fetch_response::AbortedTransaction,
+    join_group_request::JoinGroupRequestProtocol,
     join_group_response::JoinGroupResponseMember,
     metadata_request::MetadataRequestTopic,
     metadata_response::{MetadataResponseBroker, MetadataResponseTopic},
     offset_commit_request::OffsetCommitRequestPartition,
+    primitive::uuid::Uuid as KafkaUuid,
     record::deflated,
-    to_system_time, to_timestamp, ConfigResource, ErrorCode,
+    to_system_time, to_timestamp,
+    txn_offset_commit_request::TxnOffsetCommitRequestPartition,
+    Ack, ConfigResource, ErrorCode,
 };
-use tracing::debug;
+use tracing::{debug, instrument};
 use uuid::Uuid;
 
 pub mod dynostore;
 pub mod index;
+mod metrics;
 pub mod os;
 pub mod pg;
 pub mod segment;
 
-pub const NULL_TOPIC_ID: [u8; 16] = [0; 16];
+pub const NULL_TOPIC_ID: KafkaUuid = KafkaUuid::nil();
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -359,6 +365,17 @@ impl TryFrom<&OffsetCommitRequestPartition> for OffsetCommitRequest {
     }
 }
 
+impl From<&TxnOffsetCommitRequestPartition> for OffsetCommitRequest {
+    fn from(value: &TxnOffsetCommitRequestPartition) -> Self {
+        Self {
+            offset: value.committed_offset,
+            leader_epoch: value.committed_leader_epoch,
+            timestamp: None,
+            metadata: value.committed_metadata.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum TopicId {
     Name(String),
@@ -397,30 +414,43 @@ impl From<[u8; 16]> for TopicId {
     }
 }
 
-impl From<&FetchTopic> for TopicId {
-    fn from(value: &FetchTopic) -> Self {
+impl From<KafkaUuid> for TopicId {
+    fn from(value: KafkaUuid) -> Self {
+        Self::Id(value.into())
+    }
+}
+
+impl TryFrom<&FetchTopic> for TopicId {
+    type Error = Error;
+
+    fn try_from(value: &FetchTopic) -> result::Result<Self, Self::Error> {
         if let Some(ref name) = value.topic {
-            Self::Name(name.into())
-        } else if let Some(ref id) = value.topic_id {
-            Self::Id(Uuid::from_bytes(*id))
+            Ok(Self::Name(name.into()))
         } else {
-            panic!("neither name nor uuid")
+            topic_id(value.topic_id)
         }
     }
 }
 
-impl From<&MetadataRequestTopic> for TopicId {
-    fn from(value: &MetadataRequestTopic) -> Self {
+impl TryFrom<&MetadataRequestTopic> for TopicId {
+    type Error = Error;
+
+    fn try_from(value: &MetadataRequestTopic) -> result::Result<Self, Self::Error> {
         if let Some(ref name) = value.name {
-            Self::Name(name.into())
-        } else if let Some(ref id) = value.topic_id {
-            Self::Id(Uuid::from_bytes(*id))
+            Ok(Self::Name(name.into()))
         } else {
-            panic!("neither name nor uuid")
+            topic_id(value.topic_id)
         }
     }
 }
 
+// a topic without a name is identified by a non nil id
+fn topic_id(id: Option<KafkaUuid>) -> Result<TopicId> {
+    id.filter(|id| !id.is_nil())
+        .map(TopicId::from)
+        .ok_or(Error::Api(ErrorCode::UnknownTopicId))
+}
+
 impl From<DeleteTopicState> for TopicId {
     fn from(value: DeleteTopicState) -> Self {
         match value {
@@ -439,6 +469,7 @@ pub struct BrokerRegistationRequest {
     pub broker_id: i32,
     pub cluster_id: String,
     pub incarnation_id: Uuid,
+    /// The first listener is the one advertised to clients in metadata.
     pub listeners: Vec<Listener>,
     pub features: Vec<Feature>,
     pub rack: Option<String>,
@@ -497,6 +528,14 @@ impl OffsetStage {
 pub struct GroupMember {
     pub join_response: JoinGroupResponseMember,
     pub last_contact: Option<SystemTime>,
+    #[serde(default)]
+    pub session_timeout_ms: Option<i32>,
+    #[serde(default)]
+    pub joined: Option<SystemTime>,
+    pub client_id: Option<String>,
+    pub client_host: Option<String>,
+    #[serde(default)]
+    pub protocols: Option<Vec<JoinGroupRequestProtocol>>,
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
@@ -505,6 +544,10 @@ pub enum GroupState {
         protocol_type: Option<String>,
         protocol_name: Option<String>,
         leader: Option<String>,
+        #[serde(default)]
+        rebalance_started: Option<SystemTime>,
+        #[serde(default)]
+        delayed_until: Option<SystemTime>,
     },
 
     Formed {
@@ -521,6 +564,8 @@ impl Default for GroupState {
             protocol_type: None,
             protocol_name: Some("".into()),
             leader: None,
+            rebalance_started: None,
+            delayed_until: None,
         }
     }
 }
@@ -550,6 +595,115 @@ impl Default for GroupDetail {
     }
 }
 
+/// A member of a group using the consumer rebalance protocol of KIP-848,
+/// with the partitions it owns and those it is reconciling towards.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct ConsumerGroupMember {
+    pub member_epoch: i32,
+    pub instance_id: Option<String>,
+    pub rack_id: Option<String>,
+    pub client_id: Option<String>,
+    pub client_host: Option<String>,
+    pub rebalance_timeout_ms: i32,
+    pub subscribed_topic_names: Vec<String>,
+    pub server_assignor: Option<String>,
+    pub assigned: BTreeMap<String, BTreeSet<i32>>,
+    pub target: BTreeMap<String, BTreeSet<i32>>,
+    pub last_contact: Option<SystemTime>,
+}
+
+/// A group using the consumer rebalance protocol of KIP-848, where the
+/// group epoch is bumped by a change in membership or subscription, and the
+/// assignment epoch is that of the most recently computed target assignment.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct ConsumerGroupDetail {
+    pub group_epoch: i32,
+    pub assignment_epoch: i32,
+    pub members: BTreeMap<String, ConsumerGroupMember>,
+}
+
+/// A SCRAM mechanism, with the identifiers used by AlterUserScramCredentials.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum ScramMechanism {
+    Sha256,
+    Sha512,
+}
+
+impl ScramMechanism {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "SCRAM-SHA-256",
+            Self::Sha512 => "SCRAM-SHA-512",
+        }
+    }
+}
+
+impl FromStr for ScramMechanism {
+    type Err = Error;
+
+    fn from_str(s: &str) -> result::Result<Self, Self::Err> {
+        match s {
+            "SCRAM-SHA-256" => Ok(Self::Sha256),
+            "SCRAM-SHA-512" => Ok(Self::Sha512),
+            _ => Err(Error::Api(ErrorCode::UnsupportedSaslMechanism)),
+        }
+    }
+}
+
+impl TryFrom<i8> for ScramMechanism {
+    type Error = Error;
+
+    fn try_from(value: i8) -> result::Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Sha256),
+            2 => Ok(Self::Sha512),
+            _ => Err(Error::Api(ErrorCode::UnsupportedSaslMechanism)),
+        }
+    }
+}
+
+impl From<ScramMechanism> for i8 {
+    fn from(value: ScramMechanism) -> Self {
+        match value {
+            ScramMechanism::Sha256 => 1,
+            ScramMechanism::Sha512 => 2,
+        }
+    }
+}
+
+/// The salted keys of a SCRAM credential (RFC 5802), from which the
+/// password cannot be recovered.
+#[derive(Clone, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct ScramCredential {
+    pub salt: Bytes,
+    pub iterations: i32,
+    pub stored_key: Bytes,
+    pub server_key: Bytes,
+}
+
+// the keys are enough to impersonate the server, or to authenticate as the
+// user with a replayed conversation, so are never written to a log
+impl Debug for ScramCredential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(stringify!(ScramCredential))
+            .field("iterations", &self.iterations)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An ACL binding, with the resource type, pattern type, operation and
+/// permission type as their Kafka protocol values.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct AclBinding {
+    pub resource_type: i8,
+    pub resource_name: String,
+    pub pattern_type: i8,
+    pub principal: String,
+    pub host: String,
+    pub operation: i8,
+    pub permission_type: i8,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Version {
     e_tag: Option<String>,
@@ -573,6 +727,31 @@ impl Default for ProducerIdResponse {
     }
 }
 
+/// The state of an idempotent or transactional producer that has appended
+/// to a topition.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct ProducerDetail {
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub last_sequence: i32,
+    pub last_timestamp: Option<SystemTime>,
+
+    /// The first offset of the ongoing transaction of the producer, if any.
+    pub txn_start_offset: Option<i64>,
+}
+
+/// A transactional producer as seen by its coordinator, with a transaction
+/// that is ongoing while it has been started.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct TxnDetail {
+    pub transactional_id: String,
+    pub producer_id: i64,
+    pub producer_epoch: i16,
+    pub timeout_ms: i32,
+    pub started: Option<SystemTime>,
+    pub partitions: BTreeSet<Topition>,
+}
+
 #[async_trait]
 pub trait StorageProvider {
     async fn provide_storage(&mut self) -> impl Storage;
@@ -580,13 +759,25 @@ pub trait StorageProvider {
 
 #[async_trait]
 pub trait Storage: Clone + Debug + Send + Sync + 'static {
+    /// Register a broker, returning its epoch. A registration replaces, and
+    /// so fences, any earlier registration of the same broker.
     async fn register_broker(
         &mut self,
         broker_registration: BrokerRegistationRequest,
-    ) -> Result<()>;
+    ) -> Result<i64>;
 
     async fn create_topic(&mut self, topic: CreatableTopic, validate_only: bool) -> Result<Uuid>;
 
+    /// Increase the number of partitions in a topic to `count`. A count that
+    /// does not exceed the existing partitions is an
+    /// `Error::Api(ErrorCode::InvalidPartitions)`.
+    async fn create_partitions(
+        &mut self,
+        topic: &TopicId,
+        count: i32,
+        validate_only: bool,
+    ) -> Result<()>;
+
     async fn delete_records(
         &mut self,
         topics: &[DeleteRecordsTopic],
@@ -594,9 +785,43 @@ pub trait Storage: Clone + Debug + Send + Sync + 'static {
 
     async fn delete_topic(&mut self, topic: &TopicId) -> Result<ErrorCode>;
 
-    async fn brokers(&mut self) -> Result<Vec<DescribeClusterBroker>>;
+    /// The registered brokers, with the advertised address of the named
+    /// listener, or of their first listener when no name is given. Brokers
+    /// without the named listener, or that are fenced, are omitted.
+    async fn brokers(&mut self, listener: Option<&str>) -> Result<Vec<DescribeClusterBroker>>;
 
-    async fn produce(&mut self, topition: &Topition, batch: deflated::Batch) -> Result<i64>;
+    /// Record a heartbeat from a broker registered in this epoch, which is
+    /// fenced when it wants to be, otherwise unfenced. A heartbeat from an
+    /// earlier registration is an [`ErrorCode::StaleBrokerEpoch`].
+    async fn broker_heartbeat(
+        &mut self,
+        broker_id: i32,
+        broker_epoch: i64,
+        want_fence: bool,
+    ) -> Result<ErrorCode>;
+
+    /// Fence the brokers without a heartbeat within the session timeout by
+    /// `now`, so that they are omitted from metadata, returning those fenced.
+    async fn fence_brokers(
+        &mut self,
+        now: SystemTime,
+        session_timeout: Duration,
+    ) -> Result<Vec<i32>>;
+
+    /// Remove the registration of a fenced broker. A broker that isn't
+    /// registered is an [`ErrorCode::BrokerIdNotRegistered`], and one that
+    /// is still live an [`ErrorCode::InvalidRequest`]. The epoch of a broker
+    /// that registers again continues from that of its removed registration.
+    async fn unregister_broker(&mut self, broker_id: i32) -> Result<ErrorCode>;
+
+    /// Append a batch to a topition, returning its base offset. The `ack`
+    /// requested by the producer may relax how durably the batch is stored.
+    async fn produce(
+        &mut self,
+        topition: &Topition,
+        batch: deflated::Batch,
+        ack: Ack,
+    ) -> Result<i64>;
 
     async fn fetch(
         &mut self,
@@ -636,6 +861,11 @@ pub trait Storage: Clone + Debug + Send + Sync + 'static {
         keys: Option<&[String]>,
     ) -> Result<DescribeConfigsResult>;
 
+    /// The configuration overrides stored for a topic, by name. Broker
+    /// defaults are not included. An unknown topic is an
+    /// `Error::Api(ErrorCode::UnknownTopicOrPartition)`.
+    async fn topic_config(&mut self, topic: &TopicId) -> Result<BTreeMap<String, Option<String>>>;
+
     async fn update_group(
         &mut self,
         group_id: &str,
@@ -643,6 +873,32 @@ pub trait Storage: Clone + Debug + Send + Sync + 'static {
         version: Option<Version>,
     ) -> Result<Version, UpdateError<GroupDetail>>;
 
+    /// The most recently stored detail of a group, if the group exists.
+    async fn group_detail(&mut self, group_id: &str) -> Result<Option<GroupDetail>>;
+
+    /// Every group known to storage, including those that only have committed offsets.
+    async fn list_groups(&mut self) -> Result<Vec<String>>;
+
+    async fn update_consumer_group(
+        &mut self,
+        group_id: &str,
+        detail: ConsumerGroupDetail,
+        version: Option<Version>,
+    ) -> Result<Version, UpdateError<ConsumerGroupDetail>>;
+
+    /// The most recently stored detail of a group using the consumer
+    /// rebalance protocol, if the group exists.
+    async fn consumer_group_detail(
+        &mut self,
+        group_id: &str,
+    ) -> Result<Option<ConsumerGroupDetail>>;
+
+    /// Every group using the consumer rebalance protocol.
+    async fn list_consumer_groups(&mut self) -> Result<Vec<String>>;
+
+    /// Initialize a producer. A producer with a transactional id keeps its
+    /// producer id across restarts, with a bumped epoch that fences any
+    /// earlier instance, aborting a transaction that it left ongoing.
     async fn init_producer(
         &mut self,
         transactional_id: Option<&str>,
@@ -650,6 +906,127 @@ pub trait Storage: Clone + Debug + Send + Sync + 'static {
         producer_id: Option<i64>,
         producer_epoch: Option<i16>,
     ) -> Result<ProducerIdResponse>;
+
+    /// Add partitions to the ongoing transaction of a producer, starting
+    /// the transaction if necessary.
+    async fn txn_add_partitions(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        partitions: &[Topition],
+    ) -> Result<ErrorCode>;
+
+    /// End the ongoing transaction of a producer, writing a commit or abort
+    /// marker to each of its partitions.
+    async fn txn_end(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed: bool,
+    ) -> Result<ErrorCode>;
+
+    /// Abort the transactions that have been ongoing for longer than their
+    /// timeout at `now`, fencing their producers, returning their
+    /// transactional ids.
+    async fn txn_expire(&mut self, now: SystemTime) -> Result<Vec<String>>;
+
+    /// Add the offsets of a consumer group to the ongoing transaction of a
+    /// producer, starting the transaction if necessary.
+    async fn txn_add_offsets(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+    ) -> Result<ErrorCode>;
+
+    /// Stage offsets committed by a group within the ongoing transaction of
+    /// a producer. Staged offsets are committed when the transaction
+    /// commits, and discarded when it aborts.
+    async fn txn_offset_commit(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+        offsets: &[(Topition, OffsetCommitRequest)],
+    ) -> Result<ErrorCode>;
+
+    /// The topitions of a group with offsets staged by an ongoing transaction.
+    async fn txn_unstable_offsets(&mut self, group_id: &str) -> Result<BTreeSet<Topition>>;
+
+    /// The partitions added to the ongoing transaction of a producer, with an
+    /// `Error::Api` when the producer is unknown or fenced.
+    async fn txn_partitions(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+    ) -> Result<BTreeSet<Topition>>;
+
+    /// The transactions aborted in a topition that end at or after an
+    /// offset, so that a read committed consumer can skip their records.
+    async fn aborted_transactions(
+        &mut self,
+        topition: &Topition,
+        offset: i64,
+    ) -> Result<Vec<AbortedTransaction>>;
+
+    /// The idempotent and transactional producers that have appended to a
+    /// topition.
+    async fn producers(&mut self, topition: &Topition) -> Result<Vec<ProducerDetail>>;
+
+    /// Every transactional producer, with its ongoing transaction.
+    async fn transactions(&mut self) -> Result<Vec<TxnDetail>>;
+
+    /// Create or replace the SCRAM credential of a user for a mechanism.
+    async fn upsert_user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+        credential: ScramCredential,
+    ) -> Result<()>;
+
+    /// Remove the SCRAM credential of a user for a mechanism. An absent
+    /// credential is an `Error::Api(ErrorCode::ResourceNotFound)`.
+    async fn delete_user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+    ) -> Result<()>;
+
+    async fn user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+    ) -> Result<Option<ScramCredential>>;
+
+    /// The iterations of every SCRAM credential, by user and mechanism.
+    async fn user_scram_credentials(
+        &mut self,
+    ) -> Result<BTreeMap<String, BTreeMap<ScramMechanism, i32>>>;
+
+    /// Add ACL bindings, ignoring those that already exist.
+    async fn create_acls(&mut self, bindings: &[AclBinding]) -> Result<()>;
+
+    /// Remove ACL bindings, ignoring those that do not exist.
+    async fn delete_acls(&mut self, bindings: &[AclBinding]) -> Result<()>;
+
+    async fn acls(&mut self) -> Result<Vec<AclBinding>>;
+
+    /// Record the replicas that a topition is being reassigned to, or
+    /// forget its reassignment with `None`.
+    async fn alter_reassignment(
+        &mut self,
+        topition: &Topition,
+        replicas: Option<&[i32]>,
+    ) -> Result<()>;
+
+    /// The replicas that each topition with a recorded reassignment is
+    /// being reassigned to.
+    async fn reassignments(&mut self) -> Result<BTreeMap<Topition, Vec<i32>>>;
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -671,16 +1048,18 @@ pub enum StorageContainer {
 
 #[async_trait]
 impl Storage for StorageContainer {
+    #[instrument(skip_all)]
     async fn register_broker(
         &mut self,
         broker_registration: BrokerRegistationRequest,
-    ) -> Result<()> {
+    ) -> Result<i64> {
         match self {
             Self::Postgres(pg) => pg.register_broker(broker_registration).await,
             Self::DynoStore(dyn_store) => dyn_store.register_broker(broker_registration).await,
         }
     }
 
+    #[instrument(skip_all, fields(name = topic.name))]
     async fn create_topic(&mut self, topic: CreatableTopic, validate_only: bool) -> Result<Uuid> {
         match self {
             Self::Postgres(pg) => pg.create_topic(topic, validate_only).await,
@@ -688,6 +1067,24 @@ impl Storage for StorageContainer {
         }
     }
 
+    #[instrument(skip_all, fields(?topic, count))]
+    async fn create_partitions(
+        &mut self,
+        topic: &TopicId,
+        count: i32,
+        validate_only: bool,
+    ) -> Result<()> {
+        match self {
+            Self::Postgres(pg) => pg.create_partitions(topic, count, validate_only).await,
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .create_partitions(topic, count, validate_only)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
     async fn delete_records(
         &mut self,
         topics: &[DeleteRecordsTopic],
@@ -698,6 +1095,7 @@ impl Storage for StorageContainer {
         }
     }
 
+    #[instrument(skip_all, fields(?topic))]
     async fn delete_topic(&mut self, topic: &TopicId) -> Result<ErrorCode> {
         match self {
             Self::Postgres(pg) => pg.delete_topic(topic).await,
@@ -705,20 +1103,80 @@ impl Storage for StorageContainer {
         }
     }
 
-    async fn brokers(&mut self) -> Result<Vec<DescribeClusterBroker>> {
+    #[instrument(skip_all)]
+    async fn brokers(&mut self, listener: Option<&str>) -> Result<Vec<DescribeClusterBroker>> {
         match self {
-            Self::Postgres(pg) => pg.brokers().await,
-            Self::DynoStore(dyn_store) => dyn_store.brokers().await,
+            Self::Postgres(pg) => pg.brokers(listener).await,
+            Self::DynoStore(dyn_store) => dyn_store.brokers(listener).await,
         }
     }
 
-    async fn produce(&mut self, topition: &Topition, batch: deflated::Batch) -> Result<i64> {
+    #[instrument(skip_all)]
+    async fn broker_heartbeat(
+        &mut self,
+        broker_id: i32,
+        broker_epoch: i64,
+        want_fence: bool,
+    ) -> Result<ErrorCode> {
         match self {
-            Self::Postgres(pg) => pg.produce(topition, batch).await,
-            Self::DynoStore(dyn_store) => dyn_store.produce(topition, batch).await,
+            Self::Postgres(pg) => {
+                pg.broker_heartbeat(broker_id, broker_epoch, want_fence)
+                    .await
+            }
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .broker_heartbeat(broker_id, broker_epoch, want_fence)
+                    .await
+            }
         }
     }
 
+    #[instrument(skip_all)]
+    async fn fence_brokers(
+        &mut self,
+        now: SystemTime,
+        session_timeout: Duration,
+    ) -> Result<Vec<i32>> {
+        match self {
+            Self::Postgres(pg) => pg.fence_brokers(now, session_timeout).await,
+            Self::DynoStore(dyn_store) => dyn_store.fence_brokers(now, session_timeout).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn unregister_broker(&mut self, broker_id: i32) -> Result<ErrorCode> {
+        match self {
+            Self::Postgres(pg) => pg.unregister_broker(broker_id).await,
+            Self::DynoStore(dyn_store) => dyn_store.unregister_broker(broker_id).await,
+        }
+    }
+
+    #[instrument(skip_all, fields(?topition, ?ack))]
+    async fn produce(
+        &mut self,
+        topition: &Topition,
+        batch: deflated::Batch,
+        ack: Ack,
+    ) -> Result<i64> {
+        let bytes = batch.record_data.len();
+        let records = batch.record_count;
+        let last_offset_delta = i64::from(batch.last_offset_delta);
+
+        match self {
+            Self::Postgres(pg) => pg.produce(topition, batch, ack).await,
+            Self::DynoStore(dyn_store) => dyn_store.produce(topition, batch, ack).await,
+        }
+        .inspect(|base_offset| {
+            metrics::produced(
+                topition,
+                bytes,
+                records,
+                base_offset + last_offset_delta + 1,
+            )
+        })
+    }
+
+    #[instrument(skip_all, fields(?topition, offset, min_bytes, max_bytes))]
     async fn fetch(
         &mut self,
         topition: &'_ Topition,
@@ -734,8 +1192,10 @@ impl Storage for StorageContainer {
                     .await
             }
         }
+        .inspect(|batch| metrics::fetched(topition, batch.record_data.len()))
     }
 
+    #[instrument(skip_all, fields(?topition))]
     async fn offset_stage(&mut self, topition: &Topition) -> Result<OffsetStage> {
         match self {
             Self::Postgres(pg) => pg.offset_stage(topition).await,
@@ -743,6 +1203,7 @@ impl Storage for StorageContainer {
         }
     }
 
+    #[instrument(skip_all)]
     async fn list_offsets(
         &mut self,
         offsets: &[(Topition, ListOffsetRequest)],
@@ -753,6 +1214,7 @@ impl Storage for StorageContainer {
         }
     }
 
+    #[instrument(skip_all, fields(group_id))]
     async fn offset_commit(
         &mut self,
         group_id: &str,
@@ -769,6 +1231,7 @@ impl Storage for StorageContainer {
         }
     }
 
+    #[instrument(skip_all, fields(group_id))]
     async fn offset_fetch(
         &mut self,
         group_id: Option<&str>,
@@ -785,6 +1248,7 @@ impl Storage for StorageContainer {
         }
     }
 
+    #[instrument(skip_all)]
     async fn metadata(&mut self, topics: Option<&[TopicId]>) -> Result<MetadataResponse> {
         match self {
             Self::Postgres(pg) => pg.metadata(topics).await,
@@ -792,6 +1256,7 @@ impl Storage for StorageContainer {
         }
     }
 
+    #[instrument(skip_all)]
     async fn describe_config(
         &mut self,
         name: &str,
@@ -804,6 +1269,15 @@ impl Storage for StorageContainer {
         }
     }
 
+    #[instrument(skip_all)]
+    async fn topic_config(&mut self, topic: &TopicId) -> Result<BTreeMap<String, Option<String>>> {
+        match self {
+            Self::Postgres(pg) => pg.topic_config(topic).await,
+            Self::DynoStore(dyn_store) => dyn_store.topic_config(topic).await,
+        }
+    }
+
+    #[instrument(skip_all, fields(group_id))]
     async fn update_group(
         &mut self,
         group_id: &str,
@@ -816,6 +1290,59 @@ impl Storage for StorageContainer {
         }
     }
 
+    #[instrument(skip_all, fields(group_id))]
+    async fn group_detail(&mut self, group_id: &str) -> Result<Option<GroupDetail>> {
+        match self {
+            Self::Postgres(pg) => pg.group_detail(group_id).await,
+            Self::DynoStore(dyn_store) => dyn_store.group_detail(group_id).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn list_groups(&mut self) -> Result<Vec<String>> {
+        match self {
+            Self::Postgres(pg) => pg.list_groups().await,
+            Self::DynoStore(dyn_store) => dyn_store.list_groups().await,
+        }
+    }
+
+    #[instrument(skip_all, fields(group_id))]
+    async fn update_consumer_group(
+        &mut self,
+        group_id: &str,
+        detail: ConsumerGroupDetail,
+        version: Option<Version>,
+    ) -> Result<Version, UpdateError<ConsumerGroupDetail>> {
+        match self {
+            Self::Postgres(pg) => pg.update_consumer_group(group_id, detail, version).await,
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .update_consumer_group(group_id, detail, version)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip_all, fields(group_id))]
+    async fn consumer_group_detail(
+        &mut self,
+        group_id: &str,
+    ) -> Result<Option<ConsumerGroupDetail>> {
+        match self {
+            Self::Postgres(pg) => pg.consumer_group_detail(group_id).await,
+            Self::DynoStore(dyn_store) => dyn_store.consumer_group_detail(group_id).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn list_consumer_groups(&mut self) -> Result<Vec<String>> {
+        match self {
+            Self::Postgres(pg) => pg.list_consumer_groups().await,
+            Self::DynoStore(dyn_store) => dyn_store.list_consumer_groups().await,
+        }
+    }
+
+    #[instrument(skip_all)]
     async fn init_producer(
         &mut self,
         transaction_id: Option<&str>,
@@ -852,12 +1379,313 @@ impl Storage for StorageContainer {
             }
         }
     }
+
+    #[instrument(skip_all)]
+    async fn txn_add_partitions(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        partitions: &[Topition],
+    ) -> Result<ErrorCode> {
+        match self {
+            Self::Postgres(pg) => {
+                pg.txn_add_partitions(transactional_id, producer_id, producer_epoch, partitions)
+                    .await
+            }
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .txn_add_partitions(transactional_id, producer_id, producer_epoch, partitions)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn txn_end(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed: bool,
+    ) -> Result<ErrorCode> {
+        match self {
+            Self::Postgres(pg) => {
+                pg.txn_end(transactional_id, producer_id, producer_epoch, committed)
+                    .await
+            }
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .txn_end(transactional_id, producer_id, producer_epoch, committed)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn txn_expire(&mut self, now: SystemTime) -> Result<Vec<String>> {
+        match self {
+            Self::Postgres(pg) => pg.txn_expire(now).await,
+            Self::DynoStore(dyn_store) => dyn_store.txn_expire(now).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn txn_add_offsets(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+    ) -> Result<ErrorCode> {
+        match self {
+            Self::Postgres(pg) => {
+                pg.txn_add_offsets(transactional_id, producer_id, producer_epoch, group_id)
+                    .await
+            }
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .txn_add_offsets(transactional_id, producer_id, producer_epoch, group_id)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn txn_offset_commit(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+        offsets: &[(Topition, OffsetCommitRequest)],
+    ) -> Result<ErrorCode> {
+        match self {
+            Self::Postgres(pg) => {
+                pg.txn_offset_commit(
+                    transactional_id,
+                    producer_id,
+                    producer_epoch,
+                    group_id,
+                    offsets,
+                )
+                .await
+            }
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .txn_offset_commit(
+                        transactional_id,
+                        producer_id,
+                        producer_epoch,
+                        group_id,
+                        offsets,
+                    )
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn txn_unstable_offsets(&mut self, group_id: &str) -> Result<BTreeSet<Topition>> {
+        match self {
+            Self::Postgres(pg) => pg.txn_unstable_offsets(group_id).await,
+            Self::DynoStore(dyn_store) => dyn_store.txn_unstable_offsets(group_id).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn txn_partitions(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+    ) -> Result<BTreeSet<Topition>> {
+        match self {
+            Self::Postgres(pg) => {
+                pg.txn_partitions(transactional_id, producer_id, producer_epoch)
+                    .await
+            }
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .txn_partitions(transactional_id, producer_id, producer_epoch)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn aborted_transactions(
+        &mut self,
+        topition: &Topition,
+        offset: i64,
+    ) -> Result<Vec<AbortedTransaction>> {
+        match self {
+            Self::Postgres(pg) => pg.aborted_transactions(topition, offset).await,
+            Self::DynoStore(dyn_store) => dyn_store.aborted_transactions(topition, offset).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn producers(&mut self, topition: &Topition) -> Result<Vec<ProducerDetail>> {
+        match self {
+            Self::Postgres(pg) => pg.producers(topition).await,
+            Self::DynoStore(dyn_store) => dyn_store.producers(topition).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn transactions(&mut self) -> Result<Vec<TxnDetail>> {
+        match self {
+            Self::Postgres(pg) => pg.transactions().await,
+            Self::DynoStore(dyn_store) => dyn_store.transactions().await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn upsert_user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+        credential: ScramCredential,
+    ) -> Result<()> {
+        match self {
+            Self::Postgres(pg) => {
+                pg.upsert_user_scram_credential(username, mechanism, credential)
+                    .await
+            }
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .upsert_user_scram_credential(username, mechanism, credential)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+    ) -> Result<()> {
+        match self {
+            Self::Postgres(pg) => pg.delete_user_scram_credential(username, mechanism).await,
+            Self::DynoStore(dyn_store) => {
+                dyn_store
+                    .delete_user_scram_credential(username, mechanism)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+    ) -> Result<Option<ScramCredential>> {
+        match self {
+            Self::Postgres(pg) => pg.user_scram_credential(username, mechanism).await,
+            Self::DynoStore(dyn_store) => {
+                dyn_store.user_scram_credential(username, mechanism).await
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn user_scram_credentials(
+        &mut self,
+    ) -> Result<BTreeMap<String, BTreeMap<ScramMechanism, i32>>> {
+        match self {
+            Self::Postgres(pg) => pg.user_scram_credentials().await,
+            Self::DynoStore(dyn_store) => dyn_store.user_scram_credentials().await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn create_acls(&mut self, bindings: &[AclBinding]) -> Result<()> {
+        match self {
+            Self::Postgres(pg) => pg.create_acls(bindings).await,
+            Self::DynoStore(dyn_store) => dyn_store.create_acls(bindings).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn delete_acls(&mut self, bindings: &[AclBinding]) -> Result<()> {
+        match self {
+            Self::Postgres(pg) => pg.delete_acls(bindings).await,
+            Self::DynoStore(dyn_store) => dyn_store.delete_acls(bindings).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn acls(&mut self) -> Result<Vec<AclBinding>> {
+        match self {
+            Self::Postgres(pg) => pg.acls().await,
+            Self::DynoStore(dyn_store) => dyn_store.acls().await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn alter_reassignment(
+        &mut self,
+        topition: &Topition,
+        replicas: Option<&[i32]>,
+    ) -> Result<()> {
+        match self {
+            Self::Postgres(pg) => pg.alter_reassignment(topition, replicas).await,
+            Self::DynoStore(dyn_store) => dyn_store.alter_reassignment(topition, replicas).await,
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn reassignments(&mut self) -> Result<BTreeMap<Topition, Vec<i32>>> {
+        match self {
+            Self::Postgres(pg) => pg.reassignments().await,
+            Self::DynoStore(dyn_store) => dyn_store.reassignments().await,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn topic_id_from_fetch_topic() -> Result<()> {
+        let id = KafkaUuid([7; 16]);
+
+        assert_eq!(
+            TopicId::Name("abc".into()),
+            TopicId::try_from(&FetchTopic {
+                topic: Some("abc".into()),
+                topic_id: None,
+                partitions: None,
+            })?
+        );
+
+        assert_eq!(
+            TopicId::Id(id.into()),
+            TopicId::try_from(&FetchTopic {
+                topic: None,
+                topic_id: Some(id),
+                partitions: None,
+            })?
+        );
+
+        for topic_id in [None, Some(NULL_TOPIC_ID)] {
+            assert!(matches!(
+                TopicId::try_from(&FetchTopic {
+                    topic: None,
+                    topic_id,
+                    partitions: None,
+                }),
+                Err(Error::Api(ErrorCode::UnknownTopicId))
+            ));
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn topition_from_str() -> Result<()> {
         let topition = Topition::from_str("qwerty-2147483647")?;
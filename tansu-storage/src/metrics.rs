@@ -0,0 +1,109 @@
+// Copyright ⓒ 2024 Peter Morgan <peter.james.morgan@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Metrics of stored topics, registered with the default prometheus
+//! registry, named after their kafka.server and kafka.log equivalents.
+
+use crate::Topition;
+use lazy_static::lazy_static;
+use prometheus::{register_int_counter_vec, register_int_gauge_vec, IntCounterVec, IntGaugeVec};
+
+lazy_static! {
+    static ref BYTES_IN: IntCounterVec = register_int_counter_vec!(
+        "kafka_server_brokertopicmetrics_bytesin_total",
+        "Bytes of record batches produced to a topic",
+        &["topic"]
+    )
+    .expect("bytes in");
+    static ref MESSAGES_IN: IntCounterVec = register_int_counter_vec!(
+        "kafka_server_brokertopicmetrics_messagesin_total",
+        "Records produced to a topic",
+        &["topic"]
+    )
+    .expect("messages in");
+    static ref BYTES_OUT: IntCounterVec = register_int_counter_vec!(
+        "kafka_server_brokertopicmetrics_bytesout_total",
+        "Bytes of record batches fetched from a topic",
+        &["topic"]
+    )
+    .expect("bytes out");
+    static ref LOG_END_OFFSET: IntGaugeVec = register_int_gauge_vec!(
+        "kafka_log_log_logendoffset",
+        "The offset of the next record produced to a partition",
+        &["topic", "partition"]
+    )
+    .expect("log end offset");
+    static ref LOG_SEGMENTS: IntGaugeVec = register_int_gauge_vec!(
+        "kafka_log_log_numlogsegments",
+        "The number of segments of a partition",
+        &["topic", "partition"]
+    )
+    .expect("log segments");
+}
+
+pub(crate) fn produced(topition: &Topition, bytes: usize, records: u32, log_end_offset: i64) {
+    BYTES_IN
+        .with_label_values(&[topition.topic()])
+        .inc_by(bytes as u64);
+
+    MESSAGES_IN
+        .with_label_values(&[topition.topic()])
+        .inc_by(u64::from(records));
+
+    LOG_END_OFFSET
+        .with_label_values(&[topition.topic(), &topition.partition().to_string()])
+        .set(log_end_offset);
+}
+
+pub(crate) fn fetched(topition: &Topition, bytes: usize) {
+    BYTES_OUT
+        .with_label_values(&[topition.topic()])
+        .inc_by(bytes as u64);
+}
+
+pub(crate) fn segments(topition: &Topition, segments: usize) {
+    LOG_SEGMENTS
+        .with_label_values(&[topition.topic(), &topition.partition().to_string()])
+        .set(i64::try_from(segments).unwrap_or(i64::MAX));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn produced_and_fetched() {
+        let topition = Topition::new("metrics", 3);
+
+        let bytes_in = BYTES_IN.with_label_values(&["metrics"]).get();
+        let bytes_out = BYTES_OUT.with_label_values(&["metrics"]).get();
+
+        produced(&topition, 64, 3, 35);
+        fetched(&topition, 64);
+
+        assert_eq!(
+            bytes_in + 64,
+            BYTES_IN.with_label_values(&["metrics"]).get()
+        );
+        assert_eq!(
+            bytes_out + 64,
+            BYTES_OUT.with_label_values(&["metrics"]).get()
+        );
+        assert_eq!(
+            35,
+            LOG_END_OFFSET.with_label_values(&["metrics", "3"]).get()
+        );
+    }
+}
@@ -14,7 +14,8 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::{
-    collections::BTreeMap,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
     marker::PhantomData,
     str::FromStr,
     time::{Duration, SystemTime},
@@ -31,18 +32,23 @@ use tansu_kafka_sans_io::{
     delete_records_response::{DeleteRecordsPartitionResult, DeleteRecordsTopicResult},
     describe_cluster_response::DescribeClusterBroker,
     describe_configs_response::{DescribeConfigsResourceResult, DescribeConfigsResult},
+    fetch_response::AbortedTransaction,
     metadata_response::{MetadataResponseBroker, MetadataResponsePartition, MetadataResponseTopic},
-    record::{deflated, inflated, Header, Record},
-    to_system_time, to_timestamp, ConfigResource, ConfigSource, ConfigType, ErrorCode,
+    record::{
+        control::EndTransactionMarker, deflated, inflated, BatchAttributes, ControlRecord, Header,
+        Record,
+    },
+    to_system_time, to_timestamp, Ack, ConfigResource, ConfigSource, ConfigType, ErrorCode,
 };
 use tokio_postgres::{error::SqlState, Config, NoTls, Transaction};
 use tracing::{debug, error};
 use uuid::Uuid;
 
 use crate::{
-    BrokerRegistationRequest, Error, GroupDetail, ListOffsetRequest, ListOffsetResponse,
-    MetadataResponse, OffsetCommitRequest, OffsetStage, ProducerIdResponse, Result, Storage,
-    TopicId, Topition, UpdateError, Version, NULL_TOPIC_ID,
+    AclBinding, BrokerRegistationRequest, ConsumerGroupDetail, Error, GroupDetail,
+    ListOffsetRequest, ListOffsetResponse, MetadataResponse, OffsetCommitRequest, OffsetStage,
+    ProducerDetail, ProducerIdResponse, Result, ScramCredential, ScramMechanism, Storage, TopicId,
+    Topition, TxnDetail, UpdateError, Version, NULL_TOPIC_ID,
 };
 
 const DELETE_CONSUMER_OFFSETS_FOR_TOPIC: &str = concat!(
@@ -105,6 +111,57 @@ const DELETE_TOPIC: &str = concat!(
     " topic.<COLUMN> = $2"
 );
 
+const INSERT_LEADER_EPOCHS: &str = concat!(
+    "insert into leader_epoch",
+    " (cluster, topic, partition)",
+    " select topic.cluster, topic.name, generate_series($2::integer, $3 - 1)",
+    " from topic",
+    " where topic.id = $1",
+    " on conflict (cluster, topic, partition)",
+    " do nothing"
+);
+
+const ADVANCE_LEADER_EPOCHS_FOR_TOPIC: &str = concat!(
+    "update leader_epoch",
+    " set epoch = leader_epoch.epoch + 1, last_updated = current_timestamp",
+    " from cluster, topic",
+    " where",
+    " leader_epoch.cluster = cluster.id",
+    " and",
+    " leader_epoch.topic = topic.name",
+    " and",
+    " topic.cluster = cluster.id",
+    " and",
+    " cluster.name = $1",
+    " and",
+    " topic.<COLUMN> = $2"
+);
+
+const TOPIC_PARTITIONS_FOR_UPDATE: &str = concat!(
+    "select topic.id, topic.partitions",
+    " from cluster, topic",
+    " where",
+    " topic.cluster = cluster.id",
+    " and",
+    " cluster.name = $1",
+    " and",
+    " topic.<COLUMN> = $2",
+    " for update of topic"
+);
+
+const TOPIC_CONFIGURATION: &str = concat!(
+    "select topic_configuration.name, topic_configuration.value",
+    " from cluster, topic",
+    " left join topic_configuration",
+    " on topic_configuration.topic = topic.id",
+    " where",
+    " topic.cluster = cluster.id",
+    " and",
+    " cluster.name = $1",
+    " and",
+    " topic.<COLUMN> = $2"
+);
+
 #[derive(Clone, Debug)]
 pub struct Postgres {
     cluster: String,
@@ -189,6 +246,132 @@ impl Postgres {
         self.pool.get().await.map_err(Into::into)
     }
 
+    // the error for a request from this producer and epoch of a
+    // transactional id, if any
+    async fn txn_fenced(
+        &self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+    ) -> Result<Option<ErrorCode>> {
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare("select id, epoch from producer where transaction_id = $1")
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let Some(row) = c
+            .query_opt(&prepared, &[&transactional_id])
+            .await
+            .inspect_err(|err| error!(?err))?
+        else {
+            return Ok(Some(ErrorCode::InvalidProducerIdMapping));
+        };
+
+        let id: i64 = row.try_get(0)?;
+        let epoch: i32 = row.try_get(1)?;
+
+        if id != producer_id {
+            return Ok(Some(ErrorCode::InvalidProducerIdMapping));
+        }
+
+        Ok(match i32::from(producer_epoch).cmp(&epoch) {
+            Ordering::Equal => None,
+            Ordering::Less => Some(ErrorCode::ProducerFenced),
+            Ordering::Greater => Some(ErrorCode::InvalidProducerEpoch),
+        })
+    }
+
+    // end the transaction of a producer in each of its partitions with a
+    // commit or abort marker, recording the offsets of an aborted transaction
+    async fn write_marker(
+        &self,
+        tx: &Transaction<'_>,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed: bool,
+    ) -> Result<()> {
+        debug!(?producer_id, ?producer_epoch, ?committed);
+
+        let marker = EndTransactionMarker::default();
+
+        let control = if committed {
+            ControlRecord::Commit(marker)
+        } else {
+            ControlRecord::Abort(marker)
+        };
+
+        let attributes = i16::from(
+            BatchAttributes::default()
+                .with_transactional(true)
+                .with_control(true),
+        );
+
+        let insert_marker = tx
+            .prepare(concat!(
+                "insert into record",
+                " (topic, partition, producer_id, producer_epoch, attributes, sequence, timestamp, k, v)",
+                " select",
+                " topic, partition, producer, $2, $3, -1, $4, $5, $6",
+                " from txn_partition",
+                " where producer = $1",
+                " returning id, topic, partition"
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let markers = tx
+            .query(
+                &insert_marker,
+                &[
+                    &producer_id,
+                    &producer_epoch,
+                    &attributes,
+                    &SystemTime::now(),
+                    &control.key().as_ref(),
+                    &control.value().as_ref(),
+                ],
+            )
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        if committed {
+            return Ok(());
+        }
+
+        let insert_aborted = tx
+            .prepare(concat!(
+                "insert into txn_aborted",
+                " (producer, topic, partition, first_offset, last_offset)",
+                " select producer, topic, partition, first_offset, $4",
+                " from txn_partition",
+                " where",
+                " producer = $1",
+                " and topic = $2",
+                " and partition = $3",
+                " and first_offset is not null",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        for marker in markers {
+            let offset: i64 = marker.try_get(0)?;
+            let topic: Uuid = marker.try_get(1)?;
+            let partition: i32 = marker.try_get(2)?;
+
+            _ = tx
+                .execute(
+                    &insert_aborted,
+                    &[&producer_id, &topic, &partition, &offset],
+                )
+                .await
+                .inspect_err(|err| error!(?err, ?topic, ?partition, ?offset))?;
+        }
+
+        Ok(())
+    }
+
     async fn delete_for_topic(
         &self,
         tx: &Transaction<'_>,
@@ -225,7 +408,7 @@ impl Storage for Postgres {
     async fn register_broker(
         &mut self,
         broker_registration: BrokerRegistationRequest,
-    ) -> Result<()> {
+    ) -> Result<i64> {
         debug!(?broker_registration);
 
         let mut c = self.connection().await?;
@@ -255,12 +438,17 @@ impl Storage for Postgres {
             .prepare(concat!(
                 "insert into broker",
                 " (cluster, node, rack, incarnation)",
-                " values ($1, $2, $3, gen_random_uuid())",
+                " values ($1, $2, $3, $4)",
                 " on conflict (cluster, node)",
                 " do update set",
-                " incarnation = excluded.incarnation",
+                " rack = excluded.rack",
+                ", incarnation = excluded.incarnation",
+                ", epoch = broker.epoch + 1",
+                ", heartbeat = excluded.heartbeat",
+                ", fenced = false",
+                ", unregistered = false",
                 ", last_updated = excluded.last_updated",
-                " returning id"
+                " returning id, epoch"
             ))
             .await?;
         debug!(?prepared);
@@ -272,12 +460,14 @@ impl Storage for Postgres {
                     &cluster_id,
                     &broker_registration.broker_id,
                     &broker_registration.rack,
+                    &broker_registration.incarnation_id,
                 ],
             )
             .await?;
 
         let broker_id: i32 = row.get(0);
-        debug!(?broker_id);
+        let epoch: i64 = row.get(1);
+        debug!(?broker_id, ?epoch);
 
         let prepared = tx
             .prepare(concat!("delete from listener where broker=$1",))
@@ -318,29 +508,31 @@ impl Storage for Postgres {
 
         tx.commit().await?;
 
-        Ok(())
+        Ok(epoch)
     }
 
-    async fn brokers(&mut self) -> Result<Vec<DescribeClusterBroker>> {
+    async fn brokers(&mut self, listener: Option<&str>) -> Result<Vec<DescribeClusterBroker>> {
         let c = self.connection().await?;
 
         let prepared = c
             .prepare(concat!(
-                "select",
-                " broker.id, host, port, rack",
+                "select distinct on (broker.id)",
+                " node, host, port, rack",
                 " from broker, cluster, listener",
                 " where",
                 " cluster.name = $1",
-                " and listener.name = $2",
+                " and ($2::text is null or listener.name = $2)",
                 " and broker.cluster = cluster.id",
-                " and listener.broker = broker.id"
+                " and not broker.fenced",
+                " and listener.broker = broker.id",
+                " order by broker.id, listener.id"
             ))
             .await?;
 
         let mut brokers = vec![];
 
         let rows = c
-            .query(&prepared, &[&self.cluster.as_str(), &"broker"])
+            .query(&prepared, &[&self.cluster.as_str(), &listener])
             .await?;
 
         for row in rows {
@@ -360,6 +552,153 @@ impl Storage for Postgres {
         Ok(brokers)
     }
 
+    async fn broker_heartbeat(
+        &mut self,
+        broker_id: i32,
+        broker_epoch: i64,
+        want_fence: bool,
+    ) -> Result<ErrorCode> {
+        debug!(?broker_id, ?broker_epoch, ?want_fence);
+
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        let prepared = tx
+            .prepare(concat!(
+                "select broker.id, epoch",
+                " from broker, cluster",
+                " where cluster.name = $1",
+                " and broker.cluster = cluster.id",
+                " and node = $2",
+                " and not broker.unregistered",
+                " for update"
+            ))
+            .await?;
+
+        let Some(row) = tx
+            .query_opt(&prepared, &[&self.cluster, &broker_id])
+            .await?
+        else {
+            return Ok(ErrorCode::BrokerIdNotRegistered);
+        };
+
+        let id: i32 = row.try_get(0)?;
+        let epoch: i64 = row.try_get(1)?;
+
+        if epoch != broker_epoch {
+            return Ok(ErrorCode::StaleBrokerEpoch);
+        }
+
+        let prepared = tx
+            .prepare(concat!(
+                "update broker",
+                " set heartbeat = $2",
+                ", fenced = $3",
+                ", last_updated = $2",
+                " where id = $1"
+            ))
+            .await?;
+
+        _ = tx
+            .execute(&prepared, &[&id, &SystemTime::now(), &want_fence])
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(ErrorCode::None)
+    }
+
+    async fn fence_brokers(
+        &mut self,
+        now: SystemTime,
+        session_timeout: Duration,
+    ) -> Result<Vec<i32>> {
+        debug!(?now, ?session_timeout);
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "update broker",
+                " set fenced = true",
+                ", last_updated = $3",
+                " from cluster",
+                " where cluster.name = $1",
+                " and broker.cluster = cluster.id",
+                " and not broker.fenced",
+                " and broker.heartbeat <= $2",
+                " returning node"
+            ))
+            .await?;
+
+        let rows = c
+            .query(
+                &prepared,
+                &[
+                    &self.cluster,
+                    &now.checked_sub(session_timeout)
+                        .unwrap_or(SystemTime::UNIX_EPOCH),
+                    &now,
+                ],
+            )
+            .await?;
+
+        rows.iter()
+            .map(|row| row.try_get::<_, i32>(0).map_err(Into::into))
+            .collect()
+    }
+
+    async fn unregister_broker(&mut self, broker_id: i32) -> Result<ErrorCode> {
+        debug!(?broker_id);
+
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        let prepared = tx
+            .prepare(concat!(
+                "select broker.id, fenced",
+                " from broker, cluster",
+                " where cluster.name = $1",
+                " and broker.cluster = cluster.id",
+                " and node = $2",
+                " and not broker.unregistered",
+                " for update"
+            ))
+            .await?;
+
+        let Some(row) = tx
+            .query_opt(&prepared, &[&self.cluster, &broker_id])
+            .await?
+        else {
+            return Ok(ErrorCode::BrokerIdNotRegistered);
+        };
+
+        let id: i32 = row.try_get(0)?;
+        let fenced: bool = row.try_get(1)?;
+
+        if !fenced {
+            return Ok(ErrorCode::InvalidRequest);
+        }
+
+        let prepared = tx
+            .prepare(concat!(
+                "update broker",
+                " set unregistered = true",
+                ", last_updated = $2",
+                " where id = $1"
+            ))
+            .await?;
+
+        _ = tx.execute(&prepared, &[&id, &SystemTime::now()]).await?;
+
+        let prepared = tx.prepare("delete from listener where broker = $1").await?;
+        _ = tx.execute(&prepared, &[&id]).await?;
+
+        tx.commit().await?;
+
+        Ok(ErrorCode::None)
+    }
+
     async fn create_topic(&mut self, topic: CreatableTopic, validate_only: bool) -> Result<Uuid> {
         debug!(?topic, ?validate_only);
 
@@ -426,11 +765,92 @@ impl Storage for Postgres {
             }
         }
 
+        let prepared = tx
+            .prepare(INSERT_LEADER_EPOCHS)
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        _ = tx
+            .execute(&prepared, &[&topic_id, &0, &topic.num_partitions])
+            .await
+            .inspect_err(|err| error!(?err, ?topic_id))?;
+
         tx.commit().await.inspect_err(|err| error!(?err))?;
 
         Ok(topic_id)
     }
 
+    async fn create_partitions(
+        &mut self,
+        topic: &TopicId,
+        count: i32,
+        validate_only: bool,
+    ) -> Result<()> {
+        debug!(?topic, ?count, ?validate_only);
+
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        let row = match topic {
+            TopicId::Id(id) => {
+                let sql = TOPIC_PARTITIONS_FOR_UPDATE.replace("<COLUMN>", "id");
+                let prepared = tx.prepare(&sql).await.inspect_err(|err| error!(?err))?;
+
+                tx.query_opt(&prepared, &[&self.cluster, &id])
+                    .await
+                    .inspect_err(|err| error!(?err))?
+            }
+
+            TopicId::Name(name) => {
+                let sql = TOPIC_PARTITIONS_FOR_UPDATE.replace("<COLUMN>", "name");
+                let prepared = tx.prepare(&sql).await.inspect_err(|err| error!(?err))?;
+
+                tx.query_opt(&prepared, &[&self.cluster, &name])
+                    .await
+                    .inspect_err(|err| error!(?err))?
+            }
+        }
+        .ok_or(Error::Api(ErrorCode::UnknownTopicOrPartition))?;
+
+        let topic_id = row.try_get::<_, Uuid>(0)?;
+        let partitions = row.try_get::<_, i32>(1)?;
+        debug!(?topic_id, ?partitions);
+
+        if count <= partitions {
+            return Err(Error::Api(ErrorCode::InvalidPartitions));
+        }
+
+        if !validate_only {
+            let prepared = tx
+                .prepare(concat!(
+                    "update topic",
+                    " set partitions = $2, last_updated = current_timestamp",
+                    " where id = $1",
+                ))
+                .await
+                .inspect_err(|err| error!(?err))?;
+
+            _ = tx
+                .execute(&prepared, &[&topic_id, &count])
+                .await
+                .inspect_err(|err| error!(?err, ?topic_id, ?count))?;
+
+            let prepared = tx
+                .prepare(INSERT_LEADER_EPOCHS)
+                .await
+                .inspect_err(|err| error!(?err))?;
+
+            _ = tx
+                .execute(&prepared, &[&topic_id, &partitions, &count])
+                .await
+                .inspect_err(|err| error!(?err, ?topic_id, ?count))?;
+        }
+
+        tx.commit().await.inspect_err(|err| error!(?err))?;
+
+        Ok(())
+    }
+
     async fn delete_records(
         &mut self,
         topics: &[DeleteRecordsTopic],
@@ -445,7 +865,7 @@ impl Storage for Postgres {
                 " cluster.name=$1",
                 " and topic.name = $2",
                 " and record.partition = $3",
-                " and record.id >= $4",
+                " and record.id < $4",
                 " and topic.cluster = cluster.id",
                 " and record.topic = topic.id",
             ))
@@ -564,6 +984,7 @@ impl Storage for Postgres {
         let tx = c.transaction().await?;
 
         for (description, sql) in [
+            ("leader epochs", ADVANCE_LEADER_EPOCHS_FOR_TOPIC),
             ("consumer offsets", DELETE_CONSUMER_OFFSETS_FOR_TOPIC),
             ("headers", DELETE_HEADERS_FOR_TOPIC),
             ("records", DELETE_RECORDS_FOR_TOPIC),
@@ -591,8 +1012,13 @@ impl Storage for Postgres {
             .and(topic_deletion_result)
     }
 
-    async fn produce(&mut self, topition: &'_ Topition, deflated: deflated::Batch) -> Result<i64> {
-        debug!(?topition, ?deflated);
+    async fn produce(
+        &mut self,
+        topition: &'_ Topition,
+        deflated: deflated::Batch,
+        ack: Ack,
+    ) -> Result<i64> {
+        debug!(?topition, ?deflated, ?ack);
         let mut c = self.connection().await?;
 
         let tx = c.transaction().await?;
@@ -600,9 +1026,9 @@ impl Storage for Postgres {
         let insert_record = tx
             .prepare(concat!(
                 "insert into record",
-                " (topic, partition, producer_id, sequence, timestamp, k, v)",
+                " (topic, partition, producer_id, producer_epoch, attributes, sequence, timestamp, k, v)",
                 " select",
-                " topic.id, $2, $3, $4, $5, $6, $7",
+                " topic.id, $2, $3, $4, $5, $6, $7, $8, $9",
                 " from topic",
                 " where topic.name = $1",
                 " returning id"
@@ -621,6 +1047,7 @@ impl Storage for Postgres {
             .inspect_err(|err| error!(?err))?;
 
         let inflated = inflated::Batch::try_from(deflated).inspect_err(|err| error!(?err))?;
+        let transactional = inflated.is_transactional();
         let mut offsets = vec![];
 
         for record in inflated.records {
@@ -638,6 +1065,8 @@ impl Storage for Postgres {
                         &topic,
                         &partition,
                         &inflated.producer_id,
+                        &inflated.producer_epoch,
+                        &inflated.attributes,
                         &inflated.base_sequence,
                         &(to_system_time(inflated.base_timestamp + record.timestamp_delta)?),
                         &key,
@@ -665,6 +1094,41 @@ impl Storage for Postgres {
             offsets.push(offset);
         }
 
+        // a transactional batch is unstable until its transaction ends
+        if let Some(first) = offsets.first().filter(|_| transactional) {
+            let prepared = tx
+                .prepare(concat!(
+                    "update txn_partition",
+                    " set first_offset = $5",
+                    ", last_updated = current_timestamp",
+                    " from cluster, topic",
+                    " where",
+                    " cluster.name = $1",
+                    " and topic.name = $2",
+                    " and topic.cluster = cluster.id",
+                    " and txn_partition.topic = topic.id",
+                    " and txn_partition.partition = $3",
+                    " and txn_partition.producer = $4",
+                    " and txn_partition.first_offset is null",
+                ))
+                .await
+                .inspect_err(|err| error!(?err))?;
+
+            _ = tx
+                .execute(
+                    &prepared,
+                    &[
+                        &self.cluster,
+                        &topition.topic(),
+                        &topition.partition(),
+                        &inflated.producer_id,
+                        first,
+                    ],
+                )
+                .await
+                .inspect_err(|err| error!(?err, ?topition))?;
+        }
+
         tx.commit().await?;
 
         Ok(offsets.first().copied().unwrap_or(-1))
@@ -690,6 +1154,10 @@ impl Storage for Postgres {
                 ", v",
                 ", sum(coalesce(length(k), 0) + coalesce(length(v), 0))",
                 " over (order by record.id) as bytes",
+                ", producer_id",
+                ", producer_epoch",
+                ", attributes",
+                ", sequence",
                 " from cluster, record, topic",
                 " where",
                 " cluster.name = $1",
@@ -733,14 +1201,33 @@ impl Storage for Postgres {
                 .map_err(Error::from)
                 .and_then(|system_time| to_timestamp(system_time).map_err(Into::into))?;
 
+            // records are batched by producer and attributes, with
+            // each control record in a batch of its own
+            let producer_id = first.try_get::<_, Option<i64>>(5)?.unwrap_or(-1);
+            let attributes = first.try_get::<_, i16>(7)?;
+
             let mut batch_builder = inflated::Batch::builder()
                 .base_offset(base_offset)
-                .base_timestamp(base_timestamp);
+                .base_timestamp(base_timestamp)
+                .producer_id(producer_id)
+                .producer_epoch(first.try_get::<_, Option<i16>>(6)?.unwrap_or(-1))
+                .attributes(attributes)
+                .base_sequence(first.try_get::<_, Option<i32>>(8)?.unwrap_or(-1));
+
+            let control = BatchAttributes::from(attributes).is_control();
 
             for record in records.iter() {
                 let offset = record.try_get::<_, i64>(0)?;
                 let offset_delta = i32::try_from(offset - base_offset)?;
 
+                if offset_delta > 0
+                    && (control
+                        || record.try_get::<_, Option<i64>>(5)?.unwrap_or(-1) != producer_id
+                        || record.try_get::<_, i16>(7)? != attributes)
+                {
+                    break;
+                }
+
                 let timestamp_delta = first
                     .try_get::<_, SystemTime>(1)
                     .map_err(Error::from)
@@ -784,7 +1271,9 @@ impl Storage for Postgres {
                     record_builder = record_builder.header(header_builder);
                 }
 
-                batch_builder = batch_builder.record(record_builder);
+                batch_builder = batch_builder
+                    .record(record_builder)
+                    .last_offset_delta(offset_delta);
 
                 if bytes > (min_bytes as i64) {
                     break;
@@ -833,15 +1322,37 @@ impl Storage for Postgres {
             .inspect_err(|err| error!(?topition, ?prepared, ?err))?;
 
         let high_watermark = row
-            .try_get::<_, i64>(0)
+            .try_get::<_, i64>(1)
             .inspect_err(|err| error!(?topition, ?prepared, ?err))?;
 
-        let last_stable = high_watermark;
-
-        Ok(OffsetStage {
-            last_stable,
-            high_watermark,
-            log_start,
+        // the offset before which every transaction has ended
+        let prepared = c
+            .prepare(concat!(
+                "select min(txn_partition.first_offset)",
+                " from cluster, topic, txn_partition",
+                " where",
+                " cluster.name = $1",
+                " and topic.name = $2",
+                " and txn_partition.partition = $3",
+                " and topic.cluster = cluster.id",
+                " and txn_partition.topic = topic.id",
+            ))
+            .await?;
+
+        let last_stable = c
+            .query_one(
+                &prepared,
+                &[&self.cluster, &topition.topic(), &topition.partition()],
+            )
+            .await
+            .and_then(|row| row.try_get::<_, Option<i64>>(0))
+            .inspect_err(|err| error!(?topition, ?prepared, ?err))?
+            .unwrap_or(high_watermark);
+
+        Ok(OffsetStage {
+            last_stable,
+            high_watermark,
+            log_start,
         })
     }
 
@@ -1061,12 +1572,13 @@ impl Storage for Postgres {
 
         let prepared = c
             .prepare(concat!(
-                "select node, host, port, rack",
+                "select distinct on (broker.id) node, host, port, rack",
                 " from broker, cluster, listener",
                 " where cluster.name = $1",
                 " and broker.cluster = cluster.id",
+                " and not broker.fenced",
                 " and listener.broker = broker.id",
-                " and listener.name = 'broker'"
+                " order by broker.id, listener.id"
             ))
             .await
             .inspect_err(|err| error!(?err))?;
@@ -1094,6 +1606,36 @@ impl Storage for Postgres {
 
         debug!(?brokers);
 
+        let prepared = c
+            .prepare(concat!(
+                "select topic, partition, epoch",
+                " from leader_epoch, cluster",
+                " where cluster.name = $1",
+                " and leader_epoch.cluster = cluster.id",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let mut leader_epochs = BTreeMap::new();
+
+        for row in c
+            .query(&prepared, &[&self.cluster])
+            .await
+            .inspect_err(|err| error!(?err))?
+        {
+            let topic = row.try_get::<_, String>(0)?;
+            let partition = row.try_get::<_, i32>(1)?;
+            let epoch = row.try_get::<_, i32>(2)?;
+
+            _ = leader_epochs.insert(Topition::new(topic.as_str(), partition), epoch);
+        }
+
+        let leader_epoch = |topic: Option<&str>, partition: i32| {
+            topic
+                .and_then(|topic| leader_epochs.get(&Topition::new(topic, partition)).copied())
+                .or(Some(0))
+        };
+
         let responses = match topics {
             Some(topics) if !topics.is_empty() => {
                 let mut responses = vec![];
@@ -1122,7 +1664,7 @@ impl Storage for Postgres {
                                     let error_code = ErrorCode::None.into();
                                     let topic_id = row
                                         .try_get::<_, Uuid>(0)
-                                        .map(|uuid| uuid.into_bytes())
+                                        .map(Into::into)
                                         .map(Some)?;
                                     let name = row.try_get::<_, String>(1).map(Some)?;
                                     let is_internal = row.try_get::<_, bool>(2).map(Some)?;
@@ -1147,7 +1689,7 @@ impl Storage for Postgres {
                                             error_code,
                                             partition_index,
                                             leader_id,
-                                            leader_epoch: Some(-1),
+                                            leader_epoch: leader_epoch(name.as_deref(), partition_index),
                                             replica_nodes,
                                             isr_nodes,
                                             offline_replicas: Some([].into()),
@@ -1211,7 +1753,7 @@ impl Storage for Postgres {
                                     let error_code = ErrorCode::None.into();
                                     let topic_id = row
                                         .try_get::<_, Uuid>(0)
-                                        .map(|uuid| uuid.into_bytes())
+                                        .map(Into::into)
                                         .map(Some)?;
                                     let name = row.try_get::<_, String>(1).map(Some)?;
                                     let is_internal = row.try_get::<_, bool>(2).map(Some)?;
@@ -1236,7 +1778,7 @@ impl Storage for Postgres {
                                             error_code,
                                             partition_index,
                                             leader_id,
-                                            leader_epoch: Some(-1),
+                                            leader_epoch: leader_epoch(name.as_deref(), partition_index),
                                             replica_nodes,
                                             isr_nodes,
                                             offline_replicas: Some([].into()),
@@ -1256,9 +1798,9 @@ impl Storage for Postgres {
                                 Err(reason) => {
                                     debug!(?reason);
                                     MetadataResponseTopic {
-                                        error_code: ErrorCode::UnknownTopicOrPartition.into(),
+                                        error_code: ErrorCode::UnknownTopicId.into(),
                                         name: None,
-                                        topic_id: Some(id.into_bytes()),
+                                        topic_id: Some((*id).into()),
                                         is_internal: Some(false),
                                         partitions: Some([].into()),
                                         topic_authorized_operations: Some(-2147483648),
@@ -1294,10 +1836,7 @@ impl Storage for Postgres {
                     Ok(rows) => {
                         for row in rows {
                             let error_code = ErrorCode::None.into();
-                            let topic_id = row
-                                .try_get::<_, Uuid>(0)
-                                .map(|uuid| uuid.into_bytes())
-                                .map(Some)?;
+                            let topic_id = row.try_get::<_, Uuid>(0).map(Into::into).map(Some)?;
                             let name = row.try_get::<_, String>(1).map(Some)?;
                             let is_internal = row.try_get::<_, bool>(2).map(Some)?;
                             let partitions = row.try_get::<_, i32>(3)?;
@@ -1335,7 +1874,10 @@ impl Storage for Postgres {
                                             error_code,
                                             partition_index,
                                             leader_id,
-                                            leader_epoch: Some(-1),
+                                            leader_epoch: leader_epoch(
+                                                name.as_deref(),
+                                                partition_index,
+                                            ),
                                             replica_nodes,
                                             isr_nodes,
                                             offline_replicas: Some([].into()),
@@ -1465,6 +2007,48 @@ impl Storage for Postgres {
         }
     }
 
+    async fn topic_config(&mut self, topic: &TopicId) -> Result<BTreeMap<String, Option<String>>> {
+        debug!(?topic);
+
+        let c = self.connection().await?;
+
+        let rows = match topic {
+            TopicId::Id(id) => {
+                let sql = TOPIC_CONFIGURATION.replace("<COLUMN>", "id");
+                let prepared = c.prepare(&sql).await.inspect_err(|err| error!(?err))?;
+
+                c.query(&prepared, &[&self.cluster, &id])
+                    .await
+                    .inspect_err(|err| error!(?err))?
+            }
+
+            TopicId::Name(name) => {
+                let sql = TOPIC_CONFIGURATION.replace("<COLUMN>", "name");
+                let prepared = c.prepare(&sql).await.inspect_err(|err| error!(?err))?;
+
+                c.query(&prepared, &[&self.cluster, &name])
+                    .await
+                    .inspect_err(|err| error!(?err))?
+            }
+        };
+
+        // the left join yields a single null row for a topic without overrides,
+        // and no rows at all when the topic does not exist
+        if rows.is_empty() {
+            return Err(Error::Api(ErrorCode::UnknownTopicOrPartition));
+        }
+
+        let mut configs = BTreeMap::new();
+
+        for row in rows {
+            if let Some(name) = row.try_get::<_, Option<String>>(0)? {
+                _ = configs.insert(name, row.try_get::<_, Option<String>>(1)?);
+            }
+        }
+
+        Ok(configs)
+    }
+
     async fn update_group(
         &mut self,
         group_id: &str,
@@ -1571,6 +2155,228 @@ impl Storage for Postgres {
         outcome
     }
 
+    async fn group_detail(&mut self, group_id: &str) -> Result<Option<GroupDetail>> {
+        debug!(?group_id);
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select",
+                " cg.detail",
+                " from cluster c, consumer_group cg",
+                " where",
+                " cg.grp = $1",
+                " and c.name = $2",
+                " and c.id = cg.cluster"
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query_opt(&prepared, &[&group_id, &self.cluster.as_str()])
+            .await
+            .inspect_err(|err| error!(?err))?
+            .map(|row| {
+                row.try_get::<_, Value>(0)
+                    .map_err(Error::from)
+                    .and_then(|value| {
+                        serde_json::from_value::<GroupDetail>(value).map_err(Into::into)
+                    })
+            })
+            .transpose()
+    }
+
+    async fn list_groups(&mut self) -> Result<Vec<String>> {
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select cg.grp",
+                " from cluster c, consumer_group cg",
+                " where",
+                " c.name = $1",
+                " and c.id = cg.cluster",
+                " union",
+                " select co.grp",
+                " from cluster c, topic t, consumer_offset co",
+                " where",
+                " c.name = $1",
+                " and c.id = t.cluster",
+                " and t.id = co.topic",
+                " order by 1"
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query(&prepared, &[&self.cluster.as_str()])
+            .await
+            .inspect_err(|err| error!(?err))?
+            .into_iter()
+            .map(|row| row.try_get::<_, String>(0).map_err(Into::into))
+            .collect()
+    }
+
+    async fn update_consumer_group(
+        &mut self,
+        group_id: &str,
+        detail: ConsumerGroupDetail,
+        version: Option<Version>,
+    ) -> Result<Version, UpdateError<ConsumerGroupDetail>> {
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        let prepared = tx
+            .prepare(concat!(
+                "insert into consumer_protocol_group",
+                " (grp, cluster, e_tag, detail)",
+                " select $1, cluster.id, $3, $4",
+                " from cluster",
+                " where cluster.name = $2",
+                " on conflict (grp, cluster)",
+                " do update set",
+                " detail = excluded.detail, e_tag = $5",
+                " where consumer_protocol_group.e_tag = $3",
+                " returning grp, cluster, e_tag, detail",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let existing_e_tag = version
+            .as_ref()
+            .map_or(Ok(Uuid::new_v4()), |version| {
+                version.e_tag.as_ref().map_or(
+                    Err(UpdateError::MissingEtag::<ConsumerGroupDetail>),
+                    |e_tag| Uuid::from_str(e_tag.as_str()).map_err(Into::into),
+                )
+            })
+            .inspect_err(|err| error!(?err))?;
+
+        let new_e_tag = Uuid::new_v4();
+
+        let value = serde_json::to_value(detail)?;
+
+        let outcome = if let Some(row) = tx
+            .query_opt(
+                &prepared,
+                &[
+                    &group_id,
+                    &self.cluster.as_str(),
+                    &existing_e_tag,
+                    &value,
+                    &new_e_tag,
+                ],
+            )
+            .await
+            .inspect(|row| debug!(?row))
+            .inspect_err(|err| error!(?err))?
+        {
+            row.try_get::<_, Uuid>(2)
+                .inspect_err(|err| error!(?err))
+                .map_err(Into::into)
+                .map(|uuid| uuid.to_string())
+                .map(Some)
+                .map(|e_tag| Version {
+                    e_tag,
+                    version: None,
+                })
+        } else {
+            let prepared = tx
+                .prepare(concat!(
+                    "select",
+                    " cpg.e_tag, cpg.detail",
+                    " from cluster c, consumer_protocol_group cpg",
+                    " where",
+                    " cpg.grp = $1",
+                    " and c.name = $2",
+                    " and c.id = cpg.cluster"
+                ))
+                .await
+                .inspect_err(|err| error!(?err))?;
+
+            let row = tx
+                .query_one(&prepared, &[&group_id, &self.cluster.as_str()])
+                .await
+                .inspect(|row| debug!(?row))
+                .inspect_err(|err| error!(?err))?;
+
+            let version = row
+                .try_get::<_, Uuid>(0)
+                .inspect_err(|err| error!(?err))
+                .map(|uuid| uuid.to_string())
+                .map(Some)
+                .map(|e_tag| Version {
+                    e_tag,
+                    version: None,
+                })?;
+
+            let value = row.try_get::<_, Value>(1)?;
+            let current = serde_json::from_value::<ConsumerGroupDetail>(value)?;
+
+            Err(UpdateError::Outdated { current, version })
+        };
+
+        tx.commit().await.inspect_err(|err| error!(?err))?;
+
+        outcome
+    }
+
+    async fn consumer_group_detail(
+        &mut self,
+        group_id: &str,
+    ) -> Result<Option<ConsumerGroupDetail>> {
+        debug!(?group_id);
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select",
+                " cpg.detail",
+                " from cluster c, consumer_protocol_group cpg",
+                " where",
+                " cpg.grp = $1",
+                " and c.name = $2",
+                " and c.id = cpg.cluster"
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query_opt(&prepared, &[&group_id, &self.cluster.as_str()])
+            .await
+            .inspect_err(|err| error!(?err))?
+            .map(|row| {
+                row.try_get::<_, Value>(0)
+                    .map_err(Error::from)
+                    .and_then(|value| {
+                        serde_json::from_value::<ConsumerGroupDetail>(value).map_err(Into::into)
+                    })
+            })
+            .transpose()
+    }
+
+    async fn list_consumer_groups(&mut self) -> Result<Vec<String>> {
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select cpg.grp",
+                " from cluster c, consumer_protocol_group cpg",
+                " where",
+                " c.name = $1",
+                " and c.id = cpg.cluster",
+                " order by 1"
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query(&prepared, &[&self.cluster.as_str()])
+            .await
+            .inspect_err(|err| error!(?err))?
+            .into_iter()
+            .map(|row| row.try_get::<_, String>(0).map_err(Into::into))
+            .collect()
+    }
+
     async fn init_producer(
         &mut self,
         transaction_id: Option<&str>,
@@ -1578,8 +2384,60 @@ impl Storage for Postgres {
         producer_id: Option<i64>,
         producer_epoch: Option<i16>,
     ) -> Result<ProducerIdResponse> {
-        if let Some(_transaction_id) = transaction_id {
-            Ok(ProducerIdResponse::default())
+        if let Some(transaction_id) = transaction_id {
+            let mut c = self.connection().await.inspect_err(|err| error!(?err))?;
+            let tx = c.transaction().await?;
+
+            // a transactional id keeps its producer, with a bumped epoch
+            let prepared = tx
+                .prepare(concat!(
+                    "insert into producer",
+                    " (transaction_id",
+                    ", transaction_timeout_ms)",
+                    " values ($1, $2)",
+                    " on conflict (transaction_id)",
+                    " do update set",
+                    " epoch = producer.epoch + 1",
+                    ", transaction_timeout_ms = excluded.transaction_timeout_ms",
+                    ", last_updated = excluded.last_updated",
+                    " returning id, epoch"
+                ))
+                .await
+                .inspect_err(|err| error!(?err))?;
+
+            let row = tx
+                .query_one(&prepared, &[&transaction_id, &transaction_timeout_ms])
+                .await
+                .inspect_err(|err| error!(?err))?;
+
+            let id: i64 = row.get(0);
+            let epoch: i32 = row.get(1);
+
+            // aborting any transaction left ongoing by an earlier instance
+            self.write_marker(&tx, id, i16::try_from(epoch)?, false)
+                .await?;
+
+            for sql in [
+                "delete from txn_partition where producer = $1",
+                "delete from txn_offset_commit where producer = $1",
+            ] {
+                let prepared = tx.prepare(sql).await.inspect_err(|err| error!(?err))?;
+
+                _ = tx
+                    .execute(&prepared, &[&id])
+                    .await
+                    .inspect_err(|err| error!(?err))?;
+            }
+
+            tx.commit().await?;
+
+            i16::try_from(epoch)
+                .map(|epoch| ProducerIdResponse {
+                    error: ErrorCode::None,
+                    id,
+                    epoch,
+                })
+                .map_err(Into::into)
         } else if producer_id.is_some_and(|producer_id| producer_id == -1)
             && producer_epoch.is_some_and(|producer_epoch| producer_epoch == -1)
         {
@@ -1617,4 +2475,932 @@ impl Storage for Postgres {
             Ok(ProducerIdResponse::default())
         }
     }
+
+    async fn txn_add_partitions(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        partitions: &[Topition],
+    ) -> Result<ErrorCode> {
+        debug!(
+            ?transactional_id,
+            ?producer_id,
+            ?producer_epoch,
+            ?partitions
+        );
+
+        if let Some(error_code) = self
+            .txn_fenced(transactional_id, producer_id, producer_epoch)
+            .await?
+        {
+            return Ok(error_code);
+        }
+
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        let prepared = tx
+            .prepare(concat!(
+                "insert into txn_partition",
+                " (producer, topic, partition)",
+                " select $1, topic.id, $4",
+                " from cluster, topic",
+                " where",
+                " cluster.name = $2",
+                " and topic.name = $3",
+                " and topic.cluster = cluster.id",
+                " on conflict do nothing",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        for topition in partitions {
+            _ = tx
+                .execute(
+                    &prepared,
+                    &[
+                        &producer_id,
+                        &self.cluster,
+                        &topition.topic(),
+                        &topition.partition(),
+                    ],
+                )
+                .await
+                .inspect_err(|err| error!(?err, ?topition))?;
+        }
+
+        tx.commit().await?;
+
+        Ok(ErrorCode::None)
+    }
+
+    // ending a transaction writes a marker to each of its partitions, before
+    // forgetting them, committing or discarding any staged offsets
+    async fn txn_end(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        committed: bool,
+    ) -> Result<ErrorCode> {
+        debug!(?transactional_id, ?producer_id, ?producer_epoch, ?committed);
+
+        if let Some(error_code) = self
+            .txn_fenced(transactional_id, producer_id, producer_epoch)
+            .await?
+        {
+            return Ok(error_code);
+        }
+
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        self.write_marker(&tx, producer_id, producer_epoch, committed)
+            .await?;
+
+        if committed {
+            let prepared = tx
+                .prepare(concat!(
+                    "insert into consumer_offset ",
+                    " (grp, topic, partition, committed_offset, leader_epoch, timestamp, metadata) ",
+                    " select",
+                    " grp, topic, partition, committed_offset, leader_epoch, timestamp, metadata",
+                    " from txn_offset_commit",
+                    " where producer = $1",
+                    " on conflict (grp, topic, partition)",
+                    " do update set",
+                    " committed_offset = excluded.committed_offset,",
+                    " leader_epoch = excluded.leader_epoch,",
+                    " timestamp = excluded.timestamp,",
+                    " metadata = excluded.metadata",
+                ))
+                .await
+                .inspect_err(|err| error!(?err))?;
+
+            _ = tx
+                .execute(&prepared, &[&producer_id])
+                .await
+                .inspect_err(|err| error!(?err))?;
+        }
+
+        for sql in [
+            "delete from txn_partition where producer = $1",
+            "delete from txn_offset_commit where producer = $1",
+        ] {
+            let prepared = tx.prepare(sql).await.inspect_err(|err| error!(?err))?;
+
+            _ = tx
+                .execute(&prepared, &[&producer_id])
+                .await
+                .inspect_err(|err| error!(?err))?;
+        }
+
+        tx.commit().await?;
+
+        Ok(ErrorCode::None)
+    }
+
+    async fn txn_expire(&mut self, now: SystemTime) -> Result<Vec<String>> {
+        debug!(?now);
+
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        let prepared = tx
+            .prepare(concat!(
+                "update producer",
+                " set epoch = epoch + 1",
+                ", last_updated = $1",
+                " where",
+                " transaction_id is not null",
+                " and exists (",
+                "select 1 from txn_partition",
+                " where",
+                " txn_partition.producer = producer.id",
+                " and txn_partition.created_at",
+                " + producer.transaction_timeout_ms * interval '1 millisecond' <= $1",
+                ") or exists (",
+                "select 1 from txn_offset_commit",
+                " where",
+                " txn_offset_commit.producer = producer.id",
+                " and txn_offset_commit.created_at",
+                " + producer.transaction_timeout_ms * interval '1 millisecond' <= $1",
+                ")",
+                " returning id, epoch, transaction_id",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let rows = tx
+            .query(&prepared, &[&now])
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let partitions = tx
+            .prepare("delete from txn_partition where producer = $1")
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let offsets = tx
+            .prepare("delete from txn_offset_commit where producer = $1")
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let mut expired = vec![];
+
+        for row in rows {
+            let producer_id: i64 = row.try_get(0)?;
+            let producer_epoch = row
+                .try_get::<_, i32>(1)
+                .map_err(Error::from)
+                .and_then(|epoch| i16::try_from(epoch).map_err(Into::into))?;
+
+            // aborted with the bumped epoch that fences its producer
+            self.write_marker(&tx, producer_id, producer_epoch, false)
+                .await?;
+
+            for prepared in [&partitions, &offsets] {
+                _ = tx
+                    .execute(prepared, &[&producer_id])
+                    .await
+                    .inspect_err(|err| error!(?err, ?producer_id))?;
+            }
+
+            expired.push(row.try_get::<_, String>(2)?);
+        }
+
+        tx.commit().await?;
+
+        Ok(expired)
+    }
+
+    async fn txn_add_offsets(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+    ) -> Result<ErrorCode> {
+        debug!(?transactional_id, ?producer_id, ?producer_epoch, ?group_id);
+
+        self.txn_fenced(transactional_id, producer_id, producer_epoch)
+            .await
+            .map(|fenced| fenced.unwrap_or(ErrorCode::None))
+    }
+
+    async fn txn_offset_commit(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+        group_id: &str,
+        offsets: &[(Topition, OffsetCommitRequest)],
+    ) -> Result<ErrorCode> {
+        debug!(
+            ?transactional_id,
+            ?producer_id,
+            ?producer_epoch,
+            ?group_id,
+            ?offsets
+        );
+
+        if let Some(error_code) = self
+            .txn_fenced(transactional_id, producer_id, producer_epoch)
+            .await?
+        {
+            return Ok(error_code);
+        }
+
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        let prepared = tx
+            .prepare(concat!(
+                "insert into txn_offset_commit",
+                " (producer, grp, topic, partition, committed_offset, leader_epoch, timestamp, metadata)",
+                " select $1, $2, topic.id, $5, $6, $7, $8, $9",
+                " from cluster, topic",
+                " where",
+                " cluster.name = $3",
+                " and topic.name = $4",
+                " and topic.cluster = cluster.id",
+                " on conflict (producer, grp, topic, partition)",
+                " do update set",
+                " committed_offset = excluded.committed_offset,",
+                " leader_epoch = excluded.leader_epoch,",
+                " timestamp = excluded.timestamp,",
+                " metadata = excluded.metadata,",
+                " last_updated = excluded.last_updated",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        for (topition, offset) in offsets {
+            _ = tx
+                .execute(
+                    &prepared,
+                    &[
+                        &producer_id,
+                        &group_id,
+                        &self.cluster,
+                        &topition.topic(),
+                        &topition.partition(),
+                        &offset.offset,
+                        &offset.leader_epoch,
+                        &offset.timestamp,
+                        &offset.metadata,
+                    ],
+                )
+                .await
+                .inspect_err(|err| error!(?err, ?topition))?;
+        }
+
+        tx.commit().await?;
+
+        Ok(ErrorCode::None)
+    }
+
+    async fn txn_unstable_offsets(&mut self, group_id: &str) -> Result<BTreeSet<Topition>> {
+        debug!(?group_id);
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select topic.name, txn_offset_commit.partition",
+                " from cluster, topic, txn_offset_commit",
+                " where",
+                " cluster.name = $1",
+                " and topic.cluster = cluster.id",
+                " and txn_offset_commit.topic = topic.id",
+                " and txn_offset_commit.grp = $2",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query(&prepared, &[&self.cluster, &group_id])
+            .await
+            .inspect_err(|err| error!(?err))?
+            .into_iter()
+            .map(|row| {
+                Ok(Topition::new(
+                    row.try_get::<_, String>(0)?,
+                    row.try_get::<_, i32>(1)?,
+                ))
+            })
+            .collect()
+    }
+
+    async fn txn_partitions(
+        &mut self,
+        transactional_id: &str,
+        producer_id: i64,
+        producer_epoch: i16,
+    ) -> Result<BTreeSet<Topition>> {
+        debug!(?transactional_id, ?producer_id, ?producer_epoch);
+
+        if let Some(error_code) = self
+            .txn_fenced(transactional_id, producer_id, producer_epoch)
+            .await?
+        {
+            return Err(Error::Api(error_code));
+        }
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select topic.name, txn_partition.partition",
+                " from cluster, topic, txn_partition",
+                " where",
+                " cluster.name = $1",
+                " and topic.cluster = cluster.id",
+                " and txn_partition.topic = topic.id",
+                " and txn_partition.producer = $2",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query(&prepared, &[&self.cluster, &producer_id])
+            .await
+            .inspect_err(|err| error!(?err))?
+            .into_iter()
+            .map(|row| {
+                Ok(Topition::new(
+                    row.try_get::<_, String>(0)?,
+                    row.try_get::<_, i32>(1)?,
+                ))
+            })
+            .collect()
+    }
+
+    async fn aborted_transactions(
+        &mut self,
+        topition: &Topition,
+        offset: i64,
+    ) -> Result<Vec<AbortedTransaction>> {
+        debug!(?topition, ?offset);
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select txn_aborted.producer, txn_aborted.first_offset",
+                " from cluster, topic, txn_aborted",
+                " where",
+                " cluster.name = $1",
+                " and topic.name = $2",
+                " and txn_aborted.partition = $3",
+                " and txn_aborted.last_offset >= $4",
+                " and topic.cluster = cluster.id",
+                " and txn_aborted.topic = topic.id",
+                " order by txn_aborted.first_offset",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query(
+            &prepared,
+            &[
+                &self.cluster,
+                &topition.topic(),
+                &topition.partition(),
+                &offset,
+            ],
+        )
+        .await
+        .inspect_err(|err| error!(?err))?
+        .into_iter()
+        .map(|row| {
+            Ok(AbortedTransaction {
+                producer_id: row.try_get(0)?,
+                first_offset: row.try_get(1)?,
+            })
+        })
+        .collect()
+    }
+
+    async fn producers(&mut self, topition: &Topition) -> Result<Vec<ProducerDetail>> {
+        debug!(?topition);
+
+        let c = self.connection().await?;
+
+        // records of a batch share its base sequence
+        let prepared = c
+            .prepare(concat!(
+                "select batch.producer_id, producer.epoch",
+                ", max(batch.last_sequence), max(batch.last_timestamp)",
+                " from (",
+                "select record.producer_id",
+                ", (record.sequence + count(*) - 1)::integer as last_sequence",
+                ", max(record.timestamp) as last_timestamp",
+                " from cluster, topic, record",
+                " where",
+                " cluster.name = $1",
+                " and topic.cluster = cluster.id",
+                " and topic.name = $2",
+                " and record.topic = topic.id",
+                " and record.partition = $3",
+                " and record.producer_id > 0",
+                " group by record.producer_id, record.sequence",
+                ") batch",
+                " left join producer on producer.id = batch.producer_id",
+                " group by batch.producer_id, producer.epoch",
+                " order by batch.producer_id",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query(
+            &prepared,
+            &[&self.cluster, &topition.topic(), &topition.partition()],
+        )
+        .await
+        .inspect_err(|err| error!(?err))?
+        .into_iter()
+        .map(|row| {
+            Ok(ProducerDetail {
+                producer_id: row.try_get(0)?,
+                producer_epoch: row
+                    .try_get::<_, Option<i32>>(1)?
+                    .map_or(Ok(0), i16::try_from)?,
+                last_sequence: row.try_get(2)?,
+                last_timestamp: row.try_get(3)?,
+                txn_start_offset: None,
+            })
+        })
+        .collect()
+    }
+
+    async fn transactions(&mut self) -> Result<Vec<TxnDetail>> {
+        debug!(cluster = self.cluster);
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select producer.transaction_id, producer.id, producer.epoch",
+                ", producer.transaction_timeout_ms",
+                ", topic.name, txn_partition.partition, txn_partition.created_at",
+                " from producer",
+                " left join txn_partition on txn_partition.producer = producer.id",
+                " left join topic on topic.id = txn_partition.topic",
+                " where producer.transaction_id is not null",
+                " order by producer.transaction_id",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let mut transactions: Vec<TxnDetail> = vec![];
+
+        for row in c
+            .query(&prepared, &[])
+            .await
+            .inspect_err(|err| error!(?err))?
+        {
+            let transactional_id: String = row.try_get(0)?;
+
+            if transactions
+                .last()
+                .is_none_or(|txn| txn.transactional_id != transactional_id)
+            {
+                transactions.push(TxnDetail {
+                    transactional_id,
+                    producer_id: row.try_get(1)?,
+                    producer_epoch: i16::try_from(row.try_get::<_, i32>(2)?)?,
+                    timeout_ms: row.try_get(3)?,
+                    started: None,
+                    partitions: BTreeSet::new(),
+                });
+            }
+
+            if let (Some(topic), Some(partition), Some(created_at), Some(txn)) = (
+                row.try_get::<_, Option<String>>(4)?,
+                row.try_get::<_, Option<i32>>(5)?,
+                row.try_get::<_, Option<SystemTime>>(6)?,
+                transactions.last_mut(),
+            ) {
+                _ = txn.partitions.insert(Topition::new(topic, partition));
+                txn.started = Some(
+                    txn.started
+                        .map_or(created_at, |started| started.min(created_at)),
+                );
+            }
+        }
+
+        Ok(transactions)
+    }
+
+    async fn upsert_user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+        credential: ScramCredential,
+    ) -> Result<()> {
+        debug!(?username, ?mechanism);
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "insert into scram_credential",
+                " (cluster, username, mechanism, salt, iterations, stored_key, server_key)",
+                " select c.id, $2, $3, $4, $5, $6, $7",
+                " from cluster c",
+                " where c.name = $1",
+                " on conflict (cluster, username, mechanism)",
+                " do update set",
+                " salt = excluded.salt",
+                ", iterations = excluded.iterations",
+                ", stored_key = excluded.stored_key",
+                ", server_key = excluded.server_key",
+                ", last_updated = excluded.last_updated",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        _ = c
+            .execute(
+                &prepared,
+                &[
+                    &self.cluster.as_str(),
+                    &username,
+                    &i16::from(i8::from(mechanism)),
+                    &&credential.salt[..],
+                    &credential.iterations,
+                    &&credential.stored_key[..],
+                    &&credential.server_key[..],
+                ],
+            )
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        Ok(())
+    }
+
+    async fn delete_user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+    ) -> Result<()> {
+        debug!(?username, ?mechanism);
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "delete from scram_credential sc",
+                " using cluster c",
+                " where",
+                " c.name = $1",
+                " and c.id = sc.cluster",
+                " and sc.username = $2",
+                " and sc.mechanism = $3",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let deleted = c
+            .execute(
+                &prepared,
+                &[
+                    &self.cluster.as_str(),
+                    &username,
+                    &i16::from(i8::from(mechanism)),
+                ],
+            )
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        if deleted == 0 {
+            Err(Error::Api(ErrorCode::ResourceNotFound))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn user_scram_credential(
+        &mut self,
+        username: &str,
+        mechanism: ScramMechanism,
+    ) -> Result<Option<ScramCredential>> {
+        debug!(?username, ?mechanism);
+
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select",
+                " sc.salt, sc.iterations, sc.stored_key, sc.server_key",
+                " from cluster c, scram_credential sc",
+                " where",
+                " c.name = $1",
+                " and c.id = sc.cluster",
+                " and sc.username = $2",
+                " and sc.mechanism = $3",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query_opt(
+            &prepared,
+            &[
+                &self.cluster.as_str(),
+                &username,
+                &i16::from(i8::from(mechanism)),
+            ],
+        )
+        .await
+        .inspect_err(|err| error!(?err))?
+        .map(|row| -> Result<ScramCredential> {
+            Ok(ScramCredential {
+                salt: row.try_get::<_, Vec<u8>>(0).map(Bytes::from)?,
+                iterations: row.try_get(1)?,
+                stored_key: row.try_get::<_, Vec<u8>>(2).map(Bytes::from)?,
+                server_key: row.try_get::<_, Vec<u8>>(3).map(Bytes::from)?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn user_scram_credentials(
+        &mut self,
+    ) -> Result<BTreeMap<String, BTreeMap<ScramMechanism, i32>>> {
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select sc.username, sc.mechanism, sc.iterations",
+                " from cluster c, scram_credential sc",
+                " where",
+                " c.name = $1",
+                " and c.id = sc.cluster",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        let mut credentials: BTreeMap<String, BTreeMap<ScramMechanism, i32>> = BTreeMap::new();
+
+        for row in c
+            .query(&prepared, &[&self.cluster.as_str()])
+            .await
+            .inspect_err(|err| error!(?err))?
+        {
+            let username = row.try_get::<_, String>(0)?;
+            let mechanism = i8::try_from(row.try_get::<_, i16>(1)?)
+                .map_err(Error::from)
+                .and_then(ScramMechanism::try_from)?;
+            let iterations = row.try_get::<_, i32>(2)?;
+
+            _ = credentials
+                .entry(username)
+                .or_default()
+                .insert(mechanism, iterations);
+        }
+
+        Ok(credentials)
+    }
+
+    async fn create_acls(&mut self, bindings: &[AclBinding]) -> Result<()> {
+        debug!(?bindings);
+
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        let prepared = tx
+            .prepare(concat!(
+                "insert into acl",
+                " (cluster, resource_type, resource_name, pattern_type",
+                ", principal, host, operation, permission_type)",
+                " select c.id, $2, $3, $4, $5, $6, $7, $8",
+                " from cluster c",
+                " where c.name = $1",
+                " on conflict do nothing",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        for binding in bindings {
+            _ = tx
+                .execute(
+                    &prepared,
+                    &[
+                        &self.cluster.as_str(),
+                        &i16::from(binding.resource_type),
+                        &binding.resource_name,
+                        &i16::from(binding.pattern_type),
+                        &binding.principal,
+                        &binding.host,
+                        &i16::from(binding.operation),
+                        &i16::from(binding.permission_type),
+                    ],
+                )
+                .await
+                .inspect_err(|err| error!(?err))?;
+        }
+
+        tx.commit().await.inspect_err(|err| error!(?err))?;
+
+        Ok(())
+    }
+
+    async fn delete_acls(&mut self, bindings: &[AclBinding]) -> Result<()> {
+        debug!(?bindings);
+
+        let mut c = self.connection().await?;
+        let tx = c.transaction().await?;
+
+        let prepared = tx
+            .prepare(concat!(
+                "delete from acl",
+                " using cluster c",
+                " where",
+                " c.name = $1",
+                " and c.id = acl.cluster",
+                " and acl.resource_type = $2",
+                " and acl.resource_name = $3",
+                " and acl.pattern_type = $4",
+                " and acl.principal = $5",
+                " and acl.host = $6",
+                " and acl.operation = $7",
+                " and acl.permission_type = $8",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        for binding in bindings {
+            _ = tx
+                .execute(
+                    &prepared,
+                    &[
+                        &self.cluster.as_str(),
+                        &i16::from(binding.resource_type),
+                        &binding.resource_name,
+                        &i16::from(binding.pattern_type),
+                        &binding.principal,
+                        &binding.host,
+                        &i16::from(binding.operation),
+                        &i16::from(binding.permission_type),
+                    ],
+                )
+                .await
+                .inspect_err(|err| error!(?err))?;
+        }
+
+        tx.commit().await.inspect_err(|err| error!(?err))?;
+
+        Ok(())
+    }
+
+    async fn acls(&mut self) -> Result<Vec<AclBinding>> {
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select",
+                " acl.resource_type, acl.resource_name, acl.pattern_type",
+                ", acl.principal, acl.host, acl.operation, acl.permission_type",
+                " from cluster c, acl",
+                " where",
+                " c.name = $1",
+                " and c.id = acl.cluster",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query(&prepared, &[&self.cluster.as_str()])
+            .await
+            .inspect_err(|err| error!(?err))?
+            .into_iter()
+            .map(|row| -> Result<AclBinding> {
+                Ok(AclBinding {
+                    resource_type: i8::try_from(row.try_get::<_, i16>(0)?)?,
+                    resource_name: row.try_get(1)?,
+                    pattern_type: i8::try_from(row.try_get::<_, i16>(2)?)?,
+                    principal: row.try_get(3)?,
+                    host: row.try_get(4)?,
+                    operation: i8::try_from(row.try_get::<_, i16>(5)?)?,
+                    permission_type: i8::try_from(row.try_get::<_, i16>(6)?)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn alter_reassignment(
+        &mut self,
+        topition: &Topition,
+        replicas: Option<&[i32]>,
+    ) -> Result<()> {
+        debug!(?topition, ?replicas);
+
+        let c = self.connection().await?;
+
+        if let Some(replicas) = replicas {
+            let prepared = c
+                .prepare(concat!(
+                    "insert into reassignment",
+                    " (topic, partition, replicas)",
+                    " select topic.id, $3, $4",
+                    " from cluster, topic",
+                    " where",
+                    " cluster.name = $1",
+                    " and topic.name = $2",
+                    " and topic.cluster = cluster.id",
+                    " on conflict (topic, partition)",
+                    " do update set",
+                    " replicas = excluded.replicas,",
+                    " last_updated = excluded.last_updated",
+                ))
+                .await
+                .inspect_err(|err| error!(?err))?;
+
+            _ = c
+                .execute(
+                    &prepared,
+                    &[
+                        &self.cluster,
+                        &topition.topic(),
+                        &topition.partition(),
+                        &replicas,
+                    ],
+                )
+                .await
+                .inspect_err(|err| error!(?err, ?topition))?;
+        } else {
+            let prepared = c
+                .prepare(concat!(
+                    "delete from reassignment",
+                    " using cluster, topic",
+                    " where",
+                    " cluster.name = $1",
+                    " and topic.name = $2",
+                    " and topic.cluster = cluster.id",
+                    " and reassignment.topic = topic.id",
+                    " and reassignment.partition = $3",
+                ))
+                .await
+                .inspect_err(|err| error!(?err))?;
+
+            _ = c
+                .execute(
+                    &prepared,
+                    &[&self.cluster, &topition.topic(), &topition.partition()],
+                )
+                .await
+                .inspect_err(|err| error!(?err, ?topition))?;
+        }
+
+        let prepared = c
+            .prepare(concat!(
+                "update leader_epoch",
+                " set epoch = leader_epoch.epoch + 1, last_updated = current_timestamp",
+                " from cluster",
+                " where",
+                " cluster.name = $1",
+                " and leader_epoch.cluster = cluster.id",
+                " and leader_epoch.topic = $2",
+                " and leader_epoch.partition = $3",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        _ = c
+            .execute(
+                &prepared,
+                &[&self.cluster, &topition.topic(), &topition.partition()],
+            )
+            .await
+            .inspect_err(|err| error!(?err, ?topition))?;
+
+        Ok(())
+    }
+
+    async fn reassignments(&mut self) -> Result<BTreeMap<Topition, Vec<i32>>> {
+        let c = self.connection().await?;
+
+        let prepared = c
+            .prepare(concat!(
+                "select topic.name, reassignment.partition, reassignment.replicas",
+                " from cluster, topic, reassignment",
+                " where",
+                " cluster.name = $1",
+                " and topic.cluster = cluster.id",
+                " and reassignment.topic = topic.id",
+            ))
+            .await
+            .inspect_err(|err| error!(?err))?;
+
+        c.query(&prepared, &[&self.cluster])
+            .await
+            .inspect_err(|err| error!(?err))?
+            .into_iter()
+            .map(|row| {
+                Ok((
+                    Topition::new(row.try_get::<_, String>(0)?, row.try_get::<_, i32>(1)?),
+                    row.try_get::<_, Vec<i32>>(2)?,
+                ))
+            })
+            .collect()
+    }
 }
@@ -18,7 +18,7 @@ use crate::{
         offset::{FileSystemOffsetProvider, OffsetIndex},
         Offset, OffsetProvider,
     },
-    Error, Result, Topition, TopitionOffset,
+    metrics, Error, Result, Topition, TopitionOffset,
 };
 use bytes::Bytes;
 use regex::Regex;
@@ -81,6 +81,10 @@ impl Storage {
             base_offset
         };
 
+        if let Some(segments) = self.segments.get(topition) {
+            metrics::segments(topition, segments.len());
+        }
+
         for ws in self.pending_fetch.drain(..) {
             ws.wake()
         }
@@ -30,6 +30,10 @@ create table broker (
   node integer not null,
   rack text,
   incarnation uuid not null,
+  epoch bigint default 0 not null,
+  heartbeat timestamp default current_timestamp not null,
+  fenced boolean default false not null,
+  unregistered boolean default false not null,
   unique (cluster, node),
   last_updated timestamp default current_timestamp not null,
   created_at timestamp default current_timestamp not null
@@ -68,6 +72,18 @@ create table topic_leader (
   created_at timestamp default current_timestamp not null
 );
 
+-- keyed by topic name, so that a recreated topic continues from the
+-- epochs of the topic that it replaces
+create table leader_epoch (
+  cluster integer references cluster(id) not null,
+  topic text not null,
+  partition integer not null,
+  primary key (cluster, topic, partition),
+  epoch integer default 0 not null,
+  last_updated timestamp default current_timestamp not null,
+  created_at timestamp default current_timestamp not null
+);
+
 create table topic_replica_node (
   topic uuid references topic(id),
   partition integer,
@@ -107,6 +123,8 @@ create table record (
   topic uuid references topic(id),
   partition integer,
   producer_id bigint,
+  producer_epoch smallint,
+  attributes smallint default 0 not null,
   sequence integer,
   timestamp timestamp,
   k bytea,
@@ -146,12 +164,95 @@ create table consumer_group (
   created_at timestamp default current_timestamp not null
 );
 
+create table consumer_protocol_group (
+  grp text not null,
+  cluster integer references cluster(id) not null,
+  primary key (grp, cluster),
+  e_tag uuid not null,
+  detail json not null,
+  last_updated timestamp default current_timestamp not null,
+  created_at timestamp default current_timestamp not null
+);
+
 create table producer (
   id bigint generated always as identity primary key,
   transaction_id text,
   transaction_timeout_ms int,
   epoch int default 0,
   unique (id, epoch),
+  unique (transaction_id),
+  last_updated timestamp default current_timestamp not null,
+  created_at timestamp default current_timestamp not null
+);
+
+create table txn_partition (
+  producer bigint references producer(id) not null,
+  topic uuid references topic(id) on delete cascade not null,
+  partition integer not null,
+  primary key (producer, topic, partition),
+  first_offset bigint,
+  last_updated timestamp default current_timestamp not null,
+  created_at timestamp default current_timestamp not null
+);
+
+create table txn_aborted (
+  producer bigint references producer(id) not null,
+  topic uuid references topic(id) on delete cascade not null,
+  partition integer not null,
+  first_offset bigint not null,
+  last_offset bigint not null,
+  primary key (topic, partition, last_offset),
+  last_updated timestamp default current_timestamp not null,
+  created_at timestamp default current_timestamp not null
+);
+
+create table txn_offset_commit (
+  producer bigint references producer(id) not null,
+  grp text not null,
+  topic uuid references topic(id) on delete cascade not null,
+  partition integer not null,
+  primary key (producer, grp, topic, partition),
+  committed_offset bigint,
+  leader_epoch integer,
+  timestamp timestamp,
+  metadata text,
+  last_updated timestamp default current_timestamp not null,
+  created_at timestamp default current_timestamp not null
+);
+
+create table acl (
+  id int generated always as identity primary key,
+  cluster integer references cluster(id) not null,
+  resource_type smallint not null,
+  resource_name text not null,
+  pattern_type smallint not null,
+  principal text not null,
+  host text not null,
+  operation smallint not null,
+  permission_type smallint not null,
+  unique (cluster, resource_type, resource_name, pattern_type, principal, host, operation, permission_type),
+  last_updated timestamp default current_timestamp not null,
+  created_at timestamp default current_timestamp not null
+);
+
+create table scram_credential (
+  cluster integer references cluster(id) not null,
+  username text not null,
+  mechanism smallint not null,
+  primary key (cluster, username, mechanism),
+  salt bytea not null,
+  iterations integer not null,
+  stored_key bytea not null,
+  server_key bytea not null,
+  last_updated timestamp default current_timestamp not null,
+  created_at timestamp default current_timestamp not null
+);
+
+create table reassignment (
+  topic uuid references topic(id) on delete cascade not null,
+  partition integer not null,
+  primary key (topic, partition),
+  replicas integer[] not null,
   last_updated timestamp default current_timestamp not null,
   created_at timestamp default current_timestamp not null
 );